@@ -604,6 +604,9 @@ pub trait GraphSet<'g>: Clone + fmt::Debug {
     fn intersection(&self, other: &Self) -> Self;
     fn difference(&self, other: &Self) -> Self;
     fn symmetric_difference(&self, other: &Self) -> Self;
+    fn is_subset(&self, other: &Self) -> bool;
+    fn is_superset(&self, other: &Self) -> bool;
+    fn is_disjoint(&self, other: &Self) -> bool;
 
     fn ids(&self, direction: DependencyDirection) -> Vec<Self::Id>;
     fn metadatas(&self, direction: DependencyDirection) -> Vec<Self::Metadata>;
@@ -693,6 +696,18 @@ impl<'g> GraphSet<'g> for PackageSet<'g> {
         self.symmetric_difference(other)
     }
 
+    fn is_subset(&self, other: &Self) -> bool {
+        self.is_subset(other)
+    }
+
+    fn is_superset(&self, other: &Self) -> bool {
+        self.is_superset(other)
+    }
+
+    fn is_disjoint(&self, other: &Self) -> bool {
+        self.is_disjoint(other)
+    }
+
     fn ids(&self, direction: DependencyDirection) -> Vec<Self::Id> {
         self.package_ids(direction).collect()
     }
@@ -792,6 +807,18 @@ impl<'g> GraphSet<'g> for FeatureSet<'g> {
         self.symmetric_difference(other)
     }
 
+    fn is_subset(&self, other: &Self) -> bool {
+        self.is_subset(other)
+    }
+
+    fn is_superset(&self, other: &Self) -> bool {
+        self.is_superset(other)
+    }
+
+    fn is_disjoint(&self, other: &Self) -> bool {
+        self.is_disjoint(other)
+    }
+
     fn ids(&self, direction: DependencyDirection) -> Vec<Self::Id> {
         self.feature_ids(direction).collect()
     }
@@ -17,10 +17,22 @@ pub enum Error {
     CommandError(Box<dyn error::Error + Send + Sync>),
     /// An error occurred while parsing `cargo metadata` JSON.
     MetadataParseError(serde_json::Error),
+    /// An error occurred while parsing the JSON output of `cargo build --unit-graph`.
+    UnitGraphParseError(serde_json::Error),
+    /// An error occurred while parsing the JSON output of `cargo build --build-plan`.
+    BuildPlanParseError(serde_json::Error),
+    /// An invocation in a [`BuildPlan`](crate::graph::BuildPlan) couldn't be correlated with a
+    /// package in this `PackageGraph`.
+    BuildPlanError(String),
+    /// An error occurred while parsing a Cargo future-incompatibility report.
+    FutureIncompatReportParseError(serde_json::Error),
     /// An error occurred while serializing `cargo metadata` JSON.
     MetadataSerializeError(serde_json::Error),
     /// An error occurred while constructing a `PackageGraph` from parsed metadata.
     PackageGraphConstructError(String),
+    /// An error occurred while deserializing a `package.metadata` table for the given package and
+    /// tool name.
+    MetadataTableParseError(PackageId, String, serde_json::Error),
     /// A package ID was unknown to this `PackageGraph`.
     UnknownPackageId(PackageId),
     /// A feature ID was unknown to this `FeatureGraph`.
@@ -29,6 +41,10 @@ pub enum Error {
     UnknownWorkspacePath(Utf8PathBuf),
     /// A package specified by name was unknown to this workspace.
     UnknownWorkspaceName(String),
+    /// A [`PackageExpr`](crate::graph::PackageExpr) couldn't be parsed or resolved.
+    QueryExprError(String),
+    /// A [`LicenseExpr`](crate::graph::LicenseExpr) couldn't be parsed.
+    LicenseExprError(String),
     /// An error was returned by `target-spec`.
     TargetSpecError(String, TargetSpecError),
     /// An internal error occurred within this `PackageGraph`.
@@ -71,6 +87,26 @@ pub enum Error {
     /// An error occurred while serializing to TOML.
     #[cfg(feature = "summaries")]
     TomlSerializeError(toml::ser::Error),
+    /// An error occurred while reading a `Cargo.lock` or manifest to construct a `PackageGraph`.
+    ///
+    /// This is present if the `lockfile` feature is enabled.
+    #[cfg(feature = "lockfile")]
+    LockfileError(Utf8PathBuf, Box<dyn error::Error + Send + Sync>),
+    /// An error occurred while serializing a `PackageGraph` to its binary format.
+    ///
+    /// This is present if the `binary` feature is enabled.
+    #[cfg(feature = "binary")]
+    BinarySerializeError(Box<dyn error::Error + Send + Sync>),
+    /// An error occurred while deserializing a `PackageGraph` from its binary format.
+    ///
+    /// This is present if the `binary` feature is enabled.
+    #[cfg(feature = "binary")]
+    BinaryParseError(Box<dyn error::Error + Send + Sync>),
+    /// An error occurred while fetching or parsing the entry for a package from a registry index.
+    ///
+    /// This is present if the `sparse-index` feature is enabled.
+    #[cfg(feature = "sparse-index")]
+    IndexError(String, Box<dyn error::Error + Send + Sync>),
 }
 
 impl Error {
@@ -91,14 +127,27 @@ impl fmt::Display for Error {
         match self {
             CommandError(_) => write!(f, "`cargo metadata` execution failed"),
             MetadataParseError(_) => write!(f, "`cargo metadata` returned invalid JSON output"),
+            UnitGraphParseError(_) => write!(f, "failed to parse cargo unit graph JSON"),
+            BuildPlanParseError(_) => write!(f, "failed to parse cargo build plan JSON"),
+            BuildPlanError(msg) => write!(f, "failed to correlate build plan: {}", msg),
+            FutureIncompatReportParseError(_) => {
+                write!(f, "failed to parse future-incompatibility report JSON")
+            }
             MetadataSerializeError(_) => write!(f, "failed to serialize `cargo metadata` to JSON"),
             PackageGraphConstructError(s) => write!(f, "failed to construct package graph: {}", s),
+            MetadataTableParseError(package_id, tool, _) => write!(
+                f,
+                "failed to deserialize `package.metadata.{}` for package {}",
+                tool, package_id
+            ),
             UnknownPackageId(id) => write!(f, "unknown package ID: {}", id),
             UnknownFeatureId(package_id, feature) => {
                 write!(f, "unknown feature ID: '{}/{}'", package_id, feature)
             }
             UnknownWorkspacePath(path) => write!(f, "unknown workspace path: {}", path),
             UnknownWorkspaceName(name) => write!(f, "unknown workspace package name: {}", name),
+            QueryExprError(msg) => write!(f, "invalid query expression: {}", msg),
+            LicenseExprError(msg) => write!(f, "invalid license expression: {}", msg),
             TargetSpecError(msg, _) => write!(f, "target spec error while {}", msg),
             PackageGraphInternalError(msg) => write!(f, "internal error in package graph: {}", msg),
             FeatureGraphInternalError(msg) => write!(f, "internal error in feature graph: {}", msg),
@@ -146,6 +195,14 @@ impl fmt::Display for Error {
             }
             #[cfg(feature = "summaries")]
             TomlSerializeError(_) => write!(f, "failed to serialize to TOML"),
+            #[cfg(feature = "lockfile")]
+            LockfileError(path, _) => write!(f, "failed to read lockfile or manifest at {}", path),
+            #[cfg(feature = "binary")]
+            BinarySerializeError(_) => write!(f, "failed to serialize package graph"),
+            #[cfg(feature = "binary")]
+            BinaryParseError(_) => write!(f, "failed to deserialize package graph"),
+            #[cfg(feature = "sparse-index")]
+            IndexError(name, _) => write!(f, "failed to read index entry for package {}", name),
         }
     }
 }
@@ -154,13 +211,20 @@ impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self {
             MetadataParseError(err) => Some(err),
+            UnitGraphParseError(err) => Some(err),
+            BuildPlanParseError(err) => Some(err),
+            BuildPlanError(_) => None,
+            FutureIncompatReportParseError(err) => Some(err),
             MetadataSerializeError(err) => Some(err),
             CommandError(err) => Some(err.as_ref()),
             PackageGraphConstructError(_) => None,
+            MetadataTableParseError(_, _, err) => Some(err),
             UnknownPackageId(_) => None,
             UnknownFeatureId(_, _) => None,
             UnknownWorkspacePath(_) => None,
             UnknownWorkspaceName(_) => None,
+            QueryExprError(_) => None,
+            LicenseExprError(_) => None,
             TargetSpecError(_, err) => Some(err),
             PackageGraphInternalError(_) => None,
             FeatureGraphInternalError(_) => None,
@@ -172,6 +236,14 @@ impl error::Error for Error {
             UnknownRegistryName { .. } => None,
             #[cfg(feature = "summaries")]
             TomlSerializeError(err) => Some(err),
+            #[cfg(feature = "lockfile")]
+            LockfileError(_, err) => Some(err.as_ref()),
+            #[cfg(feature = "binary")]
+            BinarySerializeError(err) => Some(err.as_ref()),
+            #[cfg(feature = "binary")]
+            BinaryParseError(err) => Some(err.as_ref()),
+            #[cfg(feature = "sparse-index")]
+            IndexError(_, err) => Some(err.as_ref()),
         }
     }
 }
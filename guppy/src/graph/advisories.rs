@@ -0,0 +1,253 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Annotation of package sets with advisories from the RustSec advisory database.
+
+use crate::{
+    graph::{DependencyDirection, PackageGraph, PackageMetadata, PackageSet, SourceKind},
+    PackageId,
+};
+use fixedbitset::FixedBitSet;
+use rustsec::{
+    advisory::Advisory,
+    database::{Database, Query},
+    package::Name,
+    SourceId,
+};
+use std::collections::VecDeque;
+
+impl<'g> PackageSet<'g> {
+    /// Returns the advisories in `database` that apply to packages in this set, along with how
+    /// the affected packages are reachable from the workspace.
+    ///
+    /// Every package from `crates.io` or an alternate registry is checked against the advisories
+    /// for its name, source and version. `query` can be used to restrict the advisories
+    /// considered further, for example by severity or target platform; package-specific
+    /// constraints in it are overridden. `Query::default()` considers all advisories for crates
+    /// apart from withdrawn and informational ones, while `Query::new()` considers all
+    /// advisories.
+    ///
+    /// Requires the `advisories` feature to be enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use guppy::rustsec::database::{Database, Query};
+    /// use guppy::graph::AdvisoryReachability;
+    /// use guppy::MetadataCommand;
+    /// use std::path::Path;
+    ///
+    /// let graph = MetadataCommand::new().build_graph().expect("guppy graph constructed");
+    /// let database = Database::open(Path::new("advisory-db")).expect("database opened");
+    ///
+    /// let report = graph.resolve_all().annotate_advisories(&database, &Query::default());
+    /// for advisory_match in report.iter() {
+    ///     if advisory_match.reachability() == AdvisoryReachability::Normal {
+    ///         println!(
+    ///             "{}: {}",
+    ///             advisory_match.package().id(),
+    ///             advisory_match.advisory().id(),
+    ///         );
+    ///     }
+    /// }
+    /// ```
+    pub fn annotate_advisories<'a>(
+        &self,
+        database: &'a Database,
+        query: &Query,
+    ) -> AdvisoryReport<'g, 'a> {
+        let mut packages: Vec<_> = self.packages(DependencyDirection::Forward).collect();
+        packages.sort_by_key(|package| package.id());
+
+        let mut reachability = None;
+        let mut matches = vec![];
+        for package in packages {
+            let source_id = match package.source().kind() {
+                SourceKind::CratesIo => SourceId::default(),
+                SourceKind::AlternateRegistry(_) => {
+                    match package.source().external_source().map(SourceId::from_url) {
+                        Some(Ok(source_id)) => source_id,
+                        _ => continue,
+                    }
+                }
+                // Advisories only apply to packages published to registries.
+                _ => continue,
+            };
+            let name: Name = match package.name().parse() {
+                Ok(name) => name,
+                Err(_) => continue,
+            };
+            let package_query = query
+                .clone()
+                .package_name(name)
+                .package_version(package.version().clone())
+                .package_source(source_id);
+
+            let mut advisories = database.query(&package_query);
+            if advisories.is_empty() {
+                continue;
+            }
+            advisories.sort_by(|a, b| a.id().cmp(b.id()));
+
+            // Only compute reachability if at least one advisory applies.
+            let reachability =
+                reachability.get_or_insert_with(|| Reachability::new(self, package.graph()));
+            let package_reachability = reachability.get(package);
+            matches.extend(advisories.into_iter().map(|advisory| AdvisoryMatch {
+                package,
+                advisory,
+                reachability: package_reachability,
+            }));
+        }
+
+        AdvisoryReport { matches }
+    }
+}
+
+/// Advisories that apply to packages in a package set.
+///
+/// Returned by [`PackageSet::annotate_advisories`].
+#[derive(Clone, Debug)]
+pub struct AdvisoryReport<'g, 'a> {
+    // Sorted by package ID, then by advisory ID.
+    matches: Vec<AdvisoryMatch<'g, 'a>>,
+}
+
+impl<'g, 'a> AdvisoryReport<'g, 'a> {
+    /// Returns the number of matches in this report.
+    pub fn len(&self) -> usize {
+        self.matches.len()
+    }
+
+    /// Returns true if no advisories apply.
+    pub fn is_empty(&self) -> bool {
+        self.matches.is_empty()
+    }
+
+    /// Returns all matches, sorted by package ID and then by advisory ID.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &AdvisoryMatch<'g, 'a>> + '_ {
+        self.matches.iter()
+    }
+
+    /// Returns the matches for the package with the given ID.
+    pub fn for_package<'b>(
+        &'b self,
+        package_id: &'b PackageId,
+    ) -> impl Iterator<Item = &'b AdvisoryMatch<'g, 'a>> + 'b {
+        self.matches
+            .iter()
+            .filter(move |advisory_match| advisory_match.package.id() == package_id)
+    }
+
+    /// Returns the matches with the given reachability.
+    pub fn with_reachability(
+        &self,
+        reachability: AdvisoryReachability,
+    ) -> impl Iterator<Item = &AdvisoryMatch<'g, 'a>> + '_ {
+        self.matches
+            .iter()
+            .filter(move |advisory_match| advisory_match.reachability == reachability)
+    }
+}
+
+/// An advisory that applies to a package.
+///
+/// Part of an [`AdvisoryReport`].
+#[derive(Clone, Debug)]
+pub struct AdvisoryMatch<'g, 'a> {
+    package: PackageMetadata<'g>,
+    advisory: &'a Advisory,
+    reachability: AdvisoryReachability,
+}
+
+impl<'g, 'a> AdvisoryMatch<'g, 'a> {
+    /// Returns the affected package.
+    pub fn package(&self) -> PackageMetadata<'g> {
+        self.package
+    }
+
+    /// Returns the advisory.
+    pub fn advisory(&self) -> &'a Advisory {
+        self.advisory
+    }
+
+    /// Returns how the affected package is reachable from the workspace.
+    pub fn reachability(&self) -> AdvisoryReachability {
+        self.reachability
+    }
+}
+
+/// How a package affected by an advisory is reachable from the workspace members in a package
+/// set.
+///
+/// Only links within the package set are followed.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum AdvisoryReachability {
+    /// The package is reachable through normal and build dependencies alone, so the affected code
+    /// may be part of a regular build.
+    Normal,
+
+    /// The package is only reachable through dev-dependencies of workspace members, so the
+    /// affected code is only built for tests, examples and benchmarks.
+    DevOnly,
+
+    /// The package isn't reachable from any workspace member, except possibly through
+    /// dev-dependencies of third-party packages, which Cargo never builds.
+    Unreachable,
+}
+
+/// Precomputed reachability for all packages in a set.
+struct Reachability {
+    normal: FixedBitSet,
+    dev: FixedBitSet,
+}
+
+impl Reachability {
+    fn new(package_set: &PackageSet<'_>, graph: &PackageGraph) -> Self {
+        Self {
+            normal: reachable(package_set, graph, false),
+            dev: reachable(package_set, graph, true),
+        }
+    }
+
+    fn get(&self, package: PackageMetadata<'_>) -> AdvisoryReachability {
+        let ix = package.package_ix().index();
+        if self.normal[ix] {
+            AdvisoryReachability::Normal
+        } else if self.dev[ix] {
+            AdvisoryReachability::DevOnly
+        } else {
+            AdvisoryReachability::Unreachable
+        }
+    }
+}
+
+/// Returns the packages in the set that are reachable from workspace members in it, through
+/// normal and build dependencies, and dev-dependencies of workspace members if `include_dev` is
+/// true.
+fn reachable(package_set: &PackageSet<'_>, graph: &PackageGraph, include_dev: bool) -> FixedBitSet {
+    let mut queue: VecDeque<_> = package_set
+        .packages(DependencyDirection::Forward)
+        .filter(|package| package.in_workspace())
+        .collect();
+    let mut visited = FixedBitSet::with_capacity(graph.package_count());
+    for package in &queue {
+        visited.insert(package.package_ix().index());
+    }
+
+    while let Some(package) = queue.pop_front() {
+        for link in package.direct_links() {
+            let to = link.to();
+            let followed = link.normal().is_present()
+                || link.build().is_present()
+                || (include_dev && package.in_workspace() && link.dev().is_present());
+            if followed
+                && package_set.contains(to.id()).expect("package ID is known")
+                && !visited.put(to.package_ix().index())
+            {
+                queue.push_back(to);
+            }
+        }
+    }
+    visited
+}
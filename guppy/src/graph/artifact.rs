@@ -0,0 +1,108 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Artifact dependencies, as declared with `artifact = ...` in `Cargo.toml`.
+
+use serde::{Deserialize, Serialize};
+
+/// Information about an artifact dependency.
+///
+/// Artifact dependencies (an unstable Cargo feature, also known as "bindeps") depend on the
+/// binaries or dynamic libraries of a package rather than on its library. They're declared with
+/// the `artifact` key, and can be forced to be built for a particular platform with the `target`
+/// key:
+///
+/// ```toml
+/// [build-dependencies]
+/// kernel = { version = "1", artifact = "bin", target = "x86_64-unknown-none" }
+/// ```
+///
+/// `cargo metadata` only reports artifact dependencies with `-Z bindeps`.
+///
+/// Returned by [`DependencyReq::artifact`](crate::graph::DependencyReq::artifact).
+#[derive(Copy, Clone, Debug)]
+pub struct ArtifactDependency<'g> {
+    inner: &'g ArtifactDependencyImpl,
+}
+
+impl<'g> ArtifactDependency<'g> {
+    pub(super) fn new(inner: &'g ArtifactDependencyImpl) -> Self {
+        Self { inner }
+    }
+
+    /// Returns the kinds of artifacts depended on, in the order they were declared.
+    pub fn kinds(&self) -> impl Iterator<Item = ArtifactKind<'g>> + 'g {
+        self.inner.kinds.iter().map(|kind| ArtifactKind::new(kind))
+    }
+
+    /// Returns true if the library of the package is depended on as well (`lib = true`).
+    ///
+    /// The library is always built for the same platform as the package depending on it.
+    pub fn lib(&self) -> bool {
+        self.inner.lib
+    }
+
+    /// Returns the platform the artifacts are forced to be built for, if specified.
+    ///
+    /// If this is `None`, artifacts are built for the same platform as the package depending on
+    /// them: the host platform for build dependencies, and the target platform otherwise.
+    pub fn target(&self) -> Option<ArtifactTarget<'g>> {
+        self.inner.target.as_deref().map(|target| match target {
+            "target" => ArtifactTarget::Target,
+            triple => ArtifactTarget::Triple(triple),
+        })
+    }
+}
+
+/// A kind of artifact depended on by an [`ArtifactDependency`].
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ArtifactKind<'g> {
+    /// All binaries of the package (`artifact = "bin"`).
+    AllBinaries,
+
+    /// A single binary of the package (`artifact = "bin:<name>"`).
+    Binary(&'g str),
+
+    /// A C-compatible dynamic library (`artifact = "cdylib"`).
+    Cdylib,
+
+    /// A C-compatible static library (`artifact = "staticlib"`).
+    Staticlib,
+
+    /// An artifact kind not known to guppy.
+    Unknown(&'g str),
+}
+
+impl<'g> ArtifactKind<'g> {
+    fn new(kind: &'g str) -> Self {
+        match kind {
+            "bin" => ArtifactKind::AllBinaries,
+            "cdylib" => ArtifactKind::Cdylib,
+            "staticlib" => ArtifactKind::Staticlib,
+            other => match other.strip_prefix("bin:") {
+                Some(name) => ArtifactKind::Binary(name),
+                None => ArtifactKind::Unknown(other),
+            },
+        }
+    }
+}
+
+/// The platform an [`ArtifactDependency`] is forced to be built for.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ArtifactTarget<'g> {
+    /// The target platform of the build, even for build dependencies (`target = "target"`).
+    Target,
+
+    /// A specific platform, as a target triple.
+    Triple(&'g str),
+}
+
+/// The `artifact` field of a dependency in `cargo metadata` output.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub(crate) struct ArtifactDependencyImpl {
+    pub(crate) kinds: Vec<String>,
+    #[serde(default)]
+    pub(crate) lib: bool,
+    #[serde(default)]
+    pub(crate) target: Option<String>,
+}
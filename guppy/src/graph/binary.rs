@@ -0,0 +1,374 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Support for saving package graphs in a binary format, and loading them back.
+
+use crate::{
+    graph::{
+        PackageGraph, PackageGraphData, PackageIx, PackageLinkImpl, PackageMetadataImpl,
+        WorkspaceImpl,
+    },
+    Error, PackageId,
+};
+use once_cell::sync::OnceCell;
+use petgraph::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+};
+
+/// The magic bytes at the start of every serialized graph.
+const MAGIC: &[u8; 8] = b"GUPPYPG\0";
+
+/// The version of the binary format. This must be bumped whenever the header changes.
+const FORMAT_VERSION: u32 = 1;
+
+/// The payload is only compatible with the version of guppy that wrote it, since it encodes
+/// internal data structures directly.
+const GUPPY_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+impl PackageGraph {
+    /// Serializes this package graph into the given writer, in a binary format.
+    ///
+    /// Loading a graph with [`deserialize_binary`](Self::deserialize_binary) is much faster than
+    /// parsing `cargo metadata` JSON output, since the graph doesn't need to be rebuilt. This makes
+    /// it suitable for caching the graphs of large workspaces across tool invocations.
+    ///
+    /// The format is versioned, and is tied to the version of `guppy` that wrote it: graphs
+    /// written by other versions of `guppy` are rejected, and must be rebuilt from metadata.
+    ///
+    /// Caches such as the feature graph aren't serialized, and are recomputed on demand after
+    /// loading.
+    ///
+    /// This method is available if the `binary` feature is enabled.
+    pub fn serialize_binary(&self, writer: &mut impl io::Write) -> Result<(), Error> {
+        write_repr(writer, &self.to_repr())
+    }
+
+    fn to_repr(
+        &self,
+    ) -> GraphRepr<&PackageId, &PackageMetadataImpl, &PackageLinkImpl, &WorkspaceImpl> {
+        let packages = self
+            .dep_graph
+            .node_indices()
+            .map(|package_ix| {
+                let package_id = &self.dep_graph[package_ix];
+                (package_id, &self.data.packages[package_id])
+            })
+            .collect();
+        let links = self
+            .dep_graph
+            .edge_references()
+            .map(|edge| {
+                (
+                    edge.source().index() as u32,
+                    edge.target().index() as u32,
+                    edge.weight(),
+                )
+            })
+            .collect();
+        GraphRepr {
+            packages,
+            links,
+            workspace: &self.data.workspace,
+        }
+    }
+
+    /// Deserializes a package graph previously written by
+    /// [`serialize_binary`](Self::serialize_binary).
+    ///
+    /// Returns an error if the data is invalid, or if it was written by a different version of
+    /// `guppy`.
+    ///
+    /// This method is available if the `binary` feature is enabled.
+    pub fn deserialize_binary(reader: &mut impl io::Read) -> Result<Self, Error> {
+        let mut header = [0; 12];
+        reader
+            .read_exact(&mut header)
+            .map_err(|err| Error::BinaryParseError(Box::new(err)))?;
+        if &header[..8] != MAGIC {
+            return Err(Error::BinaryParseError(
+                "input isn't a serialized package graph".into(),
+            ));
+        }
+        let format_version = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+        if format_version != FORMAT_VERSION {
+            return Err(Error::BinaryParseError(
+                format!(
+                    "unsupported format version {} (expected {})",
+                    format_version, FORMAT_VERSION
+                )
+                .into(),
+            ));
+        }
+
+        let guppy_version: String =
+            bincode::deserialize_from(&mut *reader).map_err(|err| Error::BinaryParseError(err))?;
+        if guppy_version != GUPPY_VERSION {
+            return Err(Error::BinaryParseError(
+                format!(
+                    "package graph was written by guppy {}, but this is guppy {}",
+                    guppy_version, GUPPY_VERSION
+                )
+                .into(),
+            ));
+        }
+        let repr: GraphRepr<PackageId, PackageMetadataImpl, PackageLinkImpl, WorkspaceImpl> =
+            bincode::deserialize_from(reader).map_err(|err| Error::BinaryParseError(err))?;
+
+        let package_count = repr.packages.len();
+        let mut dep_graph = Graph::with_capacity(package_count, repr.links.len());
+        let mut packages = HashMap::with_capacity(package_count);
+        for (package_id, package) in repr.packages {
+            let package_ix = dep_graph.add_node(package_id.clone());
+            if package.package_ix != package_ix {
+                return Err(Error::BinaryParseError(
+                    format!("package '{}' has an invalid index", package_id).into(),
+                ));
+            }
+            if packages.insert(package_id, package).is_some() {
+                return Err(Error::BinaryParseError(
+                    format!("duplicate package ID '{}'", dep_graph[package_ix]).into(),
+                ));
+            }
+        }
+
+        // Package graphs have at most one link between any two packages.
+        let mut endpoints = HashSet::with_capacity(repr.links.len());
+        for (from, to, link) in repr.links {
+            if from as usize >= package_count || to as usize >= package_count {
+                return Err(Error::BinaryParseError(
+                    format!("link '{}' has an invalid endpoint", link.dep_name).into(),
+                ));
+            }
+            let from_ix = NodeIndex::<PackageIx>::new(from as usize);
+            let to_ix = NodeIndex::new(to as usize);
+            if !endpoints.insert((from_ix, to_ix)) {
+                return Err(Error::BinaryParseError(
+                    format!(
+                        "duplicate link from '{}' to '{}'",
+                        dep_graph[from_ix], dep_graph[to_ix]
+                    )
+                    .into(),
+                ));
+            }
+            dep_graph.add_edge(from_ix, to_ix, link);
+        }
+
+        let workspace = repr.workspace;
+        let members = workspace
+            .members_by_path
+            .values()
+            .chain(workspace.members_by_name.values())
+            .chain(workspace.default_members.iter().flatten());
+        for member in members {
+            if !packages.contains_key(member) {
+                return Err(Error::BinaryParseError(
+                    format!("workspace member '{}' isn't a known package", member).into(),
+                ));
+            }
+        }
+
+        Ok(Self {
+            dep_graph,
+            sccs: OnceCell::new(),
+            feature_graph: OnceCell::new(),
+            data: PackageGraphData {
+                packages,
+                workspace,
+            },
+        })
+    }
+}
+
+fn write_repr(
+    writer: &mut impl io::Write,
+    repr: &GraphRepr<&PackageId, &PackageMetadataImpl, &PackageLinkImpl, &WorkspaceImpl>,
+) -> Result<(), Error> {
+    writer
+        .write_all(MAGIC)
+        .and_then(|()| writer.write_all(&FORMAT_VERSION.to_le_bytes()))
+        .map_err(|err| Error::BinarySerializeError(Box::new(err)))?;
+    bincode::serialize_into(&mut *writer, GUPPY_VERSION)
+        .and_then(|()| bincode::serialize_into(writer, repr))
+        .map_err(|err| Error::BinarySerializeError(err))
+}
+
+/// The serialized representation of a package graph.
+///
+/// Packages are stored in node index order, and links in edge index order, so that indexes are
+/// preserved across serialization.
+#[derive(Deserialize, Serialize)]
+struct GraphRepr<I, P, L, W> {
+    packages: Vec<(I, P)>,
+    links: Vec<(u32, u32, L)>,
+    workspace: W,
+}
+
+// ---
+// Serde helpers for internal types, referred to by `#[serde(with)]` attributes
+// ---
+
+/// `JsonValue` can't be deserialized by non-self-describing formats, so it is stored as a JSON
+/// string.
+pub(crate) mod json_string {
+    use crate::JsonValue;
+    use serde::{de::Error, ser::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(
+        value: &JsonValue,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serde_json::to_string(value)
+            .map_err(S::Error::custom)?
+            .serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<JsonValue, D::Error> {
+        let json = String::deserialize(deserializer)?;
+        serde_json::from_str(&json).map_err(D::Error::custom)
+    }
+}
+
+pub(crate) mod box_path {
+    use camino::{Utf8Path, Utf8PathBuf};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(
+        path: &Utf8Path,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        path.serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Box<Utf8Path>, D::Error> {
+        Ok(Utf8PathBuf::deserialize(deserializer)?.into_boxed_path())
+    }
+}
+
+pub(crate) mod option_box_path {
+    use camino::{Utf8Path, Utf8PathBuf};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[allow(clippy::borrowed_box)]
+    pub(crate) fn serialize<S: Serializer>(
+        path: &Option<Box<Utf8Path>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        path.as_deref().serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Box<Utf8Path>>, D::Error> {
+        Ok(Option::<Utf8PathBuf>::deserialize(deserializer)?.map(Utf8PathBuf::into_boxed_path))
+    }
+}
+
+pub(crate) mod node_index {
+    use petgraph::graph::{IndexType, NodeIndex};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub(crate) fn serialize<S: Serializer, Ix: IndexType>(
+        ix: &NodeIndex<Ix>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        (ix.index() as u32).serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>, Ix: IndexType>(
+        deserializer: D,
+    ) -> Result<NodeIndex<Ix>, D::Error> {
+        Ok(NodeIndex::new(u32::deserialize(deserializer)? as usize))
+    }
+}
+
+/// Target specs are stored as the strings they were parsed from, and parsed again on load.
+pub(crate) mod target_specs {
+    use crate::graph::build::parse_cargo_target_spec;
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+    use target_spec::TargetSpec;
+
+    pub(crate) fn serialize<S: Serializer>(
+        specs: &[TargetSpec],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let specs: Vec<_> = specs
+            .iter()
+            .map(|spec| match spec {
+                TargetSpec::Triple(triple) => triple.as_str().to_owned(),
+                TargetSpec::Expression(expr) => format!("cfg({})", expr.expression_str()),
+            })
+            .collect();
+        specs.serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<TargetSpec>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|spec| parse_cargo_target_spec(spec).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CargoMetadata;
+
+    #[test]
+    fn inconsistent_graphs() {
+        let graph =
+            CargoMetadata::parse_json(include_str!("../../../fixtures/small/metadata1.json"))
+                .expect("valid metadata")
+                .build_graph()
+                .expect("valid graph");
+
+        let mut repr = graph.to_repr();
+        repr.packages[1].0 = repr.packages[0].0;
+        assert_invalid(&repr, "duplicate package ID");
+
+        let mut repr = graph.to_repr();
+        let link = repr.links[0];
+        repr.links.push(link);
+        assert_invalid(&repr, "duplicate link");
+
+        let mut repr = graph.to_repr();
+        repr.links[0].1 = repr.packages.len() as u32;
+        assert_invalid(&repr, "invalid endpoint");
+
+        let mut workspace = graph.data.workspace.clone();
+        let unknown = PackageId::new("unknown 0.1.0 (path+file:///unknown)");
+        workspace.members_by_name.insert("unknown".into(), unknown);
+        let mut repr = graph.to_repr();
+        repr.workspace = &workspace;
+        assert_invalid(&repr, "isn't a known package");
+    }
+
+    fn assert_invalid(
+        repr: &GraphRepr<&PackageId, &PackageMetadataImpl, &PackageLinkImpl, &WorkspaceImpl>,
+        message: &str,
+    ) {
+        let mut bytes = vec![];
+        write_repr(&mut bytes, repr).expect("serialization succeeded");
+        match PackageGraph::deserialize_binary(&mut bytes.as_slice()) {
+            Err(Error::BinaryParseError(err)) => {
+                assert!(
+                    err.to_string().contains(message),
+                    "error '{}' contains '{}'",
+                    err,
+                    message
+                );
+            }
+            Err(other) => panic!("unexpected error: {}", other),
+            Ok(_) => panic!("deserialization should fail with '{}'", message),
+        }
+    }
+}
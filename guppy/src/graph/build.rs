@@ -2,12 +2,16 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use crate::{
+    errors::TargetSpecError,
     graph::{
         cargo_version_matches, BuildTargetImpl, BuildTargetKindImpl, DepRequiredOrOptional,
-        DependencyReqImpl, NamedFeatureDep, OwnedBuildTargetId, PackageGraph, PackageGraphData,
-        PackageIx, PackageLinkImpl, PackageMetadataImpl, PackagePublishImpl, PackageSourceImpl,
-        WorkspaceImpl,
+        DependencyInstanceImpl, DependencyReqImpl, NamedFeatureDep, OwnedBuildTargetId,
+        PackageGraph, PackageGraphData, PackageIx, PackageLinkImpl, PackageMetadataImpl,
+        PackagePublishImpl, PackageSourceImpl, WorkspaceImpl,
     },
+    interner::Interner,
+    metadata_command::{DependencyExtra, DependencyExtras},
+    platform::PlatformStatusImpl,
     sorted_set::SortedSet,
     Error, PackageId,
 };
@@ -23,11 +27,15 @@ use std::{
     borrow::Cow,
     collections::{BTreeMap, HashMap, HashSet},
 };
-use target_spec::TargetSpec;
+use target_spec::{TargetSpec, Triple};
 
 impl PackageGraph {
     /// Constructs a new `PackageGraph` instances from the given metadata.
-    pub(crate) fn build(metadata: Metadata) -> Result<Self, Error> {
+    pub(crate) fn build(
+        metadata: Metadata,
+        default_members: Option<Vec<cargo_metadata::PackageId>>,
+        dep_extras: DependencyExtras,
+    ) -> Result<Self, Error> {
         // resolve_nodes is missing if the metadata was generated with --no-deps.
         let resolve_nodes = metadata.resolve.map(|r| r.nodes).unwrap_or_default();
 
@@ -44,13 +52,10 @@ impl PackageGraph {
             resolve_nodes,
             &workspace_root,
             &workspace_members,
+            dep_extras,
         );
 
-        let packages: HashMap<_, _> = metadata
-            .packages
-            .into_iter()
-            .map(|package| build_state.process_package(package))
-            .collect::<Result<_, _>>()?;
+        let packages = build_state.process_packages(metadata.packages)?;
 
         let dep_graph = build_state.finish();
 
@@ -60,6 +65,12 @@ impl PackageGraph {
             metadata.workspace_metadata,
             &packages,
             workspace_members,
+            default_members.map(|default_members| {
+                default_members
+                    .into_iter()
+                    .map(PackageId::from_metadata)
+                    .collect()
+            }),
         )?;
 
         Ok(Self {
@@ -82,6 +93,7 @@ impl WorkspaceImpl {
         metadata_table: serde_json::Value,
         packages: &HashMap<PackageId, PackageMetadataImpl>,
         members: impl IntoIterator<Item = PackageId>,
+        default_members: Option<HashSet<PackageId>>,
     ) -> Result<Self, Error> {
         use std::collections::btree_map::Entry;
 
@@ -107,7 +119,7 @@ impl WorkspaceImpl {
             };
             members_by_path.insert(workspace_path.to_path_buf(), id.clone());
 
-            match members_by_name.entry(package_metadata.name.clone().into_boxed_str()) {
+            match members_by_name.entry(package_metadata.name.as_str().into()) {
                 Entry::Vacant(vacant) => {
                     vacant.insert(id.clone());
                 }
@@ -122,12 +134,33 @@ impl WorkspaceImpl {
             }
         }
 
+        // Store default members in the same order as members_by_path.
+        let default_members = match default_members {
+            Some(mut default_members) => {
+                let sorted: Vec<_> = members_by_path
+                    .values()
+                    .filter(|id| default_members.remove(*id))
+                    .cloned()
+                    .collect();
+                // Anything left over isn't a workspace member.
+                if let Some(id) = default_members.into_iter().next() {
+                    return Err(Error::PackageGraphConstructError(format!(
+                        "default workspace member '{}' isn't a workspace member",
+                        id
+                    )));
+                }
+                Some(sorted)
+            }
+            None => None,
+        };
+
         Ok(Self {
             root: workspace_root,
             target_directory: target_directory.into(),
             metadata_table,
             members_by_path,
             members_by_name,
+            default_members,
             #[cfg(feature = "proptest1")]
             name_list: OnceCell::new(),
         })
@@ -135,6 +168,12 @@ impl WorkspaceImpl {
 }
 
 /// Helper struct for building up dependency graph.
+/// Used for dependencies that don't have any extra fields.
+static NO_EXTRA: DependencyExtra = DependencyExtra {
+    public: false,
+    artifact: None,
+};
+
 struct GraphBuildState<'a> {
     dep_graph: Graph<PackageId, PackageLinkImpl, Directed, PackageIx>,
     // The values of package_data are (package_ix, name, version).
@@ -143,6 +182,8 @@ struct GraphBuildState<'a> {
     resolve_data: HashMap<PackageId, Vec<NodeDep>>,
     workspace_root: &'a Utf8Path,
     workspace_members: &'a HashSet<PackageId>,
+    dep_extras: DependencyExtras,
+    interner: Interner,
 }
 
 impl<'a> GraphBuildState<'a> {
@@ -151,6 +192,7 @@ impl<'a> GraphBuildState<'a> {
         resolve_nodes: Vec<Node>,
         workspace_root: &'a Utf8Path,
         workspace_members: &'a HashSet<PackageId>,
+        dep_extras: DependencyExtras,
     ) -> Self {
         // No idea how many edges there are going to be, so use packages.len() as a reasonable lower
         // bound.
@@ -185,23 +227,80 @@ impl<'a> GraphBuildState<'a> {
             resolve_data,
             workspace_root,
             workspace_members,
+            dep_extras,
+            interner: Interner::new(),
         }
     }
 
-    fn process_package(
+    /// Processes all packages, adding the links between them to the graph.
+    ///
+    /// With the `rayon1` feature, packages are processed in parallel. Links are added to the graph
+    /// in the same order either way, so the resulting graph is deterministic.
+    fn process_packages(
         &mut self,
+        packages: Vec<Package>,
+    ) -> Result<HashMap<PackageId, PackageMetadataImpl>, Error> {
+        let packages: Vec<_> = packages
+            .into_iter()
+            .map(|package| {
+                let package_id = PackageId::from_metadata(package.id.clone());
+                // resolved_deps is missing if the metadata was generated with --no-deps.
+                let resolved_deps = self.resolve_data.remove(&package_id).unwrap_or_default();
+                (package_id, package, resolved_deps)
+            })
+            .collect();
+
+        let this = &*self;
+        let process = |(package_id, package, resolved_deps)| {
+            this.process_package(package_id, package, resolved_deps)
+        };
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "rayon1")] {
+                use rayon::prelude::*;
+                let processed: Vec<_> = packages.into_par_iter().map(process).collect();
+            } else {
+                let processed: Vec<_> = packages.into_iter().map(process).collect();
+            }
+        }
+
+        let mut package_map = HashMap::with_capacity(processed.len());
+        for result in processed {
+            let (package_id, package, links) = result?;
+            for (dep_ix, edge) in links {
+                // Use update_edge instead of add_edge to prevent multiple edges from being added
+                // between these two nodes.
+                // XXX maybe check for an existing edge?
+                self.dep_graph.update_edge(package.package_ix, dep_ix, edge);
+            }
+            package_map.insert(package_id, package);
+        }
+        Ok(package_map)
+    }
+
+    /// Processes a single package, returning its metadata and its links to other packages.
+    #[allow(clippy::type_complexity)]
+    fn process_package(
+        &self,
+        package_id: PackageId,
         package: Package,
-    ) -> Result<(PackageId, PackageMetadataImpl), Error> {
-        let package_id = PackageId::from_metadata(package.id);
+        resolved_deps: Vec<NodeDep>,
+    ) -> Result<
+        (
+            PackageId,
+            PackageMetadataImpl,
+            Vec<(NodeIndex<PackageIx>, PackageLinkImpl)>,
+        ),
+        Error,
+    > {
         let (package_ix, _, _) = self.package_data(&package_id)?;
 
         let source = if self.workspace_members.contains(&package_id) {
             PackageSourceImpl::Workspace(self.workspace_path(&package_id, &package.manifest_path)?)
-        } else if let Some(source) = package.source {
+        } else if let Some(source) = &package.source {
             if source.is_crates_io() {
                 PackageSourceImpl::CratesIo
             } else {
-                PackageSourceImpl::External(source.repr.into())
+                PackageSourceImpl::External(self.interner.intern(&source.repr))
             }
         } else {
             // Path dependency: get the directory from the manifest path.
@@ -217,18 +316,24 @@ impl<'a> GraphBuildState<'a> {
             PackageSourceImpl::create_path(dirname, self.workspace_root)
         };
 
-        let mut build_targets = BuildTargets::new(&package_id);
-        for build_target in package.targets {
-            build_targets.add(build_target)?;
-        }
-        let build_targets = build_targets.finish();
-
-        // resolved_deps is missing if the metadata was generated with --no-deps.
-        let resolved_deps = self.resolve_data.remove(&package_id).unwrap_or_default();
-
         let dep_resolver =
             DependencyResolver::new(&package_id, &self.package_data, &package.dependencies);
+        let extras = self
+            .dep_extras
+            .get(&package.id.repr)
+            .map_or(&[][..], |extras| extras.as_slice());
+        // Dependencies are resolved to references into package.dependencies, so they can be
+        // matched up with their extra fields by address.
+        let extra_for = |dep: &Dependency| {
+            package
+                .dependencies
+                .iter()
+                .zip(extras)
+                .find(|(package_dep, _)| std::ptr::eq(*package_dep, dep))
+                .map_or(&NO_EXTRA, |(_, extra)| extra)
+        };
 
+        let mut links = Vec::with_capacity(resolved_deps.len());
         for NodeDep {
             name: resolved_name,
             pkg,
@@ -238,12 +343,77 @@ impl<'a> GraphBuildState<'a> {
             let dep_id = PackageId::from_metadata(pkg);
             let (name, deps) = dep_resolver.resolve(&resolved_name, &dep_id)?;
             let (dep_idx, _, _) = self.package_data(&dep_id)?;
-            let edge = PackageLinkImpl::new(&package_id, name, &resolved_name, deps)?;
-            // Use update_edge instead of add_edge to prevent multiple edges from being added
-            // between these two nodes.
-            // XXX maybe check for an existing edge?
-            self.dep_graph.update_edge(package_ix, dep_idx, edge);
+            let edge = PackageLinkImpl::new(
+                &package_id,
+                name,
+                &resolved_name,
+                deps.map(|dep| (dep, extra_for(dep))),
+                &self.interner,
+            )?;
+            links.push((dep_idx, edge));
+        }
+
+        let package =
+            PackageMetadataImpl::new(&package_id, package_ix, source, package, &self.interner)?;
+        Ok((package_id, package, links))
+    }
+
+    fn package_data(
+        &self,
+        id: &PackageId,
+    ) -> Result<(NodeIndex<PackageIx>, &str, &Version), Error> {
+        let (package_ix, name, version) = self.package_data.get(id).ok_or_else(|| {
+            Error::PackageGraphConstructError(format!("no package data found for package '{}'", id))
+        })?;
+        Ok((*package_ix, name, version))
+    }
+
+    /// Computes the workspace path for this package. Errors if this package is not in the
+    /// workspace.
+    fn workspace_path(
+        &self,
+        id: &PackageId,
+        manifest_path: &Utf8Path,
+    ) -> Result<Box<Utf8Path>, Error> {
+        // Strip off the workspace path from the manifest path.
+        let workspace_path = manifest_path
+            .strip_prefix(self.workspace_root)
+            .map_err(|_| {
+                Error::PackageGraphConstructError(format!(
+                    "workspace member '{}' at path {:?} not in workspace (root: {})",
+                    id, manifest_path, self.workspace_root
+                ))
+            })?;
+        let workspace_path = workspace_path.parent().ok_or_else(|| {
+            Error::PackageGraphConstructError(format!(
+                "workspace member '{}' has invalid manifest path {:?}",
+                id, manifest_path
+            ))
+        })?;
+        Ok(convert_forward_slashes(workspace_path).into_boxed_path())
+    }
+
+    fn finish(self) -> Graph<PackageId, PackageLinkImpl, Directed, PackageIx> {
+        self.dep_graph
+    }
+}
+
+impl PackageMetadataImpl {
+    /// Converts a package from `cargo metadata`, given its index and source.
+    ///
+    /// Dependency links are not part of the package metadata, and are handled separately.
+    pub(super) fn new(
+        package_id: &PackageId,
+        package_ix: NodeIndex<PackageIx>,
+        source: PackageSourceImpl,
+        package: Package,
+        interner: &Interner,
+    ) -> Result<Self, Error> {
+        let mut build_targets = BuildTargets::new(package_id);
+        for build_target in package.targets {
+            build_targets.add(build_target)?;
         }
+        let build_targets = build_targets.finish();
 
         let has_default_feature = package.features.contains_key("default");
 
@@ -263,8 +433,8 @@ impl<'a> GraphBuildState<'a> {
             .filter_map(|dep| {
                 if dep.optional {
                     match dep.rename {
-                        Some(rename) => Some(rename.into_boxed_str()),
-                        None => Some(dep.name.into_boxed_str()),
+                        Some(rename) => Some(interner.intern(&rename)),
+                        None => Some(interner.intern(&dep.name)),
                     }
                 } else {
                     None
@@ -282,7 +452,7 @@ impl<'a> GraphBuildState<'a> {
             .map(|(feature_name, deps)| {
                 let mut parsed_deps = SmallVec::with_capacity(deps.len());
                 for dep in deps {
-                    let dep = NamedFeatureDep::from_cargo_string(dep);
+                    let dep = NamedFeatureDep::from_cargo_string(&dep, interner);
                     if let NamedFeatureDep::OptionalDependency(d) = &dep {
                         let index = optional_deps.get_index_of(d.as_ref()).ok_or_else(|| {
                             Error::PackageGraphConstructError(format!(
@@ -295,7 +465,7 @@ impl<'a> GraphBuildState<'a> {
                     }
                     parsed_deps.push(dep);
                 }
-                Ok((feature_name.into_boxed_str(), parsed_deps))
+                Ok((interner.intern(&feature_name), parsed_deps))
             })
             .collect::<Result<_, Error>>()?;
 
@@ -309,76 +479,34 @@ impl<'a> GraphBuildState<'a> {
             }
         }
 
-        Ok((
-            package_id,
-            PackageMetadataImpl {
-                name: package.name,
-                version: package.version,
-                authors: package.authors,
-                description: package.description.map(|s| s.into()),
-                license: package.license.map(|s| s.into()),
-                license_file: package.license_file.map(|f| f.into()),
-                manifest_path: package.manifest_path.into(),
-                categories: package.categories,
-                keywords: package.keywords,
-                readme: package.readme.map(|s| s.into()),
-                repository: package.repository.map(|s| s.into()),
-                homepage: package.homepage.map(|s| s.into()),
-                documentation: package.documentation.map(|s| s.into()),
-                edition: package.edition.to_string().into_boxed_str(),
-                metadata_table: package.metadata,
-                links: package.links.map(|s| s.into()),
-                publish: PackagePublishImpl::new(package.publish),
-                default_run: package.default_run.map(|s| s.into()),
-                rust_version: package.rust_version,
-                named_features,
-                optional_deps,
-
-                package_ix,
-                source,
-                build_targets,
-                has_default_feature,
-            },
-        ))
-    }
-
-    fn package_data(
-        &self,
-        id: &PackageId,
-    ) -> Result<(NodeIndex<PackageIx>, &str, &Version), Error> {
-        let (package_ix, name, version) = self.package_data.get(id).ok_or_else(|| {
-            Error::PackageGraphConstructError(format!("no package data found for package '{}'", id))
-        })?;
-        Ok((*package_ix, name, version))
-    }
-
-    /// Computes the workspace path for this package. Errors if this package is not in the
-    /// workspace.
-    fn workspace_path(
-        &self,
-        id: &PackageId,
-        manifest_path: &Utf8Path,
-    ) -> Result<Box<Utf8Path>, Error> {
-        // Strip off the workspace path from the manifest path.
-        let workspace_path = manifest_path
-            .strip_prefix(self.workspace_root)
-            .map_err(|_| {
-                Error::PackageGraphConstructError(format!(
-                    "workspace member '{}' at path {:?} not in workspace (root: {})",
-                    id, manifest_path, self.workspace_root
-                ))
-            })?;
-        let workspace_path = workspace_path.parent().ok_or_else(|| {
-            Error::PackageGraphConstructError(format!(
-                "workspace member '{}' has invalid manifest path {:?}",
-                id, manifest_path
-            ))
-        })?;
-        Ok(convert_forward_slashes(workspace_path).into_boxed_path())
-    }
-
-    fn finish(self) -> Graph<PackageId, PackageLinkImpl, Directed, PackageIx> {
-        self.dep_graph
+        Ok(Self {
+            name: interner.intern(&package.name),
+            version: package.version,
+            authors: package.authors,
+            description: package.description.map(|s| s.into()),
+            license: package.license.map(|s| s.into()),
+            license_file: package.license_file.map(|f| f.into()),
+            manifest_path: package.manifest_path.into(),
+            categories: package.categories,
+            keywords: package.keywords,
+            readme: package.readme.map(|s| s.into()),
+            repository: package.repository.map(|s| s.into()),
+            homepage: package.homepage.map(|s| s.into()),
+            documentation: package.documentation.map(|s| s.into()),
+            edition: package.edition.to_string().into_boxed_str(),
+            metadata_table: package.metadata,
+            links: package.links.map(|s| s.into()),
+            publish: PackagePublishImpl::new(package.publish),
+            default_run: package.default_run.map(|s| s.into()),
+            rust_version: package.rust_version,
+            named_features,
+            optional_deps,
+
+            package_ix,
+            source,
+            build_targets,
+            has_default_feature,
+        })
     }
 }
 
@@ -398,19 +526,19 @@ impl PackageSourceImpl {
 }
 
 impl NamedFeatureDep {
-    fn from_cargo_string(input: impl Into<String>) -> Self {
-        let input = input.into();
+    fn from_cargo_string(input: &str, interner: &Interner) -> Self {
         match input.split_once('/') {
             Some((dep_name, feature)) => {
+                let feature = interner.intern(feature);
                 if let Some(dep_name_without_q) = dep_name.strip_suffix('?') {
-                    Self::dep_named_feature(dep_name_without_q, feature, true)
+                    Self::dep_named_feature(interner.intern(dep_name_without_q), feature, true)
                 } else {
-                    Self::dep_named_feature(dep_name, feature, false)
+                    Self::dep_named_feature(interner.intern(dep_name), feature, false)
                 }
             }
             None => match input.strip_prefix("dep:") {
-                Some(dep_name) => Self::optional_dependency(dep_name),
-                None => Self::named_feature(input),
+                Some(dep_name) => Self::optional_dependency(interner.intern(dep_name)),
+                None => Self::named_feature(interner.intern(input)),
             },
         }
     }
@@ -691,17 +819,19 @@ impl<'g> DependencyReqs<'g> {
 }
 
 impl PackageLinkImpl {
-    fn new<'a>(
+    pub(super) fn new<'a>(
         from_id: &PackageId,
         name: &str,
         resolved_name: &str,
-        deps: impl IntoIterator<Item = &'a Dependency>,
+        deps: impl IntoIterator<Item = (&'a Dependency, &'a DependencyExtra)>,
+        interner: &Interner,
     ) -> Result<Self, Error> {
         let mut version_req = None;
+        let mut public = false;
         let mut normal = DependencyReqImpl::default();
         let mut build = DependencyReqImpl::default();
         let mut dev = DependencyReqImpl::default();
-        for dep in deps {
+        for (dep, extra) in deps {
             // Dev dependencies cannot be optional.
             if dep.kind == DependencyKind::Development && dep.optional {
                 return Err(Error::PackageGraphConstructError(format!(
@@ -715,10 +845,15 @@ impl PackageLinkImpl {
                 version_req = Some(dep.req.clone());
             }
 
+            // Only normal dependencies can be public.
+            if dep.kind == DependencyKind::Normal {
+                public |= extra.public;
+            }
+
             match dep.kind {
-                DependencyKind::Normal => normal.add_instance(from_id, dep)?,
-                DependencyKind::Build => build.add_instance(from_id, dep)?,
-                DependencyKind::Development => dev.add_instance(from_id, dep)?,
+                DependencyKind::Normal => normal.add_instance(from_id, dep, extra, interner)?,
+                DependencyKind::Build => build.add_instance(from_id, dep, extra, interner)?,
+                DependencyKind::Development => dev.add_instance(from_id, dep, extra, interner)?,
                 _ => {
                     // unknown dependency kind -- can't do much with this!
                     continue;
@@ -727,9 +862,10 @@ impl PackageLinkImpl {
         }
 
         Ok(Self {
-            dep_name: name.into(),
-            resolved_name: resolved_name.into(),
+            dep_name: interner.intern(name),
+            resolved_name: interner.intern(resolved_name),
             version_req: version_req.expect("at least one dependency instance"),
+            public,
             normal,
             build,
             dev,
@@ -764,47 +900,82 @@ impl PackageLinkImpl {
 /// causes this union-ing to *not* happen, so that's why we store all the features enabled by
 /// each target separately.
 impl DependencyReqImpl {
-    fn add_instance(&mut self, from_id: &PackageId, dep: &Dependency) -> Result<(), Error> {
-        if dep.optional {
-            self.optional.add_instance(from_id, dep)
-        } else {
-            self.required.add_instance(from_id, dep)
+    fn add_instance(
+        &mut self,
+        from_id: &PackageId,
+        dep: &Dependency,
+        extra: &DependencyExtra,
+        interner: &Interner,
+    ) -> Result<(), Error> {
+        if self.artifact.is_none() {
+            self.artifact = extra.artifact.clone();
         }
-    }
-}
 
-impl DepRequiredOrOptional {
-    fn add_instance(&mut self, from_id: &PackageId, dep: &Dependency) -> Result<(), Error> {
         // target_spec is None if this is not a platform-specific dependency.
-        let target_spec = match dep.target.as_ref() {
+        let (target, target_spec) = match dep.target.as_ref() {
             Some(spec_or_triple) => {
                 // This is a platform-specific dependency, so add it to the list of specs.
                 let spec_or_triple = format!("{}", spec_or_triple);
-                let target_spec: TargetSpec = spec_or_triple.parse().map_err(|err| {
-                    Error::PackageGraphConstructError(format!(
-                        "for package '{}': for dependency '{}', parsing target '{}' failed: {}",
-                        from_id, dep.name, spec_or_triple, err
-                    ))
-                })?;
-                Some(target_spec)
+                let target_spec = match parse_cargo_target_spec(spec_or_triple.clone()) {
+                    Ok(target_spec) => target_spec,
+                    Err(err) => {
+                        return Err(Error::PackageGraphConstructError(format!(
+                            "for package '{}': for dependency '{}', parsing target '{}' failed: {}",
+                            from_id, dep.name, spec_or_triple, err
+                        )));
+                    }
+                };
+                (Some(spec_or_triple), Some(target_spec))
             }
-            None => None,
+            None => (None, None),
         };
 
-        self.build_if.add_spec(target_spec.as_ref());
+        let mut platform_status = PlatformStatusImpl::default();
+        platform_status.add_spec(target_spec.as_ref());
+        self.instances.push(DependencyInstanceImpl {
+            target: target.map(|target| target.into_boxed_str()),
+            platform_status,
+            version_req: dep.req.clone(),
+            optional: dep.optional,
+            default_features: dep.uses_default_features,
+            features: dep
+                .features
+                .iter()
+                .map(|feature| interner.intern(feature))
+                .collect(),
+        });
+
+        if dep.optional {
+            self.optional
+                .add_instance(dep, target_spec.as_ref(), interner);
+        } else {
+            self.required
+                .add_instance(dep, target_spec.as_ref(), interner);
+        }
+        Ok(())
+    }
+}
+
+impl DepRequiredOrOptional {
+    fn add_instance(
+        &mut self,
+        dep: &Dependency,
+        target_spec: Option<&TargetSpec>,
+        interner: &Interner,
+    ) {
+        self.build_if.add_spec(target_spec);
         if dep.uses_default_features {
-            self.default_features_if.add_spec(target_spec.as_ref());
+            self.default_features_if.add_spec(target_spec);
         } else {
-            self.no_default_features_if.add_spec(target_spec.as_ref());
+            self.no_default_features_if.add_spec(target_spec);
         }
 
         for feature in &dep.features {
             self.feature_targets
-                .entry(feature.clone())
+                .entry(interner.intern(feature))
                 .or_default()
-                .add_spec(target_spec.as_ref());
+                .add_spec(target_spec);
         }
-        Ok(())
     }
 }
 
@@ -837,26 +1008,42 @@ fn convert_forward_slashes<'a>(rel_path: impl Into<Cow<'a, Utf8Path>>) -> Utf8Pa
     }
 }
 
+/// Parses a target spec in a `Cargo.toml` `[target]` table.
+pub(super) fn parse_cargo_target_spec(
+    spec_or_triple: String,
+) -> Result<TargetSpec, TargetSpecError> {
+    match spec_or_triple.parse::<TargetSpec>() {
+        Ok(target_spec) => Ok(target_spec),
+        // Triples that are too new for target-spec to know about are still matched by name, the
+        // same way Cargo does.
+        Err(TargetSpecError::UnknownTargetTriple(_)) => {
+            Ok(TargetSpec::Triple(Triple::new_opaque(spec_or_triple)))
+        }
+        Err(err) => Err(err),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_parse_named_feature_dependency() {
+        let interner = Interner::new();
         assert_eq!(
-            NamedFeatureDep::from_cargo_string("dep/bar"),
+            NamedFeatureDep::from_cargo_string("dep/bar", &interner),
             NamedFeatureDep::dep_named_feature("dep", "bar", false),
         );
         assert_eq!(
-            NamedFeatureDep::from_cargo_string("dep?/bar"),
+            NamedFeatureDep::from_cargo_string("dep?/bar", &interner),
             NamedFeatureDep::dep_named_feature("dep", "bar", true),
         );
         assert_eq!(
-            NamedFeatureDep::from_cargo_string("dep:bar"),
+            NamedFeatureDep::from_cargo_string("dep:bar", &interner),
             NamedFeatureDep::optional_dependency("bar"),
         );
         assert_eq!(
-            NamedFeatureDep::from_cargo_string("foo-bar"),
+            NamedFeatureDep::from_cargo_string("foo-bar", &interner),
             NamedFeatureDep::named_feature("foo-bar"),
         );
     }
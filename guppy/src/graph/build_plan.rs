@@ -0,0 +1,410 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Support for ingesting the build plan produced by `cargo build --build-plan`.
+
+use crate::{
+    graph::{
+        feature::{FeatureId, FeatureSet},
+        PackageGraph, PackageMetadata, PackageSet,
+    },
+    Error, PackageId,
+};
+use camino::{Utf8Path, Utf8PathBuf};
+use semver::Version;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// The build plan for a build, as produced by `cargo build --build-plan -Z unstable-options`.
+///
+/// A build plan lists every compiler and build script invocation Cargo would perform, along with
+/// their arguments, environments and output artifacts. Compared to a
+/// [`UnitGraph`](crate::graph::UnitGraph), it has the exact command lines but identifies
+/// packages only by name and version.
+///
+/// The build plan is an unstable Cargo feature, and its format may change in the future.
+///
+/// # Examples
+///
+/// ```no_run
+/// use guppy::graph::BuildPlan;
+/// use guppy::MetadataCommand;
+/// use std::fs;
+///
+/// let graph = MetadataCommand::new().build_graph().expect("guppy graph constructed");
+/// let json = fs::read_to_string("build-plan.json").expect("build plan read");
+/// let build_plan = BuildPlan::parse_json(&json).expect("build plan parsed");
+/// let correlated = build_plan.correlate(&graph).expect("invocations correlated");
+///
+/// for invocation in correlated.invocations() {
+///     println!(
+///         "{}: {} {:?}",
+///         invocation.package().id(),
+///         invocation.invocation().program(),
+///         invocation.invocation().outputs(),
+///     );
+/// }
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+pub struct BuildPlan {
+    invocations: Vec<Invocation>,
+    #[serde(default)]
+    inputs: Vec<Utf8PathBuf>,
+}
+
+impl BuildPlan {
+    /// Parses the JSON output of `cargo build --build-plan`.
+    pub fn parse_json(json: impl AsRef<str>) -> Result<Self, Error> {
+        let build_plan: Self =
+            serde_json::from_str(json.as_ref()).map_err(Error::BuildPlanParseError)?;
+        // Validate indexes upfront so that accessors don't have to.
+        let invocation_count = build_plan.invocations.len();
+        for index in build_plan
+            .invocations
+            .iter()
+            .flat_map(|invocation| invocation.deps.iter().copied())
+        {
+            if index >= invocation_count {
+                return Err(Error::BuildPlanParseError(serde::de::Error::custom(
+                    format!(
+                        "invocation index {} out of range (there are {} invocations)",
+                        index, invocation_count
+                    ),
+                )));
+            }
+        }
+        Ok(build_plan)
+    }
+
+    /// Returns all the invocations in this build plan, in the order Cargo listed them.
+    pub fn invocations(&self) -> &[Invocation] {
+        &self.invocations
+    }
+
+    /// Returns the manifest files that were used as inputs to the build plan.
+    pub fn inputs(&self) -> &[Utf8PathBuf] {
+        &self.inputs
+    }
+
+    /// Correlates invocations in this build plan with packages in the given `PackageGraph`.
+    ///
+    /// Invocations are matched to packages by name and version. If more than one package has the
+    /// same name and version (for example, if they're from different sources), the invocation's
+    /// working directory is used to pick the package whose manifest is in it.
+    ///
+    /// Returns an error if an invocation doesn't match exactly one package.
+    pub fn correlate<'g, 'a>(
+        &'a self,
+        graph: &'g PackageGraph,
+    ) -> Result<CorrelatedBuildPlan<'g, 'a>, Error> {
+        let mut candidates: HashMap<(&str, &Version), Vec<PackageMetadata<'g>>> = HashMap::new();
+        for package in graph.packages() {
+            candidates
+                .entry((package.name(), package.version()))
+                .or_default()
+                .push(package);
+        }
+
+        let mut packages = Vec::with_capacity(self.invocations.len());
+        for invocation in &self.invocations {
+            let key = (
+                invocation.package_name.as_str(),
+                &invocation.package_version,
+            );
+            let matches = candidates.get(&key).map_or(&[][..], |v| v.as_slice());
+            let package = match matches {
+                [package] => *package,
+                [] => {
+                    return Err(Error::BuildPlanError(format!(
+                        "no package found matching {} {}",
+                        invocation.package_name, invocation.package_version
+                    )))
+                }
+                _ => {
+                    let mut in_cwd = matches.iter().filter(|package| {
+                        invocation
+                            .cwd
+                            .as_deref()
+                            .map_or(false, |cwd| package.manifest_path().parent() == Some(cwd))
+                    });
+                    match (in_cwd.next(), in_cwd.next()) {
+                        (Some(package), None) => *package,
+                        _ => {
+                            return Err(Error::BuildPlanError(format!(
+                                "multiple packages found matching {} {}",
+                                invocation.package_name, invocation.package_version
+                            )))
+                        }
+                    }
+                }
+            };
+            packages.push(package);
+        }
+
+        Ok(CorrelatedBuildPlan {
+            graph,
+            build_plan: self,
+            packages,
+        })
+    }
+}
+
+/// A single compiler or build script invocation in a [`BuildPlan`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct Invocation {
+    package_name: String,
+    package_version: Version,
+    target_kind: Vec<String>,
+    kind: Option<String>,
+    compile_mode: String,
+    #[serde(default)]
+    deps: Vec<usize>,
+    #[serde(default)]
+    outputs: Vec<Utf8PathBuf>,
+    #[serde(default)]
+    links: BTreeMap<Utf8PathBuf, Utf8PathBuf>,
+    program: String,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    #[serde(default)]
+    cwd: Option<Utf8PathBuf>,
+}
+
+impl Invocation {
+    /// Returns the name of the package this invocation is for.
+    pub fn package_name(&self) -> &str {
+        &self.package_name
+    }
+
+    /// Returns the version of the package this invocation is for.
+    pub fn package_version(&self) -> &Version {
+        &self.package_version
+    }
+
+    /// Returns the kinds of the target this invocation builds or runs, e.g. `["lib"]` or
+    /// `["custom-build"]`.
+    pub fn target_kind(&self) -> impl ExactSizeIterator<Item = &str> + '_ {
+        self.target_kind.iter().map(|kind| kind.as_str())
+    }
+
+    /// Returns the target triple this invocation builds for.
+    ///
+    /// This is `None` for invocations for the host platform. If no `--target` was passed in to
+    /// Cargo, the host is also the target platform, so this is `None` for all invocations.
+    pub fn platform(&self) -> Option<&str> {
+        self.kind.as_deref()
+    }
+
+    /// Returns the compile mode of this invocation, e.g. `"build"`, `"check"` or
+    /// `"run-custom-build"`.
+    pub fn compile_mode(&self) -> &str {
+        &self.compile_mode
+    }
+
+    /// Returns true if this invocation runs a build script, rather than compiling something.
+    pub fn is_run_custom_build(&self) -> bool {
+        self.compile_mode == "run-custom-build"
+    }
+
+    /// Returns the indexes of the invocations this one depends on, in
+    /// [`BuildPlan::invocations`].
+    pub fn deps(&self) -> &[usize] {
+        &self.deps
+    }
+
+    /// Returns the artifacts produced by this invocation.
+    pub fn outputs(&self) -> &[Utf8PathBuf] {
+        &self.outputs
+    }
+
+    /// Returns the links Cargo creates after this invocation, as a map of link paths to the
+    /// artifacts they point to.
+    pub fn links(&self) -> &BTreeMap<Utf8PathBuf, Utf8PathBuf> {
+        &self.links
+    }
+
+    /// Returns the program that's run, e.g. `rustc` or the path to a compiled build script.
+    pub fn program(&self) -> &str {
+        &self.program
+    }
+
+    /// Returns the arguments passed to the program.
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// Returns the environment variables set for the program.
+    pub fn env(&self) -> &BTreeMap<String, String> {
+        &self.env
+    }
+
+    /// Returns the working directory the program is run in, if any.
+    pub fn cwd(&self) -> Option<&Utf8Path> {
+        self.cwd.as_deref()
+    }
+
+    /// Returns the crate name passed to `rustc` with `--crate-name`, if any.
+    pub fn crate_name(&self) -> Option<&str> {
+        self.arg_values("--crate-name").next()
+    }
+
+    /// Returns the features enabled for this invocation, parsed from the
+    /// `--cfg 'feature="..."'` arguments passed to `rustc`.
+    ///
+    /// Build script runs don't have these arguments, so this is empty for them.
+    pub fn features(&self) -> impl Iterator<Item = &str> + '_ {
+        self.arg_values("--cfg").filter_map(|cfg| {
+            cfg.strip_prefix("feature=\"")
+                .and_then(|feature| feature.strip_suffix('"'))
+        })
+    }
+
+    fn arg_values<'a>(&'a self, flag: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.args
+            .windows(2)
+            .filter(move |window| window[0] == flag)
+            .map(|window| window[1].as_str())
+    }
+}
+
+/// A [`BuildPlan`] whose invocations have been correlated with packages in a [`PackageGraph`].
+///
+/// Returned by [`BuildPlan::correlate`].
+#[derive(Clone, Debug)]
+pub struct CorrelatedBuildPlan<'g, 'a> {
+    graph: &'g PackageGraph,
+    build_plan: &'a BuildPlan,
+    // Same indexes as the build plan.
+    packages: Vec<PackageMetadata<'g>>,
+}
+
+impl<'g, 'a> CorrelatedBuildPlan<'g, 'a> {
+    /// Returns the package graph the invocations were correlated with.
+    pub fn graph(&self) -> &'g PackageGraph {
+        self.graph
+    }
+
+    /// Returns the underlying build plan.
+    pub fn build_plan(&self) -> &'a BuildPlan {
+        self.build_plan
+    }
+
+    /// Returns the correlated invocations, in the order Cargo listed them.
+    pub fn invocations(&self) -> impl ExactSizeIterator<Item = CorrelatedInvocation<'g, 'a>> + '_ {
+        (0..self.packages.len()).map(move |index| self.invocation_at(index))
+    }
+
+    /// Returns the correlated invocation at the given index, if any.
+    pub fn invocation(&self, index: usize) -> Option<CorrelatedInvocation<'g, 'a>> {
+        if index < self.packages.len() {
+            Some(self.invocation_at(index))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the invocations for the given package.
+    ///
+    /// Returns an error if the package ID is unknown to the graph.
+    pub fn invocations_for<'b>(
+        &'b self,
+        package_id: &'b PackageId,
+    ) -> Result<impl Iterator<Item = CorrelatedInvocation<'g, 'a>> + 'b, Error> {
+        self.graph.metadata(package_id)?;
+        Ok(self
+            .invocations()
+            .filter(move |invocation| invocation.package.id() == package_id))
+    }
+
+    /// Returns the output artifacts produced for the given package, across all its invocations.
+    ///
+    /// Returns an error if the package ID is unknown to the graph.
+    pub fn outputs_for(&self, package_id: &PackageId) -> Result<Vec<&'a Utf8Path>, Error> {
+        Ok(self
+            .invocations_for(package_id)?
+            .flat_map(|invocation| invocation.invocation.outputs.iter())
+            .map(|output| output.as_path())
+            .collect())
+    }
+
+    /// Returns the set of packages with at least one invocation.
+    pub fn package_set(&self) -> PackageSet<'g> {
+        self.package_set_with(|_| true)
+    }
+
+    /// Returns the set of packages with at least one invocation for which the callback returns
+    /// true.
+    pub fn package_set_with(
+        &self,
+        mut callback: impl FnMut(&CorrelatedInvocation<'g, 'a>) -> bool,
+    ) -> PackageSet<'g> {
+        let ids: Vec<_> = self
+            .invocations()
+            .filter(|invocation| callback(invocation))
+            .map(|invocation| invocation.package.id())
+            .collect();
+        self.graph
+            .resolve_ids(ids)
+            .expect("package IDs were correlated")
+    }
+
+    /// Returns the set of features enabled across invocations for which the callback returns true.
+    ///
+    /// Features are parsed from `rustc` arguments: see [`Invocation::features`]. The base feature
+    /// of every matching package is included. Returns an error if an invocation has a feature
+    /// unknown to the feature graph.
+    pub fn feature_set_with(
+        &self,
+        mut callback: impl FnMut(&CorrelatedInvocation<'g, 'a>) -> bool,
+    ) -> Result<FeatureSet<'g>, Error> {
+        let mut feature_ids = vec![];
+        for invocation in self.invocations().filter(|invocation| callback(invocation)) {
+            let package_id = invocation.package.id();
+            feature_ids.push(FeatureId::base(package_id));
+            feature_ids.extend(
+                invocation
+                    .invocation
+                    .features()
+                    .map(|feature| FeatureId::named(package_id, feature)),
+            );
+        }
+        self.graph.feature_graph().resolve_ids(feature_ids)
+    }
+
+    fn invocation_at(&self, index: usize) -> CorrelatedInvocation<'g, 'a> {
+        CorrelatedInvocation {
+            index,
+            invocation: &self.build_plan.invocations[index],
+            package: self.packages[index],
+        }
+    }
+}
+
+/// An [`Invocation`] correlated with the package it's for.
+///
+/// Returned by [`CorrelatedBuildPlan`].
+#[derive(Clone, Copy, Debug)]
+pub struct CorrelatedInvocation<'g, 'a> {
+    index: usize,
+    invocation: &'a Invocation,
+    package: PackageMetadata<'g>,
+}
+
+impl<'g, 'a> CorrelatedInvocation<'g, 'a> {
+    /// Returns the index of this invocation in [`BuildPlan::invocations`].
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the invocation.
+    pub fn invocation(&self) -> &'a Invocation {
+        self.invocation
+    }
+
+    /// Returns the package this invocation is for.
+    pub fn package(&self) -> PackageMetadata<'g> {
+        self.package
+    }
+}
@@ -12,6 +12,7 @@ use std::{borrow::Borrow, cmp::Ordering};
 /// For more, see [Cargo
 /// Targets](https://doc.rust-lang.org/nightly/cargo/reference/cargo-targets.html) in the Cargo
 /// reference.
+#[derive(Clone, Copy, Debug)]
 pub struct BuildTarget<'g> {
     id: BuildTargetId<'g>,
     inner: &'g BuildTargetImpl,
@@ -187,11 +188,13 @@ impl<'g> BuildTargetKind<'g> {
 
 /// Stored data in a `BuildTarget`.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "binary", derive(serde::Deserialize, serde::Serialize))]
 pub(super) struct BuildTargetImpl {
     pub(super) kind: BuildTargetKindImpl,
     // This is only set if the id is BuildTargetId::Library.
     pub(super) lib_name: Option<Box<str>>,
     pub(super) required_features: Vec<String>,
+    #[cfg_attr(feature = "binary", serde(with = "crate::graph::binary::box_path"))]
     pub(super) path: Box<Utf8Path>,
     pub(super) edition: Box<str>,
     pub(super) doc_tests: bool,
@@ -200,6 +203,7 @@ pub(super) struct BuildTargetImpl {
 /// Owned version of `BuildTargetId`.
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 #[cfg_attr(all(test, feature = "proptest1"), derive(proptest_derive::Arbitrary))]
+#[cfg_attr(feature = "binary", derive(serde::Deserialize, serde::Serialize))]
 pub(super) enum OwnedBuildTargetId {
     Library,
     BuildScript,
@@ -224,6 +228,7 @@ impl OwnedBuildTargetId {
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 #[non_exhaustive]
+#[cfg_attr(feature = "binary", derive(serde::Deserialize, serde::Serialize))]
 pub(super) enum BuildTargetKindImpl {
     LibraryOrExample(SortedSet<String>),
     ProcMacro,
@@ -4,10 +4,12 @@
 use crate::{
     graph::{
         cargo::{
-            CargoIntermediateSet, CargoOptions, CargoResolverVersion, CargoSet, InitialsPlatform,
+            BuildPlatform, CargoIntermediateSet, CargoOptions, CargoResolverVersion, CargoSet,
+            InitialsPlatform,
         },
         feature::{ConditionalLink, FeatureLabel, FeatureQuery, FeatureSet, StandardFeatures},
-        DependencyDirection, PackageGraph, PackageIx, PackageLink, PackageSet,
+        ArtifactTarget, DependencyDirection, DependencyReq, PackageGraph, PackageIx, PackageLink,
+        PackageSet,
     },
     platform::{EnabledTernary, PlatformSpec},
     sorted_set::SortedSet,
@@ -86,6 +88,28 @@ impl<'a> CargoSetBuildState<'a> {
         self.omitted_packages.contains(&package_ix)
     }
 
+    /// For an artifact dependency forced onto a platform with the `target` key, returns that
+    /// platform, along with whether the library is depended on as well (`lib = true`).
+    ///
+    /// Only the host and target platforms are modeled, so artifacts forced onto any other platform
+    /// are treated as being built for the target platform.
+    fn forced_artifact_platform(&self, req: DependencyReq<'_>) -> Option<(BuildPlatform, bool)> {
+        let artifact = req.artifact()?;
+        let platform = match artifact.target()? {
+            ArtifactTarget::Target => BuildPlatform::Target,
+            ArtifactTarget::Triple(triple) => {
+                if triple_str(&self.opts.target_platform) != Some(triple)
+                    && triple_str(&self.opts.host_platform) == Some(triple)
+                {
+                    BuildPlatform::Host
+                } else {
+                    BuildPlatform::Target
+                }
+            }
+        };
+        Some((platform, artifact.lib()))
+    }
+
     fn build_set<'g>(
         &self,
         initials: FeatureSet<'g>,
@@ -220,25 +244,49 @@ impl<'a> CargoSetBuildState<'a> {
             // instead.
             let proc_macro_redirect = follow_target && to.is_proc_macro();
 
+            // Normal/dev artifact dependencies forced onto the host platform are also redirected
+            // there. If the library is depended on as well, it's still built on the target.
+            let artifact_host_redirect = match self
+                .forced_artifact_platform(link.normal())
+                .or_else(|| self.forced_artifact_platform(link.dev()))
+            {
+                Some((BuildPlatform::Host, lib)) if follow_target => Some(lib),
+                _ => None,
+            };
+
             // Build dependencies are evaluated against the host platform.
-            let build_dep_redirect = consider_build
+            let build_dep_enabled = consider_build
                 && is_enabled(target_set, &link, DependencyKind::Build, host_platform);
+            // Build dependencies are built on the host, unless they're artifact dependencies forced
+            // onto the target platform. In that case only the library (if depended on) is built on
+            // the host.
+            let (build_dep_redirect, build_artifact_on_target) =
+                match self.forced_artifact_platform(link.build()) {
+                    Some((BuildPlatform::Target, lib)) if build_dep_enabled => (lib, true),
+                    _ => (build_dep_enabled, false),
+                };
 
             // Finally, process what needs to be done.
-            if build_dep_redirect || proc_macro_redirect {
+            if build_dep_redirect || proc_macro_redirect || artifact_host_redirect.is_some() {
                 if from.in_workspace() {
                     // The 'to' node is either in the workspace or a direct dependency [a].
                     host_direct_deps.visit(to.package_ix());
                 }
                 host_ixs.push(to.package_ix());
             }
-            if build_dep_redirect {
+            if build_dep_enabled {
                 build_dep_edge_ixs.push(link.edge_ix());
             }
             if proc_macro_redirect {
                 proc_macro_edge_ixs.push(link.edge_ix());
                 follow_target = false;
             }
+            if artifact_host_redirect == Some(false) {
+                follow_target = false;
+            }
+            if build_artifact_on_target {
+                follow_target = true;
+            }
 
             if from.in_workspace() && follow_target {
                 // The 'to' node is either in the workspace or a direct dependency.
@@ -262,6 +310,9 @@ impl<'a> CargoSetBuildState<'a> {
 
                 // All relevant nodes in host_ixs have already been added to host_direct_deps at [a].
 
+                // Artifact dependencies of packages built on the host that are forced onto the
+                // target platform aren't modeled: they're treated as if they're built on the host.
+
                 let consider_build = from.has_build_script();
 
                 // Only normal and build dependencies are considered, regardless of whether this is
@@ -396,16 +447,35 @@ impl<'a> CargoSetBuildState<'a> {
             // instead.
             let proc_macro_redirect = follow_target && to.package().is_proc_macro();
 
+            // Artifact dependencies are redirected to the platform they're forced onto, as in
+            // build_set.
+            let package_link = link.package_link();
+            let artifact_host_redirect = match self
+                .forced_artifact_platform(package_link.normal())
+                .or_else(|| self.forced_artifact_platform(package_link.dev()))
+            {
+                Some((BuildPlatform::Host, lib)) if follow_target => Some(lib),
+                _ => None,
+            };
+
             // Build dependencies are evaluated against the host platform.
-            let build_dep_redirect = is_enabled(&link, DependencyKind::Build, host_platform);
+            let build_dep_enabled = is_enabled(&link, DependencyKind::Build, host_platform);
+            let (build_dep_redirect, build_artifact_on_target) =
+                match self.forced_artifact_platform(package_link.build()) {
+                    Some((BuildPlatform::Target, lib)) if build_dep_enabled => (lib, true),
+                    _ => (build_dep_enabled, false),
+                };
 
             // Finally, process what needs to be done.
-            if build_dep_redirect || proc_macro_redirect {
+            if build_dep_redirect || proc_macro_redirect || artifact_host_redirect.is_some() {
                 host_ixs.push(to.feature_ix());
             }
-            if proc_macro_redirect {
+            if proc_macro_redirect || artifact_host_redirect == Some(false) {
                 follow_target = false;
             }
+            if build_artifact_on_target {
+                follow_target = true;
+            }
 
             follow_target
         });
@@ -436,3 +506,11 @@ impl<'a> CargoSetBuildState<'a> {
         CargoIntermediateSet::TargetHost { target, host }
     }
 }
+
+/// Returns the triple of a platform spec, if it is for an individual platform.
+fn triple_str(spec: &PlatformSpec) -> Option<&str> {
+    match spec {
+        PlatformSpec::Platform(platform) => Some(platform.triple_str()),
+        PlatformSpec::Always | PlatformSpec::Any => None,
+    }
+}
@@ -0,0 +1,371 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Export of Cargo builds as CycloneDX software bills of materials.
+
+use crate::{
+    graph::{
+        cargo::{BuildPlatform, CargoOptions, CargoSet},
+        feature::FeatureList,
+        lockfile::{lockfile_error, read_toml},
+        DependencyDirection, LicenseExpr, PackageMetadata, PackageSource,
+    },
+    platform::PlatformSpec,
+    Error, PackageId,
+};
+use camino::Utf8Path;
+use serde_json::{json, Value as JsonValue};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use toml::{value::Table, Value as TomlValue};
+
+/// Exports a [`CargoSet`] as a [CycloneDX](https://cyclonedx.org/) 1.5 bill of materials (BOM).
+///
+/// The BOM has a component for every package in the build, whether it's built on the target
+/// platform, the host platform, or both. Each component records:
+///
+/// * the package's name, version, description, license and links
+/// * a [package URL](https://github.com/package-url/purl-spec) for packages from registries and
+///   Git repositories (path dependencies and workspace members don't have one)
+/// * the SHA-256 checksum from `Cargo.lock`, if one was added with
+///   [`add_lockfile`](Self::add_lockfile) or [`read_lockfile`](Self::read_lockfile)
+/// * the platforms the package is built on, and the features enabled on each of them, as
+///   `guppy:` properties.
+///
+/// The dependency relationships between components are the direct dependencies that are
+/// enabled in the build. The target and host platforms are recorded as properties of the BOM
+/// metadata.
+///
+/// Output is deterministic: components and dependencies are sorted by package ID.
+///
+/// Requires the `cyclonedx` feature to be enabled.
+///
+/// # Examples
+///
+/// ```
+/// use guppy::graph::cargo::{CargoOptions, CycloneDxExporter};
+/// use guppy::graph::feature::StandardFeatures;
+/// use guppy::MetadataCommand;
+///
+/// let graph = MetadataCommand::new().build_graph().expect("guppy graph constructed");
+/// let opts = CargoOptions::new();
+/// let cargo_set = graph
+///     .resolve_workspace()
+///     .to_feature_set(StandardFeatures::Default)
+///     .into_cargo_set(&opts)
+///     .expect("cargo set resolved");
+///
+/// let mut exporter = CycloneDxExporter::new(&cargo_set, &opts);
+/// exporter
+///     .read_lockfile(graph.workspace().root().join("Cargo.lock"))
+///     .expect("Cargo.lock read");
+/// let bom = exporter.export();
+/// assert_eq!(bom["specVersion"], "1.5");
+/// ```
+#[derive(Clone, Debug)]
+pub struct CycloneDxExporter<'a, 'g> {
+    cargo_set: &'a CargoSet<'g>,
+    opts: &'a CargoOptions<'a>,
+    // (name, version, source) -> checksum
+    checksums: HashMap<(String, String, String), String>,
+    serial_number: Option<String>,
+}
+
+impl<'a, 'g> CycloneDxExporter<'a, 'g> {
+    /// Creates a new exporter for the given `CargoSet`.
+    ///
+    /// `opts` should be the options the `CargoSet` was resolved with.
+    pub fn new(cargo_set: &'a CargoSet<'g>, opts: &'a CargoOptions<'a>) -> Self {
+        Self {
+            cargo_set,
+            opts,
+            checksums: HashMap::new(),
+            serial_number: None,
+        }
+    }
+
+    /// Reads the `Cargo.lock` at the given path, and adds the checksums in it to the BOM.
+    pub fn read_lockfile(&mut self, path: impl AsRef<Utf8Path>) -> Result<&mut Self, Error> {
+        let path = path.as_ref();
+        let lockfile = read_toml(path)?;
+        add_checksums(&lockfile, &mut self.checksums);
+        Ok(self)
+    }
+
+    /// Parses the contents of a `Cargo.lock`, and adds the checksums in it to the BOM.
+    pub fn add_lockfile(&mut self, contents: &str) -> Result<&mut Self, Error> {
+        let lockfile: Table = toml::from_str(contents)
+            .map_err(|err| lockfile_error(Utf8Path::new("Cargo.lock"), err))?;
+        add_checksums(&lockfile, &mut self.checksums);
+        Ok(self)
+    }
+
+    /// Sets the serial number of the BOM.
+    ///
+    /// CycloneDX serial numbers are UUID URNs of the form `urn:uuid:...`. By default, the BOM
+    /// doesn't have a serial number.
+    pub fn set_serial_number(&mut self, serial_number: impl Into<String>) -> &mut Self {
+        self.serial_number = Some(serial_number.into());
+        self
+    }
+
+    /// Returns the BOM as a JSON document.
+    pub fn export(&self) -> JsonValue {
+        let mut packages: BTreeMap<&'g PackageId, PackageEntry<'g>> = BTreeMap::new();
+        for (build_platform, feature_set) in self.cargo_set.all_features() {
+            for features in feature_set.packages_with_features(DependencyDirection::Forward) {
+                let entry =
+                    packages
+                        .entry(features.package().id())
+                        .or_insert_with(|| PackageEntry {
+                            package: *features.package(),
+                            features: vec![],
+                        });
+                entry.features.push((build_platform, features));
+            }
+        }
+
+        let components: Vec<_> = packages
+            .values()
+            .map(|entry| self.component(entry))
+            .collect();
+        let dependencies: Vec<_> = packages
+            .values()
+            .map(|entry| {
+                let depends_on = self.depends_on(entry, &packages);
+                json!({
+                    "ref": entry.package.id().repr(),
+                    "dependsOn": depends_on,
+                })
+            })
+            .collect();
+
+        let mut bom = json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.5",
+            "version": 1,
+            "metadata": {
+                "tools": {
+                    "components": [{
+                        "type": "library",
+                        "name": "guppy",
+                        "version": env!("CARGO_PKG_VERSION"),
+                    }],
+                },
+                "properties": [
+                    property("guppy:target-platform", platform_str(&self.opts.target_platform)),
+                    property("guppy:host-platform", platform_str(&self.opts.host_platform)),
+                ],
+            },
+            "components": components,
+            "dependencies": dependencies,
+        });
+        if let Some(serial_number) = &self.serial_number {
+            bom["serialNumber"] = serial_number.as_str().into();
+        }
+        bom
+    }
+
+    fn component(&self, entry: &PackageEntry<'g>) -> JsonValue {
+        let package = entry.package;
+        let mut component = json!({
+            "type": "library",
+            "bom-ref": package.id().repr(),
+            "name": package.name(),
+            "version": package.version().to_string(),
+            "scope": "required",
+        });
+
+        if let Some(description) = package.description() {
+            component["description"] = description.into();
+        }
+        if !package.authors().is_empty() {
+            component["author"] = package.authors().join(", ").into();
+        }
+        if let Some(license) = package.license() {
+            component["licenses"] = match LicenseExpr::parse_lax(license) {
+                Ok(expr) => json!([{ "expression": expr.to_string() }]),
+                Err(_) => json!([{ "license": { "name": license } }]),
+            };
+        }
+        if let Some(purl) = package.purl() {
+            component["purl"] = purl.into();
+        }
+        if let PackageSource::External(source) = package.source() {
+            let key = (
+                package.name().to_owned(),
+                package.version().to_string(),
+                source.to_owned(),
+            );
+            if let Some(checksum) = self.checksums.get(&key) {
+                component["hashes"] = json!([{ "alg": "SHA-256", "content": checksum }]);
+            }
+        }
+
+        let external_references: Vec<_> = [
+            ("vcs", package.repository()),
+            ("website", package.homepage()),
+            ("documentation", package.documentation()),
+        ]
+        .iter()
+        .filter_map(|(kind, url)| url.map(|url| json!({ "type": kind, "url": url })))
+        .collect();
+        if !external_references.is_empty() {
+            component["externalReferences"] = external_references.into();
+        }
+
+        let mut properties = vec![];
+        for (build_platform, features) in &entry.features {
+            let named: Vec<_> = features.named_features().collect();
+            properties.push(property("guppy:build-platform", build_platform.to_string()));
+            properties.push(property(
+                &format!("guppy:{}-features", build_platform),
+                named.join(","),
+            ));
+        }
+        component["properties"] = properties.into();
+
+        component
+    }
+
+    fn depends_on(
+        &self,
+        entry: &PackageEntry<'g>,
+        packages: &BTreeMap<&'g PackageId, PackageEntry<'g>>,
+    ) -> Vec<&'g str> {
+        let package = entry.package;
+        let is_initial = self
+            .cargo_set
+            .initials()
+            .contains_package(package.id())
+            .expect("package ID is known");
+        let optional_enabled = |dep_name: &str| {
+            entry
+                .features
+                .iter()
+                .any(|(_, features)| features.has_optional_dependency(dep_name))
+        };
+
+        let depends_on: BTreeSet<_> = package
+            .direct_links()
+            .filter(|link| packages.contains_key(link.to().id()))
+            .filter(|link| {
+                let enabled = [link.normal(), link.build()].iter().any(|req| {
+                    req.is_present()
+                        && (!req.status().required_status().is_never()
+                            || optional_enabled(link.dep_name()))
+                });
+                enabled || (self.opts.include_dev && is_initial && link.dev().is_present())
+            })
+            .map(|link| link.to().id().repr())
+            .collect();
+        depends_on.into_iter().collect()
+    }
+}
+
+/// Adds the checksums in a parsed `Cargo.lock` to the map, keyed by (name, version, source).
+fn add_checksums(lockfile: &Table, checksums: &mut HashMap<(String, String, String), String>) {
+    for package in lockfile
+        .get("package")
+        .and_then(TomlValue::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let field = |key| package.get(key).and_then(TomlValue::as_str);
+        if let (Some(name), Some(version), Some(source), Some(checksum)) = (
+            field("name"),
+            field("version"),
+            field("source"),
+            field("checksum"),
+        ) {
+            checksums.insert(
+                (name.to_owned(), version.to_owned(), source.to_owned()),
+                checksum.to_owned(),
+            );
+        }
+    }
+
+    // Version 1 lockfiles store checksums in the [metadata] table instead, with keys of the
+    // form "checksum <name> <version> (<source>)".
+    for (key, checksum) in lockfile
+        .get("metadata")
+        .and_then(TomlValue::as_table)
+        .into_iter()
+        .flatten()
+    {
+        let mut parts = key.splitn(4, ' ');
+        if let (Some("checksum"), Some(name), Some(version), Some(source), Some(checksum)) = (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            checksum.as_str(),
+        ) {
+            let source = source.trim_start_matches('(').trim_end_matches(')');
+            checksums.insert(
+                (name.to_owned(), version.to_owned(), source.to_owned()),
+                checksum.to_owned(),
+            );
+        }
+    }
+}
+
+/// A package in the BOM, along with the features it's built with on each platform.
+struct PackageEntry<'g> {
+    package: PackageMetadata<'g>,
+    features: Vec<(BuildPlatform, FeatureList<'g>)>,
+}
+
+fn property(name: &str, value: impl Into<String>) -> JsonValue {
+    json!({ "name": name, "value": value.into() })
+}
+
+fn platform_str(platform: &PlatformSpec) -> &str {
+    match platform {
+        PlatformSpec::Platform(platform) => platform.triple_str(),
+        PlatformSpec::Any => "any",
+        PlatformSpec::Always => "always",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lockfile_checksums() {
+        let lockfile: Table = toml::from_str(
+            r#"
+[[package]]
+name = "bytes"
+version = "0.5.4"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "abc"
+
+[[package]]
+name = "local"
+version = "0.1.0"
+
+[metadata]
+"checksum lazy_static 1.4.0 (registry+https://github.com/rust-lang/crates.io-index)" = "def"
+"#,
+        )
+        .expect("valid TOML");
+        let mut checksums = HashMap::new();
+        add_checksums(&lockfile, &mut checksums);
+
+        let key = |name: &str, version: &str| {
+            (
+                name.to_owned(),
+                version.to_owned(),
+                "registry+https://github.com/rust-lang/crates.io-index".to_owned(),
+            )
+        };
+        assert_eq!(checksums.len(), 2, "packages without sources are skipped");
+        assert_eq!(checksums[&key("bytes", "0.5.4")], "abc");
+        assert_eq!(
+            checksums[&key("lazy_static", "1.4.0")],
+            "def",
+            "version 1 lockfile checksums are read"
+        );
+    }
+}
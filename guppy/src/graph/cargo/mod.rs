@@ -8,5 +8,9 @@
 
 pub(super) mod build;
 mod cargo_api;
+#[cfg(feature = "cyclonedx")]
+mod cyclonedx;
 
 pub use cargo_api::*;
+#[cfg(feature = "cyclonedx")]
+pub use cyclonedx::*;
@@ -0,0 +1,244 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Download and unpacked sizes of packages.
+
+use crate::{
+    graph::{DependencyDirection, PackageMetadata, PackageSet},
+    PackageId,
+};
+use camino::{Utf8Path, Utf8PathBuf};
+use std::{collections::HashMap, fs, io, path::Path};
+
+/// The sizes of packages, keyed by package ID.
+///
+/// Sizes aren't part of `cargo metadata` output, so they have to be looked up separately and
+/// attached to the graph through this type. They can be read from the local registry cache with
+/// [`from_registry_cache`](Self::from_registry_cache), or added from other sources (for example
+/// the `crates.io` API) with [`insert`](Self::insert).
+///
+/// # Examples
+///
+/// ```no_run
+/// use guppy::graph::CrateSizes;
+/// use guppy::MetadataCommand;
+///
+/// let graph = MetadataCommand::new().build_graph().expect("guppy graph constructed");
+/// let package_set = graph.resolve_all();
+/// let sizes = CrateSizes::from_registry_cache(&package_set);
+/// let total = package_set.total_size(&sizes);
+/// println!(
+///     "{} bytes to download, {} bytes unpacked ({} packages unknown)",
+///     total.download(),
+///     total.unpacked(),
+///     total.unknown(),
+/// );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CrateSizes {
+    sizes: HashMap<PackageId, CrateSize>,
+}
+
+impl CrateSizes {
+    /// Creates a new, empty `CrateSizes`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the sizes of packages in this set from the local registry cache.
+    ///
+    /// Cargo downloads `.crate` files for registry packages to
+    /// `$CARGO_HOME/registry/cache/<index>/`, and unpacks them to
+    /// `$CARGO_HOME/registry/src/<index>/`. For each registry package in this set, the download
+    /// size is the size of its `.crate` file, and the unpacked size is the total size of the files
+    /// in the directory its manifest is in.
+    ///
+    /// Packages that aren't from a registry, and sizes that couldn't be read (for example because
+    /// the `.crate` file has been cleaned up), are skipped.
+    pub fn from_registry_cache(package_set: &PackageSet<'_>) -> Self {
+        let mut sizes = Self::new();
+        for package in package_set.packages(DependencyDirection::Forward) {
+            if !package.source().kind().is_registry() {
+                continue;
+            }
+            let (crate_file, src_dir) = match registry_cache_paths(package.manifest_path()) {
+                Some(paths) => paths,
+                None => continue,
+            };
+            let size = CrateSize {
+                download: fs::metadata(&crate_file)
+                    .ok()
+                    .map(|metadata| metadata.len()),
+                unpacked: dir_size(src_dir.as_std_path()).ok(),
+            };
+            if size.download.is_some() || size.unpacked.is_some() {
+                sizes.insert(package.id().clone(), size);
+            }
+        }
+        sizes
+    }
+
+    /// Sets the size of a package, returning the previous size if any.
+    pub fn insert(&mut self, package_id: PackageId, size: CrateSize) -> Option<CrateSize> {
+        self.sizes.insert(package_id, size)
+    }
+
+    /// Returns the size of the given package, if known.
+    pub fn get(&self, package_id: &PackageId) -> Option<CrateSize> {
+        self.sizes.get(package_id).copied()
+    }
+
+    /// Returns the number of packages with known sizes.
+    pub fn len(&self) -> usize {
+        self.sizes.len()
+    }
+
+    /// Returns true if no sizes are known.
+    pub fn is_empty(&self) -> bool {
+        self.sizes.is_empty()
+    }
+}
+
+/// The download and unpacked sizes of a package, in bytes.
+///
+/// Part of [`CrateSizes`].
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct CrateSize {
+    download: Option<u64>,
+    unpacked: Option<u64>,
+}
+
+impl CrateSize {
+    /// Creates a new `CrateSize`.
+    pub fn new(download: Option<u64>, unpacked: Option<u64>) -> Self {
+        Self { download, unpacked }
+    }
+
+    /// Returns the size of the compressed `.crate` file, if known.
+    pub fn download(&self) -> Option<u64> {
+        self.download
+    }
+
+    /// Returns the total size of the package's files once unpacked, if known.
+    pub fn unpacked(&self) -> Option<u64> {
+        self.unpacked
+    }
+}
+
+impl<'g> PackageMetadata<'g> {
+    /// Returns the size of this package, if known to `sizes`.
+    pub fn crate_size(&self, sizes: &CrateSizes) -> Option<CrateSize> {
+        sizes.get(self.id())
+    }
+}
+
+impl<'g> PackageSet<'g> {
+    /// Returns the total sizes of the packages in this set.
+    ///
+    /// To compute the size a package pulls in transitively, call this on the result of
+    /// `package.to_package_query(DependencyDirection::Forward).resolve()`.
+    pub fn total_size(&self, sizes: &CrateSizes) -> TotalSize {
+        let mut total = TotalSize::default();
+        for package in self.packages(DependencyDirection::Forward) {
+            let size = sizes.get(package.id()).unwrap_or_default();
+            match (size.download, size.unpacked) {
+                (None, None) => total.unknown += 1,
+                (download, unpacked) => {
+                    total.download += download.unwrap_or(0);
+                    total.unpacked += unpacked.unwrap_or(0);
+                }
+            }
+        }
+        total
+    }
+}
+
+/// The total sizes of a set of packages, in bytes.
+///
+/// Returned by [`PackageSet::total_size`].
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct TotalSize {
+    download: u64,
+    unpacked: u64,
+    unknown: usize,
+}
+
+impl TotalSize {
+    /// Returns the total size of the `.crate` files for packages whose download sizes are known.
+    pub fn download(&self) -> u64 {
+        self.download
+    }
+
+    /// Returns the total unpacked size of packages whose unpacked sizes are known.
+    pub fn unpacked(&self) -> u64 {
+        self.unpacked
+    }
+
+    /// Returns the number of packages whose sizes aren't known at all.
+    ///
+    /// These include workspace members, path and Git dependencies, and registry packages that
+    /// weren't found.
+    pub fn unknown(&self) -> usize {
+        self.unknown
+    }
+}
+
+/// Returns the paths to the `.crate` file and the unpacked source directory for a package with the
+/// given manifest path, if the manifest is within a registry's unpacked sources.
+fn registry_cache_paths(manifest_path: &Utf8Path) -> Option<(Utf8PathBuf, &Utf8Path)> {
+    // The manifest path is of the form <registry>/src/<index>/<name>-<version>/Cargo.toml.
+    let src_dir = manifest_path.parent()?;
+    let index_dir = src_dir.parent()?;
+    let registry_src_dir = index_dir.parent()?;
+    if registry_src_dir.file_name() != Some("src") {
+        return None;
+    }
+    let crate_file = registry_src_dir
+        .parent()?
+        .join("cache")
+        .join(index_dir.file_name()?)
+        .join(format!("{}.crate", src_dir.file_name()?));
+    Some((crate_file, src_dir))
+}
+
+/// Returns the total size of the files in a directory, recursively. Symlinks aren't followed.
+fn dir_size(dir: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            size += dir_size(&entry.path())?;
+        } else if file_type.is_file() {
+            size += entry.metadata()?.len();
+        }
+    }
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_paths() {
+        let (crate_file, src_dir) = registry_cache_paths(Utf8Path::new(
+            "/home/user/.cargo/registry/src/github.com-1ecc6299db9ec823/regex-1.3.1/Cargo.toml",
+        ))
+        .expect("manifest is in the registry cache");
+        assert_eq!(
+            crate_file,
+            "/home/user/.cargo/registry/cache/github.com-1ecc6299db9ec823/regex-1.3.1.crate"
+        );
+        assert_eq!(
+            src_dir,
+            "/home/user/.cargo/registry/src/github.com-1ecc6299db9ec823/regex-1.3.1"
+        );
+
+        assert_eq!(
+            registry_cache_paths(Utf8Path::new("/home/user/workspace/foo/Cargo.toml")),
+            None,
+            "path dependencies aren't in the registry cache"
+        );
+    }
+}
@@ -4,10 +4,12 @@
 //! Code for handling cycles in dependency graphs.
 
 use crate::{
-    graph::{PackageGraph, PackageIx},
+    graph::{PackageGraph, PackageIx, PackageLink, PackageMetadata},
     petgraph_support::scc::Sccs,
     Error, PackageId,
 };
+use petgraph::prelude::*;
+use std::fmt;
 
 /// Contains information about dependency cycles.
 ///
@@ -50,4 +52,111 @@ impl<'g> Cycles<'g> {
             .multi_sccs()
             .map(move |scc| scc.iter().map(move |ix| &dep_graph[*ix]).collect())
     }
+
+    /// Returns every strongly connected component in this graph, including packages that aren't
+    /// part of any cycle.
+    ///
+    /// Every type of link, including dev-only links, is considered. Components are returned in
+    /// topological order, and packages within a component are ordered the same way as in
+    /// `all_cycles`.
+    pub fn all_sccs(&self) -> impl DoubleEndedIterator<Item = Vec<&'g PackageId>> + 'g {
+        let dep_graph = &self.package_graph.dep_graph;
+        self.sccs
+            .iter()
+            .map(move |scc| scc.iter().map(move |ix| &dep_graph[*ix]).collect())
+    }
+
+    /// Returns a structured report for every cycle of 2 or more elements in this graph.
+    ///
+    /// Cycles are returned in the same order as `all_cycles`. Each `DevCycle` includes the links
+    /// that form the cycle, which can be used to enforce policies about which cycles are allowed.
+    pub fn dev_cycles(&self) -> impl DoubleEndedIterator<Item = DevCycle<'g>> + 'g {
+        let package_graph = self.package_graph;
+        let sccs = self.sccs;
+        sccs.multi_sccs().map(move |package_ixs| DevCycle {
+            package_graph,
+            sccs,
+            package_ixs,
+        })
+    }
+}
+
+/// A dependency cycle: a set of packages that all depend on each other, directly or indirectly.
+///
+/// Cargo only permits cycles if they go through at least one dev-only link, and `dev_links`
+/// returns those links.
+///
+/// Returned by [`Cycles::dev_cycles`].
+#[derive(Clone, Copy)]
+pub struct DevCycle<'g> {
+    package_graph: &'g PackageGraph,
+    sccs: &'g Sccs<PackageIx>,
+    package_ixs: &'g [NodeIndex<PackageIx>],
+}
+
+assert_covariant!(DevCycle);
+
+impl<'g> DevCycle<'g> {
+    /// Returns the IDs of the packages in this cycle, in non-dev order.
+    ///
+    /// If package Foo has a dependency on Bar, and Bar has a cyclic dev-dependency on Foo, then
+    /// Foo is returned before Bar.
+    pub fn package_ids(&self) -> impl ExactSizeIterator<Item = &'g PackageId> + 'g {
+        let dep_graph = &self.package_graph.dep_graph;
+        self.package_ixs.iter().map(move |ix| &dep_graph[*ix])
+    }
+
+    /// Returns the metadata for the packages in this cycle, in non-dev order.
+    pub fn packages(&self) -> impl ExactSizeIterator<Item = PackageMetadata<'g>> + 'g {
+        let package_graph = self.package_graph;
+        self.package_ids().map(move |package_id| {
+            package_graph
+                .metadata(package_id)
+                .expect("package in cycle should have associated metadata")
+        })
+    }
+
+    /// Returns the number of packages in this cycle.
+    pub fn len(&self) -> usize {
+        self.package_ixs.len()
+    }
+
+    /// Always returns false, since a cycle has at least 2 packages.
+    pub fn is_empty(&self) -> bool {
+        self.package_ixs.is_empty()
+    }
+
+    /// Returns true if every package in this cycle is a workspace member.
+    pub fn in_workspace(&self) -> bool {
+        self.packages().all(|package| package.in_workspace())
+    }
+
+    /// Returns all the links between packages in this cycle.
+    pub fn links(&self) -> impl Iterator<Item = PackageLink<'g>> + 'g {
+        let package_graph = self.package_graph;
+        let sccs = self.sccs;
+        self.package_ixs.iter().flat_map(move |&from_ix| {
+            package_graph
+                .dep_graph
+                .edges_directed(from_ix, Outgoing)
+                .filter(move |edge| sccs.is_same_scc(from_ix, edge.target()))
+                .map(move |edge| package_graph.edge_ref_to_link(edge))
+        })
+    }
+
+    /// Returns the dev-only links between packages in this cycle.
+    ///
+    /// Removing all of these links breaks the cycle. This is empty if the cycle doesn't go through
+    /// any dev-only links, which Cargo doesn't permit.
+    pub fn dev_links(&self) -> impl Iterator<Item = PackageLink<'g>> + 'g {
+        self.links().filter(|link| link.dev_only())
+    }
+}
+
+impl<'g> fmt::Debug for DevCycle<'g> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DevCycle")
+            .field("package_ids", &self.package_ids().collect::<Vec<_>>())
+            .finish()
+    }
 }
@@ -0,0 +1,170 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Tracking how far packages are from the initials of a query.
+
+use crate::{
+    debug_ignore::DebugIgnore,
+    graph::{
+        DependencyDirection, PackageGraph, PackageIx, PackageLink, PackageMetadata, PackageQuery,
+        PackageSet,
+    },
+    petgraph_support::IxBitSet,
+    Error, PackageId,
+};
+use petgraph::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+
+impl<'g> PackageQuery<'g> {
+    /// Resolves this query into a set of known packages, following every link found along the
+    /// way, and records each package's minimum depth from the initials.
+    ///
+    /// The initials are at depth 0, their direct dependencies (or dependents, for reverse queries)
+    /// are at depth 1, and so on. This can be used to group dependencies by how close they are to
+    /// first-party code.
+    ///
+    /// If a maximum depth was set with [`set_max_depth`](Self::set_max_depth), packages beyond it
+    /// aren't resolved.
+    pub fn resolve_depths(self) -> DepthSet<'g> {
+        self.resolve_depths_with_fn(|_, _| true)
+    }
+
+    /// Resolves this query into a set of known packages, using the provided resolver function to
+    /// determine which links are followed, and records each package's minimum depth from the
+    /// initials.
+    ///
+    /// Depths are the lengths of the shortest paths along links that the resolver function
+    /// accepts.
+    pub fn resolve_depths_with_fn(
+        self,
+        mut resolver_fn: impl FnMut(&PackageQuery<'g>, PackageLink<'g>) -> bool,
+    ) -> DepthSet<'g> {
+        let graph = self.graph;
+        let dep_graph = graph.dep_graph();
+        let direction = match self.direction() {
+            DependencyDirection::Forward => Outgoing,
+            DependencyDirection::Reverse => Incoming,
+        };
+
+        // A breadth-first search visits each package at its minimum depth.
+        let mut depths = HashMap::new();
+        let mut current: Vec<_> = self
+            .params
+            .initials()
+            .iter()
+            .copied()
+            .filter(|&package_ix| depths.insert(package_ix, 0).is_none())
+            .collect();
+        let mut depth = 0;
+        while !current.is_empty() && self.max_depth.map_or(true, |max_depth| depth < max_depth) {
+            depth += 1;
+            let mut next = vec![];
+            for package_ix in current {
+                for edge in dep_graph.edges_directed(package_ix, direction) {
+                    let neighbor = match direction {
+                        Outgoing => edge.target(),
+                        Incoming => edge.source(),
+                    };
+                    if !depths.contains_key(&neighbor)
+                        && resolver_fn(&self, graph.edge_ref_to_link(edge))
+                    {
+                        depths.insert(neighbor, depth);
+                        next.push(neighbor);
+                    }
+                }
+            }
+            current = next;
+        }
+
+        let included: IxBitSet = depths.keys().copied().collect();
+        DepthSet {
+            graph: DebugIgnore(graph),
+            all: PackageSet::from_included(graph, included),
+            depths,
+        }
+    }
+}
+
+/// A set of resolved packages, along with each package's minimum depth from the initials of the
+/// query.
+///
+/// Created by [`PackageQuery::resolve_depths`] or [`PackageQuery::resolve_depths_with_fn`].
+#[derive(Clone, Debug)]
+pub struct DepthSet<'g> {
+    graph: DebugIgnore<&'g PackageGraph>,
+    all: PackageSet<'g>,
+    depths: HashMap<NodeIndex<PackageIx>, usize>,
+}
+
+assert_covariant!(DepthSet);
+
+impl<'g> DepthSet<'g> {
+    /// Returns the set of all resolved packages, regardless of depth.
+    pub fn all(&self) -> &PackageSet<'g> {
+        &self.all
+    }
+
+    /// Returns the minimum depth of the given package from the initials, or `None` if it isn't in
+    /// this set.
+    ///
+    /// Returns an error if the package ID is unknown.
+    pub fn depth(&self, package_id: &PackageId) -> Result<Option<usize>, Error> {
+        let package_ix = self.graph.package_ix(package_id)?;
+        Ok(self.depths.get(&package_ix).copied())
+    }
+
+    /// Returns the largest depth of any package in this set, or `None` if this set is empty.
+    pub fn max_depth(&self) -> Option<usize> {
+        self.depths.values().copied().max()
+    }
+
+    /// Returns the packages at exactly the given depth.
+    pub fn at_depth(&self, depth: usize) -> PackageSet<'g> {
+        self.filter_depth(|package_depth| package_depth == depth)
+    }
+
+    /// Returns the packages at the given depth or closer.
+    pub fn within_depth(&self, depth: usize) -> PackageSet<'g> {
+        self.filter_depth(|package_depth| package_depth <= depth)
+    }
+
+    /// Groups the packages in this set by their depth.
+    ///
+    /// Each `PackageSet` in the returned map is non-empty.
+    pub fn group_by_depth(&self) -> BTreeMap<usize, PackageSet<'g>> {
+        let graph = *self.graph;
+        let mut groups: BTreeMap<usize, IxBitSet> = BTreeMap::new();
+        for (&package_ix, &depth) in &self.depths {
+            groups
+                .entry(depth)
+                .or_insert_with(|| IxBitSet::with_capacity(graph.package_count()))
+                .insert_node_ix(package_ix);
+        }
+        groups
+            .into_iter()
+            .map(|(depth, included)| (depth, PackageSet::from_included(graph, included)))
+            .collect()
+    }
+
+    /// Iterates over all packages in this set along with their depths, in topological order in
+    /// the direction specified.
+    pub fn packages<'a>(
+        &'a self,
+        direction: DependencyDirection,
+    ) -> impl Iterator<Item = (PackageMetadata<'g>, usize)> + ExactSizeIterator + 'a {
+        self.all.packages(direction).map(move |package| {
+            let depth = self.depths[&package.package_ix()];
+            (package, depth)
+        })
+    }
+
+    fn filter_depth(&self, mut callback: impl FnMut(usize) -> bool) -> PackageSet<'g> {
+        let included: IxBitSet = self
+            .depths
+            .iter()
+            .filter(|(_, &depth)| callback(depth))
+            .map(|(&package_ix, _)| package_ix)
+            .collect();
+        PackageSet::from_included(*self.graph, included)
+    }
+}
@@ -0,0 +1,413 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Rendering resolved sets as Mermaid and D2 diagrams.
+
+use crate::{
+    graph::{DependencyDirection, PackageLink, PackageMetadata, PackageSet},
+    PackageId,
+};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+};
+
+/// The text format of a diagram produced by [`PackageSet::display_diagram`].
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum DiagramFormat {
+    /// A [Mermaid](https://mermaid.js.org/) flowchart.
+    ///
+    /// Mermaid diagrams are rendered inline in Markdown by GitHub and GitLab, within a code block
+    /// marked `mermaid`.
+    Mermaid,
+
+    /// A [D2](https://d2lang.com/) diagram.
+    D2,
+}
+
+/// Options for rendering a [`PackageSet`] as a diagram.
+///
+/// Diagrams are meant to be read by people, so by default they're kept small:
+///
+/// * If a package directly depends on more than [`fan_out_limit`](Self::set_fan_out_limit)
+///   third-party packages, those dependencies are collapsed into a single node, unless they're
+///   also reachable some other way.
+/// * At most [`max_packages`](Self::set_max_packages) packages are drawn. Packages closest to the
+///   roots of the set are drawn first.
+///
+/// Workspace packages are never collapsed, but they do count towards the maximum.
+#[derive(Clone, Debug)]
+pub struct DiagramOptions {
+    format: DiagramFormat,
+    max_packages: Option<usize>,
+    fan_out_limit: Option<usize>,
+}
+
+impl DiagramOptions {
+    /// The default maximum number of packages drawn.
+    pub const DEFAULT_MAX_PACKAGES: usize = 100;
+
+    /// The default maximum number of third-party dependencies of a package drawn before they're
+    /// collapsed.
+    pub const DEFAULT_FAN_OUT_LIMIT: usize = 8;
+
+    /// Creates a new set of options for the given format, with the default limits.
+    pub fn new(format: DiagramFormat) -> Self {
+        Self {
+            format,
+            max_packages: Some(Self::DEFAULT_MAX_PACKAGES),
+            fan_out_limit: Some(Self::DEFAULT_FAN_OUT_LIMIT),
+        }
+    }
+
+    /// Returns the format of the diagram.
+    pub fn format(&self) -> DiagramFormat {
+        self.format
+    }
+
+    /// Sets the maximum number of packages drawn, or `None` to draw every package.
+    pub fn set_max_packages(&mut self, max_packages: Option<usize>) -> &mut Self {
+        self.max_packages = max_packages;
+        self
+    }
+
+    /// Sets the maximum number of third-party dependencies of a package drawn before they're
+    /// collapsed, or `None` to never collapse dependencies.
+    pub fn set_fan_out_limit(&mut self, fan_out_limit: Option<usize>) -> &mut Self {
+        self.fan_out_limit = fan_out_limit;
+        self
+    }
+}
+
+impl<'g> PackageSet<'g> {
+    /// Constructs a representation of the selected packages as a Mermaid or D2 diagram, suitable
+    /// for embedding in documentation or pull request descriptions.
+    ///
+    /// Workspace packages are drawn as rectangles and third-party packages as rounded rectangles.
+    /// Links that aren't normal dependencies (build or dev-dependencies) are drawn dashed.
+    ///
+    /// For how large sets are kept readable, see [`DiagramOptions`].
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use guppy::{
+    ///     graph::{DiagramFormat, DiagramOptions},
+    ///     MetadataCommand,
+    /// };
+    ///
+    /// let graph = MetadataCommand::new().build_graph().unwrap();
+    /// let package_set = graph.query_workspace().resolve();
+    /// let diagram = package_set
+    ///     .display_diagram(&DiagramOptions::new(DiagramFormat::Mermaid))
+    ///     .to_string();
+    /// assert!(diagram.starts_with("flowchart TD\n"));
+    /// ```
+    pub fn display_diagram<'a>(&'a self, options: &DiagramOptions) -> impl fmt::Display + 'a {
+        DiagramFmt {
+            package_set: self,
+            options: options.clone(),
+        }
+    }
+}
+
+struct DiagramFmt<'a, 'g> {
+    package_set: &'a PackageSet<'g>,
+    options: DiagramOptions,
+}
+
+impl<'a, 'g> fmt::Display for DiagramFmt<'a, 'g> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let layout = DiagramLayout::new(self.package_set, &self.options);
+        let writer: &dyn DiagramWriter = match self.options.format {
+            DiagramFormat::Mermaid => &MermaidWriter,
+            DiagramFormat::D2 => &D2Writer,
+        };
+
+        writer.header(f)?;
+        for (node_ix, package) in layout.packages.iter().enumerate() {
+            writer.package(f, node_ix, *package)?;
+        }
+        for &(node_ix, count) in &layout.collapsed {
+            writer.collapsed(f, node_ix, count)?;
+        }
+        for &(from_ix, to_ix, link) in &layout.links {
+            writer.link(f, from_ix, to_ix, link)?;
+        }
+        if layout.omitted > 0 {
+            writer.omitted(f, layout.omitted)?;
+        }
+        Ok(())
+    }
+}
+
+/// The packages and links to draw.
+struct DiagramLayout<'g> {
+    // Packages to draw, in the order they're drawn. Indexes into this are node indexes.
+    packages: Vec<PackageMetadata<'g>>,
+    // For each package with collapsed dependencies, its node index and the number of direct
+    // dependencies collapsed.
+    collapsed: Vec<(usize, usize)>,
+    links: Vec<(usize, usize, PackageLink<'g>)>,
+    // The number of packages not drawn because of the size limit.
+    omitted: usize,
+}
+
+impl<'g> DiagramLayout<'g> {
+    fn new(package_set: &PackageSet<'g>, options: &DiagramOptions) -> Self {
+        let mut links_from: HashMap<&'g PackageId, Vec<PackageLink<'g>>> = HashMap::new();
+        for link in package_set.links(DependencyDirection::Forward) {
+            links_from.entry(link.from().id()).or_default().push(link);
+        }
+
+        // Links to third-party packages from packages with too many of them are cut.
+        let fan_out_exceeded: HashSet<_> = match options.fan_out_limit {
+            Some(limit) => links_from
+                .iter()
+                .filter(|(_, links)| {
+                    links
+                        .iter()
+                        .filter(|link| !link.to().in_workspace())
+                        .count()
+                        > limit
+                })
+                .map(|(&package_id, _)| package_id)
+                .collect(),
+            None => HashSet::new(),
+        };
+        let is_cut = |link: &PackageLink<'g>| {
+            !link.to().in_workspace() && fan_out_exceeded.contains(link.from().id())
+        };
+
+        // Draw the packages reachable from the roots through links that aren't cut, closest to the
+        // roots first.
+        let mut visible = HashSet::new();
+        let mut order = vec![];
+        let mut queue: VecDeque<_> = package_set
+            .root_packages(DependencyDirection::Forward)
+            .collect();
+        for package in &queue {
+            visible.insert(package.id());
+        }
+        while let Some(package) = queue.pop_front() {
+            order.push(package);
+            for link in links_from.get(package.id()).into_iter().flatten() {
+                if !is_cut(link) && visible.insert(link.to().id()) {
+                    queue.push_back(link.to());
+                }
+            }
+        }
+
+        let max_packages = options.max_packages.unwrap_or(usize::MAX);
+        let omitted = order.len().saturating_sub(max_packages);
+        order.truncate(max_packages);
+        let node_ixs: HashMap<_, _> = order
+            .iter()
+            .enumerate()
+            .map(|(node_ix, package)| (package.id(), node_ix))
+            .collect();
+
+        let mut collapsed = vec![];
+        let mut links = vec![];
+        for (from_ix, package) in order.iter().enumerate() {
+            let mut collapsed_count = 0;
+            for link in links_from.get(package.id()).into_iter().flatten() {
+                match node_ixs.get(link.to().id()) {
+                    Some(&to_ix) => links.push((from_ix, to_ix, *link)),
+                    None if !visible.contains(link.to().id()) => collapsed_count += 1,
+                    // The package is visible but wasn't drawn because of the size limit.
+                    None => {}
+                }
+            }
+            if collapsed_count > 0 {
+                collapsed.push((from_ix, collapsed_count));
+            }
+        }
+
+        Self {
+            packages: order,
+            collapsed,
+            links,
+            omitted,
+        }
+    }
+}
+
+trait DiagramWriter {
+    fn header(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+
+    fn package(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        node_ix: usize,
+        package: PackageMetadata<'_>,
+    ) -> fmt::Result;
+
+    fn collapsed(&self, f: &mut fmt::Formatter<'_>, node_ix: usize, count: usize) -> fmt::Result;
+
+    fn link(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        from_ix: usize,
+        to_ix: usize,
+        link: PackageLink<'_>,
+    ) -> fmt::Result;
+
+    fn omitted(&self, f: &mut fmt::Formatter<'_>, count: usize) -> fmt::Result;
+}
+
+struct MermaidWriter;
+
+impl DiagramWriter for MermaidWriter {
+    fn header(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "flowchart TD")
+    }
+
+    fn package(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        node_ix: usize,
+        package: PackageMetadata<'_>,
+    ) -> fmt::Result {
+        let label = MermaidStr(package_label(package));
+        if package.in_workspace() {
+            writeln!(f, "    p{}[\"{}\"]", node_ix, label)
+        } else {
+            writeln!(f, "    p{}(\"{}\")", node_ix, label)
+        }
+    }
+
+    fn collapsed(&self, f: &mut fmt::Formatter<'_>, node_ix: usize, count: usize) -> fmt::Result {
+        writeln!(
+            f,
+            "    p{}_more{{{{\"{}\"}}}}",
+            node_ix,
+            collapsed_label(count)
+        )?;
+        writeln!(f, "    p{} -.-> p{}_more", node_ix, node_ix)
+    }
+
+    fn link(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        from_ix: usize,
+        to_ix: usize,
+        link: PackageLink<'_>,
+    ) -> fmt::Result {
+        let arrow = if link.normal().is_present() {
+            "-->"
+        } else {
+            "-.->"
+        };
+        writeln!(f, "    p{} {} p{}", from_ix, arrow, to_ix)
+    }
+
+    fn omitted(&self, f: &mut fmt::Formatter<'_>, count: usize) -> fmt::Result {
+        writeln!(f, "    %% {}", omitted_comment(count))
+    }
+}
+
+struct D2Writer;
+
+impl DiagramWriter for D2Writer {
+    fn header(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "direction: down")
+    }
+
+    fn package(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        node_ix: usize,
+        package: PackageMetadata<'_>,
+    ) -> fmt::Result {
+        let label = D2Str(package_label(package));
+        if package.in_workspace() {
+            writeln!(f, "p{}: \"{}\"", node_ix, label)
+        } else {
+            writeln!(f, "p{}: \"{}\" {{style.border-radius: 8}}", node_ix, label)
+        }
+    }
+
+    fn collapsed(&self, f: &mut fmt::Formatter<'_>, node_ix: usize, count: usize) -> fmt::Result {
+        writeln!(
+            f,
+            "p{}_more: \"{}\" {{shape: hexagon}}",
+            node_ix,
+            collapsed_label(count)
+        )?;
+        writeln!(
+            f,
+            "p{} -> p{}_more: {{style.stroke-dash: 3}}",
+            node_ix, node_ix
+        )
+    }
+
+    fn link(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        from_ix: usize,
+        to_ix: usize,
+        link: PackageLink<'_>,
+    ) -> fmt::Result {
+        if link.normal().is_present() {
+            writeln!(f, "p{} -> p{}", from_ix, to_ix)
+        } else {
+            writeln!(f, "p{} -> p{}: {{style.stroke-dash: 3}}", from_ix, to_ix)
+        }
+    }
+
+    fn omitted(&self, f: &mut fmt::Formatter<'_>, count: usize) -> fmt::Result {
+        writeln!(f, "# {}", omitted_comment(count))
+    }
+}
+
+fn package_label(package: PackageMetadata<'_>) -> String {
+    format!("{} {}", package.name(), package.version())
+}
+
+fn collapsed_label(count: usize) -> String {
+    if count == 1 {
+        "+1 more dependency".to_owned()
+    } else {
+        format!("+{} more dependencies", count)
+    }
+}
+
+fn omitted_comment(count: usize) -> String {
+    if count == 1 {
+        "1 more package not shown".to_owned()
+    } else {
+        format!("{} more packages not shown", count)
+    }
+}
+
+/// Escapes a string for use within a quoted Mermaid label.
+struct MermaidStr(String);
+
+impl fmt::Display for MermaidStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("#quot;")?,
+                c => fmt::Write::write_char(f, c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Escapes a string for use within a double-quoted D2 string.
+struct D2Str(String);
+
+impl fmt::Display for D2Str {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                c => fmt::Write::write_char(f, c)?,
+            }
+        }
+        Ok(())
+    }
+}
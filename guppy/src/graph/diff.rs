@@ -0,0 +1,265 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Code for computing structural diffs between package graphs.
+
+use crate::{
+    graph::{PackageGraph, PackageLink, PackageMetadata},
+    PackageId,
+};
+use std::collections::BTreeMap;
+
+impl PackageGraph {
+    /// Computes a structural diff between this graph and `new`.
+    ///
+    /// `self` is treated as the old graph, and `new` as the new one. This is typically used to
+    /// compare two metadata snapshots, for example before and after a change.
+    ///
+    /// For more information, see the documentation for `PackageGraphDiff`.
+    pub fn diff<'g>(&'g self, new: &'g PackageGraph) -> PackageGraphDiff<'g> {
+        PackageGraphDiff::new(self, new)
+    }
+}
+
+/// A structural diff between two package graphs.
+///
+/// Packages are matched across the two graphs by name and source:
+/// * Packages with the same ID in both graphs are unchanged.
+/// * If a package with a given name and source has exactly one version that's only in the old
+///   graph and one that's only in the new graph, it is reported as a version change.
+/// * Otherwise, packages that are only in the old graph are removed, and packages that are only in
+///   the new graph are added.
+///
+/// Dependency links are compared for each package that is present in both graphs, including
+/// packages whose versions changed. Links are matched by the name and source of the dependency,
+/// and by the name the dependency is imported as, so a link is unchanged if only the version of
+/// the dependency changed. Links from added or removed packages aren't reported.
+///
+/// Constructed through `PackageGraph::diff`.
+#[derive(Clone, Debug)]
+pub struct PackageGraphDiff<'g> {
+    added: Vec<PackageMetadata<'g>>,
+    removed: Vec<PackageMetadata<'g>>,
+    version_changes: Vec<VersionChange<'g>>,
+    added_links: Vec<PackageLink<'g>>,
+    removed_links: Vec<PackageLink<'g>>,
+    changed_links: Vec<LinkChange<'g>>,
+}
+
+impl<'g> PackageGraphDiff<'g> {
+    fn new(old: &'g PackageGraph, new: &'g PackageGraph) -> Self {
+        let mut added = vec![];
+        let mut removed = vec![];
+        let mut version_changes = vec![];
+        // Pairs of packages that are present in both graphs.
+        let mut matched = vec![];
+
+        let old_by_key = packages_by_key(old);
+        let mut new_by_key = packages_by_key(new);
+
+        for (key, old_packages) in old_by_key {
+            let new_packages = new_by_key.remove(&key).unwrap_or_default();
+            let mut old_only = vec![];
+            for old_package in &old_packages {
+                match new_packages
+                    .iter()
+                    .find(|new_package| new_package.id() == old_package.id())
+                {
+                    Some(new_package) => matched.push((*old_package, *new_package)),
+                    None => old_only.push(*old_package),
+                }
+            }
+            let new_only: Vec<_> = new_packages
+                .iter()
+                .filter(|new_package| {
+                    !old_packages
+                        .iter()
+                        .any(|old_package| old_package.id() == new_package.id())
+                })
+                .copied()
+                .collect();
+
+            match (old_only.as_slice(), new_only.as_slice()) {
+                ([old_package], [new_package]) => {
+                    // A lone removal and addition is a version change.
+                    matched.push((*old_package, *new_package));
+                    version_changes.push(VersionChange {
+                        old: *old_package,
+                        new: *new_package,
+                    });
+                }
+                _ => {
+                    removed.extend(old_only);
+                    added.extend(new_only);
+                }
+            }
+        }
+        // Any remaining packages are new.
+        added.extend(new_by_key.into_values().flatten());
+
+        let mut added_links = vec![];
+        let mut removed_links = vec![];
+        let mut changed_links = vec![];
+        for (old_package, new_package) in matched {
+            let mut new_links: BTreeMap<_, _> = new_package
+                .direct_links()
+                .map(|link| (link_key(&link), link))
+                .collect();
+            for old_link in old_package.direct_links() {
+                match new_links.remove(&link_key(&old_link)) {
+                    Some(new_link) => {
+                        if link_details(&old_link) != link_details(&new_link) {
+                            changed_links.push(LinkChange {
+                                old: old_link,
+                                new: new_link,
+                            });
+                        }
+                    }
+                    None => removed_links.push(old_link),
+                }
+            }
+            added_links.extend(new_links.into_values());
+        }
+
+        added.sort_by(|a, b| a.id().cmp(b.id()));
+        removed.sort_by(|a, b| a.id().cmp(b.id()));
+        version_changes.sort_by(|a, b| a.old.id().cmp(b.old.id()));
+        added_links.sort_by(|a, b| link_ids(a).cmp(&link_ids(b)));
+        removed_links.sort_by(|a, b| link_ids(a).cmp(&link_ids(b)));
+        changed_links.sort_by(|a, b| link_ids(&a.new).cmp(&link_ids(&b.new)));
+
+        Self {
+            added,
+            removed,
+            version_changes,
+            added_links,
+            removed_links,
+            changed_links,
+        }
+    }
+
+    /// Returns true if there are no changes in this diff.
+    pub fn is_unchanged(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.version_changes.is_empty()
+            && self.added_links.is_empty()
+            && self.removed_links.is_empty()
+            && self.changed_links.is_empty()
+    }
+
+    /// Returns the packages that were added, from the new graph, sorted by package ID.
+    pub fn added(&self) -> impl ExactSizeIterator<Item = PackageMetadata<'g>> + '_ {
+        self.added.iter().copied()
+    }
+
+    /// Returns the packages that were removed, from the old graph, sorted by package ID.
+    pub fn removed(&self) -> impl ExactSizeIterator<Item = PackageMetadata<'g>> + '_ {
+        self.removed.iter().copied()
+    }
+
+    /// Returns the packages whose versions changed, sorted by old package ID.
+    pub fn version_changes(&self) -> impl ExactSizeIterator<Item = &VersionChange<'g>> {
+        self.version_changes.iter()
+    }
+
+    /// Returns the dependency links that were added, from the new graph.
+    ///
+    /// Links are sorted by the package ID they're from, then by the package ID they're to.
+    pub fn added_links(&self) -> impl ExactSizeIterator<Item = PackageLink<'g>> + '_ {
+        self.added_links.iter().copied()
+    }
+
+    /// Returns the dependency links that were removed, from the old graph.
+    ///
+    /// Links are sorted by the package ID they're from, then by the package ID they're to.
+    pub fn removed_links(&self) -> impl ExactSizeIterator<Item = PackageLink<'g>> + '_ {
+        self.removed_links.iter().copied()
+    }
+
+    /// Returns the dependency links that are present in both graphs, but whose version
+    /// requirements or dependency kinds changed.
+    ///
+    /// Links are sorted by the package IDs of their new versions.
+    pub fn changed_links(&self) -> impl ExactSizeIterator<Item = &LinkChange<'g>> {
+        self.changed_links.iter()
+    }
+}
+
+/// A package whose version changed between two package graphs.
+///
+/// Returned by `PackageGraphDiff::version_changes`.
+#[derive(Copy, Clone, Debug)]
+pub struct VersionChange<'g> {
+    old: PackageMetadata<'g>,
+    new: PackageMetadata<'g>,
+}
+
+impl<'g> VersionChange<'g> {
+    /// Returns the package in the old graph.
+    pub fn old_package(&self) -> PackageMetadata<'g> {
+        self.old
+    }
+
+    /// Returns the package in the new graph.
+    pub fn new_package(&self) -> PackageMetadata<'g> {
+        self.new
+    }
+}
+
+/// A dependency link that changed between two package graphs.
+///
+/// Returned by `PackageGraphDiff::changed_links`.
+#[derive(Copy, Clone, Debug)]
+pub struct LinkChange<'g> {
+    old: PackageLink<'g>,
+    new: PackageLink<'g>,
+}
+
+impl<'g> LinkChange<'g> {
+    /// Returns the link in the old graph.
+    pub fn old_link(&self) -> PackageLink<'g> {
+        self.old
+    }
+
+    /// Returns the link in the new graph.
+    pub fn new_link(&self) -> PackageLink<'g> {
+        self.new
+    }
+}
+
+// ---
+// Helper functions
+// ---
+
+/// Packages are matched across graphs by their name and source.
+fn packages_by_key(graph: &PackageGraph) -> BTreeMap<(&str, String), Vec<PackageMetadata<'_>>> {
+    let mut by_key: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for package in graph.packages() {
+        by_key
+            .entry((package.name(), package.source().to_string()))
+            .or_default()
+            .push(package);
+    }
+    by_key
+}
+
+/// Links are matched by the name and source of the package they're to, and by the name the
+/// dependency is imported as.
+fn link_key<'g>(link: &PackageLink<'g>) -> (&'g str, String, &'g str) {
+    let to = link.to();
+    (to.name(), to.source().to_string(), link.dep_name())
+}
+
+fn link_details(link: &PackageLink<'_>) -> (String, bool, bool, bool) {
+    (
+        link.version_req().to_string(),
+        link.normal().is_present(),
+        link.build().is_present(),
+        link.dev().is_present(),
+    )
+}
+
+fn link_ids<'g>(link: &PackageLink<'g>) -> (&'g PackageId, &'g PackageId) {
+    (link.from().id(), link.to().id())
+}
@@ -0,0 +1,227 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{
+    graph::{PackageGraph, PackageIx, PackageLink, PackageMetadata},
+    Error, PackageId,
+};
+use fixedbitset::FixedBitSet;
+use petgraph::{
+    algo::dominators::{self, Dominators},
+    prelude::*,
+};
+
+impl PackageGraph {
+    /// Computes a dominator tree over this graph, rooted at the workspace.
+    ///
+    /// The workspace is treated as a single root, and each third-party package (one that isn't a
+    /// workspace member) is attributed to the direct dependency of the workspace that every path
+    /// to it goes through, if there is one. This is useful for figuring out which direct
+    /// dependencies are responsible for the size of a dependency tree.
+    ///
+    /// Every kind of dependency (normal, build and dev) is followed.
+    ///
+    /// For more information, see the documentation for `WorkspaceDominators`.
+    pub fn workspace_dominators(&self) -> WorkspaceDominators<'_> {
+        WorkspaceDominators::new(self)
+    }
+}
+
+/// A dominator tree over a package graph, rooted at the workspace.
+///
+/// A package *A* dominates a package *B* if every dependency path from the workspace to *B* goes
+/// through *A*. A direct dependency of the workspace is *responsible* for a third-party package if
+/// the direct dependency dominates it. Third-party packages that can be reached through several
+/// direct dependencies are *shared*, and aren't attributed to any of them.
+///
+/// Created by [`PackageGraph::workspace_dominators`].
+#[derive(Clone, Debug)]
+pub struct WorkspaceDominators<'g> {
+    graph: &'g PackageGraph,
+    dominators: Dominators<NodeIndex<PackageIx>>,
+    // A virtual node standing in for the whole workspace.
+    root_ix: NodeIndex<PackageIx>,
+    // Packages that are the target of a link from a workspace member.
+    direct: FixedBitSet,
+    // The direct dependency responsible for each package, by package index.
+    responsible: Vec<Option<NodeIndex<PackageIx>>>,
+    // The number of packages each direct dependency is responsible for, in descending order.
+    counts: Vec<(NodeIndex<PackageIx>, usize)>,
+}
+
+assert_covariant!(WorkspaceDominators);
+
+impl<'g> WorkspaceDominators<'g> {
+    fn new(graph: &'g PackageGraph) -> Self {
+        let dep_graph = &graph.dep_graph;
+        let in_workspace = |package_ix: NodeIndex<PackageIx>| {
+            graph
+                .metadata(&dep_graph[package_ix])
+                .expect("valid package ID")
+                .in_workspace()
+        };
+
+        // Build an auxiliary graph where the workspace members are collapsed into a single root.
+        // Node indexes for packages are the same as in the dependency graph.
+        let mut aux_graph = Graph::<(), (), Directed, PackageIx>::with_capacity(
+            dep_graph.node_count() + 1,
+            dep_graph.edge_count(),
+        );
+        for _ in dep_graph.node_indices() {
+            aux_graph.add_node(());
+        }
+        let root_ix = aux_graph.add_node(());
+        let mut direct = FixedBitSet::with_capacity(dep_graph.node_count());
+        for edge in dep_graph.edge_references() {
+            let (source_ix, target_ix) = (edge.source(), edge.target());
+            if in_workspace(target_ix) {
+                continue;
+            }
+            if in_workspace(source_ix) {
+                direct.insert(target_ix.index());
+                aux_graph.update_edge(root_ix, target_ix, ());
+            } else {
+                aux_graph.update_edge(source_ix, target_ix, ());
+            }
+        }
+        let dominators = dominators::simple_fast(&aux_graph, root_ix);
+
+        let responsible: Vec<_> = dep_graph
+            .node_indices()
+            .map(|package_ix| {
+                // Walk up the dominator tree to the package just below the root.
+                let mut current_ix = package_ix;
+                loop {
+                    match dominators.immediate_dominator(current_ix) {
+                        Some(idom_ix) if idom_ix == root_ix => break,
+                        Some(idom_ix) => current_ix = idom_ix,
+                        // Workspace members and unreachable packages.
+                        None => return None,
+                    }
+                }
+                if direct.contains(current_ix.index()) {
+                    Some(current_ix)
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let mut counts = vec![0; dep_graph.node_count()];
+        for package_ix in responsible.iter().flatten() {
+            counts[package_ix.index()] += 1;
+        }
+        let mut counts: Vec<_> = direct
+            .ones()
+            .map(|index| (NodeIndex::new(index), counts[index]))
+            .collect();
+        counts.sort_by(|(a_ix, a_count), (b_ix, b_count)| {
+            b_count
+                .cmp(a_count)
+                .then_with(|| dep_graph[*a_ix].cmp(&dep_graph[*b_ix]))
+        });
+
+        Self {
+            graph,
+            dominators,
+            root_ix,
+            direct,
+            responsible,
+            counts,
+        }
+    }
+
+    /// Returns the immediate dominator of this package: the closest package that every path from
+    /// the workspace to it goes through.
+    ///
+    /// Returns `None` if the package is a workspace member, isn't reachable from the workspace, or
+    /// is only dominated by the workspace as a whole.
+    ///
+    /// Returns an error if the package ID is unknown.
+    pub fn immediate_dominator(
+        &self,
+        package_id: &PackageId,
+    ) -> Result<Option<PackageMetadata<'g>>, Error> {
+        let package_ix = self.graph.package_ix(package_id)?;
+        Ok(self
+            .dominators
+            .immediate_dominator(package_ix)
+            .filter(|&idom_ix| idom_ix != self.root_ix)
+            .map(|idom_ix| self.metadata(idom_ix)))
+    }
+
+    /// Returns the direct dependency of the workspace that is responsible for this package.
+    ///
+    /// A direct dependency is always responsible for itself. Returns `None` if the package is a
+    /// workspace member, isn't reachable from the workspace, or is shared between several direct
+    /// dependencies.
+    ///
+    /// Returns an error if the package ID is unknown.
+    pub fn responsible_dependency(
+        &self,
+        package_id: &PackageId,
+    ) -> Result<Option<PackageMetadata<'g>>, Error> {
+        let package_ix = self.graph.package_ix(package_id)?;
+        Ok(self.responsible[package_ix.index()].map(|ix| self.metadata(ix)))
+    }
+
+    /// Returns the links from workspace members to the direct dependency responsible for this
+    /// package.
+    ///
+    /// Returns an empty list if no direct dependency is responsible for this package.
+    ///
+    /// Returns an error if the package ID is unknown.
+    pub fn responsible_links(&self, package_id: &PackageId) -> Result<Vec<PackageLink<'g>>, Error> {
+        Ok(match self.responsible_dependency(package_id)? {
+            Some(dependency) => dependency
+                .reverse_direct_links()
+                .filter(|link| link.from().in_workspace())
+                .collect(),
+            None => vec![],
+        })
+    }
+
+    /// Returns every direct dependency of the workspace, along with the number of packages it is
+    /// responsible for (including itself).
+    ///
+    /// Dependencies are returned in descending order of the number of packages they're responsible
+    /// for, with ties broken by package ID.
+    pub fn responsible_counts(
+        &self,
+    ) -> impl ExactSizeIterator<Item = (PackageMetadata<'g>, usize)> + '_ {
+        self.counts
+            .iter()
+            .map(move |&(package_ix, count)| (self.metadata(package_ix), count))
+    }
+
+    /// Returns the third-party packages reachable from the workspace that no single direct
+    /// dependency is responsible for.
+    pub fn shared_packages(&self) -> impl Iterator<Item = PackageMetadata<'g>> + '_ {
+        self.graph
+            .dep_graph
+            .node_indices()
+            .filter(move |&package_ix| {
+                self.responsible[package_ix.index()].is_none()
+                    && self.dominators.immediate_dominator(package_ix).is_some()
+            })
+            .map(move |package_ix| self.metadata(package_ix))
+    }
+
+    /// Returns true if this package is a direct dependency of a workspace member.
+    ///
+    /// Returns an error if the package ID is unknown.
+    pub fn is_direct_dependency(&self, package_id: &PackageId) -> Result<bool, Error> {
+        let package_ix = self.graph.package_ix(package_id)?;
+        Ok(self.direct.contains(package_ix.index()))
+    }
+
+    // ---
+    // Helper methods
+    // ---
+
+    fn metadata(&self, package_ix: NodeIndex<PackageIx>) -> PackageMetadata<'g> {
+        self.graph
+            .metadata(&self.graph.dep_graph[package_ix])
+            .expect("package in dominator tree should have associated metadata")
+    }
+}
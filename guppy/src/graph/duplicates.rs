@@ -0,0 +1,290 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Code for finding packages that are present in more than one version.
+
+use crate::{
+    graph::{
+        cargo_version_matches, DependencyDirection, PackageGraph, PackageLink, PackageMetadata,
+        PackageSet,
+    },
+    petgraph_support::IxBitSet,
+};
+use semver::Version;
+use std::collections::BTreeMap;
+
+/// Contains information about packages that are present in more than one version.
+///
+/// Cargo permits a dependency graph to contain several versions of the same package, for example
+/// if two dependencies require incompatible versions of it. Such duplicates increase build times
+/// and binary sizes, and are often the target of deduplication audits.
+///
+/// Packages are grouped by name, so packages with the same name from different sources (for
+/// example, a crates.io package and a git fork of it) are duplicates as well.
+///
+/// Constructed through `PackageGraph::duplicates`.
+#[derive(Clone, Debug)]
+pub struct Duplicates<'g> {
+    // Sorted by name.
+    groups: Vec<DuplicatePackage<'g>>,
+}
+
+impl<'g> Duplicates<'g> {
+    pub(super) fn new(package_graph: &'g PackageGraph) -> Self {
+        let mut by_name: BTreeMap<&'g str, Vec<PackageMetadata<'g>>> = BTreeMap::new();
+        for package in package_graph.packages() {
+            by_name.entry(package.name()).or_default().push(package);
+        }
+
+        let groups = by_name
+            .into_iter()
+            .filter(|(_, packages)| packages.len() > 1)
+            .map(|(name, mut packages)| {
+                packages.sort_by(|a, b| {
+                    a.version()
+                        .cmp(b.version())
+                        .then_with(|| a.id().cmp(b.id()))
+                });
+                DuplicatePackage { name, packages }
+            })
+            .collect();
+        Self { groups }
+    }
+
+    /// Returns the number of package names with more than one version.
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Returns true if no packages are present in more than one version.
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Returns the duplicated packages, sorted by name.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &DuplicatePackage<'g>> {
+        self.groups.iter()
+    }
+
+    /// Returns the versions of the package with the given name, or `None` if it isn't present in
+    /// more than one version.
+    pub fn get(&self, name: impl AsRef<str>) -> Option<&DuplicatePackage<'g>> {
+        let name = name.as_ref();
+        self.groups
+            .binary_search_by(|group| group.name.cmp(name))
+            .ok()
+            .map(|idx| &self.groups[idx])
+    }
+}
+
+/// A package that is present in more than one version.
+///
+/// Returned by `Duplicates::iter` and `Duplicates::get`.
+#[derive(Clone, Debug)]
+pub struct DuplicatePackage<'g> {
+    name: &'g str,
+    // Sorted by version, then by package ID.
+    packages: Vec<PackageMetadata<'g>>,
+}
+
+impl<'g> DuplicatePackage<'g> {
+    /// Returns the name of this package.
+    pub fn name(&self) -> &'g str {
+        self.name
+    }
+
+    /// Returns the packages with this name, sorted by version.
+    ///
+    /// Packages with the same version but different sources are sorted by package ID.
+    pub fn packages(&self) -> impl ExactSizeIterator<Item = PackageMetadata<'g>> + '_ {
+        self.packages.iter().copied()
+    }
+
+    /// Returns the versions of this package, in sorted order.
+    ///
+    /// A version is repeated if it is present from more than one source.
+    pub fn versions(&self) -> impl ExactSizeIterator<Item = &'g Version> + '_ {
+        self.packages.iter().map(|package| package.version())
+    }
+
+    /// Returns the packages with this name, sorted by version, along with the set of packages that
+    /// directly depend on each of them.
+    ///
+    /// The dependents are the packages that pull each version into the graph. To find out which
+    /// workspace packages transitively depend on a version, use
+    /// `package.to_package_query(DependencyDirection::Reverse)`.
+    pub fn dependents(
+        &self,
+    ) -> impl ExactSizeIterator<Item = (PackageMetadata<'g>, PackageSet<'g>)> + '_ {
+        self.packages.iter().map(|package| {
+            let included: IxBitSet = package
+                .direct_links_directed(DependencyDirection::Reverse)
+                .map(|link| link.from().package_ix())
+                .collect();
+            (
+                *package,
+                PackageSet::from_included(package.graph(), included),
+            )
+        })
+    }
+
+    /// Returns every pair of packages with this name, classified by whether they could be unified.
+    ///
+    /// Pairs are returned in order: each package is paired with every package that sorts after
+    /// it, so the older version of each pair is always first.
+    pub fn pairs(&self) -> impl Iterator<Item = DuplicatePair<'g>> + '_ {
+        self.packages
+            .iter()
+            .enumerate()
+            .flat_map(move |(idx, older)| {
+                self.packages[idx + 1..]
+                    .iter()
+                    .map(move |newer| DuplicatePair::new(*older, *newer))
+            })
+    }
+}
+
+/// A pair of packages with the same name, present in the graph at the same time.
+///
+/// Returned by `DuplicatePackage::pairs`.
+#[derive(Clone, Debug)]
+pub struct DuplicatePair<'g> {
+    older: PackageMetadata<'g>,
+    newer: PackageMetadata<'g>,
+    compatibility: SemverCompatibility,
+    blocking_links: Vec<PackageLink<'g>>,
+}
+
+impl<'g> DuplicatePair<'g> {
+    fn new(older: PackageMetadata<'g>, newer: PackageMetadata<'g>) -> Self {
+        let compatibility = if older.source() != newer.source() {
+            SemverCompatibility::DifferentSources
+        } else if semver_compatible(older.version(), newer.version()) {
+            SemverCompatibility::Compatible
+        } else {
+            SemverCompatibility::Incompatible
+        };
+
+        // Cargo unifies compatible versions by picking the newest one, so the older package is
+        // kept around by any dependents whose requirements don't accept the newer version.
+        let blocking_links = older
+            .direct_links_directed(DependencyDirection::Reverse)
+            .filter(|link| {
+                compatibility == SemverCompatibility::DifferentSources
+                    || !cargo_version_matches(link.version_req(), newer.version())
+            })
+            .collect();
+
+        Self {
+            older,
+            newer,
+            compatibility,
+            blocking_links,
+        }
+    }
+
+    /// Returns the package with the older version.
+    ///
+    /// If both packages have the same version, this is the one whose package ID sorts first.
+    pub fn older(&self) -> PackageMetadata<'g> {
+        self.older
+    }
+
+    /// Returns the package with the newer version.
+    pub fn newer(&self) -> PackageMetadata<'g> {
+        self.newer
+    }
+
+    /// Returns whether the two versions are semver-compatible.
+    pub fn compatibility(&self) -> SemverCompatibility {
+        self.compatibility
+    }
+
+    /// Returns the links to the older package that prevent it from being unified with the newer
+    /// one.
+    ///
+    /// For semver-compatible pairs, these are the links whose version requirements don't accept
+    /// the newer version, for example because they pin an exact version. For other pairs, every
+    /// link to the older package must be changed to unify the two, so all of them are returned.
+    pub fn blocking_links(&self) -> impl ExactSizeIterator<Item = PackageLink<'g>> + '_ {
+        self.blocking_links.iter().copied()
+    }
+
+    /// Returns true if the two packages could be unified by running `cargo update`, without any
+    /// changes to the manifests of dependents.
+    ///
+    /// This is the case if the versions are semver-compatible and no links block unification.
+    pub fn is_unifiable_by_update(&self) -> bool {
+        self.compatibility == SemverCompatibility::Compatible && self.blocking_links.is_empty()
+    }
+}
+
+/// Whether a [`DuplicatePair`] is semver-compatible.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum SemverCompatibility {
+    /// The versions are semver-compatible, so Cargo could resolve both dependents to the newer
+    /// version, unless a version requirement prevents it.
+    Compatible,
+
+    /// The versions are semver-incompatible, so unifying them requires changes to the
+    /// dependents of the older version.
+    Incompatible,
+
+    /// The packages come from different sources, for example crates.io and a git fork. Cargo never
+    /// unifies packages across sources.
+    DifferentSources,
+}
+
+/// Returns true if a caret requirement on `older` would accept `newer`.
+///
+/// Following Cargo, versions are compatible if they have the same leftmost non-zero component.
+fn semver_compatible(older: &Version, newer: &Version) -> bool {
+    if !older.pre.is_empty() || !newer.pre.is_empty() {
+        // Prereleases are only compatible with themselves.
+        return older == newer;
+    }
+    match (older.major, older.minor) {
+        (0, 0) => newer.major == 0 && newer.minor == 0 && newer.patch == older.patch,
+        (0, minor) => newer.major == 0 && newer.minor == minor,
+        (major, _) => newer.major == major,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_semver_compatible() {
+        let compatible = [
+            ("1.0.0", "1.5.2"),
+            ("1.2.3", "1.2.3"),
+            ("0.3.0", "0.3.9"),
+            ("0.0.4", "0.0.4"),
+        ];
+        for (older, newer) in compatible {
+            assert!(
+                semver_compatible(&older.parse().unwrap(), &newer.parse().unwrap()),
+                "{} and {} are compatible",
+                older,
+                newer
+            );
+        }
+
+        let incompatible = [
+            ("1.4.0", "2.0.0"),
+            ("0.2.11", "1.4.0"),
+            ("0.3.0", "0.5.4"),
+            ("0.0.4", "0.0.5"),
+            ("1.0.0-alpha.1", "1.0.0"),
+        ];
+        for (older, newer) in incompatible {
+            assert!(
+                !semver_compatible(&older.parse().unwrap(), &newer.parse().unwrap()),
+                "{} and {} are incompatible",
+                older,
+                newer
+            );
+        }
+    }
+}
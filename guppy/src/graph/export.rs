@@ -0,0 +1,293 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! A stable, versioned JSON format for the contents of resolved sets.
+
+use crate::graph::{
+    cargo::CargoSet,
+    feature::{FeatureLabel, FeatureSet},
+    DependencyDirection, IterationOrder, PackageLink, PackageMetadata, PackageSet, PackageSource,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// The version of the export format produced by this version of guppy.
+///
+/// The version is incremented whenever a change is made that existing consumers could trip over,
+/// such as a field being removed or changing meaning. New optional fields may be added without
+/// changing the version, so consumers should ignore fields they don't recognize.
+pub const EXPORT_FORMAT_VERSION: u32 = 1;
+
+/// The contents of a resolved set, in a stable format meant for consumption by tools not written
+/// in Rust.
+///
+/// Created by [`PackageSet::export`], [`FeatureSet::export`] or [`CargoSet::export`]. This struct
+/// implements `Serialize` and `Deserialize`, and [`to_json`](Self::to_json) serializes it to JSON.
+///
+/// ## Schema
+///
+/// ```json
+/// {
+///   "format-version": 1,
+///   "packages": [
+///     {
+///       "id": "testcrate 0.1.0 (path+file:///fakepath/testcrate)",
+///       "name": "testcrate",
+///       "version": "0.1.0",
+///       "source": { "kind": "workspace", "path": "testcrate" },
+///       "features": ["default", "dep:serde"],
+///       "build-platforms": { "target": ["default"], "host": null }
+///     }
+///   ],
+///   "links": [
+///     {
+///       "from": "testcrate 0.1.0 (path+file:///fakepath/testcrate)",
+///       "to": "datatest 0.4.2 (registry+https://github.com/rust-lang/crates.io-index)",
+///       "dep-name": "datatest",
+///       "kinds": ["normal", "dev"]
+///     }
+///   ]
+/// }
+/// ```
+///
+/// * `format-version` is [`EXPORT_FORMAT_VERSION`].
+/// * `packages` is sorted by name, then version, then ID.
+/// * `source` has a `kind` of `"workspace"` or `"path"`, with a `path` relative to the workspace
+///   root, or `"external"`, with a `source` such as
+///   `"registry+https://github.com/rust-lang/crates.io-index"`.
+/// * `features` is only present for exports of feature sets. It lists the enabled named features
+///   and optional dependencies (as `dep:<name>`), sorted.
+/// * `build-platforms` is only present for exports of Cargo sets. It lists the features the
+///   package is built with on the target and host platforms, or `null` for a platform the
+///   package isn't built on.
+/// * `links` lists the dependency links between packages in the set, sorted by `from`, then `to`,
+///   then `dep-name`. `kinds` is a non-empty subset of `"normal"`, `"build"` and `"dev"`, in that
+///   order.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SetExport {
+    /// The version of the export format.
+    pub format_version: u32,
+
+    /// The packages in the set.
+    pub packages: Vec<PackageExport>,
+
+    /// The dependency links between packages in the set.
+    pub links: Vec<LinkExport>,
+}
+
+impl SetExport {
+    /// Serializes this export to a pretty-printed JSON string.
+    pub fn to_json(&self) -> String {
+        let mut json = serde_json::to_string_pretty(self).expect("serializing exports succeeds");
+        json.push('\n');
+        json
+    }
+}
+
+/// A package within a [`SetExport`].
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PackageExport {
+    /// The package ID.
+    pub id: String,
+
+    /// The name of the package.
+    pub name: String,
+
+    /// The version of the package.
+    pub version: String,
+
+    /// Where the package comes from.
+    pub source: SourceExport,
+
+    /// The enabled named features and optional dependencies, for exports of feature sets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub features: Option<Vec<String>>,
+
+    /// The features the package is built with on each platform, for exports of Cargo sets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_platforms: Option<BuildPlatformsExport>,
+}
+
+/// The source of a package within a [`SetExport`].
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum SourceExport {
+    /// The package is in the workspace.
+    Workspace {
+        /// The path to the package, relative to the workspace root.
+        path: String,
+    },
+
+    /// The package is a path dependency outside the workspace.
+    Path {
+        /// The path to the package, relative to the workspace root.
+        path: String,
+    },
+
+    /// The package is an external dependency, such as one from a registry or a Git repository.
+    External {
+        /// The source of the package, as reported by Cargo.
+        source: String,
+    },
+}
+
+/// The features a package is built with on each platform, within a [`SetExport`].
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BuildPlatformsExport {
+    /// The features the package is built with on the target platform, or `None` if it isn't built
+    /// on the target platform.
+    pub target: Option<Vec<String>>,
+
+    /// The features the package is built with on the host platform, or `None` if it isn't built
+    /// on the host platform.
+    pub host: Option<Vec<String>>,
+}
+
+/// A dependency link within a [`SetExport`].
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LinkExport {
+    /// The ID of the package that depends on `to`.
+    pub from: String,
+
+    /// The ID of the package that `from` depends on.
+    pub to: String,
+
+    /// The name `from` depends on `to` under.
+    pub dep_name: String,
+
+    /// The kinds of dependency this link represents: `"normal"`, `"build"` and/or `"dev"`.
+    pub kinds: Vec<String>,
+}
+
+impl<'g> PackageSet<'g> {
+    /// Exports the packages in this set and the links between them, in a stable format for
+    /// consumption by other tools.
+    ///
+    /// For the format, see [`SetExport`].
+    pub fn export(&self) -> SetExport {
+        export_impl(self, |_| (None, None))
+    }
+}
+
+impl<'g> FeatureSet<'g> {
+    /// Exports the packages in this set along with their enabled features, and the links between
+    /// them, in a stable format for consumption by other tools.
+    ///
+    /// For the format, see [`SetExport`].
+    pub fn export(&self) -> SetExport {
+        export_impl(&self.to_package_set(), |package| {
+            (Some(export_features(self, package)), None)
+        })
+    }
+}
+
+impl<'g> CargoSet<'g> {
+    /// Exports the packages this Cargo set builds, along with the features they're built with on
+    /// each platform, and the links between them, in a stable format for consumption by other
+    /// tools.
+    ///
+    /// For the format, see [`SetExport`].
+    pub fn export(&self) -> SetExport {
+        let target_packages = self.target_features().to_package_set();
+        let host_packages = self.host_features().to_package_set();
+        export_impl(&target_packages.union(&host_packages), |package| {
+            let build_platforms = BuildPlatformsExport {
+                target: target_packages
+                    .contains(package.id())
+                    .expect("valid package ID")
+                    .then(|| export_features(self.target_features(), package)),
+                host: host_packages
+                    .contains(package.id())
+                    .expect("valid package ID")
+                    .then(|| export_features(self.host_features(), package)),
+            };
+            (None, Some(build_platforms))
+        })
+    }
+}
+
+fn export_impl<'g>(
+    package_set: &PackageSet<'g>,
+    mut extra_fn: impl FnMut(PackageMetadata<'g>) -> (Option<Vec<String>>, Option<BuildPlatformsExport>),
+) -> SetExport {
+    let packages = package_set
+        .packages_in(IterationOrder::Lexical)
+        .map(|package| {
+            let (features, build_platforms) = extra_fn(package);
+            PackageExport {
+                id: package.id().repr().to_owned(),
+                name: package.name().to_owned(),
+                version: package.version().to_string(),
+                source: export_source(package.source()),
+                features,
+                build_platforms,
+            }
+        })
+        .collect();
+
+    let mut links: Vec<_> = package_set
+        .links(DependencyDirection::Forward)
+        .map(export_link)
+        .collect();
+    links
+        .sort_unstable_by(|a, b| (&a.from, &a.to, &a.dep_name).cmp(&(&b.from, &b.to, &b.dep_name)));
+
+    SetExport {
+        format_version: EXPORT_FORMAT_VERSION,
+        packages,
+        links,
+    }
+}
+
+fn export_source(source: PackageSource<'_>) -> SourceExport {
+    match source {
+        PackageSource::Workspace(path) => SourceExport::Workspace {
+            path: path.to_string(),
+        },
+        PackageSource::Path(path) => SourceExport::Path {
+            path: path.to_string(),
+        },
+        PackageSource::External(source) => SourceExport::External {
+            source: source.to_owned(),
+        },
+    }
+}
+
+fn export_features(feature_set: &FeatureSet<'_>, package: PackageMetadata<'_>) -> Vec<String> {
+    let feature_list = match feature_set
+        .features_for(package.id())
+        .expect("valid package ID")
+    {
+        Some(feature_list) => feature_list,
+        None => return vec![],
+    };
+    let features: BTreeSet<_> = feature_list
+        .labels()
+        .iter()
+        .filter(|label| **label != FeatureLabel::Base)
+        .map(|label| label.to_string())
+        .collect();
+    features.into_iter().collect()
+}
+
+fn export_link(link: PackageLink<'_>) -> LinkExport {
+    let kinds = [
+        ("normal", link.normal().is_present()),
+        ("build", link.build().is_present()),
+        ("dev", link.dev().is_present()),
+    ]
+    .iter()
+    .filter(|(_, present)| *present)
+    .map(|(kind, _)| (*kind).to_owned())
+    .collect();
+    LinkExport {
+        from: link.from().id().repr().to_owned(),
+        to: link.to().id().repr().to_owned(),
+        dep_name: link.dep_name().to_owned(),
+        kinds,
+    }
+}
@@ -14,7 +14,9 @@ mod graph_impl;
 #[cfg(feature = "proptest1")]
 mod proptest_helpers;
 mod query;
+mod required_features;
 mod resolve;
+mod unused;
 mod weak;
 
 pub(self) use build::*;
@@ -22,5 +24,6 @@ pub use cycles::*;
 pub use feature_list::FeatureList;
 pub use graph_impl::*;
 pub use query::*;
+pub use required_features::*;
 pub use resolve::*;
 pub use weak::*;
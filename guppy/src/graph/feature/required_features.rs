@@ -0,0 +1,224 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Checks for the `required-features` of build targets.
+
+use crate::{
+    graph::{
+        cargo::{BuildPlatform, CargoSet},
+        feature::{FeatureId, FeatureLabel, FeatureSet},
+        BuildTarget, BuildTargetId, DependencyDirection, PackageMetadata,
+    },
+    Error, PackageId,
+};
+use std::fmt;
+
+impl<'g> FeatureSet<'g> {
+    /// Returns true if this feature set enables the `required-features` of a build target in the
+    /// given package.
+    ///
+    /// `build_target` must be one of the package's build targets. Cargo skips binary, example,
+    /// test and benchmark targets whose required features aren't enabled, so this determines
+    /// whether the target would be built. Library targets and build scripts are always built.
+    ///
+    /// Required features of the form `dep-name/feature-name` are satisfied if the named feature
+    /// of that dependency is in this feature set.
+    ///
+    /// Returns `false` if the package isn't in this feature set, and an error if the package ID
+    /// was unknown.
+    ///
+    /// For more, see [The `required-features`
+    /// field](https://doc.rust-lang.org/nightly/cargo/reference/cargo-targets.html#the-required-features-field)
+    /// in the Cargo reference.
+    pub fn enables_required_features(
+        &self,
+        package_id: &PackageId,
+        build_target: &BuildTarget<'_>,
+    ) -> Result<bool, Error> {
+        let package = self.graph().package_graph().metadata(package_id)?;
+        if !self.contains_package(package_id)? {
+            return Ok(false);
+        }
+        Ok(self.missing_features(package, build_target).is_empty())
+    }
+
+    /// Returns the build targets in the given package that would be built with this feature set.
+    ///
+    /// This skips targets whose required features aren't enabled. For more, see
+    /// [`enables_required_features`](Self::enables_required_features).
+    ///
+    /// Returns an empty list if the package isn't in this feature set, and an error if the
+    /// package ID was unknown.
+    pub fn buildable_targets(&self, package_id: &PackageId) -> Result<Vec<BuildTarget<'g>>, Error> {
+        let package = self.graph().package_graph().metadata(package_id)?;
+        if !self.contains_package(package_id)? {
+            return Ok(vec![]);
+        }
+        Ok(package
+            .build_targets()
+            .filter(|build_target| self.missing_features(package, build_target).is_empty())
+            .collect())
+    }
+
+    /// Returns the build targets of workspace packages in this feature set that wouldn't be built,
+    /// because their required features aren't enabled.
+    ///
+    /// Cargo only builds the library targets and build scripts of packages outside the
+    /// workspace, so other packages are skipped.
+    ///
+    /// This can be used to catch feature changes that cause binaries, examples, tests or
+    /// benchmarks to silently stop being built. For more, see
+    /// [`enables_required_features`](Self::enables_required_features).
+    ///
+    /// Targets are returned in topological order of their packages, in the direction specified.
+    pub fn unbuildable_targets(
+        &self,
+        direction: DependencyDirection,
+    ) -> Vec<UnbuildableTarget<'g>> {
+        self.packages_with_features(direction)
+            .filter(|features| features.package().in_workspace())
+            .flat_map(|features| {
+                let package = *features.package();
+                package.build_targets().filter_map(move |build_target| {
+                    let missing_features = self.missing_features(package, &build_target);
+                    if missing_features.is_empty() {
+                        None
+                    } else {
+                        Some(UnbuildableTarget {
+                            package,
+                            build_target,
+                            missing_features,
+                            build_platform: None,
+                        })
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the required features of a build target that aren't in this feature set.
+    ///
+    /// The package must be in this feature set.
+    fn missing_features<'a>(
+        &self,
+        package: PackageMetadata<'g>,
+        build_target: &BuildTarget<'a>,
+    ) -> Vec<&'a str> {
+        if matches!(
+            build_target.id(),
+            BuildTargetId::Library | BuildTargetId::BuildScript
+        ) {
+            return vec![];
+        }
+        let features = self
+            .features_for(package.id())
+            .expect("package ID is known")
+            .expect("package is in the feature set");
+
+        build_target
+            .required_features()
+            .iter()
+            .filter(|required| {
+                let enabled = match required.split_once('/') {
+                    Some((dep_name, feature_name)) => {
+                        let dep_name = dep_name.trim_end_matches('?');
+                        package
+                            .direct_links()
+                            .filter(|link| link.dep_name() == dep_name)
+                            .any(|link| {
+                                self.contains(FeatureId::named(link.to().id(), feature_name))
+                                    .expect("linked package is known")
+                            })
+                    }
+                    None => features.contains(FeatureLabel::Named(required)),
+                };
+                !enabled
+            })
+            .map(|required| required.as_str())
+            .collect()
+    }
+}
+
+impl<'g> CargoSet<'g> {
+    /// Returns the build targets of workspace packages in this build that wouldn't be built,
+    /// because their required features aren't enabled.
+    ///
+    /// Target packages are returned before host packages. For more, see
+    /// [`FeatureSet::unbuildable_targets`](crate::graph::feature::FeatureSet::unbuildable_targets).
+    pub fn unbuildable_targets(
+        &self,
+        direction: DependencyDirection,
+    ) -> Vec<UnbuildableTarget<'g>> {
+        self.all_features()
+            .iter()
+            .flat_map(|(build_platform, feature_set)| {
+                feature_set
+                    .unbuildable_targets(direction)
+                    .into_iter()
+                    .map(move |unbuildable| UnbuildableTarget {
+                        build_platform: Some(*build_platform),
+                        ..unbuildable
+                    })
+            })
+            .collect()
+    }
+}
+
+/// A build target that won't be built, because its required features aren't enabled.
+///
+/// Returned by [`FeatureSet::unbuildable_targets`] and
+/// [`CargoSet::unbuildable_targets`](crate::graph::cargo::CargoSet::unbuildable_targets). The
+/// `Display` impl prints a human-readable explanation.
+#[derive(Clone, Debug)]
+pub struct UnbuildableTarget<'g> {
+    package: PackageMetadata<'g>,
+    build_target: BuildTarget<'g>,
+    missing_features: Vec<&'g str>,
+    build_platform: Option<BuildPlatform>,
+}
+
+assert_covariant!(UnbuildableTarget);
+
+impl<'g> UnbuildableTarget<'g> {
+    /// Returns the package the build target is in.
+    pub fn package(&self) -> PackageMetadata<'g> {
+        self.package
+    }
+
+    /// Returns the build target that won't be built.
+    pub fn build_target(&self) -> BuildTarget<'g> {
+        self.build_target
+    }
+
+    /// Returns the required features of the build target that aren't enabled, in the order
+    /// they're listed in the manifest.
+    pub fn missing_features(&self) -> &[&'g str] {
+        &self.missing_features
+    }
+
+    /// Returns the platform the package is built on, if this was returned by a `CargoSet`.
+    pub fn build_platform(&self) -> Option<BuildPlatform> {
+        self.build_platform
+    }
+}
+
+impl<'g> fmt::Display for UnbuildableTarget<'g> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.build_target.id() {
+            BuildTargetId::Binary(_) => "binary",
+            BuildTargetId::Example(_) => "example",
+            BuildTargetId::Test(_) => "test",
+            BuildTargetId::Benchmark(_) => "benchmark",
+            _ => "target",
+        };
+        write!(
+            f,
+            "{} `{}` in package {} {} isn't built: missing required features {}",
+            kind,
+            self.build_target.name(),
+            self.package.name(),
+            self.package.version(),
+            self.missing_features.join(", "),
+        )
+    }
+}
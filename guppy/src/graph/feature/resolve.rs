@@ -7,18 +7,27 @@ use crate::{
         cargo::{CargoOptions, CargoSet},
         feature::{
             build::FeatureEdgeReference, ConditionalLink, FeatureEdge, FeatureGraph, FeatureId,
-            FeatureList, FeatureMetadata, FeatureQuery, FeatureResolver,
+            FeatureList, FeatureMetadata, FeatureNode, FeatureQuery, FeatureResolver,
         },
         resolve_core::ResolveCore,
         DependencyDirection, FeatureGraphSpec, FeatureIx, PackageIx, PackageMetadata, PackageSet,
     },
-    petgraph_support::{dfs::BufferedEdgeFilterFn, IxBitSet},
+    petgraph_support::{
+        dfs::BufferedEdgeFilterFn,
+        dot::{DotAttributes, DotFmt, DotVisitor, DotWrite},
+        edge_ref::GraphEdgeRef,
+        IxBitSet,
+    },
     sorted_set::SortedSet,
     Error, PackageId,
 };
 use fixedbitset::FixedBitSet;
 use itertools::Either;
-use petgraph::{graph::NodeIndex, visit::EdgeRef};
+use petgraph::{
+    graph::NodeIndex,
+    visit::{EdgeRef, NodeFiltered, NodeRef},
+};
+use std::fmt;
 
 impl<'g> FeatureGraph<'g> {
     /// Creates a new `FeatureSet` consisting of all members of this feature graph.
@@ -62,6 +71,14 @@ impl<'g> FeatureGraph<'g> {
 ///
 /// Created by `FeatureQuery::resolve`, the `FeatureGraph::resolve_` methods, or from
 /// `PackageSet::to_feature_set`.
+///
+/// ## Iteration order
+///
+/// Iterators that take a `DependencyDirection` return features in topological order in that
+/// direction. Iteration is deterministic: the same set, built from the same `cargo metadata`
+/// output, is always iterated over in the same order. To iterate in lexical order instead, for
+/// example for snapshot tests, use [`feature_ids_in`](Self::feature_ids_in) with
+/// [`IterationOrder::Lexical`](crate::graph::IterationOrder::Lexical).
 #[derive(Clone, Debug)]
 pub struct FeatureSet<'g> {
     graph: DebugIgnore<FeatureGraph<'g>>,
@@ -244,6 +261,45 @@ impl<'g> FeatureSet<'g> {
         res
     }
 
+    /// Returns true if every feature in `self` is also in `other`.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the package graphs associated with `self` and `other` don't match.
+    pub fn is_subset(&self, other: &Self) -> bool {
+        assert!(
+            ::std::ptr::eq(self.graph.package_graph, other.graph.package_graph),
+            "package graphs passed into is_subset() match"
+        );
+        self.core.is_subset(&other.core)
+    }
+
+    /// Returns true if every feature in `other` is also in `self`.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the package graphs associated with `self` and `other` don't match.
+    pub fn is_superset(&self, other: &Self) -> bool {
+        assert!(
+            ::std::ptr::eq(self.graph.package_graph, other.graph.package_graph),
+            "package graphs passed into is_superset() match"
+        );
+        other.core.is_subset(&self.core)
+    }
+
+    /// Returns true if `self` and `other` have no features in common.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the package graphs associated with `self` and `other` don't match.
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        assert!(
+            ::std::ptr::eq(self.graph.package_graph, other.graph.package_graph),
+            "package graphs passed into is_disjoint() match"
+        );
+        self.core.is_disjoint(&other.core)
+    }
+
     /// Returns a `PackageSet` on which a filter has been applied.
     ///
     /// Filters out all values for which the callback returns false.
@@ -524,6 +580,18 @@ impl<'g> FeatureSet<'g> {
             })
     }
 
+    /// Constructs a representation of the selected features in `dot` format.
+    ///
+    /// Labels, other graphviz attributes and clusters are determined by the visitor. For a visitor
+    /// with reasonable defaults, see [`FeatureDotStyle`].
+    pub fn display_dot<'a, V: FeatureDotVisitor + 'g>(
+        &'a self,
+        visitor: V,
+    ) -> impl fmt::Display + 'a {
+        let node_filtered = NodeFiltered(self.graph.dep_graph(), &self.core.included);
+        DotFmt::new(node_filtered, FeatureVisitorWrap::new(*self.graph, visitor))
+    }
+
     // ---
     // Helper methods
     // ---
@@ -603,3 +671,201 @@ impl<'g> PartialEq for FeatureSet<'g> {
 }
 
 impl<'g> Eq for FeatureSet<'g> {}
+
+/// A visitor used for formatting `dot` graphs of features.
+pub trait FeatureDotVisitor {
+    /// Visits this feature. The implementation may output a label for this feature to the given
+    /// `DotWrite`.
+    fn visit_feature(&self, feature: FeatureMetadata<'_>, f: &mut DotWrite<'_, '_>) -> fmt::Result;
+
+    /// Visits this link between features. The implementation may output a label for this link to
+    /// the given `DotWrite`.
+    fn visit_link(&self, link: FeatureDotLink<'_>, f: &mut DotWrite<'_, '_>) -> fmt::Result;
+
+    /// Sets graphviz attributes, such as `color` or `shape`, for this feature. Setting `label`
+    /// overrides the label written by `visit_feature`.
+    ///
+    /// The default implementation doesn't set any attributes.
+    fn feature_attributes(&self, feature: FeatureMetadata<'_>, attrs: &mut DotAttributes) {
+        let _ = (feature, attrs);
+    }
+
+    /// Returns the name of the cluster to draw this feature in, or `None` to draw it outside any
+    /// clusters.
+    ///
+    /// Features with the same cluster name are drawn together in a box labeled with that name.
+    /// The default implementation doesn't put features in clusters.
+    fn feature_cluster(&self, feature: FeatureMetadata<'_>) -> Option<String> {
+        let _ = feature;
+        None
+    }
+
+    /// Sets graphviz attributes, such as `color` or `style`, for this link. Setting `label`
+    /// overrides the label written by `visit_link`.
+    ///
+    /// The default implementation doesn't set any attributes.
+    fn link_attributes(&self, link: FeatureDotLink<'_>, attrs: &mut DotAttributes) {
+        let _ = (link, attrs);
+    }
+}
+
+impl<T: FeatureDotVisitor + ?Sized> FeatureDotVisitor for &T {
+    fn visit_feature(&self, feature: FeatureMetadata<'_>, f: &mut DotWrite<'_, '_>) -> fmt::Result {
+        (**self).visit_feature(feature, f)
+    }
+
+    fn visit_link(&self, link: FeatureDotLink<'_>, f: &mut DotWrite<'_, '_>) -> fmt::Result {
+        (**self).visit_link(link, f)
+    }
+
+    fn feature_attributes(&self, feature: FeatureMetadata<'_>, attrs: &mut DotAttributes) {
+        (**self).feature_attributes(feature, attrs)
+    }
+
+    fn feature_cluster(&self, feature: FeatureMetadata<'_>) -> Option<String> {
+        (**self).feature_cluster(feature)
+    }
+
+    fn link_attributes(&self, link: FeatureDotLink<'_>, attrs: &mut DotAttributes) {
+        (**self).link_attributes(link, attrs)
+    }
+}
+
+/// A link between two features, as passed to a [`FeatureDotVisitor`].
+///
+/// Links within a package (from a named feature to another feature of the same package, or to the
+/// base feature) are unconditional. Links across packages, and links to optional dependencies,
+/// are represented by a [`ConditionalLink`].
+#[derive(Copy, Clone, Debug)]
+pub struct FeatureDotLink<'g> {
+    from: FeatureMetadata<'g>,
+    to: FeatureMetadata<'g>,
+    conditional: Option<ConditionalLink<'g>>,
+}
+
+impl<'g> FeatureDotLink<'g> {
+    /// Returns the feature which depends on the `to` feature.
+    pub fn from(&self) -> FeatureMetadata<'g> {
+        self.from
+    }
+
+    /// Returns the feature which is depended on by the `from` feature.
+    pub fn to(&self) -> FeatureMetadata<'g> {
+        self.to
+    }
+
+    /// Returns the conditional link this link represents, or `None` if this link is
+    /// unconditional.
+    pub fn conditional_link(&self) -> Option<ConditionalLink<'g>> {
+        self.conditional
+    }
+}
+
+/// A [`FeatureDotVisitor`] with reasonable defaults for visualizing feature graphs.
+///
+/// * Features are labeled with their labels, and clustered by the package they belong to.
+/// * Links are labeled with the names of the dependencies they go through, if any. Links that are
+///   only build dependencies are drawn dashed, and links that are only dev-dependencies are drawn
+///   dotted and gray.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FeatureDotStyle;
+
+impl FeatureDotStyle {
+    /// Creates a new `FeatureDotStyle`.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl FeatureDotVisitor for FeatureDotStyle {
+    fn visit_feature(&self, feature: FeatureMetadata<'_>, f: &mut DotWrite<'_, '_>) -> fmt::Result {
+        write!(f, "{}", feature.label())
+    }
+
+    fn visit_link(&self, link: FeatureDotLink<'_>, f: &mut DotWrite<'_, '_>) -> fmt::Result {
+        match link.conditional_link() {
+            Some(conditional) if link.from().package_id() != link.to().package_id() => {
+                write!(f, "{}", conditional.package_link().dep_name())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn feature_cluster(&self, feature: FeatureMetadata<'_>) -> Option<String> {
+        let package = feature.package();
+        Some(format!("{} {}", package.name(), package.version()))
+    }
+
+    fn link_attributes(&self, link: FeatureDotLink<'_>, attrs: &mut DotAttributes) {
+        let conditional = match link.conditional_link() {
+            Some(conditional) => conditional,
+            None => return,
+        };
+        if conditional.normal().is_present() {
+            return;
+        }
+        if conditional.build().is_present() {
+            attrs.set("style", "dashed");
+        } else {
+            attrs.set("style", "dotted").set("color", "gray");
+        }
+    }
+}
+
+struct FeatureVisitorWrap<'g, V> {
+    graph: FeatureGraph<'g>,
+    inner: V,
+}
+
+impl<'g, V> FeatureVisitorWrap<'g, V> {
+    fn new(graph: FeatureGraph<'g>, inner: V) -> Self {
+        Self { graph, inner }
+    }
+
+    fn metadata(&self, node: FeatureNode) -> FeatureMetadata<'g> {
+        self.graph
+            .metadata_for_node(node)
+            .expect("visited node should have associated metadata")
+    }
+
+    fn link<ER: GraphEdgeRef<'g, FeatureEdge, FeatureIx>>(&self, edge: ER) -> FeatureDotLink<'g> {
+        let edge = edge.into_edge_reference();
+        let dep_graph = self.graph.dep_graph();
+        FeatureDotLink {
+            from: self.metadata(dep_graph[edge.source()]),
+            to: self.metadata(dep_graph[edge.target()]),
+            conditional: self
+                .graph
+                .edge_to_conditional_link(edge.source(), edge.target(), edge.id(), None)
+                .map(|(link, _)| link),
+        }
+    }
+}
+
+impl<'g, V, NR, ER> DotVisitor<NR, ER> for FeatureVisitorWrap<'g, V>
+where
+    V: FeatureDotVisitor,
+    NR: NodeRef<NodeId = NodeIndex<FeatureIx>, Weight = FeatureNode>,
+    ER: GraphEdgeRef<'g, FeatureEdge, FeatureIx>,
+{
+    fn visit_node(&self, node: NR, f: &mut DotWrite<'_, '_>) -> fmt::Result {
+        self.inner.visit_feature(self.metadata(*node.weight()), f)
+    }
+
+    fn visit_edge(&self, edge: ER, f: &mut DotWrite<'_, '_>) -> fmt::Result {
+        self.inner.visit_link(self.link(edge), f)
+    }
+
+    fn node_attributes(&self, node: NR, attrs: &mut DotAttributes) {
+        self.inner
+            .feature_attributes(self.metadata(*node.weight()), attrs)
+    }
+
+    fn node_cluster(&self, node: NR) -> Option<String> {
+        self.inner.feature_cluster(self.metadata(*node.weight()))
+    }
+
+    fn edge_attributes(&self, edge: ER, attrs: &mut DotAttributes) {
+        self.inner.link_attributes(self.link(edge), attrs)
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Finding features and optional dependencies that are never used.
+
+use crate::graph::{
+    feature::{FeatureGraph, FeatureId, FeaturePetgraph},
+    DependencyDirection, FeatureIx, PackageMetadata,
+};
+use petgraph::prelude::*;
+use std::collections::HashSet;
+
+impl<'g> FeatureGraph<'g> {
+    /// Returns the optional dependencies that no feature in the graph ever activates.
+    ///
+    /// An optional dependency is activated if a named feature of its package enables it, including
+    /// the `default` feature, or if another package enables it through its dependency
+    /// declaration or one of its own features. Optional dependencies that aren't activated this
+    /// way are dead weight, and can usually be deleted.
+    ///
+    /// Cargo creates an implicit named feature for every optional dependency that isn't referred
+    /// to with `dep:` syntax. Such an optional dependency is only activated if its implicit feature
+    /// is enabled by some other feature. Implicit features can still be enabled directly with
+    /// `--features` on the command line, which isn't considered here: for workspace packages that
+    /// are published, check that the optional dependency isn't meant to be enabled by users.
+    ///
+    /// Feature IDs are returned in topological order of their packages, in the forward direction.
+    pub fn never_activated_optional_deps(&self) -> Vec<FeatureId<'g>> {
+        let dep_graph = self.dep_graph();
+        let mut never_activated = vec![];
+        for package in self
+            .package_graph
+            .resolve_all()
+            .packages(DependencyDirection::Forward)
+        {
+            let feature_list = self
+                .all_features_for(package.id())
+                .expect("valid package ID");
+            let base_ix = self
+                .feature_ix(FeatureId::base(package.id()))
+                .expect("base feature is in the feature graph");
+            for dep_name in feature_list.optional_deps() {
+                let dep_ix = self
+                    .feature_ix(FeatureId::optional_dependency(package.id(), dep_name))
+                    .expect("optional dependency is in the feature graph");
+                let implicit_ix = self
+                    .feature_ix(FeatureId::named(package.id(), dep_name))
+                    .ok()
+                    .filter(|&ix| is_implicit_feature(dep_graph, ix, dep_ix, base_ix));
+
+                let activated_directly = dep_graph
+                    .neighbors_directed(dep_ix, Incoming)
+                    .any(|from_ix| Some(from_ix) != implicit_ix);
+                let activated_implicitly = implicit_ix.map_or(false, |implicit_ix| {
+                    dep_graph
+                        .neighbors_directed(implicit_ix, Incoming)
+                        .next()
+                        .is_some()
+                });
+                if !activated_directly && !activated_implicitly {
+                    never_activated.push(FeatureId::optional_dependency(package.id(), dep_name));
+                }
+            }
+        }
+        never_activated
+    }
+
+    /// Returns the named features declared by workspace members that nothing in the workspace
+    /// uses.
+    ///
+    /// A feature is used if any of the following enable it:
+    /// * a feature of any workspace member, including the `default` feature of its own package
+    /// * a workspace member's dependency declaration
+    /// * the `required-features` of a build target in any workspace member
+    /// * the `[package.metadata.docs.rs]` section of its package, through the `features` list or
+    ///   `all-features = true`
+    ///
+    /// The `default` feature is never reported. Neither are the features that only enable the
+    /// optional dependency of the same name, which Cargo creates implicitly: to find unused
+    /// optional dependencies, use
+    /// [`never_activated_optional_deps`](Self::never_activated_optional_deps).
+    ///
+    /// Only direct uses are considered, so if an unused feature is the only thing enabling another
+    /// feature, the latter is reported once the former is removed. Features meant to be enabled on
+    /// the command line or by users of published packages are reported as well.
+    ///
+    /// Feature IDs are returned in the order of workspace members by path, then in the order
+    /// features are declared in each package.
+    pub fn unused_workspace_features(&self) -> Vec<FeatureId<'g>> {
+        let dep_graph = self.dep_graph();
+        let workspace = self.package_graph.workspace();
+
+        let mut entry_points = HashSet::new();
+        for member in workspace.iter() {
+            for build_target in member.build_targets() {
+                for required in build_target.required_features() {
+                    mark_feature_str(member, required, &mut entry_points);
+                }
+            }
+            let docs_rs = &member.metadata_table()["docs"]["rs"];
+            if docs_rs["all-features"].as_bool() == Some(true) {
+                for feature_name in member.named_features() {
+                    entry_points.insert(FeatureId::named(member.id(), feature_name));
+                }
+            }
+            for feature in docs_rs["features"].as_array().into_iter().flatten() {
+                if let Some(feature) = feature.as_str() {
+                    mark_feature_str(member, feature, &mut entry_points);
+                }
+            }
+        }
+
+        let mut unused = vec![];
+        for member in workspace.iter() {
+            let base_ix = self
+                .feature_ix(FeatureId::base(member.id()))
+                .expect("base feature is in the feature graph");
+            for feature_name in member.named_features() {
+                let feature_id = FeatureId::named(member.id(), feature_name);
+                if feature_name == "default" || entry_points.contains(&feature_id) {
+                    continue;
+                }
+                let feature_ix = self
+                    .feature_ix(feature_id)
+                    .expect("named feature is in the feature graph");
+                let implicit = self
+                    .feature_ix(FeatureId::optional_dependency(member.id(), feature_name))
+                    .map_or(false, |dep_ix| {
+                        is_implicit_feature(dep_graph, feature_ix, dep_ix, base_ix)
+                    });
+                let used = dep_graph
+                    .neighbors_directed(feature_ix, Incoming)
+                    .any(|from_ix| self.metadata_for_ix(from_ix).package().in_workspace());
+                if !implicit && !used {
+                    unused.push(feature_id);
+                }
+            }
+        }
+        unused
+    }
+}
+
+/// Records the feature referred to by a `required-features` or docs.rs entry in `package`, either
+/// `feature-name` or `dep-name/feature-name`.
+fn mark_feature_str<'g>(
+    package: PackageMetadata<'g>,
+    feature: &str,
+    features: &mut HashSet<FeatureId<'g>>,
+) {
+    match feature.split_once('/') {
+        Some((dep_name, feature_name)) => {
+            let dep_name = dep_name.trim_end_matches('?');
+            for link in package
+                .direct_links()
+                .filter(|link| link.dep_name() == dep_name)
+            {
+                if let Some(feature_name) = link
+                    .to()
+                    .named_features()
+                    .find(|named| *named == feature_name)
+                {
+                    features.insert(FeatureId::named(link.to().id(), feature_name));
+                }
+            }
+        }
+        None => {
+            if let Some(feature_name) = package.named_features().find(|named| *named == feature) {
+                features.insert(FeatureId::named(package.id(), feature_name));
+            }
+        }
+    }
+}
+
+/// Returns true if the only thing the given named feature does is enable the optional dependency
+/// with the same name, as the features Cargo creates implicitly do.
+fn is_implicit_feature(
+    dep_graph: &FeaturePetgraph,
+    named_ix: NodeIndex<FeatureIx>,
+    dep_ix: NodeIndex<FeatureIx>,
+    base_ix: NodeIndex<FeatureIx>,
+) -> bool {
+    // Every feature also depends on the base feature of its package.
+    dep_graph
+        .neighbors_directed(named_ix, Outgoing)
+        .all(|to_ix| to_ix == dep_ix || to_ix == base_ix)
+}
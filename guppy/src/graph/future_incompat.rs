@@ -0,0 +1,235 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Support for ingesting Cargo's future-incompatibility reports.
+
+use crate::{
+    graph::{PackageGraph, PackageMetadata, PackageSet},
+    Error, PackageId,
+};
+use serde::Deserialize;
+
+/// A future-incompatibility report, as emitted by Cargo.
+///
+/// When dependencies trigger lints that will become hard errors in future versions of Rust,
+/// Cargo emits a report with the affected packages. With `--message-format=json`, the report is a
+/// JSON message with `"reason": "future-incompat-report"`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use guppy::graph::FutureIncompatReport;
+/// use guppy::MetadataCommand;
+/// use std::fs;
+///
+/// let graph = MetadataCommand::new().build_graph().expect("guppy graph constructed");
+/// // The output of `cargo build --message-format=json --future-incompat-report`.
+/// let messages = fs::read_to_string("messages.json").expect("messages read");
+/// let report = FutureIncompatReport::from_messages(&messages)
+///     .expect("messages parsed")
+///     .expect("report found");
+/// let correlated = report.correlate(&graph).expect("packages correlated");
+/// for entry in correlated.entries() {
+///     println!(
+///         "{} is depended on by {} workspace members",
+///         entry.package().id(),
+///         entry.workspace_dependents().len(),
+///     );
+/// }
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+pub struct FutureIncompatReport {
+    #[serde(default)]
+    id: Option<u32>,
+    future_incompat_report: Vec<FutureIncompatPackage>,
+}
+
+impl FutureIncompatReport {
+    /// Parses a single `future-incompat-report` JSON message.
+    pub fn parse_json(json: impl AsRef<str>) -> Result<Self, Error> {
+        serde_json::from_str(json.as_ref()).map_err(Error::FutureIncompatReportParseError)
+    }
+
+    /// Finds and parses the `future-incompat-report` message in the output of a Cargo command run
+    /// with `--message-format=json`.
+    ///
+    /// Lines that aren't JSON objects are skipped, as are messages with other reasons. Returns
+    /// `None` if there's no report in the output.
+    pub fn from_messages(messages: impl AsRef<str>) -> Result<Option<Self>, Error> {
+        #[derive(Deserialize)]
+        struct Reason<'a> {
+            reason: Option<&'a str>,
+        }
+
+        for line in messages.as_ref().lines() {
+            let line = line.trim();
+            if !line.starts_with('{') {
+                continue;
+            }
+            match serde_json::from_str::<Reason<'_>>(line) {
+                Ok(Reason {
+                    reason: Some("future-incompat-report"),
+                }) => return Self::parse_json(line).map(Some),
+                _ => continue,
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the ID Cargo assigned to this report, for use with
+    /// `cargo report future-incompatibilities --id`.
+    pub fn id(&self) -> Option<u32> {
+        self.id
+    }
+
+    /// Returns the packages in this report.
+    pub fn packages(&self) -> &[FutureIncompatPackage] {
+        &self.future_incompat_report
+    }
+
+    /// Correlates packages in this report with packages in the given `PackageGraph`.
+    ///
+    /// Returns an error if a package in the report is unknown to the graph.
+    pub fn correlate<'g, 'a>(
+        &'a self,
+        graph: &'g PackageGraph,
+    ) -> Result<CorrelatedFutureIncompatReport<'g, 'a>, Error> {
+        let entries = self
+            .future_incompat_report
+            .iter()
+            .map(|report_package| {
+                let package =
+                    graph.metadata(&PackageId::new(report_package.package_id.as_str()))?;
+                Ok(FutureIncompatEntry {
+                    package,
+                    report_package,
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(CorrelatedFutureIncompatReport { graph, entries })
+    }
+}
+
+/// A package in a [`FutureIncompatReport`], along with the diagnostics it triggered.
+#[derive(Clone, Debug, Deserialize)]
+pub struct FutureIncompatPackage {
+    package_id: String,
+    items: Vec<FutureIncompatItem>,
+}
+
+impl FutureIncompatPackage {
+    /// Returns the ID of the package, as reported by Cargo.
+    pub fn package_id(&self) -> &str {
+        &self.package_id
+    }
+
+    /// Returns the diagnostics triggered by this package.
+    pub fn items(&self) -> &[FutureIncompatItem] {
+        &self.items
+    }
+}
+
+/// A diagnostic in a [`FutureIncompatReport`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct FutureIncompatItem {
+    #[serde(default)]
+    future_breakage_date: Option<String>,
+    diagnostic: Diagnostic,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Diagnostic {
+    rendered: String,
+    level: String,
+}
+
+impl FutureIncompatItem {
+    /// Returns the date after which this will become a hard error, if known.
+    pub fn future_breakage_date(&self) -> Option<&str> {
+        self.future_breakage_date.as_deref()
+    }
+
+    /// Returns the diagnostic as rendered by `rustc`.
+    pub fn rendered(&self) -> &str {
+        &self.diagnostic.rendered
+    }
+
+    /// Returns the level of the diagnostic, e.g. `"warning"`.
+    pub fn level(&self) -> &str {
+        &self.diagnostic.level
+    }
+}
+
+/// A [`FutureIncompatReport`] whose packages have been correlated with a [`PackageGraph`].
+///
+/// Returned by [`FutureIncompatReport::correlate`].
+#[derive(Clone, Debug)]
+pub struct CorrelatedFutureIncompatReport<'g, 'a> {
+    graph: &'g PackageGraph,
+    entries: Vec<FutureIncompatEntry<'g, 'a>>,
+}
+
+impl<'g, 'a> CorrelatedFutureIncompatReport<'g, 'a> {
+    /// Returns the package graph the report was correlated with.
+    pub fn graph(&self) -> &'g PackageGraph {
+        self.graph
+    }
+
+    /// Returns the entries in this report, in the order Cargo listed them.
+    pub fn entries(&self) -> &[FutureIncompatEntry<'g, 'a>] {
+        &self.entries
+    }
+
+    /// Returns the set of packages in this report.
+    pub fn package_set(&self) -> PackageSet<'g> {
+        self.graph
+            .resolve_ids(self.entries.iter().map(|entry| entry.package.id()))
+            .expect("package IDs were correlated")
+    }
+
+    /// Returns the workspace members that depend on any package in this report, directly or
+    /// transitively.
+    ///
+    /// Workspace members that are themselves in the report are included.
+    pub fn workspace_dependents(&self) -> PackageSet<'g> {
+        workspace_dependents(
+            self.graph,
+            self.entries.iter().map(|entry| entry.package.id()),
+        )
+    }
+}
+
+/// An entry in a [`CorrelatedFutureIncompatReport`].
+#[derive(Clone, Copy, Debug)]
+pub struct FutureIncompatEntry<'g, 'a> {
+    package: PackageMetadata<'g>,
+    report_package: &'a FutureIncompatPackage,
+}
+
+impl<'g, 'a> FutureIncompatEntry<'g, 'a> {
+    /// Returns the package that triggered the diagnostics.
+    pub fn package(&self) -> PackageMetadata<'g> {
+        self.package
+    }
+
+    /// Returns the diagnostics triggered by this package.
+    pub fn items(&self) -> &'a [FutureIncompatItem] {
+        &self.report_package.items
+    }
+
+    /// Returns the workspace members that depend on this package, directly or transitively.
+    pub fn workspace_dependents(&self) -> PackageSet<'g> {
+        workspace_dependents(self.package.graph(), std::iter::once(self.package.id()))
+    }
+}
+
+fn workspace_dependents<'g, 'a>(
+    graph: &'g PackageGraph,
+    package_ids: impl IntoIterator<Item = &'a PackageId>,
+) -> PackageSet<'g> {
+    graph
+        .query_reverse(package_ids)
+        .expect("package IDs were correlated")
+        .resolve()
+        .intersection(&graph.resolve_workspace())
+}
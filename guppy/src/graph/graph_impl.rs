@@ -5,9 +5,11 @@ use crate::{
     graph::{
         cargo_version_matches,
         feature::{FeatureGraphImpl, FeatureId, FeatureLabel, FeatureNode},
-        BuildTarget, BuildTargetId, BuildTargetImpl, BuildTargetKind, Cycles, DependencyDirection,
-        OwnedBuildTargetId, PackageIx, PackageQuery, PackageSet,
+        ArtifactDependency, ArtifactDependencyImpl, BuildTarget, BuildTargetId, BuildTargetImpl,
+        BuildTargetKind, Cycles, DependencyDirection, Duplicates, OwnedBuildTargetId, PackageIx,
+        PackageQuery, PackageSet,
     },
+    interner::InternedStr,
     petgraph_support::{scc::Sccs, topo::TopoWithCycles, IxBitSet},
     platform::{EnabledTernary, PlatformSpec, PlatformStatus, PlatformStatusImpl},
     CargoMetadata, DependencyKind, Error, JsonValue, MetadataCommand, PackageId,
@@ -23,9 +25,10 @@ use petgraph::{
     visit::EdgeFiltered,
 };
 use semver::{Version, VersionReq};
+use serde::Deserialize;
 use smallvec::SmallVec;
 use std::{
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     fmt, iter,
     iter::FromIterator,
 };
@@ -63,8 +66,15 @@ impl PackageGraph {
     }
 
     /// Parses the given `Metadata` and constructs a `PackageGraph` from it.
+    ///
+    /// If the `rayon1` feature is enabled, packages are processed in parallel. The resulting graph
+    /// is the same either way.
     pub fn from_metadata(metadata: CargoMetadata) -> Result<Self, Error> {
-        Self::build(metadata.0)
+        Self::build(
+            metadata.inner,
+            metadata.workspace_default_members,
+            metadata.dep_extras,
+        )
     }
 
     /// Constructs a package graph from the given JSON output of `cargo metadata`.
@@ -314,6 +324,14 @@ impl PackageGraph {
         Cycles::new(self)
     }
 
+    /// Returns information about packages that are present in more than one version in this
+    /// graph.
+    ///
+    /// For more information, see the documentation for `Duplicates`.
+    pub fn duplicates(&self) -> Duplicates<'_> {
+        Duplicates::new(self)
+    }
+
     // For more traversals, see query.rs.
 
     // ---
@@ -550,6 +568,33 @@ impl<'g> Workspace<'g> {
         self.inner.members_by_path.iter().map(|(_path, id)| id)
     }
 
+    /// Returns an iterator over package IDs for the default members of this workspace, sorted by
+    /// the path they're in.
+    ///
+    /// The default members are the packages that a plain `cargo build` in the workspace root would
+    /// build. They're specified by the `workspace.default-members` key in the root manifest. If
+    /// that key isn't specified, the default member is the root package, or every member for a
+    /// virtual workspace.
+    ///
+    /// Returns `None` if this information isn't available: `cargo metadata` only reports it in
+    /// Rust 1.71 and above.
+    pub fn default_member_ids(&self) -> Option<impl ExactSizeIterator<Item = &'g PackageId>> {
+        self.inner
+            .default_members
+            .as_ref()
+            .map(|default_members| default_members.iter())
+    }
+
+    /// Returns an iterator over package metadatas for the default members of this workspace,
+    /// sorted by the path they're in.
+    ///
+    /// For more, see the documentation for [`default_member_ids`](Self::default_member_ids).
+    pub fn default_members(&self) -> Option<impl ExactSizeIterator<Item = PackageMetadata<'g>>> {
+        let graph = self.graph;
+        self.default_member_ids()
+            .map(move |ids| ids.map(move |id| graph.metadata(id).expect("valid package ID")))
+    }
+
     /// Maps the given path to the corresponding workspace member.
     ///
     /// Returns an error if the path didn't match any workspace members.
@@ -580,6 +625,54 @@ impl<'g> Workspace<'g> {
             .collect()
     }
 
+    /// Finds the package that owns the given file path.
+    ///
+    /// The path is interpreted relative to the workspace root. Absolute paths are accepted as
+    /// long as they're within the workspace root. The path doesn't need to exist on disk.
+    ///
+    /// A file is owned by the package whose directory is its nearest ancestor, so files within
+    /// nested packages are owned by the innermost package. Packages in the graph that live
+    /// inside the workspace directory but aren't workspace members, such as path dependencies
+    /// listed in `workspace.exclude`, own the files within them as well: these are returned as
+    /// [`PathOwner::NonMember`] rather than being attributed to an enclosing workspace member.
+    /// Files within the target directory are owned by no package.
+    ///
+    /// `cargo metadata` doesn't report `workspace.exclude`, so excluded directories that don't
+    /// contain a package in the graph are attributed as regular files.
+    pub fn owner_of_path(&self, path: impl AsRef<Utf8Path>) -> PathOwner<'g> {
+        let root = self.root();
+        let mut path = path.as_ref();
+        if path.is_absolute() {
+            path = match path.strip_prefix(root) {
+                Ok(path) => path,
+                Err(_) => return PathOwner::Unowned,
+            };
+        }
+        let target_directory = self.target_directory().strip_prefix(root).ok();
+
+        let graph = self.graph;
+        let non_members: HashMap<_, _> = graph
+            .packages()
+            .filter_map(|package| match package.source() {
+                PackageSource::Path(path) => Some((path, package)),
+                _ => None,
+            })
+            .collect();
+
+        for ancestor in path.ancestors() {
+            if Some(ancestor) == target_directory {
+                return PathOwner::TargetDirectory;
+            }
+            if let Some(id) = self.inner.members_by_path.get(ancestor) {
+                return PathOwner::Member(graph.metadata(id).expect("valid package ID"));
+            }
+            if let Some(package) = non_members.get(ancestor) {
+                return PathOwner::NonMember(*package);
+            }
+        }
+        PathOwner::Unowned
+    }
+
     /// Maps the given name to the corresponding workspace member.
     ///
     /// Returns an error if the name didn't match any workspace members.
@@ -619,6 +712,45 @@ impl<'g> Workspace<'g> {
     }
 }
 
+/// The owner of a file path within a workspace.
+///
+/// Returned by [`Workspace::owner_of_path`].
+#[derive(Copy, Clone, Debug)]
+pub enum PathOwner<'g> {
+    /// The path is owned by this workspace member.
+    Member(PackageMetadata<'g>),
+
+    /// The path is owned by a package in the graph that isn't a workspace member, typically a
+    /// path dependency in a directory excluded from the workspace.
+    NonMember(PackageMetadata<'g>),
+
+    /// The path is within the target directory.
+    TargetDirectory,
+
+    /// The path isn't owned by any package.
+    Unowned,
+}
+
+assert_covariant!(PathOwner);
+
+impl<'g> PathOwner<'g> {
+    /// Returns the package that owns this path, whether or not it's a workspace member.
+    pub fn package(&self) -> Option<PackageMetadata<'g>> {
+        match self {
+            PathOwner::Member(package) | PathOwner::NonMember(package) => Some(*package),
+            PathOwner::TargetDirectory | PathOwner::Unowned => None,
+        }
+    }
+
+    /// Returns the workspace member that owns this path, if any.
+    pub fn member(&self) -> Option<PackageMetadata<'g>> {
+        match self {
+            PathOwner::Member(package) => Some(*package),
+            _ => None,
+        }
+    }
+}
+
 #[cfg(feature = "rayon1")]
 mod workspace_rayon {
     use super::*;
@@ -671,15 +803,20 @@ mod workspace_rayon {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "binary", derive(serde::Deserialize, serde::Serialize))]
 pub(super) struct WorkspaceImpl {
     pub(super) root: Utf8PathBuf,
     pub(super) target_directory: Utf8PathBuf,
+    #[cfg_attr(feature = "binary", serde(with = "crate::graph::binary::json_string"))]
     pub(super) metadata_table: JsonValue,
     // This is a BTreeMap to allow presenting data in sorted order.
     pub(super) members_by_path: BTreeMap<Utf8PathBuf, PackageId>,
     pub(super) members_by_name: BTreeMap<Box<str>, PackageId>,
+    // Sorted by path, or None if `cargo metadata` didn't report default members.
+    pub(super) default_members: Option<Vec<PackageId>>,
     // Cache for members by name (only used for proptests)
     #[cfg(feature = "proptest1")]
+    #[cfg_attr(feature = "binary", serde(skip))]
     pub(super) name_list: OnceCell<Vec<Box<str>>>,
 }
 
@@ -788,6 +925,35 @@ impl<'g> PackageMetadata<'g> {
         self.link_between_impl(from, Incoming)
     }
 
+    /// Returns the direct `PackageLink` from `self` for the dependency with the given name, as
+    /// written in `Cargo.toml`, or `None` if there's no such dependency.
+    ///
+    /// For a renamed dependency like `foo = { package = "bar" }`, the name is `foo`. For more, see
+    /// [`PackageLink::dep_name`].
+    pub fn link_by_dep_name(&self, dep_name: &str) -> Option<PackageLink<'g>> {
+        self.direct_links().find(|link| link.dep_name() == dep_name)
+    }
+
+    /// Returns the direct `PackageLink` from `self` for the dependency that is imported with the
+    /// given crate name in Rust code, or `None` if there's no such dependency.
+    ///
+    /// This is the name that would be used in `use` statements and `extern crate` declarations.
+    /// For more, see [`PackageLink::resolved_name`].
+    pub fn link_by_resolved_name(&self, resolved_name: &str) -> Option<PackageLink<'g>> {
+        self.direct_links()
+            .find(|link| link.resolved_name() == resolved_name)
+    }
+
+    /// Returns the crate names that this package is imported as by the packages that directly
+    /// depend on it.
+    ///
+    /// These can be different from this package's own library name if a dependent renamed it.
+    pub fn imported_as(&self) -> BTreeSet<&'g str> {
+        self.reverse_direct_links()
+            .map(|link| link.resolved_name())
+            .collect()
+    }
+
     // ---
     // Package fields
     // ---
@@ -917,6 +1083,39 @@ impl<'g> PackageMetadata<'g> {
         &self.inner.metadata_table
     }
 
+    /// Deserializes the `package.metadata.<tool>` table for this package into `T`.
+    ///
+    /// Returns `Ok(None)` if this package doesn't have a table for `tool`, and an error if the
+    /// table couldn't be deserialized into `T`.
+    ///
+    /// ```
+    /// use guppy::MetadataCommand;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct MyToolConfig {
+    ///     #[serde(default)]
+    ///     skip: bool,
+    /// }
+    ///
+    /// let graph = MetadataCommand::new().build_graph().expect("guppy graph constructed");
+    /// for package in graph.packages() {
+    ///     let config: Option<MyToolConfig> = package.tool_metadata("my-tool").unwrap();
+    ///     assert!(config.is_none(), "no packages are configured for my-tool");
+    /// }
+    /// ```
+    pub fn tool_metadata<T>(&self, tool: &str) -> Result<Option<T>, Error>
+    where
+        T: Deserialize<'g>,
+    {
+        match self.metadata_table().get(tool) {
+            Some(table) => T::deserialize(table).map(Some).map_err(|err| {
+                Error::MetadataTableParseError(self.id().clone(), tool.to_owned(), err)
+            }),
+            None => Ok(None),
+        }
+    }
+
     /// Returns the name of a native library this package links to, if specified.
     ///
     /// This is the same as the `links` field of `Cargo.toml`. See [The `links` Manifest
@@ -953,6 +1152,19 @@ impl<'g> PackageMetadata<'g> {
         self.inner.rust_version.as_ref()
     }
 
+    /// Returns the minimal Rust compiler version as a `Version`, if specified.
+    ///
+    /// `rust-version` is written as a bare version like `"1.58"`, which Cargo treats as
+    /// `1.58.0`. Components left out of the version are filled in with zeroes.
+    pub fn minimum_rust_version(&self) -> Option<Version> {
+        let comparator = self.rust_version()?.comparators.first()?;
+        Some(Version::new(
+            comparator.major,
+            comparator.minor.unwrap_or(0),
+            comparator.patch.unwrap_or(0),
+        ))
+    }
+
     /// Returns all the build targets for this package.
     ///
     /// For more, see [Cargo
@@ -1154,33 +1366,45 @@ impl<'g> PartialEq for PackageMetadata<'g> {
 impl<'g> Eq for PackageMetadata<'g> {}
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "binary", derive(serde::Deserialize, serde::Serialize))]
 pub(crate) struct PackageMetadataImpl {
     // Implementation note: we use Box<str> and Box<Path> to save on memory use when possible.
 
     // Fields extracted from the package.
-    pub(super) name: String,
+    pub(super) name: InternedStr,
     pub(super) version: Version,
     pub(super) authors: Vec<String>,
     pub(super) description: Option<Box<str>>,
     pub(super) license: Option<Box<str>>,
+    #[cfg_attr(
+        feature = "binary",
+        serde(with = "crate::graph::binary::option_box_path")
+    )]
     pub(super) license_file: Option<Box<Utf8Path>>,
+    #[cfg_attr(feature = "binary", serde(with = "crate::graph::binary::box_path"))]
     pub(super) manifest_path: Box<Utf8Path>,
     pub(super) categories: Vec<String>,
     pub(super) keywords: Vec<String>,
+    #[cfg_attr(
+        feature = "binary",
+        serde(with = "crate::graph::binary::option_box_path")
+    )]
     pub(super) readme: Option<Box<Utf8Path>>,
     pub(super) repository: Option<Box<str>>,
     pub(super) homepage: Option<Box<str>>,
     pub(super) documentation: Option<Box<str>>,
     pub(super) edition: Box<str>,
+    #[cfg_attr(feature = "binary", serde(with = "crate::graph::binary::json_string"))]
     pub(super) metadata_table: JsonValue,
     pub(super) links: Option<Box<str>>,
     pub(super) publish: PackagePublishImpl,
     pub(super) default_run: Option<Box<str>>,
     pub(super) rust_version: Option<VersionReq>,
-    pub(super) named_features: IndexMap<Box<str>, SmallVec<[NamedFeatureDep; 4]>>,
-    pub(super) optional_deps: IndexSet<Box<str>>,
+    pub(super) named_features: IndexMap<InternedStr, SmallVec<[NamedFeatureDep; 4]>>,
+    pub(super) optional_deps: IndexSet<InternedStr>,
 
     // Other information.
+    #[cfg_attr(feature = "binary", serde(with = "crate::graph::binary::node_index"))]
     pub(super) package_ix: NodeIndex<PackageIx>,
     pub(super) source: PackageSourceImpl,
     pub(super) build_targets: BTreeMap<OwnedBuildTargetId, BuildTargetImpl>,
@@ -1298,6 +1522,59 @@ impl<'g> PackageSource<'g> {
             _ => None,
         }
     }
+
+    /// Classifies this package source.
+    ///
+    /// For more, see the documentation for [`SourceKind`](SourceKind).
+    pub fn kind(&self) -> SourceKind<'g> {
+        SourceKind::new(*self)
+    }
+
+    /// Returns true if this package source represents a Git repository.
+    pub fn is_git(&self) -> bool {
+        matches!(self.parse_external(), Some(ExternalSource::Git { .. }))
+    }
+
+    /// Returns the repository URL if this is a Git dependency, or `None` otherwise.
+    ///
+    /// For more, see the documentation for [`ExternalSource::Git`](ExternalSource::Git).
+    pub fn git_repository(&self) -> Option<&'g str> {
+        match self.parse_external() {
+            Some(ExternalSource::Git { repository, .. }) => Some(repository),
+            _ => None,
+        }
+    }
+
+    /// Returns the branch, tag or revision requested in `Cargo.toml` if this is a Git
+    /// dependency, or `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use guppy::graph::{GitReq, PackageSource};
+    ///
+    /// let source = PackageSource::External("git+https://github.com/rust-lang/cargo.git?branch=main#0227f048fcb7c798026ede6cc20c92befc84c3a4");
+    /// assert_eq!(source.git_req(), Some(GitReq::Branch("main")));
+    /// assert_eq!(source.git_resolved(), Some("0227f048fcb7c798026ede6cc20c92befc84c3a4"));
+    ///
+    /// let source = PackageSource::External(PackageSource::CRATES_IO_REGISTRY);
+    /// assert_eq!(source.git_req(), None);
+    /// ```
+    pub fn git_req(&self) -> Option<GitReq<'g>> {
+        match self.parse_external() {
+            Some(ExternalSource::Git { req, .. }) => Some(req),
+            _ => None,
+        }
+    }
+
+    /// Returns the commit hash locked in `Cargo.lock` if this is a Git dependency, or `None`
+    /// otherwise.
+    pub fn git_resolved(&self) -> Option<&'g str> {
+        match self.parse_external() {
+            Some(ExternalSource::Git { resolved, .. }) => Some(resolved),
+            _ => None,
+        }
+    }
 }
 
 impl<'g> fmt::Display for PackageSource<'g> {
@@ -1310,6 +1587,108 @@ impl<'g> fmt::Display for PackageSource<'g> {
     }
 }
 
+/// A classification of a [`PackageSource`].
+///
+/// Returned by [`PackageSource::kind`](PackageSource::kind), and used by
+/// [`PackageSet::filter_source_kind`](crate::graph::PackageSet::filter_source_kind) and
+/// [`PackageSet::group_by_source_kind`](crate::graph::PackageSet::group_by_source_kind).
+///
+/// # Examples
+///
+/// ```
+/// use guppy::graph::{PackageSource, SourceKind};
+///
+/// let source = PackageSource::External("registry+https://github.com/fakeorg/crates.io-index");
+/// assert_eq!(
+///     source.kind(),
+///     SourceKind::AlternateRegistry("https://github.com/fakeorg/crates.io-index"),
+/// );
+///
+/// let source = PackageSource::External(PackageSource::CRATES_IO_REGISTRY);
+/// assert_eq!(source.kind(), SourceKind::CratesIo);
+/// ```
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[non_exhaustive]
+pub enum SourceKind<'g> {
+    /// This package is in the workspace.
+    Workspace,
+
+    /// This package is a path dependency that isn't in the workspace.
+    Path,
+
+    /// This package is from `crates.io`, either through the Git index or the sparse index.
+    CratesIo,
+
+    /// This package is from a registry other than `crates.io`.
+    ///
+    /// The associated data is the URL of the registry's index as it would be specified in Cargo's
+    /// configuration, e.g. `"https://github.com/fakeorg/crates.io-index"` or
+    /// `"sparse+https://example.com/index/"`. Registry names aren't part of package sources, so
+    /// they aren't available here.
+    AlternateRegistry(&'g str),
+
+    /// This package is from a Git repository.
+    ///
+    /// The associated data is the URL of the repository. For the requested and resolved
+    /// revisions, see [`PackageSource::git_req`](PackageSource::git_req) and
+    /// [`PackageSource::git_resolved`](PackageSource::git_resolved).
+    Git(&'g str),
+
+    /// This package is from a local directory or local registry, e.g. one created by
+    /// `cargo vendor` or `cargo local-registry`.
+    ///
+    /// The associated data is the URL of the directory, e.g. `"file:///path/to/vendor"`.
+    Vendored(&'g str),
+
+    /// The source of this package couldn't be recognized.
+    ///
+    /// The associated data is the full source string.
+    Unknown(&'g str),
+}
+
+impl<'g> SourceKind<'g> {
+    /// The URL for the sparse `crates.io` index, including the leading `"sparse+"`.
+    pub const CRATES_IO_SPARSE: &'static str = "sparse+https://index.crates.io/";
+
+    fn new(source: PackageSource<'g>) -> Self {
+        let source = match source {
+            PackageSource::Workspace(_) => return SourceKind::Workspace,
+            PackageSource::Path(_) => return SourceKind::Path,
+            PackageSource::External(source) => source,
+        };
+        if source == PackageSource::CRATES_IO_REGISTRY || source == Self::CRATES_IO_SPARSE {
+            return SourceKind::CratesIo;
+        }
+        if source.starts_with("sparse+") {
+            return SourceKind::AlternateRegistry(source);
+        }
+        if let Some(path) = source
+            .strip_prefix("directory+")
+            .or_else(|| source.strip_prefix("local-registry+"))
+        {
+            return SourceKind::Vendored(path);
+        }
+        match ExternalSource::new(source) {
+            Some(ExternalSource::Registry(url)) => SourceKind::AlternateRegistry(url),
+            Some(ExternalSource::Git { repository, .. }) => SourceKind::Git(repository),
+            None => SourceKind::Unknown(source),
+        }
+    }
+
+    /// Returns true if this package is from a registry, including `crates.io`.
+    pub fn is_registry(&self) -> bool {
+        matches!(
+            self,
+            SourceKind::CratesIo | SourceKind::AlternateRegistry(_)
+        )
+    }
+
+    /// Returns true if this package is local, i.e. either in the workspace or a path dependency.
+    pub fn is_local(&self) -> bool {
+        matches!(self, SourceKind::Workspace | SourceKind::Path)
+    }
+}
+
 /// More information about an external source.
 ///
 /// This provides information about whether an external dependency is a Git dependency or fetched
@@ -1594,12 +1973,19 @@ pub enum GitReq<'g> {
 
 /// Internal representation of the source of a package.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "binary", derive(serde::Deserialize, serde::Serialize))]
 pub(super) enum PackageSourceImpl {
-    Workspace(Box<Utf8Path>),
-    Path(Box<Utf8Path>),
+    Workspace(
+        #[cfg_attr(feature = "binary", serde(with = "crate::graph::binary::box_path"))]
+        Box<Utf8Path>,
+    ),
+    Path(
+        #[cfg_attr(feature = "binary", serde(with = "crate::graph::binary::box_path"))]
+        Box<Utf8Path>,
+    ),
     // Special, common case.
     CratesIo,
-    External(Box<str>),
+    External(InternedStr),
 }
 
 /// Locations that a package can be published to.
@@ -1704,6 +2090,7 @@ impl<'g> PackagePublish<'g> {
 
 /// Internal representation of PackagePublish.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "binary", derive(serde::Deserialize, serde::Serialize))]
 pub(super) enum PackagePublishImpl {
     Unrestricted,
     Registries(Box<[String]>),
@@ -1796,6 +2183,7 @@ impl<'g> PackageLink<'g> {
     /// Returns details about this dependency from the `[dependencies]` section.
     pub fn normal(&self) -> DependencyReq<'g> {
         DependencyReq {
+            kind: DependencyKind::Normal,
             inner: &self.inner.normal,
         }
     }
@@ -1803,6 +2191,7 @@ impl<'g> PackageLink<'g> {
     /// Returns details about this dependency from the `[build-dependencies]` section.
     pub fn build(&self) -> DependencyReq<'g> {
         DependencyReq {
+            kind: DependencyKind::Build,
             inner: &self.inner.build,
         }
     }
@@ -1810,6 +2199,7 @@ impl<'g> PackageLink<'g> {
     /// Returns details about this dependency from the `[dev-dependencies]` section.
     pub fn dev(&self) -> DependencyReq<'g> {
         DependencyReq {
+            kind: DependencyKind::Development,
             inner: &self.inner.dev,
         }
     }
@@ -1830,6 +2220,47 @@ impl<'g> PackageLink<'g> {
         self.inner.dev_only()
     }
 
+    /// Returns true if this dependency is marked `public = true` in the `[dependencies]` section.
+    ///
+    /// Public dependencies are part of a package's public API, so their types may appear in it.
+    /// Dependencies are private by default. Public dependencies are an unstable Cargo feature (see
+    /// [RFC 1977](https://rust-lang.github.io/rfcs/1977-public-private-dependencies.html)), and
+    /// `cargo metadata` only reports them with `-Z public-dependency`.
+    ///
+    /// Only normal dependencies can be public.
+    pub fn is_public(&self) -> bool {
+        self.inner.public
+    }
+
+    /// Returns every instance of this dependency across the `[dependencies]`,
+    /// `[build-dependencies]` and `[dev-dependencies]` sections, including platform-specific
+    /// `[target]` sections.
+    ///
+    /// Each instance records the platform it applies to, along with its version requirement,
+    /// whether it is optional, and the features it requests. This can be used to see exactly how
+    /// the requirements for a dependency vary across platforms.
+    ///
+    /// Instances are returned for normal dependencies first, then build dependencies, then dev
+    /// dependencies. Within each section, they're returned in the order `cargo metadata` lists
+    /// them.
+    pub fn instances(&self) -> impl Iterator<Item = DependencyInstance<'g>> + 'g {
+        let (normal, build, dev) = (self.normal(), self.build(), self.dev());
+        normal
+            .instances()
+            .chain(build.instances())
+            .chain(dev.instances())
+    }
+
+    /// Returns true if this dependency is an artifact dependency in any section.
+    ///
+    /// For information about the artifacts depended on, see
+    /// [`DependencyReq::artifact`](DependencyReq::artifact).
+    pub fn is_artifact(&self) -> bool {
+        self.inner.normal.artifact.is_some()
+            || self.inner.build.artifact.is_some()
+            || self.inner.dev.artifact.is_some()
+    }
+
     // ---
     // Helper methods
     // ---
@@ -1861,10 +2292,12 @@ pub struct PackageLinkPtrs {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "binary", derive(serde::Deserialize, serde::Serialize))]
 pub(crate) struct PackageLinkImpl {
-    pub(super) dep_name: String,
-    pub(super) resolved_name: String,
+    pub(super) dep_name: InternedStr,
+    pub(super) resolved_name: InternedStr,
     pub(super) version_req: VersionReq,
+    pub(super) public: bool,
     pub(super) normal: DependencyReqImpl,
     pub(super) build: DependencyReqImpl,
     pub(super) dev: DependencyReqImpl,
@@ -1883,6 +2316,7 @@ impl PackageLinkImpl {
 /// Usually found within the context of a [`PackageLink`](struct.PackageLink.html).
 #[derive(Clone, Debug)]
 pub struct DependencyReq<'g> {
+    pub(super) kind: DependencyKind,
     pub(super) inner: &'g DependencyReqImpl,
 }
 
@@ -1939,6 +2373,91 @@ impl<'g> DependencyReq<'g> {
     pub fn feature_status(&self, feature: &str) -> EnabledStatus<'g> {
         self.inner.feature_status(feature)
     }
+
+    /// Returns information about this dependency if it is an artifact dependency, i.e. it is
+    /// declared with the `artifact` key.
+    ///
+    /// If a dependency is declared several times within the same section, the first declaration
+    /// with an `artifact` key is used.
+    pub fn artifact(&self) -> Option<ArtifactDependency<'g>> {
+        self.inner.artifact.as_ref().map(ArtifactDependency::new)
+    }
+
+    /// Returns each instance of this dependency within this section, in the order `cargo
+    /// metadata` lists them.
+    ///
+    /// A dependency can be listed several times within the same section, once unconditionally and
+    /// once for each `[target]` section it appears in. `status`, `default_features` and
+    /// `features` are the union of all instances; this method returns them individually.
+    pub fn instances(&self) -> impl ExactSizeIterator<Item = DependencyInstance<'g>> + 'g {
+        let kind = self.kind;
+        self.inner
+            .instances
+            .iter()
+            .map(move |inner| DependencyInstance { kind, inner })
+    }
+}
+
+/// A single instance of a dependency in a `Cargo.toml`: one entry in a `[dependencies]`,
+/// `[build-dependencies]` or `[dev-dependencies]` section, possibly within a `[target]` section.
+///
+/// Returned by [`PackageLink::instances`] and [`DependencyReq::instances`].
+#[derive(Copy, Clone, Debug)]
+pub struct DependencyInstance<'g> {
+    kind: DependencyKind,
+    inner: &'g DependencyInstanceImpl,
+}
+
+assert_covariant!(DependencyInstance);
+
+impl<'g> DependencyInstance<'g> {
+    /// Returns the section this instance was declared in.
+    pub fn kind(&self) -> DependencyKind {
+        self.kind
+    }
+
+    /// Returns the platform this instance is specific to, as written in the manifest (for example
+    /// `cfg(windows)` or `x86_64-unknown-linux-gnu`).
+    ///
+    /// Returns `None` if this instance isn't platform-specific.
+    pub fn target(&self) -> Option<&'g str> {
+        self.inner.target.as_deref()
+    }
+
+    /// Returns the platforms this instance applies to.
+    ///
+    /// This is `PlatformStatus::Always` if this instance isn't platform-specific.
+    pub fn platform_status(&self) -> PlatformStatus<'g> {
+        PlatformStatus::new(&self.inner.platform_status)
+    }
+
+    /// Evaluates whether this instance applies to the given platform spec.
+    ///
+    /// Returns `Unknown` if the result was unknown, which may happen if evaluating against an
+    /// individual platform and its target features are unknown.
+    pub fn enabled_on(&self, platform_spec: &PlatformSpec) -> EnabledTernary {
+        self.platform_status().enabled_on(platform_spec)
+    }
+
+    /// Returns the version requirement specified by this instance.
+    pub fn version_req(&self) -> &'g VersionReq {
+        &self.inner.version_req
+    }
+
+    /// Returns true if this instance is marked optional.
+    pub fn optional(&self) -> bool {
+        self.inner.optional
+    }
+
+    /// Returns true if this instance uses the default features of the package depended on.
+    pub fn default_features(&self) -> bool {
+        self.inner.default_features
+    }
+
+    /// Returns the features requested by this instance, in the order they're listed.
+    pub fn features(&self) -> impl ExactSizeIterator<Item = &'g str> + 'g {
+        self.inner.features.iter().map(|feature| feature.as_str())
+    }
 }
 
 /// Whether a dependency or feature is required, optional, or disabled.
@@ -2034,36 +2553,37 @@ impl<'g> EnabledStatus<'g> {
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "binary", derive(serde::Deserialize, serde::Serialize))]
 pub(super) enum NamedFeatureDep {
-    NamedFeature(Box<str>),
-    OptionalDependency(Box<str>),
+    NamedFeature(InternedStr),
+    OptionalDependency(InternedStr),
     DependencyNamedFeature {
-        dep_name: Box<str>,
-        feature: Box<str>,
+        dep_name: InternedStr,
+        feature: InternedStr,
         weak: bool,
     },
 }
 
 impl NamedFeatureDep {
     #[inline]
-    pub(super) fn named_feature(feature_name: impl Into<String>) -> Self {
-        Self::NamedFeature(feature_name.into().into_boxed_str())
+    pub(super) fn named_feature(feature_name: impl Into<InternedStr>) -> Self {
+        Self::NamedFeature(feature_name.into())
     }
 
     #[inline]
-    pub(super) fn optional_dependency(dep_name: impl Into<String>) -> Self {
-        Self::OptionalDependency(dep_name.into().into_boxed_str())
+    pub(super) fn optional_dependency(dep_name: impl Into<InternedStr>) -> Self {
+        Self::OptionalDependency(dep_name.into())
     }
 
     #[inline]
     pub(super) fn dep_named_feature(
-        dep_name: impl Into<String>,
-        feature: impl Into<String>,
+        dep_name: impl Into<InternedStr>,
+        feature: impl Into<InternedStr>,
         weak: bool,
     ) -> Self {
         Self::DependencyNamedFeature {
-            dep_name: dep_name.into().into_boxed_str(),
-            feature: feature.into().into_boxed_str(),
+            dep_name: dep_name.into(),
+            feature: feature.into(),
             weak,
         }
     }
@@ -2093,9 +2613,12 @@ impl fmt::Display for NamedFeatureDep {
 
 /// Information about dependency requirements.
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "binary", derive(serde::Deserialize, serde::Serialize))]
 pub(super) struct DependencyReqImpl {
     pub(super) required: DepRequiredOrOptional,
     pub(super) optional: DepRequiredOrOptional,
+    pub(super) artifact: Option<ArtifactDependencyImpl>,
+    pub(super) instances: Vec<DependencyInstanceImpl>,
 }
 
 impl DependencyReqImpl {
@@ -2137,14 +2660,27 @@ impl DependencyReqImpl {
     }
 }
 
+/// A single instance of a dependency within a section.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "binary", derive(serde::Deserialize, serde::Serialize))]
+pub(super) struct DependencyInstanceImpl {
+    pub(super) target: Option<Box<str>>,
+    pub(super) platform_status: PlatformStatusImpl,
+    pub(super) version_req: VersionReq,
+    pub(super) optional: bool,
+    pub(super) default_features: bool,
+    pub(super) features: Box<[InternedStr]>,
+}
+
 /// Information about dependency requirements, scoped to either the dependency being required or
 /// optional.
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "binary", derive(serde::Deserialize, serde::Serialize))]
 pub(super) struct DepRequiredOrOptional {
     pub(super) build_if: PlatformStatusImpl,
     pub(super) default_features_if: PlatformStatusImpl,
     pub(super) no_default_features_if: PlatformStatusImpl,
-    pub(super) feature_targets: BTreeMap<String, PlatformStatusImpl>,
+    pub(super) feature_targets: BTreeMap<InternedStr, PlatformStatusImpl>,
 }
 
 impl DepRequiredOrOptional {
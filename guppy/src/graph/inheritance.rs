@@ -0,0 +1,282 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Introspection of dependencies inherited from `[workspace.dependencies]`.
+
+use crate::{
+    graph::{
+        lockfile::{
+            is_workspace_inherited, lockfile_error, raw_dependencies, read_toml,
+            ManifestDependency, WorkspaceManifest,
+        },
+        PackageGraph, PackageMetadata,
+    },
+    DependencyKind, Error,
+};
+use camino::Utf8Path;
+use semver::VersionReq;
+use toml::Value as TomlValue;
+
+impl PackageGraph {
+    /// Returns the entries in the `[workspace.dependencies]` section of the workspace's root
+    /// manifest, in the order they're declared in.
+    ///
+    /// Returns an error if the root manifest couldn't be read or parsed.
+    ///
+    /// This method is available if the `lockfile` feature is enabled.
+    pub fn workspace_dependencies(&self) -> Result<Vec<WorkspaceDependency>, Error> {
+        let workspace_root = self.workspace().root();
+        let manifest_path = workspace_root.join("Cargo.toml");
+        let workspace = read_workspace_manifest(workspace_root, &manifest_path)?;
+        workspace
+            .dependencies
+            .iter()
+            .map(|(key, value)| {
+                let dependency = ManifestDependency::new(
+                    key,
+                    value,
+                    None,
+                    None,
+                    workspace_root,
+                    &workspace,
+                    &manifest_path,
+                )?;
+                Ok(WorkspaceDependency::new(key, &dependency))
+            })
+            .collect()
+    }
+}
+
+impl<'g> PackageMetadata<'g> {
+    /// Returns the dependencies declared in this package's manifest, along with whether they're
+    /// inherited from `[workspace.dependencies]` with `workspace = true`.
+    ///
+    /// Declarations are returned in the order they're listed in the manifest, with
+    /// platform-specific declarations after the others. Inherited declarations are resolved
+    /// against the workspace's root manifest.
+    ///
+    /// Returns an error if this package isn't a workspace member, or if a manifest couldn't be
+    /// read or parsed.
+    ///
+    /// This method is available if the `lockfile` feature is enabled.
+    pub fn dependency_declarations(&self) -> Result<Vec<DependencyDeclaration>, Error> {
+        let manifest_path = self.manifest_path();
+        if !self.in_workspace() {
+            return Err(lockfile_error(
+                manifest_path,
+                format!("package '{}' isn't a workspace member", self.id()),
+            ));
+        }
+        let dir = manifest_path
+            .parent()
+            .ok_or_else(|| lockfile_error(manifest_path, "manifest path has no parent"))?;
+        let workspace_root = self.graph().workspace().root();
+        let workspace =
+            read_workspace_manifest(workspace_root, &workspace_root.join("Cargo.toml"))?;
+
+        let manifest = read_toml(manifest_path)?;
+        raw_dependencies(&manifest)
+            .into_iter()
+            .map(|raw| {
+                let inherited = match raw.value {
+                    TomlValue::Table(table) => is_workspace_inherited(table),
+                    _ => false,
+                };
+                let dependency = ManifestDependency::new(
+                    raw.key,
+                    raw.value,
+                    raw.kind,
+                    raw.target,
+                    dir,
+                    &workspace,
+                    manifest_path,
+                )?;
+                let workspace_dependency = if inherited {
+                    let value = workspace
+                        .dependencies
+                        .get(raw.key)
+                        .expect("inherited dependency was resolved against the workspace");
+                    let workspace_manifest = workspace_root.join("Cargo.toml");
+                    let dependency = ManifestDependency::new(
+                        raw.key,
+                        value,
+                        None,
+                        None,
+                        workspace_root,
+                        &workspace,
+                        &workspace_manifest,
+                    )?;
+                    Some(WorkspaceDependency::new(raw.key, &dependency))
+                } else {
+                    None
+                };
+
+                let kind = match raw.kind {
+                    Some("dev") => DependencyKind::Development,
+                    Some("build") => DependencyKind::Build,
+                    _ => DependencyKind::Normal,
+                };
+                Ok(DependencyDeclaration {
+                    dep_name: raw.key.to_owned(),
+                    package_name: dependency.name.clone(),
+                    kind,
+                    target: raw.target.map(|target| target.to_owned()),
+                    version_req: dependency.req.clone(),
+                    features: dependency.features(),
+                    optional: dependency.optional(),
+                    default_features: dependency.uses_default_features(),
+                    workspace_dependency,
+                })
+            })
+            .collect()
+    }
+}
+
+/// A dependency declared in a package's manifest.
+///
+/// Returned by [`PackageMetadata::dependency_declarations`].
+#[derive(Clone, Debug)]
+pub struct DependencyDeclaration {
+    dep_name: String,
+    package_name: String,
+    kind: DependencyKind,
+    target: Option<String>,
+    version_req: VersionReq,
+    features: Vec<String>,
+    optional: bool,
+    default_features: bool,
+    workspace_dependency: Option<WorkspaceDependency>,
+}
+
+impl DependencyDeclaration {
+    /// Returns the name of this dependency, as written in the manifest.
+    ///
+    /// This is different from `package_name` if the dependency was renamed.
+    pub fn dep_name(&self) -> &str {
+        &self.dep_name
+    }
+
+    /// Returns the name of the package depended on.
+    pub fn package_name(&self) -> &str {
+        &self.package_name
+    }
+
+    /// Returns the section this dependency was declared in.
+    pub fn kind(&self) -> DependencyKind {
+        self.kind
+    }
+
+    /// Returns the platform this dependency is specific to, as written in the manifest.
+    ///
+    /// Returns `None` if this dependency isn't platform-specific.
+    pub fn target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
+
+    /// Returns true if this dependency was declared with `workspace = true`.
+    pub fn is_inherited(&self) -> bool {
+        self.workspace_dependency.is_some()
+    }
+
+    /// Returns the entry in `[workspace.dependencies]` this dependency inherits from.
+    ///
+    /// Returns `None` if this dependency isn't inherited.
+    pub fn workspace_dependency(&self) -> Option<&WorkspaceDependency> {
+        self.workspace_dependency.as_ref()
+    }
+
+    /// Returns the version requirement for this dependency, after inheritance.
+    pub fn version_req(&self) -> &VersionReq {
+        &self.version_req
+    }
+
+    /// Returns the features enabled by this dependency, after inheritance.
+    ///
+    /// For inherited dependencies, this includes the features listed in the workspace along with
+    /// any additional features listed in the package's manifest.
+    pub fn features(&self) -> &[String] {
+        &self.features
+    }
+
+    /// Returns true if this dependency is optional, after inheritance.
+    pub fn optional(&self) -> bool {
+        self.optional
+    }
+
+    /// Returns true if this dependency uses the default features of the package depended on, after
+    /// inheritance.
+    pub fn default_features(&self) -> bool {
+        self.default_features
+    }
+}
+
+/// An entry in the `[workspace.dependencies]` section of a workspace's root manifest.
+///
+/// Returned by [`PackageGraph::workspace_dependencies`] and
+/// [`DependencyDeclaration::workspace_dependency`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WorkspaceDependency {
+    dep_name: String,
+    package_name: String,
+    version_req: VersionReq,
+    features: Vec<String>,
+    optional: bool,
+    default_features: bool,
+}
+
+impl WorkspaceDependency {
+    fn new(dep_name: &str, dependency: &ManifestDependency) -> Self {
+        Self {
+            dep_name: dep_name.to_owned(),
+            package_name: dependency.name.clone(),
+            version_req: dependency.req.clone(),
+            features: dependency.features(),
+            optional: dependency.optional(),
+            default_features: dependency.uses_default_features(),
+        }
+    }
+
+    /// Returns the name of this entry, which packages refer to it by.
+    pub fn dep_name(&self) -> &str {
+        &self.dep_name
+    }
+
+    /// Returns the name of the package depended on.
+    ///
+    /// This is different from `dep_name` if the entry specifies `package`.
+    pub fn package_name(&self) -> &str {
+        &self.package_name
+    }
+
+    /// Returns the version requirement specified by this entry.
+    pub fn version_req(&self) -> &VersionReq {
+        &self.version_req
+    }
+
+    /// Returns the features enabled by this entry.
+    pub fn features(&self) -> &[String] {
+        &self.features
+    }
+
+    /// Returns true if this entry is marked optional.
+    ///
+    /// Cargo doesn't allow workspace dependencies to be optional, so this being true usually
+    /// indicates a mistake in the manifest.
+    pub fn optional(&self) -> bool {
+        self.optional
+    }
+
+    /// Returns true if this entry uses the default features of the package depended on.
+    pub fn default_features(&self) -> bool {
+        self.default_features
+    }
+}
+
+fn read_workspace_manifest<'a>(
+    workspace_root: &'a Utf8Path,
+    manifest_path: &Utf8Path,
+) -> Result<WorkspaceManifest<'a>, Error> {
+    let root_manifest = read_toml(manifest_path)?;
+    let workspace_table = root_manifest.get("workspace").and_then(TomlValue::as_table);
+    Ok(WorkspaceManifest::new(workspace_root, workspace_table))
+}
@@ -0,0 +1,157 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Explicit iteration orders for resolved sets.
+
+use crate::{
+    graph::{
+        feature::{FeatureId, FeatureList, FeatureSet},
+        DependencyDirection, PackageMetadata, PackageSet,
+    },
+    PackageId,
+};
+
+/// The order in which to iterate over the members of a resolved set.
+///
+/// Every order is deterministic: iterating over the same set (built from the same `cargo metadata`
+/// output) twice returns members in the same order. The orders differ in which other guarantees
+/// they make.
+///
+/// Used by [`PackageSet::package_ids_in`], [`PackageSet::packages_in`],
+/// [`FeatureSet::feature_ids_in`] and [`FeatureSet::packages_with_features_in`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum IterationOrder {
+    /// Topological order in the forward direction: a package is returned before any of its
+    /// dependencies.
+    ///
+    /// This is the order that iterators taking `DependencyDirection::Forward` use. Packages within
+    /// a dependency cycle are returned in non-dev order.
+    Topological,
+
+    /// Topological order in the reverse direction: a package is returned before any of its
+    /// dependents.
+    ///
+    /// This is the order that iterators taking `DependencyDirection::Reverse` use. Packages within
+    /// a dependency cycle are returned in non-dev order.
+    ReverseTopological,
+
+    /// Lexical order by package name, then by version, then by package ID. Features of the same
+    /// package are ordered by their labels.
+    ///
+    /// Unlike the other orders, this order is stable across changes to the graph that don't
+    /// affect the members of the set, so it is well-suited for snapshot tests and diffs.
+    Lexical,
+
+    /// An unspecified order, which is the cheapest to compute.
+    ///
+    /// The order is deterministic, but it may change across `guppy` versions or with the order in
+    /// which `cargo metadata` lists packages, so it shouldn't be relied on.
+    Unspecified,
+}
+
+impl From<DependencyDirection> for IterationOrder {
+    fn from(direction: DependencyDirection) -> Self {
+        match direction {
+            DependencyDirection::Forward => IterationOrder::Topological,
+            DependencyDirection::Reverse => IterationOrder::ReverseTopological,
+        }
+    }
+}
+
+impl<'g> PackageSet<'g> {
+    /// Iterates over package IDs in the given order.
+    ///
+    /// For more about the available orders, see [`IterationOrder`].
+    pub fn package_ids_in<'a>(
+        &'a self,
+        order: IterationOrder,
+    ) -> impl Iterator<Item = &'g PackageId> + ExactSizeIterator + 'a {
+        self.packages_in(order).map(|package| package.id())
+    }
+
+    /// Iterates over package metadatas in the given order.
+    ///
+    /// For more about the available orders, see [`IterationOrder`].
+    pub fn packages_in<'a>(
+        &'a self,
+        order: IterationOrder,
+    ) -> impl Iterator<Item = PackageMetadata<'g>> + ExactSizeIterator + 'a {
+        let packages: Vec<_> = match order {
+            IterationOrder::Topological => self.packages(DependencyDirection::Forward).collect(),
+            IterationOrder::ReverseTopological => {
+                self.packages(DependencyDirection::Reverse).collect()
+            }
+            IterationOrder::Lexical => {
+                let mut packages: Vec<_> = self.packages_unordered().collect();
+                packages.sort_unstable_by(|a, b| lexical_cmp(*a, *b));
+                packages
+            }
+            IterationOrder::Unspecified => self.packages_unordered().collect(),
+        };
+        packages.into_iter()
+    }
+}
+
+impl<'g> FeatureSet<'g> {
+    /// Iterates over feature IDs in the given order.
+    ///
+    /// For more about the available orders, see [`IterationOrder`].
+    pub fn feature_ids_in<'a>(
+        &'a self,
+        order: IterationOrder,
+    ) -> impl Iterator<Item = FeatureId<'g>> + ExactSizeIterator + 'a {
+        let feature_graph = *self.graph();
+        let unordered = || {
+            self.ixs_unordered()
+                .map(move |feature_ix| feature_graph.metadata_for_ix(feature_ix).feature_id())
+        };
+        let feature_ids: Vec<_> = match order {
+            IterationOrder::Topological => self.feature_ids(DependencyDirection::Forward).collect(),
+            IterationOrder::ReverseTopological => {
+                self.feature_ids(DependencyDirection::Reverse).collect()
+            }
+            IterationOrder::Lexical => {
+                let package_graph = feature_graph.package_graph();
+                let mut feature_ids: Vec<_> = unordered().collect();
+                feature_ids.sort_unstable_by(|a, b| {
+                    let a_package = package_graph.metadata(a.package_id()).expect("valid ID");
+                    let b_package = package_graph.metadata(b.package_id()).expect("valid ID");
+                    lexical_cmp(a_package, b_package).then_with(|| a.label().cmp(&b.label()))
+                });
+                feature_ids
+            }
+            IterationOrder::Unspecified => unordered().collect(),
+        };
+        feature_ids.into_iter()
+    }
+
+    /// Iterates over package metadatas and their corresponding features, in the given order.
+    ///
+    /// For more about the available orders, see [`IterationOrder`].
+    pub fn packages_with_features_in<'a>(
+        &'a self,
+        order: IterationOrder,
+    ) -> impl Iterator<Item = FeatureList<'g>> + 'a {
+        let packages: Vec<_> = match order {
+            IterationOrder::Topological => self
+                .packages_with_features(DependencyDirection::Forward)
+                .collect(),
+            IterationOrder::ReverseTopological => self
+                .packages_with_features(DependencyDirection::Reverse)
+                .collect(),
+            IterationOrder::Lexical | IterationOrder::Unspecified => self
+                .to_package_set()
+                .packages_in(order)
+                .filter_map(|package| self.features_for(package.id()).expect("valid package ID"))
+                .collect(),
+        };
+        packages.into_iter()
+    }
+}
+
+fn lexical_cmp(a: PackageMetadata<'_>, b: PackageMetadata<'_>) -> std::cmp::Ordering {
+    a.name()
+        .cmp(b.name())
+        .then_with(|| a.version().cmp(b.version()))
+        .then_with(|| a.id().cmp(b.id()))
+}
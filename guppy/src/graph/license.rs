@@ -0,0 +1,757 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Parsing of license expressions, and aggregation of licenses over package sets.
+
+use crate::{
+    graph::{
+        spdx_ids::{EXCEPTION_IDS, LICENSE_IDS},
+        DependencyDirection, PackageMetadata, PackageSet,
+    },
+    Error,
+};
+use camino::Utf8Path;
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+/// A parsed [SPDX license expression](https://spdx.github.io/spdx-spec/v2.3/SPDX-license-expressions/),
+/// as found in the `license` field of `Cargo.toml`.
+///
+/// Expressions are made up of license identifiers, optionally followed by `+` ("or later") and
+/// `WITH <exception>`, combined with the `AND` and `OR` operators along with parentheses for
+/// grouping. `WITH` binds the tightest, followed by `AND` and then `OR`.
+///
+/// Expressions can be parsed with `str::parse`, which follows the SPDX specification, and
+/// printed back out with the `Display` impl. To also accept the legacy `/` separator that older
+/// crates use in place of `OR`, use [`parse_lax`](Self::parse_lax).
+///
+/// # Examples
+///
+/// ```
+/// use guppy::graph::LicenseExpr;
+///
+/// let expr: LicenseExpr = "MIT OR Apache-2.0 WITH LLVM-exception".parse().expect("valid expression");
+/// let ids: Vec<_> = expr.license_ids().collect();
+/// assert_eq!(ids, vec!["MIT", "Apache-2.0"]);
+/// assert!(expr.unknown_ids().is_empty(), "all identifiers are known");
+///
+/// let legacy = LicenseExpr::parse_lax("MIT/Apache-2.0").expect("valid legacy expression");
+/// assert_eq!(legacy.to_string(), "MIT OR Apache-2.0");
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum LicenseExpr {
+    /// A single license, e.g. `GPL-2.0+ WITH Classpath-exception-2.0`.
+    License {
+        /// The license identifier.
+        id: String,
+
+        /// Whether this is a `+` license, meaning "this version or any later version".
+        or_later: bool,
+
+        /// The exception specified with `WITH`, if any.
+        exception: Option<String>,
+    },
+
+    /// Both expressions apply: `a AND b`.
+    And(Box<LicenseExpr>, Box<LicenseExpr>),
+
+    /// Either expression may be chosen: `a OR b`.
+    Or(Box<LicenseExpr>, Box<LicenseExpr>),
+}
+
+impl LicenseExpr {
+    /// Parses a license expression, also accepting `/` as a separator equivalent to `OR`.
+    ///
+    /// The `/` separator was used by many crates before crates.io switched to SPDX expressions.
+    /// It binds as loosely as `OR`.
+    pub fn parse_lax(s: &str) -> Result<Self, Error> {
+        Parser::new(s, true).parse()
+    }
+
+    /// Returns the license identifiers in this expression, in the order they're listed.
+    pub fn license_ids(&self) -> impl Iterator<Item = &str> + '_ {
+        let mut licenses = vec![];
+        self.collect_licenses(&mut licenses);
+        licenses.into_iter().map(|(id, _)| id)
+    }
+
+    /// Returns the license and exception identifiers in this expression that aren't known SPDX
+    /// identifiers, in the order they're listed.
+    ///
+    /// User-defined identifiers of the form `LicenseRef-...` are always returned. guppy recognizes
+    /// every identifier on the [SPDX license list](https://spdx.org/licenses/) as of version 3.27,
+    /// including deprecated ones.
+    pub fn unknown_ids(&self) -> Vec<&str> {
+        let mut licenses = vec![];
+        self.collect_licenses(&mut licenses);
+        let mut unknown = vec![];
+        for (id, exception) in licenses {
+            if !is_known(LICENSE_IDS, id) {
+                unknown.push(id);
+            }
+            if let Some(exception) = exception {
+                if !is_known(EXCEPTION_IDS, exception) {
+                    unknown.push(exception);
+                }
+            }
+        }
+        unknown
+    }
+
+    /// Returns true if this expression can be satisfied using only licenses for which `allowed`
+    /// returns true.
+    ///
+    /// `allowed` is called with license identifiers, without any `+` or exception. Both operands of
+    /// `AND` must be satisfied, while satisfying either operand of `OR` is enough.
+    ///
+    /// ```
+    /// use guppy::graph::LicenseExpr;
+    ///
+    /// let expr: LicenseExpr = "(MIT OR Apache-2.0) AND Unicode-DFS-2016".parse().unwrap();
+    /// assert!(expr.is_satisfied_by(|id| id == "MIT" || id == "Unicode-DFS-2016"));
+    /// assert!(!expr.is_satisfied_by(|id| id == "MIT"));
+    /// ```
+    pub fn is_satisfied_by(&self, mut allowed: impl FnMut(&str) -> bool) -> bool {
+        self.satisfied_impl(&mut allowed)
+    }
+
+    fn satisfied_impl(&self, allowed: &mut dyn FnMut(&str) -> bool) -> bool {
+        match self {
+            LicenseExpr::License { id, .. } => allowed(id),
+            LicenseExpr::And(a, b) => a.satisfied_impl(allowed) && b.satisfied_impl(allowed),
+            LicenseExpr::Or(a, b) => a.satisfied_impl(allowed) || b.satisfied_impl(allowed),
+        }
+    }
+
+    /// Returns a canonical form of this expression, so that equivalent expressions compare equal.
+    ///
+    /// Operands of chains of `AND` and `OR` are sorted and deduplicated, so for example
+    /// `MIT OR Apache-2.0` and `Apache-2.0 OR MIT OR MIT` have the same canonical form.
+    pub fn canonicalize(&self) -> LicenseExpr {
+        match self {
+            LicenseExpr::License { .. } => self.clone(),
+            LicenseExpr::And(..) | LicenseExpr::Or(..) => {
+                let is_and = matches!(self, LicenseExpr::And(..));
+                let mut operands = vec![];
+                self.collect_operands(is_and, &mut operands);
+                let mut operands: Vec<_> = operands
+                    .into_iter()
+                    .map(|operand| operand.canonicalize())
+                    .map(|operand| (operand.to_string(), operand))
+                    .collect();
+                operands.sort_by(|(a, _), (b, _)| a.cmp(b));
+                operands.dedup_by(|(a, _), (b, _)| a == b);
+
+                let mut operands = operands.into_iter().map(|(_, operand)| operand);
+                let first = operands.next().expect("at least one operand");
+                operands.fold(first, |acc, operand| {
+                    if is_and {
+                        LicenseExpr::And(Box::new(acc), Box::new(operand))
+                    } else {
+                        LicenseExpr::Or(Box::new(acc), Box::new(operand))
+                    }
+                })
+            }
+        }
+    }
+
+    fn collect_licenses<'a>(&'a self, out: &mut Vec<(&'a str, Option<&'a str>)>) {
+        match self {
+            LicenseExpr::License { id, exception, .. } => {
+                out.push((id, exception.as_deref()));
+            }
+            LicenseExpr::And(a, b) | LicenseExpr::Or(a, b) => {
+                a.collect_licenses(out);
+                b.collect_licenses(out);
+            }
+        }
+    }
+
+    fn collect_operands<'a>(&'a self, is_and: bool, out: &mut Vec<&'a LicenseExpr>) {
+        match self {
+            LicenseExpr::And(a, b) if is_and => {
+                a.collect_operands(is_and, out);
+                b.collect_operands(is_and, out);
+            }
+            LicenseExpr::Or(a, b) if !is_and => {
+                a.collect_operands(is_and, out);
+                b.collect_operands(is_and, out);
+            }
+            _ => out.push(self),
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            LicenseExpr::Or(..) => 0,
+            LicenseExpr::And(..) => 1,
+            LicenseExpr::License { .. } => 2,
+        }
+    }
+
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, min_precedence: u8) -> fmt::Result {
+        if self.precedence() < min_precedence {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+impl FromStr for LicenseExpr {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Parser::new(s, false).parse()
+    }
+}
+
+impl fmt::Display for LicenseExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LicenseExpr::License {
+                id,
+                or_later,
+                exception,
+            } => {
+                write!(f, "{}", id)?;
+                if *or_later {
+                    write!(f, "+")?;
+                }
+                if let Some(exception) = exception {
+                    write!(f, " WITH {}", exception)?;
+                }
+                Ok(())
+            }
+            LicenseExpr::And(a, b) => {
+                a.fmt_operand(f, 1)?;
+                write!(f, " AND ")?;
+                b.fmt_operand(f, 2)
+            }
+            LicenseExpr::Or(a, b) => {
+                a.fmt_operand(f, 0)?;
+                write!(f, " OR ")?;
+                b.fmt_operand(f, 1)
+            }
+        }
+    }
+}
+
+impl<'g> PackageMetadata<'g> {
+    /// Parses the `license` field of this package into a [`LicenseExpr`].
+    ///
+    /// The legacy `/` separator is accepted, as with [`LicenseExpr::parse_lax`].
+    ///
+    /// Returns `None` if the `license` field isn't specified, and an error if it couldn't be
+    /// parsed.
+    pub fn license_expr(&self) -> Option<Result<LicenseExpr, Error>> {
+        self.license().map(LicenseExpr::parse_lax)
+    }
+}
+
+impl<'g> PackageSet<'g> {
+    /// Buckets the packages in this set by license, and reports packages with missing,
+    /// nonstandard or invalid licenses.
+    ///
+    /// Packages whose `license` fields are equivalent, such as `MIT/Apache-2.0` and
+    /// `Apache-2.0 OR MIT`, are placed in the same bucket.
+    pub fn license_report(&self) -> LicenseReport<'g> {
+        let mut report = LicenseReport {
+            buckets: BTreeMap::new(),
+            missing: vec![],
+            nonstandard: vec![],
+            invalid: vec![],
+        };
+
+        let mut packages: Vec<_> = self.packages(DependencyDirection::Forward).collect();
+        packages.sort_by_key(|package| package.id());
+        for package in packages {
+            let license = match package.license() {
+                Some(license) => license,
+                None => {
+                    match package.license_file() {
+                        Some(license_file) => report
+                            .nonstandard
+                            .push((package, NonstandardLicense::LicenseFile(license_file))),
+                        None => report.missing.push(package),
+                    }
+                    continue;
+                }
+            };
+
+            let expr = match LicenseExpr::parse_lax(license) {
+                Ok(expr) => expr,
+                Err(err) => {
+                    report.invalid.push((package, err));
+                    continue;
+                }
+            };
+            if license.parse::<LicenseExpr>().is_err() {
+                report
+                    .nonstandard
+                    .push((package, NonstandardLicense::LegacySyntax));
+            }
+            let unknown_ids = expr.unknown_ids();
+            if !unknown_ids.is_empty() {
+                let unknown_ids = unknown_ids.into_iter().map(|id| id.to_owned()).collect();
+                report
+                    .nonstandard
+                    .push((package, NonstandardLicense::UnknownIds(unknown_ids)));
+            }
+
+            let expr = expr.canonicalize();
+            report
+                .buckets
+                .entry(expr.to_string())
+                .or_insert_with(|| LicenseBucket {
+                    expr,
+                    packages: vec![],
+                })
+                .packages
+                .push(package);
+        }
+
+        report
+    }
+}
+
+/// A report of the licenses used by a set of packages.
+///
+/// Returned by [`PackageSet::license_report`]. All packages in the report are sorted by package
+/// ID.
+#[derive(Debug)]
+pub struct LicenseReport<'g> {
+    buckets: BTreeMap<String, LicenseBucket<'g>>,
+    missing: Vec<PackageMetadata<'g>>,
+    nonstandard: Vec<(PackageMetadata<'g>, NonstandardLicense<'g>)>,
+    invalid: Vec<(PackageMetadata<'g>, Error)>,
+}
+
+assert_covariant!(LicenseReport);
+
+impl<'g> LicenseReport<'g> {
+    /// Returns the packages in the set grouped by their license expressions, sorted by the
+    /// canonical form of the expression.
+    ///
+    /// Packages with missing or invalid licenses aren't in any bucket.
+    pub fn buckets(&self) -> impl ExactSizeIterator<Item = &LicenseBucket<'g>> + '_ {
+        self.buckets.values()
+    }
+
+    /// Returns the bucket for the given license expression, if any packages in the set use it
+    /// or an equivalent expression.
+    pub fn bucket(&self, expr: &LicenseExpr) -> Option<&LicenseBucket<'g>> {
+        self.buckets.get(&expr.canonicalize().to_string())
+    }
+
+    /// Returns the packages that specify neither a `license` nor a `license-file`.
+    pub fn missing(&self) -> &[PackageMetadata<'g>] {
+        &self.missing
+    }
+
+    /// Returns the packages whose licenses aren't standard SPDX expressions, along with the
+    /// reasons why.
+    ///
+    /// A package may be listed more than once if its license is nonstandard for several
+    /// reasons.
+    pub fn nonstandard(&self) -> &[(PackageMetadata<'g>, NonstandardLicense<'g>)] {
+        &self.nonstandard
+    }
+
+    /// Returns the packages whose `license` fields couldn't be parsed, along with the errors.
+    pub fn invalid(&self) -> &[(PackageMetadata<'g>, Error)] {
+        &self.invalid
+    }
+}
+
+/// Packages in a [`LicenseReport`] that share a license expression.
+#[derive(Clone, Debug)]
+pub struct LicenseBucket<'g> {
+    expr: LicenseExpr,
+    packages: Vec<PackageMetadata<'g>>,
+}
+
+assert_covariant!(LicenseBucket);
+
+impl<'g> LicenseBucket<'g> {
+    /// Returns the canonical form of the license expression shared by these packages.
+    pub fn expr(&self) -> &LicenseExpr {
+        &self.expr
+    }
+
+    /// Returns the packages in this bucket, sorted by package ID.
+    pub fn packages(&self) -> &[PackageMetadata<'g>] {
+        &self.packages
+    }
+}
+
+/// The reason a package's license isn't a standard SPDX expression.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum NonstandardLicense<'g> {
+    /// The package only specifies a license file, typically for a nonstandard license.
+    LicenseFile(&'g Utf8Path),
+
+    /// The license expression uses the legacy `/` separator instead of `OR`.
+    LegacySyntax,
+
+    /// The license expression refers to identifiers that aren't known SPDX identifiers. For
+    /// more, see [`LicenseExpr::unknown_ids`].
+    UnknownIds(Vec<String>),
+}
+
+assert_covariant!(NonstandardLicense);
+
+// ---
+// Parsing
+// ---
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Token<'a> {
+    Ident(&'a str),
+    Plus,
+    LParen,
+    RParen,
+    And,
+    Or,
+    With,
+    Slash,
+}
+
+impl<'a> fmt::Display for Token<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(ident) => write!(f, "'{}'", ident),
+            Token::Plus => write!(f, "'+'"),
+            Token::LParen => write!(f, "'('"),
+            Token::RParen => write!(f, "')'"),
+            Token::And => write!(f, "'AND'"),
+            Token::Or => write!(f, "'OR'"),
+            Token::With => write!(f, "'WITH'"),
+            Token::Slash => write!(f, "'/'"),
+        }
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    allow_slash: bool,
+    tokens: Vec<(usize, Token<'a>)>,
+    index: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str, allow_slash: bool) -> Self {
+        Self {
+            input,
+            allow_slash,
+            tokens: vec![],
+            index: 0,
+        }
+    }
+
+    fn parse(mut self) -> Result<LicenseExpr, Error> {
+        self.tokens = self.lex()?;
+        let expr = self.parse_or()?;
+        match self.next_token() {
+            None => Ok(expr),
+            Some((pos, token)) => Err(self.error(pos, format!("unexpected {}", token))),
+        }
+    }
+
+    // or := and (('OR' | '/') and)*
+    fn parse_or(&mut self) -> Result<LicenseExpr, Error> {
+        let mut expr = self.parse_and()?;
+        while let Some((_, Token::Or)) | Some((_, Token::Slash)) = self.peek_token() {
+            self.next_token();
+            let rhs = self.parse_and()?;
+            expr = LicenseExpr::Or(Box::new(expr), Box::new(rhs));
+        }
+        Ok(expr)
+    }
+
+    // and := license ('AND' license)*
+    fn parse_and(&mut self) -> Result<LicenseExpr, Error> {
+        let mut expr = self.parse_license()?;
+        while let Some((_, Token::And)) = self.peek_token() {
+            self.next_token();
+            let rhs = self.parse_license()?;
+            expr = LicenseExpr::And(Box::new(expr), Box::new(rhs));
+        }
+        Ok(expr)
+    }
+
+    // license := '(' or ')' | ident '+'? ('WITH' ident)?
+    fn parse_license(&mut self) -> Result<LicenseExpr, Error> {
+        match self.next_token() {
+            Some((_, Token::LParen)) => {
+                let expr = self.parse_or()?;
+                match self.next_token() {
+                    Some((_, Token::RParen)) => Ok(expr),
+                    Some((pos, token)) => {
+                        Err(self.error(pos, format!("expected ')', found {}", token)))
+                    }
+                    None => {
+                        Err(self.error(self.input.len(), "expected ')', found end of expression"))
+                    }
+                }
+            }
+            Some((_, Token::Ident(id))) => {
+                let or_later = if let Some((_, Token::Plus)) = self.peek_token() {
+                    self.next_token();
+                    true
+                } else {
+                    false
+                };
+                let exception = if let Some((_, Token::With)) = self.peek_token() {
+                    self.next_token();
+                    match self.next_token() {
+                        Some((_, Token::Ident(exception))) => Some(exception.to_owned()),
+                        Some((pos, token)) => {
+                            return Err(self.error(
+                                pos,
+                                format!("expected exception identifier, found {}", token),
+                            ))
+                        }
+                        None => {
+                            return Err(self.error(
+                                self.input.len(),
+                                "expected exception identifier, found end of expression",
+                            ))
+                        }
+                    }
+                } else {
+                    None
+                };
+                Ok(LicenseExpr::License {
+                    id: id.to_owned(),
+                    or_later,
+                    exception,
+                })
+            }
+            Some((pos, token)) => Err(self.error(pos, format!("unexpected {}", token))),
+            None => Err(self.error(self.input.len(), "unexpected end of expression")),
+        }
+    }
+
+    fn peek_token(&self) -> Option<(usize, Token<'a>)> {
+        self.tokens.get(self.index).copied()
+    }
+
+    fn next_token(&mut self) -> Option<(usize, Token<'a>)> {
+        let token = self.peek_token();
+        self.index += 1;
+        token
+    }
+
+    fn lex(&self) -> Result<Vec<(usize, Token<'a>)>, Error> {
+        let mut tokens = vec![];
+        let mut pos = 0;
+        loop {
+            let rest = &self.input[pos..];
+            let trimmed = rest.trim_start();
+            pos += rest.len() - trimmed.len();
+
+            let c = match trimmed.chars().next() {
+                Some(c) => c,
+                None => return Ok(tokens),
+            };
+            let (token, len) = match c {
+                '(' => (Token::LParen, 1),
+                ')' => (Token::RParen, 1),
+                '+' => (Token::Plus, 1),
+                '/' if self.allow_slash => (Token::Slash, 1),
+                c if is_ident_char(c) => {
+                    let len = trimmed.find(|c| !is_ident_char(c)).unwrap_or(trimmed.len());
+                    let token = match &trimmed[..len] {
+                        "AND" => Token::And,
+                        "OR" => Token::Or,
+                        "WITH" => Token::With,
+                        ident => Token::Ident(ident),
+                    };
+                    (token, len)
+                }
+                c => return Err(self.error(pos, format!("unexpected character '{}'", c))),
+            };
+            tokens.push((pos, token));
+            pos += len;
+        }
+    }
+
+    fn error(&self, pos: usize, message: impl fmt::Display) -> Error {
+        Error::LicenseExprError(format!(
+            "{} at position {} in '{}'",
+            message, pos, self.input
+        ))
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | ':')
+}
+
+fn is_known(known: &[&str], id: &str) -> bool {
+    // SPDX identifiers are matched case-insensitively, and the lists are sorted that way.
+    known
+        .binary_search_by(|known| {
+            let known = known.bytes().map(|b| b.to_ascii_lowercase());
+            known.cmp(id.bytes().map(|b| b.to_ascii_lowercase()))
+        })
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn license(id: &str) -> LicenseExpr {
+        LicenseExpr::License {
+            id: id.to_owned(),
+            or_later: false,
+            exception: None,
+        }
+    }
+
+    #[test]
+    fn parse_valid() {
+        let expr: LicenseExpr = "MIT AND BSD-3-Clause OR GPL-2.0+ WITH Classpath-exception-2.0"
+            .parse()
+            .expect("valid expression");
+        assert_eq!(
+            expr,
+            LicenseExpr::Or(
+                Box::new(LicenseExpr::And(
+                    Box::new(license("MIT")),
+                    Box::new(license("BSD-3-Clause")),
+                )),
+                Box::new(LicenseExpr::License {
+                    id: "GPL-2.0".to_owned(),
+                    or_later: true,
+                    exception: Some("Classpath-exception-2.0".to_owned()),
+                }),
+            ),
+            "precedence is WITH > AND > OR"
+        );
+
+        let expr: LicenseExpr = " MIT AND( Apache-2.0 OR LicenseRef-Custom)"
+            .parse()
+            .expect("valid expression");
+        assert_eq!(
+            expr,
+            LicenseExpr::And(
+                Box::new(license("MIT")),
+                Box::new(LicenseExpr::Or(
+                    Box::new(license("Apache-2.0")),
+                    Box::new(license("LicenseRef-Custom")),
+                )),
+            ),
+            "whitespace and parentheses"
+        );
+        assert_eq!(expr.unknown_ids(), vec!["LicenseRef-Custom"]);
+
+        let expr = LicenseExpr::parse_lax("MIT / Apache-2.0 AND Zlib").expect("valid expression");
+        assert_eq!(
+            expr,
+            LicenseExpr::Or(
+                Box::new(license("MIT")),
+                Box::new(LicenseExpr::And(
+                    Box::new(license("Apache-2.0")),
+                    Box::new(license("Zlib")),
+                )),
+            ),
+            "'/' binds as loosely as OR"
+        );
+    }
+
+    #[test]
+    fn unknown_ids() {
+        let expr: LicenseExpr =
+            "CC-BY-SA-3.0 AND EPL-1.0 AND LGPL-2.0-only AND OFL-1.1 AND GPL-2.0 AND mit"
+                .parse()
+                .expect("valid expression");
+        assert!(
+            expr.unknown_ids().is_empty(),
+            "SPDX identifiers, including deprecated and lowercase ones, are known"
+        );
+
+        let expr: LicenseExpr =
+            "Apache-2.0 WITH LLVM-exception OR GPL-2.0 WITH Bison-exception-1.24"
+                .parse()
+                .expect("valid expression");
+        assert!(expr.unknown_ids().is_empty(), "SPDX exceptions are known");
+
+        let expr: LicenseExpr = "MIT-ish OR Apache-2.0 WITH Custom-exception"
+            .parse()
+            .expect("valid expression");
+        assert_eq!(expr.unknown_ids(), vec!["MIT-ish", "Custom-exception"]);
+    }
+
+    #[test]
+    fn spdx_ids_sorted() {
+        for list in [LICENSE_IDS, EXCEPTION_IDS] {
+            for pair in list.windows(2) {
+                assert!(
+                    pair[0].to_ascii_lowercase() < pair[1].to_ascii_lowercase(),
+                    "{} sorts before {}",
+                    pair[0],
+                    pair[1]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn display_roundtrip() {
+        let inputs = [
+            "MIT",
+            "MIT OR Apache-2.0",
+            "(MIT OR Apache-2.0) AND BSD-3-Clause",
+            "Apache-2.0 WITH LLVM-exception OR Apache-2.0 OR MIT",
+            "GPL-3.0+ AND (ISC OR Zlib AND MIT)",
+        ];
+        for input in &inputs {
+            let expr: LicenseExpr = input.parse().expect("valid expression");
+            assert_eq!(&expr.to_string(), input, "display output matches input");
+        }
+    }
+
+    #[test]
+    fn canonicalize() {
+        let a: LicenseExpr = "Apache-2.0 OR MIT OR MIT"
+            .parse()
+            .expect("valid expression");
+        let b = LicenseExpr::parse_lax("MIT/Apache-2.0").expect("valid expression");
+        assert_eq!(a.canonicalize(), b.canonicalize());
+        assert_eq!(a.canonicalize().to_string(), "Apache-2.0 OR MIT");
+
+        let c: LicenseExpr = "(Zlib AND MIT) OR ISC".parse().expect("valid expression");
+        assert_eq!(c.canonicalize().to_string(), "ISC OR MIT AND Zlib");
+    }
+
+    #[test]
+    fn parse_invalid() {
+        let invalids = [
+            ("", "unexpected end of expression at position 0"),
+            ("MIT/Apache-2.0", "unexpected character '/' at position 3"),
+            ("MIT OR", "unexpected end of expression at position 6"),
+            ("MIT Apache-2.0", "unexpected 'Apache-2.0' at position 4"),
+            ("(MIT", "expected ')', found end of expression"),
+            ("MIT WITH", "expected exception identifier"),
+            ("AND MIT", "unexpected 'AND' at position 0"),
+            ("MIT,Apache-2.0", "unexpected character ','"),
+        ];
+        for (input, message) in &invalids {
+            let err = input
+                .parse::<LicenseExpr>()
+                .expect_err("invalid expression");
+            let err = err.to_string();
+            assert!(
+                err.contains(message),
+                "for input {:?}, error {:?} contains {:?}",
+                input,
+                err,
+                message
+            );
+        }
+    }
+}
@@ -0,0 +1,1302 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Support for constructing package graphs from `Cargo.lock` and manifests, without running
+//! `cargo metadata`.
+
+use crate::{
+    graph::{
+        cargo_version_matches, patches::patch_entries, wildcard_match, ArtifactDependencyImpl,
+        BuildTargetId, PackageGraph, PackageLinkImpl, PackageMetadata, PackageMetadataImpl,
+    },
+    interner::Interner,
+    metadata_command::DependencyExtra,
+    CargoMetadata, Error,
+};
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+use cargo_metadata::Package;
+use once_cell::sync::OnceCell;
+use petgraph::prelude::*;
+use semver::{Version, VersionReq};
+use serde::Deserialize;
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    error, fs, iter,
+};
+use toml::{value::Table, Value as TomlValue};
+
+const CRATES_IO_SOURCE: &str = "registry+https://github.com/rust-lang/crates.io-index";
+
+impl PackageGraph {
+    /// Constructs a package graph from the `Cargo.lock` and manifests of the workspace at the
+    /// given root, without running `cargo metadata`.
+    ///
+    /// For more information, see the documentation for
+    /// [`CargoMetadata::from_lockfile`](crate::CargoMetadata::from_lockfile).
+    ///
+    /// This method is available if the `lockfile` feature is enabled.
+    pub fn from_lockfile(workspace_root: impl AsRef<Utf8Path>) -> Result<Self, Error> {
+        CargoMetadata::from_lockfile(workspace_root)?.build_graph()
+    }
+
+    /// Updates this package graph in place after the manifest of a single workspace member
+    /// changed.
+    ///
+    /// `manifest_path` is the path to the member's `Cargo.toml`. Only that manifest is re-read:
+    /// the member's metadata is replaced, and its dependencies are resolved against the packages
+    /// already in the graph, preferring the packages they previously resolved to. The rest of the
+    /// graph is reused as-is. This works for graphs constructed from `cargo metadata` as well as
+    /// from lockfiles.
+    ///
+    /// This is much cheaper than rebuilding the graph, but is only possible if the change doesn't
+    /// require `Cargo.lock` to be updated. An error is returned, and the graph is left unchanged,
+    /// if:
+    /// * the manifest doesn't belong to a workspace member
+    /// * the member's name or version changed
+    /// * a dependency doesn't match any package in the graph
+    ///
+    /// Changes to the `[workspace]` section of the root manifest, such as added members, aren't
+    /// picked up: the graph must be rebuilt for those.
+    ///
+    /// This method is available if the `lockfile` feature is enabled.
+    pub fn update_workspace_member(
+        &mut self,
+        manifest_path: impl AsRef<Utf8Path>,
+    ) -> Result<(), Error> {
+        let manifest_path = manifest_path.as_ref();
+        let manifest_path = manifest_path
+            .canonicalize_utf8()
+            .map_err(|err| lockfile_error(manifest_path, err))?;
+        let dir = manifest_path
+            .parent()
+            .ok_or_else(|| lockfile_error(&manifest_path, "manifest path has no parent"))?;
+
+        let old = self
+            .workspace()
+            .iter()
+            .find(|member| member.manifest_path() == manifest_path)
+            .ok_or_else(|| {
+                lockfile_error(
+                    &manifest_path,
+                    "manifest doesn't belong to a workspace member",
+                )
+            })?;
+
+        let workspace_root = self.workspace().root();
+        let root_manifest = read_toml(&workspace_root.join("Cargo.toml"))?;
+        let workspace = WorkspaceManifest::new(
+            workspace_root,
+            root_manifest.get("workspace").and_then(TomlValue::as_table),
+        );
+        let local = LocalPackage::read(dir, &workspace)?;
+        if local.name != old.name() || &local.version != old.version() {
+            return Err(lockfile_error(
+                &manifest_path,
+                format!(
+                    "package changed from '{} {}' to '{} {}', so the graph must be rebuilt",
+                    old.name(),
+                    old.version(),
+                    local.name,
+                    local.version
+                ),
+            ));
+        }
+
+        let mut package = local.package.clone();
+        package.insert("id".to_owned(), old.id().repr().into());
+        let package: Package = serde_json::from_value(package.into())
+            .map_err(|err| lockfile_error(&manifest_path, err))?;
+
+        // Dependencies can only resolve to packages with one of these names.
+        let dep_names: HashSet<_> = local
+            .dependencies
+            .iter()
+            .map(|dep| dep.name.as_str())
+            .collect();
+        let mut candidates: HashMap<_, Vec<_>> = HashMap::new();
+        for package in self.packages() {
+            if dep_names.contains(package.name()) {
+                candidates.entry(package.name()).or_default().push(package);
+            }
+        }
+
+        // The manifest dependencies are serialized in order, so they line up with the
+        // deserialized ones.
+        let mut links: Vec<(PackageMetadata<'_>, &str, String, Vec<_>)> = vec![];
+        for (manifest_dep, dep) in local.dependencies.iter().zip(&package.dependencies) {
+            let to = old
+                .direct_links()
+                .map(|link| link.to())
+                .find(|to| manifest_dep.matches(*to))
+                .or_else(|| {
+                    candidates
+                        .get(manifest_dep.name.as_str())
+                        .into_iter()
+                        .flatten()
+                        .filter(|candidate| manifest_dep.matches(**candidate))
+                        .max_by(|a, b| a.version().cmp(b.version()))
+                        .copied()
+                })
+                .ok_or_else(|| {
+                    lockfile_error(
+                        &manifest_path,
+                        format!(
+                            "dependency '{}' doesn't match any package in the graph \
+                             (Cargo.lock may need to be updated)",
+                            manifest_dep.rename.as_ref().unwrap_or(&manifest_dep.name)
+                        ),
+                    )
+                })?;
+            match links
+                .iter_mut()
+                .find(|(link_to, ..)| link_to.id() == to.id())
+            {
+                Some((_, _, _, deps)) => deps.push((dep, manifest_dep.extra())),
+                None => {
+                    let (dep_name, resolved_name) = match &manifest_dep.rename {
+                        Some(rename) => (rename.as_str(), rename.replace('-', "_")),
+                        None => (manifest_dep.name.as_str(), lib_name(to)),
+                    };
+                    links.push((
+                        to,
+                        dep_name,
+                        resolved_name,
+                        vec![(dep, manifest_dep.extra())],
+                    ));
+                }
+            }
+        }
+        // Strings in the updated package aren't shared with the rest of the graph, but there are
+        // few of them.
+        let interner = Interner::new();
+        let links = links
+            .into_iter()
+            .map(|(to, dep_name, resolved_name, deps)| {
+                let link = PackageLinkImpl::new(
+                    old.id(),
+                    dep_name,
+                    &resolved_name,
+                    deps.iter().map(|(dep, extra)| (*dep, extra)),
+                    &interner,
+                )?;
+                Ok((to.package_ix(), link))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let package_id = old.id().clone();
+        let package_ix = old.package_ix();
+        let source = self.data.packages[&package_id].source.clone();
+        let metadata =
+            PackageMetadataImpl::new(&package_id, package_ix, source, package, &interner)?;
+
+        // Everything has been validated: patch the graph.
+        let mut old_edges: Vec<_> = self
+            .dep_graph
+            .edges_directed(package_ix, Outgoing)
+            .map(|edge| edge.id())
+            .collect();
+        // Removing an edge moves the last edge into its place, so remove the highest indexes
+        // first.
+        old_edges.sort_unstable_by(|a, b| b.cmp(a));
+        for edge_ix in old_edges {
+            self.dep_graph.remove_edge(edge_ix);
+        }
+        for (to_ix, link) in links {
+            self.dep_graph.update_edge(package_ix, to_ix, link);
+        }
+        self.data.packages.insert(package_id, metadata);
+
+        // The cycles and the feature graph depend on the dependency graph, so they must be
+        // recomputed.
+        self.sccs = OnceCell::new();
+        self.feature_graph = OnceCell::new();
+        Ok(())
+    }
+}
+
+impl CargoMetadata {
+    /// Synthesizes metadata from the `Cargo.lock` and manifests of the workspace at the given
+    /// root, without running `cargo metadata`.
+    ///
+    /// This is meant for environments where running `cargo` is impossible, such as sandboxed
+    /// audit tooling or server-side analysis of uploaded repositories. Nothing is downloaded, and
+    /// the only files read are the lockfile and the manifests and target sources of workspace
+    /// members and their path dependencies.
+    ///
+    /// `workspace_root` is the directory containing the root `Cargo.toml` and `Cargo.lock`. The
+    /// lockfile must be up-to-date with the manifests.
+    ///
+    /// Workspace members and path dependencies are described by their manifests, so their
+    /// dependencies, features and build targets match what `cargo metadata` would report. Other
+    /// packages are only described by the lockfile, so compared to `cargo metadata`:
+    /// * each of their dependencies is a normal, non-optional dependency on the exact version in
+    ///   the lockfile
+    /// * they have no named features, and a single library target
+    /// * their manifest paths are placeholders relative to the workspace root
+    ///
+    /// This method is available if the `lockfile` feature is enabled.
+    pub fn from_lockfile(workspace_root: impl AsRef<Utf8Path>) -> Result<Self, Error> {
+        let workspace_root = workspace_root.as_ref();
+        let workspace_root = workspace_root
+            .canonicalize_utf8()
+            .map_err(|err| lockfile_error(workspace_root, err))?;
+        let json = synthesize_metadata(&workspace_root)?;
+        CargoMetadata::from_json_value(json).map_err(Error::MetadataParseError)
+    }
+}
+
+fn synthesize_metadata(workspace_root: &Utf8Path) -> Result<JsonValue, Error> {
+    let root_manifest_path = workspace_root.join("Cargo.toml");
+    let root_manifest = read_toml(&root_manifest_path)?;
+    let workspace_table = root_manifest.get("workspace").and_then(TomlValue::as_table);
+    let workspace = WorkspaceManifest::new(workspace_root, workspace_table);
+
+    // Find the explicitly listed workspace members.
+    let mut members = HashSet::new();
+    let mut excludes = vec![];
+    if root_manifest.contains_key("package") {
+        members.insert(workspace_root.to_owned());
+    } else if workspace_table.is_none() {
+        return Err(lockfile_error(
+            &root_manifest_path,
+            "manifest has neither a [package] nor a [workspace] section",
+        ));
+    }
+    if let Some(workspace_table) = workspace_table {
+        for pattern in string_list(workspace_table.get("members")) {
+            members.extend(expand_member(workspace_root, &pattern));
+        }
+        excludes.extend(
+            string_list(workspace_table.get("exclude"))
+                .into_iter()
+                .map(|exclude| normalize_path(&workspace_root.join(exclude))),
+        );
+    }
+
+    // Read the manifests of all members and their path dependencies. Path dependencies within the
+    // workspace root are members as well. Packages substituted in through `[patch]` or `[replace]`
+    // are read too, but aren't members unless they're depended on through a path.
+    let mut local_packages = vec![];
+    let mut seen = HashSet::new();
+    let mut queue: VecDeque<_> = members.iter().cloned().collect();
+    queue.extend(
+        patch_entries(&root_manifest, &workspace, &root_manifest_path)?
+            .into_iter()
+            .filter_map(|entry| entry.dependency.path),
+    );
+    while let Some(dir) = queue.pop_front() {
+        if !seen.insert(dir.clone()) {
+            continue;
+        }
+        let package = LocalPackage::read(&dir, &workspace)?;
+        for path in &package.path_dependencies {
+            if workspace_table.is_some()
+                && path.starts_with(workspace_root)
+                && !excludes.iter().any(|exclude| path.starts_with(exclude))
+            {
+                members.insert(path.clone());
+            }
+            queue.push_back(path.clone());
+        }
+        local_packages.push(package);
+    }
+
+    let lockfile_path = workspace_root.join("Cargo.lock");
+    let lockfile = Lockfile::read(&lockfile_path, &local_packages)?;
+
+    let mut packages = vec![];
+    let mut nodes = vec![];
+    for lock_package in &lockfile.packages {
+        let deps = lock_package
+            .dependencies
+            .iter()
+            .map(|dep| lockfile.resolve_dependency(lock_package, dep))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut node_deps = vec![];
+        let package = match lock_package.local {
+            Some(local_ix) => {
+                let local = &local_packages[local_ix];
+                for dep in deps {
+                    let manifest_dep = local
+                        .dependencies
+                        .iter()
+                        .find(|manifest_dep| {
+                            manifest_dep.name == dep.name
+                                && cargo_version_matches(&manifest_dep.req, &dep.version)
+                        })
+                        .ok_or_else(|| {
+                            lockfile_error(
+                                &lockfile_path,
+                                format!(
+                                    "package '{}' depends on '{}', which isn't in its manifest \
+                                     (Cargo.lock may be out of date)",
+                                    lock_package.id, dep.id
+                                ),
+                            )
+                        })?;
+                    let resolved_name = match &manifest_dep.rename {
+                        Some(rename) => rename.replace('-', "_"),
+                        None => lockfile.lib_name(dep, &local_packages),
+                    };
+                    node_deps.push((resolved_name, dep));
+                }
+                let mut package = local.package.clone();
+                package.insert("id".to_owned(), lock_package.id.clone().into());
+                package.into()
+            }
+            None => {
+                for dep in &deps {
+                    node_deps.push((lockfile.lib_name(dep, &local_packages), *dep));
+                }
+                external_package(lock_package, &deps)
+            }
+        };
+        packages.push(package);
+
+        let deps: Vec<_> = node_deps
+            .iter()
+            .map(|(resolved_name, dep)| {
+                json!({
+                    "name": resolved_name,
+                    "pkg": dep.id,
+                    "dep_kinds": [],
+                })
+            })
+            .collect();
+        let dependencies: Vec<_> = node_deps.iter().map(|(_, dep)| &dep.id).collect();
+        nodes.push(json!({
+            "id": lock_package.id,
+            "deps": deps,
+            "dependencies": dependencies,
+            "features": [],
+        }));
+    }
+
+    // With no default-members key, the default member is the root package if there is one, and
+    // every member otherwise.
+    let default_members: Option<HashSet<_>> = workspace_table
+        .and_then(|table| table.get("default-members"))
+        .map(|patterns| {
+            string_list(Some(patterns))
+                .into_iter()
+                .flat_map(|pattern| expand_member(workspace_root, &pattern))
+                .collect()
+        })
+        .or_else(|| {
+            root_manifest
+                .contains_key("package")
+                .then(|| iter::once(workspace_root.to_owned()).collect())
+        });
+
+    let mut workspace_members = vec![];
+    let mut workspace_default_members = vec![];
+    for (local_ix, local) in local_packages.iter().enumerate() {
+        if !members.contains(&local.dir) {
+            continue;
+        }
+        let lock_package = lockfile
+            .packages
+            .iter()
+            .find(|lock_package| lock_package.local == Some(local_ix))
+            .ok_or_else(|| {
+                lockfile_error(
+                    &lockfile_path,
+                    format!(
+                        "workspace member '{}' at {} not found (Cargo.lock may be out of date)",
+                        local.name, local.dir
+                    ),
+                )
+            })?;
+        workspace_members.push(&lock_package.id);
+        if default_members
+            .as_ref()
+            .map_or(true, |default_members| default_members.contains(&local.dir))
+        {
+            workspace_default_members.push(&lock_package.id);
+        }
+    }
+
+    let workspace_metadata = workspace_table
+        .and_then(|table| table.get("metadata"))
+        .map_or(JsonValue::Null, toml_to_json);
+
+    Ok(json!({
+        "packages": packages,
+        "workspace_members": workspace_members,
+        "workspace_default_members": workspace_default_members,
+        "resolve": {
+            "nodes": nodes,
+            "root": null,
+        },
+        "workspace_root": workspace_root,
+        "target_directory": workspace_root.join("target"),
+        "metadata": workspace_metadata,
+        "version": 1,
+    }))
+}
+
+/// The parts of the root manifest's `[workspace]` section that members can inherit from.
+pub(super) struct WorkspaceManifest<'a> {
+    pub(super) root: &'a Utf8Path,
+    package: Table,
+    pub(super) dependencies: Table,
+}
+
+impl<'a> WorkspaceManifest<'a> {
+    pub(super) fn new(root: &'a Utf8Path, workspace_table: Option<&Table>) -> Self {
+        Self {
+            root,
+            package: sub_table(workspace_table, "package"),
+            dependencies: sub_table(workspace_table, "dependencies"),
+        }
+    }
+}
+
+/// A package whose manifest is available: either a workspace member or a path dependency.
+struct LocalPackage {
+    dir: Utf8PathBuf,
+    name: String,
+    version: Version,
+    lib_name: Option<String>,
+    dependencies: Vec<ManifestDependency>,
+    path_dependencies: Vec<Utf8PathBuf>,
+    // The package in `cargo metadata` format, without an ID.
+    package: JsonMap<String, JsonValue>,
+}
+
+impl LocalPackage {
+    fn read(dir: &Utf8Path, workspace: &WorkspaceManifest<'_>) -> Result<Self, Error> {
+        let manifest_path = dir.join("Cargo.toml");
+        let manifest = read_toml(&manifest_path)?;
+        let package_table = manifest
+            .get("package")
+            .and_then(TomlValue::as_table)
+            .ok_or_else(|| lockfile_error(&manifest_path, "manifest has no [package] section"))?;
+
+        // Resolves a [package] field, following inheritance from [workspace.package].
+        let field = |key: &str| -> Result<Option<&TomlValue>, Error> {
+            match package_table.get(key) {
+                Some(TomlValue::Table(table)) if is_workspace_inherited(table) => {
+                    workspace.package.get(key).map(Some).ok_or_else(|| {
+                        lockfile_error(
+                            &manifest_path,
+                            format!("package.{} is inherited, but not set by the workspace", key),
+                        )
+                    })
+                }
+                other => Ok(other),
+            }
+        };
+        let string_field = |key: &str| -> Result<Option<&str>, Error> {
+            Ok(field(key)?.and_then(TomlValue::as_str))
+        };
+
+        let name = string_field("name")?
+            .ok_or_else(|| lockfile_error(&manifest_path, "package.name not specified"))?
+            .to_owned();
+        let version = Version::parse(string_field("version")?.unwrap_or("0.0.0"))
+            .map_err(|err| lockfile_error(&manifest_path, err))?;
+        let edition = string_field("edition")?.unwrap_or("2015");
+
+        let dependencies = raw_dependencies(&manifest)
+            .into_iter()
+            .map(|raw| {
+                ManifestDependency::new(
+                    raw.key,
+                    raw.value,
+                    raw.kind,
+                    raw.target,
+                    dir,
+                    workspace,
+                    &manifest_path,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut path_dependencies: Vec<_> = dependencies
+            .iter()
+            .filter_map(|dep| dep.path.clone())
+            .collect();
+        path_dependencies.sort();
+        path_dependencies.dedup();
+
+        let targets = Targets::new(dir, &manifest, package_table, &name, edition);
+
+        let publish = match field("publish")? {
+            Some(TomlValue::Boolean(false)) => json!([]),
+            Some(value @ TomlValue::Array(_)) => toml_to_json(value),
+            _ => JsonValue::Null,
+        };
+        let json_string = |key: &str| -> Result<JsonValue, Error> {
+            Ok(string_field(key)?.map_or(JsonValue::Null, |value| value.into()))
+        };
+        let json_list =
+            |key: &str| -> Result<JsonValue, Error> { Ok(string_list(field(key)?).into()) };
+
+        let package = json!({
+            "name": name,
+            "version": version.to_string(),
+            "authors": json_list("authors")?,
+            "source": null,
+            "description": json_string("description")?,
+            "dependencies": dependencies.iter().map(|dep| &dep.json).collect::<Vec<_>>(),
+            "license": json_string("license")?,
+            "license_file": json_string("license-file")?,
+            "targets": targets.targets,
+            "features": manifest.get("features").map_or(json!({}), toml_to_json),
+            "manifest_path": manifest_path,
+            "categories": json_list("categories")?,
+            "keywords": json_list("keywords")?,
+            "readme": json_string("readme")?,
+            "repository": json_string("repository")?,
+            "homepage": json_string("homepage")?,
+            "documentation": json_string("documentation")?,
+            "edition": edition,
+            "metadata": package_table.get("metadata").map_or(JsonValue::Null, toml_to_json),
+            "links": json_string("links")?,
+            "publish": publish,
+            "default_run": json_string("default-run")?,
+            "rust_version": json_string("rust-version")?,
+        });
+        let package = match package {
+            JsonValue::Object(package) => package,
+            _ => unreachable!("json! with braces always produces an object"),
+        };
+
+        Ok(Self {
+            dir: dir.to_owned(),
+            name,
+            version,
+            lib_name: targets.lib_name,
+            dependencies,
+            path_dependencies,
+            package,
+        })
+    }
+}
+
+/// A dependency entry in a manifest, before it's been parsed.
+pub(super) struct RawDependency<'a> {
+    pub(super) key: &'a str,
+    pub(super) value: &'a TomlValue,
+    // "dev" or "build", as in the `kind` field in `cargo metadata` output.
+    pub(super) kind: Option<&'static str>,
+    pub(super) target: Option<&'a str>,
+}
+
+/// Returns all the dependency entries in a manifest, including platform-specific ones.
+pub(super) fn raw_dependencies(manifest: &Table) -> Vec<RawDependency<'_>> {
+    let mut dep_tables = vec![(manifest, None)];
+    if let Some(targets) = manifest.get("target").and_then(TomlValue::as_table) {
+        for (spec, target_table) in targets {
+            if let Some(target_table) = target_table.as_table() {
+                dep_tables.push((target_table, Some(spec.as_str())));
+            }
+        }
+    }
+
+    let mut raw = vec![];
+    for (table, target) in dep_tables {
+        for (keys, kind) in [
+            (&["dependencies"][..], None),
+            (&["dev-dependencies", "dev_dependencies"][..], Some("dev")),
+            (
+                &["build-dependencies", "build_dependencies"][..],
+                Some("build"),
+            ),
+        ] {
+            for key in keys {
+                if let Some(deps) = table.get(*key).and_then(TomlValue::as_table) {
+                    raw.extend(deps.iter().map(|(dep_key, value)| RawDependency {
+                        key: dep_key,
+                        value,
+                        kind,
+                        target,
+                    }));
+                }
+            }
+        }
+    }
+    raw
+}
+
+/// A dependency listed in a manifest.
+pub(super) struct ManifestDependency {
+    // The name of the package depended on.
+    pub(super) name: String,
+    pub(super) rename: Option<String>,
+    pub(super) req: VersionReq,
+    pub(super) path: Option<Utf8PathBuf>,
+    // The source, for dependencies that aren't on a path or an alternate registry.
+    source: Option<String>,
+    // The dependency in `cargo metadata` format.
+    json: JsonValue,
+}
+
+impl ManifestDependency {
+    pub(super) fn new(
+        key: &str,
+        value: &TomlValue,
+        kind: Option<&str>,
+        target: Option<&str>,
+        dir: &Utf8Path,
+        workspace: &WorkspaceManifest<'_>,
+        manifest_path: &Utf8Path,
+    ) -> Result<Self, Error> {
+        let mut table = Table::new();
+        // The directory that `path` is relative to.
+        let mut base_dir = dir;
+        match value {
+            TomlValue::String(req) => {
+                table.insert("version".to_owned(), req.as_str().into());
+            }
+            TomlValue::Table(local) if is_workspace_inherited(local) => {
+                match workspace.dependencies.get(key) {
+                    Some(TomlValue::String(req)) => {
+                        table.insert("version".to_owned(), req.as_str().into());
+                    }
+                    Some(TomlValue::Table(inherited)) => table.extend(inherited.clone()),
+                    _ => {
+                        return Err(lockfile_error(
+                            manifest_path,
+                            format!(
+                                "dependency '{}' is inherited, but not set by the workspace",
+                                key
+                            ),
+                        ))
+                    }
+                }
+                base_dir = workspace.root;
+                // Features are additive, and the other keys override the workspace's.
+                let mut features = string_list(table.get("features"));
+                features.extend(string_list(local.get("features")));
+                table.insert("features".to_owned(), features.into());
+                for local_key in ["optional", "default-features", "default_features"] {
+                    if let Some(local_value) = local.get(local_key) {
+                        table.insert(local_key.to_owned(), local_value.clone());
+                    }
+                }
+            }
+            TomlValue::Table(local) => table.extend(local.clone()),
+            _ => {
+                return Err(lockfile_error(
+                    manifest_path,
+                    format!("dependency '{}' is neither a string nor a table", key),
+                ))
+            }
+        }
+
+        let (name, rename) = match table.get("package").and_then(TomlValue::as_str) {
+            Some(package) => (package.to_owned(), Some(key.to_owned())),
+            None => (key.to_owned(), None),
+        };
+        let req = table
+            .get("version")
+            .and_then(TomlValue::as_str)
+            .unwrap_or("*");
+        let req = VersionReq::parse(req).map_err(|err| lockfile_error(manifest_path, err))?;
+        let path = table
+            .get("path")
+            .and_then(TomlValue::as_str)
+            .map(|path| normalize_path(&base_dir.join(path)));
+        let registry = table.get("registry").and_then(TomlValue::as_str);
+
+        let source = if path.is_some() {
+            None
+        } else if let Some(git) = table.get("git").and_then(TomlValue::as_str) {
+            let mut source = format!("git+{}", git);
+            for reference in ["branch", "tag", "rev"] {
+                if let Some(value) = table.get(reference).and_then(TomlValue::as_str) {
+                    source.push_str(&format!("?{}={}", reference, value));
+                }
+            }
+            Some(source)
+        } else if registry.is_some() {
+            // The index URL for alternate registries is in Cargo's configuration, which isn't
+            // read here.
+            None
+        } else {
+            Some(CRATES_IO_SOURCE.to_owned())
+        };
+
+        // Only normal dependencies can be public.
+        let public = kind.is_none()
+            && table
+                .get("public")
+                .and_then(TomlValue::as_bool)
+                .unwrap_or(false);
+        let artifact = match table.get("artifact") {
+            Some(artifact) => {
+                let kinds = match artifact {
+                    TomlValue::String(kind) => vec![kind.clone()],
+                    kinds => string_list(Some(kinds)),
+                };
+                Some(json!({
+                    "kinds": kinds,
+                    "lib": table.get("lib").and_then(TomlValue::as_bool).unwrap_or(false),
+                    "target": table.get("target").and_then(TomlValue::as_str),
+                }))
+            }
+            None => None,
+        };
+        let uses_default_features = table
+            .get("default-features")
+            .or_else(|| table.get("default_features"))
+            .and_then(TomlValue::as_bool)
+            .unwrap_or(true);
+
+        let json = json!({
+            "name": name,
+            "source": &source,
+            "req": req.to_string(),
+            "kind": kind,
+            "rename": rename,
+            "optional": table.get("optional").and_then(TomlValue::as_bool).unwrap_or(false),
+            "uses_default_features": uses_default_features,
+            "public": public,
+            "artifact": artifact,
+            "features": string_list(table.get("features")),
+            "target": target,
+            "registry": registry,
+            "path": path,
+        });
+
+        Ok(Self {
+            name,
+            rename,
+            req,
+            path,
+            source,
+            json,
+        })
+    }
+
+    /// Returns the features enabled by this dependency.
+    pub(super) fn features(&self) -> Vec<String> {
+        self.json["features"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|feature| feature.as_str().map(|feature| feature.to_owned()))
+            .collect()
+    }
+
+    /// Returns true if this dependency is optional.
+    pub(super) fn optional(&self) -> bool {
+        self.json["optional"].as_bool().unwrap_or(false)
+    }
+
+    /// Returns the fields of this dependency that aren't part of `cargo_metadata`'s `Dependency`.
+    pub(super) fn extra(&self) -> DependencyExtra {
+        let artifact = match &self.json["artifact"] {
+            JsonValue::Null => None,
+            artifact => ArtifactDependencyImpl::deserialize(artifact).ok(),
+        };
+        DependencyExtra {
+            public: self.json["public"].as_bool().unwrap_or(false),
+            artifact,
+        }
+    }
+
+    /// Returns true if this dependency uses the default features of the package depended on.
+    pub(super) fn uses_default_features(&self) -> bool {
+        self.json["uses_default_features"].as_bool().unwrap_or(true)
+    }
+
+    /// Returns true if this dependency can be resolved to the given package.
+    pub(super) fn matches(&self, package: PackageMetadata<'_>) -> bool {
+        if package.name() != self.name || !cargo_version_matches(&self.req, package.version()) {
+            return false;
+        }
+        match (&self.path, &self.source) {
+            (Some(path), _) => package.manifest_path().parent() == Some(path.as_path()),
+            (None, Some(source)) if source == CRATES_IO_SOURCE => package.source().is_crates_io(),
+            // Git sources in package IDs are followed by the commit hash.
+            (None, Some(source)) => package
+                .source()
+                .external_source()
+                .map_or(false, |external| external.split('#').next() == Some(source)),
+            // This is an alternate registry.
+            (None, None) => package
+                .source()
+                .external_source()
+                .map_or(false, |external| !external.starts_with("git+")),
+        }
+    }
+}
+
+/// The build targets of a local package, in `cargo metadata` format.
+struct Targets {
+    targets: Vec<JsonValue>,
+    lib_name: Option<String>,
+}
+
+impl Targets {
+    fn new(
+        dir: &Utf8Path,
+        manifest: &Table,
+        package_table: &Table,
+        package_name: &str,
+        edition: &str,
+    ) -> Self {
+        let mut targets = vec![];
+
+        let lib_table = manifest.get("lib").and_then(TomlValue::as_table);
+        let lib_path = match lib_table.and_then(|lib| lib.get("path")) {
+            Some(path) => path.as_str().map(|path| dir.join(path)),
+            None => {
+                Some(dir.join("src/lib.rs")).filter(|path| lib_table.is_some() || path.is_file())
+            }
+        };
+        let lib_name = lib_path.map(|lib_path| {
+            let lib = lib_table.cloned().unwrap_or_default();
+            let lib_name = lib
+                .get("name")
+                .and_then(TomlValue::as_str)
+                .map_or_else(|| package_name.replace('-', "_"), |name| name.to_owned());
+            let proc_macro = lib
+                .get("proc-macro")
+                .or_else(|| lib.get("proc_macro"))
+                .and_then(TomlValue::as_bool)
+                .unwrap_or(false);
+            let crate_types = if proc_macro {
+                vec!["proc-macro".to_owned()]
+            } else {
+                let crate_types =
+                    string_list(lib.get("crate-type").or_else(|| lib.get("crate_type")));
+                if crate_types.is_empty() {
+                    vec!["lib".to_owned()]
+                } else {
+                    crate_types
+                }
+            };
+            targets.push(json!({
+                "name": lib_name,
+                "kind": crate_types,
+                "crate_types": crate_types,
+                "required-features": [],
+                "src_path": lib_path,
+                "edition": lib.get("edition").and_then(TomlValue::as_str).unwrap_or(edition),
+                "doctest": bool_key(&lib, "doctest", true),
+                "test": bool_key(&lib, "test", true),
+                "doc": bool_key(&lib, "doc", true),
+            }));
+            lib_name
+        });
+
+        for (key, kind, subdir) in [
+            ("bin", "bin", "src/bin"),
+            ("example", "example", "examples"),
+            ("test", "test", "tests"),
+            ("bench", "bench", "benches"),
+        ] {
+            let mut found = vec![];
+            for target in manifest
+                .get(key)
+                .and_then(TomlValue::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(TomlValue::as_table)
+            {
+                let name = match target.get("name").and_then(TomlValue::as_str) {
+                    Some(name) => name,
+                    None => continue,
+                };
+                let path = match target.get("path").and_then(TomlValue::as_str) {
+                    Some(path) => dir.join(path),
+                    None => {
+                        let main_rs = dir.join("src/main.rs");
+                        let dir_main_rs = dir.join(subdir).join(name).join("main.rs");
+                        if kind == "bin" && name == package_name && main_rs.is_file() {
+                            main_rs
+                        } else if dir_main_rs.is_file() {
+                            dir_main_rs
+                        } else {
+                            dir.join(subdir).join(format!("{}.rs", name))
+                        }
+                    }
+                };
+                found.push((name.to_owned(), path, target.clone()));
+            }
+
+            let auto_key = format!("auto{}s", if kind == "bench" { "benche" } else { kind });
+            if bool_key(package_table, &auto_key, true) {
+                let mut auto = discover_targets(&dir.join(subdir));
+                if kind == "bin" {
+                    let main_rs = dir.join("src/main.rs");
+                    if main_rs.is_file() {
+                        auto.insert(0, (package_name.to_owned(), main_rs));
+                    }
+                }
+                for (name, path) in auto {
+                    // Explicitly specified targets take precedence.
+                    if !found.iter().any(|(found_name, found_path, _)| {
+                        found_name == &name || found_path == &path
+                    }) {
+                        found.push((name, path, Table::new()));
+                    }
+                }
+            }
+
+            for (name, path, target) in found {
+                targets.push(json!({
+                    "name": name,
+                    "kind": [kind],
+                    "crate_types": ["bin"],
+                    "required-features": string_list(target.get("required-features")),
+                    "src_path": path,
+                    "edition": target.get("edition").and_then(TomlValue::as_str).unwrap_or(edition),
+                    "doctest": false,
+                    "test": bool_key(&target, "test", kind == "bin" || kind == "test"),
+                    "doc": bool_key(&target, "doc", kind == "bin"),
+                }));
+            }
+        }
+
+        let build_path = match package_table.get("build") {
+            Some(TomlValue::String(path)) => Some(dir.join(path)),
+            Some(_) => None,
+            None => Some(dir.join("build.rs")).filter(|path| path.is_file()),
+        };
+        if let Some(build_path) = build_path {
+            targets.push(json!({
+                "name": "build-script-build",
+                "kind": ["custom-build"],
+                "crate_types": ["bin"],
+                "required-features": [],
+                "src_path": build_path,
+                "edition": edition,
+                "doctest": false,
+                "test": false,
+                "doc": false,
+            }));
+        }
+
+        Self { targets, lib_name }
+    }
+}
+
+/// The parsed contents of a `Cargo.lock`.
+struct Lockfile {
+    packages: Vec<LockPackage>,
+    by_name: HashMap<String, Vec<usize>>,
+}
+
+struct LockPackage {
+    id: String,
+    name: String,
+    version: Version,
+    source: Option<String>,
+    dependencies: Vec<String>,
+    // The index into the local packages, for packages without a source.
+    local: Option<usize>,
+}
+
+impl Lockfile {
+    fn read(path: &Utf8Path, local_packages: &[LocalPackage]) -> Result<Self, Error> {
+        let lockfile = read_toml(path)?;
+        let mut packages = vec![];
+        let mut by_name: HashMap<_, Vec<_>> = HashMap::new();
+
+        for package in lockfile
+            .get("package")
+            .and_then(TomlValue::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let name = package.get("name").and_then(TomlValue::as_str);
+            let version = package.get("version").and_then(TomlValue::as_str);
+            let (name, version) = match (name, version) {
+                (Some(name), Some(version)) => (name.to_owned(), version),
+                _ => {
+                    return Err(lockfile_error(
+                        path,
+                        "[[package]] entry without a name or version",
+                    ))
+                }
+            };
+            let version = Version::parse(version).map_err(|err| lockfile_error(path, err))?;
+            let source = package
+                .get("source")
+                .and_then(TomlValue::as_str)
+                .map(|source| source.to_owned());
+
+            let (id, local) = match &source {
+                Some(source) => (format!("{} {} ({})", name, version, source), None),
+                None => {
+                    // Packages without a source are path dependencies.
+                    let local_ix = local_packages
+                        .iter()
+                        .position(|local| local.name == name && local.version == version)
+                        .ok_or_else(|| {
+                            lockfile_error(
+                                path,
+                                format!(
+                                    "path package '{} {}' not found in the workspace or its \
+                                     path dependencies",
+                                    name, version
+                                ),
+                            )
+                        })?;
+                    let id = format!(
+                        "{} {} (path+file://{})",
+                        name, version, local_packages[local_ix].dir
+                    );
+                    (id, Some(local_ix))
+                }
+            };
+
+            by_name
+                .entry(name.clone())
+                .or_default()
+                .push(packages.len());
+            packages.push(LockPackage {
+                id,
+                name,
+                version,
+                source,
+                dependencies: string_list(package.get("dependencies")),
+                local,
+            });
+        }
+
+        Ok(Self { packages, by_name })
+    }
+
+    /// Resolves an entry in a package's `dependencies` list.
+    ///
+    /// Entries are of the form `name [version] [(source)]`, where the version and source are only
+    /// present if they're required to disambiguate the entry.
+    fn resolve_dependency(&self, from: &LockPackage, dep: &str) -> Result<&LockPackage, Error> {
+        let mut parts = dep.splitn(3, ' ');
+        let name = parts.next().unwrap_or_default();
+        let version = parts.next();
+        let source = parts
+            .next()
+            .map(|source| source.trim_start_matches('(').trim_end_matches(')'));
+
+        let mut candidates = self
+            .by_name
+            .get(name)
+            .into_iter()
+            .flatten()
+            .map(|&ix| &self.packages[ix])
+            .filter(|package| {
+                version.map_or(true, |version| package.version.to_string() == version)
+                    && source.map_or(true, |source| package.source.as_deref() == Some(source))
+            });
+        match (candidates.next(), candidates.next()) {
+            (Some(package), None) => Ok(package),
+            (None, _) => Err(Error::PackageGraphConstructError(format!(
+                "{}: dependency '{}' not found in Cargo.lock",
+                from.id, dep
+            ))),
+            (Some(_), Some(_)) => Err(Error::PackageGraphConstructError(format!(
+                "{}: dependency '{}' is ambiguous in Cargo.lock",
+                from.id, dep
+            ))),
+        }
+    }
+
+    /// Returns the name that a package's library is imported as.
+    fn lib_name(&self, package: &LockPackage, local_packages: &[LocalPackage]) -> String {
+        package
+            .local
+            .and_then(|local_ix| local_packages[local_ix].lib_name.clone())
+            .unwrap_or_else(|| package.name.replace('-', "_"))
+    }
+}
+
+/// Returns a package that isn't available locally, in `cargo metadata` format.
+fn external_package(package: &LockPackage, deps: &[&LockPackage]) -> JsonValue {
+    let dependencies: Vec<_> = deps
+        .iter()
+        .map(|dep| {
+            json!({
+                "name": dep.name,
+                "source": dep.source,
+                "req": format!("={}", dep.version),
+                "kind": null,
+                "rename": null,
+                "optional": false,
+                "uses_default_features": true,
+                "features": [],
+                "target": null,
+                "registry": null,
+                "path": null,
+            })
+        })
+        .collect();
+    let dir = format!("{}-{}", package.name, package.version);
+
+    json!({
+        "name": package.name,
+        "version": package.version.to_string(),
+        "id": package.id,
+        "authors": [],
+        "source": package.source,
+        "description": null,
+        "dependencies": dependencies,
+        "license": null,
+        "license_file": null,
+        "targets": [{
+            "name": package.name.replace('-', "_"),
+            "kind": ["lib"],
+            "crate_types": ["lib"],
+            "required-features": [],
+            "src_path": format!("{}/src/lib.rs", dir),
+            "edition": "2015",
+            "doctest": true,
+            "test": true,
+            "doc": true,
+        }],
+        "features": {},
+        "manifest_path": format!("{}/Cargo.toml", dir),
+        "categories": [],
+        "keywords": [],
+        "readme": null,
+        "repository": null,
+        "homepage": null,
+        "documentation": null,
+        "edition": "2015",
+        "metadata": null,
+        "links": null,
+        "publish": null,
+        "default_run": null,
+        "rust_version": null,
+    })
+}
+
+// ---
+// Helper functions
+// ---
+
+/// Returns the name that a package's library is imported as.
+fn lib_name(package: PackageMetadata<'_>) -> String {
+    package
+        .build_target(&BuildTargetId::Library)
+        .map_or(package.name(), |target| target.name())
+        .replace('-', "_")
+}
+
+pub(super) fn lockfile_error(
+    path: &Utf8Path,
+    err: impl Into<Box<dyn error::Error + Send + Sync>>,
+) -> Error {
+    Error::LockfileError(path.to_owned(), err.into())
+}
+
+pub(super) fn read_toml(path: &Utf8Path) -> Result<Table, Error> {
+    let contents = fs::read_to_string(path).map_err(|err| lockfile_error(path, err))?;
+    toml::from_str(&contents).map_err(|err| lockfile_error(path, err))
+}
+
+fn sub_table(table: Option<&Table>, key: &str) -> Table {
+    table
+        .and_then(|table| table.get(key))
+        .and_then(TomlValue::as_table)
+        .cloned()
+        .unwrap_or_default()
+}
+
+pub(super) fn is_workspace_inherited(table: &Table) -> bool {
+    table.get("workspace").and_then(TomlValue::as_bool) == Some(true)
+}
+
+fn bool_key(table: &Table, key: &str, default: bool) -> bool {
+    table
+        .get(key)
+        .and_then(TomlValue::as_bool)
+        .unwrap_or(default)
+}
+
+fn string_list(value: Option<&TomlValue>) -> Vec<String> {
+    value
+        .and_then(TomlValue::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|value| value.as_str().map(|value| value.to_owned()))
+        .collect()
+}
+
+fn toml_to_json(value: &TomlValue) -> JsonValue {
+    match value {
+        TomlValue::String(value) => value.as_str().into(),
+        TomlValue::Integer(value) => (*value).into(),
+        TomlValue::Float(value) => (*value).into(),
+        TomlValue::Boolean(value) => (*value).into(),
+        TomlValue::Datetime(value) => value.to_string().into(),
+        TomlValue::Array(values) => values.iter().map(toml_to_json).collect(),
+        TomlValue::Table(table) => table
+            .iter()
+            .map(|(key, value)| (key.clone(), toml_to_json(value)))
+            .collect::<JsonMap<_, _>>()
+            .into(),
+    }
+}
+
+/// Expands a `[workspace] members` entry, which may contain `*` and `?` wildcards.
+fn expand_member(workspace_root: &Utf8Path, pattern: &str) -> Vec<Utf8PathBuf> {
+    let mut dirs = vec![workspace_root.to_owned()];
+    let mut is_glob = false;
+    for component in pattern.split('/') {
+        if component.contains(['*', '?']) {
+            is_glob = true;
+            let mut matches = vec![];
+            for dir in &dirs {
+                for entry in dir.read_dir_utf8().into_iter().flatten().flatten() {
+                    if entry.path().is_dir()
+                        && wildcard_match(component.as_bytes(), entry.file_name().as_bytes())
+                    {
+                        matches.push(entry.path().to_owned());
+                    }
+                }
+            }
+            matches.sort();
+            dirs = matches;
+        } else {
+            for dir in &mut dirs {
+                dir.push(component);
+            }
+        }
+    }
+    if is_glob {
+        // Like Cargo, ignore directories matched by a glob that don't contain a manifest.
+        dirs.retain(|dir| dir.join("Cargo.toml").is_file());
+    }
+    dirs.iter().map(|dir| normalize_path(dir)).collect()
+}
+
+/// Finds targets in a directory like `examples`: these are either `.rs` files, or directories
+/// containing `main.rs`.
+fn discover_targets(dir: &Utf8Path) -> Vec<(String, Utf8PathBuf)> {
+    let mut targets = vec![];
+    for entry in dir.read_dir_utf8().into_iter().flatten().flatten() {
+        let path = entry.path();
+        if path.is_file() && path.extension() == Some("rs") {
+            if let Some(stem) = path.file_stem() {
+                targets.push((stem.to_owned(), path.to_owned()));
+            }
+        } else if path.join("main.rs").is_file() {
+            targets.push((entry.file_name().to_owned(), path.join("main.rs")));
+        }
+    }
+    targets.sort();
+    targets
+}
+
+/// Resolves `.` and `..` components without touching the file system.
+fn normalize_path(path: &Utf8Path) -> Utf8PathBuf {
+    let mut normalized = Utf8PathBuf::new();
+    for component in path.components() {
+        match component {
+            Utf8Component::CurDir => {}
+            Utf8Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_str()),
+        }
+    }
+    normalized
+}
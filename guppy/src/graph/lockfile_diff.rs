@@ -0,0 +1,396 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Diffs between two versions of `Cargo.lock`, mapped onto a package graph.
+
+use crate::{
+    graph::{lockfile::lockfile_error, IterationOrder, PackageGraph, PackageMetadata},
+    Error,
+};
+use camino::Utf8Path;
+use semver::Version;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    iter,
+};
+use toml::{value::Table, Value as TomlValue};
+
+impl PackageGraph {
+    /// Computes the changes to external packages between two versions of `Cargo.lock`, and maps
+    /// them onto this graph.
+    ///
+    /// `old` and `new` are the contents of the two lockfiles. This graph is expected to correspond
+    /// to `new`: each change records which workspace members in this graph are affected by it.
+    /// This is typically used to review dependency bumps.
+    ///
+    /// For more information, see the documentation for [`LockfileDiff`].
+    ///
+    /// This method is available if the `lockfile` feature is enabled.
+    pub fn diff_lockfiles(&self, old: &str, new: &str) -> Result<LockfileDiff<'_>, Error> {
+        LockfileDiff::new(self, &LockEntries::parse(old)?, &LockEntries::parse(new)?)
+    }
+}
+
+/// The changes to external packages between two versions of `Cargo.lock`.
+///
+/// Packages are matched across the two lockfiles by name and source, the same way
+/// [`PackageGraphDiff`](crate::graph::PackageGraphDiff) matches packages:
+/// * Versions present in both lockfiles are unchanged.
+/// * If a package with a given name and source has exactly one version that's only in the old
+///   lockfile and one that's only in the new lockfile, it is reported as updated.
+/// * Otherwise, versions that are only in the old lockfile are removed, and versions that are only
+///   in the new lockfile are added.
+///
+/// Path dependencies and workspace members, which don't have a source, aren't reported.
+///
+/// Each change is mapped onto the package graph the diff was computed against:
+/// * For added and updated packages, the affected workspace members are those that depend on the
+///   new version in the graph, directly or transitively.
+/// * For removed packages, which generally aren't in the graph, the affected workspace members are
+///   those that depended on the package according to the old lockfile.
+///
+/// Constructed through [`PackageGraph::diff_lockfiles`].
+#[derive(Clone, Debug)]
+pub struct LockfileDiff<'g> {
+    changes: Vec<LockfileChange<'g>>,
+}
+
+impl<'g> LockfileDiff<'g> {
+    fn new(graph: &'g PackageGraph, old: &LockEntries, new: &LockEntries) -> Result<Self, Error> {
+        let old_by_key = old.external_versions();
+        let mut new_by_key = new.external_versions();
+
+        let mut changes = vec![];
+        for ((name, source), old_versions) in old_by_key {
+            let new_versions = new_by_key
+                .remove(&(name.clone(), source.clone()))
+                .unwrap_or_default();
+            let old_only: Vec<_> = old_versions.difference(&new_versions).cloned().collect();
+            let new_only: Vec<_> = new_versions.difference(&old_versions).cloned().collect();
+
+            let kinds: Vec<_> = match (old_only.as_slice(), new_only.as_slice()) {
+                ([old_version], [new_version]) => vec![LockfileChangeKind::Updated {
+                    old: old_version.clone(),
+                    new: new_version.clone(),
+                }],
+                _ => old_only
+                    .into_iter()
+                    .map(|version| LockfileChangeKind::Removed { version })
+                    .chain(
+                        new_only
+                            .into_iter()
+                            .map(|version| LockfileChangeKind::Added { version }),
+                    )
+                    .collect(),
+            };
+            for kind in kinds {
+                changes.push(LockfileChange::new(
+                    graph,
+                    old,
+                    name.clone(),
+                    source.clone(),
+                    kind,
+                )?);
+            }
+        }
+        // Any remaining packages are new.
+        for ((name, source), new_versions) in new_by_key {
+            for version in new_versions {
+                let kind = LockfileChangeKind::Added { version };
+                changes.push(LockfileChange::new(
+                    graph,
+                    old,
+                    name.clone(),
+                    source.clone(),
+                    kind,
+                )?);
+            }
+        }
+
+        changes.sort_by(|a, b| {
+            (&a.name, &a.source, a.kind.sort_version()).cmp(&(
+                &b.name,
+                &b.source,
+                b.kind.sort_version(),
+            ))
+        });
+        Ok(Self { changes })
+    }
+
+    /// Returns true if no external packages changed.
+    pub fn is_unchanged(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Returns all changes, sorted by package name, then source, then version.
+    pub fn changes(&self) -> impl ExactSizeIterator<Item = &LockfileChange<'g>> {
+        self.changes.iter()
+    }
+
+    /// Returns the changes that affect the given workspace member.
+    pub fn changes_affecting<'a>(
+        &'a self,
+        member: PackageMetadata<'g>,
+    ) -> impl Iterator<Item = &'a LockfileChange<'g>> + 'a {
+        self.changes.iter().filter(move |change| {
+            change
+                .affected_members
+                .iter()
+                .any(|affected| affected.id() == member.id())
+        })
+    }
+}
+
+/// A change to a single external package between two versions of `Cargo.lock`.
+///
+/// Returned by [`LockfileDiff::changes`].
+#[derive(Clone, Debug)]
+pub struct LockfileChange<'g> {
+    name: String,
+    source: String,
+    kind: LockfileChangeKind,
+    package: Option<PackageMetadata<'g>>,
+    affected_members: Vec<PackageMetadata<'g>>,
+}
+
+impl<'g> LockfileChange<'g> {
+    fn new(
+        graph: &'g PackageGraph,
+        old: &LockEntries,
+        name: String,
+        source: String,
+        kind: LockfileChangeKind,
+    ) -> Result<Self, Error> {
+        let (package, affected_members) = match &kind {
+            LockfileChangeKind::Added { version }
+            | LockfileChangeKind::Updated { new: version, .. } => {
+                let package = graph
+                    .resolve_package_name(&name)
+                    .packages_in(IterationOrder::Lexical)
+                    .find(|package| {
+                        package.version() == version && package.source().to_string() == source
+                    });
+                let affected_members = match package {
+                    Some(package) => graph
+                        .query_reverse(iter::once(package.id()))?
+                        .resolve()
+                        .workspace_packages()
+                        .packages_in(IterationOrder::Lexical)
+                        .collect(),
+                    None => vec![],
+                };
+                (package, affected_members)
+            }
+            LockfileChangeKind::Removed { version } => {
+                let workspace = graph.workspace();
+                let affected_members = old
+                    .local_dependents(&name, version, &source)
+                    .into_iter()
+                    .filter_map(|member_name| workspace.member_by_name(member_name).ok())
+                    .collect();
+                (None, affected_members)
+            }
+        };
+
+        Ok(Self {
+            name,
+            source,
+            kind,
+            package,
+            affected_members,
+        })
+    }
+
+    /// Returns the name of the package.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the source of the package, as recorded in `Cargo.lock`.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the kind of change.
+    pub fn kind(&self) -> &LockfileChangeKind {
+        &self.kind
+    }
+
+    /// Returns the package in the graph corresponding to the new version, if any.
+    ///
+    /// This is `None` for removed packages, and for added or updated packages that aren't in the
+    /// graph (for example, if the graph doesn't correspond to the new lockfile).
+    pub fn package(&self) -> Option<PackageMetadata<'g>> {
+        self.package
+    }
+
+    /// Returns the workspace members affected by this change, sorted by name.
+    pub fn affected_members(&self) -> impl ExactSizeIterator<Item = PackageMetadata<'g>> + '_ {
+        self.affected_members.iter().copied()
+    }
+}
+
+/// The kind of a [`LockfileChange`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LockfileChangeKind {
+    /// The package was added.
+    Added {
+        /// The version of the package in the new lockfile.
+        version: Version,
+    },
+
+    /// The package was removed.
+    Removed {
+        /// The version of the package in the old lockfile.
+        version: Version,
+    },
+
+    /// The package was updated from one version to another.
+    Updated {
+        /// The version of the package in the old lockfile.
+        old: Version,
+
+        /// The version of the package in the new lockfile.
+        new: Version,
+    },
+}
+
+impl LockfileChangeKind {
+    fn sort_version(&self) -> &Version {
+        match self {
+            LockfileChangeKind::Added { version } | LockfileChangeKind::Removed { version } => {
+                version
+            }
+            LockfileChangeKind::Updated { old, .. } => old,
+        }
+    }
+}
+
+// ---
+// Helper functions
+// ---
+
+/// The `[[package]]` entries of a `Cargo.lock`.
+struct LockEntries {
+    entries: Vec<LockEntry>,
+}
+
+struct LockEntry {
+    name: String,
+    version: Version,
+    source: Option<String>,
+    dependencies: Vec<String>,
+}
+
+impl LockEntries {
+    fn parse(contents: &str) -> Result<Self, Error> {
+        let path = Utf8Path::new("Cargo.lock");
+        let lockfile: Table = toml::from_str(contents).map_err(|err| lockfile_error(path, err))?;
+
+        let mut entries = vec![];
+        for package in lockfile
+            .get("package")
+            .and_then(TomlValue::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let name = package.get("name").and_then(TomlValue::as_str);
+            let version = package.get("version").and_then(TomlValue::as_str);
+            let (name, version) = match (name, version) {
+                (Some(name), Some(version)) => (name.to_owned(), version),
+                _ => {
+                    return Err(lockfile_error(
+                        path,
+                        "[[package]] entry without a name or version",
+                    ))
+                }
+            };
+            entries.push(LockEntry {
+                name,
+                version: Version::parse(version).map_err(|err| lockfile_error(path, err))?,
+                source: package
+                    .get("source")
+                    .and_then(TomlValue::as_str)
+                    .map(|source| source.to_owned()),
+                dependencies: package
+                    .get("dependencies")
+                    .and_then(TomlValue::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|dep| dep.as_str().map(|dep| dep.to_owned()))
+                    .collect(),
+            });
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Returns the versions of external packages, grouped by name and source.
+    fn external_versions(&self) -> BTreeMap<(String, String), BTreeSet<Version>> {
+        let mut by_key: BTreeMap<_, BTreeSet<_>> = BTreeMap::new();
+        for entry in &self.entries {
+            if let Some(source) = &entry.source {
+                by_key
+                    .entry((entry.name.clone(), source.clone()))
+                    .or_default()
+                    .insert(entry.version.clone());
+            }
+        }
+        by_key
+    }
+
+    /// Returns the names of packages without a source that transitively depend on the given
+    /// package.
+    fn local_dependents(&self, name: &str, version: &Version, source: &str) -> BTreeSet<&str> {
+        let mut dependents: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (from_ix, entry) in self.entries.iter().enumerate() {
+            for dep in &entry.dependencies {
+                if let Some(to_ix) = self.resolve_dependency(dep) {
+                    dependents.entry(to_ix).or_default().push(from_ix);
+                }
+            }
+        }
+
+        let start = self.entries.iter().position(|entry| {
+            entry.name == name
+                && &entry.version == version
+                && entry.source.as_deref() == Some(source)
+        });
+        let mut visited: HashSet<_> = start.into_iter().collect();
+        let mut queue: VecDeque<_> = start.into_iter().collect();
+        let mut local = BTreeSet::new();
+        while let Some(ix) = queue.pop_front() {
+            for &from_ix in dependents.get(&ix).into_iter().flatten() {
+                if visited.insert(from_ix) {
+                    let from = &self.entries[from_ix];
+                    if from.source.is_none() {
+                        local.insert(from.name.as_str());
+                    }
+                    queue.push_back(from_ix);
+                }
+            }
+        }
+        local
+    }
+
+    /// Resolves an entry in a package's `dependencies` list, of the form
+    /// `name [version] [(source)]`.
+    fn resolve_dependency(&self, dep: &str) -> Option<usize> {
+        let mut parts = dep.splitn(3, ' ');
+        let name = parts.next().unwrap_or_default();
+        let version = parts.next();
+        let source = parts
+            .next()
+            .map(|source| source.trim_start_matches('(').trim_end_matches(')'));
+
+        let mut candidates = self.entries.iter().enumerate().filter(|(_, entry)| {
+            entry.name == name
+                && version.map_or(true, |version| entry.version.to_string() == version)
+                && source.map_or(true, |source| entry.source.as_deref() == Some(source))
+        });
+        match (candidates.next(), candidates.next()) {
+            (Some((ix, _)), None) => Some(ix),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,297 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Merging the metadata for several workspaces into a single package graph.
+
+use crate::{
+    graph::{PackageGraph, PackageSet},
+    metadata_compat::common_ancestor,
+    CargoMetadata, Error, JsonValue, PackageId,
+};
+use camino::{Utf8Path, Utf8PathBuf};
+use std::collections::{HashMap, HashSet};
+
+impl PackageGraph {
+    /// Builds a single package graph out of the metadata for several Cargo workspaces.
+    ///
+    /// Monorepos sometimes contain more than one workspace. This merges them into one graph whose
+    /// workspace contains the members of every input workspace, rooted at the deepest directory
+    /// containing all of them. Third-party packages shared between workspaces (those with the same
+    /// package ID) are unified into a single package. Which input workspace each member came from
+    /// remains queryable through the returned [`MergedPackageGraph`].
+    ///
+    /// If a shared package's dependencies were resolved to different versions in different
+    /// workspaces, links to all of them are kept. Similarly, a shared package has the union of the
+    /// features enabled for it in each workspace.
+    ///
+    /// Returns an error if no metadata is provided, if two inputs are for the same workspace, if
+    /// some inputs were generated with `--no-deps` and others weren't, or if the merged metadata
+    /// doesn't form a valid graph.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use guppy::graph::PackageGraph;
+    /// use guppy::MetadataCommand;
+    ///
+    /// let metadatas = ["frontend", "backend"].iter().map(|dir| {
+    ///     MetadataCommand::new()
+    ///         .current_dir(dir)
+    ///         .exec()
+    ///         .expect("cargo metadata succeeded")
+    /// });
+    /// let merged = PackageGraph::from_workspaces(metadatas).expect("workspaces merged");
+    /// for root in merged.workspace_roots() {
+    ///     let members = merged.resolve_workspace_at(root).expect("known workspace root");
+    ///     println!("{}: {} members", root, members.len());
+    /// }
+    /// ```
+    pub fn from_workspaces(
+        metadatas: impl IntoIterator<Item = CargoMetadata>,
+    ) -> Result<MergedPackageGraph, Error> {
+        let mut workspaces: Vec<MergedWorkspace> = vec![];
+        let mut merged: Option<JsonValue> = None;
+        let mut package_ids: HashSet<String> = HashSet::new();
+        // Maps resolve node IDs to their index in the merged resolve nodes.
+        let mut node_indexes: HashMap<String, usize> = HashMap::new();
+
+        for metadata in metadatas {
+            let root = metadata.inner.workspace_root.clone();
+            if workspaces.iter().any(|workspace| workspace.root == root) {
+                return Err(merge_error(format!(
+                    "workspace at '{}' specified more than once",
+                    root
+                )));
+            }
+            workspaces.push(MergedWorkspace {
+                root: root.clone(),
+                members: metadata
+                    .inner
+                    .workspace_members
+                    .iter()
+                    .map(|id| PackageId::from_metadata(id.clone()))
+                    .collect(),
+            });
+
+            let mut json =
+                serde_json::to_value(&metadata).map_err(Error::MetadataSerializeError)?;
+            if merged.is_none() {
+                for package in json["packages"].as_array().into_iter().flatten() {
+                    package_ids.insert(id_of(package, "package")?);
+                }
+                let nodes = json["resolve"]["nodes"].as_array().into_iter().flatten();
+                for (index, node) in nodes.enumerate() {
+                    node_indexes.insert(id_of(node, "resolve node")?, index);
+                }
+                merged = Some(json);
+                continue;
+            }
+            let merged = merged.as_mut().expect("merged is set above");
+
+            // Unify packages and resolve nodes by ID, keeping the first one seen.
+            for package in take_array(&mut json["packages"]) {
+                if package_ids.insert(id_of(&package, "package")?) {
+                    push_array(&mut merged["packages"], package);
+                }
+            }
+            for member in take_array(&mut json["workspace_members"]) {
+                push_array(&mut merged["workspace_members"], member);
+            }
+            match (
+                json["workspace_default_members"].is_array(),
+                merged["workspace_default_members"].is_array(),
+            ) {
+                (true, true) => {
+                    for member in take_array(&mut json["workspace_default_members"]) {
+                        push_array(&mut merged["workspace_default_members"], member);
+                    }
+                }
+                // If default members aren't known for every workspace, they aren't known for the
+                // merged workspace.
+                _ => {
+                    if let Some(object) = merged.as_object_mut() {
+                        object.remove("workspace_default_members");
+                    }
+                }
+            }
+            match (json["resolve"].is_null(), merged["resolve"].is_null()) {
+                (true, true) => {}
+                (false, false) => {
+                    let nodes = &mut merged["resolve"]["nodes"];
+                    for mut node in take_array(&mut json["resolve"]["nodes"]) {
+                        let id = id_of(&node, "resolve node")?;
+                        match node_indexes.get(&id) {
+                            Some(&index) => merge_node(&mut nodes[index], &mut node),
+                            None => {
+                                let len = nodes.as_array().map_or(0, |nodes| nodes.len());
+                                node_indexes.insert(id, len);
+                                push_array(nodes, node);
+                            }
+                        }
+                    }
+                }
+                _ => {
+                    return Err(merge_error(format!(
+                        "workspace at '{}' is inconsistent with the others: \
+                         either all or none of the metadata must be generated with --no-deps",
+                        root
+                    )));
+                }
+            }
+        }
+
+        let mut merged = merged.ok_or_else(|| merge_error("no workspaces specified"))?;
+        let root = common_ancestor(workspaces.iter().map(|workspace| &workspace.root))
+            .expect("at least one workspace is present");
+        merged["workspace_root"] = root.as_str().into();
+        // The root package and workspace metadata are specific to each workspace.
+        merged["resolve"]["root"] = JsonValue::Null;
+        merged["metadata"] = JsonValue::Null;
+
+        let metadata = CargoMetadata::from_json_value(merged).map_err(Error::MetadataParseError)?;
+        let graph = metadata.build_graph()?;
+        Ok(MergedPackageGraph { graph, workspaces })
+    }
+}
+
+/// A package graph built out of the metadata for several Cargo workspaces.
+///
+/// Created by [`PackageGraph::from_workspaces`].
+#[derive(Clone, Debug)]
+pub struct MergedPackageGraph {
+    graph: PackageGraph,
+    workspaces: Vec<MergedWorkspace>,
+}
+
+#[derive(Clone, Debug)]
+struct MergedWorkspace {
+    root: Utf8PathBuf,
+    members: Vec<PackageId>,
+}
+
+impl MergedPackageGraph {
+    /// Returns the merged package graph.
+    ///
+    /// The graph's workspace contains the members of every input workspace.
+    pub fn graph(&self) -> &PackageGraph {
+        &self.graph
+    }
+
+    /// Consumes `self`, returning the merged package graph.
+    pub fn into_graph(self) -> PackageGraph {
+        self.graph
+    }
+
+    /// Iterates over the roots of the input workspaces, in the order they were provided.
+    pub fn workspace_roots(&self) -> impl ExactSizeIterator<Item = &Utf8Path> {
+        self.workspaces
+            .iter()
+            .map(|workspace| workspace.root.as_path())
+    }
+
+    /// Returns the root of the input workspace that the given package is a member of, or `None` if
+    /// it isn't a member of any of them.
+    pub fn workspace_root_for(&self, package_id: &PackageId) -> Option<&Utf8Path> {
+        self.workspaces
+            .iter()
+            .find(|workspace| workspace.members.contains(package_id))
+            .map(|workspace| workspace.root.as_path())
+    }
+
+    /// Returns the members of the input workspace rooted at the given path, or `None` if there's no
+    /// such workspace.
+    pub fn resolve_workspace_at(&self, root: impl AsRef<Utf8Path>) -> Option<PackageSet<'_>> {
+        let root = root.as_ref();
+        let workspace = self
+            .workspaces
+            .iter()
+            .find(|workspace| workspace.root == root)?;
+        Some(
+            self.graph
+                .resolve_ids(&workspace.members)
+                .expect("workspace members are in the graph"),
+        )
+    }
+}
+
+/// Returns the ID of a package or resolve node in the serialized metadata.
+fn id_of(value: &JsonValue, what: &str) -> Result<String, Error> {
+    value["id"]
+        .as_str()
+        .map(|id| id.to_owned())
+        .ok_or_else(|| merge_error(format!("{} without a string ID", what)))
+}
+
+fn take_array(value: &mut JsonValue) -> Vec<JsonValue> {
+    match value.take() {
+        JsonValue::Array(array) => array,
+        _ => vec![],
+    }
+}
+
+fn push_array(value: &mut JsonValue, item: JsonValue) {
+    if let Some(array) = value.as_array_mut() {
+        array.push(item);
+    }
+}
+
+/// Adds the dependencies and features of `node` that aren't already present to `existing`, a node
+/// with the same ID.
+///
+/// A package shared between workspaces may be built with different features in each of them, so
+/// the merged node has the union of the features enabled anywhere.
+fn merge_node(existing: &mut JsonValue, node: &mut JsonValue) {
+    for key in ["dependencies", "deps", "features"] {
+        for item in take_array(&mut node[key]) {
+            let present = existing[key]
+                .as_array()
+                .map_or(false, |items| items.contains(&item));
+            if !present {
+                push_array(&mut existing[key], item);
+            }
+        }
+    }
+}
+
+fn merge_error(message: impl Into<String>) -> Error {
+    Error::PackageGraphConstructError(format!("failed to merge workspaces: {}", message.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn merge_node_features() {
+        let mut existing = json!({
+            "id": "foo 1.0.0",
+            "dependencies": ["bar 1.0.0"],
+            "deps": [{ "name": "bar", "pkg": "bar 1.0.0", "dep_kinds": [] }],
+            "features": ["default", "std"],
+        });
+        let mut node = json!({
+            "id": "foo 1.0.0",
+            "dependencies": ["bar 1.0.0", "baz 1.0.0"],
+            "deps": [
+                { "name": "bar", "pkg": "bar 1.0.0", "dep_kinds": [] },
+                { "name": "baz", "pkg": "baz 1.0.0", "dep_kinds": [] },
+            ],
+            "features": ["baz", "std"],
+        });
+        merge_node(&mut existing, &mut node);
+        assert_eq!(
+            existing,
+            json!({
+                "id": "foo 1.0.0",
+                "dependencies": ["bar 1.0.0", "baz 1.0.0"],
+                "deps": [
+                    { "name": "bar", "pkg": "bar 1.0.0", "dep_kinds": [] },
+                    { "name": "baz", "pkg": "baz 1.0.0", "dep_kinds": [] },
+                ],
+                "features": ["default", "std", "baz"],
+            }),
+            "dependencies and features are unioned"
+        );
+    }
+}
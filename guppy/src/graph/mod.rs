@@ -10,32 +10,111 @@ use crate::PackageId;
 use petgraph::prelude::*;
 use std::fmt;
 
+#[cfg(feature = "advisories")]
+mod advisories;
+mod artifact;
+#[cfg(feature = "binary")]
+pub(crate) mod binary;
 mod build;
+mod build_plan;
 mod build_targets;
 pub mod cargo;
+mod crate_size;
 mod cycles;
+mod depth;
+mod diagram;
+mod diff;
+mod dominators;
+mod duplicates;
+mod export;
 pub mod feature;
+mod future_incompat;
 mod graph_impl;
+#[cfg(feature = "lockfile")]
+mod inheritance;
+mod iteration_order;
+mod license;
+#[cfg(feature = "lockfile")]
+mod lockfile;
+#[cfg(feature = "lockfile")]
+mod lockfile_diff;
+mod merge;
+mod msrv;
+mod native_links;
+#[cfg(feature = "lockfile")]
+mod patches;
+mod paths;
+mod petgraph_interop;
+mod policy;
 #[cfg(feature = "proptest1")]
 mod proptest_helpers;
+mod provenance;
+mod public_deps;
+mod purl;
 mod query;
 mod query_core;
+mod query_expr;
 mod resolve;
 mod resolve_core;
+mod schedule;
+#[cfg(feature = "sparse-index")]
+mod sparse_index;
+#[cfg(feature = "spdx")]
+mod spdx;
+mod spdx_ids;
 #[cfg(feature = "summaries")]
 pub mod summaries;
-
-pub use crate::petgraph_support::dot::DotWrite;
+mod traverse;
+mod unit_graph;
+mod weights;
+
+pub use crate::petgraph_support::dot::{DotAttributes, DotWrite};
+#[cfg(feature = "advisories")]
+pub use advisories::*;
+pub use artifact::*;
+pub use build_plan::*;
 pub use build_targets::*;
+pub use crate_size::*;
 pub use cycles::*;
+pub use depth::*;
+pub use diagram::*;
+pub use diff::*;
+pub use dominators::*;
+pub use duplicates::*;
+pub use export::*;
+pub use future_incompat::*;
 pub use graph_impl::*;
+#[cfg(feature = "lockfile")]
+pub use inheritance::*;
+pub use iteration_order::*;
+pub use license::*;
+#[cfg(feature = "lockfile")]
+pub use lockfile_diff::*;
+pub use merge::*;
+pub use msrv::*;
+pub use native_links::*;
 use once_cell::sync::Lazy;
+#[cfg(feature = "lockfile")]
+pub use patches::*;
+pub use paths::*;
 use petgraph::graph::IndexType;
+pub use petgraph_interop::*;
+pub use policy::*;
 #[cfg(feature = "proptest1")]
 pub use proptest_helpers::*;
+pub use provenance::*;
+pub use public_deps::*;
 pub use query::*;
+pub use query_expr::*;
 pub use resolve::*;
 use semver::{Version, VersionReq};
+#[cfg(feature = "sparse-index")]
+pub use sparse_index::*;
+#[cfg(feature = "spdx")]
+pub use spdx::*;
+pub use traverse::*;
+pub use unit_graph::*;
+pub use weights::*;
 
 /// The direction in which to follow dependencies.
 ///
@@ -150,3 +229,17 @@ fn cargo_version_matches(req: &VersionReq, version: &Version) -> bool {
 
     req == &*MAJOR_WILDCARD || req.matches(version)
 }
+
+/// Matches `name` against a pattern where `*` matches any sequence of characters and `?` matches
+/// any single byte.
+fn wildcard_match(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.split_first(), name.split_first()) {
+        (None, None) => true,
+        (Some((b'*', rest)), _) => {
+            wildcard_match(rest, name) || (!name.is_empty() && wildcard_match(pattern, &name[1..]))
+        }
+        (Some((b'?', rest)), Some((_, name_rest))) => wildcard_match(rest, name_rest),
+        (Some((p, rest)), Some((n, name_rest))) => p == n && wildcard_match(rest, name_rest),
+        _ => false,
+    }
+}
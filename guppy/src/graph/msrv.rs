@@ -0,0 +1,83 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Aggregation of the minimum supported Rust versions of packages.
+
+use crate::graph::{cargo::CargoSet, DependencyDirection, PackageMetadata, PackageSet};
+use semver::Version;
+
+impl<'g> PackageSet<'g> {
+    /// Returns the highest minimum supported Rust version (MSRV) among packages in this set,
+    /// along with the packages that require it.
+    ///
+    /// This is the oldest Rust toolchain that can build every package in this set, going by the
+    /// `rust-version` fields in their manifests. Packages that don't specify a `rust-version` are
+    /// ignored.
+    ///
+    /// Returns `None` if no package in this set specifies a `rust-version`.
+    ///
+    /// To find the MSRV for a specific feature configuration, resolve a
+    /// [`FeatureSet`](crate::graph::feature::FeatureSet) and call this on the result of
+    /// [`to_package_set`](crate::graph::feature::FeatureSet::to_package_set).
+    pub fn msrv(&self) -> Option<Msrv<'g>> {
+        let mut msrv: Option<Msrv<'g>> = None;
+        for package in self.packages(DependencyDirection::Forward) {
+            let version = match package.minimum_rust_version() {
+                Some(version) => version,
+                None => continue,
+            };
+            match &mut msrv {
+                Some(msrv) if msrv.version > version => {}
+                Some(msrv) if msrv.version == version => msrv.packages.push(package),
+                _ => {
+                    msrv = Some(Msrv {
+                        version,
+                        packages: vec![package],
+                    })
+                }
+            }
+        }
+
+        let mut msrv = msrv?;
+        msrv.packages.sort_by_key(|package| package.id());
+        Some(msrv)
+    }
+}
+
+impl<'g> CargoSet<'g> {
+    /// Returns the highest minimum supported Rust version (MSRV) among packages in this build,
+    /// along with the packages that require it.
+    ///
+    /// Both target and host packages are considered. For more, see
+    /// [`PackageSet::msrv`](crate::graph::PackageSet::msrv).
+    pub fn msrv(&self) -> Option<Msrv<'g>> {
+        let target_set = self.target_features().to_package_set();
+        let host_set = self.host_features().to_package_set();
+        target_set.union(&host_set).msrv()
+    }
+}
+
+/// The minimum supported Rust version (MSRV) of a set of packages.
+///
+/// Returned by [`PackageSet::msrv`] and [`CargoSet::msrv`](crate::graph::cargo::CargoSet::msrv).
+#[derive(Clone, Debug)]
+pub struct Msrv<'g> {
+    version: Version,
+    packages: Vec<PackageMetadata<'g>>,
+}
+
+assert_covariant!(Msrv);
+
+impl<'g> Msrv<'g> {
+    /// Returns the minimum Rust version required to build every package in the set.
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
+
+    /// Returns the packages that require this Rust version, sorted by package ID.
+    ///
+    /// Each of these packages has a `rust-version` equal to [`version`](Self::version).
+    pub fn packages(&self) -> &[PackageMetadata<'g>] {
+        &self.packages
+    }
+}
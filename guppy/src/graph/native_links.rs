@@ -0,0 +1,153 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Detection of conflicts between packages that link to the same native library.
+
+use crate::{
+    graph::{
+        cargo::CargoSet, DependencyChain, DependencyDirection, PackageLink, PackageMetadata,
+        PackageSet,
+    },
+    PackageId,
+};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fmt,
+};
+
+impl<'g> PackageSet<'g> {
+    /// Returns conflicts between packages in this set that specify the same `links` value.
+    ///
+    /// Cargo requires that at most one package in a build links to a given native library, and
+    /// fails to resolve dependencies otherwise. See [The `links` Manifest
+    /// Key](https://doc.rust-lang.org/cargo/reference/build-scripts.html#the-links-manifest-key)
+    /// in the Cargo reference for more.
+    ///
+    /// Conflicts are returned in order of `links` value, and each conflict includes a dependency
+    /// path to every package involved.
+    pub fn links_conflicts(&self) -> Vec<LinksConflict<'g>> {
+        let mut by_links: BTreeMap<&'g str, Vec<PackageMetadata<'g>>> = BTreeMap::new();
+        for package in self.packages(DependencyDirection::Forward) {
+            if let Some(links) = package.links() {
+                by_links.entry(links).or_default().push(package);
+            }
+        }
+
+        by_links
+            .into_iter()
+            .filter(|(_, packages)| packages.len() > 1)
+            .map(|(links, mut packages)| {
+                packages.sort_by_key(|package| package.id());
+                let paths = packages
+                    .iter()
+                    .map(|package| self.path_from_root(*package))
+                    .collect();
+                LinksConflict {
+                    links,
+                    packages,
+                    paths,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns a shortest path to this package from a root of this set, following links within the
+    /// set.
+    fn path_from_root(&self, package: PackageMetadata<'g>) -> DependencyChain<'g> {
+        // Search backwards from the package until a package without any incoming links is found.
+        // Each package maps to the link through which it was reached.
+        let mut parents: HashMap<&'g PackageId, PackageLink<'g>> = HashMap::new();
+        let mut visited: HashSet<&'g PackageId> = HashSet::new();
+        visited.insert(package.id());
+        let mut queue = VecDeque::new();
+        queue.push_back(package);
+
+        while let Some(current) = queue.pop_front() {
+            let mut incoming = current
+                .reverse_direct_links()
+                .filter(|link| {
+                    self.contains(link.from().id())
+                        .expect("linked package is known")
+                })
+                .peekable();
+            if incoming.peek().is_none() {
+                let mut links = vec![];
+                let mut package_id = current.id();
+                while let Some(link) = parents.get(package_id) {
+                    links.push(*link);
+                    package_id = link.to().id();
+                }
+                return DependencyChain::from_links(current, links);
+            }
+            for link in incoming {
+                if visited.insert(link.from().id()) {
+                    parents.insert(link.from().id(), link);
+                    queue.push_back(link.from());
+                }
+            }
+        }
+
+        // Every package that depends on this one is part of a cycle.
+        DependencyChain::from_links(package, vec![])
+    }
+}
+
+impl<'g> CargoSet<'g> {
+    /// Returns conflicts between packages in this build that specify the same `links` value.
+    ///
+    /// Both target and host packages are considered, since Cargo applies this rule across the
+    /// entire build. For more, see
+    /// [`PackageSet::links_conflicts`](crate::graph::PackageSet::links_conflicts).
+    pub fn links_conflicts(&self) -> Vec<LinksConflict<'g>> {
+        let target_set = self.target_features().to_package_set();
+        let host_set = self.host_features().to_package_set();
+        target_set.union(&host_set).links_conflicts()
+    }
+}
+
+/// A conflict between packages that specify the same `links` value.
+///
+/// Returned by [`PackageSet::links_conflicts`] and
+/// [`CargoSet::links_conflicts`](crate::graph::cargo::CargoSet::links_conflicts). The `Display`
+/// impl prints a human-readable explanation of the conflict.
+#[derive(Clone, Debug)]
+pub struct LinksConflict<'g> {
+    links: &'g str,
+    packages: Vec<PackageMetadata<'g>>,
+    paths: Vec<DependencyChain<'g>>,
+}
+
+assert_covariant!(LinksConflict);
+
+impl<'g> LinksConflict<'g> {
+    /// Returns the name of the native library the packages link to.
+    pub fn links(&self) -> &'g str {
+        self.links
+    }
+
+    /// Returns the packages that link to this native library, sorted by package ID.
+    pub fn packages(&self) -> &[PackageMetadata<'g>] {
+        &self.packages
+    }
+
+    /// Returns a dependency path to each package, in the same order as `packages`.
+    ///
+    /// Each path starts at a package in the set that no other package in the set depends on.
+    pub fn paths(&self) -> &[DependencyChain<'g>] {
+        &self.paths
+    }
+}
+
+impl<'g> fmt::Display for LinksConflict<'g> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "multiple packages link to native library `{}`:",
+            self.links
+        )?;
+        for path in &self.paths {
+            write!(f, "\n  {}", path)?;
+        }
+        Ok(())
+    }
+}
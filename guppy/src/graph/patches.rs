@@ -0,0 +1,199 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Information about packages that come from `[patch]` and `[replace]` sections.
+
+use crate::{
+    graph::{
+        lockfile::{lockfile_error, read_toml, ManifestDependency, WorkspaceManifest},
+        PackageGraph, PackageMetadata,
+    },
+    Error,
+};
+use camino::{Utf8Path, Utf8PathBuf};
+use toml::{value::Table, Value as TomlValue};
+
+impl PackageGraph {
+    /// Returns the packages in this graph that come from a `[patch]` or `[replace]` entry in the
+    /// workspace's root manifest.
+    ///
+    /// Cargo only honors these sections in the root manifest, so this reads the `Cargo.toml` at
+    /// the workspace root. Entries are returned in the order they're declared in, with `[patch]`
+    /// entries before `[replace]` entries. Entries that don't match any package in this graph,
+    /// such as unused patches, are skipped.
+    ///
+    /// Patches specified through Cargo's configuration files aren't considered.
+    ///
+    /// Returns an error if the root manifest couldn't be read or parsed.
+    ///
+    /// This method is available if the `lockfile` feature is enabled.
+    pub fn patches(&self) -> Result<Vec<PatchedPackage<'_>>, Error> {
+        let workspace_root = self.workspace().root();
+        let manifest_path = workspace_root.join("Cargo.toml");
+        let root_manifest = read_toml(&manifest_path)?;
+        let workspace_table = root_manifest.get("workspace").and_then(TomlValue::as_table);
+        let workspace = WorkspaceManifest::new(workspace_root, workspace_table);
+
+        let mut patched = vec![];
+        for entry in patch_entries(&root_manifest, &workspace, &manifest_path)? {
+            let mut packages: Vec<_> = self
+                .packages()
+                .filter(|package| entry.dependency.matches(*package))
+                .collect();
+            packages.sort_by_key(|package| package.id());
+            patched.extend(packages.into_iter().map(|package| PatchedPackage {
+                package,
+                kind: entry.kind,
+                key: entry.key.clone(),
+                original_source: entry.original_source.clone(),
+                manifest_path: manifest_path.clone(),
+            }));
+        }
+        Ok(patched)
+    }
+}
+
+/// A package that comes from a `[patch]` or `[replace]` entry.
+///
+/// Returned by [`PackageGraph::patches`].
+#[derive(Clone, Debug)]
+pub struct PatchedPackage<'g> {
+    package: PackageMetadata<'g>,
+    kind: PatchKind,
+    key: String,
+    original_source: Option<String>,
+    manifest_path: Utf8PathBuf,
+}
+
+assert_covariant!(PatchedPackage);
+
+impl<'g> PatchedPackage<'g> {
+    /// Returns the package that was substituted in by this entry.
+    pub fn package(&self) -> PackageMetadata<'g> {
+        self.package
+    }
+
+    /// Returns whether this entry is from a `[patch]` or a `[replace]` section.
+    pub fn kind(&self) -> PatchKind {
+        self.kind
+    }
+
+    /// Returns the key of this entry in the manifest.
+    ///
+    /// For `[patch]` entries this is the dependency name, and for `[replace]` entries this is the
+    /// package ID specification, e.g. `"foo:1.2.3"`.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns the source that the original package came from, as written in the manifest.
+    ///
+    /// For `[patch]` entries this is the name of the section, e.g. `"crates-io"` for
+    /// `[patch.crates-io]`, or the URL of a registry or Git repository. For `[replace]` entries
+    /// this is the URL in the package ID specification, or `None` if it doesn't have one.
+    pub fn original_source(&self) -> Option<&str> {
+        self.original_source.as_deref()
+    }
+
+    /// Returns the path to the manifest that declared this entry.
+    pub fn manifest_path(&self) -> &Utf8Path {
+        &self.manifest_path
+    }
+}
+
+/// The section a [`PatchedPackage`] was declared in.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum PatchKind {
+    /// The package was declared in a `[patch]` section.
+    Patch,
+
+    /// The package was declared in the deprecated `[replace]` section.
+    Replace,
+}
+
+/// An entry in a `[patch]` or `[replace]` section.
+pub(super) struct PatchEntry {
+    kind: PatchKind,
+    key: String,
+    original_source: Option<String>,
+    pub(super) dependency: ManifestDependency,
+}
+
+/// Reads the `[patch]` and `[replace]` entries from a root manifest.
+pub(super) fn patch_entries(
+    root_manifest: &Table,
+    workspace: &WorkspaceManifest<'_>,
+    manifest_path: &Utf8Path,
+) -> Result<Vec<PatchEntry>, Error> {
+    let mut entries = vec![];
+
+    let patch_sections = root_manifest.get("patch").and_then(TomlValue::as_table);
+    for (source, section) in patch_sections.into_iter().flatten() {
+        let section = section.as_table().ok_or_else(|| {
+            lockfile_error(manifest_path, format!("[patch.{}] isn't a table", source))
+        })?;
+        for (key, value) in section {
+            let dependency = ManifestDependency::new(
+                key,
+                value,
+                None,
+                None,
+                workspace.root,
+                workspace,
+                manifest_path,
+            )?;
+            entries.push(PatchEntry {
+                kind: PatchKind::Patch,
+                key: key.clone(),
+                original_source: Some(source.clone()),
+                dependency,
+            });
+        }
+    }
+
+    let replace_section = root_manifest.get("replace").and_then(TomlValue::as_table);
+    for (key, value) in replace_section.into_iter().flatten() {
+        // Package ID specifications are of the form `[url#]name[:version]`, with `@` also
+        // accepted as a version separator.
+        let (original_source, spec) = match key.rsplit_once('#') {
+            Some((url, spec)) => (Some(url.to_owned()), spec),
+            None => (None, key.as_str()),
+        };
+        let (name, version) = match spec.split_once([':', '@']) {
+            Some((name, version)) => (name, Some(version)),
+            None => (spec, None),
+        };
+
+        let mut table = match value {
+            TomlValue::Table(table) => table.clone(),
+            _ => {
+                return Err(lockfile_error(
+                    manifest_path,
+                    format!("[replace] entry '{}' isn't a table", key),
+                ))
+            }
+        };
+        // The replacement must have the same name and version as the package it replaces.
+        table.insert("package".to_owned(), name.into());
+        if let Some(version) = version {
+            table.insert("version".to_owned(), format!("={}", version).into());
+        }
+        let dependency = ManifestDependency::new(
+            name,
+            &TomlValue::Table(table),
+            None,
+            None,
+            workspace.root,
+            workspace,
+            manifest_path,
+        )?;
+        entries.push(PatchEntry {
+            kind: PatchKind::Replace,
+            key: key.clone(),
+            original_source,
+            dependency,
+        });
+    }
+
+    Ok(entries)
+}
@@ -0,0 +1,416 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{
+    graph::{
+        query_core::reachable_map, PackageGraph, PackageIx, PackageLink, PackageLinkImpl,
+        PackageMetadata,
+    },
+    Error, PackageId,
+};
+use fixedbitset::FixedBitSet;
+use petgraph::{
+    graph::Edges,
+    prelude::*,
+    visit::{Reversed, Visitable},
+};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    fmt,
+};
+
+/// ## Dependency paths
+///
+/// The methods in this section explain how one package comes to depend on another.
+impl PackageGraph {
+    /// Returns a shortest dependency chain from `from` to `to`, or `None` if `from` doesn't
+    /// depend on `to`.
+    ///
+    /// This answers the question "why is `to` in the dependency tree of `from`?". Every kind of
+    /// dependency (normal, build and dev) is followed. If `from` and `to` are the same package, the
+    /// chain is empty.
+    ///
+    /// Returns an error if either package ID is unknown.
+    pub fn why(
+        &self,
+        from: &PackageId,
+        to: &PackageId,
+    ) -> Result<Option<DependencyChain<'_>>, Error> {
+        let from_ix = self.package_ix(from)?;
+        let to_ix = self.package_ix(to)?;
+        let banned_nodes = self.dep_graph.visit_map();
+        Ok(
+            shortest_path(self, from_ix, to_ix, &banned_nodes, &HashSet::new())
+                .map(|edge_ixs| DependencyChain::new(self, from_ix, edge_ixs)),
+        )
+    }
+
+    /// Returns up to `k` of the shortest dependency chains from `from` to `to`, in order of
+    /// increasing length.
+    ///
+    /// Each chain is a simple path, visiting every package at most once. Fewer than `k` chains are
+    /// returned if there aren't that many distinct paths between the two packages.
+    ///
+    /// Returns an error if either package ID is unknown.
+    pub fn why_k_shortest(
+        &self,
+        from: &PackageId,
+        to: &PackageId,
+        k: usize,
+    ) -> Result<Vec<DependencyChain<'_>>, Error> {
+        let from_ix = self.package_ix(from)?;
+        let to_ix = self.package_ix(to)?;
+        Ok(k_shortest_paths(self, from_ix, to_ix, k)
+            .into_iter()
+            .map(|edge_ixs| DependencyChain::new(self, from_ix, edge_ixs))
+            .collect())
+    }
+
+    /// Returns an iterator over every simple dependency path from `from` to `to`.
+    ///
+    /// A simple path visits each package at most once. This is useful for audits that need to
+    /// know every route through which a package can be reached. Every kind of dependency (normal,
+    /// build and dev) is followed.
+    ///
+    /// The number of paths can grow exponentially with the size of the graph. `max_len`, if
+    /// provided, caps the number of links in each path; paths are produced lazily, so use
+    /// [`Iterator::take`] to limit the number of paths.
+    ///
+    /// Returns an error if either package ID is unknown.
+    pub fn simple_paths(
+        &self,
+        from: &PackageId,
+        to: &PackageId,
+        max_len: Option<usize>,
+    ) -> Result<SimplePaths<'_>, Error> {
+        let from_ix = self.package_ix(from)?;
+        let to_ix = self.package_ix(to)?;
+        Ok(SimplePaths::new(self, from_ix, to_ix, max_len))
+    }
+}
+
+/// A chain of dependency links from one package to another.
+///
+/// Returned by [`PackageGraph::why`] and [`PackageGraph::why_k_shortest`]. The `Display` impl
+/// prints a human-readable explanation of the chain, including the kind and version requirement of
+/// every link.
+#[derive(Clone, Debug)]
+pub struct DependencyChain<'g> {
+    from: PackageMetadata<'g>,
+    links: Vec<PackageLink<'g>>,
+}
+
+assert_covariant!(DependencyChain);
+
+impl<'g> DependencyChain<'g> {
+    fn new(
+        graph: &'g PackageGraph,
+        from_ix: NodeIndex<PackageIx>,
+        edge_ixs: Vec<EdgeIndex<PackageIx>>,
+    ) -> Self {
+        let from = graph
+            .metadata(&graph.dep_graph[from_ix])
+            .expect("package in chain should have associated metadata");
+        let links = edge_ixs
+            .into_iter()
+            .map(|edge_ix| graph.edge_ix_to_link(edge_ix))
+            .collect();
+        Self::from_links(from, links)
+    }
+
+    pub(super) fn from_links(from: PackageMetadata<'g>, links: Vec<PackageLink<'g>>) -> Self {
+        Self { from, links }
+    }
+
+    /// Returns the package this chain starts at.
+    pub fn from(&self) -> PackageMetadata<'g> {
+        self.from
+    }
+
+    /// Returns the package this chain ends at.
+    ///
+    /// This is the same as `from` if the chain is empty.
+    pub fn to(&self) -> PackageMetadata<'g> {
+        match self.links.last() {
+            Some(link) => link.to(),
+            None => self.from,
+        }
+    }
+
+    /// Returns the links in this chain, in order from `from` to `to`.
+    pub fn links(&self) -> &[PackageLink<'g>] {
+        &self.links
+    }
+
+    /// Returns the packages in this chain, in order from `from` to `to`.
+    pub fn packages(&self) -> impl Iterator<Item = PackageMetadata<'g>> + '_ {
+        std::iter::once(self.from).chain(self.links.iter().map(|link| link.to()))
+    }
+
+    /// Returns the number of links in this chain.
+    pub fn len(&self) -> usize {
+        self.links.len()
+    }
+
+    /// Returns true if this chain has no links, i.e. `from` and `to` are the same package.
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty()
+    }
+}
+
+impl<'g> fmt::Display for DependencyChain<'g> {
+    /// Formats the chain as, for example:
+    ///
+    /// ```text
+    /// testcrate v0.1.0 -> datatest v0.4.2 (dev, ^0.4.2) -> regex v1.3.1 (normal, ^1.1.8)
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} v{}", self.from.name(), self.from.version())?;
+        for link in &self.links {
+            let to = link.to();
+            write!(f, " -> {} v{} (", to.name(), to.version())?;
+            let kinds = [
+                ("normal", link.normal()),
+                ("build", link.build()),
+                ("dev", link.dev()),
+            ];
+            for (kind, req) in &kinds {
+                if req.is_present() {
+                    write!(f, "{}, ", kind)?;
+                }
+            }
+            write!(f, "{})", link.version_req())?;
+        }
+        Ok(())
+    }
+}
+
+/// An iterator over simple dependency paths between two packages.
+///
+/// Returned by [`PackageGraph::simple_paths`].
+pub struct SimplePaths<'g> {
+    graph: &'g PackageGraph,
+    from_ix: NodeIndex<PackageIx>,
+    to_ix: NodeIndex<PackageIx>,
+    max_len: usize,
+    // Packages that `to` is reachable from. Other packages can never lead to a path.
+    can_reach: FixedBitSet,
+    on_path: FixedBitSet,
+    // The links to each package on the current path.
+    path: Vec<EdgeIndex<PackageIx>>,
+    // The links remaining to be explored out of each package on the current path.
+    stack: Vec<Edges<'g, PackageLinkImpl, Directed, PackageIx>>,
+    // Whether the empty path has been returned, if `from` and `to` are the same package.
+    returned_empty: bool,
+}
+
+assert_covariant!(SimplePaths);
+
+impl<'g> SimplePaths<'g> {
+    fn new(
+        graph: &'g PackageGraph,
+        from_ix: NodeIndex<PackageIx>,
+        to_ix: NodeIndex<PackageIx>,
+        max_len: Option<usize>,
+    ) -> Self {
+        let dep_graph = &graph.dep_graph;
+        let (can_reach, _) = reachable_map(Reversed(dep_graph), vec![to_ix]);
+        let mut on_path = dep_graph.visit_map();
+        on_path.insert(from_ix.index());
+        let stack = if from_ix != to_ix && can_reach[from_ix.index()] {
+            vec![dep_graph.edges_directed(from_ix, Outgoing)]
+        } else {
+            vec![]
+        };
+
+        Self {
+            graph,
+            from_ix,
+            to_ix,
+            max_len: max_len.unwrap_or(usize::MAX),
+            can_reach,
+            on_path,
+            path: vec![],
+            stack,
+            returned_empty: false,
+        }
+    }
+}
+
+impl<'g> Iterator for SimplePaths<'g> {
+    type Item = DependencyChain<'g>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.from_ix == self.to_ix {
+            if self.returned_empty {
+                return None;
+            }
+            self.returned_empty = true;
+            return Some(DependencyChain::new(self.graph, self.from_ix, vec![]));
+        }
+
+        let dep_graph = &self.graph.dep_graph;
+        while let Some(edges) = self.stack.last_mut() {
+            let edge = match edges.next() {
+                Some(edge) => edge,
+                None => {
+                    self.stack.pop();
+                    if let Some(edge_ix) = self.path.pop() {
+                        let (_, package_ix) =
+                            dep_graph.edge_endpoints(edge_ix).expect("valid edge index");
+                        self.on_path.set(package_ix.index(), false);
+                    }
+                    continue;
+                }
+            };
+
+            let target_ix = edge.target();
+            if target_ix == self.to_ix {
+                if self.path.len() < self.max_len {
+                    let mut path = self.path.clone();
+                    path.push(edge.id());
+                    return Some(DependencyChain::new(self.graph, self.from_ix, path));
+                }
+                continue;
+            }
+            // Extending the path must leave room for at least one more link to `to`.
+            if self.path.len() + 1 < self.max_len
+                && self.can_reach[target_ix.index()]
+                && !self.on_path[target_ix.index()]
+            {
+                self.on_path.insert(target_ix.index());
+                self.path.push(edge.id());
+                self.stack
+                    .push(dep_graph.edges_directed(target_ix, Outgoing));
+            }
+        }
+
+        None
+    }
+}
+
+// ---
+// Helper functions
+// ---
+
+/// Finds a shortest path from `from_ix` to `to_ix` with a breadth-first search, avoiding the given
+/// packages and links.
+fn shortest_path(
+    graph: &PackageGraph,
+    from_ix: NodeIndex<PackageIx>,
+    to_ix: NodeIndex<PackageIx>,
+    banned_nodes: &FixedBitSet,
+    banned_edges: &HashSet<EdgeIndex<PackageIx>>,
+) -> Option<Vec<EdgeIndex<PackageIx>>> {
+    if from_ix == to_ix {
+        return Some(vec![]);
+    }
+
+    let dep_graph = &graph.dep_graph;
+    let mut visited = dep_graph.visit_map();
+    visited.insert(from_ix.index());
+    // Maps each visited package to the link it was first reached through.
+    let mut parents = HashMap::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(from_ix);
+
+    while let Some(package_ix) = queue.pop_front() {
+        for edge in dep_graph.edges_directed(package_ix, Outgoing) {
+            let target_ix = edge.target();
+            if visited[target_ix.index()]
+                || banned_nodes[target_ix.index()]
+                || banned_edges.contains(&edge.id())
+            {
+                continue;
+            }
+            visited.insert(target_ix.index());
+            parents.insert(target_ix, edge.id());
+            if target_ix == to_ix {
+                let mut edge_ixs = vec![];
+                let mut current_ix = to_ix;
+                while current_ix != from_ix {
+                    let edge_ix = parents[&current_ix];
+                    edge_ixs.push(edge_ix);
+                    current_ix = dep_graph
+                        .edge_endpoints(edge_ix)
+                        .expect("valid edge index")
+                        .0;
+                }
+                edge_ixs.reverse();
+                return Some(edge_ixs);
+            }
+            queue.push_back(target_ix);
+        }
+    }
+
+    None
+}
+
+/// Finds up to `k` shortest simple paths from `from_ix` to `to_ix`, using Yen's algorithm.
+fn k_shortest_paths(
+    graph: &PackageGraph,
+    from_ix: NodeIndex<PackageIx>,
+    to_ix: NodeIndex<PackageIx>,
+    k: usize,
+) -> Vec<Vec<EdgeIndex<PackageIx>>> {
+    let dep_graph = &graph.dep_graph;
+    let mut accepted: Vec<Vec<EdgeIndex<PackageIx>>> = vec![];
+    if k == 0 {
+        return accepted;
+    }
+    match shortest_path(
+        graph,
+        from_ix,
+        to_ix,
+        &dep_graph.visit_map(),
+        &HashSet::new(),
+    ) {
+        Some(path) => accepted.push(path),
+        None => return accepted,
+    }
+
+    // Candidates are ordered by length, then by edge indexes so that the results are
+    // deterministic.
+    let mut candidates: BTreeSet<(usize, Vec<EdgeIndex<PackageIx>>)> = BTreeSet::new();
+    while accepted.len() < k {
+        let previous = accepted.last().expect("at least one path was accepted");
+        let mut nodes = vec![from_ix];
+        nodes.extend(
+            previous
+                .iter()
+                .map(|&edge_ix| dep_graph.edge_endpoints(edge_ix).expect("valid edge").1),
+        );
+
+        let mut banned_nodes = dep_graph.visit_map();
+        for spur_idx in 0..previous.len() {
+            let root = &previous[..spur_idx];
+            // Ban the next link of every accepted path that shares this root, so that the spur
+            // path diverges from all of them.
+            let banned_edges: HashSet<_> = accepted
+                .iter()
+                .filter(|path| path.len() > spur_idx && &path[..spur_idx] == root)
+                .map(|path| path[spur_idx])
+                .collect();
+            if let Some(spur_path) =
+                shortest_path(graph, nodes[spur_idx], to_ix, &banned_nodes, &banned_edges)
+            {
+                let mut path = root.to_vec();
+                path.extend(spur_path);
+                if !accepted.contains(&path) {
+                    candidates.insert((path.len(), path));
+                }
+            }
+            // The root path's packages can't be revisited by later spur paths.
+            banned_nodes.insert(nodes[spur_idx].index());
+        }
+
+        let next = match candidates.iter().next() {
+            Some(next) => next.clone(),
+            None => break,
+        };
+        candidates.remove(&next);
+        accepted.push(next.1);
+    }
+
+    accepted
+}
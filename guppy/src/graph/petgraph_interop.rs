@@ -0,0 +1,78 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Conversions from package graphs and sets into standalone `petgraph` graphs.
+
+use crate::{
+    graph::{DependencyDirection, PackageGraph, PackageLink, PackageMetadata, PackageSet},
+    PackageId,
+};
+use petgraph::prelude::*;
+use std::collections::HashMap;
+
+impl PackageGraph {
+    /// Converts this graph into a standalone [`petgraph::Graph`], with nodes and edges referring
+    /// back to the metadata in this graph.
+    ///
+    /// This can be used to run `petgraph` algorithms that guppy doesn't expose directly. The
+    /// returned graph is independent of guppy's internal representation: node and edge indexes
+    /// in it have no relation to anything else in guppy.
+    pub fn to_petgraph(&self) -> PackagePetgraph<'_> {
+        self.resolve_all().to_petgraph()
+    }
+}
+
+impl<'g> PackageSet<'g> {
+    /// Converts the packages in this set, and the links between them, into a standalone
+    /// [`petgraph::Graph`].
+    ///
+    /// Nodes are added in forward topological order, and edges point from dependents to
+    /// dependencies. Links to or from packages outside this set are not included.
+    pub fn to_petgraph(&self) -> PackagePetgraph<'g> {
+        let mut graph = Graph::with_capacity(self.len(), 0);
+        let mut node_ixs = HashMap::with_capacity(self.len());
+        for package in self.packages(DependencyDirection::Forward) {
+            node_ixs.insert(package.id(), graph.add_node(package));
+        }
+        for link in self.links(DependencyDirection::Forward) {
+            graph.add_edge(node_ixs[link.from().id()], node_ixs[link.to().id()], link);
+        }
+
+        PackagePetgraph { graph, node_ixs }
+    }
+}
+
+/// A standalone [`petgraph::Graph`] of packages and the links between them.
+///
+/// Created by [`PackageGraph::to_petgraph`] or [`PackageSet::to_petgraph`].
+#[derive(Clone, Debug)]
+pub struct PackagePetgraph<'g> {
+    graph: Graph<PackageMetadata<'g>, PackageLink<'g>>,
+    node_ixs: HashMap<&'g PackageId, NodeIndex>,
+}
+
+assert_covariant!(PackagePetgraph);
+
+impl<'g> PackagePetgraph<'g> {
+    /// Returns the underlying `petgraph` graph.
+    pub fn graph(&self) -> &Graph<PackageMetadata<'g>, PackageLink<'g>> {
+        &self.graph
+    }
+
+    /// Returns the node index corresponding to this package ID, or `None` if it isn't in this
+    /// graph.
+    pub fn node_ix(&self, package_id: &PackageId) -> Option<NodeIndex> {
+        self.node_ixs.get(package_id).copied()
+    }
+
+    /// Returns the package corresponding to this node index, or `None` if the index is out of
+    /// bounds.
+    pub fn package(&self, node_ix: NodeIndex) -> Option<PackageMetadata<'g>> {
+        self.graph.node_weight(node_ix).copied()
+    }
+
+    /// Consumes `self`, returning the underlying `petgraph` graph.
+    pub fn into_graph(self) -> Graph<PackageMetadata<'g>, PackageLink<'g>> {
+        self.graph
+    }
+}
@@ -0,0 +1,468 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Checking package graphs against policies for banned packages, licenses, sources and
+//! duplicates.
+
+use crate::graph::{
+    DependencyChain, DependencyDirection, LicenseExpr, PackageGraph, PackageLink, PackageMetadata,
+    PackageSet, SourceKind,
+};
+use crate::PackageId;
+use semver::VersionReq;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+/// A policy for the packages in a dependency graph, similar to the configuration of
+/// [cargo-deny](https://github.com/EmbarkStudios/cargo-deny).
+///
+/// A policy consists of four kinds of checks, each of which is disabled by default:
+///
+/// * **Bans:** packages that must not be present, optionally limited to some versions.
+/// * **Licenses:** the licenses third-party packages may use.
+/// * **Sources:** the registries and Git repositories third-party packages may come from.
+/// * **Duplicates:** how many versions of a package may be present at the same time.
+///
+/// Policies are checked with [`PackageSet::check_policy`] or [`PackageGraph::check_policy`].
+///
+/// # Examples
+///
+/// ```
+/// use guppy::graph::{Policy, SourcePolicy};
+/// use guppy::{MetadataCommand, VersionReq};
+///
+/// let graph = MetadataCommand::new().build_graph().expect("guppy graph constructed");
+///
+/// let mut policy = Policy::new();
+/// policy
+///     .add_ban("openssl-sys", VersionReq::STAR)
+///     .set_allowed_licenses(["MIT", "Apache-2.0", "BSD-3-Clause", "Unicode-DFS-2016"])
+///     .set_sources(SourcePolicy::new())
+///     .set_max_versions(2);
+///
+/// let report = graph.check_policy(&policy);
+/// for violation in report.violations() {
+///     println!("{}: {:?}", violation.package().id(), violation.kind());
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Policy {
+    bans: Vec<(String, VersionReq)>,
+    allowed_licenses: Option<BTreeSet<String>>,
+    sources: Option<SourcePolicy>,
+    max_versions: Option<usize>,
+    max_versions_for: HashMap<String, usize>,
+}
+
+impl Policy {
+    /// Creates a new policy with all checks disabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bans versions of the package with the given name that match `version_req`.
+    ///
+    /// Use `VersionReq::STAR` to ban every version. Bans apply to workspace members as well as
+    /// third-party packages.
+    pub fn add_ban(&mut self, name: impl Into<String>, version_req: VersionReq) -> &mut Self {
+        self.bans.push((name.into(), version_req));
+        self
+    }
+
+    /// Sets the license identifiers that third-party packages may use, and enables license
+    /// checks.
+    ///
+    /// A package's license expression must be satisfiable with the allowed licenses, as with
+    /// [`LicenseExpr::is_satisfied_by`]: for example, `MIT OR GPL-3.0` is allowed if `MIT` is
+    /// allowed. Identifiers are compared case-insensitively, and any `+` or exception is
+    /// ignored.
+    ///
+    /// Packages without a `license` field, and packages whose `license` field can't be parsed,
+    /// are violations.
+    pub fn set_allowed_licenses(
+        &mut self,
+        licenses: impl IntoIterator<Item = impl Into<String>>,
+    ) -> &mut Self {
+        self.allowed_licenses = Some(
+            licenses
+                .into_iter()
+                .map(|license| license.into().to_ascii_lowercase())
+                .collect(),
+        );
+        self
+    }
+
+    /// Sets the sources third-party packages may come from, and enables source checks.
+    pub fn set_sources(&mut self, sources: SourcePolicy) -> &mut Self {
+        self.sources = Some(sources);
+        self
+    }
+
+    /// Sets the maximum number of versions of any package that may be present, and enables
+    /// duplicate checks.
+    ///
+    /// A limit of 1 disallows duplicates altogether. Packages with the same name from different
+    /// sources count as different versions.
+    pub fn set_max_versions(&mut self, max_versions: usize) -> &mut Self {
+        self.max_versions = Some(max_versions);
+        self
+    }
+
+    /// Sets the maximum number of versions of the package with the given name that may be
+    /// present, overriding the limit set with [`set_max_versions`](Self::set_max_versions).
+    ///
+    /// This also enables duplicate checks for this package, even if no overall limit is set.
+    pub fn set_max_versions_for(
+        &mut self,
+        name: impl Into<String>,
+        max_versions: usize,
+    ) -> &mut Self {
+        self.max_versions_for.insert(name.into(), max_versions);
+        self
+    }
+}
+
+/// The sources that third-party packages may come from, as part of a [`Policy`].
+///
+/// Workspace members and path dependencies are always allowed. By default, packages from
+/// `crates.io` are allowed and packages from all other sources are not.
+#[derive(Clone, Debug)]
+pub struct SourcePolicy {
+    allow_crates_io: bool,
+    registries: BTreeSet<String>,
+    git: BTreeSet<String>,
+}
+
+impl SourcePolicy {
+    /// Creates a new source policy that only allows `crates.io`.
+    pub fn new() -> Self {
+        Self {
+            allow_crates_io: true,
+            registries: BTreeSet::new(),
+            git: BTreeSet::new(),
+        }
+    }
+
+    /// Sets whether packages from `crates.io` are allowed.
+    pub fn set_allow_crates_io(&mut self, allow_crates_io: bool) -> &mut Self {
+        self.allow_crates_io = allow_crates_io;
+        self
+    }
+
+    /// Allows packages from the registry with the given index URL.
+    ///
+    /// The URL is in the form reported by [`SourceKind::AlternateRegistry`], e.g.
+    /// `"https://github.com/fakeorg/crates.io-index"`. Vendored directories are also allowed
+    /// through this method, with URLs of the form reported by [`SourceKind::Vendored`].
+    pub fn allow_registry(&mut self, url: impl Into<String>) -> &mut Self {
+        self.registries.insert(url.into());
+        self
+    }
+
+    /// Allows packages from the Git repository with the given URL.
+    ///
+    /// URLs are compared without any trailing `/` or `.git`, so
+    /// `"https://github.com/fakeorg/repo.git"` and `"https://github.com/fakeorg/repo"` are
+    /// equivalent.
+    pub fn allow_git(&mut self, url: impl AsRef<str>) -> &mut Self {
+        self.git.insert(normalize_git_url(url.as_ref()).to_owned());
+        self
+    }
+
+    fn allows(&self, kind: SourceKind<'_>) -> bool {
+        match kind {
+            SourceKind::Workspace | SourceKind::Path => true,
+            SourceKind::CratesIo => self.allow_crates_io,
+            SourceKind::AlternateRegistry(url) | SourceKind::Vendored(url) => {
+                self.registries.contains(url)
+            }
+            SourceKind::Git(url) => self.git.contains(normalize_git_url(url)),
+            SourceKind::Unknown(_) => false,
+        }
+    }
+}
+
+impl Default for SourcePolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn normalize_git_url(url: &str) -> &str {
+    let url = url.trim_end_matches('/');
+    url.strip_suffix(".git").unwrap_or(url)
+}
+
+impl PackageGraph {
+    /// Checks every package in this graph against the given policy.
+    ///
+    /// This is equivalent to `self.resolve_all().check_policy(policy)`.
+    pub fn check_policy(&self, policy: &Policy) -> PolicyReport<'_> {
+        self.resolve_all().check_policy(policy)
+    }
+}
+
+impl<'g> PackageSet<'g> {
+    /// Checks the packages in this set against the given policy.
+    ///
+    /// Every violation comes with a shortest dependency path from a workspace member in this set
+    /// to the offending package, following only links within this set. To leave dev-dependencies
+    /// of third-party packages out of the check, resolve the set with
+    /// `resolve_with_fn(|_, link| !link.dev_only())` or similar.
+    pub fn check_policy(&self, policy: &Policy) -> PolicyReport<'g> {
+        let mut packages: Vec<_> = self.packages(DependencyDirection::Forward).collect();
+        packages.sort_by_key(|package| package.id());
+
+        let mut violations = vec![];
+        for package in &packages {
+            for (name, version_req) in &policy.bans {
+                if package.name() == name && version_req.matches(package.version()) {
+                    violations.push((
+                        *package,
+                        ViolationKind::Banned {
+                            version_req: version_req.clone(),
+                        },
+                    ));
+                }
+            }
+        }
+
+        if let Some(allowed_licenses) = &policy.allowed_licenses {
+            for package in packages.iter().filter(|package| !package.in_workspace()) {
+                let kind = match package.license_expr() {
+                    None => ViolationKind::MissingLicense,
+                    Some(Err(_)) => ViolationKind::InvalidLicense,
+                    Some(Ok(expr)) => {
+                        if expr.is_satisfied_by(|id| {
+                            allowed_licenses.contains(&id.to_ascii_lowercase())
+                        }) {
+                            continue;
+                        }
+                        ViolationKind::DisallowedLicense { expr }
+                    }
+                };
+                violations.push((*package, kind));
+            }
+        }
+
+        if let Some(sources) = &policy.sources {
+            for package in &packages {
+                let kind = package.source().kind();
+                if !sources.allows(kind) {
+                    violations.push((*package, ViolationKind::DisallowedSource { kind }));
+                }
+            }
+        }
+
+        if policy.max_versions.is_some() || !policy.max_versions_for.is_empty() {
+            let mut by_name: BTreeMap<&'g str, Vec<PackageMetadata<'g>>> = BTreeMap::new();
+            for package in &packages {
+                by_name.entry(package.name()).or_default().push(*package);
+            }
+            for (name, group) in by_name {
+                let limit = match policy.max_versions_for.get(name) {
+                    Some(limit) => *limit,
+                    None => match policy.max_versions {
+                        Some(limit) => limit,
+                        None => continue,
+                    },
+                };
+                if group.len() > limit {
+                    let count = group.len();
+                    violations.extend(
+                        group
+                            .into_iter()
+                            .map(|package| (package, ViolationKind::Duplicate { count, limit })),
+                    );
+                }
+            }
+        }
+
+        let mut paths = HashMap::new();
+        let violations = violations
+            .into_iter()
+            .map(|(package, kind)| {
+                let path = paths
+                    .entry(package.id())
+                    .or_insert_with(|| shortest_path_from_workspace(self, package))
+                    .clone();
+                PolicyViolation {
+                    package,
+                    path,
+                    kind,
+                }
+            })
+            .collect();
+        PolicyReport { violations }
+    }
+}
+
+/// Searches backwards from the package for the closest workspace member.
+fn shortest_path_from_workspace<'g>(
+    package_set: &PackageSet<'g>,
+    package: PackageMetadata<'g>,
+) -> Option<DependencyChain<'g>> {
+    // Maps each visited package to the link it was first reached through.
+    let mut parents: HashMap<&'g PackageId, Option<PackageLink<'g>>> = HashMap::new();
+    parents.insert(package.id(), None);
+    let mut queue = VecDeque::new();
+    queue.push_back(package);
+
+    while let Some(current) = queue.pop_front() {
+        if current.in_workspace() {
+            let mut links = vec![];
+            let mut id = current.id();
+            while let Some(link) = parents[id] {
+                links.push(link);
+                id = link.to().id();
+            }
+            return Some(DependencyChain::from_links(current, links));
+        }
+        for link in current.direct_links_directed(DependencyDirection::Reverse) {
+            let from = link.from();
+            if !parents.contains_key(from.id())
+                && package_set
+                    .contains(from.id())
+                    .expect("package ID is known")
+            {
+                parents.insert(from.id(), Some(link));
+                queue.push_back(from);
+            }
+        }
+    }
+    None
+}
+
+/// The result of checking a package set against a [`Policy`].
+///
+/// Returned by [`PackageSet::check_policy`] and [`PackageGraph::check_policy`].
+#[derive(Clone, Debug)]
+pub struct PolicyReport<'g> {
+    violations: Vec<PolicyViolation<'g>>,
+}
+
+impl<'g> PolicyReport<'g> {
+    /// Returns true if there are no violations.
+    pub fn is_empty(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// Returns all violations.
+    ///
+    /// Violations are grouped by check, in the order bans, licenses, sources and duplicates.
+    /// Within each check, violations are sorted by package ID, except for duplicates which are
+    /// sorted by package name.
+    pub fn violations(&self) -> &[PolicyViolation<'g>] {
+        &self.violations
+    }
+
+    /// Returns the violations for the package with the given ID.
+    pub fn violations_for<'a>(
+        &'a self,
+        package_id: &'a PackageId,
+    ) -> impl Iterator<Item = &'a PolicyViolation<'g>> + 'a {
+        self.violations
+            .iter()
+            .filter(move |violation| violation.package.id() == package_id)
+    }
+}
+
+/// A package that violates a [`Policy`].
+///
+/// Part of a [`PolicyReport`].
+#[derive(Clone, Debug)]
+pub struct PolicyViolation<'g> {
+    package: PackageMetadata<'g>,
+    path: Option<DependencyChain<'g>>,
+    kind: ViolationKind<'g>,
+}
+
+impl<'g> PolicyViolation<'g> {
+    /// Returns the offending package.
+    pub fn package(&self) -> PackageMetadata<'g> {
+        self.package
+    }
+
+    /// Returns the kind of violation.
+    pub fn kind(&self) -> &ViolationKind<'g> {
+        &self.kind
+    }
+
+    /// Returns a shortest dependency path from a workspace member to the offending package.
+    ///
+    /// The path is empty if the package is a workspace member itself, and `None` if the package
+    /// can't be reached from any workspace member in the package set.
+    pub fn path(&self) -> Option<&DependencyChain<'g>> {
+        self.path.as_ref()
+    }
+}
+
+/// The ways in which a package may violate a [`Policy`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ViolationKind<'g> {
+    /// The package is banned.
+    Banned {
+        /// The version requirement of the ban that matched.
+        version_req: VersionReq,
+    },
+
+    /// The package doesn't specify a license.
+    MissingLicense,
+
+    /// The package's `license` field couldn't be parsed.
+    InvalidLicense,
+
+    /// The package's license can't be satisfied with the allowed licenses.
+    DisallowedLicense {
+        /// The package's license expression.
+        expr: LicenseExpr,
+    },
+
+    /// The package comes from a source that isn't allowed.
+    DisallowedSource {
+        /// The package's source.
+        kind: SourceKind<'g>,
+    },
+
+    /// More versions of this package are present than the limit allows.
+    ///
+    /// Every version of the package is reported.
+    Duplicate {
+        /// The number of versions present.
+        count: usize,
+
+        /// The maximum number of versions allowed.
+        limit: usize,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_policy_allows() {
+        let mut sources = SourcePolicy::new();
+        sources
+            .allow_registry("https://github.com/fakeorg/crates.io-index")
+            .allow_git("https://github.com/fakeorg/repo.git/");
+
+        assert!(sources.allows(SourceKind::CratesIo));
+        assert!(sources.allows(SourceKind::Path));
+        assert!(sources.allows(SourceKind::AlternateRegistry(
+            "https://github.com/fakeorg/crates.io-index"
+        )));
+        assert!(!sources.allows(SourceKind::AlternateRegistry(
+            "https://github.com/otherorg/crates.io-index"
+        )));
+        assert!(
+            sources.allows(SourceKind::Git("https://github.com/fakeorg/repo")),
+            "git URLs are normalized"
+        );
+        assert!(!sources.allows(SourceKind::Git("https://github.com/fakeorg/other")));
+        assert!(!sources.allows(SourceKind::Unknown("foo+bar")));
+
+        sources.set_allow_crates_io(false);
+        assert!(!sources.allows(SourceKind::CratesIo));
+    }
+}
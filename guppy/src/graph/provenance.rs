@@ -0,0 +1,150 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Tracking the kinds of dependency edges packages are reached through.
+
+use crate::{
+    graph::{DependencyDirection, PackageLink, PackageMetadata, PackageQuery, PackageSet},
+    Error, PackageId,
+};
+
+impl<'g> PackageQuery<'g> {
+    /// Resolves this query into a set of known packages, following every link found along the
+    /// way, and records the kinds of dependency edges each package is reached through.
+    ///
+    /// This distinguishes packages that ship as part of the final product (reachable through
+    /// normal dependencies alone) from those that are only needed at build time or for tests. For
+    /// more, see [`DependencyProvenance`].
+    pub fn resolve_provenance(self) -> ProvenanceSet<'g> {
+        self.resolve_provenance_with_fn(|_, _| true)
+    }
+
+    /// Resolves this query into a set of known packages, using the provided resolver function to
+    /// determine which links are followed, and records the kinds of dependency edges each package
+    /// is reached through.
+    ///
+    /// The resolver function is called for every link that could be followed. Links it rejects
+    /// aren't followed for any kind of dependency.
+    pub fn resolve_provenance_with_fn(
+        self,
+        mut resolver_fn: impl FnMut(&PackageQuery<'g>, PackageLink<'g>) -> bool,
+    ) -> ProvenanceSet<'g> {
+        let normal = self
+            .clone()
+            .resolve_with_fn(|query, link| link.normal().is_present() && resolver_fn(query, link));
+        let build = self.clone().resolve_with_fn(|query, link| {
+            (link.normal().is_present() || link.build().is_present()) && resolver_fn(query, link)
+        });
+        let all = self.resolve_with_fn(resolver_fn);
+        ProvenanceSet { normal, build, all }
+    }
+}
+
+/// The kinds of dependency edges through which a package is reached from the initials of a query.
+///
+/// Variants are ordered from most to least "shipped": a package reachable through normal edges
+/// alone is `Normal`, even if it can also be reached through build or dev edges.
+///
+/// Returned by [`ProvenanceSet::provenance`].
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum DependencyProvenance {
+    /// The package is reachable through a path made up of normal dependencies only.
+    ///
+    /// Such packages ship as part of the final product.
+    Normal,
+
+    /// The package is only reachable through paths that include a build dependency, and there is
+    /// such a path without any dev-dependencies.
+    ///
+    /// Such packages are only needed at build time.
+    Build,
+
+    /// The package is only reachable through paths that include a dev-dependency.
+    ///
+    /// Such packages are only needed for tests, examples and benchmarks.
+    Dev,
+}
+
+impl DependencyProvenance {
+    /// Returns true if this package ships as part of the final product.
+    pub fn is_normal(self) -> bool {
+        self == DependencyProvenance::Normal
+    }
+}
+
+/// A set of resolved packages, along with the kinds of dependency edges each one is reached
+/// through.
+///
+/// Created by [`PackageQuery::resolve_provenance`] or
+/// [`PackageQuery::resolve_provenance_with_fn`].
+#[derive(Clone, Debug)]
+pub struct ProvenanceSet<'g> {
+    normal: PackageSet<'g>,
+    build: PackageSet<'g>,
+    all: PackageSet<'g>,
+}
+
+assert_covariant!(ProvenanceSet);
+
+impl<'g> ProvenanceSet<'g> {
+    /// Returns the set of all resolved packages, regardless of provenance.
+    ///
+    /// This is the same set that the corresponding `resolve` call would return.
+    pub fn all(&self) -> &PackageSet<'g> {
+        &self.all
+    }
+
+    /// Returns the provenance of the given package, or `None` if it isn't in this set.
+    ///
+    /// Returns an error if the package ID is unknown.
+    pub fn provenance(
+        &self,
+        package_id: &PackageId,
+    ) -> Result<Option<DependencyProvenance>, Error> {
+        if self.normal.contains(package_id)? {
+            Ok(Some(DependencyProvenance::Normal))
+        } else if self.build.contains(package_id)? {
+            Ok(Some(DependencyProvenance::Build))
+        } else if self.all.contains(package_id)? {
+            Ok(Some(DependencyProvenance::Dev))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Returns the packages with the given provenance.
+    pub fn with_provenance(&self, provenance: DependencyProvenance) -> PackageSet<'g> {
+        match provenance {
+            DependencyProvenance::Normal => self.normal.clone(),
+            DependencyProvenance::Build => self.build.difference(&self.normal),
+            DependencyProvenance::Dev => self.all.difference(&self.build),
+        }
+    }
+
+    /// Returns the packages that ship as part of the final product, i.e. those reachable through
+    /// normal dependencies alone.
+    pub fn normal(&self) -> &PackageSet<'g> {
+        &self.normal
+    }
+
+    /// Returns the packages that are only needed at build time or for tests.
+    pub fn build_or_dev_only(&self) -> PackageSet<'g> {
+        self.all.difference(&self.normal)
+    }
+
+    /// Iterates over all packages in this set along with their provenance, in topological order
+    /// in the direction specified.
+    pub fn packages<'a>(
+        &'a self,
+        direction: DependencyDirection,
+    ) -> impl Iterator<Item = (PackageMetadata<'g>, DependencyProvenance)> + ExactSizeIterator + 'a
+    {
+        self.all.packages(direction).map(move |package| {
+            let provenance = self
+                .provenance(package.id())
+                .expect("valid package ID")
+                .expect("package is in the set");
+            (package, provenance)
+        })
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Auditing of public and private dependencies.
+
+use crate::{
+    graph::{DependencyChain, DependencyDirection, PackageLink, PackageMetadata, PackageSet},
+    PackageId,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+};
+
+impl<'g> PackageSet<'g> {
+    /// Returns the private dependencies of packages in this set whose types are exposed through
+    /// public dependencies.
+    ///
+    /// If package `A` publicly depends on `B`, and `B` publicly depends on `C`, then types from
+    /// `C` can appear in `A`'s public API. If `A` also lists `C` as a private dependency, that
+    /// declaration is misleading: `C` is part of `A`'s API regardless, and should be marked
+    /// `public = true` as well. For more about public dependencies, see
+    /// [`PackageLink::is_public`].
+    ///
+    /// Only links between packages in this set are considered. Results are sorted by the package
+    /// the private dependency is from, then by dependency name.
+    pub fn leaked_private_dependencies(&self) -> Vec<LeakedPrivateDependency<'g>> {
+        let mut leaked = vec![];
+        for package in self.packages(DependencyDirection::Forward) {
+            let private_links: Vec<_> = package
+                .direct_links()
+                .filter(|link| {
+                    link.normal().is_present()
+                        && !link.is_public()
+                        && self
+                            .contains(link.to().id())
+                            .expect("linked package is known")
+                })
+                .collect();
+            if private_links.is_empty() {
+                continue;
+            }
+
+            let exposed = self.publicly_reachable(package);
+            for private_link in private_links {
+                if let Some(links) = exposed.get(private_link.to().id()) {
+                    leaked.push(LeakedPrivateDependency {
+                        private_link,
+                        exposed_by: DependencyChain::from_links(package, links.clone()),
+                    });
+                }
+            }
+        }
+        leaked.sort_by(|a, b| {
+            a.private_link
+                .from()
+                .id()
+                .cmp(b.private_link.from().id())
+                .then_with(|| a.private_link.dep_name().cmp(b.private_link.dep_name()))
+        });
+        leaked
+    }
+
+    /// Returns the packages reachable from `package` through chains of public links of length at
+    /// least one, along with the shortest such chain for each.
+    fn publicly_reachable(
+        &self,
+        package: PackageMetadata<'g>,
+    ) -> HashMap<&'g PackageId, Vec<PackageLink<'g>>> {
+        let mut reachable: HashMap<_, Vec<_>> = HashMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back((package, vec![]));
+        while let Some((current, chain)) = queue.pop_front() {
+            for link in current.direct_links() {
+                let to = link.to();
+                if !link.is_public()
+                    || reachable.contains_key(to.id())
+                    || !self.contains(to.id()).expect("linked package is known")
+                {
+                    continue;
+                }
+                let mut chain: Vec<_> = chain.clone();
+                chain.push(link);
+                reachable.insert(to.id(), chain.clone());
+                queue.push_back((to, chain));
+            }
+        }
+        reachable
+    }
+}
+
+/// A private dependency whose types are exposed through public dependencies.
+///
+/// Returned by [`PackageSet::leaked_private_dependencies`].
+#[derive(Clone, Debug)]
+pub struct LeakedPrivateDependency<'g> {
+    private_link: PackageLink<'g>,
+    exposed_by: DependencyChain<'g>,
+}
+
+impl<'g> LeakedPrivateDependency<'g> {
+    /// Returns the private link.
+    pub fn private_link(&self) -> PackageLink<'g> {
+        self.private_link
+    }
+
+    /// Returns a chain of public links from the same package to the same dependency, through
+    /// which the dependency's types are exposed.
+    ///
+    /// The chain is never empty, and is one of the shortest such chains.
+    pub fn exposed_by(&self) -> &DependencyChain<'g> {
+        &self.exposed_by
+    }
+}
+
+impl<'g> fmt::Display for LeakedPrivateDependency<'g> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} depends privately on {}, which is exposed publicly through: {}",
+            self.private_link.from().id(),
+            self.private_link.to().id(),
+            self.exposed_by,
+        )
+    }
+}
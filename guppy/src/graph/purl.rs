@@ -0,0 +1,70 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Package URLs for packages.
+
+use crate::graph::PackageMetadata;
+
+impl<'g> PackageMetadata<'g> {
+    /// Returns the [package URL](https://github.com/package-url/purl-spec) (purl) for this
+    /// package, if it was retrieved from a registry or a Git repository.
+    ///
+    /// * Packages from `crates.io` have purls of the form `pkg:cargo/name@version`.
+    /// * Packages from other registries specify the registry with a `repository_url` qualifier.
+    /// * Packages from Git repositories specify the repository and commit with a `vcs_url`
+    ///   qualifier.
+    ///
+    /// Returns `None` for workspace members and path dependencies, since they can't be located
+    /// outside this workspace.
+    pub fn purl(&self) -> Option<String> {
+        let base = format!("pkg:cargo/{}@{}", self.name(), self.version());
+        let source = self.source();
+        if source.is_crates_io() {
+            return Some(base);
+        }
+        let source = source.external_source()?;
+        if let Some(url) = source.strip_prefix("git+") {
+            // Git sources are of the form git+<url>[?<query>][#<commit>].
+            let (url, commit) = match url.split_once('#') {
+                Some((url, commit)) => (url, Some(commit)),
+                None => (url, None),
+            };
+            let url = url.split_once('?').map_or(url, |(url, _)| url);
+            let vcs_url = match commit {
+                Some(commit) => format!("git+{}@{}", url, commit),
+                None => format!("git+{}", url),
+            };
+            Some(format!("{}?vcs_url={}", base, percent_encode(&vcs_url)))
+        } else {
+            let url = source.strip_prefix("registry+").unwrap_or(source);
+            Some(format!("{}?repository_url={}", base, percent_encode(url)))
+        }
+    }
+}
+
+fn percent_encode(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                out.push(byte as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_encode_values() {
+        assert_eq!(
+            percent_encode("https://github.com/foo/bar.git@abc123"),
+            "https://github.com/foo/bar.git%40abc123"
+        );
+        assert_eq!(percent_encode("a b+c?d"), "a%20b%2Bc%3Fd");
+    }
+}
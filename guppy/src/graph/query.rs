@@ -11,7 +11,7 @@ use crate::{
     sorted_set::SortedSet,
     Error, PackageId,
 };
-use camino::Utf8Path;
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
 use petgraph::prelude::*;
 
 /// A query over a package graph.
@@ -23,6 +23,7 @@ pub struct PackageQuery<'g> {
     // The fields are pub(super) for access within the graph module.
     pub(super) graph: &'g PackageGraph,
     pub(super) params: QueryParams<PackageGraph>,
+    pub(super) max_depth: Option<usize>,
 }
 
 assert_covariant!(PackageQuery);
@@ -41,6 +42,21 @@ impl PackageGraph {
             .expect("workspace packages should all be known")
     }
 
+    /// Creates a new forward query over the default members of the workspace.
+    ///
+    /// This selects the packages that a plain `cargo build` in the workspace root would build,
+    /// along with their transitive dependencies.
+    ///
+    /// Returns `None` if the default members aren't known. For more, see the documentation for
+    /// [`Workspace::default_member_ids`](crate::graph::Workspace::default_member_ids).
+    pub fn query_workspace_default_members(&self) -> Option<PackageQuery<'_>> {
+        let default_member_ids = self.workspace().default_member_ids()?;
+        Some(
+            self.query_forward(default_member_ids)
+                .expect("default members should all be known"),
+        )
+    }
+
     /// Creates a new forward query over the specified workspace packages by path.
     ///
     /// Returns an error if any workspace paths were unknown.
@@ -61,6 +77,48 @@ impl PackageGraph {
         Ok(self.query_from_parts(package_ixs, DependencyDirection::Forward))
     }
 
+    /// Creates a new forward query over all workspace packages under the given directory.
+    ///
+    /// The directory can be relative to the workspace root, or absolute. A workspace package is
+    /// under the directory if its `Cargo.toml` is in the directory or any of its subdirectories.
+    /// For example, with a directory of `crates/storage`, the packages at `crates/storage` and
+    /// `crates/storage/engine` are selected, but the package at `crates/storage-utils` is not.
+    ///
+    /// Returns an error if no workspace packages are under the directory.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use guppy::MetadataCommand;
+    ///
+    /// let package_graph = MetadataCommand::new().build_graph().unwrap();
+    /// // Select every workspace package under `guppy/`, along with its dependencies.
+    /// let query = package_graph.query_directory("guppy").unwrap();
+    /// let guppy = package_graph.workspace().member_by_path("guppy").unwrap();
+    /// assert!(query.starts_from(guppy.id()).unwrap());
+    /// ```
+    pub fn query_directory(&self, path: impl AsRef<Utf8Path>) -> Result<PackageQuery<'_>, Error> {
+        let path = path.as_ref();
+        let workspace = self.workspace();
+        // Workspace paths are relative to the workspace root, and don't contain `.` components.
+        let dir: Utf8PathBuf = path
+            .strip_prefix(workspace.root())
+            .unwrap_or(path)
+            .components()
+            .filter(|component| *component != Utf8Component::CurDir)
+            .collect();
+        let package_ixs: SortedSet<_> = workspace
+            .iter_by_path()
+            .filter(|(member_path, _)| member_path.starts_with(&dir))
+            .map(|(_, package)| package.package_ix())
+            .collect();
+        if package_ixs.is_empty() {
+            return Err(Error::UnknownWorkspacePath(path.to_path_buf()));
+        }
+
+        Ok(self.query_from_parts(package_ixs, DependencyDirection::Forward))
+    }
+
     /// Creates a new forward query over the specified workspace packages by name.
     ///
     /// This is similar to `cargo`'s `--package` option.
@@ -108,6 +166,7 @@ impl PackageGraph {
         Ok(PackageQuery {
             graph: self,
             params: QueryParams::Forward(self.package_ixs(package_ids)?),
+            max_depth: None,
         })
     }
 
@@ -121,6 +180,7 @@ impl PackageGraph {
         Ok(PackageQuery {
             graph: self,
             params: QueryParams::Reverse(self.package_ixs(package_ids)?),
+            max_depth: None,
         })
     }
 
@@ -136,6 +196,7 @@ impl PackageGraph {
         PackageQuery {
             graph: self,
             params,
+            max_depth: None,
         }
     }
 }
@@ -172,6 +233,24 @@ impl<'g> PackageQuery<'g> {
         Ok(self.params.has_initial(self.graph.package_ix(package_id)?))
     }
 
+    /// Returns the maximum depth of this query, if one was set.
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    /// Limits this query to packages that are at most `max_depth` links away from the initials.
+    ///
+    /// A depth of 0 only includes the initials, and a depth of 1 includes the initials and their
+    /// direct dependencies (or dependents, for reverse queries). Each package's depth is the length
+    /// of the shortest path to it along links that are followed.
+    ///
+    /// The limit applies to `resolve`, `resolve_with` and `resolve_with_fn`. It isn't carried over
+    /// to feature queries, or to Cargo build simulations.
+    pub fn set_max_depth(&mut self, max_depth: usize) -> &mut Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
     /// Converts this `PackageQuery` into a `FeatureQuery`, using the given feature filter.
     ///
     /// This will cause the feature graph to be constructed if it hasn't been done so already.
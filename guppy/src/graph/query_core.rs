@@ -8,9 +8,9 @@ use crate::{
 };
 use fixedbitset::FixedBitSet;
 use petgraph::{
-    graph::IndexType,
+    graph::{EdgeReference, IndexType},
     prelude::*,
-    visit::{IntoEdges, IntoNeighbors, Visitable},
+    visit::{IntoEdges, IntoNeighbors, VisitMap, Visitable},
 };
 use std::fmt;
 
@@ -127,3 +127,48 @@ where
     let len = reachable.count_ones(..);
     (reachable, len)
 }
+
+/// Returns the nodes reachable from the roots through at most `max_depth` edges.
+///
+/// The arguments to the edge filter are unreversed, even if the direction is `Incoming`.
+pub(super) fn reachable_map_depth_limited<'g, N, E, Ix>(
+    graph: &'g Graph<N, E, Directed, Ix>,
+    direction: Direction,
+    roots: impl Into<Vec<NodeIndex<Ix>>>,
+    max_depth: usize,
+    mut edge_filter: impl FnMut(EdgeReference<'g, E, Ix>) -> bool,
+) -> (FixedBitSet, usize)
+where
+    Ix: IndexType,
+{
+    // A breadth-first search visits each node at its shortest distance from the roots, so nodes
+    // beyond max_depth are never seen.
+    let mut reachable = graph.visit_map();
+    let mut current: Vec<_> = roots
+        .into()
+        .into_iter()
+        .filter(|&root| reachable.visit(root))
+        .collect();
+    for _ in 0..max_depth {
+        let mut next = vec![];
+        for node_ix in current {
+            for edge in graph.edges_directed(node_ix, direction) {
+                let neighbor = match direction {
+                    Outgoing => edge.target(),
+                    Incoming => edge.source(),
+                };
+                if !reachable.is_visited(&neighbor) && edge_filter(edge) {
+                    reachable.visit(neighbor);
+                    next.push(neighbor);
+                }
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        current = next;
+    }
+
+    let len = reachable.count_ones(..);
+    (reachable, len)
+}
@@ -0,0 +1,556 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! A small expression language for selecting packages.
+
+use crate::{
+    graph::{DependencyDirection, PackageGraph, PackageLink, PackageSet},
+    DependencyKind, Error,
+};
+use std::{fmt, str::FromStr};
+
+/// An expression that selects a set of packages from a `PackageGraph`.
+///
+/// Expressions are made up of function calls combined with the `&` (intersection), `|` (union)
+/// and `!` (complement) operators, along with parentheses for grouping. `!` binds the tightest,
+/// followed by `&` and then `|`.
+///
+/// The following functions are supported:
+///
+/// * `all()`: every package in the graph.
+/// * `workspace()`: the workspace members.
+/// * `package(a, b, ...)`: packages with the given names.
+/// * `deps(a, b, ...)`: packages with the given names, along with their transitive dependencies.
+/// * `rdeps(a, b, ...)`: packages with the given names, along with their transitive reverse
+///   dependencies.
+/// * `normal_deps(...)`, `build_deps(...)`, `dev_deps(...)`: like `deps`, but only following
+///   links of the given [`DependencyKind`].
+/// * `normal_rdeps(...)`, `build_rdeps(...)`, `dev_rdeps(...)`: like `rdeps`, but only following
+///   links of the given [`DependencyKind`].
+///
+/// Package names passed into functions may contain `*` and `?` wildcards, as with
+/// [`PackageGraph::resolve_package_name_glob`]. Each name must match at least one package in the
+/// graph.
+///
+/// Expressions can be parsed with `str::parse`, and printed back out with the `Display` impl.
+///
+/// # Examples
+///
+/// ```
+/// use guppy::{graph::PackageExpr, MetadataCommand};
+///
+/// let graph = MetadataCommand::new().build_graph().expect("guppy graph constructed");
+/// let expr: PackageExpr = "deps(guppy) & !workspace()".parse().expect("valid expression");
+/// let package_set = expr.resolve(&graph).expect("package names are known");
+/// let guppy = graph.workspace().member_by_name("guppy").expect("guppy is a member");
+/// assert!(!package_set.contains(guppy.id()).expect("valid package ID"));
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum PackageExpr {
+    /// Every package in the graph: `all()`.
+    All,
+
+    /// The workspace members: `workspace()`.
+    Workspace,
+
+    /// Packages with any of the given names: `package(...)`.
+    Package(Vec<String>),
+
+    /// Packages with any of the given names, along with the packages reachable from them:
+    /// `deps(...)`, `rdeps(...)`, or one of their variants that only follow links of a given
+    /// kind.
+    Deps {
+        /// The direction in which links are followed.
+        direction: DependencyDirection,
+
+        /// If specified, only links of this kind are followed.
+        kind: Option<DependencyKind>,
+
+        /// The names of the packages to start from.
+        names: Vec<String>,
+    },
+
+    /// Packages not in the inner expression: `!expr`.
+    Not(Box<PackageExpr>),
+
+    /// Packages in both expressions: `a & b`.
+    And(Box<PackageExpr>, Box<PackageExpr>),
+
+    /// Packages in either expression: `a | b`.
+    Or(Box<PackageExpr>, Box<PackageExpr>),
+}
+
+impl PackageExpr {
+    /// Resolves this expression against the given package graph.
+    ///
+    /// Returns an error if a package name in this expression doesn't match any packages.
+    pub fn resolve<'g>(&self, graph: &'g PackageGraph) -> Result<PackageSet<'g>, Error> {
+        match self {
+            PackageExpr::All => Ok(graph.resolve_all()),
+            PackageExpr::Workspace => Ok(graph.resolve_workspace()),
+            PackageExpr::Package(names) => resolve_names(graph, names),
+            PackageExpr::Deps {
+                direction,
+                kind,
+                names,
+            } => {
+                let initials = resolve_names(graph, names)?;
+                let query = graph
+                    .query_directed(initials.package_ids(*direction), *direction)
+                    .expect("package IDs in the set are known");
+                Ok(match kind {
+                    Some(kind) => {
+                        let kind = *kind;
+                        query.resolve_with_fn(move |_, link| link_has_kind(link, kind))
+                    }
+                    None => query.resolve(),
+                })
+            }
+            PackageExpr::Not(inner) => Ok(graph.resolve_all().difference(&inner.resolve(graph)?)),
+            PackageExpr::And(a, b) => Ok(a.resolve(graph)?.intersection(&b.resolve(graph)?)),
+            PackageExpr::Or(a, b) => Ok(a.resolve(graph)?.union(&b.resolve(graph)?)),
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            PackageExpr::Or(..) => 0,
+            PackageExpr::And(..) => 1,
+            _ => 2,
+        }
+    }
+
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, min_precedence: u8) -> fmt::Result {
+        if self.precedence() < min_precedence {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+impl PackageGraph {
+    /// Parses a [`PackageExpr`] and resolves it against this graph.
+    ///
+    /// Returns an error if the expression couldn't be parsed, or if a package name in it doesn't
+    /// match any packages.
+    pub fn resolve_expr(&self, expr: &str) -> Result<PackageSet<'_>, Error> {
+        expr.parse::<PackageExpr>()?.resolve(self)
+    }
+}
+
+impl FromStr for PackageExpr {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::new(s);
+        let expr = parser.parse_or()?;
+        match parser.next_token()? {
+            None => Ok(expr),
+            Some((pos, token)) => Err(parser.error(pos, format!("unexpected {}", token))),
+        }
+    }
+}
+
+impl fmt::Display for PackageExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PackageExpr::All => write!(f, "all()"),
+            PackageExpr::Workspace => write!(f, "workspace()"),
+            PackageExpr::Package(names) => write!(f, "package({})", names.join(", ")),
+            PackageExpr::Deps {
+                direction,
+                kind,
+                names,
+            } => {
+                let function = match (direction, kind) {
+                    (DependencyDirection::Forward, None) => "deps",
+                    (DependencyDirection::Reverse, None) => "rdeps",
+                    (DependencyDirection::Forward, Some(DependencyKind::Normal)) => "normal_deps",
+                    (DependencyDirection::Forward, Some(DependencyKind::Build)) => "build_deps",
+                    (DependencyDirection::Forward, Some(DependencyKind::Development)) => "dev_deps",
+                    (DependencyDirection::Reverse, Some(DependencyKind::Normal)) => "normal_rdeps",
+                    (DependencyDirection::Reverse, Some(DependencyKind::Build)) => "build_rdeps",
+                    (DependencyDirection::Reverse, Some(DependencyKind::Development)) => {
+                        "dev_rdeps"
+                    }
+                };
+                write!(f, "{}({})", function, names.join(", "))
+            }
+            PackageExpr::Not(inner) => {
+                write!(f, "!")?;
+                inner.fmt_operand(f, 2)
+            }
+            PackageExpr::And(a, b) => {
+                a.fmt_operand(f, 1)?;
+                write!(f, " & ")?;
+                b.fmt_operand(f, 2)
+            }
+            PackageExpr::Or(a, b) => {
+                a.fmt_operand(f, 0)?;
+                write!(f, " | ")?;
+                b.fmt_operand(f, 1)
+            }
+        }
+    }
+}
+
+fn resolve_names<'g>(graph: &'g PackageGraph, names: &[String]) -> Result<PackageSet<'g>, Error> {
+    let mut package_set = graph.resolve_none();
+    for name in names {
+        let named = graph.resolve_package_name_glob(name);
+        if named.is_empty() {
+            return Err(Error::QueryExprError(format!(
+                "no packages matching '{}' found",
+                name
+            )));
+        }
+        package_set = package_set.union(&named);
+    }
+    Ok(package_set)
+}
+
+fn link_has_kind(link: PackageLink<'_>, kind: DependencyKind) -> bool {
+    match kind {
+        DependencyKind::Normal => link.normal().is_present(),
+        DependencyKind::Build => link.build().is_present(),
+        DependencyKind::Development => link.dev().is_present(),
+    }
+}
+
+// ---
+// Parsing
+// ---
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Token<'a> {
+    Ident(&'a str),
+    LParen,
+    RParen,
+    Comma,
+    And,
+    Or,
+    Not,
+}
+
+impl<'a> fmt::Display for Token<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(ident) => write!(f, "'{}'", ident),
+            Token::LParen => write!(f, "'('"),
+            Token::RParen => write!(f, "')'"),
+            Token::Comma => write!(f, "','"),
+            Token::And => write!(f, "'&'"),
+            Token::Or => write!(f, "'|'"),
+            Token::Not => write!(f, "'!'"),
+        }
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    peeked: Option<Option<(usize, Token<'a>)>>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            pos: 0,
+            peeked: None,
+        }
+    }
+
+    // or := and ('|' and)*
+    fn parse_or(&mut self) -> Result<PackageExpr, Error> {
+        let mut expr = self.parse_and()?;
+        while let Some((_, Token::Or)) = self.peek_token()? {
+            self.next_token()?;
+            let rhs = self.parse_and()?;
+            expr = PackageExpr::Or(Box::new(expr), Box::new(rhs));
+        }
+        Ok(expr)
+    }
+
+    // and := unary ('&' unary)*
+    fn parse_and(&mut self) -> Result<PackageExpr, Error> {
+        let mut expr = self.parse_unary()?;
+        while let Some((_, Token::And)) = self.peek_token()? {
+            self.next_token()?;
+            let rhs = self.parse_unary()?;
+            expr = PackageExpr::And(Box::new(expr), Box::new(rhs));
+        }
+        Ok(expr)
+    }
+
+    // unary := '!' unary | '(' or ')' | ident '(' (ident (',' ident)*)? ')'
+    fn parse_unary(&mut self) -> Result<PackageExpr, Error> {
+        match self.next_token()? {
+            Some((_, Token::Not)) => Ok(PackageExpr::Not(Box::new(self.parse_unary()?))),
+            Some((_, Token::LParen)) => {
+                let expr = self.parse_or()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Some((pos, Token::Ident(function))) => {
+                self.expect(Token::LParen)?;
+                let args = self.parse_args()?;
+                self.function(pos, function, args)
+            }
+            Some((pos, token)) => Err(self.error(pos, format!("unexpected {}", token))),
+            None => Err(self.error(self.input.len(), "unexpected end of expression")),
+        }
+    }
+
+    fn parse_args(&mut self) -> Result<Vec<String>, Error> {
+        let mut args = vec![];
+        if let Some((_, Token::RParen)) = self.peek_token()? {
+            self.next_token()?;
+            return Ok(args);
+        }
+        loop {
+            match self.next_token()? {
+                Some((_, Token::Ident(arg))) => args.push(arg.to_owned()),
+                Some((pos, token)) => {
+                    return Err(self.error(pos, format!("expected package name, found {}", token)))
+                }
+                None => return Err(self.error(self.input.len(), "unexpected end of expression")),
+            }
+            match self.next_token()? {
+                Some((_, Token::Comma)) => {}
+                Some((_, Token::RParen)) => return Ok(args),
+                Some((pos, token)) => {
+                    return Err(self.error(pos, format!("expected ',' or ')', found {}", token)))
+                }
+                None => {
+                    return Err(self.error(
+                        self.input.len(),
+                        "expected ',' or ')', found end of expression",
+                    ))
+                }
+            }
+        }
+    }
+
+    fn function(
+        &self,
+        pos: usize,
+        function: &str,
+        args: Vec<String>,
+    ) -> Result<PackageExpr, Error> {
+        let deps = |direction, kind| PackageExpr::Deps {
+            direction,
+            kind,
+            names: args.clone(),
+        };
+        let expr = match function {
+            "all" => PackageExpr::All,
+            "workspace" => PackageExpr::Workspace,
+            "package" => PackageExpr::Package(args.clone()),
+            "deps" => deps(DependencyDirection::Forward, None),
+            "rdeps" => deps(DependencyDirection::Reverse, None),
+            "normal_deps" => deps(DependencyDirection::Forward, Some(DependencyKind::Normal)),
+            "build_deps" => deps(DependencyDirection::Forward, Some(DependencyKind::Build)),
+            "dev_deps" => deps(
+                DependencyDirection::Forward,
+                Some(DependencyKind::Development),
+            ),
+            "normal_rdeps" => deps(DependencyDirection::Reverse, Some(DependencyKind::Normal)),
+            "build_rdeps" => deps(DependencyDirection::Reverse, Some(DependencyKind::Build)),
+            "dev_rdeps" => deps(
+                DependencyDirection::Reverse,
+                Some(DependencyKind::Development),
+            ),
+            _ => return Err(self.error(pos, format!("unknown function '{}'", function))),
+        };
+
+        let takes_args = !matches!(expr, PackageExpr::All | PackageExpr::Workspace);
+        if takes_args && args.is_empty() {
+            Err(self.error(
+                pos,
+                format!("'{}' requires at least one package name", function),
+            ))
+        } else if !takes_args && !args.is_empty() {
+            Err(self.error(pos, format!("'{}' doesn't take any arguments", function)))
+        } else {
+            Ok(expr)
+        }
+    }
+
+    fn expect(&mut self, expected: Token<'_>) -> Result<(), Error> {
+        match self.next_token()? {
+            Some((_, token)) if token == expected => Ok(()),
+            Some((pos, token)) => {
+                Err(self.error(pos, format!("expected {}, found {}", expected, token)))
+            }
+            None => Err(self.error(
+                self.input.len(),
+                format!("expected {}, found end of expression", expected),
+            )),
+        }
+    }
+
+    fn peek_token(&mut self) -> Result<Option<(usize, Token<'a>)>, Error> {
+        if self.peeked.is_none() {
+            let token = self.lex()?;
+            self.peeked = Some(token);
+        }
+        Ok(self.peeked.expect("just set"))
+    }
+
+    fn next_token(&mut self) -> Result<Option<(usize, Token<'a>)>, Error> {
+        match self.peeked.take() {
+            Some(token) => Ok(token),
+            None => self.lex(),
+        }
+    }
+
+    fn lex(&mut self) -> Result<Option<(usize, Token<'a>)>, Error> {
+        let rest = &self.input[self.pos..];
+        let trimmed = rest.trim_start();
+        self.pos += rest.len() - trimmed.len();
+        let start = self.pos;
+
+        let c = match trimmed.chars().next() {
+            Some(c) => c,
+            None => return Ok(None),
+        };
+        let token = match c {
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            '&' => Token::And,
+            '|' => Token::Or,
+            '!' => Token::Not,
+            c if is_ident_char(c) => {
+                let len = trimmed.find(|c| !is_ident_char(c)).unwrap_or(trimmed.len());
+                self.pos += len;
+                return Ok(Some((start, Token::Ident(&trimmed[..len]))));
+            }
+            c => return Err(self.error(start, format!("unexpected character '{}'", c))),
+        };
+        self.pos += c.len_utf8();
+        Ok(Some((start, token)))
+    }
+
+    fn error(&self, pos: usize, message: impl fmt::Display) -> Error {
+        Error::QueryExprError(format!(
+            "{} at position {} in '{}'",
+            message, pos, self.input
+        ))
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '*' | '?')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| (*name).to_owned()).collect()
+    }
+
+    #[test]
+    fn parse_valid() {
+        let expr: PackageExpr = "deps(serde) & !build_deps(cc) | workspace()"
+            .parse()
+            .expect("valid expression");
+        assert_eq!(
+            expr,
+            PackageExpr::Or(
+                Box::new(PackageExpr::And(
+                    Box::new(PackageExpr::Deps {
+                        direction: DependencyDirection::Forward,
+                        kind: None,
+                        names: names(&["serde"]),
+                    }),
+                    Box::new(PackageExpr::Not(Box::new(PackageExpr::Deps {
+                        direction: DependencyDirection::Forward,
+                        kind: Some(DependencyKind::Build),
+                        names: names(&["cc"]),
+                    }))),
+                )),
+                Box::new(PackageExpr::Workspace),
+            ),
+            "precedence is ! > & > |"
+        );
+
+        let expr: PackageExpr = " package( serde_json ,proc-macro* )&(all()|rdeps(a))"
+            .parse()
+            .expect("valid expression");
+        assert_eq!(
+            expr,
+            PackageExpr::And(
+                Box::new(PackageExpr::Package(names(&["serde_json", "proc-macro*"]))),
+                Box::new(PackageExpr::Or(
+                    Box::new(PackageExpr::All),
+                    Box::new(PackageExpr::Deps {
+                        direction: DependencyDirection::Reverse,
+                        kind: None,
+                        names: names(&["a"]),
+                    }),
+                )),
+            ),
+            "whitespace and parentheses"
+        );
+    }
+
+    #[test]
+    fn display_roundtrip() {
+        let inputs = [
+            "all()",
+            "deps(serde) & !build_deps(cc) | workspace()",
+            "package(a, b) & (dev_deps(c) | normal_rdeps(d))",
+            "!(workspace() | deps(a)) & !!all()",
+        ];
+        for input in &inputs {
+            let expr: PackageExpr = input.parse().expect("valid expression");
+            assert_eq!(&expr.to_string(), input, "display output matches input");
+            assert_eq!(
+                expr.to_string()
+                    .parse::<PackageExpr>()
+                    .expect("display output parses"),
+                expr,
+                "roundtrip for {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn parse_invalid() {
+        let invalids = [
+            ("", "unexpected end of expression at position 0"),
+            ("deps(serde", "expected ',' or ')', found end of expression"),
+            ("deps()", "'deps' requires at least one package name"),
+            ("workspace(a)", "'workspace' doesn't take any arguments"),
+            ("foo(a)", "unknown function 'foo' at position 0"),
+            ("all() all()", "unexpected 'all' at position 6"),
+            ("all() & ", "unexpected end of expression"),
+            (
+                "deps(a) + deps(b)",
+                "unexpected character '+' at position 8",
+            ),
+            ("(all()", "expected ')', found end of expression"),
+            ("deps", "expected '(', found end of expression"),
+        ];
+        for (input, message) in &invalids {
+            let err = input
+                .parse::<PackageExpr>()
+                .expect_err("invalid expression");
+            let err = err.to_string();
+            assert!(
+                err.contains(message),
+                "for input {:?}, error {:?} contains {:?}",
+                input,
+                err,
+                message
+            );
+        }
+    }
+}
@@ -6,11 +6,11 @@ use crate::{
     graph::{
         feature::{FeatureFilter, FeatureSet},
         resolve_core::{ResolveCore, Topo},
-        DependencyDirection, PackageGraph, PackageIx, PackageLink, PackageLinkImpl,
-        PackageMetadata, PackageQuery,
+        wildcard_match, DependencyDirection, PackageGraph, PackageGraphData, PackageIx,
+        PackageLink, PackageLinkImpl, PackageMetadata, PackageQuery, SourceKind, WorkspaceImpl,
     },
     petgraph_support::{
-        dot::{DotFmt, DotVisitor, DotWrite},
+        dot::{DotAttributes, DotFmt, DotVisitor, DotWrite},
         edge_ref::GraphEdgeRef,
         IxBitSet,
     },
@@ -19,11 +19,16 @@ use crate::{
 };
 use camino::Utf8Path;
 use fixedbitset::FixedBitSet;
+use once_cell::sync::OnceCell;
 use petgraph::{
     prelude::*,
     visit::{NodeFiltered, NodeRef},
 };
-use std::fmt;
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
 
 impl PackageGraph {
     /// Creates a new `PackageSet` consisting of all members of this package graph.
@@ -148,11 +153,58 @@ impl PackageGraph {
             .collect();
         PackageSet::from_included(self, included)
     }
+
+    /// Creates a new `PackageSet` consisting of packages with names that match the given glob
+    /// pattern.
+    ///
+    /// In the pattern, `*` matches any sequence of characters and `?` matches any single
+    /// character. For example, `tokio-*` matches `tokio-util` and `tokio-stream`, but not `tokio`.
+    /// Both workspace and third-party packages are matched.
+    ///
+    /// The result is empty if no package names match the pattern.
+    pub fn resolve_package_name_glob(&self, pattern: impl AsRef<str>) -> PackageSet<'_> {
+        let pattern = pattern.as_ref().as_bytes();
+        self.resolve_package_name_fn(|name| wildcard_match(pattern, name.as_bytes()))
+    }
+
+    /// Creates a new `PackageSet` consisting of packages with names for which the callback returns
+    /// true.
+    ///
+    /// The callback is called once for every package in the graph. This can be used to match
+    /// package names against regular expressions, for example by passing in
+    /// `|name| regex.is_match(name)` with a `Regex` from the `regex` crate.
+    ///
+    /// To start a query from the resulting packages, use
+    /// [`PackageSet::to_package_query`](PackageSet::to_package_query).
+    pub fn resolve_package_name_fn(
+        &self,
+        mut callback: impl FnMut(&str) -> bool,
+    ) -> PackageSet<'_> {
+        let included: IxBitSet = self
+            .packages()
+            .filter_map(|package| {
+                if callback(package.name()) {
+                    Some(package.package_ix())
+                } else {
+                    None
+                }
+            })
+            .collect();
+        PackageSet::from_included(self, included)
+    }
 }
 
 /// A set of resolved packages in a package graph.
 ///
 /// Created by `PackageQuery::resolve`.
+///
+/// ## Iteration order
+///
+/// Iterators that take a `DependencyDirection` return packages in topological order in that
+/// direction. Iteration is deterministic: the same set, built from the same `cargo metadata`
+/// output, is always iterated over in the same order. To iterate in lexical order instead, for
+/// example for snapshot tests, use [`packages_in`](Self::packages_in) with
+/// [`IterationOrder::Lexical`](crate::graph::IterationOrder::Lexical).
 #[derive(Clone, Debug)]
 pub struct PackageSet<'g> {
     graph: DebugIgnore<&'g PackageGraph>,
@@ -164,9 +216,15 @@ assert_covariant!(PackageSet);
 impl<'g> PackageSet<'g> {
     pub(super) fn new(query: PackageQuery<'g>) -> Self {
         let graph = query.graph;
+        let core = match query.max_depth {
+            Some(max_depth) => {
+                ResolveCore::with_depth_limit(graph.dep_graph(), query.params, max_depth, |_| true)
+            }
+            None => ResolveCore::new(graph.dep_graph(), query.params),
+        };
         Self {
             graph: DebugIgnore(graph),
-            core: ResolveCore::new(graph.dep_graph(), query.params),
+            core,
         }
     }
 
@@ -183,12 +241,19 @@ impl<'g> PackageSet<'g> {
     ) -> Self {
         let graph = query.graph;
         let params = query.params.clone();
+        let edge_filter = |edge| {
+            let link = graph.edge_ref_to_link(edge);
+            resolver.accept(&query, link)
+        };
+        let core = match query.max_depth {
+            Some(max_depth) => {
+                ResolveCore::with_depth_limit(graph.dep_graph(), params, max_depth, edge_filter)
+            }
+            None => ResolveCore::with_edge_filter(graph.dep_graph(), params, edge_filter),
+        };
         Self {
             graph: DebugIgnore(graph),
-            core: ResolveCore::with_edge_filter(graph.dep_graph(), params, |edge| {
-                let link = graph.edge_ref_to_link(edge);
-                resolver.accept(&query, link)
-            }),
+            core,
         }
     }
 
@@ -290,6 +355,45 @@ impl<'g> PackageSet<'g> {
         res
     }
 
+    /// Returns true if every package in `self` is also in `other`.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the package graphs associated with `self` and `other` don't match.
+    pub fn is_subset(&self, other: &Self) -> bool {
+        assert!(
+            ::std::ptr::eq(self.graph.0, other.graph.0),
+            "package graphs passed into is_subset() match"
+        );
+        self.core.is_subset(&other.core)
+    }
+
+    /// Returns true if every package in `other` is also in `self`.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the package graphs associated with `self` and `other` don't match.
+    pub fn is_superset(&self, other: &Self) -> bool {
+        assert!(
+            ::std::ptr::eq(self.graph.0, other.graph.0),
+            "package graphs passed into is_superset() match"
+        );
+        other.core.is_subset(&self.core)
+    }
+
+    /// Returns true if `self` and `other` have no packages in common.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the package graphs associated with `self` and `other` don't match.
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        assert!(
+            ::std::ptr::eq(self.graph.0, other.graph.0),
+            "package graphs passed into is_disjoint() match"
+        );
+        self.core.is_disjoint(&other.core)
+    }
+
     /// Returns a `PackageSet` on which a filter has been applied.
     ///
     /// Filters out all values for which the callback returns false.
@@ -385,6 +489,185 @@ impl<'g> PackageSet<'g> {
         )
     }
 
+    /// Returns a `PackageSet` containing packages whose sources are of a kind for which the
+    /// callback returns true.
+    ///
+    /// For more about source kinds, see the documentation for [`SourceKind`](SourceKind).
+    pub fn filter_source_kind(&self, mut callback: impl FnMut(SourceKind<'g>) -> bool) -> Self {
+        self.filter(DependencyDirection::Forward, |package| {
+            callback(package.source().kind())
+        })
+    }
+
+    /// Groups the packages in this `PackageSet` by the kind of their sources.
+    ///
+    /// Each `PackageSet` in the returned map is non-empty.
+    ///
+    /// For more about source kinds, see the documentation for [`SourceKind`](SourceKind).
+    pub fn group_by_source_kind(&self) -> BTreeMap<SourceKind<'g>, PackageSet<'g>> {
+        let graph = *self.graph;
+        let mut groups: BTreeMap<_, IxBitSet> = BTreeMap::new();
+        for package in self.packages(DependencyDirection::Forward) {
+            groups
+                .entry(package.source().kind())
+                .or_insert_with(|| IxBitSet::with_capacity(self.core.included.len()))
+                .insert_node_ix(package.package_ix());
+        }
+        groups
+            .into_iter()
+            .map(|(kind, included)| (kind, Self::from_included(graph, included)))
+            .collect()
+    }
+
+    /// Returns a `PackageSet` containing the packages in this set that have a build script.
+    ///
+    /// Build scripts have to be compiled and run before the rest of the package is built, so
+    /// they're often responsible for a significant portion of build times.
+    pub fn build_script_packages(&self) -> Self {
+        self.filter(DependencyDirection::Forward, |package| {
+            package.has_build_script()
+        })
+    }
+
+    /// Returns a `PackageSet` containing the packages in this set that are procedural macros.
+    ///
+    /// Procedural macros, and everything they depend on, have to be built for the host platform
+    /// before any packages that use them.
+    pub fn proc_macro_packages(&self) -> Self {
+        self.filter(DependencyDirection::Forward, |package| {
+            package.is_proc_macro()
+        })
+    }
+
+    /// Returns a `PackageSet` containing the workspace packages in this set.
+    pub fn workspace_packages(&self) -> Self {
+        self.filter(DependencyDirection::Forward, |package| {
+            package.in_workspace()
+        })
+    }
+
+    /// Returns a `PackageSet` containing the third-party packages in this set, i.e. the packages
+    /// that aren't in the workspace.
+    ///
+    /// Path dependencies outside the workspace are considered third-party.
+    ///
+    /// This is the union of [`direct_third_party_packages`](Self::direct_third_party_packages) and
+    /// [`transitive_third_party_packages`](Self::transitive_third_party_packages).
+    pub fn third_party_packages(&self) -> Self {
+        self.filter(DependencyDirection::Forward, |package| {
+            !package.in_workspace()
+        })
+    }
+
+    /// Returns a `PackageSet` containing the third-party packages in this set that are directly
+    /// depended on by a workspace package in this set.
+    ///
+    /// These are the packages whose versions are specified in the workspace's `Cargo.toml` files.
+    pub fn direct_third_party_packages(&self) -> Self {
+        self.filter(DependencyDirection::Forward, |package| {
+            !package.in_workspace()
+                && package.reverse_direct_links().any(|link| {
+                    let from = link.from();
+                    from.in_workspace() && self.contains_ix(from.package_ix())
+                })
+        })
+    }
+
+    /// Returns a `PackageSet` containing the third-party packages in this set that aren't directly
+    /// depended on by any workspace package in this set.
+    ///
+    /// These packages are only in the set because other third-party packages depend on them.
+    pub fn transitive_third_party_packages(&self) -> Self {
+        self.third_party_packages()
+            .difference(&self.direct_third_party_packages())
+    }
+
+    /// Returns a `PackageSet` containing the packages in this set that transitively depend on a
+    /// package in `targets`, along with the packages in `targets` that are in this set.
+    ///
+    /// Only links between packages in this set are followed.
+    ///
+    /// Combined with [`build_script_packages`](Self::build_script_packages) and
+    /// [`proc_macro_packages`](Self::proc_macro_packages), this can be used to find out why build
+    /// scripts or procedural macros are part of a build. For example, to find the direct
+    /// dependencies of a package that transitively pull in a build script:
+    ///
+    /// ```
+    /// use guppy::{graph::DependencyDirection, MetadataCommand};
+    ///
+    /// let graph = MetadataCommand::new().build_graph().expect("guppy graph constructed");
+    /// let package = graph.workspace().member_by_name("guppy").expect("guppy is a member");
+    /// let package_set = graph.query_forward([package.id()]).unwrap().resolve();
+    /// let pulls_in_build_scripts =
+    ///     package_set.depends_on_any(&package_set.build_script_packages());
+    /// for link in package.direct_links() {
+    ///     if pulls_in_build_scripts.contains(link.to().id()).unwrap() {
+    ///         println!("{} pulls in a build script", link.to().name());
+    ///     }
+    /// }
+    /// ```
+    pub fn depends_on_any(&self, targets: &PackageSet<'g>) -> Self {
+        let graph = self.graph;
+        let roots = targets.intersection(self);
+        graph
+            .query_reverse(roots.package_ids(DependencyDirection::Forward))
+            .expect("package IDs in the set are known")
+            .resolve_with_fn(|_, link| {
+                self.contains(link.from().id())
+                    .expect("package IDs in the graph are known")
+            })
+    }
+
+    /// Returns a `PackageSet` containing packages whose Rust editions are ones for which the
+    /// callback returns true.
+    ///
+    /// Editions are passed in as they're written in `Cargo.toml`, e.g. `"2018"`. For example, to
+    /// find packages that haven't been migrated to the 2021 edition:
+    ///
+    /// ```
+    /// use guppy::MetadataCommand;
+    ///
+    /// let graph = MetadataCommand::new().build_graph().expect("guppy graph constructed");
+    /// let old_editions = graph.resolve_all().filter_edition(|edition| edition < "2021");
+    /// for package in old_editions.packages(guppy::graph::DependencyDirection::Forward) {
+    ///     assert!(package.edition() < "2021", "package is on an older edition");
+    /// }
+    /// ```
+    pub fn filter_edition(&self, mut callback: impl FnMut(&'g str) -> bool) -> Self {
+        self.filter(DependencyDirection::Forward, |package| {
+            callback(package.edition())
+        })
+    }
+
+    /// Groups the packages in this `PackageSet` by their Rust editions.
+    ///
+    /// Each `PackageSet` in the returned map is non-empty.
+    pub fn group_by_edition(&self) -> BTreeMap<&'g str, PackageSet<'g>> {
+        let graph = *self.graph;
+        let mut groups: BTreeMap<_, IxBitSet> = BTreeMap::new();
+        for package in self.packages(DependencyDirection::Forward) {
+            groups
+                .entry(package.edition())
+                .or_insert_with(|| IxBitSet::with_capacity(self.core.included.len()))
+                .insert_node_ix(package.package_ix());
+        }
+        groups
+            .into_iter()
+            .map(|(edition, included)| (edition, Self::from_included(graph, included)))
+            .collect()
+    }
+
+    /// Returns the number of packages in this `PackageSet` for each Rust edition.
+    ///
+    /// Only editions used by at least one package are included.
+    pub fn edition_counts(&self) -> BTreeMap<&'g str, usize> {
+        let mut counts = BTreeMap::new();
+        for package in self.packages(DependencyDirection::Forward) {
+            *counts.entry(package.edition()).or_insert(0) += 1;
+        }
+        counts
+    }
+
     // ---
     // Conversion to FeatureSet
     // ---
@@ -403,6 +686,82 @@ impl<'g> PackageSet<'g> {
         FeatureSet::from_included(feature_graph, included)
     }
 
+    // ---
+    // Conversion to PackageGraph
+    // ---
+
+    /// Creates a new, standalone `PackageGraph` consisting of the packages in this set and the
+    /// links between them.
+    ///
+    /// Links to or from packages outside this set are dropped, as are workspace members that aren't
+    /// in this set. The workspace root, target directory and metadata table are preserved.
+    ///
+    /// Package IDs and metadata are the same as in this graph, so the new graph can be queried,
+    /// serialized, or passed to code that expects a full `PackageGraph`. Anything computed on
+    /// demand, like the feature graph, is computed afresh for the new graph.
+    pub fn to_package_graph(&self) -> PackageGraph {
+        let graph = self.graph.0;
+        let included = &self.core.included;
+        // filter_map preserves the relative order of nodes and edges, and drops edges whose
+        // endpoints are filtered out.
+        let dep_graph = graph.dep_graph.filter_map(
+            |package_ix, package_id| {
+                included
+                    .contains(package_ix.index())
+                    .then(|| package_id.clone())
+            },
+            |_, link| Some(link.clone()),
+        );
+
+        let packages: HashMap<_, _> = dep_graph
+            .node_indices()
+            .map(|package_ix| {
+                let package_id = &dep_graph[package_ix];
+                let mut package = graph.data.packages[package_id].clone();
+                package.package_ix = package_ix;
+                (package_id.clone(), package)
+            })
+            .collect();
+
+        let workspace = &graph.data.workspace;
+        let workspace = WorkspaceImpl {
+            root: workspace.root.clone(),
+            target_directory: workspace.target_directory.clone(),
+            metadata_table: workspace.metadata_table.clone(),
+            members_by_path: workspace
+                .members_by_path
+                .iter()
+                .filter(|(_, id)| packages.contains_key(*id))
+                .map(|(path, id)| (path.clone(), id.clone()))
+                .collect(),
+            members_by_name: workspace
+                .members_by_name
+                .iter()
+                .filter(|(_, id)| packages.contains_key(*id))
+                .map(|(name, id)| (name.clone(), id.clone()))
+                .collect(),
+            default_members: workspace.default_members.as_ref().map(|default_members| {
+                default_members
+                    .iter()
+                    .filter(|id| packages.contains_key(*id))
+                    .cloned()
+                    .collect()
+            }),
+            #[cfg(feature = "proptest1")]
+            name_list: OnceCell::new(),
+        };
+
+        PackageGraph {
+            dep_graph,
+            sccs: OnceCell::new(),
+            feature_graph: OnceCell::new(),
+            data: PackageGraphData {
+                packages,
+                workspace,
+            },
+        }
+    }
+
     // ---
     // Iterators
     // ---
@@ -444,6 +803,28 @@ impl<'g> PackageSet<'g> {
             .map(move |package_id| graph.metadata(package_id).expect("known package IDs"))
     }
 
+    /// Iterates over packages that have a `package.metadata.<tool>` table, along with the result
+    /// of deserializing that table into `T`, in topological order in the direction specified.
+    ///
+    /// Packages without a table for `tool` are skipped. An error for one package doesn't stop
+    /// iteration, so that every misconfigured package can be reported at once. For more, see
+    /// [`PackageMetadata::tool_metadata`](crate::graph::PackageMetadata::tool_metadata).
+    pub fn tool_metadata<'a, T>(
+        &'a self,
+        tool: &'a str,
+        direction: DependencyDirection,
+    ) -> impl Iterator<Item = (PackageMetadata<'g>, Result<T, Error>)> + 'a
+    where
+        T: Deserialize<'g> + 'a,
+    {
+        self.packages(direction).filter_map(move |package| {
+            package
+                .tool_metadata(tool)
+                .transpose()
+                .map(|result| (package, result))
+        })
+    }
+
     /// Returns the set of "root package" IDs in the specified direction.
     ///
     /// * If direction is Forward, return the set of packages that do not have any dependencies
@@ -520,6 +901,9 @@ impl<'g> PackageSet<'g> {
     }
 
     /// Constructs a representation of the selected packages in `dot` format.
+    ///
+    /// Labels, other graphviz attributes and clusters are determined by the visitor. For a visitor
+    /// with reasonable defaults, see [`PackageDotStyle`].
     pub fn display_dot<'a, V: PackageDotVisitor + 'g>(
         &'a self,
         visitor: V,
@@ -538,6 +922,16 @@ impl<'g> PackageSet<'g> {
         self.core.included.ones().map(NodeIndex::new)
     }
 
+    /// Returns all the packages without topologically sorting them.
+    pub(super) fn packages_unordered(&self) -> impl Iterator<Item = PackageMetadata<'g>> + '_ {
+        let graph = self.graph;
+        self.ixs_unordered().map(move |package_ix| {
+            graph
+                .metadata(&graph.dep_graph[package_ix])
+                .expect("valid package index")
+        })
+    }
+
     pub(super) fn contains_ix(&self, package_ix: NodeIndex<PackageIx>) -> bool {
         self.core.contains(package_ix)
     }
@@ -602,6 +996,149 @@ pub trait PackageDotVisitor {
     /// Visits this dependency link. The implementation may output a label for this link to the
     /// given `DotWrite`.
     fn visit_link(&self, link: PackageLink<'_>, f: &mut DotWrite<'_, '_>) -> fmt::Result;
+
+    /// Sets graphviz attributes, such as `color` or `shape`, for this package. Setting `label`
+    /// overrides the label written by `visit_package`.
+    ///
+    /// The default implementation doesn't set any attributes.
+    fn package_attributes(&self, package: PackageMetadata<'_>, attrs: &mut DotAttributes) {
+        let _ = (package, attrs);
+    }
+
+    /// Returns the name of the cluster to draw this package in, or `None` to draw it outside any
+    /// clusters.
+    ///
+    /// Packages with the same cluster name are drawn together in a box labeled with that name.
+    /// The default implementation doesn't put packages in clusters.
+    fn package_cluster(&self, package: PackageMetadata<'_>) -> Option<String> {
+        let _ = package;
+        None
+    }
+
+    /// Sets graphviz attributes, such as `color` or `style`, for this dependency link. Setting
+    /// `label` overrides the label written by `visit_link`.
+    ///
+    /// The default implementation doesn't set any attributes.
+    fn link_attributes(&self, link: PackageLink<'_>, attrs: &mut DotAttributes) {
+        let _ = (link, attrs);
+    }
+}
+
+impl<T: PackageDotVisitor + ?Sized> PackageDotVisitor for &T {
+    fn visit_package(&self, package: PackageMetadata<'_>, f: &mut DotWrite<'_, '_>) -> fmt::Result {
+        (**self).visit_package(package, f)
+    }
+
+    fn visit_link(&self, link: PackageLink<'_>, f: &mut DotWrite<'_, '_>) -> fmt::Result {
+        (**self).visit_link(link, f)
+    }
+
+    fn package_attributes(&self, package: PackageMetadata<'_>, attrs: &mut DotAttributes) {
+        (**self).package_attributes(package, attrs)
+    }
+
+    fn package_cluster(&self, package: PackageMetadata<'_>) -> Option<String> {
+        (**self).package_cluster(package)
+    }
+
+    fn link_attributes(&self, link: PackageLink<'_>, attrs: &mut DotAttributes) {
+        (**self).link_attributes(link, attrs)
+    }
+}
+
+/// A [`PackageDotVisitor`] with reasonable defaults for visualizing dependency graphs.
+///
+/// * Packages are labeled with their names and versions.
+/// * Workspace packages are drawn as boxes, and, by default, clustered together.
+/// * Links are labeled with the names they're depended on under. By default, links that are only
+///   build dependencies are drawn dashed, and links that are only dev-dependencies are drawn
+///   dotted and gray.
+///
+/// To customize output further, implement `PackageDotVisitor` for a type that wraps a
+/// `PackageDotStyle` and forwards to it.
+///
+/// ## Examples
+///
+/// ```
+/// use guppy::{graph::PackageDotStyle, MetadataCommand};
+///
+/// let graph = MetadataCommand::new().build_graph().unwrap();
+/// let package_set = graph.query_workspace().resolve();
+/// let dot = package_set.display_dot(PackageDotStyle::new()).to_string();
+/// assert!(dot.contains("subgraph cluster_0"), "workspace packages are clustered");
+/// ```
+#[derive(Clone, Debug)]
+pub struct PackageDotStyle {
+    workspace_cluster: Option<String>,
+    style_links_by_kind: bool,
+}
+
+impl PackageDotStyle {
+    /// Creates a new `PackageDotStyle` with the default settings.
+    pub fn new() -> Self {
+        Self {
+            workspace_cluster: Some("workspace".to_owned()),
+            style_links_by_kind: true,
+        }
+    }
+
+    /// Sets the name of the cluster workspace packages are drawn in, or `None` to not cluster
+    /// them.
+    ///
+    /// The default is `"workspace"`.
+    pub fn set_workspace_cluster(&mut self, name: Option<impl Into<String>>) -> &mut Self {
+        self.workspace_cluster = name.map(|name| name.into());
+        self
+    }
+
+    /// Sets whether links are styled by the kinds of dependencies they represent.
+    ///
+    /// The default is true.
+    pub fn set_style_links_by_kind(&mut self, style_links_by_kind: bool) -> &mut Self {
+        self.style_links_by_kind = style_links_by_kind;
+        self
+    }
+}
+
+impl Default for PackageDotStyle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PackageDotVisitor for PackageDotStyle {
+    fn visit_package(&self, package: PackageMetadata<'_>, f: &mut DotWrite<'_, '_>) -> fmt::Result {
+        write!(f, "{} {}", package.name(), package.version())
+    }
+
+    fn visit_link(&self, link: PackageLink<'_>, f: &mut DotWrite<'_, '_>) -> fmt::Result {
+        write!(f, "{}", link.dep_name())
+    }
+
+    fn package_attributes(&self, package: PackageMetadata<'_>, attrs: &mut DotAttributes) {
+        if package.in_workspace() {
+            attrs.set("shape", "box");
+        }
+    }
+
+    fn package_cluster(&self, package: PackageMetadata<'_>) -> Option<String> {
+        if package.in_workspace() {
+            self.workspace_cluster.clone()
+        } else {
+            None
+        }
+    }
+
+    fn link_attributes(&self, link: PackageLink<'_>, attrs: &mut DotAttributes) {
+        if !self.style_links_by_kind || link.normal().is_present() {
+            return;
+        }
+        if link.build().is_present() {
+            attrs.set("style", "dashed");
+        } else {
+            attrs.set("style", "dotted").set("color", "gray");
+        }
+    }
 }
 
 struct VisitorWrap<'g, V> {
@@ -633,4 +1170,25 @@ where
         let link = self.graph.edge_ref_to_link(edge.into_edge_reference());
         self.inner.visit_link(link, f)
     }
+
+    fn node_attributes(&self, node: NR, attrs: &mut DotAttributes) {
+        let metadata = self
+            .graph
+            .metadata(node.weight())
+            .expect("visited node should have associated metadata");
+        self.inner.package_attributes(metadata, attrs)
+    }
+
+    fn node_cluster(&self, node: NR) -> Option<String> {
+        let metadata = self
+            .graph
+            .metadata(node.weight())
+            .expect("visited node should have associated metadata");
+        self.inner.package_cluster(metadata)
+    }
+
+    fn edge_attributes(&self, edge: ER, attrs: &mut DotAttributes) {
+        let link = self.graph.edge_ref_to_link(edge.into_edge_reference());
+        self.inner.link_attributes(link, attrs)
+    }
 }
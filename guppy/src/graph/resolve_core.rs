@@ -4,7 +4,10 @@
 use crate::{
     debug_ignore::DebugIgnore,
     graph::{
-        query_core::{all_visit_map, reachable_map, reachable_map_buffered_filter, QueryParams},
+        query_core::{
+            all_visit_map, reachable_map, reachable_map_buffered_filter,
+            reachable_map_depth_limited, QueryParams,
+        },
         DependencyDirection, GraphSpec,
     },
     petgraph_support::{
@@ -89,6 +92,38 @@ impl<G: GraphSpec> ResolveCore<G> {
         }
     }
 
+    /// Only includes nodes that are at most `max_depth` edges away from the initials.
+    ///
+    /// The arguments to the edge filter are the (source, target, edge ix), unreversed.
+    pub(super) fn with_depth_limit<'g>(
+        graph: &'g Graph<G::Node, G::Edge, Directed, G::Ix>,
+        params: QueryParams<G>,
+        max_depth: usize,
+        edge_filter: impl FnMut(EdgeReference<'g, G::Edge, G::Ix>) -> bool,
+    ) -> Self {
+        let (included, len) = match params {
+            QueryParams::Forward(initials) => reachable_map_depth_limited(
+                graph,
+                Outgoing,
+                initials.into_inner(),
+                max_depth,
+                edge_filter,
+            ),
+            QueryParams::Reverse(initials) => reachable_map_depth_limited(
+                graph,
+                Incoming,
+                initials.into_inner(),
+                max_depth,
+                edge_filter,
+            ),
+        };
+        Self {
+            included,
+            len,
+            _phantom: PhantomData,
+        }
+    }
+
     /// The arguments to the edge filter are the (source, target, edge ix), unreversed.
     pub(super) fn with_buffered_edge_filter<'g>(
         graph: &'g Graph<G::Node, G::Edge, Directed, G::Ix>,
@@ -162,6 +197,14 @@ impl<G: GraphSpec> ResolveCore<G> {
         self.invalidate_caches();
     }
 
+    pub(super) fn is_subset(&self, other: &Self) -> bool {
+        self.included.is_subset(&other.included)
+    }
+
+    pub(super) fn is_disjoint(&self, other: &Self) -> bool {
+        self.included.is_disjoint(&other.included)
+    }
+
     pub(super) fn invalidate_caches(&mut self) {
         self.len = self.included.count_ones(..);
     }
@@ -0,0 +1,170 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Build schedules for package and Cargo sets.
+
+use crate::graph::{
+    cargo::{BuildPlatform, CargoSet},
+    DependencyDirection, PackageLink, PackageMetadata, PackageSet,
+};
+use std::collections::HashMap;
+
+impl<'g> PackageSet<'g> {
+    /// Returns the build order for this package set, as a list of *waves*.
+    ///
+    /// Every package in a wave only depends on packages in earlier waves, so all the packages in a
+    /// wave can be built concurrently once the previous waves are complete. Packages are placed in
+    /// the earliest wave possible, and packages within a wave are sorted by package ID.
+    ///
+    /// Only links to packages within this set are considered. Dev-only links are ignored, since
+    /// they're only required to build tests, examples and benchmarks.
+    ///
+    /// Cargo doesn't permit cycles that don't go through dev-only links. If this set has any such
+    /// cycles, the packages that participate in or depend on them are placed in a final wave.
+    pub fn build_waves(&self) -> Vec<Vec<PackageMetadata<'g>>> {
+        let packages: Vec<_> = self.packages(DependencyDirection::Forward).collect();
+        let index: HashMap<_, _> = packages
+            .iter()
+            .enumerate()
+            .map(|(idx, package)| (package.id(), idx))
+            .collect();
+        let waves = build_waves(&packages, |package| {
+            package
+                .direct_links()
+                .filter(|link| !link.dev_only())
+                .filter_map(|link| index.get(link.to().id()).copied())
+                .collect()
+        });
+
+        waves
+            .into_iter()
+            .map(|wave| {
+                let mut wave: Vec<_> = wave.into_iter().map(|idx| packages[idx]).collect();
+                wave.sort_by_key(|package| package.id());
+                wave
+            })
+            .collect()
+    }
+}
+
+impl<'g> CargoSet<'g> {
+    /// Returns the build order for this Cargo set, as a list of *waves*.
+    ///
+    /// Each entry is a package built on either the target or the host platform. A package that is
+    /// built on both platforms appears twice, and the two builds are scheduled independently.
+    ///
+    /// Every entry in a wave only depends on entries in earlier waves, so all the entries in a wave
+    /// can be built concurrently once the previous waves are complete. Within a wave, entries are
+    /// sorted by package ID, then by build platform.
+    ///
+    /// Dependencies are computed from the links between packages in the set, following Cargo's
+    /// rules: procedural macros and build dependencies are built on the host. Platform-specific
+    /// links are always considered, so the schedule may be more conservative than Cargo's own.
+    /// Dev-only links are ignored, as with
+    /// [`PackageSet::build_waves`](crate::graph::PackageSet::build_waves).
+    pub fn build_waves(&self) -> Vec<Vec<(BuildPlatform, PackageMetadata<'g>)>> {
+        let target_set = self.target_features().to_package_set();
+        let host_set = self.host_features().to_package_set();
+        let mut units: Vec<_> = target_set
+            .packages(DependencyDirection::Forward)
+            .map(|package| (BuildPlatform::Target, package))
+            .collect();
+        units.extend(
+            host_set
+                .packages(DependencyDirection::Forward)
+                .map(|package| (BuildPlatform::Host, package)),
+        );
+        let index: HashMap<_, _> = units
+            .iter()
+            .enumerate()
+            .map(|(idx, (build_platform, package))| ((*build_platform, package.id()), idx))
+            .collect();
+
+        let waves = build_waves(&units, |(build_platform, package)| {
+            package
+                .direct_links()
+                .flat_map(|link| dep_platforms(link, *build_platform).into_iter().flatten())
+                .filter_map(|(dep_platform, link)| {
+                    index.get(&(dep_platform, link.to().id())).copied()
+                })
+                .collect()
+        });
+
+        waves
+            .into_iter()
+            .map(|wave| {
+                let mut wave: Vec<_> = wave.into_iter().map(|idx| units[idx]).collect();
+                wave.sort_by_key(|(build_platform, package)| (package.id(), *build_platform));
+                wave
+            })
+            .collect()
+    }
+}
+
+/// Returns the platforms the `to` end of this link is built on, given the platform the `from` end
+/// is built on.
+#[allow(clippy::type_complexity)]
+fn dep_platforms(
+    link: PackageLink<'_>,
+    build_platform: BuildPlatform,
+) -> [Option<(BuildPlatform, PackageLink<'_>)>; 2] {
+    let normal = if link.normal().is_present() {
+        if link.to().is_proc_macro() {
+            Some((BuildPlatform::Host, link))
+        } else {
+            Some((build_platform, link))
+        }
+    } else {
+        None
+    };
+    let build = if link.build().is_present() {
+        Some((BuildPlatform::Host, link))
+    } else {
+        None
+    };
+    [normal, build]
+}
+
+/// Groups nodes into waves using Kahn's algorithm, given a function that returns the indexes of
+/// the nodes each node depends on.
+///
+/// Returns indexes into `nodes`.
+fn build_waves<N>(nodes: &[N], mut deps: impl FnMut(&N) -> Vec<usize>) -> Vec<Vec<usize>> {
+    let mut remaining = vec![0; nodes.len()];
+    let mut dependents = vec![vec![]; nodes.len()];
+    for (idx, node) in nodes.iter().enumerate() {
+        let mut dep_idxs = deps(node);
+        dep_idxs.sort_unstable();
+        dep_idxs.dedup();
+        dep_idxs.retain(|&dep_idx| dep_idx != idx);
+        remaining[idx] = dep_idxs.len();
+        for dep_idx in dep_idxs {
+            dependents[dep_idx].push(idx);
+        }
+    }
+
+    let mut waves = vec![];
+    let mut current: Vec<_> = (0..nodes.len())
+        .filter(|&idx| remaining[idx] == 0)
+        .collect();
+    while !current.is_empty() {
+        let mut next = vec![];
+        for &idx in &current {
+            for &dependent_idx in &dependents[idx] {
+                remaining[dependent_idx] -= 1;
+                if remaining[dependent_idx] == 0 {
+                    next.push(dependent_idx);
+                }
+            }
+        }
+        waves.push(current);
+        current = next;
+    }
+
+    // Anything left over is part of, or depends on, a cycle.
+    let cyclic: Vec<_> = (0..nodes.len()).filter(|&idx| remaining[idx] > 0).collect();
+    if !cyclic.is_empty() {
+        waves.push(cyclic);
+    }
+    waves
+}
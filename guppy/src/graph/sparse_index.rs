@@ -0,0 +1,532 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Lookups of published versions through sparse registry indexes.
+
+use crate::{
+    graph::{DependencyDirection, PackageMetadata, PackageSet, SourceKind},
+    Error,
+};
+use semver::Version;
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+/// A client for a [sparse registry index](https://doc.rust-lang.org/cargo/reference/registry-index.html#sparse-protocol),
+/// such as the one for `crates.io`.
+///
+/// Index entries are fetched over HTTP the first time they're requested, and cached for the
+/// lifetime of this client. Entries can also be added ahead of time with
+/// [`insert_entry`](Self::insert_entry), for example from a local mirror of the index.
+///
+/// Requires the `sparse-index` feature to be enabled.
+///
+/// # Examples
+///
+/// ```no_run
+/// use guppy::graph::SparseIndex;
+/// use guppy::MetadataCommand;
+///
+/// let graph = MetadataCommand::new().build_graph().expect("guppy graph constructed");
+/// let mut index = SparseIndex::crates_io();
+/// let report = graph
+///     .resolve_all()
+///     .latest_versions(&mut index)
+///     .expect("index entries fetched");
+/// for latest in report.outdated() {
+///     println!(
+///         "{} {}: latest compatible {:?}, latest {:?}",
+///         latest.package().name(),
+///         latest.package().version(),
+///         latest.latest_compatible(),
+///         latest.latest(),
+///     );
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SparseIndex {
+    url: String,
+    agent: ureq::Agent,
+    // None means that the package isn't present in the index.
+    entries: HashMap<String, Option<IndexEntry>>,
+}
+
+impl SparseIndex {
+    /// The URL of the sparse index for `crates.io`.
+    pub const CRATES_IO_URL: &'static str = "https://index.crates.io/";
+
+    /// Creates a new client for the `crates.io` sparse index.
+    pub fn crates_io() -> Self {
+        Self::new(Self::CRATES_IO_URL)
+    }
+
+    /// Creates a new client for the sparse index at the given URL.
+    ///
+    /// The URL may optionally start with `sparse+`, as in Cargo's configuration.
+    ///
+    /// Requests time out if connecting takes longer than 30 seconds, or if reading a response
+    /// stalls for longer than 60 seconds. To configure timeouts, proxies or TLS differently, use
+    /// [`with_agent`](Self::with_agent).
+    pub fn new(url: impl Into<String>) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .user_agent(&format!("guppy/{}", env!("CARGO_PKG_VERSION")))
+            .timeout_connect(Duration::from_secs(30))
+            .timeout_read(Duration::from_secs(60))
+            .build();
+        Self::with_agent(url, agent)
+    }
+
+    /// Creates a new client for the sparse index at the given URL, making requests through the
+    /// given [`ureq::Agent`].
+    ///
+    /// The URL may optionally start with `sparse+`, as in Cargo's configuration.
+    pub fn with_agent(url: impl Into<String>, agent: ureq::Agent) -> Self {
+        let url = url.into();
+        let mut url = match url.strip_prefix("sparse+") {
+            Some(url) => url.to_owned(),
+            None => url,
+        };
+        if !url.ends_with('/') {
+            url.push('/');
+        }
+        Self {
+            url,
+            agent,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Returns the URL of this index, without the `sparse+` prefix and with a trailing `/`.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns true if this index is the index for `crates.io`.
+    pub fn is_crates_io(&self) -> bool {
+        self.url == Self::CRATES_IO_URL
+    }
+
+    /// Parses the contents of the index file for the given package, and caches the result.
+    ///
+    /// Later lookups for this package will use this entry rather than fetching it.
+    pub fn insert_entry(
+        &mut self,
+        name: impl Into<String>,
+        contents: &str,
+    ) -> Result<&IndexEntry, Error> {
+        let name = name.into();
+        let entry = IndexEntry::parse(name.clone(), contents)?;
+        let slot = self.entries.entry(name).or_default();
+        Ok(slot.insert(entry))
+    }
+
+    /// Returns the index entry for the given package, fetching it if it isn't cached.
+    ///
+    /// Returns `None` if the package isn't present in the index, and an error if the entry
+    /// couldn't be fetched or parsed.
+    pub fn entry(&mut self, name: &str) -> Result<Option<&IndexEntry>, Error> {
+        if !self.entries.contains_key(name) {
+            let entry = self.fetch(name)?;
+            self.entries.insert(name.to_owned(), entry);
+        }
+        Ok(self.entries[name].as_ref())
+    }
+
+    fn fetch(&self, name: &str) -> Result<Option<IndexEntry>, Error> {
+        let url = format!("{}{}", self.url, index_path(name));
+        let response = match self.agent.get(&url).call() {
+            Ok(response) => response,
+            // Cargo treats these status codes as the package not being present.
+            Err(ureq::Error::Status(404 | 410 | 451, _)) => return Ok(None),
+            Err(err) => return Err(Error::IndexError(name.to_owned(), Box::new(err))),
+        };
+        let contents = response
+            .into_string()
+            .map_err(|err| Error::IndexError(name.to_owned(), Box::new(err)))?;
+        IndexEntry::parse(name.to_owned(), &contents).map(Some)
+    }
+
+    /// Returns true if the package comes from the registry this index is for.
+    fn serves(&self, package: PackageMetadata<'_>) -> bool {
+        match package.source().kind() {
+            SourceKind::CratesIo => self.is_crates_io(),
+            SourceKind::AlternateRegistry(url) => {
+                url.strip_prefix("sparse+").map_or(false, |url| {
+                    url.trim_end_matches('/') == self.url.trim_end_matches('/')
+                })
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Returns the path to the index file for a package, relative to the root of the index.
+fn index_path(name: &str) -> String {
+    let name = name.to_ascii_lowercase();
+    match name.len() {
+        1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", &name[..1], name),
+        _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
+    }
+}
+
+/// The versions of a package published to a registry, as listed in its index.
+#[derive(Clone, Debug)]
+pub struct IndexEntry {
+    name: String,
+    // Sorted by version.
+    versions: Vec<IndexVersion>,
+}
+
+impl IndexEntry {
+    /// Parses the contents of the index file for the given package.
+    ///
+    /// Index files have one JSON object per line, each describing a published version. Fields
+    /// other than `vers` and `yanked` are ignored.
+    pub fn parse(name: impl Into<String>, contents: &str) -> Result<Self, Error> {
+        #[derive(Deserialize)]
+        struct Line {
+            vers: String,
+            #[serde(default)]
+            yanked: bool,
+        }
+
+        let name = name.into();
+        let mut versions = vec![];
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let line: Line = serde_json::from_str(line)
+                .map_err(|err| Error::IndexError(name.clone(), Box::new(err)))?;
+            let version = Version::parse(&line.vers)
+                .map_err(|err| Error::IndexError(name.clone(), Box::new(err)))?;
+            versions.push(IndexVersion {
+                version,
+                yanked: line.yanked,
+            });
+        }
+        versions.sort_by(|a, b| a.version.cmp(&b.version));
+        Ok(Self { name, versions })
+    }
+
+    /// Returns the name of the package.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the published versions of the package, including yanked ones, in sorted order.
+    pub fn versions(&self) -> &[IndexVersion] {
+        &self.versions
+    }
+
+    /// Returns the published version matching `version` exactly, if any.
+    pub fn version(&self, version: &Version) -> Option<&IndexVersion> {
+        self.versions
+            .binary_search_by(|candidate| candidate.version.cmp(version))
+            .ok()
+            .map(|ix| &self.versions[ix])
+    }
+
+    /// Returns true if `version` has been published and then yanked, false if it has been
+    /// published and not yanked, and `None` if it hasn't been published.
+    pub fn is_yanked(&self, version: &Version) -> Option<bool> {
+        self.version(version).map(|version| version.yanked)
+    }
+
+    /// Returns the latest version that isn't yanked.
+    ///
+    /// Pre-release versions are only returned if no stable versions are available.
+    pub fn latest(&self) -> Option<&Version> {
+        let mut available = self.available();
+        available
+            .clone()
+            .rev()
+            .find(|version| version.pre.is_empty())
+            .or_else(|| available.next_back())
+    }
+
+    /// Returns the latest version that isn't yanked and is semver-compatible with `version`,
+    /// i.e. one that `cargo update` could move to under a default `^version` requirement.
+    ///
+    /// Pre-release versions are only considered if `version` is itself a pre-release. `version`
+    /// is returned if it's published and there's nothing newer. Returns `None` if there are no
+    /// compatible versions at or above `version`.
+    pub fn latest_compatible(&self, version: &Version) -> Option<&Version> {
+        self.available().rev().find(|candidate| {
+            *candidate >= version
+                && is_compatible(version, candidate)
+                && (candidate.pre.is_empty() || !version.pre.is_empty())
+        })
+    }
+
+    fn available(&self) -> impl DoubleEndedIterator<Item = &Version> + Clone + '_ {
+        self.versions
+            .iter()
+            .filter(|version| !version.yanked)
+            .map(|version| &version.version)
+    }
+}
+
+/// Returns true if `candidate` is semver-compatible with `version`, following Cargo's rules:
+/// versions are compatible if their leftmost nonzero components are the same.
+fn is_compatible(version: &Version, candidate: &Version) -> bool {
+    if version.major != 0 {
+        candidate.major == version.major
+    } else if version.minor != 0 {
+        candidate.major == 0 && candidate.minor == version.minor
+    } else {
+        candidate.major == 0 && candidate.minor == 0 && candidate.patch == version.patch
+    }
+}
+
+/// A version of a package published to a registry.
+///
+/// Part of an [`IndexEntry`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IndexVersion {
+    version: Version,
+    yanked: bool,
+}
+
+impl IndexVersion {
+    /// Returns the version.
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
+
+    /// Returns true if this version has been yanked.
+    pub fn is_yanked(&self) -> bool {
+        self.yanked
+    }
+}
+
+impl<'g> PackageMetadata<'g> {
+    /// Looks up whether the exact version of this package has been yanked from the registry
+    /// that `index` is for.
+    ///
+    /// Returns `None` if this package isn't from that registry, if it isn't present in the index,
+    /// or if its version hasn't been published there. Returns an error if the index entry
+    /// couldn't be fetched or parsed.
+    ///
+    /// Requires the `sparse-index` feature to be enabled.
+    pub fn is_yanked(&self, index: &mut SparseIndex) -> Result<Option<bool>, Error> {
+        if !index.serves(*self) {
+            return Ok(None);
+        }
+        Ok(index
+            .entry(self.name())?
+            .and_then(|entry| entry.is_yanked(self.version())))
+    }
+}
+
+impl<'g> PackageSet<'g> {
+    /// Returns a `PackageSet` containing the packages in this set whose exact versions have been
+    /// yanked from the registry that `index` is for.
+    ///
+    /// Cargo keeps using yanked versions that are already pinned in `Cargo.lock`, so this is
+    /// useful for catching them in lockfile audits. For more, see
+    /// [`PackageMetadata::is_yanked`].
+    ///
+    /// Returns an error if an index entry couldn't be fetched or parsed.
+    ///
+    /// Requires the `sparse-index` feature to be enabled.
+    pub fn yanked_packages(&self, index: &mut SparseIndex) -> Result<PackageSet<'g>, Error> {
+        let mut yanked = HashSet::new();
+        for package in self.packages(DependencyDirection::Forward) {
+            if package.is_yanked(index)? == Some(true) {
+                yanked.insert(package.id());
+            }
+        }
+        Ok(self.filter(DependencyDirection::Forward, |package| {
+            yanked.contains(package.id())
+        }))
+    }
+
+    /// Looks up the latest published versions of the packages in this set.
+    ///
+    /// Only packages from the registry that `index` is for are looked up: for `crates.io`, for
+    /// example, workspace members, path dependencies, Git dependencies and packages from other
+    /// registries are skipped.
+    ///
+    /// Returns an error if an index entry couldn't be fetched or parsed.
+    ///
+    /// Requires the `sparse-index` feature to be enabled.
+    pub fn latest_versions(
+        &self,
+        index: &mut SparseIndex,
+    ) -> Result<LatestVersionsReport<'g>, Error> {
+        let mut packages: Vec<_> = self
+            .packages(DependencyDirection::Forward)
+            .filter(|package| index.serves(*package))
+            .collect();
+        packages.sort_by_key(|package| package.id());
+
+        let mut latest_versions = vec![];
+        let mut not_found = vec![];
+        for package in packages {
+            match index.entry(package.name())? {
+                Some(entry) => latest_versions.push(LatestVersions {
+                    package,
+                    latest_compatible: entry.latest_compatible(package.version()).cloned(),
+                    latest: entry.latest().cloned(),
+                }),
+                None => not_found.push(package),
+            }
+        }
+
+        Ok(LatestVersionsReport {
+            latest_versions,
+            not_found,
+        })
+    }
+}
+
+/// The latest published versions of the packages in a set.
+///
+/// Returned by [`PackageSet::latest_versions`]. All packages in the report are sorted by package
+/// ID.
+#[derive(Clone, Debug)]
+pub struct LatestVersionsReport<'g> {
+    latest_versions: Vec<LatestVersions<'g>>,
+    not_found: Vec<PackageMetadata<'g>>,
+}
+
+impl<'g> LatestVersionsReport<'g> {
+    /// Returns the latest versions for every package that was found in the index.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &LatestVersions<'g>> + '_ {
+        self.latest_versions.iter()
+    }
+
+    /// Returns the packages for which a newer version has been published.
+    pub fn outdated(&self) -> impl Iterator<Item = &LatestVersions<'g>> + '_ {
+        self.latest_versions
+            .iter()
+            .filter(|latest_versions| latest_versions.is_outdated())
+    }
+
+    /// Returns the packages that weren't found in the index.
+    pub fn not_found(&self) -> &[PackageMetadata<'g>] {
+        &self.not_found
+    }
+}
+
+/// The latest published versions of a package.
+///
+/// Part of a [`LatestVersionsReport`].
+#[derive(Clone, Debug)]
+pub struct LatestVersions<'g> {
+    package: PackageMetadata<'g>,
+    latest_compatible: Option<Version>,
+    latest: Option<Version>,
+}
+
+impl<'g> LatestVersions<'g> {
+    /// Returns the package.
+    pub fn package(&self) -> PackageMetadata<'g> {
+        self.package
+    }
+
+    /// Returns the latest published version that's semver-compatible with the package's version.
+    ///
+    /// For more, see [`IndexEntry::latest_compatible`].
+    pub fn latest_compatible(&self) -> Option<&Version> {
+        self.latest_compatible.as_ref()
+    }
+
+    /// Returns the latest published version.
+    ///
+    /// For more, see [`IndexEntry::latest`].
+    pub fn latest(&self) -> Option<&Version> {
+        self.latest.as_ref()
+    }
+
+    /// Returns true if the latest published version is newer than the package's version.
+    pub fn is_outdated(&self) -> bool {
+        self.latest
+            .as_ref()
+            .map_or(false, |latest| latest > self.package.version())
+    }
+
+    /// Returns true if a newer semver-compatible version has been published.
+    pub fn has_compatible_update(&self) -> bool {
+        self.latest_compatible
+            .as_ref()
+            .map_or(false, |latest| latest > self.package.version())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_paths() {
+        assert_eq!(index_path("a"), "1/a");
+        assert_eq!(index_path("cc"), "2/cc");
+        assert_eq!(index_path("syn"), "3/s/syn");
+        assert_eq!(index_path("Serde_JSON"), "se/rd/serde_json");
+    }
+
+    #[test]
+    fn entry_versions() {
+        let entry = IndexEntry::parse(
+            "foo",
+            r#"
+{"name":"foo","vers":"0.1.0","deps":[],"cksum":"","features":{},"yanked":false}
+{"name":"foo","vers":"0.1.2","deps":[],"cksum":"","features":{},"yanked":true}
+{"name":"foo","vers":"0.1.1","deps":[],"cksum":"","features":{},"yanked":false}
+{"name":"foo","vers":"1.0.0","deps":[],"cksum":"","features":{},"yanked":false}
+{"name":"foo","vers":"1.2.0","deps":[],"cksum":"","features":{},"yanked":false}
+{"name":"foo","vers":"2.0.0-rc.1","deps":[],"cksum":"","features":{},"yanked":false}
+"#,
+        )
+        .expect("valid index entry");
+
+        let version = |s: &str| Version::parse(s).expect("valid version");
+        assert_eq!(entry.versions().len(), 6);
+        assert_eq!(
+            entry.latest(),
+            Some(&version("1.2.0")),
+            "pre-releases skipped"
+        );
+        assert_eq!(
+            entry.latest_compatible(&version("0.1.0")),
+            Some(&version("0.1.1")),
+            "yanked versions skipped"
+        );
+        assert_eq!(
+            entry.latest_compatible(&version("1.0.0")),
+            Some(&version("1.2.0"))
+        );
+        assert_eq!(
+            entry.latest_compatible(&version("2.0.0-beta.1")),
+            Some(&version("2.0.0-rc.1")),
+            "pre-releases considered for pre-release versions"
+        );
+        assert_eq!(
+            entry.latest_compatible(&version("1.3.0")),
+            None,
+            "no compatible versions at or above 1.3.0"
+        );
+        assert_eq!(entry.is_yanked(&version("0.1.2")), Some(true));
+        assert_eq!(entry.is_yanked(&version("0.1.1")), Some(false));
+        assert_eq!(entry.is_yanked(&version("0.1.3")), None, "not published");
+
+        let err = IndexEntry::parse("bar", "not json").expect_err("invalid index entry");
+        assert_eq!(
+            err.to_string(),
+            "failed to read index entry for package bar"
+        );
+    }
+
+    #[test]
+    fn compatibility() {
+        let version = |s: &str| Version::parse(s).expect("valid version");
+        assert!(is_compatible(&version("1.2.3"), &version("1.9.0")));
+        assert!(!is_compatible(&version("1.2.3"), &version("2.0.0")));
+        assert!(is_compatible(&version("0.2.3"), &version("0.2.9")));
+        assert!(!is_compatible(&version("0.2.3"), &version("0.3.0")));
+        assert!(!is_compatible(&version("0.0.3"), &version("0.0.4")));
+    }
+}
@@ -0,0 +1,302 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Export of package sets as SPDX documents.
+
+use crate::{
+    graph::{DependencyDirection, LicenseExpr, PackageMetadata, PackageSet, PackageSource},
+    PackageId,
+};
+use serde_json::{json, Value as JsonValue};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Exports a [`PackageSet`] as an [SPDX](https://spdx.dev/) 2.3 document, in the JSON format.
+///
+/// The document has an SPDX package for every package in the set. Each package records:
+///
+/// * the package's name, version, description, homepage and declared license
+/// * a download location and a [package URL](https://github.com/package-url/purl-spec) for
+///   packages from registries and Git repositories.
+///
+/// Relationships between packages are derived from the kinds of the links between them:
+///
+/// * normal dependencies become `A DEPENDS_ON B` relationships
+/// * build dependencies become `B BUILD_DEPENDENCY_OF A` relationships
+/// * dev-dependencies become `B DEV_DEPENDENCY_OF A` relationships.
+///
+/// Only links where both ends are in the set are considered. A link with several kinds
+/// results in several relationships.
+///
+/// The document `DESCRIBES` the workspace members in the set. If the set doesn't contain any
+/// workspace members, it describes the packages that nothing else in the set depends on.
+///
+/// Output is deterministic apart from the creation timestamp, which can be fixed with
+/// [`set_created`](Self::set_created).
+///
+/// Requires the `spdx` feature to be enabled.
+///
+/// # Examples
+///
+/// ```
+/// use guppy::{graph::SpdxExporter, MetadataCommand};
+///
+/// let graph = MetadataCommand::new().build_graph().expect("guppy graph constructed");
+/// let package_set = graph
+///     .query_workspace()
+///     .resolve_with_fn(|_, link| !link.dev_only());
+///
+/// let mut exporter = SpdxExporter::new(
+///     &package_set,
+///     "guppy-workspace",
+///     "https://example.com/spdx/guppy-workspace",
+/// );
+/// exporter.set_created("2021-01-01T00:00:00Z");
+/// let document = exporter.export();
+/// assert_eq!(document["spdxVersion"], "SPDX-2.3");
+/// ```
+#[derive(Clone, Debug)]
+pub struct SpdxExporter<'a, 'g> {
+    package_set: &'a PackageSet<'g>,
+    name: String,
+    namespace: String,
+    created: Option<String>,
+}
+
+impl<'a, 'g> SpdxExporter<'a, 'g> {
+    /// Creates a new exporter for the given `PackageSet`.
+    ///
+    /// `name` is the name of the document, and `namespace` is a unique URI identifying this
+    /// document. For more about namespaces, see [Document
+    /// namespace](https://spdx.github.io/spdx-spec/v2.3/document-creation-information/#65-spdx-document-namespace-field)
+    /// in the SPDX specification.
+    pub fn new(
+        package_set: &'a PackageSet<'g>,
+        name: impl Into<String>,
+        namespace: impl Into<String>,
+    ) -> Self {
+        Self {
+            package_set,
+            name: name.into(),
+            namespace: namespace.into(),
+            created: None,
+        }
+    }
+
+    /// Sets the creation timestamp of the document.
+    ///
+    /// The timestamp must be in the format `YYYY-MM-DDThh:mm:ssZ`. By default, the current time
+    /// is used.
+    pub fn set_created(&mut self, created: impl Into<String>) -> &mut Self {
+        self.created = Some(created.into());
+        self
+    }
+
+    /// Returns the SPDX document as JSON.
+    pub fn export(&self) -> JsonValue {
+        let mut packages: Vec<_> = self
+            .package_set
+            .packages(DependencyDirection::Forward)
+            .collect();
+        packages.sort_by_key(|package| package.id());
+        let spdx_ids: HashMap<&'g PackageId, String> = packages
+            .iter()
+            .enumerate()
+            .map(|(index, package)| (package.id(), spdx_id(index, *package)))
+            .collect();
+
+        let spdx_packages: Vec<_> = packages
+            .iter()
+            .map(|package| spdx_package(*package, &spdx_ids[package.id()]))
+            .collect();
+
+        let mut relationships = BTreeSet::new();
+        let mut has_dependents = HashSet::new();
+        for link in self.package_set.links(DependencyDirection::Forward) {
+            let from = &spdx_ids[link.from().id()];
+            let to = &spdx_ids[link.to().id()];
+            has_dependents.insert(link.to().id());
+            if link.normal().is_present() {
+                relationships.insert((from, "DEPENDS_ON", to));
+            }
+            if link.build().is_present() {
+                relationships.insert((to, "BUILD_DEPENDENCY_OF", from));
+            }
+            if link.dev().is_present() {
+                relationships.insert((to, "DEV_DEPENDENCY_OF", from));
+            }
+        }
+
+        let mut described: Vec<_> = packages
+            .iter()
+            .filter(|package| package.in_workspace())
+            .collect();
+        if described.is_empty() {
+            described = packages
+                .iter()
+                .filter(|package| !has_dependents.contains(package.id()))
+                .collect();
+        }
+        let describes = described.into_iter().map(|package| {
+            json!({
+                "spdxElementId": "SPDXRef-DOCUMENT",
+                "relationshipType": "DESCRIBES",
+                "relatedSpdxElement": spdx_ids[package.id()],
+            })
+        });
+        let relationships: Vec<_> = describes
+            .chain(relationships.into_iter().map(|(element, kind, related)| {
+                json!({
+                    "spdxElementId": element,
+                    "relationshipType": kind,
+                    "relatedSpdxElement": related,
+                })
+            }))
+            .collect();
+
+        let created = match &self.created {
+            Some(created) => created.clone(),
+            None => current_timestamp(),
+        };
+        json!({
+            "spdxVersion": "SPDX-2.3",
+            "dataLicense": "CC0-1.0",
+            "SPDXID": "SPDXRef-DOCUMENT",
+            "name": self.name,
+            "documentNamespace": self.namespace,
+            "creationInfo": {
+                "created": created,
+                "creators": [format!("Tool: guppy-{}", env!("CARGO_PKG_VERSION"))],
+            },
+            "packages": spdx_packages,
+            "relationships": relationships,
+        })
+    }
+}
+
+fn spdx_package(package: PackageMetadata<'_>, spdx_id: &str) -> JsonValue {
+    let mut spdx_package = json!({
+        "SPDXID": spdx_id,
+        "name": package.name(),
+        "versionInfo": package.version().to_string(),
+        "downloadLocation": download_location(package),
+        "filesAnalyzed": false,
+        "licenseConcluded": "NOASSERTION",
+        "licenseDeclared": license_declared(package),
+        "copyrightText": "NOASSERTION",
+    });
+    if let Some(description) = package.description() {
+        spdx_package["description"] = description.into();
+    }
+    if let Some(homepage) = package.homepage() {
+        spdx_package["homepage"] = homepage.into();
+    }
+    if let Some(purl) = package.purl() {
+        spdx_package["externalRefs"] = json!([{
+            "referenceCategory": "PACKAGE-MANAGER",
+            "referenceType": "purl",
+            "referenceLocator": purl,
+        }]);
+    }
+    spdx_package
+}
+
+/// Returns an SPDX identifier for a package. The index keeps identifiers unique, even across
+/// packages with the same name and version.
+fn spdx_id(index: usize, package: PackageMetadata<'_>) -> String {
+    // SPDX identifiers may only contain letters, numbers, '.' and '-'.
+    let sanitized: String = format!("{}-{}", package.name(), package.version())
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    format!("SPDXRef-Package-{}-{}", index, sanitized)
+}
+
+fn download_location(package: PackageMetadata<'_>) -> String {
+    let source = package.source();
+    if source.is_crates_io() {
+        return format!(
+            "https://crates.io/api/v1/crates/{}/{}/download",
+            package.name(),
+            package.version()
+        );
+    }
+    match source {
+        PackageSource::External(source) if source.starts_with("git+") => {
+            // SPDX uses the form git+<url>@<commit> for Git repositories.
+            let (url, commit) = match source.split_once('#') {
+                Some((url, commit)) => (url, Some(commit)),
+                None => (source, None),
+            };
+            let url = url.split_once('?').map_or(url, |(url, _)| url);
+            match commit {
+                Some(commit) => format!("{}@{}", url, commit),
+                None => url.to_owned(),
+            }
+        }
+        _ => "NOASSERTION".to_owned(),
+    }
+}
+
+/// Returns the declared license for a package, if it's a valid SPDX expression.
+fn license_declared(package: PackageMetadata<'_>) -> String {
+    package
+        .license_expr()
+        .and_then(Result::ok)
+        .filter(|expr: &LicenseExpr| expr.unknown_ids().is_empty())
+        .map_or_else(|| "NOASSERTION".to_owned(), |expr| expr.to_string())
+}
+
+/// Returns the current time in the format `YYYY-MM-DDThh:mm:ssZ`.
+fn current_timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    format_timestamp(secs)
+}
+
+fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let secs_of_day = secs % 86400;
+
+    // Convert days since the Unix epoch to a civil date, using Howard Hinnant's algorithm.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14T22:13:20Z");
+    }
+}
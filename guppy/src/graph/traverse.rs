@@ -0,0 +1,352 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::graph::{
+    PackageGraph, PackageIx, PackageLink, PackageLinkImpl, PackageMetadata, PackageQuery,
+};
+use petgraph::{
+    graph::{EdgeReference, Edges},
+    prelude::*,
+    visit::{VisitMap, Visitable},
+};
+use std::collections::VecDeque;
+
+/// The order in which a traversal visits packages.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TraversalOrder {
+    /// Visit packages depth-first, following each link as far as possible before backtracking.
+    DepthFirst,
+    /// Visit packages breadth-first, level by level outward from the initials.
+    BreadthFirst,
+}
+
+/// An event produced during a traversal, passed to a [`PackageVisitor`].
+///
+/// Links are always presented in their original direction, even for reverse queries: for a reverse
+/// query, the package being traversed to is `link.from()`.
+#[derive(Clone, Copy, Debug)]
+pub enum PackageTraversalEvent<'g> {
+    /// This package was seen for the first time.
+    ///
+    /// Returning [`TraversalControl::Prune`] skips the links out of this package.
+    Discover(PackageMetadata<'g>),
+
+    /// This link leads to a package that hasn't been seen yet.
+    ///
+    /// Returning [`TraversalControl::Prune`] doesn't follow this link. The package on the other
+    /// side may still be discovered through other links.
+    TreeLink(PackageLink<'g>),
+
+    /// This link leads to a package that is still being traversed, so it is part of a cycle.
+    ///
+    /// Only produced by depth-first traversals.
+    BackLink(PackageLink<'g>),
+
+    /// This link leads to a package that has already been seen, and isn't part of a cycle through
+    /// the current path.
+    ///
+    /// Breadth-first traversals don't distinguish back links from cross links, so every link to a
+    /// package that has already been seen is reported as a `CrossForwardLink`.
+    CrossForwardLink(PackageLink<'g>),
+
+    /// All links out of this package have been visited.
+    Finish(PackageMetadata<'g>),
+}
+
+/// Controls how a traversal continues after an event.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TraversalControl {
+    /// Continue the traversal as usual.
+    Continue,
+    /// Skip the links out of a package (for `Discover`), or don't follow a link (for `TreeLink`).
+    ///
+    /// For other events, this is the same as `Continue`.
+    Prune,
+    /// Stop the whole traversal immediately.
+    Stop,
+}
+
+/// Receives events during a traversal started by
+/// [`PackageQuery::traverse`](crate::graph::PackageQuery::traverse).
+pub trait PackageVisitor<'g> {
+    /// Visits this event, and returns how the traversal should continue.
+    fn visit(
+        &mut self,
+        query: &PackageQuery<'g>,
+        event: PackageTraversalEvent<'g>,
+    ) -> TraversalControl;
+}
+
+impl<'g, T> PackageVisitor<'g> for &mut T
+where
+    T: PackageVisitor<'g>,
+{
+    fn visit(
+        &mut self,
+        query: &PackageQuery<'g>,
+        event: PackageTraversalEvent<'g>,
+    ) -> TraversalControl {
+        (**self).visit(query, event)
+    }
+}
+
+impl<'g, 'a> PackageVisitor<'g> for Box<dyn PackageVisitor<'g> + 'a> {
+    fn visit(
+        &mut self,
+        query: &PackageQuery<'g>,
+        event: PackageTraversalEvent<'g>,
+    ) -> TraversalControl {
+        (**self).visit(query, event)
+    }
+}
+
+impl<'g> PackageVisitor<'g> for &mut dyn PackageVisitor<'g> {
+    fn visit(
+        &mut self,
+        query: &PackageQuery<'g>,
+        event: PackageTraversalEvent<'g>,
+    ) -> TraversalControl {
+        (**self).visit(query, event)
+    }
+}
+
+pub(super) struct VisitorFn<F>(pub(super) F);
+
+impl<'g, F> PackageVisitor<'g> for VisitorFn<F>
+where
+    F: FnMut(&PackageQuery<'g>, PackageTraversalEvent<'g>) -> TraversalControl,
+{
+    fn visit(
+        &mut self,
+        query: &PackageQuery<'g>,
+        event: PackageTraversalEvent<'g>,
+    ) -> TraversalControl {
+        (self.0)(query, event)
+    }
+}
+
+/// Shared state for a single traversal.
+struct Traversal<'a, 'g, V> {
+    query: &'a PackageQuery<'g>,
+    graph: &'g PackageGraph,
+    direction: Direction,
+    visitor: V,
+}
+
+impl<'a, 'g, V: PackageVisitor<'g>> Traversal<'a, 'g, V> {
+    fn new(query: &'a PackageQuery<'g>, visitor: V) -> Self {
+        Self {
+            query,
+            graph: query.graph,
+            direction: query.direction().into(),
+            visitor,
+        }
+    }
+
+    fn depth_first(&mut self) -> bool {
+        let dep_graph = self.graph.dep_graph();
+        let mut discovered = dep_graph.visit_map();
+        let mut finished = dep_graph.visit_map();
+        let mut stack = vec![];
+
+        for &initial in self.query.params.initials() {
+            if !discovered.visit(initial) {
+                continue;
+            }
+            match self.discover(initial) {
+                TraversalControl::Continue => stack.push((initial, self.edges(initial))),
+                TraversalControl::Prune => {
+                    finished.visit(initial);
+                    if self.finish(initial) == TraversalControl::Stop {
+                        return false;
+                    }
+                }
+                TraversalControl::Stop => return false,
+            }
+
+            while let Some((package_ix, edges)) = stack.last_mut() {
+                let package_ix = *package_ix;
+                let edge = match edges.next() {
+                    Some(edge) => edge,
+                    None => {
+                        stack.pop();
+                        finished.visit(package_ix);
+                        if self.finish(package_ix) == TraversalControl::Stop {
+                            return false;
+                        }
+                        continue;
+                    }
+                };
+
+                let neighbor_ix = self.neighbor(edge);
+                let link = self.graph.edge_ref_to_link(edge);
+                if discovered.is_visited(&neighbor_ix) {
+                    let event = if finished.is_visited(&neighbor_ix) {
+                        PackageTraversalEvent::CrossForwardLink(link)
+                    } else {
+                        PackageTraversalEvent::BackLink(link)
+                    };
+                    if self.visit(event) == TraversalControl::Stop {
+                        return false;
+                    }
+                    continue;
+                }
+
+                match self.visit(PackageTraversalEvent::TreeLink(link)) {
+                    TraversalControl::Continue => {}
+                    TraversalControl::Prune => continue,
+                    TraversalControl::Stop => return false,
+                }
+                discovered.visit(neighbor_ix);
+                match self.discover(neighbor_ix) {
+                    TraversalControl::Continue => {
+                        stack.push((neighbor_ix, self.edges(neighbor_ix)));
+                    }
+                    TraversalControl::Prune => {
+                        finished.visit(neighbor_ix);
+                        if self.finish(neighbor_ix) == TraversalControl::Stop {
+                            return false;
+                        }
+                    }
+                    TraversalControl::Stop => return false,
+                }
+            }
+        }
+
+        true
+    }
+
+    fn breadth_first(&mut self) -> bool {
+        let dep_graph = self.graph.dep_graph();
+        let mut discovered = dep_graph.visit_map();
+        let mut queue = VecDeque::new();
+
+        for &initial in self.query.params.initials() {
+            if !discovered.visit(initial) {
+                continue;
+            }
+            match self.discover(initial) {
+                TraversalControl::Continue => queue.push_back(initial),
+                TraversalControl::Prune => {
+                    if self.finish(initial) == TraversalControl::Stop {
+                        return false;
+                    }
+                }
+                TraversalControl::Stop => return false,
+            }
+        }
+
+        while let Some(package_ix) = queue.pop_front() {
+            for edge in self.edges(package_ix) {
+                let neighbor_ix = self.neighbor(edge);
+                let link = self.graph.edge_ref_to_link(edge);
+                if discovered.is_visited(&neighbor_ix) {
+                    if self.visit(PackageTraversalEvent::CrossForwardLink(link))
+                        == TraversalControl::Stop
+                    {
+                        return false;
+                    }
+                    continue;
+                }
+
+                match self.visit(PackageTraversalEvent::TreeLink(link)) {
+                    TraversalControl::Continue => {}
+                    TraversalControl::Prune => continue,
+                    TraversalControl::Stop => return false,
+                }
+                discovered.visit(neighbor_ix);
+                match self.discover(neighbor_ix) {
+                    TraversalControl::Continue => queue.push_back(neighbor_ix),
+                    TraversalControl::Prune => {
+                        if self.finish(neighbor_ix) == TraversalControl::Stop {
+                            return false;
+                        }
+                    }
+                    TraversalControl::Stop => return false,
+                }
+            }
+            if self.finish(package_ix) == TraversalControl::Stop {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    // ---
+    // Helper methods
+    // ---
+
+    fn edges(
+        &self,
+        package_ix: NodeIndex<PackageIx>,
+    ) -> Edges<'g, PackageLinkImpl, Directed, PackageIx> {
+        self.graph
+            .dep_graph()
+            .edges_directed(package_ix, self.direction)
+    }
+
+    fn neighbor(
+        &self,
+        edge: EdgeReference<'g, PackageLinkImpl, PackageIx>,
+    ) -> NodeIndex<PackageIx> {
+        match self.direction {
+            Outgoing => edge.target(),
+            Incoming => edge.source(),
+        }
+    }
+
+    fn metadata(&self, package_ix: NodeIndex<PackageIx>) -> PackageMetadata<'g> {
+        self.graph
+            .metadata(&self.graph.dep_graph()[package_ix])
+            .expect("traversed package should have associated metadata")
+    }
+
+    fn discover(&mut self, package_ix: NodeIndex<PackageIx>) -> TraversalControl {
+        let metadata = self.metadata(package_ix);
+        self.visit(PackageTraversalEvent::Discover(metadata))
+    }
+
+    fn finish(&mut self, package_ix: NodeIndex<PackageIx>) -> TraversalControl {
+        let metadata = self.metadata(package_ix);
+        self.visit(PackageTraversalEvent::Finish(metadata))
+    }
+
+    fn visit(&mut self, event: PackageTraversalEvent<'g>) -> TraversalControl {
+        self.visitor.visit(self.query, event)
+    }
+}
+
+impl<'g> PackageQuery<'g> {
+    /// Traverses the packages reachable from this query in the given order, passing events to the
+    /// visitor.
+    ///
+    /// Unlike a [`PackageResolver`](crate::graph::PackageResolver), which can only choose whether
+    /// to follow individual links, a visitor sees every step of the traversal and can stop it
+    /// early. Each package is discovered and finished at most once.
+    ///
+    /// Returns `true` if the traversal ran to completion, or `false` if the visitor stopped it
+    /// with [`TraversalControl::Stop`].
+    ///
+    /// The depth limit set by [`set_max_depth`](Self::set_max_depth) isn't applied to traversals.
+    /// To bound a traversal, prune packages from the visitor instead.
+    pub fn traverse(&self, order: TraversalOrder, visitor: impl PackageVisitor<'g>) -> bool {
+        let mut traversal = Traversal::new(self, visitor);
+        match order {
+            TraversalOrder::DepthFirst => traversal.depth_first(),
+            TraversalOrder::BreadthFirst => traversal.breadth_first(),
+        }
+    }
+
+    /// Traverses the packages reachable from this query in the given order, passing events to the
+    /// visitor function.
+    ///
+    /// See [`traverse`](Self::traverse) for more details.
+    pub fn traverse_with_fn(
+        &self,
+        order: TraversalOrder,
+        visitor_fn: impl FnMut(&PackageQuery<'g>, PackageTraversalEvent<'g>) -> TraversalControl,
+    ) -> bool {
+        self.traverse(order, VisitorFn(visitor_fn))
+    }
+}
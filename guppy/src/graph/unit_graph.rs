@@ -0,0 +1,450 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Support for ingesting the unit graph produced by `cargo build --unit-graph`.
+
+use crate::{
+    graph::{
+        feature::{FeatureId, FeatureSet},
+        BuildTargetId, PackageGraph, PackageMetadata, PackageSet,
+    },
+    Error, PackageId,
+};
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::Deserialize;
+
+/// The unit graph for a build, as produced by `cargo build --unit-graph -Z unstable-options`.
+///
+/// A unit is a single compilation or execution step that Cargo performs: a target of a package,
+/// built for a given platform with a given profile and set of features. Unlike
+/// [`CargoSet`](crate::graph::cargo::CargoSet), which simulates a build, the unit graph records
+/// exactly what Cargo decided to do, so it can be used to check guppy's results or to analyze
+/// host/target and profile splits.
+///
+/// The unit graph is an unstable Cargo feature, and its format may change in the future. This
+/// type understands version 1 of the format.
+///
+/// # Examples
+///
+/// ```no_run
+/// use guppy::graph::UnitGraph;
+/// use guppy::MetadataCommand;
+/// use std::fs;
+///
+/// let graph = MetadataCommand::new().build_graph().expect("guppy graph constructed");
+/// let json = fs::read_to_string("unit-graph.json").expect("unit graph read");
+/// let unit_graph = UnitGraph::parse_json(&json).expect("unit graph parsed");
+/// let correlated = unit_graph.correlate(&graph).expect("units correlated");
+///
+/// // Find the features enabled on host units, i.e. build scripts and proc macros.
+/// let host_features = correlated
+///     .feature_set_with(|unit| unit.unit().platform().is_none())
+///     .expect("features are known");
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+pub struct UnitGraph {
+    version: u32,
+    units: Vec<Unit>,
+    roots: Vec<usize>,
+}
+
+impl UnitGraph {
+    /// Parses the JSON output of `cargo build --unit-graph`.
+    pub fn parse_json(json: impl AsRef<str>) -> Result<Self, Error> {
+        let unit_graph: Self =
+            serde_json::from_str(json.as_ref()).map_err(Error::UnitGraphParseError)?;
+        // Validate indexes upfront so that accessors don't have to.
+        let unit_count = unit_graph.units.len();
+        let out_of_range = unit_graph.roots.iter().copied().chain(
+            unit_graph
+                .units
+                .iter()
+                .flat_map(|unit| unit.dependencies.iter().map(|dep| dep.index)),
+        );
+        for index in out_of_range {
+            if index >= unit_count {
+                return Err(Error::UnitGraphParseError(serde::de::Error::custom(
+                    format!(
+                        "unit index {} out of range (there are {} units)",
+                        index, unit_count
+                    ),
+                )));
+            }
+        }
+        Ok(unit_graph)
+    }
+
+    /// Returns the version of the unit graph format.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns all the units in this graph.
+    pub fn units(&self) -> &[Unit] {
+        &self.units
+    }
+
+    /// Returns the unit at the given index, if any.
+    pub fn unit(&self, index: usize) -> Option<&Unit> {
+        self.units.get(index)
+    }
+
+    /// Returns the indexes of the root units, i.e. the units that were requested on the command
+    /// line.
+    pub fn roots(&self) -> &[usize] {
+        &self.roots
+    }
+
+    /// Correlates units in this graph with packages in the given `PackageGraph`.
+    ///
+    /// The package graph should be constructed from `cargo metadata` run on the same workspace
+    /// with the same version of Cargo, so that package IDs line up. Units for the standard library
+    /// (with `-Z build-std`) are skipped.
+    ///
+    /// Returns an error if any other unit refers to a package unknown to the graph.
+    pub fn correlate<'g, 'a>(
+        &'a self,
+        graph: &'g PackageGraph,
+    ) -> Result<CorrelatedUnitGraph<'g, 'a>, Error> {
+        let mut packages = Vec::with_capacity(self.units.len());
+        for unit in &self.units {
+            if unit.is_std {
+                packages.push(None);
+                continue;
+            }
+            let package = graph.metadata(&PackageId::new(unit.pkg_id.as_str()))?;
+            packages.push(Some(package));
+        }
+        Ok(CorrelatedUnitGraph {
+            graph,
+            unit_graph: self,
+            packages,
+        })
+    }
+}
+
+/// A single unit of work in a [`UnitGraph`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct Unit {
+    pkg_id: String,
+    target: UnitTarget,
+    profile: UnitProfile,
+    platform: Option<String>,
+    mode: String,
+    #[serde(default)]
+    features: Vec<String>,
+    #[serde(default)]
+    is_std: bool,
+    #[serde(default)]
+    dependencies: Vec<UnitDep>,
+}
+
+impl Unit {
+    /// Returns the ID of the package this unit is for, as reported by Cargo.
+    pub fn pkg_id(&self) -> &str {
+        &self.pkg_id
+    }
+
+    /// Returns the target this unit builds or runs.
+    pub fn target(&self) -> &UnitTarget {
+        &self.target
+    }
+
+    /// Returns the profile this unit is built with.
+    pub fn profile(&self) -> &UnitProfile {
+        &self.profile
+    }
+
+    /// Returns the target triple this unit is built for.
+    ///
+    /// This is `None` for units built for the host platform. If no `--target` was passed in to
+    /// Cargo, the host is also the target platform, so this is `None` for all units.
+    pub fn platform(&self) -> Option<&str> {
+        self.platform.as_deref()
+    }
+
+    /// Returns the mode of this unit, e.g. `"build"`, `"check"`, `"test"` or
+    /// `"run-custom-build"`.
+    pub fn mode(&self) -> &str {
+        &self.mode
+    }
+
+    /// Returns true if this unit runs a build script, rather than compiling something.
+    pub fn is_run_custom_build(&self) -> bool {
+        self.mode == "run-custom-build"
+    }
+
+    /// Returns the named features enabled for this unit.
+    pub fn features(&self) -> impl ExactSizeIterator<Item = &str> + '_ {
+        self.features.iter().map(|feature| feature.as_str())
+    }
+
+    /// Returns true if this unit is part of the standard library, built with `-Z build-std`.
+    pub fn is_std(&self) -> bool {
+        self.is_std
+    }
+
+    /// Returns the direct dependencies of this unit.
+    pub fn dependencies(&self) -> &[UnitDep] {
+        &self.dependencies
+    }
+}
+
+/// The target a [`Unit`] builds or runs.
+#[derive(Clone, Debug, Deserialize)]
+pub struct UnitTarget {
+    kind: Vec<String>,
+    #[serde(default)]
+    crate_types: Vec<String>,
+    name: String,
+    src_path: Utf8PathBuf,
+    #[serde(default)]
+    edition: Option<String>,
+}
+
+impl UnitTarget {
+    /// Returns the kinds of this target, e.g. `["lib"]`, `["bin"]` or `["custom-build"]`.
+    pub fn kind(&self) -> impl ExactSizeIterator<Item = &str> + '_ {
+        self.kind.iter().map(|kind| kind.as_str())
+    }
+
+    /// Returns the crate types of this target, e.g. `["rlib"]` or `["proc-macro"]`.
+    pub fn crate_types(&self) -> impl ExactSizeIterator<Item = &str> + '_ {
+        self.crate_types
+            .iter()
+            .map(|crate_type| crate_type.as_str())
+    }
+
+    /// Returns the name of this target.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the path to the root source file of this target.
+    pub fn src_path(&self) -> &Utf8Path {
+        &self.src_path
+    }
+
+    /// Returns the edition this target is built with, if reported.
+    pub fn edition(&self) -> Option<&str> {
+        self.edition.as_deref()
+    }
+
+    /// Returns the [`BuildTargetId`] corresponding to this target, if its kind is known.
+    pub fn build_target_id(&self) -> Option<BuildTargetId<'_>> {
+        let kind = |expected: &str| self.kind.iter().any(|kind| kind == expected);
+        if kind("custom-build") {
+            Some(BuildTargetId::BuildScript)
+        } else if kind("bin") {
+            Some(BuildTargetId::Binary(&self.name))
+        } else if kind("example") {
+            Some(BuildTargetId::Example(&self.name))
+        } else if kind("test") {
+            Some(BuildTargetId::Test(&self.name))
+        } else if kind("bench") {
+            Some(BuildTargetId::Benchmark(&self.name))
+        } else if self
+            .kind
+            .iter()
+            .any(|kind| LIBRARY_KINDS.contains(&kind.as_str()))
+        {
+            Some(BuildTargetId::Library)
+        } else {
+            None
+        }
+    }
+}
+
+static LIBRARY_KINDS: &[&str] = &["lib", "rlib", "dylib", "cdylib", "staticlib", "proc-macro"];
+
+/// The profile a [`Unit`] is built with.
+#[derive(Clone, Debug, Deserialize)]
+pub struct UnitProfile {
+    name: String,
+    opt_level: String,
+    #[serde(default)]
+    debug_assertions: bool,
+    #[serde(default)]
+    overflow_checks: bool,
+    #[serde(default)]
+    panic: Option<String>,
+}
+
+impl UnitProfile {
+    /// Returns the name of the profile, e.g. `"dev"` or `"release"`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the optimization level, e.g. `"0"`, `"3"` or `"s"`.
+    pub fn opt_level(&self) -> &str {
+        &self.opt_level
+    }
+
+    /// Returns true if debug assertions are enabled.
+    pub fn debug_assertions(&self) -> bool {
+        self.debug_assertions
+    }
+
+    /// Returns true if overflow checks are enabled.
+    pub fn overflow_checks(&self) -> bool {
+        self.overflow_checks
+    }
+
+    /// Returns the panic strategy, e.g. `"unwind"` or `"abort"`, if reported.
+    pub fn panic(&self) -> Option<&str> {
+        self.panic.as_deref()
+    }
+}
+
+/// A dependency of a [`Unit`] on another unit.
+#[derive(Clone, Debug, Deserialize)]
+pub struct UnitDep {
+    index: usize,
+    extern_crate_name: String,
+    #[serde(default)]
+    public: bool,
+}
+
+impl UnitDep {
+    /// Returns the index of the unit depended on, in [`UnitGraph::units`].
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the name the dependency is available as in source code.
+    pub fn extern_crate_name(&self) -> &str {
+        &self.extern_crate_name
+    }
+
+    /// Returns true if this is a public dependency.
+    pub fn is_public(&self) -> bool {
+        self.public
+    }
+}
+
+/// A [`UnitGraph`] whose units have been correlated with packages in a [`PackageGraph`].
+///
+/// Returned by [`UnitGraph::correlate`].
+#[derive(Clone, Debug)]
+pub struct CorrelatedUnitGraph<'g, 'a> {
+    graph: &'g PackageGraph,
+    unit_graph: &'a UnitGraph,
+    // Same indexes as the unit graph. None for standard library units.
+    packages: Vec<Option<PackageMetadata<'g>>>,
+}
+
+impl<'g, 'a> CorrelatedUnitGraph<'g, 'a> {
+    /// Returns the package graph the units were correlated with.
+    pub fn graph(&self) -> &'g PackageGraph {
+        self.graph
+    }
+
+    /// Returns the underlying unit graph.
+    pub fn unit_graph(&self) -> &'a UnitGraph {
+        self.unit_graph
+    }
+
+    /// Returns the correlated units, skipping standard library units.
+    pub fn units(&self) -> impl Iterator<Item = CorrelatedUnit<'g, 'a>> + '_ {
+        (0..self.packages.len()).filter_map(move |index| self.unit(index))
+    }
+
+    /// Returns the correlated unit at the given index, or `None` if the index is out of range or
+    /// the unit is part of the standard library.
+    pub fn unit(&self, index: usize) -> Option<CorrelatedUnit<'g, 'a>> {
+        let package = (*self.packages.get(index)?)?;
+        Some(CorrelatedUnit {
+            index,
+            unit: &self.unit_graph.units[index],
+            package,
+        })
+    }
+
+    /// Returns the correlated root units.
+    pub fn roots(&self) -> impl Iterator<Item = CorrelatedUnit<'g, 'a>> + '_ {
+        self.unit_graph
+            .roots
+            .iter()
+            .filter_map(move |index| self.unit(*index))
+    }
+
+    /// Returns the set of packages with at least one unit.
+    pub fn package_set(&self) -> PackageSet<'g> {
+        self.package_set_with(|_| true)
+    }
+
+    /// Returns the set of packages with at least one unit for which the callback returns true.
+    pub fn package_set_with(
+        &self,
+        mut callback: impl FnMut(&CorrelatedUnit<'g, 'a>) -> bool,
+    ) -> PackageSet<'g> {
+        let ids: Vec<_> = self
+            .units()
+            .filter(|unit| callback(unit))
+            .map(|unit| unit.package.id())
+            .collect();
+        self.graph
+            .resolve_ids(ids)
+            .expect("package IDs were correlated")
+    }
+
+    /// Returns the set of features enabled across all units.
+    ///
+    /// Returns an error if a unit has a feature unknown to the feature graph.
+    pub fn feature_set(&self) -> Result<FeatureSet<'g>, Error> {
+        self.feature_set_with(|_| true)
+    }
+
+    /// Returns the set of features enabled across units for which the callback returns true.
+    ///
+    /// For example, passing in `|unit| unit.unit().platform().is_none()` returns the features
+    /// enabled on the host platform, which can be compared against
+    /// [`CargoSet::host_features`](crate::graph::cargo::CargoSet::host_features).
+    ///
+    /// The base feature of every matching package is included. Returns an error if a unit has a
+    /// feature unknown to the feature graph.
+    pub fn feature_set_with(
+        &self,
+        mut callback: impl FnMut(&CorrelatedUnit<'g, 'a>) -> bool,
+    ) -> Result<FeatureSet<'g>, Error> {
+        let mut feature_ids = vec![];
+        for unit in self.units().filter(|unit| callback(unit)) {
+            let package_id = unit.package.id();
+            feature_ids.push(FeatureId::base(package_id));
+            feature_ids.extend(
+                unit.unit
+                    .features()
+                    .map(|feature| FeatureId::named(package_id, feature)),
+            );
+        }
+        self.graph.feature_graph().resolve_ids(feature_ids)
+    }
+}
+
+/// A [`Unit`] correlated with the package it's for.
+///
+/// Returned by [`CorrelatedUnitGraph`].
+#[derive(Clone, Copy, Debug)]
+pub struct CorrelatedUnit<'g, 'a> {
+    index: usize,
+    unit: &'a Unit,
+    package: PackageMetadata<'g>,
+}
+
+impl<'g, 'a> CorrelatedUnit<'g, 'a> {
+    /// Returns the index of this unit in [`UnitGraph::units`].
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the unit.
+    pub fn unit(&self) -> &'a Unit {
+        self.unit
+    }
+
+    /// Returns the package this unit is for.
+    pub fn package(&self) -> PackageMetadata<'g> {
+        self.package
+    }
+}
@@ -0,0 +1,145 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{
+    graph::{cargo::BuildPlatform, DependencyDirection, PackageLink, PackageMetadata, PackageSet},
+    PackageId,
+};
+use std::collections::{HashSet, VecDeque};
+
+impl<'g> PackageSet<'g> {
+    /// Computes transitive weight metrics for every package in this set.
+    ///
+    /// For each package, this counts the unique dependencies it pulls in transitively, and
+    /// estimates the number of build units needed to build it. Only links to packages within this
+    /// set are followed, and dev-only links are skipped since they aren't needed to build a
+    /// package.
+    ///
+    /// Weights are returned in descending order of build units, with ties broken by package ID, so
+    /// that the heaviest packages come first.
+    ///
+    /// For more about how build units are estimated, see the documentation for `PackageWeight`.
+    pub fn transitive_weights(&self) -> Vec<PackageWeight<'g>> {
+        let mut weights: Vec<_> = self
+            .packages(DependencyDirection::Forward)
+            .map(|package| PackageWeight::new(self, package))
+            .collect();
+        weights.sort_by(|a, b| {
+            b.build_units
+                .cmp(&a.build_units)
+                .then_with(|| a.package.id().cmp(b.package.id()))
+        });
+        weights
+    }
+}
+
+/// Transitive weight metrics for a package.
+///
+/// Returned by [`PackageSet::transitive_weights`].
+///
+/// The build unit count is an estimate of the number of separate compilation and execution steps
+/// Cargo would perform to build the package:
+/// * every package is built once for each platform it is needed on. Proc macros, build scripts and
+///   their dependencies are built for the host platform, so a package used both by a proc macro
+///   and by regular code is built twice.
+/// * every build script is compiled once, and run once for each platform its package is built on.
+///
+/// Platform-specific dependencies are always included, and features aren't taken into account.
+#[derive(Clone, Copy, Debug)]
+pub struct PackageWeight<'g> {
+    package: PackageMetadata<'g>,
+    transitive_deps: usize,
+    build_units: usize,
+}
+
+assert_covariant!(PackageWeight);
+
+impl<'g> PackageWeight<'g> {
+    fn new(package_set: &PackageSet<'g>, package: PackageMetadata<'g>) -> Self {
+        let initial_platform = if package.is_proc_macro() {
+            BuildPlatform::Host
+        } else {
+            BuildPlatform::Target
+        };
+        let mut units: HashSet<(&'g PackageId, BuildPlatform)> = HashSet::new();
+        units.insert((package.id(), initial_platform));
+        let mut queue = VecDeque::new();
+        queue.push_back((package, initial_platform));
+
+        while let Some((current, platform)) = queue.pop_front() {
+            for link in current.direct_links() {
+                let to = link.to();
+                if !package_set
+                    .contains(to.id())
+                    .expect("linked package is known")
+                {
+                    continue;
+                }
+                for next_platform in next_platforms(link, platform).iter().flatten() {
+                    if units.insert((to.id(), *next_platform)) {
+                        queue.push_back((to, *next_platform));
+                    }
+                }
+            }
+        }
+
+        let packages: HashSet<_> = units.iter().map(|(package_id, _)| *package_id).collect();
+        let graph = package.graph();
+        let has_build_script = |package_id: &PackageId| {
+            graph
+                .metadata(package_id)
+                .expect("package is known")
+                .has_build_script()
+        };
+        let build_scripts = packages
+            .iter()
+            .filter(|package_id| has_build_script(package_id))
+            .count();
+        let build_script_runs = units
+            .iter()
+            .filter(|(package_id, _)| has_build_script(package_id))
+            .count();
+
+        Self {
+            package,
+            transitive_deps: packages.len() - 1,
+            build_units: units.len() + build_scripts + build_script_runs,
+        }
+    }
+
+    /// Returns the package these metrics are for.
+    pub fn package(&self) -> PackageMetadata<'g> {
+        self.package
+    }
+
+    /// Returns the number of unique packages this package depends on transitively, not including
+    /// itself.
+    pub fn transitive_deps(&self) -> usize {
+        self.transitive_deps
+    }
+
+    /// Returns the estimated number of build units needed to build this package, including itself.
+    pub fn build_units(&self) -> usize {
+        self.build_units
+    }
+}
+
+/// Returns the platforms the other side of this link is built on, if the link is needed to build
+/// the package on the given platform.
+fn next_platforms(link: PackageLink<'_>, platform: BuildPlatform) -> [Option<BuildPlatform>; 2] {
+    let normal = if link.normal().is_present() {
+        if link.to().is_proc_macro() {
+            Some(BuildPlatform::Host)
+        } else {
+            Some(platform)
+        }
+    } else {
+        None
+    };
+    let build = if link.build().is_present() {
+        Some(BuildPlatform::Host)
+    } else {
+        None
+    };
+    [normal, build]
+}
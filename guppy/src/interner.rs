@@ -0,0 +1,136 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! String interning for package graphs.
+//!
+//! Large graphs contain many copies of the same strings: package and dependency names, feature
+//! names like `default` and `std`, and sources shared by many packages. Interning them means that
+//! each distinct string is only stored once.
+
+use std::{borrow::Borrow, collections::HashSet, fmt, ops::Deref, sync::Arc, sync::Mutex};
+
+/// An immutable, reference-counted string.
+///
+/// Strings interned through the same `Interner` share an allocation, and cloning an `InternedStr`
+/// is cheap. Comparisons and hashing are by value, so an `InternedStr` behaves like a `Box<str>`
+/// in maps and sets.
+#[derive(Clone, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) struct InternedStr(Arc<str>);
+
+impl InternedStr {
+    /// Returns the string as a `&str`.
+    #[inline]
+    pub(crate) fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for InternedStr {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl AsRef<str> for InternedStr {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for InternedStr {
+    #[inline]
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for InternedStr {
+    /// Creates a string that isn't shared with any others.
+    fn from(s: &str) -> Self {
+        Self(s.into())
+    }
+}
+
+impl fmt::Debug for InternedStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for InternedStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+#[cfg(feature = "binary")]
+mod binary_impls {
+    use super::*;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    impl Serialize for InternedStr {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.as_str().serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for InternedStr {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            Ok(Self(String::deserialize(deserializer)?.into()))
+        }
+    }
+}
+
+/// Deduplicates strings while a package graph is being built.
+///
+/// The interner is only needed during construction: once a graph is built, its strings are kept
+/// alive by their reference counts. It can be shared across threads.
+#[derive(Debug, Default)]
+pub(crate) struct Interner {
+    strings: Mutex<HashSet<Arc<str>>>,
+}
+
+impl Interner {
+    /// Creates a new, empty interner.
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns an interned copy of this string.
+    pub(crate) fn intern(&self, s: &str) -> InternedStr {
+        let mut strings = self.strings.lock().expect("interner lock isn't poisoned");
+        match strings.get(s) {
+            Some(existing) => InternedStr(existing.clone()),
+            None => {
+                let new: Arc<str> = s.into();
+                strings.insert(new.clone());
+                InternedStr(new)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intern_shares_allocations() {
+        let interner = Interner::new();
+        let a = interner.intern("default");
+        let b = interner.intern(&String::from("default"));
+        let c = interner.intern("std");
+        assert!(Arc::ptr_eq(&a.0, &b.0), "equal strings share an allocation");
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(&*c, "std");
+
+        let unshared = InternedStr::from("default");
+        assert!(!Arc::ptr_eq(&a.0, &unshared.0));
+        assert_eq!(a, unshared, "comparisons are by value");
+    }
+}
@@ -28,7 +28,9 @@
 //!   traversal: a set of packages and a direction. A traversal is performed with
 //!   [`PackageQuery::resolve`](crate::graph::PackageQuery::resolve), and fine-grained control over
 //!   the traversal is achieved with
-//!   [`PackageQuery::resolve_with_fn`](crate::graph::PackageQuery::resolve_with_fn).
+//!   [`PackageQuery::resolve_with_fn`](crate::graph::PackageQuery::resolve_with_fn). Individual
+//!   traversal events can be observed with
+//!   [`PackageQuery::traverse`](crate::graph::PackageQuery::traverse).
 //! * [`PackageSet`](crate::graph::PackageSet) represents the result of a graph traversal. This
 //!   struct provides several methods to iterate over packages.
 //!
@@ -48,11 +50,24 @@
 //!
 //! # Optional features
 //!
+//! * `advisories`: Support for annotating package sets with advisories from the
+//!   [RustSec advisory database](https://rustsec.org/).
+//! * `binary`: Support for saving package graphs in a versioned binary format, and loading them
+//!   back quickly.
+//! * `cyclonedx`: Support for exporting Cargo builds as [CycloneDX](https://cyclonedx.org/) software
+//!   bills of materials, including checksums from `Cargo.lock`.
+//! * `lockfile`: Support for constructing package graphs from `Cargo.lock` and workspace manifests,
+//!   without running `cargo metadata`, and for reading `[patch]` and `[replace]` sections.
 //! * `proptest1`: Support for [property-based testing](https://jessitron.com/2013/04/25/property-based-testing-what-is-it/)
 //!   using the [`proptest`](https://altsysrq.github.io/proptest-book/intro.html) framework.
 //! * `rayon1`: Support for parallel iterators through [Rayon](docs.rs/rayon/1) (preliminary work
-//!   so far, more parallel iterators to be added in the future).
+//!   so far, more parallel iterators to be added in the future). With this feature, package graphs
+//!   are also constructed from metadata in parallel.
+//! * `sparse-index`: Support for looking up the latest published versions of packages through
+//!   sparse registry indexes such as the one for `crates.io`.
+//! * `spdx`: Support for exporting package sets as [SPDX](https://spdx.dev/) 2.3 documents.
 //! * `summaries`: Support for writing out [build summaries](https://github.com/guppy-rs/guppy/tree/main/guppy-summaries).
+//! * `tokio1`: Support for running `cargo metadata` asynchronously with [Tokio](https://tokio.rs).
 //!
 //! # Examples
 //!
@@ -96,7 +111,9 @@ pub use debug_ignore;
 mod dependency_kind;
 pub mod errors;
 pub mod graph;
+mod interner;
 mod metadata_command;
+mod metadata_compat;
 mod package_id;
 pub(crate) mod petgraph_support;
 pub mod platform;
@@ -107,11 +124,20 @@ mod unit_tests;
 pub use dependency_kind::*;
 pub use errors::Error;
 pub use metadata_command::*;
+pub use metadata_compat::MetadataDegradation;
 pub use package_id::PackageId;
 
 // Public re-exports for upstream crates used in APIs. The no_inline ensures that they show up as
 // re-exports in documentation.
 #[doc(no_inline)]
+pub use petgraph;
+#[cfg(feature = "advisories")]
+#[doc(no_inline)]
+pub use rustsec;
+#[doc(no_inline)]
 pub use semver::{Version, VersionReq};
 #[doc(no_inline)]
 pub use serde_json::Value as JsonValue;
+#[cfg(feature = "sparse-index")]
+#[doc(no_inline)]
+pub use ureq;
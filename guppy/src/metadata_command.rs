@@ -1,10 +1,21 @@
 // Copyright (c) The cargo-guppy Contributors
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use crate::{graph::PackageGraph, Error};
+use crate::{
+    graph::{ArtifactDependencyImpl, PackageGraph},
+    metadata_compat::{check_format_version, normalize_metadata, MetadataDegradation},
+    Error, JsonValue,
+};
 use cargo_metadata::CargoOpt;
-use serde::{Deserialize, Serialize};
-use std::{convert::TryFrom, io, path::PathBuf, process::Command};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    ffi::{OsStr, OsString},
+    io,
+    path::PathBuf,
+    process::{Command, Output},
+};
 
 /// A builder for configuring `cargo metadata` invocations.
 ///
@@ -24,6 +35,12 @@ use std::{convert::TryFrom, io, path::PathBuf, process::Command};
 #[derive(Clone, Debug, Default)]
 pub struct MetadataCommand {
     inner: cargo_metadata::MetadataCommand,
+    offline: bool,
+    frozen: bool,
+    locked: bool,
+    // A value of None means that the variable is removed.
+    envs: BTreeMap<OsString, Option<OsString>>,
+    other_options: Vec<String>,
 }
 
 impl MetadataCommand {
@@ -35,7 +52,10 @@ impl MetadataCommand {
         let mut inner = cargo_metadata::MetadataCommand::new();
         // Always use --all-features so that we get a full view of the graph.
         inner.features(CargoOpt::AllFeatures);
-        Self { inner }
+        Self {
+            inner,
+            ..Default::default()
+        }
     }
 
     /// Sets the path to the `cargo` executable.
@@ -78,6 +98,47 @@ impl MetadataCommand {
         self
     }
 
+    /// Runs `cargo metadata` without accessing the network, with `--offline`.
+    ///
+    /// Dependencies that haven't been downloaded yet will cause `cargo metadata` to fail.
+    pub fn offline(&mut self) -> &mut Self {
+        self.offline = true;
+        self
+    }
+
+    /// Requires `Cargo.lock` to be up-to-date and doesn't access the network, with `--frozen`.
+    ///
+    /// This is equivalent to both [`locked`](Self::locked) and [`offline`](Self::offline).
+    pub fn frozen(&mut self) -> &mut Self {
+        self.frozen = true;
+        self
+    }
+
+    /// Requires `Cargo.lock` to be up-to-date, with `--locked`.
+    ///
+    /// If `Cargo.lock` is missing or needs to be updated, `cargo metadata` will fail.
+    pub fn locked(&mut self) -> &mut Self {
+        self.locked = true;
+        self
+    }
+
+    /// Sets an environment variable for the `cargo metadata` process.
+    ///
+    /// This overrides a variable inherited from this process, and can be used to configure Cargo
+    /// through variables like `CARGO_HOME` or `CARGO_NET_OFFLINE`.
+    pub fn env(&mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> &mut Self {
+        self.envs
+            .insert(key.as_ref().to_owned(), Some(value.as_ref().to_owned()));
+        self
+    }
+
+    /// Removes an environment variable from the `cargo metadata` process, so that it isn't
+    /// inherited from this process.
+    pub fn env_remove(&mut self, key: impl AsRef<OsStr>) -> &mut Self {
+        self.envs.insert(key.as_ref().to_owned(), None);
+        self
+    }
+
     // *Do not* implement features.
 
     /// Arbitrary flags to pass to `cargo metadata`. These will be added to the end of the
@@ -88,25 +149,47 @@ impl MetadataCommand {
     /// * passes in `--all-features`, so that `guppy` has a full view of the dependency graph.
     ///
     /// Attempting to override either of those options may lead to unexpected results.
+    ///
+    /// For `--offline`, `--frozen` and `--locked`, use the [`offline`](Self::offline),
+    /// [`frozen`](Self::frozen) and [`locked`](Self::locked) methods instead.
     pub fn other_options(
         &mut self,
         options: impl IntoIterator<Item = impl Into<String>>,
     ) -> &mut Self {
-        self.inner
-            .other_options(options.into_iter().map(|s| s.into()).collect::<Vec<_>>());
+        self.other_options = options.into_iter().map(|s| s.into()).collect();
         self
     }
 
     /// Builds a [`Command`] instance. This is the first part of calling
     /// [`exec`](Self::exec).
     pub fn cargo_command(&self) -> Command {
-        self.inner.cargo_command()
+        let mut command = self.inner.cargo_command();
+        if self.offline {
+            command.arg("--offline");
+        }
+        if self.frozen {
+            command.arg("--frozen");
+        }
+        if self.locked {
+            command.arg("--locked");
+        }
+        command.args(&self.other_options);
+        for (key, value) in &self.envs {
+            match value {
+                Some(value) => command.env(key, value),
+                None => command.env_remove(key),
+            };
+        }
+        command
     }
 
     /// Runs the configured `cargo metadata` and returns a deserialized `CargoMetadata`.
     pub fn exec(&self) -> Result<CargoMetadata, Error> {
-        let inner = self.inner.exec().map_err(Error::command_error)?;
-        Ok(CargoMetadata(inner))
+        let output = self
+            .cargo_command()
+            .output()
+            .map_err(|err| Error::command_error(err.into()))?;
+        Self::parse_output(output)
     }
 
     /// Runs the configured `cargo metadata` and returns a parsed `PackageGraph`.
@@ -114,6 +197,66 @@ impl MetadataCommand {
         let metadata = self.exec()?;
         metadata.build_graph()
     }
+
+    /// Runs the configured `cargo metadata` asynchronously and returns a deserialized
+    /// `CargoMetadata`.
+    ///
+    /// `cargo` is spawned through [`tokio::process`], so the executor isn't blocked while
+    /// `cargo metadata` runs. This must be called from within a Tokio runtime. If the returned
+    /// future is dropped before it completes, the `cargo metadata` process is killed.
+    ///
+    /// This method is available if the `tokio1` feature is enabled.
+    #[cfg(feature = "tokio1")]
+    pub async fn exec_async(&self) -> Result<CargoMetadata, Error> {
+        let mut command = tokio::process::Command::from(self.cargo_command());
+        command.kill_on_drop(true);
+        let output = command
+            .output()
+            .await
+            .map_err(|err| Error::command_error(err.into()))?;
+        Self::parse_output(output)
+    }
+
+    /// Runs the configured `cargo metadata` asynchronously and returns a parsed `PackageGraph`.
+    ///
+    /// Only running `cargo metadata` is asynchronous: the `PackageGraph` is built on the current
+    /// thread once the output is available.
+    ///
+    /// This method is available if the `tokio1` feature is enabled.
+    ///
+    /// ## Examples
+    ///
+    /// ```rust
+    /// use guppy::MetadataCommand;
+    ///
+    /// # async fn example() -> Result<(), guppy::Error> {
+    /// let package_graph = MetadataCommand::new().build_graph_async().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "tokio1")]
+    pub async fn build_graph_async(&self) -> Result<PackageGraph, Error> {
+        let metadata = self.exec_async().await?;
+        metadata.build_graph()
+    }
+
+    /// Parses the output of a `cargo metadata` process.
+    fn parse_output(output: Output) -> Result<CargoMetadata, Error> {
+        if !output.status.success() {
+            let stderr =
+                String::from_utf8(output.stderr).map_err(|err| Error::command_error(err.into()))?;
+            return Err(Error::command_error(cargo_metadata::Error::CargoMetadata {
+                stderr,
+            }));
+        }
+        let stdout = std::str::from_utf8(&output.stdout)
+            .map_err(|err| Error::command_error(err.into()))?
+            .lines()
+            .find(|line| line.starts_with('{'))
+            .ok_or_else(|| Error::command_error(cargo_metadata::Error::NoJson))?;
+        CargoMetadata::from_json_str(stdout)
+            .map_err(|err| Error::command_error(cargo_metadata::Error::Json(err)))
+    }
 }
 
 /// Although consuming a `MetadataCommand` is not required for building a `PackageGraph`, this impl
@@ -142,21 +285,197 @@ impl<'a> TryFrom<&'a MetadataCommand> for PackageGraph {
 /// output of `cargo metadata` is already available. To construct a `PackageGraph` from an on-disk
 /// Cargo workspace, use [`MetadataCommand`](MetadataCommand).
 ///
-/// This struct implements `serde::Serialize` and `Deserialize`.
-#[derive(Clone, Debug, Deserialize, Serialize)]
-#[serde(transparent)]
-pub struct CargoMetadata(pub(crate) cargo_metadata::Metadata);
+/// This struct implements `serde::Serialize` and `Deserialize`. The `Deserialize` impl only reads
+/// the fields known to `cargo_metadata`: to also read the workspace's default members and the
+/// `public` and `artifact` fields of dependencies, use [`parse_json`](Self::parse_json).
+///
+/// ## Compatibility
+///
+/// [`parse_json`](Self::parse_json) accepts the output of a range of Cargo versions. Fields that
+/// newer versions of Cargo add are ignored, and fields that older versions of Cargo don't produce
+/// are reconstructed where possible: see [`degradations`](Self::degradations) for the list of
+/// reconstructions done. Serializing a `CargoMetadata` always produces output with every field
+/// guppy knows about filled in.
+///
+/// Output for a `cargo metadata` format version other than 1 results in an error.
+#[derive(Clone, Debug)]
+pub struct CargoMetadata {
+    pub(crate) inner: cargo_metadata::Metadata,
+    // Added to `cargo metadata` in Rust 1.71, and not yet available through `cargo_metadata`.
+    pub(crate) workspace_default_members: Option<Vec<cargo_metadata::PackageId>>,
+    // Fields of dependencies that aren't available through `cargo_metadata`: the `public` flag
+    // (only present with `-Z public-dependency`) and artifact dependency information (only present
+    // with `-Z bindeps`).
+    pub(crate) dep_extras: DependencyExtras,
+    // Fields that were missing from the original output and had to be reconstructed. Not
+    // serialized, since serialized output has these fields filled in.
+    pub(crate) degradations: Vec<MetadataDegradation>,
+}
+
+/// For every package with public or artifact dependencies, a list of extra dependency fields with
+/// the same indexes as its dependencies, keyed by package ID.
+pub(crate) type DependencyExtras = HashMap<String, Vec<DependencyExtra>>;
+
+/// Fields of a dependency that aren't available through `cargo_metadata`.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+pub(crate) struct DependencyExtra {
+    #[serde(default)]
+    pub(crate) public: bool,
+    #[serde(default)]
+    pub(crate) artifact: Option<ArtifactDependencyImpl>,
+}
+
+impl DependencyExtra {
+    fn is_empty(&self) -> bool {
+        !self.public && self.artifact.is_none()
+    }
+}
+
+/// The fields of `cargo metadata` output that `cargo_metadata` doesn't know about.
+///
+/// These are read in a separate pass over the output. Everything else is skipped without being
+/// buffered.
+#[derive(Deserialize)]
+struct MetadataExtrasRepr {
+    #[serde(default)]
+    version: Option<u64>,
+    #[serde(default)]
+    workspace_default_members: Option<Vec<cargo_metadata::PackageId>>,
+    #[serde(default)]
+    packages: Vec<PackageExtrasRepr>,
+}
+
+#[derive(Deserialize)]
+struct PackageExtrasRepr {
+    id: String,
+    #[serde(default)]
+    dependencies: Vec<DependencyExtra>,
+}
+
+#[derive(Serialize)]
+struct CargoMetadataReprRef<'a> {
+    #[serde(flatten)]
+    inner: &'a cargo_metadata::Metadata,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    workspace_default_members: &'a Option<Vec<cargo_metadata::PackageId>>,
+}
+
+impl<'de> Deserialize<'de> for CargoMetadata {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let inner = cargo_metadata::Metadata::deserialize(deserializer)?;
+        Ok(Self {
+            inner,
+            workspace_default_members: None,
+            dep_extras: DependencyExtras::new(),
+            degradations: vec![],
+        })
+    }
+}
+
+impl Serialize for CargoMetadata {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let repr = CargoMetadataReprRef {
+            inner: &self.inner,
+            workspace_default_members: &self.workspace_default_members,
+        };
+        if self.dep_extras.is_empty() {
+            return repr.serialize(serializer);
+        }
+        let mut json = serde_json::to_value(&repr).map_err(serde::ser::Error::custom)?;
+        write_dep_extras(&mut json, &self.dep_extras).map_err(serde::ser::Error::custom)?;
+        json.serialize(serializer)
+    }
+}
+
+fn write_dep_extras(
+    json: &mut JsonValue,
+    dep_extras: &DependencyExtras,
+) -> Result<(), serde_json::Error> {
+    let packages = json["packages"].as_array_mut().into_iter().flatten();
+    for package in packages {
+        let extras = match package["id"].as_str().and_then(|id| dep_extras.get(id)) {
+            Some(extras) => extras,
+            None => continue,
+        };
+        let deps = package["dependencies"].as_array_mut().into_iter().flatten();
+        for (dep, extra) in deps.zip(extras) {
+            if extra.public {
+                dep["public"] = true.into();
+            }
+            if let Some(artifact) = &extra.artifact {
+                dep["artifact"] = serde_json::to_value(artifact)?;
+            }
+        }
+    }
+    Ok(())
+}
 
 impl CargoMetadata {
     /// Deserializes this JSON blob into a `CargoMetadata`.
     pub fn parse_json(json: impl AsRef<str>) -> Result<Self, Error> {
-        let inner = serde_json::from_str(json.as_ref()).map_err(Error::MetadataParseError)?;
-        Ok(Self(inner))
+        Self::from_json_str(json.as_ref()).map_err(Error::MetadataParseError)
+    }
+
+    pub(crate) fn from_json_str(json: &str) -> Result<Self, serde_json::Error> {
+        // Output from current versions of Cargo is deserialized directly, and then the fields
+        // cargo_metadata doesn't know about are read in a second pass.
+        let inner = match serde_json::from_str(json) {
+            Ok(inner) => inner,
+            // Output from older versions of Cargo is missing fields, which have to be
+            // reconstructed before deserializing it.
+            Err(_) => return Self::from_json_value(serde_json::from_str(json)?),
+        };
+        let extras: MetadataExtrasRepr = serde_json::from_str(json)?;
+        if let Some(version) = extras.version {
+            check_format_version(version).map_err(serde::de::Error::custom)?;
+        }
+        Ok(Self::from_parts(inner, extras, vec![]))
+    }
+
+    pub(crate) fn from_json_value(mut json: JsonValue) -> Result<Self, serde_json::Error> {
+        let degradations = normalize_metadata(&mut json).map_err(serde::de::Error::custom)?;
+        let extras = MetadataExtrasRepr::deserialize(&json)?;
+        let inner = cargo_metadata::Metadata::deserialize(json)?;
+        Ok(Self::from_parts(inner, extras, degradations))
+    }
+
+    fn from_parts(
+        inner: cargo_metadata::Metadata,
+        extras: MetadataExtrasRepr,
+        degradations: Vec<MetadataDegradation>,
+    ) -> Self {
+        let dep_extras = extras
+            .packages
+            .into_iter()
+            .filter(|package| !package.dependencies.iter().all(DependencyExtra::is_empty))
+            .map(|package| (package.id, package.dependencies))
+            .collect();
+        Self {
+            inner,
+            workspace_default_members: extras.workspace_default_members,
+            dep_extras,
+            degradations,
+        }
     }
 
     /// Serializes this metadata into the given writer.
     pub fn serialize(&self, writer: &mut impl io::Write) -> Result<(), Error> {
-        serde_json::to_writer(writer, &self.0).map_err(Error::MetadataSerializeError)
+        serde_json::to_writer(writer, self).map_err(Error::MetadataSerializeError)
+    }
+
+    /// Returns the fields that were missing from the `cargo metadata` output, typically because it
+    /// was produced by an older version of Cargo, and that guppy reconstructed.
+    ///
+    /// Graphs built from metadata with degradations may be less precise. An empty list means the
+    /// output was complete.
+    pub fn degradations(&self) -> &[MetadataDegradation] {
+        &self.degradations
     }
 
     /// Parses this metadata and builds a `PackageGraph` from it.
@@ -172,3 +491,113 @@ impl TryFrom<CargoMetadata> for PackageGraph {
         metadata.build_graph()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_extras() {
+        let mut json: JsonValue =
+            serde_json::from_str(include_str!("../../fixtures/small/metadata1.json"))
+                .expect("valid JSON");
+        let package = json["packages"]
+            .as_array_mut()
+            .expect("packages is an array")
+            .iter_mut()
+            .find(|package| package["dependencies"][0].is_object())
+            .expect("a package has dependencies");
+        package["dependencies"][0]["public"] = true.into();
+        let id = package["id"].clone();
+        let json = json.to_string();
+
+        let metadata = CargoMetadata::parse_json(&json).expect("valid metadata");
+        assert!(metadata.degradations().is_empty());
+        let extras = &metadata.dep_extras[id.as_str().expect("ID is a string")];
+        assert!(extras[0].public);
+        assert_eq!(metadata.dep_extras.len(), 1, "only one package has extras");
+
+        // The Deserialize impl only reads fields known to cargo_metadata.
+        let metadata: CargoMetadata = serde_json::from_str(&json).expect("valid metadata");
+        assert!(metadata.dep_extras.is_empty());
+    }
+
+    #[test]
+    fn test_parse_default_members() {
+        let mut json: JsonValue =
+            serde_json::from_str(include_str!("../../fixtures/small/metadata1.json"))
+                .expect("valid JSON");
+        json["workspace_default_members"] = json["workspace_members"].clone();
+        let expected: Vec<_> = json["workspace_members"]
+            .as_array()
+            .expect("workspace members is an array")
+            .iter()
+            .map(|id| id.as_str().expect("ID is a string").to_owned())
+            .collect();
+        let default_members = |metadata: &CargoMetadata| -> Option<Vec<String>> {
+            let members = metadata.workspace_default_members.as_ref()?;
+            Some(members.iter().map(|id| id.repr.clone()).collect())
+        };
+
+        // Default members are read in the same pass as dependency extras.
+        let current = json.to_string();
+        let metadata = CargoMetadata::parse_json(&current).expect("valid metadata");
+        assert!(metadata.degradations().is_empty());
+        assert_eq!(default_members(&metadata), Some(expected.clone()));
+
+        // They're also read from older output that needs to be normalized.
+        json.as_object_mut()
+            .expect("metadata is an object")
+            .remove("version");
+        let metadata = CargoMetadata::parse_json(json.to_string()).expect("valid metadata");
+        assert_eq!(
+            metadata.degradations(),
+            &[MetadataDegradation::MissingFormatVersion]
+        );
+        assert_eq!(default_members(&metadata), Some(expected));
+
+        // The Deserialize impl doesn't read them.
+        let metadata: CargoMetadata = serde_json::from_str(&current).expect("valid metadata");
+        assert_eq!(metadata.workspace_default_members, None);
+    }
+
+    #[test]
+    fn test_cargo_command() {
+        let mut command = MetadataCommand::new();
+        command
+            .offline()
+            .locked()
+            .other_options(["--color", "never"])
+            .env("CARGO_HOME", "/fake/cargo-home")
+            .env_remove("CARGO_TARGET_DIR");
+        let cargo_command = command.cargo_command();
+
+        let args: Vec<_> = cargo_command.get_args().collect();
+        assert_eq!(
+            args,
+            [
+                "metadata",
+                "--format-version",
+                "1",
+                "--all-features",
+                "--offline",
+                "--locked",
+                "--color",
+                "never"
+            ],
+            "typed flags come before other options"
+        );
+
+        let envs: Vec<_> = cargo_command.get_envs().collect();
+        assert_eq!(
+            envs,
+            [
+                (
+                    OsStr::new("CARGO_HOME"),
+                    Some(OsStr::new("/fake/cargo-home"))
+                ),
+                (OsStr::new("CARGO_TARGET_DIR"), None),
+            ]
+        );
+    }
+}
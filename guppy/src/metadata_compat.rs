@@ -0,0 +1,328 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Compatibility with `cargo metadata` output produced by a range of Cargo versions.
+//!
+//! Fields added in newer versions of Cargo are ignored unless guppy knows about them. Fields
+//! missing in older versions of Cargo are reconstructed where possible, and each reconstruction is
+//! recorded as a [`MetadataDegradation`].
+
+use crate::JsonValue;
+use camino::{Utf8Path, Utf8PathBuf};
+use std::collections::HashMap;
+
+/// The `cargo metadata` format version that guppy understands.
+pub(crate) const METADATA_FORMAT_VERSION: u64 = 1;
+
+/// A field missing from `cargo metadata` output, and how guppy made up for it.
+///
+/// Older versions of Cargo produce less information in `cargo metadata` output. Rather than
+/// failing to parse such output, guppy reconstructs what it can and records what it had to do, so
+/// that tools can warn about less precise results.
+///
+/// Returned by [`CargoMetadata::degradations`](crate::CargoMetadata::degradations).
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[non_exhaustive]
+pub enum MetadataDegradation {
+    /// The output didn't specify a format version, so version 1 was assumed.
+    MissingFormatVersion,
+
+    /// The output didn't specify the workspace root, so it was set to the deepest directory
+    /// containing the manifests of every workspace member.
+    MissingWorkspaceRoot,
+
+    /// The output didn't specify the target directory, so it was set to the `target` directory
+    /// within the workspace root.
+    MissingTargetDirectory,
+
+    /// Resolved dependencies didn't have names, so names were reconstructed from the dependencies
+    /// declared in manifests.
+    ///
+    /// Names are exact unless a package depends on the same package several times under different
+    /// names (with `package = ...`), in which case links may be attributed to the wrong name.
+    MissingResolvedNames,
+}
+
+/// Fills in fields missing from older `cargo metadata` output, returning the reconstructions that
+/// were done.
+///
+/// Returns an error if the output is for an unsupported format version.
+pub(crate) fn normalize_metadata(json: &mut JsonValue) -> Result<Vec<MetadataDegradation>, String> {
+    let mut degradations = vec![];
+    if !json.is_object() {
+        // Leave reporting this to the deserializer.
+        return Ok(degradations);
+    }
+
+    match &json["version"] {
+        JsonValue::Null => {
+            json["version"] = METADATA_FORMAT_VERSION.into();
+            degradations.push(MetadataDegradation::MissingFormatVersion);
+        }
+        version => match version.as_u64() {
+            Some(version) => check_format_version(version)?,
+            None => {
+                return Err(format!(
+                    "invalid `cargo metadata` format version {}",
+                    version
+                ))
+            }
+        },
+    }
+
+    if json["workspace_root"].is_null() {
+        if let Some(root) = members_root(json) {
+            json["workspace_root"] = root.as_str().into();
+            degradations.push(MetadataDegradation::MissingWorkspaceRoot);
+        }
+    }
+    if json["target_directory"].is_null() {
+        if let Some(root) = json["workspace_root"].as_str() {
+            let target_directory = Utf8PathBuf::from(root).join("target");
+            json["target_directory"] = target_directory.as_str().into();
+            degradations.push(MetadataDegradation::MissingTargetDirectory);
+        }
+    }
+    if add_resolved_names(json) {
+        degradations.push(MetadataDegradation::MissingResolvedNames);
+    }
+
+    Ok(degradations)
+}
+
+/// Returns an error if the given `cargo metadata` format version isn't supported.
+pub(crate) fn check_format_version(version: u64) -> Result<(), String> {
+    if version == METADATA_FORMAT_VERSION {
+        Ok(())
+    } else {
+        Err(format!(
+            "unsupported `cargo metadata` format version {} (supported: {})",
+            version, METADATA_FORMAT_VERSION
+        ))
+    }
+}
+
+/// Returns the deepest directory containing the manifests of every workspace member.
+fn members_root(json: &JsonValue) -> Option<Utf8PathBuf> {
+    let members: Vec<_> = json["workspace_members"]
+        .as_array()?
+        .iter()
+        .filter_map(JsonValue::as_str)
+        .collect();
+    let dirs = json["packages"]
+        .as_array()?
+        .iter()
+        .filter(|package| {
+            package["id"]
+                .as_str()
+                .map_or(false, |id| members.contains(&id))
+        })
+        .filter_map(|package| {
+            let manifest_path = Utf8PathBuf::from(package["manifest_path"].as_str()?);
+            Some(manifest_path.parent()?.to_path_buf())
+        });
+    common_ancestor(dirs)
+}
+
+/// Returns the deepest directory containing all of the given paths, or `None` if there are no
+/// paths.
+pub(crate) fn common_ancestor(
+    paths: impl IntoIterator<Item = impl AsRef<Utf8Path>>,
+) -> Option<Utf8PathBuf> {
+    let mut paths = paths.into_iter();
+    let mut root = paths.next()?.as_ref().to_path_buf();
+    for path in paths {
+        while !path.as_ref().starts_with(&root) {
+            if !root.pop() {
+                break;
+            }
+        }
+    }
+    Some(root)
+}
+
+/// Reconstructs the `deps` field of resolve nodes from their `dependencies` field, returning true
+/// if any node was missing it.
+fn add_resolved_names(json: &mut JsonValue) -> bool {
+    let needs_names = json["resolve"]["nodes"].as_array().map_or(false, |nodes| {
+        nodes
+            .iter()
+            .any(|node| node["deps"].is_null() && node["dependencies"].is_array())
+    });
+    if !needs_names {
+        return false;
+    }
+
+    // For each package, its name, the name of its library target, and the names it's depended on
+    // under by each package.
+    let mut package_names: HashMap<String, (String, String)> = HashMap::new();
+    let mut declared: HashMap<String, Vec<(String, Option<String>)>> = HashMap::new();
+    for package in json["packages"].as_array().into_iter().flatten() {
+        let (id, name) = match (package["id"].as_str(), package["name"].as_str()) {
+            (Some(id), Some(name)) => (id, name),
+            _ => continue,
+        };
+        let lib_name = package["targets"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|target| {
+                target["kind"].as_array().map_or(false, |kinds| {
+                    kinds.iter().any(|kind| {
+                        matches!(
+                            kind.as_str(),
+                            Some("lib" | "rlib" | "dylib" | "proc-macro" | "cdylib" | "staticlib")
+                        )
+                    })
+                })
+            })
+            .and_then(|target| target["name"].as_str())
+            .unwrap_or(name)
+            .replace('-', "_");
+        package_names.insert(id.to_owned(), (name.to_owned(), lib_name));
+        let deps = package["dependencies"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|dep| {
+                let name = dep["name"].as_str()?.to_owned();
+                let rename = dep["rename"].as_str().map(|rename| rename.to_owned());
+                Some((name, rename))
+            })
+            .collect();
+        declared.insert(id.to_owned(), deps);
+    }
+
+    let nodes = json["resolve"]["nodes"]
+        .as_array_mut()
+        .into_iter()
+        .flatten();
+    for node in nodes {
+        if !node["deps"].is_null() {
+            continue;
+        }
+        let declared = node["id"].as_str().and_then(|id| declared.get(id));
+        let deps: Vec<_> = node["dependencies"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|dep_id| {
+                let dep_id = dep_id.as_str()?;
+                let (name, lib_name) = package_names.get(dep_id)?;
+                let renames: Vec<_> = declared
+                    .into_iter()
+                    .flatten()
+                    .filter(|(dep_name, _)| dep_name == name)
+                    .map(|(_, rename)| rename.as_deref())
+                    .collect();
+                // Prefer the original name if the package is ever depended on without a rename.
+                let resolved_name = match renames.iter().find_map(|rename| *rename) {
+                    Some(rename) if !renames.contains(&None) => rename.replace('-', "_"),
+                    _ => lib_name.clone(),
+                };
+                Some(serde_json::json!({
+                    "name": resolved_name,
+                    "pkg": dep_id,
+                    "dep_kinds": [],
+                }))
+            })
+            .collect();
+        node["deps"] = deps.into();
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn normalize_old_metadata() {
+        let mut metadata = json!({
+            "packages": [
+                {
+                    "id": "a 0.1.0 (path+file:///ws/a)",
+                    "name": "a",
+                    "manifest_path": "/ws/a/Cargo.toml",
+                    "targets": [{ "name": "a", "kind": ["lib"] }],
+                    "dependencies": [
+                        { "name": "b-sys", "rename": "b-renamed" },
+                        { "name": "c" },
+                    ],
+                },
+                {
+                    "id": "b-sys 0.1.0 (path+file:///ws/b)",
+                    "name": "b-sys",
+                    "manifest_path": "/ws/b/Cargo.toml",
+                    "targets": [{ "name": "b_sys", "kind": ["lib"] }],
+                    "dependencies": [],
+                },
+                {
+                    "id": "c 0.1.0 (path+file:///ws/nested/c)",
+                    "name": "c",
+                    "manifest_path": "/ws/nested/c/Cargo.toml",
+                    "targets": [{ "name": "c", "kind": ["bin"] }],
+                    "dependencies": [],
+                },
+            ],
+            "workspace_members": ["a 0.1.0 (path+file:///ws/a)", "b-sys 0.1.0 (path+file:///ws/b)"],
+            "resolve": {
+                "nodes": [
+                    {
+                        "id": "a 0.1.0 (path+file:///ws/a)",
+                        "dependencies": [
+                            "b-sys 0.1.0 (path+file:///ws/b)",
+                            "c 0.1.0 (path+file:///ws/nested/c)",
+                        ],
+                    },
+                ],
+            },
+        });
+
+        let degradations = normalize_metadata(&mut metadata).expect("supported metadata");
+        assert_eq!(
+            degradations,
+            vec![
+                MetadataDegradation::MissingFormatVersion,
+                MetadataDegradation::MissingWorkspaceRoot,
+                MetadataDegradation::MissingTargetDirectory,
+                MetadataDegradation::MissingResolvedNames,
+            ]
+        );
+        assert_eq!(metadata["version"], 1);
+        assert_eq!(metadata["workspace_root"], "/ws");
+        assert_eq!(metadata["target_directory"], "/ws/target");
+        assert_eq!(
+            metadata["resolve"]["nodes"][0]["deps"],
+            json!([
+                { "name": "b_renamed", "pkg": "b-sys 0.1.0 (path+file:///ws/b)", "dep_kinds": [] },
+                { "name": "c", "pkg": "c 0.1.0 (path+file:///ws/nested/c)", "dep_kinds": [] },
+            ])
+        );
+
+        // Normalizing again is a no-op.
+        assert_eq!(normalize_metadata(&mut metadata), Ok(vec![]));
+    }
+
+    #[test]
+    fn unsupported_format_version() {
+        let mut metadata = json!({ "version": 2 });
+        assert!(normalize_metadata(&mut metadata).is_err());
+    }
+
+    #[test]
+    fn common_ancestors() {
+        let paths = [
+            "/home/user/monorepo/frontend",
+            "/home/user/monorepo/backend/api",
+        ];
+        assert_eq!(common_ancestor(paths), Some("/home/user/monorepo".into()));
+        assert_eq!(
+            common_ancestor(&paths[..1]),
+            Some("/home/user/monorepo/frontend".into())
+        );
+        assert_eq!(common_ancestor(["/a/b", "/c"]), Some("/".into()));
+        assert_eq!(common_ancestor(Vec::<&str>::new()), None);
+    }
+}
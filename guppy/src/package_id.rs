@@ -5,6 +5,11 @@ use std::fmt;
 
 /// An "opaque" identifier for a package.
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(
+    feature = "binary",
+    derive(serde::Deserialize, serde::Serialize),
+    serde(transparent)
+)]
 #[allow(clippy::derive_hash_xor_eq)] // safe because the same PartialEq impl is used everywhere
 pub struct PackageId {
     /// The underlying string representation of an ID.
@@ -8,6 +8,7 @@ use petgraph::{
 use std::fmt::{self, Write};
 
 static INDENT: &str = "    ";
+static NESTED_INDENT: &str = "        ";
 
 /// A visitor interface for formatting graph labels.
 pub trait DotVisitor<NR, ER> {
@@ -19,7 +20,16 @@ pub trait DotVisitor<NR, ER> {
     /// `DotWrite`.
     fn visit_edge(&self, edge: ER, f: &mut DotWrite<'_, '_>) -> fmt::Result;
 
-    // TODO: allow more customizations? more labels, colors etc to be set?
+    /// Sets graphviz attributes for this node. Setting `label` overrides the visitor's label.
+    fn node_attributes(&self, _node: NR, _attrs: &mut DotAttributes) {}
+
+    /// Returns the name of the cluster to draw this node in, if any.
+    fn node_cluster(&self, _node: NR) -> Option<String> {
+        None
+    }
+
+    /// Sets graphviz attributes for this edge. Setting `label` overrides the visitor's label.
+    fn edge_attributes(&self, _edge: ER, _attrs: &mut DotAttributes) {}
 }
 
 /// A visitor for formatting graph labels that outputs `fmt::Display` impls for node and edge
@@ -56,6 +66,18 @@ where
     fn visit_edge(&self, edge: ER, f: &mut DotWrite<'_, '_>) -> fmt::Result {
         (*self).visit_edge(edge, f)
     }
+
+    fn node_attributes(&self, node: NR, attrs: &mut DotAttributes) {
+        (*self).node_attributes(node, attrs)
+    }
+
+    fn node_cluster(&self, node: NR) -> Option<String> {
+        (*self).node_cluster(node)
+    }
+
+    fn edge_attributes(&self, edge: ER, attrs: &mut DotAttributes) {
+        (*self).edge_attributes(edge, attrs)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -80,33 +102,56 @@ where
     pub fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "{} {{", graph_type(&self.graph))?;
 
+        // Nodes outside clusters are written out first, followed by each cluster in the order
+        // it's first seen.
+        let mut clusters: Vec<(String, Vec<_>)> = vec![];
         for node in self.graph.node_references() {
-            write!(
-                f,
-                "{}{} [label=\"",
-                INDENT,
-                (&self.graph).to_index(node.id())
-            )?;
-            self.visitor.visit_node(node, &mut DotWrite::new(f))?;
-            writeln!(f, "\"]")?;
+            match self.visitor.node_cluster(node) {
+                Some(name) => match clusters.iter_mut().find(|(existing, _)| *existing == name) {
+                    Some((_, nodes)) => nodes.push(node),
+                    None => clusters.push((name, vec![node])),
+                },
+                None => self.fmt_node(node, INDENT, f)?,
+            }
+        }
+        for (cluster_ix, (name, nodes)) in clusters.into_iter().enumerate() {
+            writeln!(f, "{}subgraph cluster_{} {{", INDENT, cluster_ix)?;
+            writeln!(f, "{}{}label={}", INDENT, INDENT, QuotedStr(&name))?;
+            for node in nodes {
+                self.fmt_node(node, NESTED_INDENT, f)?;
+            }
+            writeln!(f, "{}}}", INDENT)?;
         }
 
         let edge_str = edge_str(&self.graph);
         for edge in self.graph.edge_references() {
             write!(
                 f,
-                "{}{} {} {} [label=\"",
+                "{}{} {} {} ",
                 INDENT,
                 (&self.graph).to_index(edge.source()),
                 edge_str,
                 (&self.graph).to_index(edge.target())
             )?;
-            self.visitor.visit_edge(edge, &mut DotWrite::new(f))?;
-            writeln!(f, "\"]")?;
+            let mut attrs = DotAttributes::new();
+            self.visitor.edge_attributes(edge, &mut attrs);
+            attrs.fmt_list(f, |f| self.visitor.visit_edge(edge, &mut DotWrite::new(f)))?;
         }
 
         writeln!(f, "}}")
     }
+
+    fn fmt_node<'a>(
+        &'a self,
+        node: <&'a G as IntoNodeReferences>::NodeRef,
+        indent: &str,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write!(f, "{}{} ", indent, (&self.graph).to_index(node.id()))?;
+        let mut attrs = DotAttributes::new();
+        self.visitor.node_attributes(node, &mut attrs);
+        attrs.fmt_list(f, |f| self.visitor.visit_node(node, &mut DotWrite::new(f)))
+    }
 }
 
 impl<G, V> fmt::Display for DotFmt<G, V>
@@ -120,6 +165,125 @@ where
     }
 }
 
+/// Graphviz attributes for a node or edge.
+///
+/// Attributes are written out after the label, in the order they're first set. Values, and names
+/// that aren't valid DOT identifiers, are quoted and escaped, so they can contain arbitrary text.
+///
+/// For the list of attributes graphviz understands, see
+/// [the graphviz documentation](https://graphviz.org/doc/info/attrs.html).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DotAttributes {
+    attrs: Vec<(String, String)>,
+}
+
+impl DotAttributes {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets an attribute, replacing any previous value for it.
+    ///
+    /// Setting the `label` attribute replaces the label written by the visitor with the given
+    /// text.
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        let name = name.into();
+        let value = value.into();
+        match self
+            .attrs
+            .iter_mut()
+            .find(|(existing, _)| *existing == name)
+        {
+            Some((_, existing_value)) => *existing_value = value,
+            None => self.attrs.push((name, value)),
+        }
+        self
+    }
+
+    /// Returns the value of an attribute, if it's been set.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(existing, _)| existing == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Iterates over the attributes that have been set, in the order they were first set.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
+        self.attrs
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Returns true if no attributes have been set.
+    pub fn is_empty(&self) -> bool {
+        self.attrs.is_empty()
+    }
+}
+
+impl DotAttributes {
+    /// Writes out a bracketed attribute list, using `visit_label` to write the label if it hasn't
+    /// been set.
+    fn fmt_list(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        visit_label: impl FnOnce(&mut fmt::Formatter<'_>) -> fmt::Result,
+    ) -> fmt::Result {
+        match self.get("label") {
+            Some(label) => write!(f, "[label={}", QuotedStr(label))?,
+            None => {
+                f.write_str("[label=\"")?;
+                visit_label(f)?;
+                f.write_char('"')?;
+            }
+        }
+        for (name, value) in self.attrs.iter().filter(|(name, _)| name != "label") {
+            write!(f, ", {}={}", DotId(name), QuotedStr(value))?;
+        }
+        writeln!(f, "]")
+    }
+}
+
+/// Returns true if `name` is a DOT identifier that can be written without quotes.
+fn is_dot_id(name: &str) -> bool {
+    let mut chars = name.chars();
+    let is_id_char = |c: char| c == '_' || c.is_ascii_alphanumeric() || !c.is_ascii();
+    match chars.next() {
+        Some(first) if is_id_char(first) && !first.is_ascii_digit() => chars.all(is_id_char),
+        _ => false,
+    }
+}
+
+/// Writes out a DOT identifier, quoting it if necessary.
+struct DotId<'a>(&'a str);
+
+impl<'a> fmt::Display for DotId<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if is_dot_id(self.0) {
+            f.write_str(self.0)
+        } else {
+            QuotedStr(self.0).fmt(f)
+        }
+    }
+}
+
+struct QuotedStr<'a>(&'a str);
+
+impl<'a> fmt::Display for QuotedStr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str(r#"\""#)?,
+                '\\' => f.write_str(r#"\\"#)?,
+                '\n' => f.write_str(r#"\n"#)?,
+                c => f.write_char(c)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
+
 /// A write target for `Dot` graphs. Use with the `write!` macro.
 pub struct DotWrite<'a, 'b> {
     f: &'a mut fmt::Formatter<'b>,
@@ -191,3 +355,25 @@ fn edge_str<G: GraphProp>(graph: G) -> &'static str {
         "--"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dot_ids() {
+        for name in ["shape", "penwidth", "_x1", "fontname"] {
+            assert!(is_dot_id(name), "{} is a DOT identifier", name);
+        }
+        for name in ["", "1st", "font name", "\"shape\"", "a=b"] {
+            assert!(!is_dot_id(name), "{} is not a DOT identifier", name);
+        }
+    }
+
+    #[test]
+    fn dot_id_quoting() {
+        assert_eq!(DotId("shape").to_string(), "shape");
+        assert_eq!(DotId("font name").to_string(), r#""font name""#);
+        assert_eq!(DotId("a\"b").to_string(), r#""a\"b""#);
+    }
+}
@@ -59,6 +59,11 @@ impl<Ix: IndexType> Sccs<Ix> {
         }
     }
 
+    /// Returns all the SCCs, including ones with a single element.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &[NodeIndex<Ix>]> {
+        self.sccs.iter()
+    }
+
     /// Returns all the SCCs with more than one element.
     pub fn multi_sccs(&self) -> impl Iterator<Item = &[NodeIndex<Ix>]> + DoubleEndedIterator {
         self.sccs.iter().filter(|scc| scc.len() > 1)
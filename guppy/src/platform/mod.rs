@@ -55,16 +55,12 @@
 //! in the Cargo reference.
 
 mod platform_eval;
-mod platform_spec;
-#[cfg(feature = "proptest1")]
-mod proptest_helpers;
 #[cfg(feature = "summaries")]
 mod summaries;
 
 pub use platform_eval::*;
-pub use platform_spec::*;
 #[cfg(feature = "summaries")]
 pub use summaries::*;
 // These are inlined -- generally, treat target_spec as a private dependency so expose these types
 // as part of guppy's API.
-pub use target_spec::{Platform, TargetFeatures, Triple};
+pub use target_spec::{Platform, PlatformSpec, TargetFeatures, Triple};
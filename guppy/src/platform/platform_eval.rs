@@ -68,14 +68,8 @@ impl<'g> PlatformStatus<'g> {
         match (self, platform_spec) {
             (PlatformStatus::Always, _) => EnabledTernary::Enabled,
             (PlatformStatus::Never, _) => EnabledTernary::Disabled,
-            (PlatformStatus::PlatformDependent { .. }, PlatformSpec::Any) => {
-                EnabledTernary::Enabled
-            }
-            (PlatformStatus::PlatformDependent { eval }, PlatformSpec::Platform(platform)) => {
-                eval.eval(platform)
-            }
-            (PlatformStatus::PlatformDependent { .. }, PlatformSpec::Always) => {
-                EnabledTernary::Disabled
+            (PlatformStatus::PlatformDependent { eval }, platform_spec) => {
+                eval.eval_platform_spec(platform_spec)
             }
         }
     }
@@ -173,13 +167,34 @@ impl<'g> PlatformEval<'g> {
         }
         res
     }
+
+    /// Runs this evaluator against the given platform spec.
+    ///
+    /// For more about how specs are evaluated against each kind of platform spec, see
+    /// [`PlatformSpec`].
+    pub fn eval_platform_spec(&self, platform_spec: &PlatformSpec) -> EnabledTernary {
+        let mut res = EnabledTernary::Disabled;
+        for spec in self.specs.iter() {
+            let matches = spec.eval_platform_spec(platform_spec);
+            // Short-circuit evaluation if possible.
+            if matches == Some(true) {
+                return EnabledTernary::Enabled;
+            }
+            res = res | EnabledTernary::new(matches);
+        }
+        res
+    }
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "binary", derive(serde::Deserialize, serde::Serialize))]
 pub(crate) enum PlatformStatusImpl {
     Always,
     // Empty vector means never.
-    Specs(Vec<TargetSpec>),
+    Specs(
+        #[cfg_attr(feature = "binary", serde(with = "crate::graph::binary::target_specs"))]
+        Vec<TargetSpec>,
+    ),
 }
 
 impl PlatformStatusImpl {
@@ -5,6 +5,11 @@ use std::{fmt, iter::FromIterator, ops::Deref};
 
 /// An immutable set stored as a sorted vector.
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(
+    feature = "binary",
+    derive(serde::Deserialize, serde::Serialize),
+    serde(transparent)
+)]
 pub struct SortedSet<T> {
     inner: Box<[T]>,
 }
@@ -0,0 +1,114 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use fixtures::json::JsonFixture;
+use guppy::{
+    graph::{AdvisoryReachability, AdvisoryReport},
+    rustsec::database::{Database, Query},
+};
+use std::path::Path;
+
+fn open_database() -> Database {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../fixtures/advisory-db");
+    Database::open(&path).expect("advisory database opened")
+}
+
+/// Returns (package name, advisory ID, reachability) triples for the report.
+fn summarize(report: &AdvisoryReport<'_, '_>) -> Vec<(String, String, AdvisoryReachability)> {
+    report
+        .iter()
+        .map(|advisory_match| {
+            (
+                advisory_match.package().name().to_owned(),
+                advisory_match.advisory().id().to_string(),
+                advisory_match.reachability(),
+            )
+        })
+        .collect()
+}
+
+fn triple(
+    name: &str,
+    id: &str,
+    reachability: AdvisoryReachability,
+) -> (String, String, AdvisoryReachability) {
+    (name.to_owned(), id.to_owned(), reachability)
+}
+
+#[test]
+fn advisories_metadata1() {
+    let database = open_database();
+    let graph = JsonFixture::metadata1().graph();
+    let package_set = graph.resolve_all();
+
+    let report = package_set.annotate_advisories(&database, &Query::default());
+    assert_eq!(
+        summarize(&report),
+        vec![
+            triple("regex", "RUSTSEC-2022-0013", AdvisoryReachability::Normal),
+            triple(
+                "thread_local",
+                "RUSTSEC-2022-0006",
+                AdvisoryReachability::Normal
+            ),
+        ],
+        "patched versions and informational advisories are skipped"
+    );
+
+    let report = package_set.annotate_advisories(&database, &Query::new());
+    assert_eq!(report.len(), 3, "informational advisory is included");
+    let yaml_rust = report
+        .iter()
+        .find(|advisory_match| advisory_match.package().name() == "yaml-rust")
+        .expect("yaml-rust advisory matched");
+    assert_eq!(yaml_rust.advisory().title(), "yaml-rust is unmaintained");
+    assert_eq!(
+        report.for_package(yaml_rust.package().id()).count(),
+        1,
+        "one advisory for yaml-rust"
+    );
+
+    let report = graph
+        .resolve_package_name("regex")
+        .annotate_advisories(&database, &Query::default());
+    assert_eq!(
+        summarize(&report),
+        vec![triple(
+            "regex",
+            "RUSTSEC-2022-0013",
+            AdvisoryReachability::Unreachable
+        )],
+        "packages are unreachable without workspace members"
+    );
+}
+
+#[test]
+fn advisories_libra_dev_only() {
+    let database = open_database();
+    let graph = JsonFixture::metadata_libra().graph();
+
+    let package_set = graph
+        .query_workspace_names(["libra-canonical-serialization"])
+        .expect("workspace member found")
+        .resolve();
+    let report = package_set.annotate_advisories(&database, &Query::default());
+    let dev_only: Vec<_> = report
+        .with_reachability(AdvisoryReachability::DevOnly)
+        .map(|advisory_match| advisory_match.package().name())
+        .collect();
+    assert_eq!(
+        dev_only,
+        vec!["remove_dir_all"],
+        "remove_dir_all is only pulled in through dev-dependencies"
+    );
+
+    let report = graph
+        .resolve_all()
+        .annotate_advisories(&database, &Query::default());
+    assert!(
+        report
+            .iter()
+            .all(|advisory_match| advisory_match.reachability() == AdvisoryReachability::Normal),
+        "all affected packages are normal dependencies of some workspace member"
+    );
+}
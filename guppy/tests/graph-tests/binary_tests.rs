@@ -0,0 +1,103 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use fixtures::json::JsonFixture;
+use guppy::{graph::PackageGraph, Error, PackageId};
+
+#[test]
+fn binary_roundtrip() {
+    for (name, fixture) in JsonFixture::all_fixtures() {
+        let graph = fixture.graph();
+        let mut bytes = vec![];
+        graph
+            .serialize_binary(&mut bytes)
+            .unwrap_or_else(|err| panic!("{}: serialization failed: {}", name, err));
+
+        let loaded = PackageGraph::deserialize_binary(&mut bytes.as_slice())
+            .unwrap_or_else(|err| panic!("{}: deserialization failed: {}", name, err));
+        loaded
+            .verify()
+            .unwrap_or_else(|err| panic!("{}: loaded graph verifies: {}", name, err));
+        assert_eq!(
+            graph.package_count(),
+            loaded.package_count(),
+            "{}: package counts match",
+            name
+        );
+        assert_eq!(
+            graph.link_count(),
+            loaded.link_count(),
+            "{}: link counts match",
+            name
+        );
+        assert_eq!(
+            package_ids(graph),
+            package_ids(&loaded),
+            "{}: package IDs match",
+            name
+        );
+        assert_eq!(links(graph), links(&loaded), "{}: links match", name);
+        assert_eq!(
+            graph.feature_graph().feature_count(),
+            loaded.feature_graph().feature_count(),
+            "{}: feature counts match",
+            name
+        );
+
+        let mut reserialized = vec![];
+        loaded
+            .serialize_binary(&mut reserialized)
+            .expect("reserialization succeeded");
+        assert_eq!(bytes, reserialized, "{}: serialization is stable", name);
+    }
+}
+
+#[test]
+fn binary_invalid() {
+    let mut bytes = vec![];
+    JsonFixture::metadata1()
+        .graph()
+        .serialize_binary(&mut bytes)
+        .expect("serialization succeeded");
+
+    let mut bad_magic = bytes.clone();
+    bad_magic[0] = b'X';
+    assert_parse_error(&bad_magic, "bad magic");
+
+    let mut bad_version = bytes.clone();
+    bad_version[8] = 0xff;
+    assert_parse_error(&bad_version, "bad format version");
+
+    assert_parse_error(&bytes[..bytes.len() / 2], "truncated");
+}
+
+fn package_ids(graph: &PackageGraph) -> Vec<&PackageId> {
+    let mut package_ids: Vec<_> = graph.package_ids().collect();
+    package_ids.sort_unstable();
+    package_ids
+}
+
+fn links(graph: &PackageGraph) -> Vec<(&PackageId, &PackageId, &str, String)> {
+    let mut links: Vec<_> = graph
+        .packages()
+        .flat_map(|package| package.direct_links())
+        .map(|link| {
+            (
+                link.from().id(),
+                link.to().id(),
+                link.dep_name(),
+                link.version_req().to_string(),
+            )
+        })
+        .collect();
+    links.sort_unstable();
+    links
+}
+
+fn assert_parse_error(bytes: &[u8], desc: &str) {
+    match PackageGraph::deserialize_binary(&mut &*bytes) {
+        Err(Error::BinaryParseError(_)) => {}
+        Err(other) => panic!("{}: unexpected error {}", desc, other),
+        Ok(_) => panic!("{}: deserialization should fail", desc),
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use camino::Utf8Path;
+use guppy::{
+    graph::{
+        cargo::{CargoOptions, CycloneDxExporter},
+        feature::StandardFeatures,
+        PackageGraph,
+    },
+    platform::{Platform, TargetFeatures},
+};
+use serde_json::Value as JsonValue;
+
+fn component<'a>(bom: &'a JsonValue, name: &str) -> &'a JsonValue {
+    bom["components"]
+        .as_array()
+        .expect("components is an array")
+        .iter()
+        .find(|component| component["name"] == name)
+        .unwrap_or_else(|| panic!("component {} found", name))
+}
+
+/// Returns the names of the components the given component depends on.
+fn depends_on<'a>(bom: &'a JsonValue, name: &str) -> Vec<&'a str> {
+    let components = bom["components"]
+        .as_array()
+        .expect("components is an array");
+    let bom_ref = &component(bom, name)["bom-ref"];
+    let mut names: Vec<_> = bom["dependencies"]
+        .as_array()
+        .expect("dependencies is an array")
+        .iter()
+        .find(|dependency| &dependency["ref"] == bom_ref)
+        .expect("dependency entry found")["dependsOn"]
+        .as_array()
+        .expect("dependsOn is an array")
+        .iter()
+        .map(|dep| {
+            let component = components
+                .iter()
+                .find(|component| &component["bom-ref"] == dep)
+                .expect("dependsOn refers to a component");
+            component["name"].as_str().expect("name is a string")
+        })
+        .collect();
+    names.sort_unstable();
+    names
+}
+
+fn properties(component: &JsonValue) -> Vec<(&str, &str)> {
+    component["properties"]
+        .as_array()
+        .expect("properties is an array")
+        .iter()
+        .map(|property| {
+            (
+                property["name"].as_str().expect("name is a string"),
+                property["value"].as_str().expect("value is a string"),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn cyclonedx_inside_outside() {
+    let workspace_root = Utf8Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../fixtures/workspace/inside-outside/workspace");
+    let graph = PackageGraph::from_lockfile(&workspace_root).expect("graph should be constructed");
+
+    let mut opts = CargoOptions::new();
+    opts.set_platform(
+        Platform::new("x86_64-unknown-linux-gnu", TargetFeatures::Unknown).expect("known triple"),
+    );
+    let cargo_set = graph
+        .resolve_workspace_names(["main"])
+        .expect("main is a workspace member")
+        .to_feature_set(StandardFeatures::Default)
+        .into_cargo_set(&opts)
+        .expect("cargo set resolved");
+
+    let mut exporter = CycloneDxExporter::new(&cargo_set, &opts);
+    exporter
+        .read_lockfile(workspace_root.join("Cargo.lock"))
+        .expect("Cargo.lock read")
+        .set_serial_number("urn:uuid:3e671687-395b-41f5-a30f-a58921a69b79");
+    let bom = exporter.export();
+
+    assert_eq!(bom["bomFormat"], "CycloneDX");
+    assert_eq!(bom["specVersion"], "1.5");
+    assert_eq!(
+        bom["serialNumber"],
+        "urn:uuid:3e671687-395b-41f5-a30f-a58921a69b79"
+    );
+    assert_eq!(
+        properties(&bom["metadata"]),
+        vec![
+            ("guppy:target-platform", "x86_64-unknown-linux-gnu"),
+            ("guppy:host-platform", "x86_64-unknown-linux-gnu"),
+        ],
+        "platforms are recorded"
+    );
+
+    let mut names: Vec<_> = bom["components"]
+        .as_array()
+        .expect("components is an array")
+        .iter()
+        .map(|component| component["name"].as_str().expect("name is a string"))
+        .collect();
+    names.sort_unstable();
+    assert_eq!(
+        names,
+        vec![
+            "external",
+            "internal",
+            "internal-macro",
+            "lazy_static",
+            "main",
+            "spin",
+            "transitive",
+        ],
+        "inactive platform-specific dependencies and dev-dependencies are skipped"
+    );
+
+    let lazy_static = component(&bom, "lazy_static");
+    assert_eq!(lazy_static["purl"], "pkg:cargo/lazy_static@1.4.0");
+    assert_eq!(
+        lazy_static["hashes"],
+        serde_json::json!([{
+            "alg": "SHA-256",
+            "content": "e2abad23fbc42b3700f2f279844dc832adb2b2eb069b2df918f455c4e18cc646",
+        }]),
+        "checksum from Cargo.lock"
+    );
+
+    let main = component(&bom, "main");
+    assert!(main.get("purl").is_none(), "workspace members have no purl");
+    assert!(
+        main.get("hashes").is_none(),
+        "path packages have no checksum"
+    );
+    assert_eq!(
+        depends_on(&bom, "main"),
+        vec!["external", "internal", "internal-macro"],
+        "main's enabled dependencies"
+    );
+    assert_eq!(
+        depends_on(&bom, "internal"),
+        vec!["external", "internal-macro", "lazy_static"],
+        "disabled optional dependencies and dev-dependencies are skipped"
+    );
+    assert_eq!(
+        properties(component(&bom, "internal-macro")),
+        vec![
+            ("guppy:build-platform", "host"),
+            (
+                "guppy:host-features",
+                "internal-normal-feature,main-build-feature"
+            ),
+        ],
+        "proc macros are only built on the host"
+    );
+
+    let external = component(&bom, "external");
+    let external_properties = properties(external);
+    assert!(
+        external_properties.contains(&("guppy:build-platform", "target"))
+            && external_properties.contains(&("guppy:build-platform", "host")),
+        "external is built on both platforms: {:?}",
+        external_properties
+    );
+}
@@ -6,11 +6,27 @@ use fixtures::{
     package_id,
 };
 use guppy::graph::{
-    feature::{named_feature_filter, FeatureId, FeatureLabel, StandardFeatures},
-    BuildTargetId, BuildTargetKind, DependencyDirection, DotWrite, PackageDotVisitor, PackageLink,
-    PackageMetadata,
+    cargo::{BuildPlatform, CargoOptions},
+    feature::{named_feature_filter, FeatureDotStyle, FeatureId, FeatureLabel, StandardFeatures},
+    ArtifactKind, ArtifactTarget, BuildPlan, BuildTargetId, BuildTargetKind, CrateSize, CrateSizes,
+    DependencyChain, DependencyDirection, DependencyProvenance, DiagramFormat, DiagramOptions,
+    DotAttributes, DotWrite, FutureIncompatReport, GitReq, IterationOrder, LicenseExpr,
+    NonstandardLicense, PackageDotStyle, PackageDotVisitor, PackageGraph, PackageLink,
+    PackageMetadata, PackageSet, PackageTraversalEvent, PathOwner, Policy, PolicyReport,
+    SemverCompatibility, SetExport, SourceExport, SourceKind, SourcePolicy, TraversalControl,
+    TraversalOrder, UnitGraph, ViolationKind, EXPORT_FORMAT_VERSION,
+};
+use guppy::{
+    petgraph::{self, visit::EdgeRef},
+    platform::{EnabledTernary, Platform, PlatformSpec, TargetFeatures},
+    CargoMetadata, DependencyKind, Error, MetadataDegradation, PackageId, Version, VersionReq,
+};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt, iter,
+    sync::Arc,
 };
-use std::{fmt, iter};
 
 mod small {
     use super::*;
@@ -138,312 +154,3675 @@ mod small {
     proptest_suite!(metadata1);
 
     #[test]
-    fn metadata2() {
-        let metadata2 = JsonFixture::metadata2();
-        metadata2.verify();
+    fn metadata1_unit_graph() {
+        let graph = JsonFixture::metadata1().graph();
+        let unit_graph = UnitGraph::parse_json(
+            r#"{
+  "version": 1,
+  "units": [
+    {
+      "pkg_id": "testcrate 0.1.0 (path+file:///fakepath/testcrate)",
+      "target": {"kind": ["bin"], "crate_types": ["bin"], "name": "testcrate", "src_path": "/fakepath/testcrate/src/main.rs", "edition": "2018"},
+      "profile": {"name": "dev", "opt_level": "0", "debug_assertions": true, "overflow_checks": true, "panic": "unwind"},
+      "platform": "x86_64-unknown-linux-gnu",
+      "mode": "build",
+      "features": [],
+      "dependencies": [{"index": 1, "extern_crate_name": "regex", "public": false}]
+    },
+    {
+      "pkg_id": "regex 1.3.1 (registry+https://github.com/rust-lang/crates.io-index)",
+      "target": {"kind": ["lib"], "crate_types": ["lib"], "name": "regex", "src_path": "/fakepath/regex/src/lib.rs", "edition": "2015"},
+      "profile": {"name": "dev", "opt_level": "0", "debug_assertions": true, "overflow_checks": true, "panic": "unwind"},
+      "platform": "x86_64-unknown-linux-gnu",
+      "mode": "build",
+      "features": ["default", "std"],
+      "dependencies": []
+    },
+    {
+      "pkg_id": "lazy_static 1.4.0 (registry+https://github.com/rust-lang/crates.io-index)",
+      "target": {"kind": ["lib"], "crate_types": ["lib"], "name": "lazy_static", "src_path": "/fakepath/lazy_static/src/lib.rs", "edition": "2015"},
+      "profile": {"name": "dev", "opt_level": "0", "debug_assertions": true, "overflow_checks": true, "panic": "unwind"},
+      "platform": null,
+      "mode": "build",
+      "features": [],
+      "dependencies": []
+    },
+    {
+      "pkg_id": "core 0.0.0 (path+file:///rustlib/src/rust/library/core)",
+      "target": {"kind": ["lib"], "crate_types": ["lib"], "name": "core", "src_path": "/rustlib/src/rust/library/core/src/lib.rs", "edition": "2021"},
+      "profile": {"name": "dev", "opt_level": "0", "debug_assertions": true, "overflow_checks": true, "panic": "unwind"},
+      "platform": "x86_64-unknown-linux-gnu",
+      "mode": "build",
+      "features": [],
+      "is_std": true,
+      "dependencies": []
+    }
+  ],
+  "roots": [0]
+}"#,
+        )
+        .expect("valid unit graph");
+        assert_eq!(unit_graph.version(), 1);
+        assert_eq!(unit_graph.units().len(), 4);
+        assert_eq!(
+            unit_graph.units()[0].target().build_target_id(),
+            Some(BuildTargetId::Binary("testcrate"))
+        );
+        assert_eq!(
+            unit_graph.units()[1].target().build_target_id(),
+            Some(BuildTargetId::Library)
+        );
 
-        let feature_graph = metadata2.graph().feature_graph();
-        assert_eq!(feature_graph.feature_count(), 484, "feature count");
-        assert_eq!(feature_graph.link_count(), 589, "link count");
-        let root_ids: Vec<_> = feature_graph
-            .query_workspace(StandardFeatures::None)
-            .resolve()
-            .root_ids(DependencyDirection::Forward)
+        let correlated = unit_graph.correlate(graph).expect("units correlated");
+        assert_eq!(correlated.units().count(), 3, "std units are skipped");
+        let roots: Vec<_> = correlated
+            .roots()
+            .map(|unit| unit.package().name())
             .collect();
-        let testcrate_id = package_id(json::METADATA2_TESTCRATE);
-        let expected = vec![FeatureId::base(&testcrate_id)];
-        assert_eq!(root_ids, expected, "feature graph root IDs match");
-    }
+        assert_eq!(roots, vec!["testcrate"], "roots match");
 
-    proptest_suite!(metadata2);
+        let mut target_packages: Vec<_> = correlated
+            .package_set_with(|unit| unit.unit().platform().is_some())
+            .packages(DependencyDirection::Forward)
+            .map(|package| package.name())
+            .collect();
+        target_packages.sort_unstable();
+        assert_eq!(target_packages, vec!["regex", "testcrate"]);
 
-    #[test]
-    fn metadata_dups() {
-        let metadata_dups = JsonFixture::metadata_dups();
-        metadata_dups.verify();
-    }
+        let regex_id =
+            PackageId::new("regex 1.3.1 (registry+https://github.com/rust-lang/crates.io-index)");
+        let feature_set = correlated.feature_set().expect("features are known");
+        assert!(feature_set
+            .contains((&regex_id, FeatureLabel::Named("std")))
+            .expect("valid feature ID"));
+        assert!(!feature_set
+            .contains((&regex_id, FeatureLabel::Named("perf")))
+            .expect("valid feature ID"));
+        let host_features = correlated
+            .feature_set_with(|unit| unit.unit().platform().is_none())
+            .expect("features are known");
+        assert_eq!(host_features.len(), 1, "only the lazy_static base feature");
 
-    proptest_suite!(metadata_dups);
+        let unknown = UnitGraph::parse_json(
+            r#"{"version": 1, "units": [
+                {"pkg_id": "unknown 1.0.0 (registry+https://github.com/rust-lang/crates.io-index)",
+                 "target": {"kind": ["lib"], "name": "unknown", "src_path": "/lib.rs"},
+                 "profile": {"name": "dev", "opt_level": "0"},
+                 "platform": null, "mode": "build"}
+            ], "roots": [0]}"#,
+        )
+        .expect("valid unit graph");
+        assert!(
+            matches!(unknown.correlate(graph), Err(Error::UnknownPackageId(_))),
+            "unknown packages are reported"
+        );
+        assert!(
+            matches!(
+                UnitGraph::parse_json(r#"{"version": 1, "units": [], "roots": [0]}"#),
+                Err(Error::UnitGraphParseError(_))
+            ),
+            "out of range roots are reported"
+        );
+    }
 
     #[test]
-    fn metadata_cycle1() {
-        let metadata_cycle1 = JsonFixture::metadata_cycle1();
-        metadata_cycle1.verify();
+    fn metadata1_build_plan() {
+        let graph = JsonFixture::metadata1().graph();
+        let build_plan = BuildPlan::parse_json(
+            r#"{
+  "invocations": [
+    {
+      "package_name": "regex",
+      "package_version": "1.3.1",
+      "target_kind": ["lib"],
+      "kind": null,
+      "compile_mode": "build",
+      "deps": [],
+      "outputs": ["/fakepath/testcrate/target/debug/deps/libregex-0123.rlib"],
+      "links": {},
+      "program": "rustc",
+      "args": ["--crate-name", "regex", "--edition=2015", "--cfg", "feature=\"default\"", "--cfg", "feature=\"std\""],
+      "env": {"CARGO_PKG_NAME": "regex"},
+      "cwd": "/fakepath/regex"
+    },
+    {
+      "package_name": "testcrate",
+      "package_version": "0.1.0",
+      "target_kind": ["bin"],
+      "kind": null,
+      "compile_mode": "build",
+      "deps": [0],
+      "outputs": ["/fakepath/testcrate/target/debug/deps/testcrate-4567"],
+      "links": {"/fakepath/testcrate/target/debug/testcrate": "/fakepath/testcrate/target/debug/deps/testcrate-4567"},
+      "program": "rustc",
+      "args": ["--crate-name", "testcrate", "--edition=2018"],
+      "env": {},
+      "cwd": "/fakepath/testcrate"
     }
+  ],
+  "inputs": ["/fakepath/testcrate/Cargo.toml"]
+}"#,
+        )
+        .expect("valid build plan");
+        assert_eq!(build_plan.invocations().len(), 2);
+        let regex_invocation = &build_plan.invocations()[0];
+        assert_eq!(regex_invocation.crate_name(), Some("regex"));
+        assert_eq!(
+            regex_invocation.features().collect::<Vec<_>>(),
+            vec!["default", "std"],
+            "features parsed from rustc arguments"
+        );
 
-    proptest_suite!(metadata_cycle1);
+        let correlated = build_plan
+            .correlate(graph)
+            .expect("invocations correlated");
+        let testcrate_id = package_id(json::METADATA1_TESTCRATE);
+        assert_eq!(
+            correlated
+                .outputs_for(&testcrate_id)
+                .expect("valid package ID"),
+            vec!["/fakepath/testcrate/target/debug/deps/testcrate-4567"],
+            "outputs for testcrate match"
+        );
+        let invocation = correlated
+            .invocations_for(&testcrate_id)
+            .expect("valid package ID")
+            .next()
+            .expect("testcrate has an invocation");
+        assert_eq!(invocation.index(), 1);
+        assert_eq!(invocation.invocation().deps(), &[0]);
+        assert_eq!(correlated.package_set().len(), 2);
 
-    #[test]
-    fn metadata_cycle2() {
-        let metadata_cycle2 = JsonFixture::metadata_cycle2();
-        metadata_cycle2.verify();
-    }
+        let regex_id =
+            PackageId::new("regex 1.3.1 (registry+https://github.com/rust-lang/crates.io-index)");
+        let feature_set = correlated
+            .feature_set_with(|_| true)
+            .expect("features are known");
+        assert!(feature_set
+            .contains((&regex_id, FeatureLabel::Named("std")))
+            .expect("valid feature ID"));
 
-    proptest_suite!(metadata_cycle2);
+        // quote 1.0.2 is present both from crates.io and from a path, so the working directory is
+        // used to disambiguate.
+        let quote_plan = |cwd: &str| {
+            BuildPlan::parse_json(format!(
+                r#"{{"invocations": [
+                    {{"package_name": "quote", "package_version": "1.0.2", "target_kind": ["lib"],
+                      "kind": null, "compile_mode": "build", "program": "rustc", "cwd": "{}"}}
+                ]}}"#,
+                cwd
+            ))
+            .expect("valid build plan")
+        };
+        let plan = quote_plan("/fakepath/quote");
+        let correlated = plan.correlate(graph).expect("invocations correlated");
+        assert_eq!(
+            correlated
+                .invocation(0)
+                .expect("invocation exists")
+                .package()
+                .id(),
+            &PackageId::new("quote 1.0.2 (path+file:///fakepath/quote)"),
+        );
+        assert!(
+            matches!(
+                quote_plan("/elsewhere").correlate(graph),
+                Err(Error::BuildPlanError(_))
+            ),
+            "ambiguous packages are reported"
+        );
+    }
 
     #[test]
-    fn metadata_cycle_features() {
-        let metadata_cycle_features = JsonFixture::metadata_cycle_features();
-        metadata_cycle_features.verify();
-        let feature_graph = metadata_cycle_features.graph().feature_graph();
-
-        let base_id = package_id(METADATA_CYCLE_FEATURES_BASE);
-        let default_id = FeatureId::named(&base_id, "default");
+    fn metadata1_future_incompat_report() {
+        let graph = JsonFixture::metadata1().graph();
+        let messages = r#"
+{"reason":"compiler-artifact","package_id":"datatest 0.4.2 (registry+https://github.com/rust-lang/crates.io-index)"}
+{"reason":"future-incompat-report","id":3,"future_incompat_report":[{"package_id":"datatest 0.4.2 (registry+https://github.com/rust-lang/crates.io-index)","items":[{"future_breakage_date":null,"diagnostic":{"rendered":"warning: this was previously accepted by the compiler","level":"warning"}}]}]}
+{"reason":"build-finished","success":true}
+"#;
+        let report = FutureIncompatReport::from_messages(messages)
+            .expect("messages parsed")
+            .expect("report found");
+        assert_eq!(report.id(), Some(3));
+        assert_eq!(report.packages().len(), 1);
+        let item = &report.packages()[0].items()[0];
+        assert_eq!(item.level(), "warning");
+        assert_eq!(item.future_breakage_date(), None);
 
-        // default, default-enable and default-transitive are default features.
-        for &f in &["default", "default-enable", "default-transitive"] {
-            let this_id = FeatureId::named(&base_id, f);
-            assert!(
-                feature_graph
-                    .is_default_feature(this_id)
-                    .expect("valid feature ID"),
-                "{} is a default feature",
-                f,
-            );
-            assert!(
-                feature_graph
-                    .depends_on(default_id, this_id)
-                    .expect("valid feature IDs"),
-                "{} should depend on {} but does not",
-                default_id,
-                this_id,
-            );
-        }
+        let correlated = report.correlate(graph).expect("packages correlated");
+        let entry = correlated.entries()[0];
+        assert_eq!(entry.package().name(), "datatest");
+        let testcrate_id = package_id(json::METADATA1_TESTCRATE);
+        let dependents: Vec<_> = correlated
+            .workspace_dependents()
+            .package_ids(DependencyDirection::Forward)
+            .collect();
+        assert_eq!(
+            dependents,
+            vec![&testcrate_id],
+            "testcrate depends on datatest"
+        );
+        assert_eq!(
+            entry.workspace_dependents().len(),
+            1,
+            "entry dependents match"
+        );
 
-        // helper-enable and helper-transitive are *not* default features even though they are
-        // enabled by the cyclic dev dependency. But the dependency relation is present.
-        for &f in &["helper-enable", "helper-transitive"] {
-            let this_id = FeatureId::named(&base_id, f);
-            assert!(
-                !feature_graph
-                    .is_default_feature(this_id)
-                    .expect("valid feature ID"),
-                "{} is NOT a default feature",
-                f,
-            );
-            assert!(
-                feature_graph
-                    .depends_on(default_id, this_id)
-                    .expect("valid feature IDs"),
-                "{} should depend on {} but does not",
-                default_id,
-                this_id,
-            );
-        }
+        assert!(
+            FutureIncompatReport::from_messages(r#"{"reason":"build-finished","success":true}"#)
+                .expect("messages parsed")
+                .is_none(),
+            "no report in output"
+        );
     }
 
-    proptest_suite!(metadata_cycle_features);
-
     #[test]
-    fn metadata_targets1() {
-        let metadata_targets1 = JsonFixture::metadata_targets1();
-        metadata_targets1.verify();
+    fn metadata1_public_dependencies() {
+        // Mark regex -> aho-corasick and aho-corasick -> memchr as public, so that regex's private
+        // dependency on memchr is exposed through aho-corasick.
+        let mut json: serde_json::Value =
+            serde_json::from_str(JsonFixture::metadata1().json()).expect("valid JSON");
+        for package in json["packages"]
+            .as_array_mut()
+            .expect("packages is an array")
+        {
+            let public_dep = match package["name"].as_str() {
+                Some("regex") => "aho-corasick",
+                Some("aho-corasick") => "memchr",
+                _ => continue,
+            };
+            for dep in package["dependencies"].as_array_mut().expect("array") {
+                if dep["name"] == public_dep {
+                    dep["public"] = true.into();
+                }
+            }
+        }
+        let metadata = CargoMetadata::parse_json(json.to_string()).expect("valid metadata");
 
-        let package_graph = metadata_targets1.graph();
-        let package_set = package_graph.resolve_all();
-        let feature_graph = metadata_targets1.graph().feature_graph();
-        assert_eq!(feature_graph.feature_count(), 38, "feature count");
+        // The public flags are preserved across serialization.
+        let mut serialized = vec![];
+        metadata.serialize(&mut serialized).expect("serialized");
+        let serialized = String::from_utf8(serialized).expect("valid UTF-8");
+        let graph = CargoMetadata::parse_json(serialized)
+            .expect("valid metadata")
+            .build_graph()
+            .expect("valid graph");
 
-        // Some code that might be useful for debugging.
-        if false {
-            for (source, target, edge) in feature_graph
-                .resolve_all()
-                .links(DependencyDirection::Forward)
-            {
-                let source_metadata = package_graph.metadata(source.package_id()).unwrap();
-                let target_metadata = package_graph.metadata(target.package_id()).unwrap();
+        let regex_id =
+            PackageId::new("regex 1.3.1 (registry+https://github.com/rust-lang/crates.io-index)");
+        let regex = graph.metadata(&regex_id).expect("regex is known");
+        let public: Vec<_> = regex
+            .direct_links()
+            .filter(|link| link.is_public())
+            .map(|link| link.dep_name())
+            .collect();
+        assert_eq!(public, vec!["aho-corasick"], "regex's public dependencies");
+        assert!(
+            JsonFixture::metadata1()
+                .graph()
+                .packages()
+                .flat_map(|package| package.direct_links())
+                .all(|link| !link.is_public()),
+            "dependencies are private by default"
+        );
 
-                println!(
-                    "feature link: {}:{} {} -> {}:{} {} {:?}",
-                    source_metadata.name(),
-                    source_metadata.version(),
-                    source.label(),
-                    target_metadata.name(),
-                    target_metadata.version(),
-                    target.label(),
-                    edge
-                );
+        let leaked = graph.resolve_all().leaked_private_dependencies();
+        assert_eq!(leaked.len(), 1, "one leaked private dependency");
+        let leaked = &leaked[0];
+        assert_eq!(leaked.private_link().from().name(), "regex");
+        assert_eq!(leaked.private_link().to().name(), "memchr");
+        let exposed_by: Vec<_> = leaked
+            .exposed_by()
+            .links()
+            .iter()
+            .map(|link| link.to().name())
+            .collect();
+        assert_eq!(exposed_by, vec!["aho-corasick", "memchr"]);
+    }
+
+    #[test]
+    fn metadata1_artifact_dependencies() {
+        // Turn testcrate's normal dependency on datatest into an artifact dependency forced onto
+        // the host platform.
+        let host_triple = "x86_64-unknown-linux-gnu";
+        let mut json: serde_json::Value =
+            serde_json::from_str(JsonFixture::metadata1().json()).expect("valid JSON");
+        for package in json["packages"]
+            .as_array_mut()
+            .expect("packages is an array")
+        {
+            if package["name"] != "testcrate" {
+                continue;
+            }
+            for dep in package["dependencies"].as_array_mut().expect("array") {
+                if dep["name"] == "datatest" && dep["kind"].is_null() {
+                    dep["artifact"] = serde_json::json!({
+                        "kinds": ["bin", "bin:datatest-helper"],
+                        "lib": false,
+                        "target": host_triple,
+                    });
+                }
             }
         }
+        let metadata = CargoMetadata::parse_json(json.to_string()).expect("valid metadata");
 
-        assert_eq!(feature_graph.link_count(), 58, "feature link count");
+        // Artifact information is preserved across serialization.
+        let mut serialized = vec![];
+        metadata.serialize(&mut serialized).expect("serialized");
+        let serialized = String::from_utf8(serialized).expect("valid UTF-8");
+        let graph = CargoMetadata::parse_json(serialized)
+            .expect("valid metadata")
+            .build_graph()
+            .expect("valid graph");
 
-        // Check that resolve_packages + a feature filter works.
-        let feature_set = package_set.to_feature_set(named_feature_filter(
-            StandardFeatures::Default,
-            ["foo", "bar"].iter().copied(),
-        ));
-        let dep_a_id = package_id(json::METADATA_TARGETS1_DEP_A);
-        assert!(feature_set
-            .contains((&dep_a_id, FeatureLabel::Named("foo")))
-            .expect("valid feature ID"));
-        assert!(feature_set
-            .contains((&dep_a_id, FeatureLabel::Named("bar")))
-            .expect("valid feature ID"));
-        assert!(!feature_set
-            .contains((&dep_a_id, FeatureLabel::Named("baz")))
-            .expect("valid feature ID"));
-        assert!(!feature_set
-            .contains((&dep_a_id, FeatureLabel::Named("quux")))
-            .expect("valid feature ID"));
+        let testcrate = graph
+            .metadata(&package_id(json::METADATA1_TESTCRATE))
+            .expect("testcrate is known");
+        let link = testcrate
+            .direct_links()
+            .find(|link| link.to().name() == "datatest")
+            .expect("testcrate depends on datatest");
+        assert!(link.is_artifact(), "datatest is an artifact dependency");
+        assert!(link.build().artifact().is_none(), "not a build dependency");
+        let artifact = link
+            .normal()
+            .artifact()
+            .expect("normal artifact dependency");
+        assert_eq!(
+            artifact.kinds().collect::<Vec<_>>(),
+            vec![
+                ArtifactKind::AllBinaries,
+                ArtifactKind::Binary("datatest-helper")
+            ],
+        );
+        assert!(!artifact.lib(), "library isn't depended on");
+        assert_eq!(artifact.target(), Some(ArtifactTarget::Triple(host_triple)));
+        assert!(
+            JsonFixture::metadata1()
+                .graph()
+                .packages()
+                .flat_map(|package| package.direct_links())
+                .all(|link| !link.is_artifact()),
+            "no artifact dependencies by default"
+        );
 
-        assert_features_for_package(
-            &feature_set,
-            &package_id(json::METADATA_TARGETS1_TESTCRATE),
-            Some(&[FeatureLabel::Base]),
-            "testcrate",
+        // When cross-compiling, datatest is built on the host, not the target.
+        let mut opts = CargoOptions::new();
+        opts.set_host_platform(
+            Platform::new(host_triple, TargetFeatures::Unknown).expect("known triple"),
+        )
+        .set_target_platform(
+            Platform::new("aarch64-unknown-linux-gnu", TargetFeatures::Unknown)
+                .expect("known triple"),
         );
-        assert_features_for_package(
-            &feature_set,
-            &dep_a_id,
-            Some(&[
-                FeatureLabel::Base,
-                FeatureLabel::Named("bar"),
-                FeatureLabel::Named("foo"),
-            ]),
-            "dep a",
+        let cargo_set = graph
+            .resolve_workspace()
+            .to_feature_set(StandardFeatures::Default)
+            .into_cargo_set(&opts)
+            .expect("cargo resolution succeeds");
+        let datatest_id = link.to().id();
+        assert!(
+            !cargo_set
+                .target_features()
+                .contains_package(datatest_id)
+                .expect("valid ID"),
+            "datatest isn't built on the target"
         );
-        assert_features_for_package(
-            &feature_set,
-            &package_id(json::METADATA_TARGETS1_LAZY_STATIC_1),
-            Some(&[FeatureLabel::Base]),
-            "lazy_static",
+        assert!(
+            cargo_set
+                .host_features()
+                .contains_package(datatest_id)
+                .expect("valid ID"),
+            "datatest is built on the host"
         );
     }
 
-    proptest_suite!(metadata_targets1);
-
     #[test]
-    fn metadata_build_targets1() {
-        let metadata_build_targets1 = JsonFixture::metadata_build_targets1();
-        metadata_build_targets1.verify();
-    }
+    fn metadata1_provenance() {
+        let graph = JsonFixture::metadata1().graph();
+        let query = graph
+            .query_forward(iter::once(&package_id(json::METADATA1_TESTCRATE)))
+            .expect("valid package ID");
+        let provenance_set = query.clone().resolve_provenance();
+        assert_eq!(
+            provenance_set.all(),
+            &query.resolve(),
+            "all packages are resolved"
+        );
 
-    // No need for proptests because there are no dependencies involved.
+        let provenance_for = |name: &str| {
+            let package = graph
+                .resolve_package_name(name)
+                .packages(DependencyDirection::Forward)
+                .next()
+                .expect("package is known");
+            provenance_set
+                .provenance(package.id())
+                .expect("valid package ID")
+        };
+        assert_eq!(
+            provenance_for("testcrate"),
+            Some(DependencyProvenance::Normal),
+            "initials are normal"
+        );
+        assert_eq!(
+            provenance_for("regex"),
+            Some(DependencyProvenance::Normal),
+            "regex ships in the product"
+        );
+        assert_eq!(
+            provenance_for("version_check"),
+            Some(DependencyProvenance::Build),
+            "version_check is a build dependency of datatest"
+        );
+        assert!(
+            provenance_set
+                .with_provenance(DependencyProvenance::Dev)
+                .is_empty(),
+            "testcrate's only dev-dependency is also a normal dependency"
+        );
 
-    #[test]
-    fn metadata_proc_macro1() {
-        let metadata = JsonFixture::metadata_proc_macro1();
-        metadata.verify();
-        let graph = metadata.graph();
+        for (package, provenance) in provenance_set.packages(DependencyDirection::Forward) {
+            assert!(
+                provenance_set
+                    .with_provenance(provenance)
+                    .contains(package.id())
+                    .expect("valid package ID"),
+                "{} is in the set for {:?}",
+                package.name(),
+                provenance,
+            );
+        }
+        assert_eq!(
+            provenance_set.normal().len() + provenance_set.build_or_dev_only().len(),
+            provenance_set.all().len(),
+            "normal and build/dev-only packages partition the set"
+        );
+    }
 
-        let package = graph
-            .metadata(&package_id(json::METADATA_PROC_MACRO1_MACRO))
+    #[test]
+    fn metadata1_depths() {
+        let graph = JsonFixture::metadata1().graph();
+        let testcrate_id = package_id(json::METADATA1_TESTCRATE);
+        let query = graph
+            .query_forward(iter::once(&testcrate_id))
             .expect("valid package ID");
-        assert!(package.is_proc_macro(), "is proc macro");
-        assert!(matches!(
-            package
-                .build_target(&BuildTargetId::Library)
-                .expect("library package is present")
-                .kind(),
-            BuildTargetKind::ProcMacro
-        ));
-    }
+        let depth_set = query.clone().resolve_depths();
+        assert_eq!(depth_set.all(), &query.clone().resolve());
+        assert_eq!(
+            depth_set.depth(&testcrate_id).expect("valid package ID"),
+            Some(0),
+            "initials are at depth 0"
+        );
+        assert_eq!(
+            depth_set
+                .depth(&package_id(json::METADATA1_DATATEST))
+                .expect("valid package ID"),
+            Some(1),
+            "direct dependencies are at depth 1"
+        );
 
-    // No need for proptests because this is a really simple test.
-}
+        for (package, depth) in depth_set.packages(DependencyDirection::Forward) {
+            if depth == 0 {
+                continue;
+            }
+            let parent_depth = package
+                .reverse_direct_links()
+                .filter_map(|link| depth_set.depth(link.from().id()).expect("valid package ID"))
+                .min();
+            assert_eq!(
+                parent_depth,
+                Some(depth - 1),
+                "{} is one link away from its closest dependent",
+                package.id()
+            );
+        }
 
-mod large {
-    use super::*;
-    use fixtures::dep_helpers::GraphAssert;
+        let max_depth = depth_set.max_depth().expect("set is non-empty");
+        let groups = depth_set.group_by_depth();
+        assert_eq!(
+            groups.keys().copied().collect::<Vec<_>>(),
+            (0..=max_depth).collect::<Vec<_>>()
+        );
+        for depth in 0..=max_depth {
+            assert_eq!(depth_set.at_depth(depth), groups[&depth]);
+            let mut limited = query.clone();
+            limited.set_max_depth(depth);
+            assert_eq!(
+                depth_set.within_depth(depth),
+                limited.clone().resolve(),
+                "packages within depth {} match a depth-limited query",
+                depth
+            );
+            assert_eq!(
+                limited.resolve_depths().all(),
+                &depth_set.within_depth(depth)
+            );
+        }
+    }
 
     #[test]
-    fn metadata_libra() {
-        let metadata_libra = JsonFixture::metadata_libra();
-        metadata_libra.verify();
-    }
+    fn metadata1_iteration_orders() {
+        let graph = JsonFixture::metadata1().graph();
+        let package_set = graph.resolve_all();
+
+        let topo: Vec<_> = package_set
+            .package_ids_in(IterationOrder::Topological)
+            .collect();
+        assert_eq!(
+            topo,
+            package_set
+                .package_ids(DependencyDirection::Forward)
+                .collect::<Vec<_>>(),
+            "topological order matches forward iteration"
+        );
+        let reverse_topo: Vec<_> = package_set
+            .package_ids_in(DependencyDirection::Reverse.into())
+            .collect();
+        assert_eq!(
+            reverse_topo,
+            package_set
+                .package_ids(DependencyDirection::Reverse)
+                .collect::<Vec<_>>(),
+            "reverse topological order matches reverse iteration"
+        );
+
+        let lexical: Vec<_> = package_set
+            .packages_in(IterationOrder::Lexical)
+            .map(|package| (package.name(), package.version(), package.id()))
+            .collect();
+        let mut sorted = lexical.clone();
+        sorted.sort();
+        assert_eq!(
+            lexical, sorted,
+            "lexical order is sorted by name and version"
+        );
+
+        let unspecified: BTreeSet<_> = package_set
+            .package_ids_in(IterationOrder::Unspecified)
+            .collect();
+        assert_eq!(
+            unspecified,
+            topo.iter().copied().collect(),
+            "every order returns the same packages"
+        );
+        assert!(
+            package_set
+                .package_ids_in(IterationOrder::Unspecified)
+                .eq(package_set.package_ids_in(IterationOrder::Unspecified)),
+            "unspecified order is deterministic"
+        );
+
+        let feature_set = graph.feature_graph().resolve_all();
+        let features: Vec<_> = feature_set
+            .feature_ids_in(IterationOrder::Lexical)
+            .map(|feature_id| {
+                let package = graph
+                    .metadata(feature_id.package_id())
+                    .expect("valid package ID");
+                (package.name(), package.version(), feature_id)
+            })
+            .collect();
+        let mut sorted = features.clone();
+        sorted.sort();
+        assert_eq!(features, sorted, "lexical order for features is sorted");
+        assert_eq!(
+            features.len(),
+            feature_set.len(),
+            "every feature is returned"
+        );
+        let lists: Vec<_> = feature_set
+            .packages_with_features_in(IterationOrder::Lexical)
+            .map(|list| list.package().name())
+            .collect();
+        let mut sorted = lists.clone();
+        sorted.sort_unstable();
+        assert_eq!(lists, sorted, "lexical order for feature lists is sorted");
+    }
+
+    #[test]
+    fn metadata1_old_cargo_format() {
+        let mut json: serde_json::Value =
+            serde_json::from_str(JsonFixture::metadata1().json()).expect("valid JSON");
+        // Remove fields that older versions of Cargo don't produce, and add fields that guppy
+        // doesn't know about.
+        let object = json.as_object_mut().expect("metadata is an object");
+        object.remove("version");
+        object.remove("target_directory");
+        object.insert(
+            "from_the_future".to_owned(),
+            serde_json::json!({ "a": [1, 2] }),
+        );
+        for node in json["resolve"]["nodes"].as_array_mut().expect("nodes") {
+            node.as_object_mut()
+                .expect("node is an object")
+                .remove("deps");
+            node["future_field"] = true.into();
+        }
+
+        let metadata = CargoMetadata::parse_json(json.to_string()).expect("parsed");
+        assert_eq!(
+            metadata.degradations(),
+            &[
+                MetadataDegradation::MissingFormatVersion,
+                MetadataDegradation::MissingTargetDirectory,
+                MetadataDegradation::MissingResolvedNames,
+            ],
+        );
+        let old_graph = metadata.build_graph().expect("graph built");
+
+        let graph = JsonFixture::metadata1().graph();
+        assert!(
+            CargoMetadata::parse_json(JsonFixture::metadata1().json())
+                .expect("parsed")
+                .degradations()
+                .is_empty(),
+            "current metadata has no degradations"
+        );
+        assert_eq!(old_graph.package_count(), graph.package_count());
+        let links = |graph: &PackageGraph| -> BTreeSet<(String, String, String)> {
+            graph
+                .resolve_all()
+                .links(DependencyDirection::Forward)
+                .map(|link| {
+                    (
+                        link.from().id().repr().to_owned(),
+                        link.dep_name().to_owned(),
+                        link.to().id().repr().to_owned(),
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(
+            links(&old_graph),
+            links(graph),
+            "links reconstructed without resolved names"
+        );
+
+        json["version"] = 2.into();
+        assert!(
+            CargoMetadata::parse_json(json.to_string()).is_err(),
+            "unsupported format version"
+        );
+        let mut json: serde_json::Value =
+            serde_json::from_str(JsonFixture::metadata1().json()).expect("valid JSON");
+        json["version"] = 2.into();
+        assert!(
+            CargoMetadata::parse_json(json.to_string()).is_err(),
+            "unsupported format version in otherwise current metadata"
+        );
+    }
+
+    #[test]
+    fn metadata1_third_party_classification() {
+        let graph = JsonFixture::metadata1().graph();
+        let package_set = graph.resolve_all();
+        fn names<'a>(set: &PackageSet<'a>) -> Vec<&'a str> {
+            let mut names: Vec<_> = set
+                .packages(DependencyDirection::Forward)
+                .map(|package| package.name())
+                .collect();
+            names.sort_unstable();
+            names
+        }
+
+        let workspace = package_set.workspace_packages();
+        assert_eq!(names(&workspace), ["testcrate"]);
+        let third_party = package_set.third_party_packages();
+        assert_eq!(
+            workspace.union(&third_party),
+            package_set,
+            "workspace and third-party packages cover the set"
+        );
+        assert!(workspace.is_disjoint(&third_party));
+
+        let direct = package_set.direct_third_party_packages();
+        assert_eq!(names(&direct), ["datatest"]);
+        let transitive = package_set.transitive_third_party_packages();
+        assert!(transitive
+            .contains(&package_id(json::METADATA1_DTOA))
+            .expect("valid package ID"));
+        assert!(direct.is_disjoint(&transitive));
+        assert_eq!(direct.union(&transitive), third_party);
+
+        // Only workspace packages in the set count: without testcrate, nothing is direct.
+        let without_workspace = package_set.difference(&workspace);
+        assert!(without_workspace.direct_third_party_packages().is_empty());
+        assert_eq!(
+            without_workspace.transitive_third_party_packages(),
+            third_party
+        );
+    }
+
+    #[test]
+    fn metadata1_dot_styles() {
+        let graph = JsonFixture::metadata1().graph();
+        let package_set = graph
+            .query_reverse(iter::once(&package_id(json::METADATA1_DTOA)))
+            .unwrap()
+            .resolve();
+
+        static EXPECTED_DOT_STYLED: &str = r#"digraph {
+    1 [label="datatest"]
+    9 [label="serde_yaml"]
+    15 [label="dtoa"]
+    subgraph cluster_0 {
+        label="first \"party\""
+        18 [label="testcrate", shape="box", color="blue", "custom attr"="x"]
+    }
+    1 -> 9 [label="serde_yaml"]
+    9 -> 15 [label="dtoa"]
+    18 -> 1 [label="workspace \"dep\"", penwidth="2"]
+}
+"#;
+        assert_eq!(
+            EXPECTED_DOT_STYLED,
+            format!("{}", package_set.display_dot(StyledNameVisitor)),
+            "styled dot output matches"
+        );
+
+        let mut style = PackageDotStyle::new();
+        let dot = format!("{}", package_set.display_dot(&style));
+        assert!(dot.contains("subgraph cluster_0 {\n        label=\"workspace\""));
+        assert!(dot.contains("[label=\"testcrate 0.1.0\", shape=\"box\"]"));
+        style.set_workspace_cluster(None::<String>);
+        let dot = format!("{}", package_set.display_dot(&style));
+        assert!(!dot.contains("subgraph"), "workspace isn't clustered");
+
+        let feature_set = graph
+            .feature_graph()
+            .query_workspace(StandardFeatures::All)
+            .resolve();
+        let dot = format!("{}", feature_set.display_dot(FeatureDotStyle::new()));
+        assert_eq!(
+            dot.matches("subgraph cluster_").count(),
+            feature_set.to_package_set().len(),
+            "one cluster per package"
+        );
+        assert_eq!(
+            dot.matches("[label=\"[base]\"]").count(),
+            feature_set.to_package_set().len(),
+            "one base feature per package"
+        );
+    }
+
+    #[test]
+    fn metadata1_diagrams() {
+        let graph = JsonFixture::metadata1().graph();
+        let package_set = graph
+            .query_forward(iter::once(&package_id(json::METADATA1_TESTCRATE)))
+            .unwrap()
+            .resolve();
+
+        // datatest has 9 third-party dependencies, which is more than the default fan-out limit.
+        static EXPECTED_MERMAID: &str = r#"flowchart TD
+    p0["testcrate 0.1.0"]
+    p1("datatest 0.4.2")
+    p1_more{{"+9 more dependencies"}}
+    p1 -.-> p1_more
+    p0 --> p1
+"#;
+        assert_eq!(
+            EXPECTED_MERMAID,
+            package_set
+                .display_diagram(&DiagramOptions::new(DiagramFormat::Mermaid))
+                .to_string(),
+            "mermaid output matches"
+        );
+
+        static EXPECTED_D2: &str = r#"direction: down
+p0: "testcrate 0.1.0"
+p1: "datatest 0.4.2" {style.border-radius: 8}
+p1_more: "+9 more dependencies" {shape: hexagon}
+p1 -> p1_more: {style.stroke-dash: 3}
+p0 -> p1
+"#;
+        assert_eq!(
+            EXPECTED_D2,
+            package_set
+                .display_diagram(&DiagramOptions::new(DiagramFormat::D2))
+                .to_string(),
+            "d2 output matches"
+        );
+
+        let mut options = DiagramOptions::new(DiagramFormat::D2);
+        options.set_fan_out_limit(None).set_max_packages(Some(3));
+        let diagram = package_set.display_diagram(&options).to_string();
+        assert!(!diagram.contains("_more"), "nothing is collapsed");
+        assert_eq!(
+            diagram.lines().filter(|line| line.contains(": \"")).count(),
+            3,
+            "only 3 packages are drawn"
+        );
+        assert!(diagram.ends_with(&format!(
+            "# {} more packages not shown\n",
+            package_set.len() - 3
+        )));
+
+        options.set_max_packages(None);
+        let diagram = package_set.display_diagram(&options).to_string();
+        assert_eq!(
+            diagram.lines().filter(|line| line.contains(": \"")).count(),
+            package_set.len(),
+            "every package is drawn"
+        );
+        assert_eq!(
+            diagram.lines().filter(|line| line.contains(" -> ")).count(),
+            package_set.links(DependencyDirection::Forward).count(),
+            "every link is drawn"
+        );
+    }
+
+    #[test]
+    fn metadata1_export() {
+        let graph = JsonFixture::metadata1().graph();
+        let testcrate_id = package_id(json::METADATA1_TESTCRATE);
+        let datatest_id = package_id(json::METADATA1_DATATEST);
+        let package_set = graph
+            .query_forward(iter::once(&testcrate_id))
+            .unwrap()
+            .resolve();
+
+        let export = package_set.export();
+        assert_eq!(export.format_version, EXPORT_FORMAT_VERSION);
+        assert_eq!(export.packages.len(), package_set.len());
+        let names: Vec<_> = export.packages.iter().map(|p| p.name.as_str()).collect();
+        let mut sorted_names = names.clone();
+        sorted_names.sort_unstable();
+        assert_eq!(names, sorted_names, "packages are in lexical order");
+        assert_eq!(
+            export.links.len(),
+            package_set.links(DependencyDirection::Forward).count(),
+        );
+
+        let datatest = export
+            .packages
+            .iter()
+            .find(|p| p.id == datatest_id.repr())
+            .expect("datatest is exported");
+        assert_eq!(datatest.version, "0.4.2");
+        assert_eq!(
+            datatest.source,
+            SourceExport::External {
+                source: "registry+https://github.com/rust-lang/crates.io-index".to_owned()
+            }
+        );
+        assert_eq!(
+            datatest.features, None,
+            "package sets don't export features"
+        );
+        let testcrate_link = export
+            .links
+            .iter()
+            .find(|link| link.from == testcrate_id.repr())
+            .expect("testcrate has a link");
+        assert_eq!(testcrate_link.to, datatest_id.repr());
+        assert_eq!(testcrate_link.kinds, ["normal", "build", "dev"]);
+
+        let roundtrip: SetExport =
+            serde_json::from_str(&export.to_json()).expect("export deserializes");
+        assert_eq!(export, roundtrip, "export roundtrips through JSON");
+
+        let feature_export = graph
+            .feature_graph()
+            .query_workspace(StandardFeatures::All)
+            .resolve()
+            .export();
+        assert!(
+            feature_export.packages.iter().all(|p| p.features.is_some()),
+            "feature sets export features"
+        );
+
+        let cargo_export = graph
+            .feature_graph()
+            .query_workspace(StandardFeatures::Default)
+            .resolve()
+            .into_cargo_set(&CargoOptions::new())
+            .expect("cargo resolution succeeds")
+            .export();
+        let testcrate = cargo_export
+            .packages
+            .iter()
+            .find(|p| p.id == testcrate_id.repr())
+            .expect("testcrate is exported");
+        let build_platforms = testcrate
+            .build_platforms
+            .as_ref()
+            .expect("cargo sets export build platforms");
+        assert!(
+            build_platforms.target.is_some(),
+            "testcrate is built on target"
+        );
+    }
+
+    #[test]
+    fn metadata1_petgraph() {
+        let graph = JsonFixture::metadata1().graph();
+        let converted = graph.to_petgraph();
+        assert_eq!(converted.graph().node_count(), graph.package_count());
+        assert_eq!(converted.graph().edge_count(), graph.link_count());
+        assert!(
+            !petgraph::algo::is_cyclic_directed(converted.graph()),
+            "metadata1 has no cycles"
+        );
+
+        let testcrate_ix = converted
+            .node_ix(&package_id(json::METADATA1_TESTCRATE))
+            .expect("testcrate is in the graph");
+        let region_ix = converted
+            .node_ix(&package_id(json::METADATA1_REGION))
+            .expect("region is in the graph");
+        assert!(
+            petgraph::algo::has_path_connecting(converted.graph(), testcrate_ix, region_ix, None),
+            "testcrate transitively depends on region"
+        );
+        assert_eq!(
+            converted
+                .package(testcrate_ix)
+                .map(|package| package.name()),
+            Some("testcrate")
+        );
+        for edge in converted.graph().edge_references() {
+            let link = edge.weight();
+            assert_eq!(converted.node_ix(link.from().id()), Some(edge.source()));
+            assert_eq!(converted.node_ix(link.to().id()), Some(edge.target()));
+        }
+
+        let package_set = graph
+            .query_reverse(iter::once(&package_id(json::METADATA1_REGION)))
+            .unwrap()
+            .resolve();
+        let converted = package_set.to_petgraph();
+        assert_eq!(converted.graph().node_count(), package_set.len());
+        assert_eq!(
+            converted.graph().edge_count(),
+            package_set.links(DependencyDirection::Forward).count()
+        );
+        assert_eq!(
+            converted.node_ix(&package_id(json::METADATA1_DTOA)),
+            None,
+            "dtoa doesn't depend on region"
+        );
+    }
+
+    #[test]
+    fn metadata1_never_activated_optional_deps() {
+        let graph = JsonFixture::metadata1().graph();
+        let never_activated = graph.feature_graph().never_activated_optional_deps();
+        assert!(
+            never_activated
+                .iter()
+                .all(|feature_id| feature_id.is_optional_dependency()),
+            "only optional dependencies are returned"
+        );
+
+        let testcrate_id = package_id(json::METADATA1_TESTCRATE);
+        assert!(
+            never_activated.contains(&FeatureId::optional_dependency(&testcrate_id, "datatest")),
+            "testcrate has no features, so its optional build dependency is never activated"
+        );
+        let datatest_id = package_id(json::METADATA1_DATATEST);
+        assert!(
+            !never_activated.contains(&FeatureId::optional_dependency(&datatest_id, "region")),
+            "region is activated by datatest's unsafe_test_runner feature"
+        );
+
+        let linked_hash_map = graph.resolve_package_name("linked-hash-map");
+        let linked_hash_map_id = linked_hash_map
+            .package_ids(DependencyDirection::Forward)
+            .next()
+            .expect("linked-hash-map is in metadata1");
+        assert!(
+            never_activated.contains(&FeatureId::optional_dependency(
+                linked_hash_map_id,
+                "clippy"
+            )),
+            "no feature activates clippy"
+        );
+        assert!(
+            !never_activated.contains(&FeatureId::optional_dependency(linked_hash_map_id, "serde")),
+            "serde is activated by the serde_impl feature"
+        );
+    }
+
+    #[test]
+    fn metadata1_crate_sizes() {
+        let graph = JsonFixture::metadata1().graph();
+        let package_set = graph.resolve_all();
+        assert!(
+            CrateSizes::from_registry_cache(&package_set).is_empty(),
+            "fixture registry cache doesn't exist"
+        );
+
+        let regex_id =
+            PackageId::new("regex 1.3.1 (registry+https://github.com/rust-lang/crates.io-index)");
+        let memchr_id =
+            PackageId::new("memchr 2.2.1 (registry+https://github.com/rust-lang/crates.io-index)");
+        let mut sizes = CrateSizes::new();
+        sizes.insert(
+            regex_id.clone(),
+            CrateSize::new(Some(240_000), Some(1_400_000)),
+        );
+        sizes.insert(memchr_id, CrateSize::new(Some(23_000), None));
+
+        let regex = graph.metadata(&regex_id).expect("regex is known");
+        assert_eq!(
+            regex.crate_size(&sizes).and_then(|size| size.unpacked()),
+            Some(1_400_000)
+        );
+
+        let total = package_set.total_size(&sizes);
+        assert_eq!(total.download(), 263_000, "download sizes are summed");
+        assert_eq!(
+            total.unpacked(),
+            1_400_000,
+            "unknown unpacked sizes are skipped"
+        );
+        assert_eq!(
+            total.unknown(),
+            graph.package_count() - 2,
+            "packages without sizes are unknown"
+        );
+    }
+
+    #[test]
+    fn metadata1_why() {
+        let graph = JsonFixture::metadata1().graph();
+        let testcrate_id = package_id(json::METADATA1_TESTCRATE);
+        let dtoa_id = package_id(json::METADATA1_DTOA);
+
+        let chain = graph
+            .why(&testcrate_id, &dtoa_id)
+            .expect("valid package IDs")
+            .expect("testcrate depends on dtoa");
+        assert_eq!(
+            chain.from().id(),
+            &testcrate_id,
+            "chain starts at testcrate"
+        );
+        assert_eq!(chain.to().id(), &dtoa_id, "chain ends at dtoa");
+        assert_eq!(
+            chain.to_string(),
+            "testcrate v0.1.0 -> datatest v0.4.2 (normal, build, dev, ^0.4.2) \
+             -> serde_yaml v0.8.9 (normal, ^0.8.7) -> dtoa v0.4.4 (normal, ^0.4)",
+            "chain is displayed"
+        );
+
+        assert!(
+            graph
+                .why(&dtoa_id, &testcrate_id)
+                .expect("valid package IDs")
+                .is_none(),
+            "dtoa doesn't depend on testcrate"
+        );
+        let empty = graph
+            .why(&testcrate_id, &testcrate_id)
+            .expect("valid package IDs")
+            .expect("a package depends on itself");
+        assert!(empty.is_empty(), "chain from a package to itself is empty");
+        assert_eq!(empty.to_string(), "testcrate v0.1.0");
+    }
+
+    #[test]
+    fn metadata1_simple_paths() {
+        let graph = JsonFixture::metadata1().graph();
+        let testcrate_id = package_id(json::METADATA1_TESTCRATE);
+        let dtoa_id = package_id(json::METADATA1_DTOA);
+
+        let paths: Vec<_> = graph
+            .simple_paths(&testcrate_id, &dtoa_id, None)
+            .expect("valid package IDs")
+            .collect();
+        let shortest = graph
+            .why(&testcrate_id, &dtoa_id)
+            .expect("valid package IDs")
+            .expect("testcrate depends on dtoa");
+        assert!(
+            paths
+                .iter()
+                .any(|path| path.to_string() == shortest.to_string()),
+            "shortest chain is one of the simple paths"
+        );
+
+        assert_eq!(
+            graph
+                .simple_paths(&dtoa_id, &testcrate_id, None)
+                .expect("valid package IDs")
+                .count(),
+            0,
+            "dtoa doesn't depend on testcrate"
+        );
+        let to_self: Vec<_> = graph
+            .simple_paths(&testcrate_id, &testcrate_id, None)
+            .expect("valid package IDs")
+            .collect();
+        assert_eq!(to_self.len(), 1, "one path from a package to itself");
+        assert!(
+            to_self[0].is_empty(),
+            "path from a package to itself is empty"
+        );
+    }
+
+    #[test]
+    fn metadata1_owner_of_path() {
+        let graph = JsonFixture::metadata1().graph();
+        let workspace = graph.workspace();
+        let testcrate_id = package_id(json::METADATA1_TESTCRATE);
+
+        for path in ["src/lib.rs", "Cargo.toml", "vendor/quote/src/lib.rs", ""] {
+            let owner = workspace.owner_of_path(path);
+            assert_eq!(
+                owner.member().map(|package| package.id()),
+                Some(&testcrate_id),
+                "{} is owned by the root package",
+                path
+            );
+        }
+        assert_eq!(
+            workspace
+                .owner_of_path("/fakepath/testcrate/src/main.rs")
+                .member()
+                .map(|package| package.id()),
+            Some(&testcrate_id),
+            "absolute paths within the workspace are accepted"
+        );
+        assert!(
+            matches!(
+                workspace.owner_of_path("target/debug/testcrate"),
+                PathOwner::TargetDirectory
+            ),
+            "the target directory isn't owned by the root package"
+        );
+        assert!(
+            matches!(
+                workspace.owner_of_path("/fakepath/quote/src/lib.rs"),
+                PathOwner::Unowned
+            ),
+            "absolute paths outside the workspace are unowned"
+        );
+
+        // Move quote into an excluded directory within the workspace.
+        let json = JsonFixture::metadata1()
+            .json()
+            .replace("/fakepath/quote", "/fakepath/testcrate/vendor/quote");
+        let graph = guppy::CargoMetadata::parse_json(json)
+            .expect("modified fixture parses")
+            .build_graph()
+            .expect("modified fixture builds");
+        let workspace = graph.workspace();
+        match workspace.owner_of_path("vendor/quote/src/lib.rs") {
+            PathOwner::NonMember(package) => {
+                assert_eq!(package.name(), "quote", "quote owns its own files")
+            }
+            other => panic!("unexpected owner for excluded path: {:?}", other),
+        }
+        assert_eq!(
+            workspace
+                .owner_of_path("vendor/README.md")
+                .member()
+                .map(|package| package.name()),
+            Some("testcrate"),
+            "files next to the excluded package are owned by the root package"
+        );
+    }
+
+    #[test]
+    fn metadata2() {
+        let metadata2 = JsonFixture::metadata2();
+        metadata2.verify();
+
+        let feature_graph = metadata2.graph().feature_graph();
+        assert_eq!(feature_graph.feature_count(), 484, "feature count");
+        assert_eq!(feature_graph.link_count(), 589, "link count");
+        let root_ids: Vec<_> = feature_graph
+            .query_workspace(StandardFeatures::None)
+            .resolve()
+            .root_ids(DependencyDirection::Forward)
+            .collect();
+        let testcrate_id = package_id(json::METADATA2_TESTCRATE);
+        let expected = vec![FeatureId::base(&testcrate_id)];
+        assert_eq!(root_ids, expected, "feature graph root IDs match");
+    }
+
+    proptest_suite!(metadata2);
+
+    #[test]
+    fn metadata2_tool_metadata() {
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        #[serde(deny_unknown_fields, rename_all = "kebab-case")]
+        struct Playground {
+            #[serde(default)]
+            features: Vec<String>,
+            #[serde(default)]
+            all_features: bool,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct Docs {
+            rs: DocsRs,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct DocsRs {
+            features: Vec<String>,
+        }
+
+        let graph = JsonFixture::metadata2().graph();
+        let serde = graph
+            .metadata(&package_id(
+                "serde 1.0.100 (registry+https://github.com/rust-lang/crates.io-index)",
+            ))
+            .expect("serde is in the fixture");
+        assert_eq!(
+            serde
+                .tool_metadata::<Playground>("playground")
+                .expect("playground table is valid"),
+            Some(Playground {
+                features: vec!["derive".to_owned(), "rc".to_owned()],
+                all_features: false,
+            }),
+            "serde playground table"
+        );
+        assert!(
+            serde
+                .tool_metadata::<Docs>("docs")
+                .expect("missing table isn't an error")
+                .is_none(),
+            "serde has no docs table"
+        );
+
+        let package_set = graph.resolve_all();
+        let mut playground: Vec<_> = package_set
+            .tool_metadata::<Playground>("playground", DependencyDirection::Forward)
+            .map(|(package, result)| (package.name(), result.is_ok()))
+            .collect();
+        playground.sort_unstable();
+        assert_eq!(
+            playground,
+            vec![("serde", true), ("syn", true)],
+            "playground tables"
+        );
+
+        let docs: BTreeMap<_, _> = package_set
+            .tool_metadata::<Docs>("docs", DependencyDirection::Forward)
+            .map(|(package, result)| (package.name(), result))
+            .collect();
+        assert_eq!(docs.len(), 3, "packages with docs tables");
+        assert_eq!(
+            docs["winapi"]
+                .as_ref()
+                .expect("winapi docs table is valid")
+                .rs
+                .features,
+            vec!["everything", "impl-debug", "impl-default"],
+            "winapi docs features"
+        );
+        for name in ["proc-macro2", "syn"] {
+            let err = docs[name].as_ref().expect_err("features is missing");
+            assert!(
+                matches!(err, Error::MetadataTableParseError(_, tool, _) if tool == "docs"),
+                "{}: unexpected error {:?}",
+                name,
+                err
+            );
+            assert!(
+                err.to_string()
+                    .starts_with("failed to deserialize `package.metadata.docs` for package"),
+                "{}: error message",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn metadata_dups() {
+        let metadata_dups = JsonFixture::metadata_dups();
+        metadata_dups.verify();
+
+        let graph = metadata_dups.graph();
+        let duplicates = graph.duplicates();
+        let names: Vec<_> = duplicates.iter().map(|dup| dup.name()).collect();
+        assert_eq!(names, vec!["bytes", "lazy_static"], "duplicate names match");
+
+        let lazy_static = duplicates
+            .get("lazy_static")
+            .expect("lazy_static is duplicated");
+        let versions: Vec<_> = lazy_static
+            .versions()
+            .map(|version| version.to_string())
+            .collect();
+        assert_eq!(versions, vec!["0.2.11", "1.4.0"], "versions are sorted");
+        let ids: Vec<_> = lazy_static.packages().map(|package| package.id()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                &package_id(json::METADATA_DUPS_LAZY_STATIC_02),
+                &package_id(json::METADATA_DUPS_LAZY_STATIC_1)
+            ],
+            "package IDs match"
+        );
+
+        let testcrate = package_id(json::METADATA_DUPS_TESTCRATE);
+        for (package, dependents) in lazy_static.dependents() {
+            let dependent_ids: Vec<_> = dependents
+                .package_ids(DependencyDirection::Forward)
+                .collect();
+            assert_eq!(
+                dependent_ids,
+                vec![&testcrate],
+                "{} is pulled in by testcrate-dups",
+                package.id()
+            );
+        }
+
+        assert!(duplicates.get("testcrate-dups").is_none());
+
+        let pairs: Vec<_> = lazy_static.pairs().collect();
+        assert_eq!(pairs.len(), 1, "one pair of lazy_static versions");
+        let pair = &pairs[0];
+        assert_eq!(
+            pair.older().id(),
+            &package_id(json::METADATA_DUPS_LAZY_STATIC_02)
+        );
+        assert_eq!(
+            pair.newer().id(),
+            &package_id(json::METADATA_DUPS_LAZY_STATIC_1)
+        );
+        assert_eq!(pair.compatibility(), SemverCompatibility::Incompatible);
+        assert!(!pair.is_unifiable_by_update());
+        let blocking: Vec<_> = pair
+            .blocking_links()
+            .map(|link| (link.from().id(), link.version_req().to_string()))
+            .collect();
+        assert_eq!(
+            blocking,
+            vec![(&testcrate, "^0.2".to_owned())],
+            "testcrate-dups's requirement on lazy_static 0.2 blocks unification"
+        );
+    }
+
+    #[test]
+    fn metadata_merged_workspaces() {
+        let parse = |fixture: &JsonFixture| {
+            CargoMetadata::parse_json(fixture.json()).expect("valid metadata")
+        };
+        let dups = JsonFixture::metadata_dups();
+        let targets = JsonFixture::metadata_targets1();
+        let merged = PackageGraph::from_workspaces(vec![parse(dups), parse(targets)])
+            .expect("workspaces merged");
+        let graph = merged.graph();
+        graph.verify().expect("merged graph is valid");
+
+        assert_eq!(
+            graph.workspace().root(),
+            "/Users/fakeuser/local/testcrates",
+            "merged workspace root is the common ancestor"
+        );
+        let roots: Vec<_> = merged.workspace_roots().collect();
+        assert_eq!(
+            roots,
+            vec![
+                dups.graph().workspace().root(),
+                targets.graph().workspace().root()
+            ],
+            "workspace roots are in input order"
+        );
+
+        let dups_testcrate = package_id(json::METADATA_DUPS_TESTCRATE);
+        let targets_testcrate = package_id(json::METADATA_TARGETS1_TESTCRATE);
+        let members: Vec<_> = graph
+            .resolve_workspace()
+            .package_ids(DependencyDirection::Forward)
+            .collect();
+        assert_eq!(members.len(), 2, "members of both workspaces are merged");
+        assert_eq!(
+            merged.workspace_root_for(&targets_testcrate),
+            Some(targets.graph().workspace().root()),
+        );
+        let dups_members = merged
+            .resolve_workspace_at(dups.graph().workspace().root())
+            .expect("known workspace root");
+        assert_eq!(
+            dups_members
+                .package_ids(DependencyDirection::Forward)
+                .collect::<Vec<_>>(),
+            vec![&dups_testcrate],
+        );
+        assert!(
+            merged.resolve_workspace_at("/nonexistent").is_none(),
+            "unknown workspace root"
+        );
+
+        // lazy_static 1.4.0 is used by both workspaces, and is unified.
+        let lazy_static_1 = package_id(json::METADATA_DUPS_LAZY_STATIC_1);
+        let dependents: Vec<_> = graph
+            .query_reverse(iter::once(&lazy_static_1))
+            .expect("valid package ID")
+            .resolve()
+            .intersection(&graph.resolve_workspace())
+            .package_ids(DependencyDirection::Forward)
+            .cloned()
+            .collect();
+        assert_eq!(
+            dependents.len(),
+            2,
+            "both workspaces depend on lazy_static 1.4.0"
+        );
+        assert_eq!(
+            graph.package_count(),
+            dups.graph().package_count() + targets.graph().package_count() - 2,
+            "shared packages are unified"
+        );
+
+        assert!(
+            PackageGraph::from_workspaces(vec![parse(dups), parse(dups)]).is_err(),
+            "the same workspace can't be merged twice"
+        );
+        assert!(
+            PackageGraph::from_workspaces(vec![]).is_err(),
+            "at least one workspace is required"
+        );
+    }
+
+    #[test]
+    fn metadata_dups_policy() {
+        let graph = JsonFixture::metadata_dups().graph();
+        let testcrate = package_id(json::METADATA_DUPS_TESTCRATE);
+
+        let mut policy = Policy::new();
+        policy.set_max_versions(1);
+        let report = graph.check_policy(&policy);
+        let violations: Vec<_> = report
+            .violations()
+            .iter()
+            .map(|violation| (violation.package().name(), violation.kind().clone()))
+            .collect();
+        let duplicate = ViolationKind::Duplicate { count: 2, limit: 1 };
+        assert_eq!(
+            violations,
+            vec![
+                ("bytes", duplicate.clone()),
+                ("bytes", duplicate.clone()),
+                ("lazy_static", duplicate.clone()),
+                ("lazy_static", duplicate),
+            ],
+            "every version of a duplicated package is reported"
+        );
+        for violation in report.violations() {
+            let path = violation.path().expect("path from the workspace exists");
+            assert_eq!(
+                path.from().id(),
+                &testcrate,
+                "path starts at testcrate-dups"
+            );
+            assert_eq!(
+                path.to().id(),
+                violation.package().id(),
+                "path ends at package"
+            );
+        }
+
+        policy.set_max_versions_for("lazy_static", 2).add_ban(
+            "lazy_static",
+            VersionReq::parse("<1").expect("valid version req"),
+        );
+        let report = graph.check_policy(&policy);
+        let violations: Vec<_> = report
+            .violations()
+            .iter()
+            .map(|violation| (violation.package().id(), violation.kind().clone()))
+            .collect();
+        let lazy_static_02 = package_id(json::METADATA_DUPS_LAZY_STATIC_02);
+        assert_eq!(
+            violations[0],
+            (
+                &lazy_static_02,
+                ViolationKind::Banned {
+                    version_req: VersionReq::parse("<1").expect("valid version req")
+                }
+            ),
+            "bans are reported first"
+        );
+        assert_eq!(
+            report.violations_for(&lazy_static_02).count(),
+            1,
+            "lazy_static limit is overridden"
+        );
+        assert_eq!(violations.len(), 3, "ban and bytes duplicates are reported");
+    }
+
+    proptest_suite!(metadata_dups);
+
+    #[test]
+    fn metadata_cycle1() {
+        let metadata_cycle1 = JsonFixture::metadata_cycle1();
+        metadata_cycle1.verify();
+    }
+
+    proptest_suite!(metadata_cycle1);
+
+    #[test]
+    fn metadata_cycle2() {
+        let metadata_cycle2 = JsonFixture::metadata_cycle2();
+        metadata_cycle2.verify();
+    }
+
+    proptest_suite!(metadata_cycle2);
+
+    #[test]
+    fn metadata_cycle1_traverse() {
+        let graph = JsonFixture::metadata_cycle1().graph();
+        let query = graph
+            .query_forward(iter::once(&package_id(json::METADATA_CYCLE1_BASE)))
+            .expect("valid package ID");
+
+        let mut back_links = vec![];
+        let completed = query.traverse_with_fn(TraversalOrder::DepthFirst, |_, event| {
+            if let PackageTraversalEvent::BackLink(link) = event {
+                back_links.push((link.from().id().clone(), link.to().id().clone()));
+            }
+            TraversalControl::Continue
+        });
+        assert!(completed, "traversal ran to completion");
+        assert_eq!(
+            back_links,
+            vec![(
+                package_id(json::METADATA_CYCLE1_HELPER),
+                package_id(json::METADATA_CYCLE1_BASE)
+            )],
+            "depth-first traversal finds the cycle"
+        );
+
+        query.traverse_with_fn(TraversalOrder::BreadthFirst, |_, event| {
+            assert!(
+                !matches!(event, PackageTraversalEvent::BackLink(_)),
+                "breadth-first traversals don't report back links"
+            );
+            TraversalControl::Continue
+        });
+    }
+
+    #[test]
+    fn metadata_cycle1_dev_cycles() {
+        let graph = JsonFixture::metadata_cycle1().graph();
+        let cycles = graph.cycles();
+        let base_id = package_id(json::METADATA_CYCLE1_BASE);
+        let helper_id = package_id(json::METADATA_CYCLE1_HELPER);
+
+        let dev_cycles: Vec<_> = cycles.dev_cycles().collect();
+        assert_eq!(dev_cycles.len(), 1, "one cycle");
+        let cycle = &dev_cycles[0];
+        assert_eq!(
+            cycle.package_ids().collect::<Vec<_>>(),
+            vec![&helper_id, &base_id],
+            "helper depends on base, so it is returned first"
+        );
+        assert!(
+            !cycle.in_workspace(),
+            "helper isn't a workspace member, so the cycle isn't within the workspace"
+        );
+        assert_eq!(cycle.links().count(), 2, "two links form the cycle");
+        let dev_links: Vec<_> = cycle
+            .dev_links()
+            .map(|link| (link.from().id(), link.to().id()))
+            .collect();
+        assert_eq!(
+            dev_links,
+            vec![(&base_id, &helper_id)],
+            "base dev-depends on helper"
+        );
+    }
+
+    #[test]
+    fn metadata_cycle2_sccs() {
+        let graph = JsonFixture::metadata_cycle2().graph();
+        let cycles = graph.cycles();
+
+        let sccs: Vec<_> = cycles.all_sccs().collect();
+        assert_eq!(
+            sccs.iter().map(|scc| scc.len()).sum::<usize>(),
+            graph.package_count(),
+            "every package is in exactly one SCC"
+        );
+        let multi_sccs: Vec<_> = sccs.into_iter().filter(|scc| scc.len() > 1).collect();
+        let all_cycles: Vec<_> = cycles.all_cycles().collect();
+        assert_eq!(
+            multi_sccs, all_cycles,
+            "multi-package SCCs match all_cycles"
+        );
+
+        let dev_cycles: Vec<_> = cycles.dev_cycles().collect();
+        assert_eq!(dev_cycles.len(), all_cycles.len(), "one report per cycle");
+        for (cycle, expected) in dev_cycles.iter().zip(&all_cycles) {
+            assert_eq!(&cycle.package_ids().collect::<Vec<_>>(), expected);
+            assert!(
+                cycle.dev_links().count() > 0,
+                "cycle goes through a dev-only link"
+            );
+            for link in cycle.links() {
+                assert!(
+                    cycles
+                        .is_cyclic(link.from().id(), link.to().id())
+                        .expect("valid package IDs"),
+                    "link is within the cycle"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn metadata_cycle_features() {
+        let metadata_cycle_features = JsonFixture::metadata_cycle_features();
+        metadata_cycle_features.verify();
+        let feature_graph = metadata_cycle_features.graph().feature_graph();
+
+        let base_id = package_id(METADATA_CYCLE_FEATURES_BASE);
+        let default_id = FeatureId::named(&base_id, "default");
+
+        // default, default-enable and default-transitive are default features.
+        for &f in &["default", "default-enable", "default-transitive"] {
+            let this_id = FeatureId::named(&base_id, f);
+            assert!(
+                feature_graph
+                    .is_default_feature(this_id)
+                    .expect("valid feature ID"),
+                "{} is a default feature",
+                f,
+            );
+            assert!(
+                feature_graph
+                    .depends_on(default_id, this_id)
+                    .expect("valid feature IDs"),
+                "{} should depend on {} but does not",
+                default_id,
+                this_id,
+            );
+        }
+
+        // helper-enable and helper-transitive are *not* default features even though they are
+        // enabled by the cyclic dev dependency. But the dependency relation is present.
+        for &f in &["helper-enable", "helper-transitive"] {
+            let this_id = FeatureId::named(&base_id, f);
+            assert!(
+                !feature_graph
+                    .is_default_feature(this_id)
+                    .expect("valid feature ID"),
+                "{} is NOT a default feature",
+                f,
+            );
+            assert!(
+                feature_graph
+                    .depends_on(default_id, this_id)
+                    .expect("valid feature IDs"),
+                "{} should depend on {} but does not",
+                default_id,
+                this_id,
+            );
+        }
+
+        assert_eq!(
+            feature_graph.unused_workspace_features(),
+            vec![],
+            "every feature is enabled by another feature or by testcycles-helper"
+        );
+    }
+
+    proptest_suite!(metadata_cycle_features);
+
+    #[test]
+    fn metadata_targets1() {
+        let metadata_targets1 = JsonFixture::metadata_targets1();
+        metadata_targets1.verify();
+
+        let package_graph = metadata_targets1.graph();
+        let package_set = package_graph.resolve_all();
+        let feature_graph = metadata_targets1.graph().feature_graph();
+        assert_eq!(feature_graph.feature_count(), 38, "feature count");
+
+        // Some code that might be useful for debugging.
+        if false {
+            for (source, target, edge) in feature_graph
+                .resolve_all()
+                .links(DependencyDirection::Forward)
+            {
+                let source_metadata = package_graph.metadata(source.package_id()).unwrap();
+                let target_metadata = package_graph.metadata(target.package_id()).unwrap();
+
+                println!(
+                    "feature link: {}:{} {} -> {}:{} {} {:?}",
+                    source_metadata.name(),
+                    source_metadata.version(),
+                    source.label(),
+                    target_metadata.name(),
+                    target_metadata.version(),
+                    target.label(),
+                    edge
+                );
+            }
+        }
+
+        assert_eq!(feature_graph.link_count(), 58, "feature link count");
+
+        // Check that resolve_packages + a feature filter works.
+        let feature_set = package_set.to_feature_set(named_feature_filter(
+            StandardFeatures::Default,
+            ["foo", "bar"].iter().copied(),
+        ));
+        let dep_a_id = package_id(json::METADATA_TARGETS1_DEP_A);
+        assert!(feature_set
+            .contains((&dep_a_id, FeatureLabel::Named("foo")))
+            .expect("valid feature ID"));
+        assert!(feature_set
+            .contains((&dep_a_id, FeatureLabel::Named("bar")))
+            .expect("valid feature ID"));
+        assert!(!feature_set
+            .contains((&dep_a_id, FeatureLabel::Named("baz")))
+            .expect("valid feature ID"));
+        assert!(!feature_set
+            .contains((&dep_a_id, FeatureLabel::Named("quux")))
+            .expect("valid feature ID"));
+
+        assert_features_for_package(
+            &feature_set,
+            &package_id(json::METADATA_TARGETS1_TESTCRATE),
+            Some(&[FeatureLabel::Base]),
+            "testcrate",
+        );
+        assert_features_for_package(
+            &feature_set,
+            &dep_a_id,
+            Some(&[
+                FeatureLabel::Base,
+                FeatureLabel::Named("bar"),
+                FeatureLabel::Named("foo"),
+            ]),
+            "dep a",
+        );
+        assert_features_for_package(
+            &feature_set,
+            &package_id(json::METADATA_TARGETS1_LAZY_STATIC_1),
+            Some(&[FeatureLabel::Base]),
+            "lazy_static",
+        );
+    }
+
+    proptest_suite!(metadata_targets1);
+
+    #[test]
+    fn metadata_targets1_dependency_instances() {
+        let graph = JsonFixture::metadata_targets1().graph();
+        let testcrate = graph
+            .metadata(&package_id(json::METADATA_TARGETS1_TESTCRATE))
+            .expect("testcrate is known");
+        let link = testcrate
+            .direct_links()
+            .find(|link| link.to().id() == package_id(json::METADATA_TARGETS1_DEP_A))
+            .expect("testcrate depends on dep-a");
+
+        let instances: Vec<_> = link
+            .instances()
+            .map(|instance| {
+                (
+                    instance.kind(),
+                    instance.target(),
+                    instance.optional(),
+                    instance.default_features(),
+                    instance.features().collect::<Vec<_>>(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            instances,
+            vec![
+                (DependencyKind::Normal, None, true, true, vec![]),
+                (
+                    DependencyKind::Normal,
+                    Some("cfg(not(windows))"),
+                    false,
+                    true,
+                    vec!["foo"]
+                ),
+                (
+                    DependencyKind::Normal,
+                    Some("cfg(target_arch = \"x86\")"),
+                    false,
+                    true,
+                    vec!["bar"]
+                ),
+                (
+                    DependencyKind::Build,
+                    Some("cfg(all(unix, target_feature = \"sse\"))"),
+                    true,
+                    false,
+                    vec!["bar"]
+                ),
+                (
+                    DependencyKind::Build,
+                    Some("cfg(any(unix, target_feature = \"sse\"))"),
+                    true,
+                    true,
+                    vec![]
+                ),
+                (
+                    DependencyKind::Build,
+                    Some("cfg(target_feature = \"sse\")"),
+                    false,
+                    false,
+                    vec!["foo"]
+                ),
+                (
+                    DependencyKind::Development,
+                    Some("cfg(all(unix, not(target_feature = \"sse\")))"),
+                    false,
+                    true,
+                    vec![]
+                ),
+                (
+                    DependencyKind::Development,
+                    Some("cfg(any(target_feature = \"sse2\", target_feature = \"atomics\"))"),
+                    false,
+                    false,
+                    vec!["baz"]
+                ),
+                (
+                    DependencyKind::Development,
+                    Some("cfg(any(unix, target_feature = \"sse\"))"),
+                    false,
+                    false,
+                    vec!["quux"]
+                ),
+            ],
+            "dependency instances"
+        );
+        assert_eq!(
+            link.normal().instances().len(),
+            3,
+            "three instances in [dependencies]"
+        );
+
+        let linux = PlatformSpec::Platform(Arc::new(
+            Platform::new("x86_64-unknown-linux-gnu", TargetFeatures::Unknown)
+                .expect("known triple"),
+        ));
+        let enabled: Vec<_> = link
+            .normal()
+            .instances()
+            .map(|instance| instance.enabled_on(&linux))
+            .collect();
+        assert_eq!(
+            enabled,
+            vec![
+                EnabledTernary::Enabled,
+                EnabledTernary::Enabled,
+                EnabledTernary::Disabled
+            ],
+            "normal instances on x86_64 Linux"
+        );
+        assert!(link
+            .normal()
+            .instances()
+            .next()
+            .unwrap()
+            .platform_status()
+            .is_always());
+    }
+
+    #[test]
+    fn metadata_build_targets1() {
+        let metadata_build_targets1 = JsonFixture::metadata_build_targets1();
+        metadata_build_targets1.verify();
+    }
+
+    // No need for proptests because there are no dependencies involved.
+
+    #[test]
+    fn metadata_proc_macro1() {
+        let metadata = JsonFixture::metadata_proc_macro1();
+        metadata.verify();
+        let graph = metadata.graph();
+
+        let package = graph
+            .metadata(&package_id(json::METADATA_PROC_MACRO1_MACRO))
+            .expect("valid package ID");
+        assert!(package.is_proc_macro(), "is proc macro");
+        assert!(matches!(
+            package
+                .build_target(&BuildTargetId::Library)
+                .expect("library package is present")
+                .kind(),
+            BuildTargetKind::ProcMacro
+        ));
+    }
+
+    // No need for proptests because this is a really simple test.
+
+    #[test]
+    fn metadata_alternate_registries_required_features() {
+        let graph = JsonFixture::metadata_alternate_registries().graph();
+        let debug_ignore = graph
+            .workspace()
+            .member_by_name("debug-ignore")
+            .expect("debug-ignore is a member");
+        let serde_test = debug_ignore
+            .build_target(&BuildTargetId::Test("serde"))
+            .expect("serde test exists");
+        assert_eq!(
+            serde_test.required_features(),
+            &["serde".to_owned()][..],
+            "required features"
+        );
+        let library = debug_ignore
+            .build_target(&BuildTargetId::Library)
+            .expect("library exists");
+
+        let feature_graph = graph.feature_graph();
+        let no_features = feature_graph
+            .query_workspace(StandardFeatures::None)
+            .resolve();
+        assert!(
+            !no_features
+                .enables_required_features(debug_ignore.id(), &serde_test)
+                .expect("valid package ID"),
+            "serde test isn't built without features"
+        );
+        assert!(
+            no_features
+                .enables_required_features(debug_ignore.id(), &library)
+                .expect("valid package ID"),
+            "library is always built"
+        );
+        let buildable: Vec<_> = no_features
+            .buildable_targets(debug_ignore.id())
+            .expect("valid package ID")
+            .iter()
+            .map(|build_target| build_target.id())
+            .collect();
+        assert_eq!(buildable, vec![BuildTargetId::Library], "without features");
+
+        let all_features = feature_graph
+            .query_workspace(StandardFeatures::All)
+            .resolve();
+        let buildable: Vec<_> = all_features
+            .buildable_targets(debug_ignore.id())
+            .expect("valid package ID")
+            .iter()
+            .map(|build_target| build_target.id())
+            .collect();
+        assert_eq!(
+            buildable,
+            vec![BuildTargetId::Library, BuildTargetId::Test("serde")],
+            "with all features"
+        );
+
+        assert!(
+            feature_graph
+                .resolve_none()
+                .buildable_targets(debug_ignore.id())
+                .expect("valid package ID")
+                .is_empty(),
+            "nothing is built for packages outside the feature set"
+        );
+
+        let unbuildable = no_features.unbuildable_targets(DependencyDirection::Forward);
+        assert_eq!(unbuildable.len(), 1, "one unbuildable target");
+        assert_eq!(unbuildable[0].package().id(), debug_ignore.id());
+        assert_eq!(
+            unbuildable[0].build_target().id(),
+            BuildTargetId::Test("serde")
+        );
+        assert_eq!(unbuildable[0].missing_features(), &["serde"][..]);
+        assert_eq!(unbuildable[0].build_platform(), None);
+        assert_eq!(
+            unbuildable[0].to_string(),
+            "test `serde` in package debug-ignore 1.0.1 isn't built: \
+             missing required features serde",
+            "display output"
+        );
+        assert!(
+            all_features
+                .unbuildable_targets(DependencyDirection::Forward)
+                .is_empty(),
+            "every target is built with all features"
+        );
+
+        let cargo_set = no_features
+            .into_cargo_set(&CargoOptions::new())
+            .expect("cargo resolution succeeds");
+        let unbuildable = cargo_set.unbuildable_targets(DependencyDirection::Forward);
+        assert_eq!(unbuildable.len(), 1, "one unbuildable target in cargo set");
+        assert_eq!(
+            unbuildable[0].build_platform(),
+            Some(BuildPlatform::Target),
+            "workspace packages are built on the target platform"
+        );
+    }
+
+    #[test]
+    fn metadata_alternate_registries_source_kinds() {
+        let graph = JsonFixture::metadata_alternate_registries().graph();
+        let alternate = SourceKind::AlternateRegistry(json::METADATA_ALTERNATE_REGISTRY_URL);
+
+        let groups = graph.resolve_all().group_by_source_kind();
+        let counts: Vec<_> = groups
+            .iter()
+            .map(|(kind, package_set)| (*kind, package_set.len()))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                (SourceKind::Workspace, 1),
+                (SourceKind::CratesIo, 5),
+                (alternate, 6),
+            ],
+            "packages grouped by source kind"
+        );
+        assert!(alternate.is_registry() && !alternate.is_local());
+
+        let alternate_set = graph
+            .resolve_all()
+            .filter_source_kind(|kind| kind == alternate);
+        assert_eq!(alternate_set, groups[&alternate], "filter matches group");
+        for package in alternate_set.packages(DependencyDirection::Forward) {
+            assert_eq!(
+                package.source().external_source(),
+                Some("registry+https://github.com/fakeorg/crates.io-index"),
+                "{} is from the alternate registry",
+                package.id()
+            );
+        }
+    }
+
+    #[test]
+    fn metadata_alternate_registries_policy() {
+        let graph = JsonFixture::metadata_alternate_registries().graph();
+        let alternate = SourceKind::AlternateRegistry(json::METADATA_ALTERNATE_REGISTRY_URL);
+
+        let mut sources = SourcePolicy::new();
+        let mut policy = Policy::new();
+        policy.set_sources(sources.clone());
+        let report = graph.check_policy(&policy);
+        assert_eq!(
+            report.violations().len(),
+            6,
+            "alternate registry isn't allowed"
+        );
+        for violation in report.violations() {
+            assert_eq!(
+                violation.kind(),
+                &ViolationKind::DisallowedSource { kind: alternate },
+                "{} comes from the alternate registry",
+                violation.package().id()
+            );
+            assert!(
+                violation.path().is_some(),
+                "{} is reachable from the workspace",
+                violation.package().id()
+            );
+        }
+
+        sources.allow_registry(json::METADATA_ALTERNATE_REGISTRY_URL);
+        policy.set_sources(sources);
+        assert!(
+            graph.check_policy(&policy).is_empty(),
+            "alternate registry is allowed"
+        );
+    }
+}
+
+mod large {
+    use super::*;
+    use fixtures::dep_helpers::GraphAssert;
+
+    #[test]
+    fn metadata_libra() {
+        let metadata_libra = JsonFixture::metadata_libra();
+        metadata_libra.verify();
+    }
 
     proptest_suite!(metadata_libra);
 
     #[test]
-    fn metadata_libra_f0091a4() {
-        let metadata = JsonFixture::metadata_libra_f0091a4();
-        metadata.verify();
+    fn metadata_libra_f0091a4() {
+        let metadata = JsonFixture::metadata_libra_f0091a4();
+        metadata.verify();
+    }
+
+    proptest_suite!(metadata_libra_f0091a4);
+
+    #[test]
+    fn metadata_libra_9ffd93b() {
+        let metadata = JsonFixture::metadata_libra_9ffd93b();
+        metadata.verify();
+
+        let graph = metadata.graph();
+        graph.assert_depends_on(
+            &package_id(json::METADATA_LIBRA_ADMISSION_CONTROL_SERVICE),
+            &package_id(json::METADATA_LIBRA_EXECUTOR_UTILS),
+            DependencyDirection::Forward,
+            "admission-control-service should depend on executor-utils",
+        );
+        graph.assert_not_depends_on(
+            &package_id(json::METADATA_LIBRA_EXECUTOR_UTILS),
+            &package_id(json::METADATA_LIBRA_ADMISSION_CONTROL_SERVICE),
+            DependencyDirection::Forward,
+            "executor-utils should not depend on admission-control-service",
+        );
+
+        let proc_macro_packages: Vec<_> = graph
+            .workspace()
+            .iter_by_path()
+            .filter_map(|(_, metadata)| {
+                if metadata.is_proc_macro() {
+                    Some(metadata.name())
+                } else {
+                    None
+                }
+            })
+            .collect();
+        assert_eq!(
+            proc_macro_packages,
+            ["num-variants", "libra-crypto-derive"],
+            "proc macro packages"
+        );
+
+        let build_script_packages: Vec<_> = graph
+            .workspace()
+            .iter_by_path()
+            .filter_map(|(_, metadata)| {
+                if metadata.has_build_script() {
+                    Some(metadata.name())
+                } else {
+                    None
+                }
+            })
+            .collect();
+        assert_eq!(
+            build_script_packages,
+            [
+                "admission-control-proto",
+                "libra-dev",
+                "debug-interface",
+                "libra-metrics",
+                "storage-proto",
+                "libra_fuzzer_fuzz",
+                "libra-types"
+            ],
+            "build script packages"
+        );
+
+        let mut build_dep_but_no_build_script: Vec<_> = graph
+            .resolve_all()
+            .links(DependencyDirection::Forward)
+            .filter_map(|link| {
+                if link.build().is_present() && !link.from().has_build_script() {
+                    Some(link.from().name())
+                } else {
+                    None
+                }
+            })
+            .collect();
+        build_dep_but_no_build_script.sort_unstable();
+        assert_eq!(
+            build_dep_but_no_build_script,
+            ["libra-mempool", "rusoto_signature"],
+            "packages with build deps but no build scripts"
+        );
+    }
+
+    proptest_suite!(metadata_libra_9ffd93b);
+
+    #[test]
+    fn metadata_libra_diff() {
+        let old = JsonFixture::metadata_libra_f0091a4().graph();
+        let new = JsonFixture::metadata_libra_9ffd93b().graph();
+
+        assert!(
+            old.diff(old).is_unchanged(),
+            "a graph has no diff with itself"
+        );
+
+        let diff = old.diff(new);
+        assert!(!diff.is_unchanged(), "the two snapshots are different");
+        assert_eq!(diff.version_changes().len(), 59, "version change count");
+        let aho_corasick = diff
+            .version_changes()
+            .find(|change| change.old_package().name() == "aho-corasick")
+            .expect("aho-corasick's version changed");
+        assert_eq!(aho_corasick.old_package().version().to_string(), "0.7.8");
+        assert_eq!(aho_corasick.new_package().version().to_string(), "0.7.10");
+
+        for package in diff.added() {
+            assert!(
+                old.metadata(package.id()).is_err(),
+                "added package {} isn't in the old graph",
+                package.id()
+            );
+        }
+        for package in diff.removed() {
+            assert!(
+                new.metadata(package.id()).is_err(),
+                "removed package {} isn't in the new graph",
+                package.id()
+            );
+        }
+        for link in diff.added_links() {
+            assert!(
+                std::ptr::eq(link.from().graph(), new),
+                "added links are from the new graph"
+            );
+        }
+        for link in diff.removed_links() {
+            assert!(
+                std::ptr::eq(link.from().graph(), old),
+                "removed links are from the old graph"
+            );
+        }
+        for change in diff.changed_links() {
+            let (old_link, new_link) = (change.old_link(), change.new_link());
+            assert_eq!(old_link.to().name(), new_link.to().name());
+            assert_eq!(old_link.dep_name(), new_link.dep_name());
+        }
+
+        // The reverse diff swaps additions and removals.
+        let reverse = new.diff(old);
+        let added: Vec<_> = diff.added().map(|package| package.id()).collect();
+        let reverse_removed: Vec<_> = reverse.removed().map(|package| package.id()).collect();
+        assert_eq!(added, reverse_removed, "reverse diff matches");
+        assert_eq!(
+            diff.added_links().len(),
+            reverse.removed_links().len(),
+            "reverse diff link counts match"
+        );
+    }
+
+    #[test]
+    fn metadata_libra_to_package_graph() {
+        let graph = JsonFixture::metadata_libra_9ffd93b().graph();
+        let package_set = graph
+            .query_forward(iter::once(&package_id(
+                json::METADATA_LIBRA_ADMISSION_CONTROL_SERVICE,
+            )))
+            .expect("valid package ID")
+            .resolve();
+
+        let subgraph = &package_set.to_package_graph();
+        subgraph.verify().expect("subgraph verifies");
+        assert_eq!(subgraph.package_count(), package_set.len(), "package count");
+        assert_eq!(
+            subgraph.link_count(),
+            package_set.links(DependencyDirection::Forward).count(),
+            "link count"
+        );
+        assert_eq!(
+            subgraph.workspace().root(),
+            graph.workspace().root(),
+            "workspace root is preserved"
+        );
+        for (path, member) in subgraph.workspace().iter_by_path() {
+            assert!(
+                package_set.contains(member.id()).expect("valid package ID"),
+                "workspace member {} at {} is in the set",
+                member.id(),
+                path
+            );
+        }
+
+        // The subgraph is self-contained: queries on it match the source graph.
+        let mut expected: Vec<_> = package_set
+            .package_ids(DependencyDirection::Forward)
+            .collect();
+        expected.sort_unstable();
+        let mut actual: Vec<_> = subgraph.package_ids().collect();
+        actual.sort_unstable();
+        assert_eq!(expected, actual, "package IDs match");
+        subgraph.assert_depends_on(
+            &package_id(json::METADATA_LIBRA_ADMISSION_CONTROL_SERVICE),
+            &package_id(json::METADATA_LIBRA_EXECUTOR_UTILS),
+            DependencyDirection::Forward,
+            "admission-control-service still depends on executor-utils",
+        );
+        assert!(
+            subgraph.feature_graph().feature_count() > subgraph.package_count(),
+            "feature graph is constructed for the subgraph"
+        );
+    }
+
+    #[test]
+    fn metadata_libra_traverse() {
+        let graph = JsonFixture::metadata_libra_9ffd93b().graph();
+        let query = graph
+            .query_forward(iter::once(&package_id(
+                json::METADATA_LIBRA_ADMISSION_CONTROL_SERVICE,
+            )))
+            .expect("valid package ID");
+        let expected: BTreeSet<_> = query
+            .clone()
+            .resolve()
+            .package_ids(DependencyDirection::Forward)
+            .collect();
+
+        for order in [TraversalOrder::DepthFirst, TraversalOrder::BreadthFirst] {
+            let mut discovered = vec![];
+            let mut finished = BTreeSet::new();
+            let mut tree_links = 0;
+            let completed = query.traverse_with_fn(order, |_, event| {
+                match event {
+                    PackageTraversalEvent::Discover(package) => discovered.push(package.id()),
+                    PackageTraversalEvent::TreeLink(_) => tree_links += 1,
+                    PackageTraversalEvent::Finish(package) => {
+                        assert!(
+                            finished.insert(package.id()),
+                            "{:?}: package {} finished once",
+                            order,
+                            package.id()
+                        );
+                    }
+                    PackageTraversalEvent::BackLink(_)
+                    | PackageTraversalEvent::CrossForwardLink(_) => {}
+                }
+                TraversalControl::Continue
+            });
+            assert!(completed, "{:?}: traversal ran to completion", order);
+            assert_eq!(
+                discovered.len(),
+                expected.len(),
+                "{:?}: each package is discovered once",
+                order
+            );
+            assert_eq!(
+                discovered.iter().copied().collect::<BTreeSet<_>>(),
+                expected,
+                "{:?}: discovered packages match resolve",
+                order
+            );
+            assert_eq!(finished, expected, "{:?}: every package is finished", order);
+            assert_eq!(
+                tree_links,
+                expected.len() - 1,
+                "{:?}: tree links form a spanning tree",
+                order
+            );
+
+            let mut discover_count = 0;
+            let completed = query.traverse_with_fn(order, |_, event| match event {
+                PackageTraversalEvent::Discover(_) => {
+                    discover_count += 1;
+                    if discover_count == 5 {
+                        TraversalControl::Stop
+                    } else {
+                        TraversalControl::Continue
+                    }
+                }
+                _ => TraversalControl::Continue,
+            });
+            assert!(!completed, "{:?}: traversal was stopped", order);
+            assert_eq!(discover_count, 5, "{:?}: no events after stopping", order);
+
+            // Pruning the root only produces events for the root.
+            let mut events = 0;
+            query.traverse_with_fn(order, |_, _| {
+                events += 1;
+                TraversalControl::Prune
+            });
+            assert_eq!(events, 2, "{:?}: discover and finish the root", order);
+        }
+    }
+
+    #[test]
+    fn metadata_libra_why() {
+        let graph = JsonFixture::metadata_libra_9ffd93b().graph();
+        let from_id = package_id(json::METADATA_LIBRA_ADMISSION_CONTROL_SERVICE);
+        let to_id = package_id(json::METADATA_LIBRA_EXECUTOR_UTILS);
+
+        let shortest = graph
+            .why(&from_id, &to_id)
+            .expect("valid package IDs")
+            .expect("admission-control-service depends on executor-utils");
+        assert_chain_valid(&shortest, &from_id, &to_id);
+        // The chain is a shortest one: no package within fewer links is the target.
+        let mut query = graph
+            .query_forward(iter::once(&from_id))
+            .expect("valid package ID");
+        query.set_max_depth(shortest.len() - 1);
+        assert!(
+            !query.resolve().contains(&to_id).expect("valid package ID"),
+            "no shorter chain exists"
+        );
+
+        let chains = graph
+            .why_k_shortest(&from_id, &to_id, 10)
+            .expect("valid package IDs");
+        assert_eq!(chains.len(), 2, "only 2 chains exist");
+        assert_eq!(
+            chains[0].len(),
+            shortest.len(),
+            "first chain is a shortest one"
+        );
+
+        let lazy_static_id = package_id(json::METADATA_LIBRA_LAZY_STATIC);
+        let chains = graph
+            .why_k_shortest(&from_id, &lazy_static_id, 10)
+            .expect("valid package IDs");
+        assert_eq!(chains.len(), 10, "at least 10 chains to lazy_static exist");
+        let mut seen = BTreeSet::new();
+        for window in chains.windows(2) {
+            assert!(
+                window[0].len() <= window[1].len(),
+                "chains are in order of increasing length"
+            );
+        }
+        for chain in &chains {
+            assert_chain_valid(chain, &from_id, &lazy_static_id);
+            let packages: Vec<_> = chain.packages().map(|package| package.id()).collect();
+            assert!(seen.insert(packages), "chains are distinct");
+        }
+        assert!(
+            graph
+                .why_k_shortest(&from_id, &to_id, 0)
+                .expect("valid package IDs")
+                .is_empty(),
+            "k = 0 returns no chains"
+        );
+    }
+
+    #[test]
+    fn metadata_libra_query_directory() {
+        let graph = JsonFixture::metadata_libra_9ffd93b().graph();
+        let workspace = graph.workspace();
+        let expected: BTreeSet<_> = workspace
+            .iter_by_path()
+            .filter(|(path, _)| path.starts_with("language"))
+            .map(|(_, package)| package.id())
+            .collect();
+        assert!(
+            !expected.is_empty(),
+            "workspace has packages under language/"
+        );
+
+        for dir in [
+            "language".to_owned(),
+            "./language/".to_owned(),
+            workspace.root().join("language").into_string(),
+        ] {
+            let query = graph.query_directory(&dir).expect("directory has packages");
+            for package in workspace.iter() {
+                assert_eq!(
+                    query.starts_from(package.id()).expect("valid package ID"),
+                    expected.contains(package.id()),
+                    "for directory {}, {} is selected if it's under language/",
+                    dir,
+                    package.id(),
+                );
+            }
+        }
+
+        let query = graph.query_directory(".").expect("root has packages");
+        assert_eq!(
+            query.resolve().len(),
+            graph.query_workspace().resolve().len(),
+            "querying the root selects the whole workspace"
+        );
+        assert!(
+            matches!(
+                graph.query_directory("languag"),
+                Err(Error::UnknownWorkspacePath(_))
+            ),
+            "directory prefixes match whole components only"
+        );
+    }
+
+    #[test]
+    fn metadata_libra_simple_paths() {
+        let graph = JsonFixture::metadata_libra_9ffd93b().graph();
+        let from_id = package_id(json::METADATA_LIBRA_ADMISSION_CONTROL_SERVICE);
+        let to_id = package_id(json::METADATA_LIBRA_EXECUTOR_UTILS);
+
+        let chain_ids = |chain: &DependencyChain<'_>| -> Vec<PackageId> {
+            chain
+                .packages()
+                .map(|package| package.id().clone())
+                .collect()
+        };
+        let paths: BTreeSet<_> = graph
+            .simple_paths(&from_id, &to_id, None)
+            .expect("valid package IDs")
+            .map(|path| chain_ids(&path))
+            .collect();
+        let shortest: BTreeSet<_> = graph
+            .why_k_shortest(&from_id, &to_id, 10)
+            .expect("valid package IDs")
+            .iter()
+            .map(chain_ids)
+            .collect();
+        assert_eq!(paths, shortest, "all simple paths are found");
+
+        // With a length cap, the paths match the k shortest chains up to that length.
+        let lazy_static_id = package_id(json::METADATA_LIBRA_LAZY_STATIC);
+        let min_len = graph
+            .why(&from_id, &lazy_static_id)
+            .expect("valid package IDs")
+            .expect("admission-control-service depends on lazy_static")
+            .len();
+        let paths: Vec<_> = graph
+            .simple_paths(&from_id, &lazy_static_id, Some(min_len + 1))
+            .expect("valid package IDs")
+            .collect();
+        for path in &paths {
+            assert_chain_valid(path, &from_id, &lazy_static_id);
+            assert!(path.len() <= min_len + 1, "path length is capped");
+        }
+        assert!(paths.len() > 1, "multiple capped paths exist");
+        let path_ids: BTreeSet<_> = paths.iter().map(chain_ids).collect();
+        assert_eq!(path_ids.len(), paths.len(), "paths are distinct");
+        let shortest_ids: BTreeSet<_> = graph
+            .why_k_shortest(&from_id, &lazy_static_id, paths.len() + 1)
+            .expect("valid package IDs")
+            .iter()
+            .filter(|chain| chain.len() <= min_len + 1)
+            .map(chain_ids)
+            .collect();
+        assert_eq!(path_ids, shortest_ids, "capped paths match shortest chains");
+
+        let first_five = graph
+            .simple_paths(&from_id, &lazy_static_id, None)
+            .expect("valid package IDs")
+            .take(5)
+            .count();
+        assert_eq!(first_five, 5, "paths are produced lazily");
+    }
+
+    #[test]
+    fn metadata_libra_workspace_dominators() {
+        let graph = JsonFixture::metadata_libra_9ffd93b().graph();
+        let dominators = graph.workspace_dominators();
+
+        let reachable: Vec<_> = graph
+            .query_workspace()
+            .resolve()
+            .packages(DependencyDirection::Forward)
+            .filter(|package| !package.in_workspace())
+            .collect();
+        let counts: Vec<_> = dominators.responsible_counts().collect();
+        let attributed: usize = counts.iter().map(|(_, count)| count).sum();
+        assert_eq!(
+            attributed + dominators.shared_packages().count(),
+            reachable.len(),
+            "every reachable third-party package is either attributed or shared"
+        );
+        for window in counts.windows(2) {
+            assert!(window[0].1 >= window[1].1, "counts are in descending order");
+        }
+
+        for package in &reachable {
+            let responsible = dominators
+                .responsible_dependency(package.id())
+                .expect("valid package ID");
+            if let Some(responsible) = responsible {
+                assert!(
+                    dominators
+                        .is_direct_dependency(responsible.id())
+                        .expect("valid package ID"),
+                    "responsible package {} is a direct dependency",
+                    responsible.id()
+                );
+                let links = dominators
+                    .responsible_links(package.id())
+                    .expect("valid package ID");
+                assert!(!links.is_empty(), "responsible links are returned");
+                for link in links {
+                    assert!(link.from().in_workspace(), "link is from the workspace");
+                    assert_eq!(link.to().id(), responsible.id(), "link is to responsible");
+                }
+            }
+        }
+
+        // Cutting off the direct dependency responsible for the most packages makes them all
+        // unreachable.
+        let (top, top_count) = counts[0];
+        assert!(
+            top_count > 1,
+            "top dependency is responsible for other packages"
+        );
+        let without_top = graph
+            .query_workspace()
+            .resolve_with_fn(|_, link| link.to().id() != top.id());
+        for package in &reachable {
+            let responsible = dominators
+                .responsible_dependency(package.id())
+                .expect("valid package ID");
+            if responsible.map(|responsible| responsible.id()) == Some(top.id()) {
+                assert!(
+                    !without_top
+                        .contains(package.id())
+                        .expect("valid package ID"),
+                    "package {} is unreachable without {}",
+                    package.id(),
+                    top.id()
+                );
+                let idom = dominators
+                    .immediate_dominator(package.id())
+                    .expect("valid package ID");
+                assert_eq!(
+                    idom.is_none(),
+                    package.id() == top.id(),
+                    "only the top dependency is dominated by the workspace as a whole"
+                );
+            }
+        }
+
+        // Shared packages are still reachable after cutting off any one direct dependency.
+        for shared in dominators.shared_packages().take(5) {
+            assert!(
+                without_top.contains(shared.id()).expect("valid package ID"),
+                "shared package {} is reachable without {}",
+                shared.id(),
+                top.id()
+            );
+        }
+    }
+
+    #[test]
+    fn metadata_libra_transitive_weights() {
+        let graph = JsonFixture::metadata_libra_9ffd93b().graph();
+        let weights = graph.resolve_all().transitive_weights();
+        assert_eq!(
+            weights.len(),
+            graph.package_count(),
+            "one weight per package"
+        );
+        for window in weights.windows(2) {
+            assert!(
+                window[0].build_units() >= window[1].build_units(),
+                "weights are in descending order"
+            );
+        }
+        for weight in &weights {
+            let package = weight.package();
+            let expected = graph
+                .query_forward(iter::once(package.id()))
+                .expect("valid package ID")
+                .resolve_with_fn(|_, link| !link.dev_only())
+                .len();
+            assert_eq!(
+                weight.transitive_deps(),
+                expected - 1,
+                "{}: transitive deps match non-dev resolve",
+                package.id()
+            );
+            assert!(
+                weight.build_units() > weight.transitive_deps(),
+                "{}: every package is at least one build unit",
+                package.id()
+            );
+            if expected == 1 {
+                let mut build_units = 1;
+                if package.has_build_script() {
+                    build_units += 2;
+                }
+                assert_eq!(
+                    weight.build_units(),
+                    build_units,
+                    "{}: build units for a package without dependencies",
+                    package.id()
+                );
+            }
+        }
+        // serde_derive is a proc macro, so it and its 4 dependencies are built for the host.
+        // proc-macro2 and syn have build scripts, adding a compile and a run each.
+        let weight_for = |id: &str| {
+            *weights
+                .iter()
+                .find(|weight| *weight.package().id() == PackageId::new(id))
+                .expect("package is weighed")
+        };
+        let serde_derive = weight_for(
+            "serde_derive 1.0.104 (registry+https://github.com/rust-lang/crates.io-index)",
+        );
+        assert_eq!(serde_derive.transitive_deps(), 4, "serde_derive deps");
+        assert_eq!(serde_derive.build_units(), 9, "serde_derive build units");
+        // serde is built for the target, and has a build script of its own.
+        let serde =
+            weight_for("serde 1.0.104 (registry+https://github.com/rust-lang/crates.io-index)");
+        assert_eq!(serde.transitive_deps(), 5, "serde deps");
+        assert_eq!(serde.build_units(), 12, "serde build units");
+
+        // Only packages in the set are counted.
+        let workspace_weights = graph.resolve_workspace().transitive_weights();
+        for weight in &workspace_weights {
+            assert!(
+                weight.package().in_workspace(),
+                "only workspace packages are weighed"
+            );
+        }
+    }
+
+    #[test]
+    fn metadata_libra_build_waves() {
+        let graph = JsonFixture::metadata_libra_9ffd93b().graph();
+        let package_set = graph.query_workspace().resolve();
+        let waves = package_set.build_waves();
+
+        let mut wave_for = HashMap::new();
+        for (wave_idx, wave) in waves.iter().enumerate() {
+            assert!(!wave.is_empty(), "waves aren't empty");
+            for package in wave {
+                assert!(
+                    wave_for.insert(package.id(), wave_idx).is_none(),
+                    "package {} is in exactly one wave",
+                    package.id()
+                );
+            }
+        }
+        assert_eq!(
+            wave_for.len(),
+            package_set.len(),
+            "every package is scheduled"
+        );
+        for package in package_set.packages(DependencyDirection::Forward) {
+            let wave_idx = wave_for[package.id()];
+            let dep_waves: Vec<_> = package
+                .direct_links()
+                .filter(|link| !link.dev_only())
+                .map(|link| wave_for[link.to().id()])
+                .collect();
+            for &dep_wave_idx in &dep_waves {
+                assert!(
+                    dep_wave_idx < wave_idx,
+                    "{}: dependencies are built in earlier waves",
+                    package.id()
+                );
+            }
+            let expected = dep_waves.iter().map(|idx| idx + 1).max().unwrap_or(0);
+            assert_eq!(
+                wave_idx,
+                expected,
+                "{}: package is built in the earliest possible wave",
+                package.id()
+            );
+        }
+
+        let cargo_set = graph
+            .query_workspace()
+            .to_feature_query(StandardFeatures::Default)
+            .resolve()
+            .into_cargo_set(&CargoOptions::new())
+            .expect("cargo resolution succeeds");
+        let waves = cargo_set.build_waves();
+        let mut wave_for = HashMap::new();
+        for (wave_idx, wave) in waves.iter().enumerate() {
+            for (build_platform, package) in wave {
+                assert!(
+                    wave_for
+                        .insert((*build_platform, package.id()), wave_idx)
+                        .is_none(),
+                    "{} on {} is in exactly one wave",
+                    package.id(),
+                    build_platform
+                );
+                if package.is_proc_macro() {
+                    assert_eq!(
+                        *build_platform,
+                        BuildPlatform::Host,
+                        "proc macros are built on the host"
+                    );
+                }
+            }
+        }
+        assert_eq!(
+            wave_for.len(),
+            cargo_set.target_features().to_package_set().len()
+                + cargo_set.host_features().to_package_set().len(),
+            "every target and host package is scheduled"
+        );
+        for link in cargo_set
+            .proc_macro_links()
+            .chain(cargo_set.build_dep_links())
+        {
+            assert!(
+                wave_for[&(BuildPlatform::Host, link.to().id())]
+                    < wave_for[&(BuildPlatform::Target, link.from().id())],
+                "host dependency {} is built before {}",
+                link.to().id(),
+                link.from().id()
+            );
+        }
+    }
+
+    fn assert_chain_valid(chain: &DependencyChain<'_>, from_id: &PackageId, to_id: &PackageId) {
+        assert_eq!(chain.from().id(), from_id, "chain starts at from");
+        assert_eq!(chain.to().id(), to_id, "chain ends at to");
+        let mut expected_from = chain.from().id();
+        for link in chain.links() {
+            assert_eq!(link.from().id(), expected_from, "links are connected");
+            expected_from = link.to().id();
+        }
+        let packages: BTreeSet<_> = chain.packages().map(|package| package.id()).collect();
+        assert_eq!(packages.len(), chain.len() + 1, "chain is a simple path");
+    }
+
+    #[test]
+    fn metadata_libra_max_depth() {
+        let graph = JsonFixture::metadata_libra_9ffd93b().graph();
+        let root_id = package_id(json::METADATA_LIBRA_ADMISSION_CONTROL_SERVICE);
+        let root = graph.metadata(&root_id).expect("valid package ID");
+        let forward_query = |max_depth| {
+            let mut query = graph
+                .query_forward(iter::once(&root_id))
+                .expect("valid package ID");
+            query.set_max_depth(max_depth);
+            query
+        };
+        fn sorted_ids(ids: Vec<&PackageId>) -> BTreeSet<&PackageId> {
+            ids.into_iter().collect()
+        }
+
+        let query = forward_query(0);
+        assert_eq!(query.max_depth(), Some(0), "max depth is set");
+        let actual: Vec<_> = query
+            .resolve()
+            .package_ids(DependencyDirection::Forward)
+            .collect();
+        assert_eq!(actual, vec![&root_id], "depth 0 only includes the initials");
+
+        let mut expected = vec![root.id()];
+        expected.extend(root.direct_links().map(|link| link.to().id()));
+        let actual = forward_query(1)
+            .resolve()
+            .package_ids(DependencyDirection::Forward)
+            .collect();
+        assert_eq!(
+            sorted_ids(expected.clone()),
+            sorted_ids(actual),
+            "depth 1 includes direct dependencies"
+        );
+
+        let mut second_level = expected.clone();
+        for link in root.direct_links() {
+            second_level.extend(link.to().direct_links().map(|link| link.to().id()));
+        }
+        let actual = forward_query(2)
+            .resolve()
+            .package_ids(DependencyDirection::Forward)
+            .collect();
+        assert_eq!(
+            sorted_ids(second_level),
+            sorted_ids(actual),
+            "depth 2 includes second-level dependencies"
+        );
+
+        let mut expected = vec![root.id()];
+        expected.extend(
+            root.direct_links()
+                .filter(|link| !link.dev_only())
+                .map(|link| link.to().id()),
+        );
+        let actual = forward_query(1)
+            .resolve_with_fn(|_, link| !link.dev_only())
+            .package_ids(DependencyDirection::Forward)
+            .collect();
+        assert_eq!(
+            sorted_ids(expected),
+            sorted_ids(actual),
+            "depth 1 with a resolver skips filtered links"
+        );
+
+        let full = graph
+            .query_forward(iter::once(&root_id))
+            .expect("valid package ID")
+            .resolve();
+        assert_eq!(
+            forward_query(usize::MAX).resolve(),
+            full,
+            "unbounded depth matches the full traversal"
+        );
+
+        let target_id = package_id(json::METADATA_LIBRA_EXECUTOR_UTILS);
+        let target = graph.metadata(&target_id).expect("valid package ID");
+        let mut expected = vec![target.id()];
+        expected.extend(target.reverse_direct_links().map(|link| link.from().id()));
+        let mut reverse_query = graph
+            .query_reverse(iter::once(&target_id))
+            .expect("valid package ID");
+        reverse_query.set_max_depth(1);
+        let actual = reverse_query
+            .resolve()
+            .package_ids(DependencyDirection::Reverse)
+            .collect();
+        assert_eq!(
+            sorted_ids(expected),
+            sorted_ids(actual),
+            "reverse depth 1 includes direct dependents"
+        );
+    }
+
+    #[test]
+    fn metadata_libra_git_sources() {
+        let graph = JsonFixture::metadata_libra_9ffd93b().graph();
+        let source_for = |name: &str| {
+            let package_set = graph.resolve_package_name(name);
+            let package = package_set
+                .packages(DependencyDirection::Forward)
+                .next()
+                .unwrap_or_else(|| panic!("package {} found", name));
+            package.source()
+        };
+
+        let zstd_sys = source_for("zstd-sys");
+        assert!(zstd_sys.is_git(), "zstd-sys is a Git dependency");
+        assert_eq!(
+            zstd_sys.git_repository(),
+            Some("https://github.com/gyscos/zstd-rs.git")
+        );
+        assert_eq!(zstd_sys.git_req(), Some(GitReq::Default));
+        assert_eq!(
+            zstd_sys.git_resolved(),
+            Some("bc874a57298bdb500cdb5aeac5f23878b6480d0b")
+        );
+
+        let lz4_sys = source_for("lz4-sys");
+        assert_eq!(lz4_sys.git_req(), Some(GitReq::Branch("adjust-build")));
+
+        let rocksdb_sys = source_for("librocksdb_sys");
+        assert_eq!(
+            rocksdb_sys.git_req(),
+            Some(GitReq::Rev("72e45c3f3283302c825d53c3cd7154f4cd9e8f5b"))
+        );
+        assert_eq!(
+            rocksdb_sys.git_resolved(),
+            Some("72e45c3f3283302c825d53c3cd7154f4cd9e8f5b"),
+            "resolved hash matches requested revision"
+        );
+
+        for name in ["lazy_static", "libra-types"] {
+            let source = source_for(name);
+            assert!(!source.is_git(), "{} isn't a Git dependency", name);
+            assert_eq!(source.git_repository(), None);
+            assert_eq!(source.git_req(), None);
+            assert_eq!(source.git_resolved(), None);
+        }
     }
 
-    proptest_suite!(metadata_libra_f0091a4);
+    #[test]
+    fn metadata_libra_policy() {
+        let graph = JsonFixture::metadata_libra_9ffd93b().graph();
+        let violating_names = |report: &PolicyReport<'_>| -> BTreeSet<String> {
+            report
+                .violations()
+                .iter()
+                .map(|violation| violation.package().name().to_owned())
+                .collect()
+        };
+
+        let mut sources = SourcePolicy::new();
+        let mut policy = Policy::new();
+        policy.set_sources(sources.clone());
+        let report = graph.check_policy(&policy);
+        assert_eq!(
+            report.violations().len(),
+            10,
+            "Git dependencies are reported"
+        );
+        let rocksdb = report
+            .violations()
+            .iter()
+            .find(|violation| violation.package().name() == "rocksdb")
+            .expect("rocksdb is a Git dependency");
+        assert_eq!(
+            rocksdb.kind(),
+            &ViolationKind::DisallowedSource {
+                kind: SourceKind::Git("https://github.com/tikv/rust-rocksdb.git")
+            }
+        );
+        assert_eq!(
+            rocksdb
+                .path()
+                .expect("path from the workspace exists")
+                .to_string(),
+            "schemadb v0.1.0 -> rocksdb v0.3.0 (normal, *)",
+            "shortest path from the workspace"
+        );
+
+        sources
+            .allow_git("https://github.com/tikv/rust-rocksdb")
+            .allow_git("https://github.com/calibra/curve25519-dalek.git/");
+        policy.set_sources(sources);
+        let report = graph.check_policy(&policy);
+        let names = violating_names(&report);
+        assert_eq!(
+            report.violations().len(),
+            6,
+            "allowed repositories are skipped"
+        );
+        for name in [
+            "rocksdb",
+            "librocksdb_sys",
+            "libtitan_sys",
+            "curve25519-dalek",
+        ] {
+            assert!(
+                !names.contains(name),
+                "{} is from an allowed repository",
+                name
+            );
+        }
+
+        let mut policy = Policy::new();
+        policy.set_allowed_licenses(["mit", "Apache-2.0"]);
+        let report = graph.check_policy(&policy);
+        let snow = report
+            .violations()
+            .iter()
+            .find(|violation| violation.package().name() == "snow")
+            .expect("snow's license isn't allowed");
+        assert_eq!(
+            snow.kind(),
+            &ViolationKind::DisallowedLicense {
+                expr: "Unlicense".parse().expect("valid license expression")
+            }
+        );
+        let webpki = report
+            .violations()
+            .iter()
+            .find(|violation| violation.package().name() == "webpki")
+            .expect("webpki doesn't specify a license");
+        assert_eq!(webpki.kind(), &ViolationKind::MissingLicense);
+        assert_eq!(
+            webpki.path().expect("path from the workspace exists").to_string(),
+            "libra-vault-client v0.1.0 -> ureq v0.11.4 (normal, ^0.11.3) -> webpki v0.21.2 (normal, ^0.21)",
+        );
+        assert!(
+            !violating_names(&report).contains("libra-types"),
+            "workspace members are skipped"
+        );
+
+        policy.set_allowed_licenses([
+            "MIT",
+            "Apache-2.0",
+            "BSD-2-Clause",
+            "BSD-3-Clause",
+            "CC0-1.0",
+            "ISC",
+            "MPL-2.0",
+            "Unlicense",
+            "Zlib",
+        ]);
+        let report = graph.check_policy(&policy);
+        let expected: BTreeSet<_> = [
+            "fuchsia-cprng",
+            "librocksdb_sys",
+            "libtitan_sys",
+            "ring",
+            "webpki",
+        ]
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+        assert_eq!(
+            violating_names(&report),
+            expected,
+            "only packages without licenses are reported"
+        );
+    }
 
     #[test]
-    fn metadata_libra_9ffd93b() {
-        let metadata = JsonFixture::metadata_libra_9ffd93b();
-        metadata.verify();
+    fn metadata_libra_renamed_deps() {
+        let graph = JsonFixture::metadata_libra_9ffd93b().graph();
+        let executor = graph
+            .metadata(&package_id(json::METADATA_LIBRA_EXECUTOR))
+            .expect("valid package ID");
 
-        let graph = metadata.graph();
-        graph.assert_depends_on(
-            &package_id(json::METADATA_LIBRA_ADMISSION_CONTROL_SERVICE),
-            &package_id(json::METADATA_LIBRA_EXECUTOR_UTILS),
-            DependencyDirection::Forward,
-            "admission-control-service should depend on executor-utils",
+        let lcs = executor
+            .link_by_dep_name("lcs")
+            .expect("executor depends on lcs");
+        assert_eq!(lcs.to().name(), "libra-canonical-serialization");
+        assert_eq!(lcs.resolved_name(), "lcs");
+        assert_eq!(
+            executor
+                .link_by_resolved_name("lcs")
+                .map(|link| link.to().id()),
+            Some(lcs.to().id()),
+            "resolved name maps to the same package"
         );
-        graph.assert_not_depends_on(
-            &package_id(json::METADATA_LIBRA_EXECUTOR_UTILS),
-            &package_id(json::METADATA_LIBRA_ADMISSION_CONTROL_SERVICE),
-            DependencyDirection::Forward,
-            "executor-utils should not depend on admission-control-service",
+        assert!(
+            executor
+                .link_by_dep_name("libra-canonical-serialization")
+                .is_none(),
+            "renamed dependency isn't available under its package name"
         );
 
-        let proc_macro_packages: Vec<_> = graph
-            .workspace()
-            .iter_by_path()
-            .filter_map(|(_, metadata)| {
-                if metadata.is_proc_macro() {
-                    Some(metadata.name())
-                } else {
-                    None
-                }
-            })
+        let libra_types = executor
+            .link_by_dep_name("libra-types")
+            .expect("executor depends on libra-types");
+        assert_eq!(libra_types.resolved_name(), "libra_types");
+        assert_eq!(
+            executor
+                .link_by_resolved_name("libra_types")
+                .map(|link| link.to().id()),
+            Some(libra_types.to().id()),
+            "resolved name has hyphens replaced with underscores"
+        );
+        assert!(executor.link_by_resolved_name("nonexistent").is_none());
+
+        let imported_as = lcs.to().imported_as();
+        assert!(imported_as.contains("lcs"), "lcs is imported as lcs");
+        for link in lcs.to().reverse_direct_links() {
+            assert!(
+                imported_as.contains(link.resolved_name()),
+                "{} imports lcs under a collected name",
+                link.from().id()
+            );
+        }
+    }
+
+    #[test]
+    fn metadata_libra_links_conflicts() {
+        let graph = JsonFixture::metadata_libra_9ffd93b().graph();
+        assert!(
+            graph
+                .query_workspace()
+                .resolve()
+                .links_conflicts()
+                .is_empty(),
+            "no links conflicts in original fixture"
+        );
+
+        // Make bzip2-sys link to the same native library as libz-sys.
+        let bzip2_id = "bzip2-sys 0.1.8+1.0.8 (git+https://github.com/alexcrichton/bzip2-rs.git#461d66916a5e6848e455c6d4fb9a5f70f4617efd)";
+        let libz_id = "libz-sys 1.0.25 (registry+https://github.com/rust-lang/crates.io-index)";
+        let mut json: serde_json::Value =
+            serde_json::from_str(JsonFixture::metadata_libra_9ffd93b().json())
+                .expect("fixture is valid JSON");
+        let bzip2 = json["packages"]
+            .as_array_mut()
+            .expect("packages is an array")
+            .iter_mut()
+            .find(|package| package["id"] == bzip2_id)
+            .expect("bzip2-sys is in the fixture");
+        bzip2["links"] = "z".into();
+        let graph = guppy::CargoMetadata::parse_json(json.to_string())
+            .expect("modified fixture parses")
+            .build_graph()
+            .expect("modified fixture builds");
+
+        let package_set = graph.query_workspace().resolve();
+        let conflicts = package_set.links_conflicts();
+        assert_eq!(conflicts.len(), 1, "one links conflict");
+        let conflict = &conflicts[0];
+        assert_eq!(conflict.links(), "z", "conflicting links value");
+        let ids: Vec<_> = conflict
+            .packages()
+            .iter()
+            .map(|package| package.id().repr())
             .collect();
+        assert_eq!(ids, vec![bzip2_id, libz_id], "conflicting packages");
+
+        assert_eq!(conflict.paths().len(), 2, "one path per package");
+        for (package, path) in conflict.packages().iter().zip(conflict.paths()) {
+            assert_eq!(path.to().id(), package.id(), "path ends at package");
+            let mut current = path.from();
+            for link in path.links() {
+                assert_eq!(link.from().id(), current.id(), "path is connected");
+                current = link.to();
+            }
+            assert!(
+                path.from().reverse_direct_links().all(|link| !package_set
+                    .contains(link.from().id())
+                    .expect("valid package ID")),
+                "path starts at a root of the set"
+            );
+        }
+
+        let display = conflict.to_string();
+        let mut lines = display.lines();
         assert_eq!(
-            proc_macro_packages,
-            ["num-variants", "libra-crypto-derive"],
-            "proc macro packages"
+            lines.next(),
+            Some("multiple packages link to native library `z`:"),
+            "display header"
         );
+        assert_eq!(lines.count(), 2, "display has one line per path");
 
-        let build_script_packages: Vec<_> = graph
+        let cargo_set = package_set
+            .to_feature_set(StandardFeatures::All)
+            .into_cargo_set(&CargoOptions::new())
+            .expect("cargo resolution succeeds");
+        let cargo_conflicts = cargo_set.links_conflicts();
+        assert_eq!(cargo_conflicts.len(), 1, "one links conflict in cargo set");
+        assert_eq!(cargo_conflicts[0].links(), "z", "cargo set links value");
+    }
+
+    #[test]
+    fn metadata_libra_build_scripts_proc_macros() {
+        let graph = JsonFixture::metadata_libra_9ffd93b().graph();
+        let all = graph.resolve_all();
+        assert_eq!(
+            all.build_script_packages().len(),
+            94,
+            "build script packages"
+        );
+        assert_eq!(all.proc_macro_packages().len(), 27, "proc macro packages");
+
+        let libra_crypto = graph
             .workspace()
-            .iter_by_path()
-            .filter_map(|(_, metadata)| {
-                if metadata.has_build_script() {
-                    Some(metadata.name())
-                } else {
-                    None
-                }
+            .member_by_name("libra-crypto")
+            .expect("libra-crypto is a member");
+        let package_set = graph
+            .query_forward(iter::once(libra_crypto.id()))
+            .expect("valid package ID")
+            .resolve();
+        assert_eq!(package_set.len(), 162, "libra-crypto dependencies");
+        let build_scripts = package_set.build_script_packages();
+        assert_eq!(build_scripts.len(), 37, "libra-crypto build scripts");
+        assert!(
+            build_scripts
+                .packages(DependencyDirection::Forward)
+                .all(|package| package.has_build_script()),
+            "only packages with build scripts are returned"
+        );
+        let proc_macros = package_set.proc_macro_packages();
+        assert_eq!(proc_macros.len(), 6, "libra-crypto proc macros");
+        assert!(
+            proc_macros
+                .packages(DependencyDirection::Forward)
+                .all(|package| package.is_proc_macro()),
+            "only proc macros are returned"
+        );
+
+        let pulls_in_build_scripts = package_set.depends_on_any(&build_scripts);
+        let direct_deps: BTreeSet<_> = libra_crypto
+            .direct_links()
+            .filter(|link| {
+                pulls_in_build_scripts
+                    .contains(link.to().id())
+                    .expect("valid package ID")
             })
+            .map(|link| link.to().name())
             .collect();
         assert_eq!(
-            build_script_packages,
-            [
-                "admission-control-proto",
-                "libra-dev",
-                "debug-interface",
-                "libra-metrics",
-                "storage-proto",
-                "libra_fuzzer_fuzz",
-                "libra-types"
+            direct_deps.into_iter().collect::<Vec<_>>(),
+            vec![
+                "anyhow",
+                "byteorder",
+                "bytes",
+                "criterion",
+                "curve25519-dalek",
+                "digest",
+                "ed25519-dalek",
+                "hmac",
+                "libra-canonical-serialization",
+                "libra-crypto-derive",
+                "libra-nibble",
+                "pairing",
+                "proptest",
+                "proptest-derive",
+                "rand",
+                "ripemd160",
+                "serde",
+                "sha2",
+                "sha3",
+                "thiserror",
+                "threshold_crypto",
+                "tiny-keccak",
+                "x25519-dalek",
             ],
-            "build script packages"
+            "direct dependencies that pull in build scripts"
         );
 
-        let mut build_dep_but_no_build_script: Vec<_> = graph
-            .resolve_all()
-            .links(DependencyDirection::Forward)
-            .filter_map(|link| {
-                if link.build().is_present() && !link.from().has_build_script() {
-                    Some(link.from().name())
-                } else {
-                    None
+        // Packages outside the set aren't returned, even if they're in the targets.
+        let workspace = graph.resolve_workspace();
+        let outside = workspace.depends_on_any(&all.build_script_packages());
+        assert!(
+            outside
+                .packages(DependencyDirection::Forward)
+                .all(|package| package.in_workspace()),
+            "results are limited to the set"
+        );
+    }
+
+    #[test]
+    fn metadata_libra_package_name_matching() {
+        fn names(package_set: PackageSet<'_>) -> Vec<&str> {
+            let mut names: Vec<_> = package_set
+                .packages(DependencyDirection::Forward)
+                .map(|package| package.name())
+                .collect();
+            names.sort_unstable();
+            names
+        }
+
+        let graph = JsonFixture::metadata_libra_9ffd93b().graph();
+
+        let tokio = graph.resolve_package_name_glob("tokio-*");
+        assert_eq!(
+            tokio.len(),
+            23,
+            "tokio-* matches two versions of some packages"
+        );
+        assert!(
+            names(tokio).iter().all(|name| name.starts_with("tokio-")),
+            "tokio-* only matches names starting with tokio-"
+        );
+        assert_eq!(
+            names(graph.resolve_package_name_glob("libra-*s")),
+            vec!["libra-metrics", "libra-proptest-helpers", "libra-types"],
+            "workspace packages are matched"
+        );
+        assert_eq!(
+            names(graph.resolve_package_name_glob("sha?")),
+            vec!["sha2", "sha3"],
+            "? matches a single character"
+        );
+        assert_eq!(
+            graph.resolve_package_name_glob("lazy_static"),
+            graph.resolve_package_name("lazy_static"),
+            "patterns without wildcards match exactly"
+        );
+        assert!(
+            graph.resolve_package_name_glob("tokio-").is_empty(),
+            "no matches"
+        );
+
+        let package_set = graph.resolve_package_name_fn(|name| name.ends_with("-sys"));
+        assert!(!package_set.is_empty(), "some -sys packages");
+        assert!(
+            names(package_set).iter().all(|name| name.ends_with("-sys")),
+            "callback is honored"
+        );
+
+        let query_set = graph
+            .resolve_package_name_glob("libra-c*")
+            .to_package_query(DependencyDirection::Forward)
+            .resolve();
+        assert_eq!(
+            query_set,
+            graph
+                .resolve_expr("deps(libra-c*)")
+                .expect("valid expression"),
+            "globs are supported in expressions"
+        );
+    }
+
+    #[test]
+    fn metadata_libra_query_expr() {
+        let graph = JsonFixture::metadata_libra_9ffd93b().graph();
+        let libra_crypto = graph
+            .workspace()
+            .member_by_name("libra-crypto")
+            .expect("libra-crypto is a member");
+        let crypto_deps = graph
+            .query_forward(iter::once(libra_crypto.id()))
+            .expect("valid package ID")
+            .resolve();
+
+        let package_set = graph
+            .resolve_expr("deps(libra-crypto) & !workspace()")
+            .expect("valid expression");
+        assert_eq!(
+            package_set,
+            crypto_deps.difference(&graph.resolve_workspace()),
+            "third-party dependencies of libra-crypto"
+        );
+
+        let package_set = graph
+            .resolve_expr("build_deps(libra-crypto) | package(libra-types)")
+            .expect("valid expression");
+        let expected = graph
+            .query_forward(iter::once(libra_crypto.id()))
+            .expect("valid package ID")
+            .resolve_with_fn(|_, link| link.build().is_present())
+            .union(&graph.resolve_package_name("libra-types"));
+        assert_eq!(package_set, expected, "build deps and libra-types");
+
+        let package_set = graph
+            .resolve_expr("rdeps(lazy_static) & workspace()")
+            .expect("valid expression");
+        assert!(
+            package_set
+                .packages(DependencyDirection::Forward)
+                .all(|package| package.in_workspace()),
+            "only workspace packages returned"
+        );
+        assert!(
+            package_set
+                .contains(&package_id(
+                    "libra-types 0.1.0 (path+file:///Users/fakeuser/local/libra/types)"
+                ))
+                .expect("valid package ID"),
+            "libra-types depends on lazy_static"
+        );
+
+        let err = graph
+            .resolve_expr("deps(no-such-package)")
+            .expect_err("unknown package name");
+        assert_eq!(
+            err.to_string(),
+            "invalid query expression: no packages matching 'no-such-package' found",
+            "unknown package name error"
+        );
+    }
+
+    #[test]
+    fn metadata_libra_owner_of_path() {
+        let graph = JsonFixture::metadata_libra().graph();
+        let workspace = graph.workspace();
+
+        let owner_name = |path: &str| {
+            workspace
+                .owner_of_path(path)
+                .member()
+                .map(|package| package.name())
+        };
+        assert_eq!(owner_name("client/src/main.rs"), Some("client"));
+        assert_eq!(
+            owner_name("client/libra_wallet/src/lib.rs"),
+            Some("libra-wallet"),
+            "nested packages own their own files"
+        );
+        assert_eq!(
+            owner_name("client/libra_wallet"),
+            Some("libra-wallet"),
+            "package directories are owned by the package"
+        );
+        assert_eq!(
+            owner_name("client/libra_wallet_extra/src/lib.rs"),
+            Some("client"),
+            "path components are matched exactly"
+        );
+        assert!(
+            matches!(workspace.owner_of_path("README.md"), PathOwner::Unowned),
+            "virtual workspace root isn't owned by any package"
+        );
+    }
+
+    #[test]
+    fn metadata_libra_license_report() {
+        let graph = JsonFixture::metadata_libra().graph();
+        let report = graph.resolve_all().license_report();
+
+        assert_eq!(report.buckets().len(), 18, "number of distinct licenses");
+        let dual: LicenseExpr = "MIT OR Apache-2.0".parse().expect("valid expression");
+        let bucket = report.bucket(&dual).expect("MIT OR Apache-2.0 is used");
+        assert_eq!(bucket.expr().to_string(), "Apache-2.0 OR MIT");
+        assert_eq!(
+            bucket.packages().len(),
+            257,
+            "equivalent expressions are bucketed together"
+        );
+        let bucket = report
+            .bucket(&LicenseExpr::parse_lax("Unlicense/MIT").expect("valid expression"))
+            .expect("Unlicense OR MIT is used");
+        assert_eq!(bucket.packages().len(), 11);
+        let bucket = report
+            .bucket(
+                &"MIT OR Apache-2.0 AND BSD-2-Clause"
+                    .parse()
+                    .expect("valid expression"),
+            )
+            .expect("legacy syntax mixed with AND is parsed");
+        assert_eq!(bucket.packages().len(), 1);
+        assert!(
+            report
+                .bucket(&"GPL-3.0".parse().expect("valid expression"))
+                .is_none(),
+            "GPL-3.0 isn't used"
+        );
+
+        let mut missing: Vec<_> = report
+            .missing()
+            .iter()
+            .map(|package| package.name())
+            .collect();
+        missing.sort_unstable();
+        assert_eq!(missing, vec!["librocksdb_sys", "libtitan_sys", "tree_heap"]);
+
+        let license_files: Vec<_> = report
+            .nonstandard()
+            .iter()
+            .filter_map(|(package, reason)| match reason {
+                NonstandardLicense::LicenseFile(license_file) => {
+                    Some((package.name(), license_file.as_str()))
                 }
+                _ => None,
             })
             .collect();
-        build_dep_but_no_build_script.sort_unstable();
         assert_eq!(
-            build_dep_but_no_build_script,
-            ["libra-mempool", "rusoto_signature"],
-            "packages with build deps but no build scripts"
+            license_files,
+            vec![
+                ("fuchsia-cprng", "LICENSE"),
+                ("ring", "LICENSE"),
+                ("webpki", "LICENSE"),
+            ],
+            "license file only packages"
+        );
+        let legacy_count = report
+            .nonstandard()
+            .iter()
+            .filter(|(_, reason)| *reason == NonstandardLicense::LegacySyntax)
+            .count();
+        assert_eq!(legacy_count, 213, "packages using the legacy '/' separator");
+        assert_eq!(
+            report.nonstandard().len(),
+            216,
+            "no unknown license identifiers"
         );
+        assert!(report.invalid().is_empty(), "all licenses parse");
     }
 
-    proptest_suite!(metadata_libra_9ffd93b);
+    #[test]
+    fn metadata_libra_editions() {
+        let graph = JsonFixture::metadata_libra_9ffd93b().graph();
+        let package_set = graph.resolve_all();
+        let counts = package_set.edition_counts();
+        assert_eq!(
+            counts.into_iter().collect::<Vec<_>>(),
+            vec![("2015", 303), ("2018", 303)],
+            "edition counts"
+        );
+
+        let edition_2015 = package_set.filter_edition(|edition| edition == "2015");
+        assert_eq!(edition_2015.len(), 303, "2015 edition packages");
+        assert!(
+            edition_2015
+                .packages(DependencyDirection::Forward)
+                .all(|package| package.edition() == "2015"),
+            "filter only returns 2015 edition packages"
+        );
+
+        let groups = package_set.group_by_edition();
+        assert_eq!(
+            groups.keys().copied().collect::<Vec<_>>(),
+            vec!["2015", "2018"],
+            "edition groups"
+        );
+        assert_eq!(
+            groups["2015"].len() + groups["2018"].len(),
+            package_set.len(),
+            "groups partition the set"
+        );
+
+        // Every workspace package in this fixture is on the 2018 edition.
+        let workspace_counts = graph.resolve_workspace().edition_counts();
+        assert_eq!(
+            workspace_counts.into_iter().collect::<Vec<_>>(),
+            vec![("2018", 100)],
+            "workspace edition counts"
+        );
+    }
+
+    #[test]
+    fn metadata_libra_msrv() {
+        let graph = JsonFixture::metadata_libra_9ffd93b().graph();
+        assert!(
+            graph.query_workspace().resolve().msrv().is_none(),
+            "no rust-version fields in original fixture"
+        );
+
+        let bzip2_id = "bzip2-sys 0.1.8+1.0.8 (git+https://github.com/alexcrichton/bzip2-rs.git#461d66916a5e6848e455c6d4fb9a5f70f4617efd)";
+        let libz_id = "libz-sys 1.0.25 (registry+https://github.com/rust-lang/crates.io-index)";
+        let lazy_static_id =
+            "lazy_static 1.4.0 (registry+https://github.com/rust-lang/crates.io-index)";
+        let mut json: serde_json::Value =
+            serde_json::from_str(JsonFixture::metadata_libra_9ffd93b().json())
+                .expect("fixture is valid JSON");
+        for package in json["packages"]
+            .as_array_mut()
+            .expect("packages is an array")
+        {
+            let rust_version = if package["id"] == bzip2_id || package["id"] == libz_id {
+                "1.60"
+            } else if package["id"] == lazy_static_id {
+                "1.40.0"
+            } else {
+                continue;
+            };
+            package["rust_version"] = rust_version.into();
+        }
+        let graph = guppy::CargoMetadata::parse_json(json.to_string())
+            .expect("modified fixture parses")
+            .build_graph()
+            .expect("modified fixture builds");
+
+        let lazy_static = graph
+            .metadata(&PackageId::new(lazy_static_id))
+            .expect("lazy_static is in the fixture");
+        assert_eq!(
+            lazy_static.minimum_rust_version(),
+            Some(Version::new(1, 40, 0)),
+            "lazy_static rust version"
+        );
+
+        let package_set = graph.query_workspace().resolve();
+        let msrv = package_set
+            .msrv()
+            .expect("some packages specify rust-version");
+        assert_eq!(
+            msrv.version(),
+            &Version::new(1, 60, 0),
+            "highest rust version"
+        );
+        let ids: Vec<_> = msrv
+            .packages()
+            .iter()
+            .map(|package| package.id().repr())
+            .collect();
+        assert_eq!(ids, vec![bzip2_id, libz_id], "packages requiring the MSRV");
+
+        let lazy_static_set = graph
+            .query_forward(iter::once(lazy_static.id()))
+            .expect("valid package ID")
+            .resolve();
+        let msrv = lazy_static_set
+            .msrv()
+            .expect("lazy_static specifies rust-version");
+        assert_eq!(msrv.version(), &Version::new(1, 40, 0), "lazy_static MSRV");
+
+        let cargo_set = package_set
+            .to_feature_set(StandardFeatures::All)
+            .into_cargo_set(&CargoOptions::new())
+            .expect("cargo resolution succeeds");
+        let msrv = cargo_set.msrv().expect("cargo set has an MSRV");
+        assert_eq!(msrv.version(), &Version::new(1, 60, 0), "cargo set MSRV");
+    }
 }
 
 mod guppy_tests {
@@ -472,6 +3851,43 @@ mod guppy_tests {
     proptest_suite!(metadata_guppy_44b62fa);
 }
 
+struct StyledNameVisitor;
+
+impl PackageDotVisitor for StyledNameVisitor {
+    fn visit_package(&self, package: PackageMetadata<'_>, f: &mut DotWrite<'_, '_>) -> fmt::Result {
+        NameVisitor.visit_package(package, f)
+    }
+
+    fn visit_link(&self, link: PackageLink<'_>, f: &mut DotWrite<'_, '_>) -> fmt::Result {
+        NameVisitor.visit_link(link, f)
+    }
+
+    fn package_attributes(&self, package: PackageMetadata<'_>, attrs: &mut DotAttributes) {
+        if package.in_workspace() {
+            // Setting an attribute twice replaces it, and names are quoted if necessary.
+            attrs
+                .set("shape", "box")
+                .set("color", "red")
+                .set("color", "blue")
+                .set("custom attr", "x");
+        }
+    }
+
+    fn package_cluster(&self, package: PackageMetadata<'_>) -> Option<String> {
+        if package.in_workspace() {
+            Some("first \"party\"".to_owned())
+        } else {
+            None
+        }
+    }
+
+    fn link_attributes(&self, link: PackageLink<'_>, attrs: &mut DotAttributes) {
+        if link.from().in_workspace() {
+            attrs.set("penwidth", "2").set("label", "workspace \"dep\"");
+        }
+    }
+}
+
 struct NameVisitor;
 
 impl PackageDotVisitor for NameVisitor {
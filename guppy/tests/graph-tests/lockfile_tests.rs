@@ -0,0 +1,647 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use camino::{Utf8Path, Utf8PathBuf};
+use guppy::{
+    graph::{
+        DependencyDirection, LockfileChangeKind, PackageGraph, PackageLink, PackageMetadata,
+        PatchKind,
+    },
+    DependencyKind, Error, Version,
+};
+use std::fs;
+
+fn inside_outside_graph() -> PackageGraph {
+    let workspace_root = Utf8Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../fixtures/workspace/inside-outside/workspace");
+    let graph = PackageGraph::from_lockfile(workspace_root).expect("graph should be constructed");
+    graph.verify().expect("graph verification should succeed");
+    graph
+}
+
+fn package<'g>(graph: &'g PackageGraph, name: &str) -> PackageMetadata<'g> {
+    let package_set = graph.resolve_package_name(name);
+    let mut packages = package_set.packages(DependencyDirection::Forward);
+    let package = packages
+        .next()
+        .unwrap_or_else(|| panic!("package {} found", name));
+    assert!(packages.next().is_none(), "package {} is unique", name);
+    package
+}
+
+fn link<'g>(from: PackageMetadata<'g>, to: &str) -> PackageLink<'g> {
+    from.direct_links()
+        .find(|link| link.to().name() == to)
+        .unwrap_or_else(|| panic!("link from {} to {} found", from.name(), to))
+}
+
+#[test]
+fn lockfile_inside_outside() {
+    let graph = inside_outside_graph();
+    assert_eq!(graph.package_count(), 12, "all packages in Cargo.lock");
+
+    let workspace = graph.workspace();
+    let mut members: Vec<_> = workspace
+        .iter_by_path()
+        .map(|(path, package)| (path.as_str(), package.name()))
+        .collect();
+    members.sort_unstable();
+    assert_eq!(
+        members,
+        vec![
+            ("internal", "internal"),
+            ("internal-macro", "internal-macro"),
+            ("main", "main"),
+            ("side", "side"),
+        ],
+        "workspace members"
+    );
+
+    let main = package(&graph, "main");
+    assert!(main.has_build_script(), "main has a build script");
+    let main_internal = link(main, "internal");
+    assert!(main_internal.normal().is_present());
+    assert!(main_internal.build().is_present());
+    assert!(main_internal.dev().is_present());
+    assert_eq!(main_internal.version_req().to_string(), "*");
+
+    let internal = package(&graph, "internal");
+    let internal_x86 = link(internal, "x86-active");
+    assert!(
+        internal_x86.normal().is_present(),
+        "platform-specific dependency is present"
+    );
+    assert!(
+        internal_x86.normal().status().required_status().is_never(),
+        "x86-active is optional"
+    );
+    assert!(!internal_x86.normal().status().optional_status().is_never());
+    let internal_bytes = link(internal, "bytes");
+    assert!(internal_bytes.dev_only(), "bytes is a dev-only dependency");
+    assert_eq!(internal_bytes.version_req().to_string(), "^0.5");
+
+    let internal_macro = package(&graph, "internal-macro");
+    assert!(
+        internal_macro.is_proc_macro(),
+        "internal-macro is a proc macro"
+    );
+    assert!(internal_macro.has_build_script());
+
+    let external = package(&graph, "external");
+    assert!(external.source().is_path(), "external is a path dependency");
+    assert!(!external.in_workspace());
+    assert_eq!(
+        external.source().to_string(),
+        "../external",
+        "path is relative to the workspace root"
+    );
+    assert!(
+        external
+            .named_features()
+            .any(|feature| feature == "side-feature"),
+        "features are read from the manifest"
+    );
+
+    let bytes = package(&graph, "bytes");
+    assert!(bytes.source().is_crates_io(), "bytes is from crates.io");
+    assert_eq!(bytes.version().to_string(), "0.5.4");
+
+    let lazy_static = package(&graph, "lazy_static");
+    let lazy_static_spin = link(lazy_static, "spin");
+    assert_eq!(lazy_static_spin.version_req().to_string(), "=0.5.2");
+
+    // The feature graph should be constructible.
+    let feature_graph = graph.feature_graph();
+    assert!(feature_graph.feature_count() > graph.package_count());
+}
+
+#[test]
+fn lockfile_missing() {
+    let workspace_root = Utf8Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../fixtures/workspace/inside-outside/external");
+    let err = PackageGraph::from_lockfile(&workspace_root)
+        .expect_err("external has no lockfile, so construction fails");
+    match err {
+        Error::LockfileError(path, _) => {
+            assert_eq!(
+                path.file_name(),
+                Some("Cargo.lock"),
+                "error is for lockfile"
+            )
+        }
+        other => panic!("unexpected error {}", other),
+    }
+}
+
+#[test]
+fn update_workspace_member_unchanged() {
+    let mut graph = inside_outside_graph();
+    let before = all_links(&graph);
+    let manifest_paths: Vec<_> = graph
+        .workspace()
+        .iter()
+        .map(|member| member.manifest_path().to_owned())
+        .collect();
+    for manifest_path in manifest_paths {
+        graph
+            .update_workspace_member(&manifest_path)
+            .unwrap_or_else(|err| panic!("updating {} failed: {}", manifest_path, err));
+    }
+    graph.verify().expect("graph verification should succeed");
+    assert_eq!(before, all_links(&graph), "links are unchanged");
+}
+
+#[test]
+fn update_workspace_member_changed() {
+    let temp_dir = TempFixture::new("update_workspace_member_changed");
+    let mut graph = PackageGraph::from_lockfile(temp_dir.workspace_root())
+        .expect("graph should be constructed");
+    let feature_count = graph.feature_graph().feature_count();
+    let side_manifest = temp_dir.workspace_root().join("side/Cargo.toml");
+
+    // Replace side's dependency on external with dependencies on other packages in the graph.
+    temp_dir.write_side_dependencies(
+        r#"
+[dependencies]
+internal = { path = "../internal" }
+
+[dev-dependencies]
+static = { package = "lazy_static", version = "1" }
+
+[features]
+extra = ["internal/extra"]
+"#,
+    );
+    graph
+        .update_workspace_member(&side_manifest)
+        .expect("update succeeded");
+    graph.verify().expect("graph verification should succeed");
+
+    let side = package(&graph, "side");
+    let mut links: Vec<_> = side
+        .direct_links()
+        .map(|link| (link.dep_name(), link.resolved_name(), link.to().name()))
+        .collect();
+    links.sort_unstable();
+    assert_eq!(
+        links,
+        vec![
+            ("internal", "internal", "internal"),
+            ("static", "static", "lazy_static")
+        ],
+        "side's links are updated"
+    );
+    assert!(link(side, "lazy_static").dev_only());
+    assert!(side.named_features().any(|feature| feature == "extra"));
+    assert_eq!(
+        package(&graph, "external").reverse_direct_links().count(),
+        3,
+        "external is no longer depended on by side"
+    );
+    assert_eq!(
+        graph.feature_graph().feature_count(),
+        feature_count + 1,
+        "feature graph is recomputed"
+    );
+
+    // Dependencies that aren't in the graph can't be resolved, and leave the graph unchanged.
+    temp_dir.write_side_dependencies(
+        r#"
+[dependencies]
+serde = "1"
+"#,
+    );
+    let before = all_links(&graph);
+    let err = graph
+        .update_workspace_member(&side_manifest)
+        .expect_err("serde isn't in the graph");
+    assert!(
+        matches!(err, Error::LockfileError(..)),
+        "unexpected error {}",
+        err
+    );
+    assert_eq!(before, all_links(&graph), "graph is unchanged");
+
+    // Neither can manifests that aren't workspace members.
+    let external_manifest = temp_dir.root.join("external/Cargo.toml");
+    graph
+        .update_workspace_member(&external_manifest)
+        .expect_err("external isn't a workspace member");
+}
+
+#[test]
+fn lockfile_patches() {
+    let graph = inside_outside_graph();
+    assert!(
+        graph.patches().expect("patches read").is_empty(),
+        "original fixture has no patches"
+    );
+
+    let temp_dir = TempFixture::new("lockfile_patches");
+    temp_dir.write_package("bytes", "0.5.4", "");
+    temp_dir.write_package("lazy_static", "1.4.0", "[dependencies]\nspin = \"0.5\"\n");
+    let root_manifest = temp_dir.workspace_root().join("Cargo.toml");
+    let mut contents = fs::read_to_string(&root_manifest).expect("manifest read");
+    contents.push_str(
+        r#"
+[patch.crates-io]
+bytes = { path = "../bytes" }
+
+[replace]
+"lazy_static:1.4.0" = { path = "../lazy_static" }
+"#,
+    );
+    fs::write(&root_manifest, contents).expect("manifest written");
+    // Packages substituted in with a path don't have a source in Cargo.lock.
+    let lockfile_path = temp_dir.workspace_root().join("Cargo.lock");
+    let lockfile = fs::read_to_string(&lockfile_path).expect("lockfile read");
+    let lockfile: Vec<_> = lockfile
+        .split("\n\n")
+        .map(|entry| {
+            if entry.contains("name = \"bytes\"") || entry.contains("name = \"lazy_static\"") {
+                entry
+                    .lines()
+                    .filter(|line| !line.starts_with("source") && !line.starts_with("checksum"))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            } else {
+                entry.to_owned()
+            }
+        })
+        .collect();
+    fs::write(&lockfile_path, lockfile.join("\n\n")).expect("lockfile written");
+
+    let graph = PackageGraph::from_lockfile(temp_dir.workspace_root())
+        .expect("graph should be constructed");
+    let patches = graph.patches().expect("patches read");
+    let summary: Vec<_> = patches
+        .iter()
+        .map(|patched| {
+            (
+                patched.kind(),
+                patched.key(),
+                patched.original_source(),
+                patched.package().name(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (PatchKind::Patch, "bytes", Some("crates-io"), "bytes"),
+            (PatchKind::Replace, "lazy_static:1.4.0", None, "lazy_static"),
+        ],
+        "patched packages"
+    );
+    for patched in &patches {
+        assert_eq!(
+            patched.manifest_path(),
+            root_manifest,
+            "patches are declared in the root manifest"
+        );
+        assert!(
+            patched.package().source().is_path(),
+            "{} is substituted with a path package",
+            patched.package().name()
+        );
+        assert!(!patched.package().in_workspace());
+    }
+    assert_eq!(
+        link(package(&graph, "lazy_static"), "spin")
+            .to()
+            .version()
+            .to_string(),
+        "0.5.2",
+        "replacement's dependencies are resolved"
+    );
+}
+
+#[test]
+fn lockfile_workspace_dependencies() {
+    let temp_dir = TempFixture::new("lockfile_workspace_dependencies");
+    let root_manifest = temp_dir.workspace_root().join("Cargo.toml");
+    let mut contents = fs::read_to_string(&root_manifest).expect("manifest read");
+    contents.push_str(
+        r#"
+[workspace.dependencies]
+external = { path = "../external", features = ["side-feature"] }
+static = { package = "lazy_static", version = "1", default-features = false }
+"#,
+    );
+    fs::write(&root_manifest, contents).expect("manifest written");
+    temp_dir.write_side_dependencies(
+        r#"
+[dependencies]
+external = { workspace = true, features = ["main-dev-feature"], optional = true }
+
+[dev-dependencies]
+static = { workspace = true }
+
+[target.'cfg(unix)'.build-dependencies]
+bytes = "0.5"
+"#,
+    );
+
+    let graph = PackageGraph::from_lockfile(temp_dir.workspace_root())
+        .expect("graph should be constructed");
+    let workspace_deps = graph
+        .workspace_dependencies()
+        .expect("workspace dependencies read");
+    let summary: Vec<_> = workspace_deps
+        .iter()
+        .map(|dep| {
+            (
+                dep.dep_name(),
+                dep.package_name(),
+                dep.version_req().to_string(),
+                dep.features(),
+                dep.default_features(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (
+                "external",
+                "external",
+                "*".to_owned(),
+                &["side-feature".to_owned()][..],
+                true
+            ),
+            ("static", "lazy_static", "^1".to_owned(), &[][..], false),
+        ],
+        "workspace dependencies"
+    );
+
+    let declarations = package(&graph, "side")
+        .dependency_declarations()
+        .expect("declarations read");
+    assert_eq!(declarations.len(), 3, "all declarations returned");
+
+    let external = &declarations[0];
+    assert_eq!(external.dep_name(), "external");
+    assert_eq!(external.kind(), DependencyKind::Normal);
+    assert!(external.is_inherited(), "external is inherited");
+    assert_eq!(
+        external.workspace_dependency(),
+        Some(&workspace_deps[0]),
+        "external resolves to workspace entry"
+    );
+    assert_eq!(
+        external.features(),
+        &["side-feature".to_owned(), "main-dev-feature".to_owned()][..],
+        "features are merged"
+    );
+    assert!(external.optional(), "optionality is set by the member");
+
+    let lazy_static = &declarations[1];
+    assert_eq!(lazy_static.dep_name(), "static");
+    assert_eq!(lazy_static.package_name(), "lazy_static");
+    assert_eq!(lazy_static.kind(), DependencyKind::Development);
+    assert_eq!(lazy_static.workspace_dependency(), Some(&workspace_deps[1]));
+    assert_eq!(lazy_static.version_req().to_string(), "^1");
+    assert!(
+        !lazy_static.default_features(),
+        "default features inherited"
+    );
+
+    let bytes = &declarations[2];
+    assert_eq!(bytes.kind(), DependencyKind::Build);
+    assert_eq!(bytes.target(), Some("cfg(unix)"));
+    assert!(!bytes.is_inherited(), "bytes isn't inherited");
+    assert_eq!(bytes.workspace_dependency(), None);
+
+    package(&graph, "external")
+        .dependency_declarations()
+        .expect_err("external isn't a workspace member");
+}
+
+fn all_links(graph: &PackageGraph) -> Vec<(String, String, String, String, bool, bool, bool)> {
+    let mut links: Vec<_> = graph
+        .packages()
+        .flat_map(|package| package.direct_links())
+        .map(|link| {
+            (
+                link.from().id().to_string(),
+                link.to().id().to_string(),
+                link.dep_name().to_owned(),
+                link.version_req().to_string(),
+                link.normal().is_present(),
+                link.build().is_present(),
+                link.dev().is_present(),
+            )
+        })
+        .collect();
+    links.sort_unstable();
+    links
+}
+
+/// A copy of the inside-outside fixture in a temporary directory, so that manifests can be
+/// modified.
+#[test]
+fn lockfile_default_members() {
+    // Virtual workspaces without default-members build every member by default.
+    let graph = inside_outside_graph();
+    let mut names: Vec<_> = graph
+        .workspace()
+        .default_members()
+        .expect("default members are known for lockfile graphs")
+        .map(|package| package.name())
+        .collect();
+    names.sort_unstable();
+    assert_eq!(
+        names,
+        vec!["internal", "internal-macro", "main", "side"],
+        "all members are default members"
+    );
+
+    let temp_dir = TempFixture::new("lockfile_default_members");
+    let root_manifest = temp_dir.workspace_root().join("Cargo.toml");
+    let contents = fs::read_to_string(&root_manifest).expect("manifest read");
+    let contents = contents.replacen(
+        "members = [",
+        "default-members = [\"side\"]\nmembers = [",
+        1,
+    );
+    fs::write(&root_manifest, contents).expect("manifest written");
+
+    let graph = PackageGraph::from_lockfile(temp_dir.workspace_root())
+        .expect("graph should be constructed");
+    graph.verify().expect("graph verification should succeed");
+    let default_ids: Vec<_> = graph
+        .workspace()
+        .default_member_ids()
+        .expect("default members are known")
+        .collect();
+    assert_eq!(
+        default_ids,
+        vec![package(&graph, "side").id()],
+        "default-members is honored"
+    );
+
+    let query = graph
+        .query_workspace_default_members()
+        .expect("default members are known");
+    let names: Vec<_> = query
+        .resolve()
+        .packages(DependencyDirection::Forward)
+        .map(|package| package.name())
+        .collect();
+    assert!(names.contains(&"side"), "side is in the query");
+    assert!(!names.contains(&"main"), "main isn't in the query");
+}
+
+#[test]
+fn lockfile_diff() {
+    let graph = inside_outside_graph();
+    let new =
+        fs::read_to_string(graph.workspace().root().join("Cargo.lock")).expect("Cargo.lock read");
+
+    let diff = graph
+        .diff_lockfiles(&new, &new)
+        .expect("lockfiles are parsed");
+    assert!(diff.is_unchanged(), "identical lockfiles are unchanged");
+
+    // In the old lockfile, lazy_static is at an older version and spin isn't present, and side
+    // depends on a package that's since been removed.
+    let old = new
+        .split("\n\n")
+        .filter(|entry| !entry.contains("name = \"spin\""))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+        .replace(
+            "name = \"lazy_static\"\nversion = \"1.4.0\"",
+            "name = \"lazy_static\"\nversion = \"1.3.0\"",
+        )
+        .replace("dependencies = [\n \"spin\",\n]\n", "")
+        .replace(
+            "name = \"side\"\nversion = \"0.1.0\"\ndependencies = [\n \"external\",\n]",
+            "name = \"side\"\nversion = \"0.1.0\"\ndependencies = [\n \"external\",\n \"old-dep\",\n]\n\n\
+             [[package]]\nname = \"old-dep\"\nversion = \"1.0.0\"\n\
+             source = \"registry+https://github.com/rust-lang/crates.io-index\"",
+        );
+    let diff = graph
+        .diff_lockfiles(&old, &new)
+        .expect("lockfiles are parsed");
+
+    let changes: Vec<_> = diff
+        .changes()
+        .map(|change| {
+            let affected: Vec<_> = change
+                .affected_members()
+                .map(|member| member.name())
+                .collect();
+            (change.name(), change.kind().clone(), affected)
+        })
+        .collect();
+    assert_eq!(
+        changes,
+        vec![
+            (
+                "lazy_static",
+                LockfileChangeKind::Updated {
+                    old: Version::new(1, 3, 0),
+                    new: Version::new(1, 4, 0),
+                },
+                vec!["internal", "main"],
+            ),
+            (
+                "old-dep",
+                LockfileChangeKind::Removed {
+                    version: Version::new(1, 0, 0),
+                },
+                vec!["side"],
+            ),
+            (
+                "spin",
+                LockfileChangeKind::Added {
+                    version: Version::new(0, 5, 2),
+                },
+                vec!["internal", "main"],
+            ),
+        ],
+        "lockfile changes"
+    );
+
+    let lazy_static = diff
+        .changes()
+        .next()
+        .and_then(|change| change.package())
+        .expect("lazy_static is in the graph");
+    assert_eq!(lazy_static.id(), package(&graph, "lazy_static").id());
+    let side = package(&graph, "side");
+    let side_changes: Vec<_> = diff
+        .changes_affecting(side)
+        .map(|change| change.name())
+        .collect();
+    assert_eq!(side_changes, vec!["old-dep"], "changes affecting side");
+
+    assert!(
+        graph.diff_lockfiles("not toml [", &new).is_err(),
+        "invalid lockfile is an error"
+    );
+}
+
+struct TempFixture {
+    root: Utf8PathBuf,
+}
+
+impl TempFixture {
+    fn new(name: &str) -> Self {
+        let src =
+            Utf8Path::new(env!("CARGO_MANIFEST_DIR")).join("../fixtures/workspace/inside-outside");
+        let root = Utf8PathBuf::try_from(std::env::temp_dir())
+            .expect("temp dir is valid UTF-8")
+            .join(format!("guppy-{}-{}", name, std::process::id()));
+        if root.exists() {
+            fs::remove_dir_all(&root).expect("stale temp dir removed");
+        }
+        copy_dir(&src, &root);
+        Self { root }
+    }
+
+    fn workspace_root(&self) -> Utf8PathBuf {
+        self.root.join("workspace")
+    }
+
+    /// Writes out a package next to the workspace.
+    fn write_package(&self, name: &str, version: &str, dependencies: &str) {
+        let dir = self.root.join(name);
+        fs::create_dir_all(dir.join("src")).expect("directory created");
+        let contents = format!(
+            "[package]\nname = \"{}\"\nversion = \"{}\"\nedition = \"2018\"\n{}",
+            name, version, dependencies
+        );
+        fs::write(dir.join("Cargo.toml"), contents).expect("manifest written");
+        fs::write(dir.join("src/lib.rs"), "").expect("library written");
+    }
+
+    fn write_side_dependencies(&self, dependencies: &str) {
+        let contents = format!(
+            "[package]\nname = \"side\"\nversion = \"0.1.0\"\nedition = \"2018\"\n{}",
+            dependencies
+        );
+        fs::write(self.workspace_root().join("side/Cargo.toml"), contents)
+            .expect("manifest written");
+    }
+}
+
+impl Drop for TempFixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+fn copy_dir(src: &Utf8Path, dest: &Utf8Path) {
+    fs::create_dir_all(dest).expect("directory created");
+    for entry in src.read_dir_utf8().expect("directory read") {
+        let entry = entry.expect("directory entry read");
+        let dest = dest.join(entry.file_name());
+        if entry.path().is_dir() {
+            copy_dir(entry.path(), &dest);
+        } else {
+            fs::copy(entry.path(), &dest).expect("file copied");
+        }
+    }
+}
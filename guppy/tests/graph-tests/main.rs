@@ -12,7 +12,19 @@ macro_rules! proptest_suite {
     };
 }
 
+#[cfg(feature = "advisories")]
+mod advisories_tests;
+#[cfg(feature = "binary")]
+mod binary_tests;
+#[cfg(feature = "cyclonedx")]
+mod cyclonedx_tests;
 mod feature_helpers;
 mod graph_tests;
 mod invalid_tests;
+#[cfg(feature = "lockfile")]
+mod lockfile_tests;
+#[cfg(feature = "sparse-index")]
+mod sparse_index_tests;
+#[cfg(feature = "spdx")]
+mod spdx_tests;
 mod weak_namespaced;
@@ -478,6 +478,7 @@ fn resolve_ops_impl<G: GraphAssert<'static>>(
         ResolveTree::Union(a, b) => {
             let (resolve_a, hashset_a) = resolve_ops_impl(graph, a);
             let (resolve_b, hashset_b) = resolve_ops_impl(graph, b);
+            assert_set_relations(&resolve_a, &resolve_b, &hashset_a, &hashset_b);
             (
                 resolve_a.union(&resolve_b),
                 hashset_a.union(&hashset_b).copied().collect(),
@@ -486,6 +487,7 @@ fn resolve_ops_impl<G: GraphAssert<'static>>(
         ResolveTree::Intersection(a, b) => {
             let (resolve_a, hashset_a) = resolve_ops_impl(graph, a);
             let (resolve_b, hashset_b) = resolve_ops_impl(graph, b);
+            assert_set_relations(&resolve_a, &resolve_b, &hashset_a, &hashset_b);
             (
                 resolve_a.intersection(&resolve_b),
                 hashset_a.intersection(&hashset_b).copied().collect(),
@@ -494,6 +496,7 @@ fn resolve_ops_impl<G: GraphAssert<'static>>(
         ResolveTree::Difference(a, b) => {
             let (resolve_a, hashset_a) = resolve_ops_impl(graph, a);
             let (resolve_b, hashset_b) = resolve_ops_impl(graph, b);
+            assert_set_relations(&resolve_a, &resolve_b, &hashset_a, &hashset_b);
             (
                 resolve_a.difference(&resolve_b),
                 hashset_a.difference(&hashset_b).copied().collect(),
@@ -502,6 +505,7 @@ fn resolve_ops_impl<G: GraphAssert<'static>>(
         ResolveTree::SymmetricDifference(a, b) => {
             let (resolve_a, hashset_a) = resolve_ops_impl(graph, a);
             let (resolve_b, hashset_b) = resolve_ops_impl(graph, b);
+            assert_set_relations(&resolve_a, &resolve_b, &hashset_a, &hashset_b);
             (
                 resolve_a.symmetric_difference(&resolve_b),
                 hashset_a
@@ -513,6 +517,29 @@ fn resolve_ops_impl<G: GraphAssert<'static>>(
     }
 }
 
+fn assert_set_relations<'g, S: GraphSet<'g>>(
+    resolve_a: &S,
+    resolve_b: &S,
+    hashset_a: &HashSet<S::Id>,
+    hashset_b: &HashSet<S::Id>,
+) {
+    assert_eq!(
+        resolve_a.is_subset(resolve_b),
+        hashset_a.is_subset(hashset_b),
+        "is_subset on resolve and hashset match"
+    );
+    assert_eq!(
+        resolve_a.is_superset(resolve_b),
+        hashset_a.is_superset(hashset_b),
+        "is_superset on resolve and hashset match"
+    );
+    assert_eq!(
+        resolve_a.is_disjoint(resolve_b),
+        hashset_a.is_disjoint(hashset_b),
+        "is_disjoint on resolve and hashset match"
+    );
+}
+
 pub(super) fn package_feature_set_roundtrip(
     package_graph: &PackageGraph,
     query_ids: Vec<&PackageId>,
@@ -0,0 +1,191 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use fixtures::json::JsonFixture;
+use guppy::{
+    graph::{DependencyDirection, SparseIndex},
+    Error, Version,
+};
+use std::time::Duration;
+
+/// Returns the contents of an index file with the given (version, yanked) pairs.
+fn index_file(name: &str, versions: &[(&str, bool)]) -> String {
+    versions
+        .iter()
+        .map(|(version, yanked)| {
+            format!(
+                r#"{{"name":"{}","vers":"{}","deps":[],"cksum":"","features":{{}},"yanked":{}}}"#,
+                name, version, yanked
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn sparse_index_metadata1() {
+    let graph = JsonFixture::metadata1().graph();
+    let package_set = graph.resolve_package_name("regex").union(
+        &graph
+            .resolve_package_name("lazy_static")
+            .union(&graph.resolve_package_name("memchr")),
+    );
+
+    let mut index = SparseIndex::new("sparse+https://index.crates.io");
+    assert!(index.is_crates_io(), "URL is normalized");
+    index
+        .insert_entry(
+            "regex",
+            &index_file(
+                "regex",
+                &[("1.3.1", false), ("1.3.9", false), ("1.4.0", true)],
+            ),
+        )
+        .expect("valid index entry");
+    index
+        .insert_entry(
+            "lazy_static",
+            &index_file("lazy_static", &[("1.4.0", false)]),
+        )
+        .expect("valid index entry");
+    index
+        .insert_entry(
+            "memchr",
+            &index_file(
+                "memchr",
+                &[("2.2.1", false), ("2.7.0", false), ("3.0.0", false)],
+            ),
+        )
+        .expect("valid index entry");
+
+    let report = package_set
+        .latest_versions(&mut index)
+        .expect("index entries are cached");
+    assert!(report.not_found().is_empty(), "all packages found");
+    let version = |s: &str| Version::parse(s).expect("valid version");
+    let latest: Vec<_> = report
+        .iter()
+        .map(|latest| {
+            (
+                latest.package().name(),
+                latest.latest_compatible().cloned(),
+                latest.latest().cloned(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        latest,
+        vec![
+            (
+                "lazy_static",
+                Some(version("1.4.0")),
+                Some(version("1.4.0"))
+            ),
+            ("memchr", Some(version("2.7.0")), Some(version("3.0.0"))),
+            ("regex", Some(version("1.3.9")), Some(version("1.3.9"))),
+        ],
+        "latest versions match"
+    );
+
+    let outdated: Vec<_> = report
+        .outdated()
+        .map(|latest| (latest.package().name(), latest.has_compatible_update()))
+        .collect();
+    assert_eq!(
+        outdated,
+        vec![("memchr", true), ("regex", true)],
+        "lazy_static is up to date"
+    );
+}
+
+#[test]
+fn sparse_index_yanked() {
+    let graph = JsonFixture::metadata1().graph();
+    let mut index = SparseIndex::crates_io();
+    index
+        .insert_entry(
+            "regex",
+            &index_file("regex", &[("1.3.1", true), ("1.3.9", false)]),
+        )
+        .expect("valid index entry");
+    index
+        .insert_entry("memchr", &index_file("memchr", &[("2.2.1", false)]))
+        .expect("valid index entry");
+    // lazy_static 1.4.0 is missing from the index entry.
+    index
+        .insert_entry(
+            "lazy_static",
+            &index_file("lazy_static", &[("1.3.0", true)]),
+        )
+        .expect("valid index entry");
+
+    let package = |name: &str| {
+        graph
+            .packages()
+            .find(|package| package.name() == name)
+            .expect("package found")
+    };
+    assert_eq!(package("regex").is_yanked(&mut index).unwrap(), Some(true));
+    assert_eq!(
+        package("memchr").is_yanked(&mut index).unwrap(),
+        Some(false)
+    );
+    assert_eq!(
+        package("lazy_static").is_yanked(&mut index).unwrap(),
+        None,
+        "version not published"
+    );
+    assert_eq!(
+        package("testcrate").is_yanked(&mut index).unwrap(),
+        None,
+        "workspace members aren't looked up"
+    );
+
+    let package_set = graph.resolve_package_name("regex").union(
+        &graph
+            .resolve_package_name("lazy_static")
+            .union(&graph.resolve_package_name("memchr")),
+    );
+    let yanked: Vec<_> = package_set
+        .yanked_packages(&mut index)
+        .expect("index entries are cached")
+        .packages(DependencyDirection::Forward)
+        .map(|package| package.name())
+        .collect();
+    assert_eq!(yanked, vec!["regex"], "only regex is yanked");
+}
+
+#[test]
+fn sparse_index_other_registry() {
+    let graph = JsonFixture::metadata_alternate_registries().graph();
+    let mut index = SparseIndex::new("https://example.com/index/");
+    assert!(!index.is_crates_io());
+    let report = graph
+        .resolve_all()
+        .latest_versions(&mut index)
+        .expect("no packages are looked up");
+    assert_eq!(
+        report.iter().len(),
+        0,
+        "packages from crates.io and Git registry indexes aren't looked up"
+    );
+    assert!(report.not_found().is_empty());
+}
+
+#[test]
+fn sparse_index_custom_agent() {
+    let agent = guppy::ureq::AgentBuilder::new()
+        .timeout_connect(Duration::from_secs(5))
+        .build();
+    // Nothing listens on port 1, so fetching an entry fails quickly.
+    let mut index = SparseIndex::with_agent("sparse+http://127.0.0.1:1/index", agent);
+    assert_eq!(
+        index.url(),
+        "http://127.0.0.1:1/index/",
+        "URL is normalized"
+    );
+    assert!(
+        matches!(index.entry("regex"), Err(Error::IndexError(name, _)) if name == "regex"),
+        "fetching from an unreachable index fails"
+    );
+}
@@ -0,0 +1,130 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use fixtures::json::JsonFixture;
+use guppy::graph::SpdxExporter;
+use serde_json::Value as JsonValue;
+
+fn package<'a>(document: &'a JsonValue, name: &str) -> &'a JsonValue {
+    document["packages"]
+        .as_array()
+        .expect("packages is an array")
+        .iter()
+        .find(|package| package["name"] == name)
+        .unwrap_or_else(|| panic!("package {} found", name))
+}
+
+/// Returns (element, relationship type, related element) triples, with package names in place
+/// of SPDX identifiers.
+fn relationships(document: &JsonValue) -> Vec<(&str, &str, &str)> {
+    let name = |spdx_id: &JsonValue| -> &str {
+        if spdx_id == "SPDXRef-DOCUMENT" {
+            return "DOCUMENT";
+        }
+        document["packages"]
+            .as_array()
+            .expect("packages is an array")
+            .iter()
+            .find(|package| &package["SPDXID"] == spdx_id)
+            .expect("relationship refers to a package")["name"]
+            .as_str()
+            .expect("name is a string")
+    };
+    document["relationships"]
+        .as_array()
+        .expect("relationships is an array")
+        .iter()
+        .map(|relationship| {
+            (
+                name(&relationship["spdxElementId"]),
+                relationship["relationshipType"]
+                    .as_str()
+                    .expect("relationship type is a string"),
+                name(&relationship["relatedSpdxElement"]),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn spdx_metadata1() {
+    let graph = JsonFixture::metadata1().graph();
+    let package_set = graph.resolve_all();
+    let mut exporter = SpdxExporter::new(
+        &package_set,
+        "testcrate",
+        "https://example.com/spdx/testcrate",
+    );
+    exporter.set_created("2021-01-01T00:00:00Z");
+    let document = exporter.export();
+
+    assert_eq!(document["spdxVersion"], "SPDX-2.3");
+    assert_eq!(document["SPDXID"], "SPDXRef-DOCUMENT");
+    assert_eq!(
+        document["documentNamespace"],
+        "https://example.com/spdx/testcrate"
+    );
+    assert_eq!(document["creationInfo"]["created"], "2021-01-01T00:00:00Z");
+    assert_eq!(
+        document["packages"]
+            .as_array()
+            .expect("packages is an array")
+            .len(),
+        package_set.len(),
+        "one SPDX package per package"
+    );
+
+    let lazy_static = package(&document, "lazy_static");
+    assert_eq!(lazy_static["SPDXID"], "SPDXRef-Package-6-lazy-static-1.4.0");
+    assert_eq!(
+        lazy_static["downloadLocation"],
+        "https://crates.io/api/v1/crates/lazy_static/1.4.0/download"
+    );
+    assert_eq!(lazy_static["licenseDeclared"], "MIT OR Apache-2.0");
+    assert_eq!(
+        lazy_static["externalRefs"][0]["referenceLocator"],
+        "pkg:cargo/lazy_static@1.4.0"
+    );
+
+    let testcrate = package(&document, "testcrate");
+    assert_eq!(testcrate["downloadLocation"], "NOASSERTION");
+    assert!(
+        testcrate.get("externalRefs").is_none(),
+        "workspace members don't have purls"
+    );
+
+    let relationships = relationships(&document);
+    assert_eq!(relationships.len(), 47, "number of relationships");
+    assert_eq!(
+        relationships[0],
+        ("DOCUMENT", "DESCRIBES", "testcrate"),
+        "the document describes the workspace"
+    );
+    for expected in [
+        ("testcrate", "DEPENDS_ON", "datatest"),
+        ("datatest", "BUILD_DEPENDENCY_OF", "testcrate"),
+        ("datatest", "DEV_DEPENDENCY_OF", "testcrate"),
+        ("version_check", "BUILD_DEPENDENCY_OF", "datatest"),
+        ("serde", "DEV_DEPENDENCY_OF", "datatest"),
+    ] {
+        assert!(
+            relationships.contains(&expected),
+            "relationship {:?} found",
+            expected
+        );
+    }
+}
+
+#[test]
+fn spdx_describes_roots() {
+    let graph = JsonFixture::metadata1().graph();
+    let package_set = graph.resolve_package_name("datatest");
+    let document = SpdxExporter::new(&package_set, "datatest", "https://example.com/datatest")
+        .set_created("2021-01-01T00:00:00Z")
+        .export();
+    assert_eq!(
+        relationships(&document),
+        vec![("DOCUMENT", "DESCRIBES", "datatest")],
+        "without workspace members, packages without dependents are described"
+    );
+}
@@ -410,6 +410,37 @@ fn test_edge_upgrades() {
     }
 }
 
+#[test]
+fn unused_workspace_features() {
+    let graph = JsonFixture::metadata_weak_namespaced_features().graph();
+    let package_id = package_id(json::METADATA_WEAK_NAMESPACED_ID);
+    let unused = graph.feature_graph().unused_workspace_features();
+    let unused: Vec<_> = unused
+        .iter()
+        .map(|feature_id| {
+            assert_eq!(feature_id.package_id(), &package_id);
+            feature_id.label()
+        })
+        .collect();
+
+    for feature_name in ["bar", "baz", "pathdiff2", "upgrade1", "windows-weak"] {
+        assert!(
+            unused.contains(&FeatureLabel::Named(feature_name)),
+            "{} is unused",
+            feature_name
+        );
+    }
+    // arrayvec is enabled by bar's non-weak "arrayvec/std", foo by smallvec and smallvec by
+    // upgrade5. tinyvec only enables the optional dependency of the same name.
+    for feature_name in ["arrayvec", "foo", "smallvec", "tinyvec"] {
+        assert!(
+            !unused.contains(&FeatureLabel::Named(feature_name)),
+            "{} is not reported",
+            feature_name
+        );
+    }
+}
+
 fn feature_set_fn(named_features: &[&str]) -> FeatureSet<'static> {
     JsonFixture::metadata_weak_namespaced_features()
         .graph()
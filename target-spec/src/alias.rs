@@ -0,0 +1,242 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{Error, TargetSpec};
+use std::collections::BTreeMap;
+
+/// A set of aliases: short names that expand to target specifications.
+///
+/// `TargetSpec::new` understands a small set of builtin aliases, such as `macos`, `linux-gnu`
+/// and `windows-msvc`. For the full list, see [`TargetAliases::builtin`].
+///
+/// Aliases aren't supported by Cargo, but they're useful in configuration files that refer to an
+/// organization's standard platforms. To add custom aliases, create a `TargetAliases`, add aliases
+/// to it with [`add`](Self::add), and parse specifications with
+/// [`parse_spec`](Self::parse_spec).
+///
+/// An alias expands to a triple or a `cfg()` expression. The resulting `TargetSpec` doesn't
+/// remember the alias it was created from.
+///
+/// # Examples
+///
+/// ```
+/// use target_spec::{Platform, TargetAliases, TargetFeatures, TargetSpec};
+///
+/// // Builtin aliases are supported by TargetSpec::new.
+/// let spec = TargetSpec::new("windows-msvc").unwrap();
+/// let windows = Platform::new("x86_64-pc-windows-msvc", TargetFeatures::Unknown).unwrap();
+/// assert_eq!(spec.eval(&windows), Some(true));
+///
+/// // Custom aliases can be added.
+/// let mut aliases = TargetAliases::builtin();
+/// aliases
+///     .add("ci-linux", "x86_64-unknown-linux-gnu")
+///     .unwrap()
+///     .add("embedded", "cfg(target_os = \"none\")")
+///     .unwrap();
+/// let spec = aliases.parse_spec("ci-linux").unwrap();
+/// assert_eq!(spec, TargetSpec::new("x86_64-unknown-linux-gnu").unwrap());
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TargetAliases {
+    aliases: BTreeMap<String, TargetSpec>,
+}
+
+impl TargetAliases {
+    /// Creates a new, empty set of aliases.
+    pub fn new() -> Self {
+        Self {
+            aliases: BTreeMap::new(),
+        }
+    }
+
+    /// Creates a new set of aliases containing the builtin aliases.
+    ///
+    /// The builtin aliases are:
+    ///
+    /// | Alias          | Expansion                                          |
+    /// |----------------|----------------------------------------------------|
+    /// | `android`      | `cfg(target_os = "android")`                       |
+    /// | `freebsd`      | `cfg(target_os = "freebsd")`                       |
+    /// | `ios`          | `cfg(target_os = "ios")`                           |
+    /// | `linux`        | `cfg(target_os = "linux")`                         |
+    /// | `linux-gnu`    | `cfg(all(target_os = "linux", target_env = "gnu"))`  |
+    /// | `linux-musl`   | `cfg(all(target_os = "linux", target_env = "musl"))` |
+    /// | `macos`        | `cfg(target_os = "macos")`                         |
+    /// | `wasm`         | `cfg(target_family = "wasm")`                      |
+    /// | `windows`      | `cfg(windows)`                                     |
+    /// | `windows-gnu`  | `cfg(all(windows, target_env = "gnu"))`            |
+    /// | `windows-msvc` | `cfg(all(windows, target_env = "msvc"))`           |
+    pub fn builtin() -> Self {
+        let aliases = BUILTIN_ALIASES
+            .iter()
+            .map(|(alias, expansion)| {
+                let spec = TargetSpec::new_without_aliases((*expansion).into())
+                    .expect("builtin alias expansions are valid");
+                ((*alias).to_owned(), spec)
+            })
+            .collect();
+        Self { aliases }
+    }
+
+    /// Adds an alias that expands to the given target specification.
+    ///
+    /// The expansion is a triple or a `cfg()` expression. It can't refer to other aliases. If the alias already exists, it is replaced.
+    ///
+    /// Returns an error if the expansion couldn't be parsed.
+    pub fn add(
+        &mut self,
+        alias: impl Into<String>,
+        expansion: impl AsRef<str>,
+    ) -> Result<&mut Self, Error> {
+        let spec = TargetSpec::new_without_aliases(expansion.as_ref().to_owned().into())?;
+        self.aliases.insert(alias.into(), spec);
+        Ok(self)
+    }
+
+    /// Removes an alias, returning its expansion if it existed.
+    pub fn remove(&mut self, alias: &str) -> Option<TargetSpec> {
+        self.aliases.remove(alias)
+    }
+
+    /// Returns the target specification the given alias expands to, or `None` if it isn't an
+    /// alias.
+    pub fn get(&self, alias: &str) -> Option<&TargetSpec> {
+        self.aliases.get(alias)
+    }
+
+    /// Returns an iterator over the aliases and their expansions, sorted by alias.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&str, &TargetSpec)> {
+        self.aliases
+            .iter()
+            .map(|(alias, spec)| (alias.as_str(), spec))
+    }
+
+    /// Parses a target specification, expanding it if it is one of these aliases.
+    ///
+    /// Unlike [`TargetSpec::new`], builtin aliases are only expanded if they're part of this set.
+    pub fn parse_spec(&self, input: &str) -> Result<TargetSpec, Error> {
+        match self.get(input) {
+            Some(spec) => Ok(spec.clone()),
+            None => TargetSpec::new_without_aliases(input.to_owned().into()),
+        }
+    }
+}
+
+impl Default for TargetAliases {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the expansion for a builtin alias.
+pub(crate) fn builtin_alias_expansion(alias: &str) -> Option<&'static str> {
+    BUILTIN_ALIASES
+        .binary_search_by_key(&alias, |(alias, _)| alias)
+        .ok()
+        .map(|idx| BUILTIN_ALIASES[idx].1)
+}
+
+// This list is sorted by alias, so that it can be binary searched.
+static BUILTIN_ALIASES: &[(&str, &str)] = &[
+    ("android", r#"cfg(target_os = "android")"#),
+    ("freebsd", r#"cfg(target_os = "freebsd")"#),
+    ("ios", r#"cfg(target_os = "ios")"#),
+    ("linux", r#"cfg(target_os = "linux")"#),
+    (
+        "linux-gnu",
+        r#"cfg(all(target_os = "linux", target_env = "gnu"))"#,
+    ),
+    (
+        "linux-musl",
+        r#"cfg(all(target_os = "linux", target_env = "musl"))"#,
+    ),
+    ("macos", r#"cfg(target_os = "macos")"#),
+    ("wasm", r#"cfg(target_family = "wasm")"#),
+    ("windows", "cfg(windows)"),
+    ("windows-gnu", r#"cfg(all(windows, target_env = "gnu"))"#),
+    ("windows-msvc", r#"cfg(all(windows, target_env = "msvc"))"#),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Platform, TargetFeatures, Triple};
+
+    #[test]
+    fn test_builtin_aliases() {
+        let mut sorted = BUILTIN_ALIASES.to_vec();
+        sorted.sort_unstable();
+        assert_eq!(BUILTIN_ALIASES, &sorted[..], "builtin aliases are sorted");
+
+        for (alias, _) in BUILTIN_ALIASES {
+            assert!(
+                Triple::new(*alias).is_err(),
+                "alias {} doesn't shadow a triple",
+                alias
+            );
+        }
+        assert_eq!(TargetAliases::builtin().iter().len(), BUILTIN_ALIASES.len());
+
+        let cases = [
+            ("macos", "x86_64-apple-darwin", true),
+            ("macos", "aarch64-apple-ios", false),
+            ("linux", "x86_64-unknown-linux-musl", true),
+            ("linux-gnu", "x86_64-unknown-linux-gnu", true),
+            ("linux-gnu", "x86_64-unknown-linux-musl", false),
+            ("linux-musl", "aarch64-unknown-linux-musl", true),
+            ("windows", "i686-pc-windows-gnu", true),
+            ("windows-msvc", "x86_64-pc-windows-msvc", true),
+            ("windows-msvc", "x86_64-pc-windows-gnu", false),
+            ("wasm", "wasm32-unknown-unknown", true),
+        ];
+        for (alias, triple_str, expected) in cases {
+            let spec = TargetSpec::new(alias).expect("builtin alias is valid");
+            let platform = Platform::new(triple_str, TargetFeatures::Unknown).unwrap();
+            assert_eq!(
+                spec.eval(&platform),
+                Some(expected),
+                "for alias {} and triple {}",
+                alias,
+                triple_str
+            );
+        }
+    }
+
+    #[test]
+    fn test_custom_aliases() {
+        let mut aliases = TargetAliases::new();
+        aliases
+            .add("ci", r#"cfg(all(unix, target_arch = "x86_64"))"#)
+            .unwrap()
+            .add("macos", "aarch64-apple-darwin")
+            .unwrap();
+
+        // Custom aliases replace builtin ones.
+        assert_eq!(
+            aliases.parse_spec("macos").unwrap(),
+            TargetSpec::new("aarch64-apple-darwin").unwrap()
+        );
+        // Builtin aliases aren't expanded if they aren't part of the set.
+        assert!(aliases.parse_spec("linux").is_err());
+        // Non-aliases are parsed as usual.
+        assert_eq!(
+            aliases.parse_spec("x86_64-unknown-linux-gnu").unwrap(),
+            TargetSpec::new("x86_64-unknown-linux-gnu").unwrap()
+        );
+
+        let platform = Platform::new("x86_64-unknown-linux-gnu", TargetFeatures::Unknown).unwrap();
+        assert_eq!(
+            aliases.parse_spec("ci").unwrap().eval(&platform),
+            Some(true)
+        );
+
+        // Expansions can't refer to other aliases.
+        assert!(aliases.add("ci2", "ci").is_err());
+        assert!(aliases.add("bad", "cfg(").is_err());
+
+        assert!(aliases.remove("ci").is_some());
+        assert_eq!(aliases.get("ci"), None);
+    }
+}
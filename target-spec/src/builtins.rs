@@ -0,0 +1,411 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{Platform, TargetFeatures, TargetSpec, Triple};
+use cfg_expr::targets::{
+    get_builtin_target_by_triple, Arch, Endian, Env, Families, HasAtomics, Os, Panic, TargetInfo,
+    Triple as TripleStr, Vendor, ALL_BUILTINS,
+};
+use std::{fmt, iter::FusedIterator, slice};
+
+/// Returns an iterator over all the targets builtin to `target-spec`, in sorted order.
+///
+/// The list of builtin targets is based on the targets known to rustc at the time this version of
+/// `target-spec` was released.
+///
+/// # Examples
+///
+/// ```
+/// use target_spec::{builtin_targets, Platform, TargetFeatures, TargetSpec};
+///
+/// // Find all the builtin targets a spec matches.
+/// let spec: TargetSpec = "cfg(all(windows, target_env = \"msvc\"))".parse().unwrap();
+/// let matching: Vec<_> = builtin_targets()
+///     .filter(|target| spec.eval(&target.to_platform(TargetFeatures::Unknown)) == Some(true))
+///     .map(|target| target.triple_str())
+///     .collect();
+/// assert!(matching.contains(&"x86_64-pc-windows-msvc"));
+/// assert!(!matching.contains(&"x86_64-pc-windows-gnu"));
+/// ```
+pub fn builtin_targets() -> BuiltinTargets {
+    BuiltinTargets {
+        iter: ALL_BUILTINS.iter(),
+    }
+}
+
+/// Returns an iterator over all the builtin targets that are part of the given family, in sorted
+/// order.
+///
+/// Families are matched by `cfg(target_family = "...")`. The most common ones are `unix`,
+/// `windows` and `wasm`. A target may be part of several families, or none at all.
+///
+/// # Examples
+///
+/// ```
+/// use target_spec::builtin_targets_in_family;
+///
+/// let wasm: Vec<_> = builtin_targets_in_family("wasm")
+///     .map(|target| target.triple_str())
+///     .collect();
+/// assert!(wasm.contains(&"wasm32-unknown-unknown"));
+/// // Emscripten targets are part of both the unix and wasm families.
+/// assert!(wasm.contains(&"wasm32-unknown-emscripten"));
+/// assert!(builtin_targets_in_family("unix").any(|target| target.has_family("wasm")));
+/// ```
+pub fn builtin_targets_in_family(family: &str) -> impl Iterator<Item = BuiltinTarget> + '_ {
+    builtin_targets().filter(move |target| target.has_family(family))
+}
+
+/// An iterator over all builtin targets.
+///
+/// Returned by [`builtin_targets`].
+#[derive(Clone, Debug)]
+pub struct BuiltinTargets {
+    iter: slice::Iter<'static, TargetInfo>,
+}
+
+impl Iterator for BuiltinTargets {
+    type Item = BuiltinTarget;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(BuiltinTarget::new)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for BuiltinTargets {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(BuiltinTarget::new)
+    }
+}
+
+impl ExactSizeIterator for BuiltinTargets {}
+
+impl FusedIterator for BuiltinTargets {}
+
+/// Information about a target builtin to `target-spec`.
+///
+/// Returned by [`builtin_targets`] and [`BuiltinTarget::new_from_triple_str`].
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct BuiltinTarget {
+    info: &'static TargetInfo,
+}
+
+impl BuiltinTarget {
+    #[inline]
+    pub(crate) fn new(info: &'static TargetInfo) -> Self {
+        Self { info }
+    }
+
+    /// Looks up a builtin target by its triple string.
+    ///
+    /// Returns `None` if this triple isn't builtin to `target-spec`.
+    pub fn new_from_triple_str(triple_str: &str) -> Option<Self> {
+        builtin_target_info(triple_str).map(Self::new)
+    }
+
+    /// Returns the triple string for this target, e.g. `"x86_64-unknown-linux-gnu"`.
+    #[inline]
+    pub fn triple_str(&self) -> &'static str {
+        static_str(&self.info.triple)
+    }
+
+    /// Returns the operating system for this target, as matched by `cfg(target_os = "...")`.
+    ///
+    /// Returns `None` for targets without an operating system.
+    #[inline]
+    pub fn os(&self) -> Option<&'static str> {
+        self.info.os.as_ref().map(static_str)
+    }
+
+    /// Returns the CPU architecture for this target, as matched by `cfg(target_arch = "...")`.
+    #[inline]
+    pub fn arch(&self) -> &'static str {
+        static_str(&self.info.arch)
+    }
+
+    /// Returns the environment (typically the ABI or libc) for this target, as matched by
+    /// `cfg(target_env = "...")`.
+    ///
+    /// Returns `None` if this target doesn't specify an environment.
+    #[inline]
+    pub fn env(&self) -> Option<&'static str> {
+        self.info.env.as_ref().map(static_str)
+    }
+
+    /// Returns the vendor for this target, as matched by `cfg(target_vendor = "...")`.
+    #[inline]
+    pub fn vendor(&self) -> Option<&'static str> {
+        self.info.vendor.as_ref().map(static_str)
+    }
+
+    /// Returns the families this target is part of, as matched by `cfg(target_family = "...")`,
+    /// in sorted order.
+    ///
+    /// The `unix` and `windows` families are also matched by the bare `cfg(unix)` and
+    /// `cfg(windows)` predicates.
+    pub fn families(&self) -> impl ExactSizeIterator<Item = &'static str> {
+        self.info.families.iter().map(static_str)
+    }
+
+    /// Returns true if this target is part of the given family, as matched by
+    /// `cfg(target_family = "...")`.
+    #[inline]
+    pub fn has_family(&self, family: &str) -> bool {
+        self.families().any(|f| f == family)
+    }
+
+    /// Returns the size of a pointer in bits, as matched by `cfg(target_pointer_width = "...")`.
+    #[inline]
+    pub fn pointer_width(&self) -> u8 {
+        self.info.pointer_width
+    }
+
+    /// Returns a [`Triple`] corresponding to this target.
+    pub fn to_triple(&self) -> Triple {
+        Triple::new(self.triple_str()).expect("builtin targets are always known")
+    }
+
+    /// Returns a [`Platform`] corresponding to this target, with the given target features.
+    pub fn to_platform(&self, target_features: TargetFeatures) -> Platform {
+        Platform::from_triple(self.to_triple(), target_features)
+    }
+}
+
+impl fmt::Display for BuiltinTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.triple_str())
+    }
+}
+
+impl TargetSpec {
+    /// Returns the builtin targets this specification matches, in sorted order.
+    ///
+    /// Each builtin target is evaluated with [`TargetFeatures::Unknown`]. Targets for which the
+    /// result is unknown -- for example, because the specification depends on target features --
+    /// are included, since they might match.
+    ///
+    /// This is useful for diagnostics: a specification that matches no builtin targets is
+    /// likely to be a mistake.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use target_spec::TargetSpec;
+    ///
+    /// let spec = TargetSpec::new("cfg(all(windows, target_env = \"musl\"))").unwrap();
+    /// assert!(spec.matching_builtin_targets().is_empty());
+    ///
+    /// let spec = TargetSpec::new("cfg(all(target_os = \"linux\", target_env = \"musl\"))").unwrap();
+    /// let matching = spec.matching_builtin_targets();
+    /// assert!(matching.iter().any(|target| target.triple_str() == "x86_64-unknown-linux-musl"));
+    /// ```
+    pub fn matching_builtin_targets(&self) -> Vec<BuiltinTarget> {
+        let compiled = self.compile();
+        builtin_targets()
+            .filter(|target| {
+                compiled.eval(&target.to_platform(TargetFeatures::Unknown)) != Some(false)
+            })
+            .collect()
+    }
+}
+
+/// Looks up the builtin information for a triple, including targets newer than `cfg-expr`'s list.
+pub(crate) fn builtin_target_info(triple_str: &str) -> Option<&'static TargetInfo> {
+    get_builtin_target_by_triple(triple_str).or_else(|| {
+        NEWER_TARGETS
+            .iter()
+            .find(|target_info| target_info.triple.as_str() == triple_str)
+    })
+}
+
+/// Targets that are newer than the rustc release `cfg-expr`'s builtin list is based on.
+///
+/// These are recognized by [`Triple::new`] and [`BuiltinTarget::new_from_triple_str`], but
+/// aren't returned by [`builtin_targets`], which is a snapshot of that rustc release.
+static NEWER_TARGETS: &[TargetInfo] = &[
+    // The WASI preview 1 and 2 targets. wasm32-wasip1 is the new name for wasm32-wasi.
+    TargetInfo {
+        triple: TripleStr::new_const("wasm32-wasip1"),
+        os: Some(Os::wasi),
+        abi: None,
+        arch: Arch::wasm32,
+        env: Some(Env::new_const("p1")),
+        vendor: Some(Vendor::unknown),
+        families: Families::wasm,
+        pointer_width: 32,
+        endian: Endian::little,
+        has_atomics: HasAtomics::atomic_8_16_32_64_ptr,
+        panic: Panic::abort,
+    },
+    TargetInfo {
+        triple: TripleStr::new_const("wasm32-wasip1-threads"),
+        os: Some(Os::wasi),
+        abi: None,
+        arch: Arch::wasm32,
+        env: Some(Env::new_const("p1")),
+        vendor: Some(Vendor::unknown),
+        families: Families::wasm,
+        pointer_width: 32,
+        endian: Endian::little,
+        has_atomics: HasAtomics::atomic_8_16_32_64_ptr,
+        panic: Panic::abort,
+    },
+    TargetInfo {
+        triple: TripleStr::new_const("wasm32-wasip2"),
+        os: Some(Os::wasi),
+        abi: None,
+        arch: Arch::wasm32,
+        env: Some(Env::new_const("p2")),
+        vendor: Some(Vendor::unknown),
+        families: Families::wasm,
+        pointer_width: 32,
+        endian: Endian::little,
+        has_atomics: HasAtomics::atomic_8_16_32_64_ptr,
+        panic: Panic::abort,
+    },
+];
+
+/// `cfg-expr`'s builtin fields are `Cow<'static, str>`, but they're always borrowed in practice.
+/// This returns the `'static` string so that callers aren't tied to the lifetime of a
+/// `BuiltinTarget`.
+fn static_str(field: &'static impl AsRef<str>) -> &'static str {
+    field.as_ref()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_targets() {
+        assert_eq!(builtin_targets().len(), ALL_BUILTINS.len());
+        let triples: Vec<_> = builtin_targets()
+            .map(|target| target.triple_str())
+            .collect();
+        let mut sorted = triples.clone();
+        sorted.sort_unstable();
+        assert_eq!(
+            triples, sorted,
+            "builtin targets are returned in sorted order"
+        );
+
+        let linux = BuiltinTarget::new_from_triple_str("x86_64-unknown-linux-gnu")
+            .expect("x86_64-unknown-linux-gnu is builtin");
+        assert_eq!(linux.triple_str(), "x86_64-unknown-linux-gnu");
+        assert_eq!(linux.os(), Some("linux"));
+        assert_eq!(linux.arch(), "x86_64");
+        assert_eq!(linux.env(), Some("gnu"));
+        assert_eq!(linux.vendor(), Some("unknown"));
+        assert_eq!(linux.families().collect::<Vec<_>>(), vec!["unix"]);
+        assert_eq!(linux.pointer_width(), 64);
+        assert_eq!(linux.to_triple().as_str(), "x86_64-unknown-linux-gnu");
+
+        let wasm = BuiltinTarget::new_from_triple_str("wasm32-unknown-unknown")
+            .expect("wasm32-unknown-unknown is builtin");
+        assert_eq!(wasm.os(), Some("unknown"));
+        assert_eq!(wasm.env(), None);
+        assert_eq!(wasm.families().collect::<Vec<_>>(), vec!["wasm"]);
+        assert_eq!(wasm.pointer_width(), 32);
+
+        assert_eq!(BuiltinTarget::new_from_triple_str("x86_64-pc-darwin"), None);
+    }
+
+    #[test]
+    fn test_matching_builtin_targets() {
+        let triples = |spec: &str| -> Vec<&'static str> {
+            TargetSpec::new(spec.to_owned())
+                .unwrap()
+                .matching_builtin_targets()
+                .into_iter()
+                .map(|target| target.triple_str())
+                .collect()
+        };
+
+        assert_eq!(
+            triples("x86_64-unknown-linux-gnu"),
+            vec!["x86_64-unknown-linux-gnu"]
+        );
+        assert!(triples("cfg(all(windows, target_env = \"musl\"))").is_empty());
+        assert!(triples("cfg(any())").is_empty());
+        assert_eq!(triples("cfg(all())").len(), builtin_targets().len());
+
+        let windows_gnu = triples("cfg(all(windows, target_env = \"gnu\"))");
+        assert!(windows_gnu.contains(&"x86_64-pc-windows-gnu"));
+        assert!(!windows_gnu.contains(&"x86_64-pc-windows-msvc"));
+
+        // Targets for which the result is unknown are included.
+        assert_eq!(
+            triples("cfg(target_feature = \"sse2\")").len(),
+            builtin_targets().len()
+        );
+    }
+
+    #[test]
+    fn test_builtin_targets_in_family() {
+        for family in ["unix", "windows", "wasm"] {
+            let in_family: Vec<_> = builtin_targets_in_family(family).collect();
+            assert!(!in_family.is_empty(), "family {} has targets", family);
+            for target in builtin_targets() {
+                assert_eq!(
+                    in_family.contains(&target),
+                    target.to_triple().has_family(family),
+                    "for target {} and family {}",
+                    target,
+                    family
+                );
+            }
+        }
+
+        let windows: Vec<_> = builtin_targets_in_family("windows")
+            .map(|target| target.triple_str())
+            .collect();
+        assert!(windows.contains(&"x86_64-pc-windows-msvc"));
+        assert!(!windows.contains(&"x86_64-unknown-linux-gnu"));
+
+        assert_eq!(builtin_targets_in_family("no-such-family").count(), 0);
+    }
+
+    #[test]
+    fn test_newer_targets() {
+        for target_info in NEWER_TARGETS {
+            let triple_str = target_info.triple.as_str();
+            assert!(
+                get_builtin_target_by_triple(triple_str).is_none(),
+                "{} is not builtin to cfg-expr (remove it if it is)",
+                triple_str
+            );
+            assert!(!builtin_targets().any(|target| target.triple_str() == triple_str));
+            let target = BuiltinTarget::new_from_triple_str(triple_str).expect("target is known");
+            assert_eq!(target.to_triple().target_info(), Some(target_info));
+        }
+
+        let eval = |spec: &str, triple_str: &str| {
+            let spec: TargetSpec = spec.parse().unwrap();
+            let platform = Platform::new(triple_str.to_owned(), TargetFeatures::Unknown).unwrap();
+            spec.eval(&platform)
+        };
+        for triple_str in ["wasm32-wasip1", "wasm32-wasip1-threads", "wasm32-wasip2"] {
+            assert_eq!(eval("cfg(target_os = \"wasi\")", triple_str), Some(true));
+            assert_eq!(
+                eval("cfg(target_family = \"wasm\")", triple_str),
+                Some(true)
+            );
+            assert_eq!(eval("cfg(unix)", triple_str), Some(false));
+        }
+        assert_eq!(
+            eval("cfg(target_env = \"p2\")", "wasm32-wasip2"),
+            Some(true)
+        );
+        assert_eq!(
+            eval("cfg(target_env = \"p2\")", "wasm32-wasip1"),
+            Some(false)
+        );
+    }
+}
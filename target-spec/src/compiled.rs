@@ -0,0 +1,382 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{
+    spec::{eval_predicate, eval_target_predicate},
+    tree::ExprTree,
+    EvalMode, Platform, TargetExpression, TargetSpec,
+};
+use cfg_expr::{expr::TargetPredicate, Predicate};
+
+/// A [`TargetSpec`] prepared for repeated evaluation.
+///
+/// Created with [`TargetSpec::compile`].
+///
+/// Evaluating a `TargetSpec` walks the whole expression and evaluates every predicate in it.
+/// A `CompiledTargetSpec` is built once, and then:
+///
+/// * evaluates `all()` and `any()` lazily, stopping as soon as the result is known
+/// * evaluates each distinct predicate at most once, even if it appears several times in the
+///   expression
+///
+/// This makes it cheaper to evaluate the same spec against many platforms. Evaluation results
+/// are always the same as for the `TargetSpec` it was compiled from.
+///
+/// # Examples
+///
+/// ```
+/// use target_spec::{Platform, TargetFeatures, TargetSpec};
+///
+/// let spec: TargetSpec = "cfg(any(all(unix, target_arch = \"x86_64\"), all(unix, target_arch = \"aarch64\")))"
+///     .parse()
+///     .unwrap();
+/// let compiled = spec.compile();
+///
+/// let platforms = [
+///     ("x86_64-unknown-linux-gnu", true),
+///     ("aarch64-apple-darwin", true),
+///     ("i686-unknown-linux-gnu", false),
+///     ("x86_64-pc-windows-msvc", false),
+/// ];
+/// for (triple_str, expected) in platforms {
+///     let platform = Platform::new(triple_str, TargetFeatures::Unknown).unwrap();
+///     assert_eq!(compiled.eval(&platform), Some(expected));
+///     assert_eq!(compiled.eval(&platform), spec.eval(&platform));
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct CompiledTargetSpec {
+    spec: TargetSpec,
+    expr: Option<CompiledExpr>,
+}
+
+impl CompiledTargetSpec {
+    pub(crate) fn new(spec: &TargetSpec) -> Self {
+        let expr = match spec {
+            TargetSpec::Triple(_) => None,
+            TargetSpec::Expression(expr) => Some(CompiledExpr::new(expr)),
+        };
+        Self {
+            spec: spec.clone(),
+            expr,
+        }
+    }
+
+    /// Returns the `TargetSpec` this was compiled from.
+    #[inline]
+    pub fn spec(&self) -> &TargetSpec {
+        &self.spec
+    }
+
+    /// Evaluates this specification against the given platform.
+    ///
+    /// This returns the same result as [`TargetSpec::eval`].
+    #[inline]
+    pub fn eval(&self, platform: &Platform) -> Option<bool> {
+        self.eval_with_mode(platform, EvalMode::Cargo)
+    }
+
+    /// Evaluates this specification against the given platform, using the given evaluation mode.
+    ///
+    /// This returns the same result as [`TargetSpec::eval_with_mode`].
+    pub fn eval_with_mode(&self, platform: &Platform, mode: EvalMode) -> Option<bool> {
+        match &self.expr {
+            Some(expr) => expr.eval(platform, mode),
+            None => self.spec.eval_with_mode(platform, mode),
+        }
+    }
+
+    /// Evaluates this specification against each of the given platforms, in order.
+    ///
+    /// This returns the same results as [`TargetSpec::eval_many`].
+    pub fn eval_many<'a>(
+        &self,
+        platforms: impl IntoIterator<Item = &'a Platform>,
+    ) -> Vec<Option<bool>> {
+        platforms
+            .into_iter()
+            .map(|platform| self.eval(platform))
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct CompiledExpr {
+    tree: ExprTree,
+    // Distinct predicates. The predicate indexes in the tree point into this list.
+    predicates: Vec<OwnedPredicate>,
+}
+
+impl CompiledExpr {
+    fn new(expr: &TargetExpression) -> Self {
+        let mut predicates: Vec<OwnedPredicate> = vec![];
+        // Maps indexes in the expression's predicate list to indexes in `predicates`.
+        let remap: Vec<usize> = expr
+            .predicates()
+            .map(|pred| {
+                let pred = OwnedPredicate::new(&pred);
+                match predicates.iter().position(|existing| existing == &pred) {
+                    Some(idx) => idx,
+                    None => {
+                        predicates.push(pred);
+                        predicates.len() - 1
+                    }
+                }
+            })
+            .collect();
+
+        let tree = remap_tree(expr.tree(), &remap);
+        Self { tree, predicates }
+    }
+
+    fn eval(&self, platform: &Platform, mode: EvalMode) -> Option<bool> {
+        let mut cache = PredicateCache::default();
+        self.eval_tree(&self.tree, platform, mode, &mut cache)
+    }
+
+    fn eval_tree(
+        &self,
+        tree: &ExprTree,
+        platform: &Platform,
+        mode: EvalMode,
+        cache: &mut PredicateCache,
+    ) -> Option<bool> {
+        match tree {
+            ExprTree::Predicate(idx) => {
+                cache.get_or_insert(*idx, || match &self.predicates[*idx] {
+                    // Avoid cloning target predicates.
+                    OwnedPredicate::Target(target) => eval_target_predicate(target, platform, mode),
+                    other => eval_predicate(&other.as_predicate(), platform, mode),
+                })
+            }
+            ExprTree::Not(operand) => self.eval_tree(operand, platform, mode, cache).map(|v| !v),
+            ExprTree::All(operands) => {
+                // all() is false as soon as any operand is false.
+                let mut unknown = false;
+                for operand in operands {
+                    match self.eval_tree(operand, platform, mode, cache) {
+                        Some(true) => {}
+                        Some(false) => return Some(false),
+                        None => unknown = true,
+                    }
+                }
+                if unknown {
+                    None
+                } else {
+                    Some(true)
+                }
+            }
+            ExprTree::Any(operands) => {
+                // any() is true as soon as any operand is true.
+                let mut unknown = false;
+                for operand in operands {
+                    match self.eval_tree(operand, platform, mode, cache) {
+                        Some(true) => return Some(true),
+                        Some(false) => {}
+                        None => unknown = true,
+                    }
+                }
+                if unknown {
+                    None
+                } else {
+                    Some(false)
+                }
+            }
+        }
+    }
+}
+
+fn remap_tree(tree: ExprTree, remap: &[usize]) -> ExprTree {
+    let remap_all = |operands: Vec<ExprTree>| {
+        operands
+            .into_iter()
+            .map(|operand| remap_tree(operand, remap))
+            .collect()
+    };
+    match tree {
+        ExprTree::Predicate(idx) => ExprTree::Predicate(remap[idx]),
+        ExprTree::Not(operand) => ExprTree::Not(Box::new(remap_tree(*operand, remap))),
+        ExprTree::All(operands) => ExprTree::All(remap_all(operands)),
+        ExprTree::Any(operands) => ExprTree::Any(remap_all(operands)),
+    }
+}
+
+/// Caches the results of evaluating the first 64 distinct predicates, as bitsets.
+#[derive(Debug, Default)]
+struct PredicateCache {
+    evaluated: u64,
+    known: u64,
+    value: u64,
+}
+
+impl PredicateCache {
+    fn get_or_insert(&mut self, idx: usize, eval: impl FnOnce() -> Option<bool>) -> Option<bool> {
+        if idx >= 64 {
+            return eval();
+        }
+
+        let bit = 1 << idx;
+        if self.evaluated & bit != 0 {
+            return if self.known & bit != 0 {
+                Some(self.value & bit != 0)
+            } else {
+                None
+            };
+        }
+
+        let result = eval();
+        self.evaluated |= bit;
+        if let Some(value) = result {
+            self.known |= bit;
+            if value {
+                self.value |= bit;
+            }
+        }
+        result
+    }
+}
+
+/// An owned version of `cfg_expr::Predicate`.
+#[derive(Clone, Debug, PartialEq)]
+enum OwnedPredicate {
+    Target(TargetPredicate),
+    Test,
+    DebugAssertions,
+    ProcMacro,
+    Feature(String),
+    TargetFeature(String),
+    Flag(String),
+    KeyValue { key: String, val: String },
+}
+
+impl OwnedPredicate {
+    fn new(pred: &Predicate<'_>) -> Self {
+        match pred {
+            Predicate::Target(target) => OwnedPredicate::Target(target.clone()),
+            Predicate::Test => OwnedPredicate::Test,
+            Predicate::DebugAssertions => OwnedPredicate::DebugAssertions,
+            Predicate::ProcMacro => OwnedPredicate::ProcMacro,
+            Predicate::Feature(feature) => OwnedPredicate::Feature((*feature).to_owned()),
+            Predicate::TargetFeature(feature) => {
+                OwnedPredicate::TargetFeature((*feature).to_owned())
+            }
+            Predicate::Flag(flag) => OwnedPredicate::Flag((*flag).to_owned()),
+            Predicate::KeyValue { key, val } => OwnedPredicate::KeyValue {
+                key: (*key).to_owned(),
+                val: (*val).to_owned(),
+            },
+        }
+    }
+
+    fn as_predicate(&self) -> Predicate<'_> {
+        match self {
+            OwnedPredicate::Target(target) => Predicate::Target(target.clone()),
+            OwnedPredicate::Test => Predicate::Test,
+            OwnedPredicate::DebugAssertions => Predicate::DebugAssertions,
+            OwnedPredicate::ProcMacro => Predicate::ProcMacro,
+            OwnedPredicate::Feature(feature) => Predicate::Feature(feature),
+            OwnedPredicate::TargetFeature(feature) => Predicate::TargetFeature(feature),
+            OwnedPredicate::Flag(flag) => Predicate::Flag(flag),
+            OwnedPredicate::KeyValue { key, val } => Predicate::KeyValue { key, val },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{builtin_targets, TargetFeatures, Triple};
+
+    #[test]
+    fn test_compiled_matches_uncompiled() {
+        let specs = [
+            "x86_64-unknown-linux-gnu",
+            "aarch64-apple-darwin",
+            "cfg(unix)",
+            "cfg(all(unix, not(target_os = \"macos\")))",
+            "cfg(any(all(unix, target_arch = \"x86_64\"), all(unix, target_arch = \"aarch64\")))",
+            "cfg(any(windows, target_feature = \"sse2\", my_flag))",
+            "cfg(all(target_feature = \"neon\", not(target_feature = \"sse2\")))",
+            "cfg(all(my_key = \"value\", debug_assertions))",
+            "cfg(any(version(\"1.60\"), test, target_has_atomic = \"64\"))",
+            "cfg(all())",
+            "cfg(any())",
+        ];
+        let modes = [EvalMode::Cargo, EvalMode::Strict];
+
+        let mut platforms = vec![];
+        for target in builtin_targets() {
+            let triple = Triple::new(target.triple_str()).unwrap();
+            platforms.push(Platform::from_triple(
+                triple.clone(),
+                TargetFeatures::Unknown,
+            ));
+            platforms.push(Platform::from_triple(
+                triple.clone(),
+                TargetFeatures::none(),
+            ));
+            platforms.push(
+                Platform::builder(triple)
+                    .set_target_features(TargetFeatures::features(["sse2"].iter().copied()))
+                    .add_flag("my_flag")
+                    .add_key_value("my_key", "value")
+                    .set_rustc_version(semver::Version::new(1, 64, 0))
+                    .build(),
+            );
+        }
+
+        for spec_str in specs {
+            let spec = TargetSpec::new(spec_str).unwrap();
+            let compiled = spec.compile();
+            assert_eq!(compiled.spec(), &spec);
+            for platform in &platforms {
+                for mode in modes {
+                    assert_eq!(
+                        compiled.eval_with_mode(platform, mode),
+                        spec.eval_with_mode(platform, mode),
+                        "for spec {}, platform {}, mode {:?}",
+                        spec_str,
+                        platform.triple_str(),
+                        mode
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_eval_many() {
+        let platforms: Vec<_> = builtin_targets()
+            .map(|target| target.to_platform(TargetFeatures::Unknown))
+            .collect();
+        for spec_str in [
+            "x86_64-unknown-linux-gnu",
+            "aarch64-apple-darwin",
+            "cfg(all(unix, not(target_os = \"macos\")))",
+            "cfg(any(windows, target_feature = \"sse2\"))",
+        ] {
+            let spec = TargetSpec::new(spec_str).unwrap();
+            let expected: Vec<_> = platforms
+                .iter()
+                .map(|platform| spec.eval(platform))
+                .collect();
+            assert_eq!(
+                spec.eval_many(&platforms),
+                expected,
+                "for spec {}",
+                spec_str
+            );
+        }
+
+        let spec = TargetSpec::new("cfg(unix)").unwrap();
+        assert_eq!(spec.eval_many(&[]), Vec::<Option<bool>>::new());
+    }
+
+    #[test]
+    fn test_dedup_predicates() {
+        let spec = TargetSpec::new("cfg(any(all(unix, a), all(unix, b), not(a)))").unwrap();
+        let compiled = spec.compile();
+        let expr = compiled.expr.as_ref().expect("spec is an expression");
+        assert_eq!(expr.predicates.len(), 3, "unix and a are deduplicated");
+    }
+}
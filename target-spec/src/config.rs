@@ -0,0 +1,404 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Support for constructing platforms from Cargo configuration files.
+//!
+//! Requires the `config` feature to be enabled.
+
+use crate::{
+    errors::{CargoConfigError, CargoConfigErrorKind},
+    Error, Platform, PlatformBuilder, TargetSpec, Triple,
+};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use toml::Value;
+
+/// The parts of a [Cargo configuration file](https://doc.rust-lang.org/cargo/reference/config.html)
+/// that determine which platforms are built, and which custom cfgs are passed to rustc.
+///
+/// The keys read are:
+///
+/// * `build.target`, a triple or a list of triples.
+/// * `build.rustflags`.
+/// * `target.<triple>.rustflags` and `target.<cfg>.rustflags`.
+///
+/// Of the rustflags, only `--cfg` options are used. As with Cargo, if any `target.` rustflags
+/// apply to a platform, `build.rustflags` is ignored for it. The `RUSTFLAGS` and
+/// `CARGO_ENCODED_RUSTFLAGS` environment variables are not read.
+///
+/// Requires the `config` feature to be enabled.
+///
+/// # Examples
+///
+/// ```
+/// use target_spec::{CargoConfig, TargetSpec};
+///
+/// let config = CargoConfig::from_toml_str(r#"
+///     [build]
+///     target = "x86_64-unknown-linux-gnu"
+///
+///     [target.'cfg(unix)']
+///     rustflags = ["--cfg", "tokio_unstable"]
+/// "#).unwrap();
+///
+/// let platforms = config.platforms().unwrap();
+/// assert_eq!(platforms.len(), 1);
+/// assert_eq!(platforms[0].triple_str(), "x86_64-unknown-linux-gnu");
+///
+/// let spec: TargetSpec = "cfg(all(unix, tokio_unstable))".parse().unwrap();
+/// assert_eq!(spec.eval(&platforms[0]), Some(true));
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CargoConfig {
+    build_targets: Vec<String>,
+    build_rustflags: Option<Vec<String>>,
+    // Keys are either triples or cfg() expressions.
+    target_rustflags: BTreeMap<String, Vec<String>>,
+}
+
+impl CargoConfig {
+    /// Creates a new, empty configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a configuration from a TOML string.
+    pub fn from_toml_str(contents: &str) -> Result<Self, Error> {
+        Self::parse(contents).map_err(|kind| Error::CargoConfig(CargoConfigError::new(None, kind)))
+    }
+
+    /// Reads and parses a configuration file at the given path.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let make_err =
+            |kind| Error::CargoConfig(CargoConfigError::new(Some(path.to_path_buf()), kind));
+        let contents = fs::read_to_string(path)
+            .map_err(|err| make_err(CargoConfigErrorKind::Read(err.to_string())))?;
+        Self::parse(&contents).map_err(make_err)
+    }
+
+    /// Discovers and merges the configuration files that Cargo would read for a build in the
+    /// given directory.
+    ///
+    /// This reads `.cargo/config.toml` (or `.cargo/config`) in the given directory and each of
+    /// its ancestors. Values in directories closer to the given one take precedence, and
+    /// rustflags lists are joined together, matching Cargo. The configuration in `$CARGO_HOME`
+    /// is not read.
+    pub fn discover(dir: impl AsRef<Path>) -> Result<Self, Error> {
+        let mut config = Self::new();
+        for dir in dir.as_ref().ancestors() {
+            if let Some(path) = config_path(dir) {
+                config.merge_lower(Self::from_path(path)?);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Returns the triples listed in `build.target`.
+    pub fn build_targets(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.build_targets.iter().map(|target| target.as_str())
+    }
+
+    /// Returns the rustflags that Cargo would pass to rustc when building for this platform.
+    ///
+    /// The platform's own flags are ignored while evaluating `target.<cfg>` keys.
+    pub fn rustflags(&self, platform: &Platform) -> Vec<&str> {
+        let mut found = false;
+        let mut rustflags = vec![];
+        for (key, flags) in &self.target_rustflags {
+            let matches = if key.starts_with("cfg(") {
+                // Invalid keys are ignored. Cargo warns about them.
+                match TargetSpec::new(key.clone()) {
+                    Ok(spec) => spec.eval(platform) == Some(true),
+                    Err(_) => false,
+                }
+            } else {
+                key == platform.triple_str()
+            };
+            if matches {
+                found = true;
+                rustflags.extend(flags.iter().map(|flag| flag.as_str()));
+            }
+        }
+
+        if found {
+            rustflags
+        } else {
+            self.build_rustflags
+                .iter()
+                .flatten()
+                .map(|flag| flag.as_str())
+                .collect()
+        }
+    }
+
+    /// Returns a platform for the given triple, with the custom cfgs this configuration passes
+    /// to rustc for it.
+    ///
+    /// Target features are unknown.
+    pub fn platform(&self, triple: Triple) -> Platform {
+        let mut builder = PlatformBuilder::new(triple);
+        let base = builder.build();
+        add_cfgs(&mut builder, &self.rustflags(&base));
+        builder.build()
+    }
+
+    /// Returns the platforms listed in `build.target`, with custom cfgs applied.
+    ///
+    /// If `build.target` isn't set, returns the [current platform](Platform::current), as Cargo
+    /// builds for the host by default.
+    ///
+    /// Returns an error if any of the triples are unknown.
+    pub fn platforms(&self) -> Result<Vec<Platform>, Error> {
+        if self.build_targets.is_empty() {
+            let current = Platform::current()?;
+            let mut builder = PlatformBuilder::new(current.triple().clone());
+            builder.set_target_features(current.target_features().clone());
+            add_cfgs(&mut builder, &self.rustflags(&current));
+            return Ok(vec![builder.build()]);
+        }
+
+        self.build_targets
+            .iter()
+            .map(|target| {
+                let triple = Triple::new(target.clone()).map_err(Error::UnknownPlatformTriple)?;
+                Ok(self.platform(triple))
+            })
+            .collect()
+    }
+
+    fn parse(contents: &str) -> Result<Self, CargoConfigErrorKind> {
+        let value: Value = contents
+            .parse()
+            .map_err(|err: toml::de::Error| CargoConfigErrorKind::Parse(err.to_string()))?;
+
+        let mut config = Self::new();
+        if let Some(build) = value.get("build") {
+            if let Some(target) = build.get("target") {
+                config.build_targets = string_or_list(target, "build.target")?;
+            }
+            if let Some(rustflags) = build.get("rustflags") {
+                config.build_rustflags = Some(string_or_list(rustflags, "build.rustflags")?);
+            }
+        }
+
+        if let Some(targets) = value.get("target") {
+            let targets = targets
+                .as_table()
+                .ok_or_else(|| invalid_value("target", "a table"))?;
+            for (key, target) in targets {
+                if let Some(rustflags) = target.get("rustflags") {
+                    let rustflags =
+                        string_or_list(rustflags, &format!("target.{}.rustflags", key))?;
+                    config.target_rustflags.insert(key.clone(), rustflags);
+                }
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Merges a configuration with lower precedence into this one.
+    fn merge_lower(&mut self, lower: Self) {
+        if self.build_targets.is_empty() {
+            self.build_targets = lower.build_targets;
+        }
+        self.build_rustflags = match (self.build_rustflags.take(), lower.build_rustflags) {
+            (Some(higher), Some(mut lower)) => {
+                lower.extend(higher);
+                Some(lower)
+            }
+            (higher, lower) => higher.or(lower),
+        };
+        for (key, mut lower) in lower.target_rustflags {
+            if let Some(higher) = self.target_rustflags.remove(&key) {
+                lower.extend(higher);
+            }
+            self.target_rustflags.insert(key, lower);
+        }
+    }
+}
+
+fn config_path(dir: &Path) -> Option<PathBuf> {
+    let cargo_dir = dir.join(".cargo");
+    // Cargo prefers config without an extension if both exist.
+    ["config", "config.toml"]
+        .iter()
+        .map(|name| cargo_dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Parses a value that is either a whitespace-separated string or a list of strings.
+fn string_or_list(value: &Value, key: &str) -> Result<Vec<String>, CargoConfigErrorKind> {
+    const EXPECTED: &str = "a string or a list of strings";
+    match value {
+        Value::String(s) => Ok(s.split_whitespace().map(|s| s.to_owned()).collect()),
+        Value::Array(values) => values
+            .iter()
+            .map(|value| {
+                value
+                    .as_str()
+                    .map(|s| s.to_owned())
+                    .ok_or_else(|| invalid_value(key, EXPECTED))
+            })
+            .collect(),
+        _ => Err(invalid_value(key, EXPECTED)),
+    }
+}
+
+fn invalid_value(key: &str, expected: &'static str) -> CargoConfigErrorKind {
+    CargoConfigErrorKind::InvalidValue {
+        key: key.to_owned(),
+        expected,
+    }
+}
+
+/// Adds the `--cfg` options in these rustflags to the builder.
+fn add_cfgs(builder: &mut PlatformBuilder, rustflags: &[&str]) {
+    let mut iter = rustflags.iter();
+    while let Some(flag) = iter.next() {
+        let cfg = if *flag == "--cfg" {
+            match iter.next() {
+                Some(cfg) => *cfg,
+                None => break,
+            }
+        } else if let Some(cfg) = flag.strip_prefix("--cfg=") {
+            cfg
+        } else {
+            continue;
+        };
+
+        match cfg.split_once('=') {
+            Some((key, value)) => {
+                builder.add_key_value(
+                    key.trim().to_owned(),
+                    value.trim().trim_matches('"').to_owned(),
+                );
+            }
+            None => {
+                builder.add_flag(cfg.trim().to_owned());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TargetFeatures;
+
+    #[test]
+    fn test_parse() {
+        let config = CargoConfig::from_toml_str(
+            r#"
+            [build]
+            target = ["x86_64-unknown-linux-gnu", "x86_64-pc-windows-msvc"]
+            rustflags = "--cfg build_flag -C opt-level=2"
+
+            [target.x86_64-pc-windows-msvc]
+            rustflags = ["--cfg=windows_flag", "--cfg", 'mode="windows"']
+
+            [target.'cfg(unix)']
+            rustflags = ["--cfg", "unix_flag"]
+
+            [target.'cfg(target_os = "linux")']
+            rustflags = ["--cfg", 'mode="linux"']
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.build_targets().collect::<Vec<_>>(),
+            ["x86_64-unknown-linux-gnu", "x86_64-pc-windows-msvc"]
+        );
+
+        let platforms = config.platforms().unwrap();
+        assert_eq!(platforms.len(), 2);
+
+        let linux = &platforms[0];
+        assert_eq!(linux.triple_str(), "x86_64-unknown-linux-gnu");
+        assert!(linux.has_flag("unix_flag"));
+        assert!(linux.has_key_value("mode", "linux"));
+        assert!(
+            !linux.has_flag("build_flag"),
+            "build.rustflags is ignored if target rustflags apply"
+        );
+
+        let windows = &platforms[1];
+        assert_eq!(windows.triple_str(), "x86_64-pc-windows-msvc");
+        assert!(windows.has_flag("windows_flag"));
+        assert!(windows.has_key_value("mode", "windows"));
+        assert!(!windows.has_flag("unix_flag"));
+
+        // build.rustflags is used if no target rustflags apply.
+        let wasm = config.platform(Triple::new("wasm32-unknown-unknown").unwrap());
+        assert_eq!(wasm.flags().collect::<Vec<_>>(), ["build_flag"]);
+        assert_eq!(wasm.target_features(), &TargetFeatures::Unknown);
+    }
+
+    #[test]
+    fn test_default_target() {
+        let config = CargoConfig::new();
+        let platforms = config.platforms().unwrap();
+        assert_eq!(platforms.len(), 1);
+        assert_eq!(
+            platforms[0].triple_str(),
+            Platform::current().unwrap().triple_str()
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        let err = CargoConfig::from_toml_str("[build]\ntarget = 1").unwrap_err();
+        match err {
+            Error::CargoConfig(err) => assert_eq!(
+                err.kind(),
+                &CargoConfigErrorKind::InvalidValue {
+                    key: "build.target".to_owned(),
+                    expected: "a string or a list of strings",
+                }
+            ),
+            other => panic!("unexpected error: {}", other),
+        }
+
+        let err = CargoConfig::from_toml_str("[build").unwrap_err();
+        assert!(matches!(err, Error::CargoConfig(_)));
+
+        let config = CargoConfig::from_toml_str("[build]\ntarget = \"not-a-triple\"").unwrap();
+        assert!(matches!(
+            config.platforms(),
+            Err(Error::UnknownPlatformTriple(_))
+        ));
+    }
+
+    #[test]
+    fn test_discover() {
+        let dir = std::env::temp_dir().join(format!("target-spec-config-{}", std::process::id()));
+        let inner = dir.join("inner");
+        fs::create_dir_all(dir.join(".cargo")).unwrap();
+        fs::create_dir_all(inner.join(".cargo")).unwrap();
+        fs::write(
+            dir.join(".cargo/config.toml"),
+            "[build]\ntarget = \"x86_64-unknown-linux-gnu\"\nrustflags = [\"--cfg\", \"outer\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            inner.join(".cargo/config.toml"),
+            "[build]\ntarget = \"aarch64-unknown-linux-gnu\"\nrustflags = [\"--cfg\", \"inner\"]\n",
+        )
+        .unwrap();
+
+        let config = CargoConfig::discover(&inner).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            config.build_targets().collect::<Vec<_>>(),
+            ["aarch64-unknown-linux-gnu"]
+        );
+        let platforms = config.platforms().unwrap();
+        assert_eq!(platforms[0].flags().collect::<Vec<_>>(), ["inner", "outer"]);
+    }
+}
@@ -0,0 +1,289 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Support for custom target JSON specifications.
+//!
+//! Requires the `custom` feature to be enabled.
+
+use crate::{errors::CustomTripleCreateError, Error, Platform, TargetFeatures, Triple};
+use cfg_expr::targets::{
+    Abi, Arch, Endian, Env, Families, Family, HasAtomic, HasAtomics, Os, Panic, TargetInfo, Vendor,
+};
+use serde::{Deserialize, Deserializer};
+use std::borrow::Cow;
+
+impl Triple {
+    /// Creates a new `Triple` from a triple string and a custom target JSON specification.
+    ///
+    /// The JSON is in the format accepted by rustc's `--target` option, as documented in [the
+    /// rustc book](https://doc.rust-lang.org/rustc/targets/custom.html). Only the fields that
+    /// influence `cfg()` evaluation are read, and all other fields are ignored.
+    ///
+    /// Requires the `custom` feature to be enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use target_spec::Triple;
+    ///
+    /// let json = r#"{
+    ///     "arch": "arm",
+    ///     "os": "none",
+    ///     "target-endian": "little",
+    ///     "target-pointer-width": "32",
+    ///     "max-atomic-width": 32
+    /// }"#;
+    ///
+    /// let triple = Triple::new_custom("armv7em-acme-none-eabi", json).unwrap();
+    /// assert_eq!(triple.as_str(), "armv7em-acme-none-eabi");
+    /// ```
+    pub fn new_custom(
+        triple_str: impl Into<Cow<'static, str>>,
+        json: &str,
+    ) -> Result<Self, CustomTripleCreateError> {
+        let triple_str = triple_str.into();
+        let definition: TargetDefinition = serde_json::from_str(json)
+            .map_err(|error| CustomTripleCreateError::new(triple_str.clone(), Box::new(error)))?;
+        let target_info = definition.into_target_info(triple_str)?;
+        Ok(Self::from_custom_info(target_info, Some(json.into())))
+    }
+}
+
+impl Platform {
+    /// Creates a new `Platform` from a triple string, a custom target JSON specification, and
+    /// target features.
+    ///
+    /// For more about custom target JSON specifications, see [`Triple::new_custom`].
+    ///
+    /// Requires the `custom` feature to be enabled.
+    pub fn new_custom(
+        triple_str: impl Into<Cow<'static, str>>,
+        json: &str,
+        target_features: TargetFeatures,
+    ) -> Result<Self, Error> {
+        let triple = Triple::new_custom(triple_str, json).map_err(Error::CustomTripleCreate)?;
+        Ok(Self::from_triple(triple, target_features))
+    }
+}
+
+/// The subset of a rustc target JSON specification that's relevant to `cfg()` evaluation.
+///
+/// rustc's own definition lives in `rustc_target::spec::Target`.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct TargetDefinition {
+    arch: String,
+    #[serde(default)]
+    abi: Option<String>,
+    #[serde(default)]
+    os: Option<String>,
+    #[serde(default)]
+    env: Option<String>,
+    #[serde(default)]
+    vendor: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_families")]
+    target_family: Vec<String>,
+    #[serde(deserialize_with = "deserialize_pointer_width")]
+    target_pointer_width: u8,
+    #[serde(default)]
+    target_endian: Option<String>,
+    #[serde(default)]
+    max_atomic_width: Option<u16>,
+    #[serde(default)]
+    min_atomic_width: Option<u16>,
+    #[serde(default)]
+    panic_strategy: Option<String>,
+}
+
+impl TargetDefinition {
+    fn into_target_info(
+        self,
+        triple_str: Cow<'static, str>,
+    ) -> Result<TargetInfo, CustomTripleCreateError> {
+        let endian = match self.target_endian.as_deref() {
+            None | Some("little") => Endian::little,
+            Some("big") => Endian::big,
+            Some(other) => {
+                return Err(CustomTripleCreateError::new(
+                    triple_str,
+                    format!(
+                        "unknown target-endian `{}`: expected `little` or `big`",
+                        other
+                    )
+                    .into(),
+                ));
+            }
+        };
+
+        // rustc defaults max-atomic-width to the pointer width, and min-atomic-width to 8.
+        let max_atomic_width = self
+            .max_atomic_width
+            .unwrap_or_else(|| self.target_pointer_width.into());
+        let min_atomic_width = self.min_atomic_width.unwrap_or(8);
+        let mut has_atomics: Vec<_> = [8, 16, 32, 64, 128]
+            .into_iter()
+            .filter(|&size| size >= min_atomic_width && size <= max_atomic_width)
+            .map(HasAtomic::IntegerSize)
+            .collect();
+        if max_atomic_width >= self.target_pointer_width.into() {
+            has_atomics.push(HasAtomic::Pointer);
+        }
+
+        // An unset or empty string is how rustc represents a missing field.
+        let non_empty = |s: Option<String>| s.filter(|s| !s.is_empty());
+
+        Ok(TargetInfo {
+            triple: cfg_expr::targets::Triple::new(triple_str),
+            // rustc defaults the OS to "none".
+            os: Some(Os::new(
+                non_empty(self.os).unwrap_or_else(|| "none".to_owned()),
+            )),
+            abi: non_empty(self.abi).map(Abi::new),
+            arch: Arch::new(self.arch),
+            env: non_empty(self.env).map(Env::new),
+            vendor: Some(Vendor::new(
+                non_empty(self.vendor).unwrap_or_else(|| "unknown".to_owned()),
+            )),
+            families: Families::new(self.target_family.into_iter().map(Family::new)),
+            pointer_width: self.target_pointer_width,
+            endian,
+            has_atomics: HasAtomics::new(has_atomics),
+            panic: Panic::new(self.panic_strategy.unwrap_or_else(|| "unwind".to_owned())),
+        })
+    }
+}
+
+/// Older versions of rustc accept a single string for `target-family`, while newer versions
+/// accept a list.
+fn deserialize_families<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Families {
+        Single(String),
+        List(Vec<String>),
+    }
+
+    match Families::deserialize(deserializer)? {
+        Families::Single(family) => Ok(vec![family]),
+        Families::List(families) => Ok(families),
+    }
+}
+
+/// Older versions of rustc represent `target-pointer-width` as a string, while newer versions
+/// use an integer.
+fn deserialize_pointer_width<'de, D>(deserializer: D) -> Result<u8, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum PointerWidth {
+        String(String),
+        Integer(u8),
+    }
+
+    match PointerWidth::deserialize(deserializer)? {
+        PointerWidth::String(width) => width
+            .parse()
+            .map_err(|_| D::Error::custom(format!("invalid target-pointer-width: {}", width))),
+        PointerWidth::Integer(width) => Ok(width),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TargetSpec;
+
+    static ARMV7EM_JSON: &str = r#"{
+        "arch": "arm",
+        "data-layout": "e-m:e-p:32:32-Fi8-i64:64-v128:64:128-a:0:32-n32-S64",
+        "llvm-target": "thumbv7em-none-eabi",
+        "os": "none",
+        "target-endian": "little",
+        "target-pointer-width": "32",
+        "max-atomic-width": 32,
+        "panic-strategy": "abort"
+    }"#;
+
+    static ACMEOS_JSON: &str = r#"{
+        "arch": "x86_64",
+        "os": "acmeos",
+        "env": "acme",
+        "vendor": "acme",
+        "target-family": ["unix"],
+        "target-endian": "little",
+        "target-pointer-width": 64
+    }"#;
+
+    #[test]
+    fn test_custom_eval() {
+        let platform = Platform::new_custom(
+            "armv7em-acme-none-eabi",
+            ARMV7EM_JSON,
+            TargetFeatures::Unknown,
+        )
+        .expect("custom platform is valid");
+        assert_eq!(platform.triple_str(), "armv7em-acme-none-eabi");
+
+        let expected = [
+            ("cfg(target_arch = \"arm\")", true),
+            ("cfg(target_os = \"none\")", true),
+            ("cfg(target_vendor = \"unknown\")", true),
+            ("cfg(target_env = \"\")", true),
+            ("cfg(target_pointer_width = \"32\")", true),
+            ("cfg(target_endian = \"little\")", true),
+            ("cfg(target_has_atomic = \"32\")", true),
+            ("cfg(target_has_atomic = \"64\")", false),
+            ("cfg(target_has_atomic = \"ptr\")", true),
+            ("cfg(panic = \"abort\")", true),
+            ("cfg(unix)", false),
+            ("cfg(windows)", false),
+            ("thumbv7em-none-eabi", false),
+        ];
+        for (spec, result) in expected {
+            let spec: TargetSpec = spec.parse().expect("spec is valid");
+            assert_eq!(spec.eval(&platform), Some(result), "for spec {:?}", spec);
+        }
+    }
+
+    #[test]
+    fn test_custom_families() {
+        let platform =
+            Platform::new_custom("x86_64-acme-acmeos", ACMEOS_JSON, TargetFeatures::none())
+                .expect("custom platform is valid");
+
+        let expected = [
+            ("cfg(unix)", true),
+            ("cfg(target_family = \"unix\")", true),
+            ("cfg(target_os = \"acmeos\")", true),
+            ("cfg(target_env = \"acme\")", true),
+            ("cfg(target_vendor = \"acme\")", true),
+            ("cfg(target_pointer_width = \"64\")", true),
+            ("cfg(target_has_atomic = \"64\")", true),
+            ("cfg(panic = \"unwind\")", true),
+        ];
+        for (spec, result) in expected {
+            let spec: TargetSpec = spec.parse().expect("spec is valid");
+            assert_eq!(spec.eval(&platform), Some(result), "for spec {:?}", spec);
+        }
+    }
+
+    #[test]
+    fn test_custom_invalid() {
+        let err = Triple::new_custom("bogus", "{}").expect_err("missing arch");
+        assert_eq!(err.triple_str(), "bogus");
+
+        let err = Triple::new_custom(
+            "bogus",
+            r#"{ "arch": "x86_64", "target-pointer-width": "64", "target-endian": "middle" }"#,
+        )
+        .expect_err("invalid endian");
+        assert_eq!(err.triple_str(), "bogus");
+    }
+}
@@ -3,7 +3,8 @@
 
 //! Errors returned by `target-spec`.
 
-use std::{borrow::Cow, error, fmt};
+use crate::suggest::best_match;
+use std::{borrow::Cow, error, fmt, path::PathBuf, sync::Arc};
 
 /// An error that happened during `target-spec` parsing or evaluation.
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -15,6 +16,12 @@ pub enum Error {
     UnknownTargetTriple(TripleParseError),
     /// The provided platform triple was unknown.
     UnknownPlatformTriple(TripleParseError),
+    /// An error occurred while creating a custom target from a target JSON specification.
+    CustomTripleCreate(CustomTripleCreateError),
+    /// An error occurred while running `rustc --print cfg` or `rustc -vV`.
+    RustcCfg(RustcCfgError),
+    /// An error occurred while reading or parsing a Cargo configuration file.
+    CargoConfig(CargoConfigError),
     /// The provided `cfg()` expression parsed correctly, but it had an unknown predicate.
     ///
     /// This is no longer used, but is kept for backwards compatibility.
@@ -31,6 +38,9 @@ impl fmt::Display for Error {
             Error::UnknownPlatformTriple(_) => {
                 write!(f, "unknown platform triple")
             }
+            Error::CustomTripleCreate(_) => write!(f, "error creating custom target"),
+            Error::RustcCfg(_) => write!(f, "error querying rustc"),
+            Error::CargoConfig(_) => write!(f, "error reading Cargo configuration"),
             #[allow(deprecated)]
             Error::UnknownPredicate(pred) => {
                 write!(f, "cfg() expression has unknown predicate: {}", pred)
@@ -45,6 +55,9 @@ impl error::Error for Error {
             Error::InvalidExpression(err) => Some(err),
             Error::UnknownTargetTriple(err) => Some(err),
             Error::UnknownPlatformTriple(err) => Some(err),
+            Error::CustomTripleCreate(err) => Some(err),
+            Error::RustcCfg(err) => Some(err),
+            Error::CargoConfig(err) => Some(err),
             #[allow(deprecated)]
             Error::UnknownPredicate(_) => None,
         }
@@ -67,6 +80,13 @@ pub struct ExpressionParseError {
 
     /// The kind of error that occurred.
     pub kind: ExpressionParseErrorKind,
+
+    /// The term that was most likely intended, if the error looks like it was caused by a
+    /// misspelling.
+    ///
+    /// For example, for `cfg(target_oss = "linux")` this is `target_os`, and for
+    /// `cfg(nott(unix))` this is `not`.
+    pub suggestion: Option<&'static str>,
 }
 
 impl ExpressionParseError {
@@ -78,14 +98,43 @@ impl ExpressionParseError {
         } else {
             error.span
         };
+        let kind = ExpressionParseErrorKind::from_cfg_expr(error.reason);
+        let suggestion = suggest_term(input, &span, &kind);
         Self {
             input: input.to_owned(),
             span,
-            kind: ExpressionParseErrorKind::from_cfg_expr(error.reason),
+            kind,
+            suggestion,
         }
     }
 }
 
+/// Suggests a replacement for a misspelled term in a `cfg()` expression.
+fn suggest_term(
+    input: &str,
+    span: &std::ops::Range<usize>,
+    kind: &ExpressionParseErrorKind,
+) -> Option<&'static str> {
+    static FUNCTIONS: &[&str] = &["all", "any", "not", "version"];
+
+    let expected = match kind {
+        ExpressionParseErrorKind::Unexpected { expected } => expected,
+        _ => return None,
+    };
+    let term = input.get(span.clone())?;
+    if term == "(" {
+        // An unknown identifier followed by `(` was most likely meant to be a function.
+        let before = input[..span.start].trim_end();
+        let start = before
+            .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .map_or(0, |idx| idx + 1);
+        best_match(&before[start..], FUNCTIONS.iter().copied())
+    } else {
+        // Otherwise, compare against the terms cfg-expr expected here, such as `target_os`.
+        best_match(term, expected.iter().copied())
+    }
+}
+
 impl fmt::Display for ExpressionParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "error parsing cfg() expression")
@@ -183,6 +232,65 @@ impl fmt::Display for ExpressionParseErrorKind {
     }
 }
 
+#[cfg(feature = "miette5")]
+mod miette_impls {
+    use super::*;
+    use miette::{Diagnostic, LabeledSpan, SourceCode};
+
+    /// Requires the `miette5` feature to be enabled.
+    impl Diagnostic for Error {
+        fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+            let code = match self {
+                Error::InvalidExpression(_) => "target_spec::invalid_expression",
+                Error::UnknownTargetTriple(_) => "target_spec::unknown_target_triple",
+                Error::UnknownPlatformTriple(_) => "target_spec::unknown_platform_triple",
+                Error::CustomTripleCreate(_) => "target_spec::custom_triple_create",
+                Error::RustcCfg(_) => "target_spec::rustc_cfg",
+                Error::CargoConfig(_) => "target_spec::cargo_config",
+                #[allow(deprecated)]
+                Error::UnknownPredicate(_) => "target_spec::unknown_predicate",
+            };
+            Some(Box::new(code))
+        }
+
+        fn diagnostic_source(&self) -> Option<&dyn Diagnostic> {
+            match self {
+                Error::InvalidExpression(err) => Some(err),
+                _ => None,
+            }
+        }
+    }
+
+    /// The expression is used as the source code, and the span of the error is labeled with
+    /// its kind.
+    ///
+    /// Requires the `miette5` feature to be enabled.
+    impl Diagnostic for ExpressionParseError {
+        fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+            Some(Box::new("target_spec::invalid_expression"))
+        }
+
+        fn source_code(&self) -> Option<&dyn SourceCode> {
+            Some(&self.input)
+        }
+
+        fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+            self.suggestion
+                .map(|suggestion| -> Box<dyn fmt::Display + 'a> {
+                    Box::new(format!("did you mean `{}`?", suggestion))
+                })
+        }
+
+        fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+            let label = LabeledSpan::new_with_span(
+                Some(self.kind.to_string()),
+                (self.span.start, self.span.end - self.span.start),
+            );
+            Some(Box::new(std::iter::once(label)))
+        }
+    }
+}
+
 /// An error returned while parsing a single target.
 ///
 /// This is caused by a triple not being understood by either `cfg-expr` or `target-lexicon`.
@@ -190,6 +298,7 @@ impl fmt::Display for ExpressionParseErrorKind {
 pub struct TripleParseError {
     triple_str: Cow<'static, str>,
     lexicon_err: cfg_expr::target_lexicon::ParseError,
+    suggestion: Option<&'static str>,
 }
 
 impl TripleParseError {
@@ -197,9 +306,16 @@ impl TripleParseError {
         triple_str: Cow<'static, str>,
         lexicon_err: cfg_expr::target_lexicon::ParseError,
     ) -> Self {
+        let suggestion = best_match(
+            &triple_str,
+            cfg_expr::targets::ALL_BUILTINS
+                .iter()
+                .map(|target_info| target_info.triple.as_str()),
+        );
         Self {
             triple_str,
             lexicon_err,
+            suggestion,
         }
     }
 
@@ -207,11 +323,23 @@ impl TripleParseError {
     pub fn triple_str(&self) -> &str {
         &self.triple_str
     }
+
+    /// Returns the builtin triple that was most likely intended, if the triple string looks like
+    /// a misspelling of one.
+    ///
+    /// For example, the suggestion for `x86_64-unknown-linux-gn` is `x86_64-unknown-linux-gnu`.
+    pub fn suggestion(&self) -> Option<&'static str> {
+        self.suggestion
+    }
 }
 
 impl fmt::Display for TripleParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "unknown triple string: {}", self.triple_str)
+        write!(f, "unknown triple string: {}", self.triple_str)?;
+        if let Some(suggestion) = self.suggestion {
+            write!(f, " (did you mean `{}`?)", suggestion)?;
+        }
+        Ok(())
     }
 }
 
@@ -221,6 +349,150 @@ impl error::Error for TripleParseError {
     }
 }
 
+/// An error returned while parsing the string form of a set of target features.
+///
+/// Returned by the `FromStr` implementation for [`TargetFeatures`](crate::TargetFeatures).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TargetFeaturesParseError {
+    input: String,
+    reason: &'static str,
+}
+
+impl TargetFeaturesParseError {
+    pub(crate) fn new(input: impl Into<String>, reason: &'static str) -> Self {
+        Self {
+            input: input.into(),
+            reason,
+        }
+    }
+
+    /// Returns the string that could not be parsed.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+}
+
+impl fmt::Display for TargetFeaturesParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid target features `{}`: {}",
+            self.input, self.reason
+        )
+    }
+}
+
+impl error::Error for TargetFeaturesParseError {}
+
+/// An error returned while creating a custom target from a target JSON specification.
+///
+/// Returned by [`Triple::new_custom`](crate::Triple::new_custom).
+#[derive(Clone, Debug)]
+pub struct CustomTripleCreateError {
+    triple_str: Cow<'static, str>,
+    error: Arc<dyn error::Error + Send + Sync>,
+}
+
+impl CustomTripleCreateError {
+    #[cfg(feature = "custom")]
+    pub(crate) fn new(
+        triple_str: Cow<'static, str>,
+        error: Box<dyn error::Error + Send + Sync>,
+    ) -> Self {
+        Self {
+            triple_str,
+            error: error.into(),
+        }
+    }
+
+    /// Returns the triple string for which the custom target could not be created.
+    pub fn triple_str(&self) -> &str {
+        &self.triple_str
+    }
+}
+
+impl fmt::Display for CustomTripleCreateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "error creating custom target: {}", self.triple_str)
+    }
+}
+
+impl error::Error for CustomTripleCreateError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&*self.error)
+    }
+}
+
+// The underlying errors aren't comparable, so compare them by their string representations.
+impl PartialEq for CustomTripleCreateError {
+    fn eq(&self, other: &Self) -> bool {
+        self.triple_str == other.triple_str && self.error.to_string() == other.error.to_string()
+    }
+}
+
+impl Eq for CustomTripleCreateError {}
+
+/// An error returned while running `rustc --print cfg`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RustcCfgError {
+    kind: RustcCfgErrorKind,
+}
+
+impl RustcCfgError {
+    #[cfg(feature = "rustc")]
+    pub(crate) fn new(kind: RustcCfgErrorKind) -> Self {
+        Self { kind }
+    }
+
+    /// Returns the kind of error that occurred.
+    pub fn kind(&self) -> &RustcCfgErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for RustcCfgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            RustcCfgErrorKind::Exec(err) => write!(f, "failed to execute rustc: {}", err),
+            RustcCfgErrorKind::Failed { exit_code, stderr } => {
+                match exit_code {
+                    Some(code) => write!(f, "rustc exited with code {}", code)?,
+                    None => write!(f, "rustc terminated by a signal")?,
+                }
+                if !stderr.is_empty() {
+                    write!(f, "\n--- stderr:\n{}", stderr)?;
+                }
+                Ok(())
+            }
+            RustcCfgErrorKind::InvalidUtf8 => write!(f, "rustc produced invalid UTF-8 output"),
+            RustcCfgErrorKind::MissingHost => {
+                write!(f, "rustc -vV output did not have a host triple")
+            }
+        }
+    }
+}
+
+impl error::Error for RustcCfgError {}
+
+/// The kind of [`RustcCfgError`] that occurred.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RustcCfgErrorKind {
+    /// The rustc binary could not be executed.
+    Exec(String),
+    /// rustc exited with a failure.
+    Failed {
+        /// The exit code, or `None` if rustc was terminated by a signal.
+        exit_code: Option<i32>,
+        /// The standard error output of rustc.
+        stderr: String,
+    },
+    /// rustc produced output that wasn't valid UTF-8.
+    InvalidUtf8,
+    /// The output of `rustc -vV` didn't have a `host:` line.
+    MissingHost,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -238,4 +510,136 @@ mod tests {
         };
         assert_eq!(err.span, expected_span);
     }
+
+    #[test]
+    fn test_expression_parse_error_details() {
+        let input = "cfg(all(unix, target_arch == \"x86\"))";
+        let err = match TargetExpression::new(input).unwrap_err() {
+            Error::InvalidExpression(err) => err,
+            other => {
+                panic!("unexpected error type {other:?}");
+            }
+        };
+        assert_eq!(err.input, input);
+        // The span points to the second `=`, where a quoted string was expected.
+        assert_eq!(err.span, 27..28);
+        assert_eq!(
+            err.kind,
+            ExpressionParseErrorKind::Unexpected { expected: &["\""] }
+        );
+    }
+
+    #[test_case("cfg(target_oss = \"linux\")", Some("target_os"); "misspelled target key")]
+    #[test_case("cfg(nott(unix))", Some("not"); "misspelled function")]
+    #[test_case("cfg(all(unix, ayn(windows, macos)))", Some("any"); "nested misspelled function")]
+    #[test_case("cfg(foo(unix))", None; "unknown function")]
+    #[test_case("cfg(unix", None; "not a misspelling")]
+    fn test_expression_parse_error_suggestion(input: &str, expected: Option<&str>) {
+        let err = match TargetExpression::new(input).unwrap_err() {
+            Error::InvalidExpression(err) => err,
+            other => {
+                panic!("unexpected error type {other:?}");
+            }
+        };
+        assert_eq!(err.suggestion, expected, "for error {:?}", err);
+    }
+
+    #[test]
+    fn test_triple_parse_error_suggestion() {
+        let err = crate::Triple::new("x86_64-unknown-linux-gn").unwrap_err();
+        assert_eq!(err.suggestion(), Some("x86_64-unknown-linux-gnu"));
+        assert_eq!(
+            err.to_string(),
+            "unknown triple string: x86_64-unknown-linux-gn (did you mean `x86_64-unknown-linux-gnu`?)"
+        );
+
+        let err = crate::Triple::new("cannot-be-known").unwrap_err();
+        assert_eq!(err.suggestion(), None);
+        assert_eq!(err.to_string(), "unknown triple string: cannot-be-known");
+    }
+
+    #[cfg(feature = "miette5")]
+    #[test]
+    fn test_miette_diagnostic() {
+        use miette::Diagnostic;
+
+        let input = "cfg(all(unix, target_arch == \"x86\"))";
+        let err = TargetExpression::new(input).unwrap_err();
+        assert_eq!(
+            err.code().map(|code| code.to_string()).as_deref(),
+            Some("target_spec::invalid_expression")
+        );
+
+        let source = err
+            .diagnostic_source()
+            .expect("diagnostic source is present");
+        assert!(source.source_code().is_some(), "source code is present");
+        let labels: Vec<_> = source.labels().expect("labels are present").collect();
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels[0].offset(), 27);
+        assert_eq!(labels[0].len(), 1);
+        assert!(labels[0].label().is_some(), "label has a message");
+    }
+}
+
+/// An error that occurred while reading or parsing a Cargo configuration file.
+///
+/// Returned by [`CargoConfig`](crate::CargoConfig) methods.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CargoConfigError {
+    path: Option<PathBuf>,
+    kind: CargoConfigErrorKind,
+}
+
+impl CargoConfigError {
+    #[cfg(feature = "config")]
+    pub(crate) fn new(path: Option<PathBuf>, kind: CargoConfigErrorKind) -> Self {
+        Self { path, kind }
+    }
+
+    /// Returns the path to the configuration file, or `None` if the configuration wasn't read
+    /// from a file.
+    pub fn path(&self) -> Option<&PathBuf> {
+        self.path.as_ref()
+    }
+
+    /// Returns the kind of error that occurred.
+    pub fn kind(&self) -> &CargoConfigErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for CargoConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            CargoConfigErrorKind::Read(err) => write!(f, "failed to read file: {}", err)?,
+            CargoConfigErrorKind::Parse(err) => write!(f, "failed to parse TOML: {}", err)?,
+            CargoConfigErrorKind::InvalidValue { key, expected } => {
+                write!(f, "invalid value for `{}`: expected {}", key, expected)?
+            }
+        }
+        if let Some(path) = &self.path {
+            write!(f, " (in {})", path.display())?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for CargoConfigError {}
+
+/// The kind of [`CargoConfigError`] that occurred.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CargoConfigErrorKind {
+    /// The configuration file could not be read.
+    Read(String),
+    /// The configuration file wasn't valid TOML.
+    Parse(String),
+    /// A key had a value of the wrong type.
+    InvalidValue {
+        /// The key, for example `build.target`.
+        key: String,
+        /// A description of the expected type.
+        expected: &'static str,
+    },
 }
@@ -0,0 +1,184 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::Platform;
+use std::fmt;
+
+impl Platform {
+    /// Returns a fingerprint of this platform's contents.
+    ///
+    /// The fingerprint covers everything that affects evaluation: the triple string, target
+    /// features, flags, custom key-value cfgs, the rustc version, and the `test`,
+    /// `debug_assertions` and `proc_macro` cfgs. Two platforms with the same contents always have
+    /// the same fingerprint.
+    ///
+    /// Unlike the `Hash` implementation, the fingerprint is stable: it doesn't depend on the
+    /// version of Rust or `target-spec` it was computed with, or on the order in which flags were
+    /// added. This makes it suitable as a cache key for data that's persisted across runs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use target_spec::{Platform, TargetFeatures, Triple};
+    ///
+    /// let triple = Triple::new("x86_64-unknown-linux-gnu").unwrap();
+    /// let a = Platform::builder(triple.clone()).add_flags(["a", "b"]).build();
+    /// let b = Platform::builder(triple.clone()).add_flags(["b", "a"]).build();
+    /// assert_eq!(a.fingerprint(), b.fingerprint());
+    ///
+    /// let c = Platform::builder(triple).add_flag("a").build();
+    /// assert_ne!(a.fingerprint(), c.fingerprint());
+    /// ```
+    pub fn fingerprint(&self) -> PlatformFingerprint {
+        let mut hasher = Fnv128::new();
+        // Bump this if the encoding below changes.
+        hasher.write_field(b"target-spec-platform-v1");
+
+        hasher.write_field(self.triple_str().as_bytes());
+        hasher.write_field(self.target_features().to_string().as_bytes());
+        // Flags and key-values are stored in sorted order.
+        hasher.write_len(self.flags().len());
+        for flag in self.flags() {
+            hasher.write_field(flag.as_bytes());
+        }
+        hasher.write_len(self.key_values().len());
+        for (key, value) in self.key_values() {
+            hasher.write_field(key.as_bytes());
+            hasher.write_field(value.as_bytes());
+        }
+        match self.rustc_version() {
+            Some(version) => hasher.write_field(version.to_string().as_bytes()),
+            None => hasher.write_field(b""),
+        }
+        hasher.write_field(&[
+            self.test() as u8,
+            self.debug_assertions() as u8,
+            self.proc_macro() as u8,
+        ]);
+
+        PlatformFingerprint(hasher.finish())
+    }
+}
+
+/// A stable fingerprint of a [`Platform`].
+///
+/// Returned by [`Platform::fingerprint`]. The `Display` implementation renders the fingerprint
+/// as 32 lowercase hexadecimal digits.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct PlatformFingerprint(u128);
+
+impl PlatformFingerprint {
+    /// Returns the fingerprint as an integer.
+    #[inline]
+    pub fn as_u128(&self) -> u128 {
+        self.0
+    }
+}
+
+impl fmt::Display for PlatformFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
+/// The 128-bit FNV-1a hash. This is simple and fully specified, so its output never changes.
+struct Fnv128(u128);
+
+impl Fnv128 {
+    const OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+
+    fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u128::from(*byte);
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn write_len(&mut self, len: usize) {
+        self.write(&(len as u64).to_le_bytes());
+    }
+
+    // Fields are prefixed with their lengths, so that e.g. the flags ["ab"] and ["a", "b"] are
+    // encoded differently.
+    fn write_field(&mut self, bytes: &[u8]) {
+        self.write_len(bytes.len());
+        self.write(bytes);
+    }
+
+    fn finish(&self) -> u128 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TargetFeatures, Triple};
+
+    #[test]
+    fn test_fnv128() {
+        // Test vectors from the FNV reference implementation.
+        let hash = |input: &[u8]| {
+            let mut hasher = Fnv128::new();
+            hasher.write(input);
+            hasher.finish()
+        };
+        assert_eq!(hash(b""), 0x6c62272e07bb014262b821756295c58d);
+        assert_eq!(hash(b"a"), 0xd228cb696f1a8caf78912b704e4a8964);
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let triple = Triple::new("x86_64-unknown-linux-gnu").unwrap();
+        let base = Platform::from_triple(triple.clone(), TargetFeatures::Unknown);
+
+        let variants = [
+            Platform::from_triple(
+                Triple::new("aarch64-unknown-linux-gnu").unwrap(),
+                TargetFeatures::Unknown,
+            ),
+            Platform::from_triple(triple.clone(), TargetFeatures::none()),
+            Platform::from_triple(triple.clone(), TargetFeatures::All),
+            Platform::builder(triple.clone()).add_flag("ab").build(),
+            Platform::builder(triple.clone())
+                .add_flags(["a", "b"])
+                .build(),
+            Platform::builder(triple.clone())
+                .add_key_value("a", "b")
+                .build(),
+            Platform::builder(triple.clone())
+                .set_rustc_version(semver::Version::new(1, 70, 0))
+                .build(),
+            Platform::builder(triple).set_test(true).build(),
+        ];
+
+        let mut fingerprints = vec![base.fingerprint()];
+        fingerprints.extend(variants.iter().map(|platform| platform.fingerprint()));
+        let mut deduped = fingerprints.clone();
+        deduped.sort_unstable();
+        deduped.dedup();
+        assert_eq!(
+            fingerprints.len(),
+            deduped.len(),
+            "all fingerprints are distinct"
+        );
+
+        assert_eq!(base.fingerprint(), base.clone().fingerprint());
+        assert_eq!(base.fingerprint().to_string().len(), 32);
+    }
+
+    #[test]
+    fn test_fingerprint_stable() {
+        // These values must never change: persisted fingerprints would be invalidated.
+        let platform = Platform::new("x86_64-unknown-linux-gnu", TargetFeatures::Unknown).unwrap();
+        assert_eq!(
+            platform.fingerprint().to_string(),
+            "0148554371cdd4ef14e63c180e1349df"
+        );
+    }
+}
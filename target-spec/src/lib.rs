@@ -34,13 +34,28 @@
 //! assert_eq!(eval("x86_64-unknown-linux-gnu", "x86_64-pc-windows-msvc"), Ok(Some(false)));
 //! ```
 //!
+//! `TargetSpec` also supports [aliases](TargetAliases) like `macos` or `linux-gnu` that expand to
+//! triples or `cfg()` expressions, and [`SpecOrPattern`] additionally supports
+//! [wildcard patterns](TriplePattern) like `x86_64-*-linux-*`. Neither is supported by Cargo.
+//!
 //! For more advanced usage, see [`Platform`] and [`TargetSpec`].
 //!
 //! ## Optional features
 //!
-//! * **`summaries`**: Adds the [`summaries`] module to enable serialization of [`Platform`] and [`TargetFeatures`].
-//! * **`proptest1`**: Enables support for property-based testing of [`Platform`] and
-//!   [`TargetFeatures`] using [`proptest`].
+//! * **`config`**: Adds support for constructing [`Platform`] instances from
+//!   [Cargo configuration files](https://doc.rust-lang.org/cargo/reference/config.html), with
+//!   `--cfg` rustflags applied, via [`CargoConfig`].
+//! * **`custom`**: Adds support for custom targets defined by a target JSON specification, via
+//!   [`Triple::new_custom`] and [`Platform::new_custom`].
+//! * **`rustc`**: Adds support for constructing [`Platform`] instances with accurate target features
+//!   by [querying rustc](Platform::from_rustc).
+//! * **`miette5`**: Implements [`miette::Diagnostic`](https://docs.rs/miette/5) for [`Error`] and
+//!   [`errors::ExpressionParseError`], so that parse errors can be rendered with the offending
+//!   part of the expression underlined.
+//! * **`summaries`**: Adds the [`summaries`] module to enable serialization of [`Platform`] and [`TargetFeatures`],
+//!   and implements `Serialize` and `Deserialize` for [`Platform`] and [`TargetSpec`].
+//! * **`proptest1`**: Enables support for property-based testing of [`Platform`],
+//!   [`PlatformSpec`] and [`TargetFeatures`] using [`proptest`].
 //!
 //! ## Minimum supported Rust version
 //!
@@ -60,18 +75,59 @@
 #![forbid(unsafe_code)]
 #![cfg_attr(doc_cfg, feature(doc_cfg, doc_auto_cfg))]
 
+mod alias;
+mod builtins;
+mod compiled;
+#[cfg(feature = "config")]
+mod config;
+#[cfg(feature = "custom")]
+mod custom;
 pub mod errors;
+mod fingerprint;
+mod pattern;
 mod platform;
+mod platform_spec;
 #[cfg(feature = "proptest1")]
 mod proptest_helpers;
+#[cfg(feature = "rustc")]
+mod rustc;
 mod simple_eval;
 mod spec;
+mod spec_expr;
+mod suggest;
 #[cfg(feature = "summaries")]
 pub mod summaries;
+mod target_table;
+mod tiers;
+mod trace;
+mod tree;
 mod triple;
+mod version;
 
+pub use alias::*;
+pub use builtins::*;
+pub use compiled::*;
+#[cfg(feature = "config")]
+pub use config::CargoConfig;
 pub use errors::Error;
+pub use fingerprint::*;
+pub use pattern::*;
 pub use platform::*;
+pub use platform_spec::*;
+#[cfg(feature = "rustc")]
+pub use rustc::RustcCfgCommand;
 pub use simple_eval::*;
 pub use spec::*;
+pub use spec_expr::*;
+pub use target_table::*;
+pub use tiers::*;
+pub use trace::*;
 pub use triple::*;
+
+/// The version of `target-lexicon` used by `target-spec`, re-exported for the component types
+/// returned by [`Triple`].
+pub use cfg_expr::target_lexicon;
+
+/// The version of `cfg-expr` used by `target-spec`, re-exported for conversions between
+/// [`Triple`] and [`cfg_expr::targets::TargetInfo`].
+pub use cfg_expr;
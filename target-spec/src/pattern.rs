@@ -0,0 +1,240 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{builtin_targets, BuiltinTarget, Error, Platform, TargetSpec};
+use std::{borrow::Cow, fmt, str::FromStr};
+
+/// A pattern that matches a family of target triples.
+///
+/// A pattern is a triple string with one or more `*` wildcards in it, such as `x86_64-*-linux-*`
+/// or `*-apple-darwin`. Each `*` matches any sequence of characters, including `-`.
+///
+/// Patterns aren't supported by Cargo, so [`TargetSpec`] doesn't accept them. They're useful in
+/// configuration files where writing out a full `cfg()` expression would be overkill: to accept
+/// either a pattern or a `TargetSpec` there, use [`SpecOrPattern`].
+///
+/// # Examples
+///
+/// ```
+/// use target_spec::{Platform, TargetFeatures, TriplePattern};
+///
+/// let pattern = TriplePattern::new("x86_64-*-linux-*").unwrap();
+/// let linux_gnu = Platform::new("x86_64-unknown-linux-gnu", TargetFeatures::Unknown).unwrap();
+/// let linux_musl = Platform::new("x86_64-unknown-linux-musl", TargetFeatures::Unknown).unwrap();
+/// let aarch64_linux = Platform::new("aarch64-unknown-linux-gnu", TargetFeatures::Unknown).unwrap();
+///
+/// assert!(pattern.eval(&linux_gnu));
+/// assert!(pattern.eval(&linux_musl));
+/// assert!(!pattern.eval(&aarch64_linux));
+/// ```
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct TriplePattern {
+    pattern: Cow<'static, str>,
+}
+
+impl TriplePattern {
+    /// Creates a new `TriplePattern` from a string.
+    ///
+    /// Returns `None` if the string doesn't contain any `*` wildcards.
+    pub fn new(pattern: impl Into<Cow<'static, str>>) -> Option<Self> {
+        let pattern = pattern.into();
+        if Self::is_pattern(&pattern) {
+            Some(Self { pattern })
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if the given string is a pattern, i.e. it has a `*` wildcard in it.
+    #[inline]
+    pub fn is_pattern(input: &str) -> bool {
+        input.contains('*')
+    }
+
+    /// Returns the string corresponding to this pattern.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns true if this pattern matches the given triple string.
+    pub fn matches(&self, triple_str: &str) -> bool {
+        let mut parts = self.pattern.split('*');
+        // There is always at least one '*' in the pattern, so there are at least two parts.
+        let first = parts.next().expect("at least two parts");
+        let mut rest = match triple_str.strip_prefix(first) {
+            Some(rest) => rest,
+            None => return false,
+        };
+
+        let mut parts: Vec<_> = parts.collect();
+        let last = parts.pop().expect("at least two parts");
+        for part in parts {
+            match rest.find(part) {
+                Some(idx) => rest = &rest[idx + part.len()..],
+                None => return false,
+            }
+        }
+        rest.ends_with(last)
+    }
+
+    /// Evaluates this pattern against the given platform.
+    ///
+    /// This matches the pattern against the platform's triple string, ignoring target features
+    /// and flags.
+    #[inline]
+    pub fn eval(&self, platform: &Platform) -> bool {
+        self.matches(platform.triple_str())
+    }
+
+    /// Returns an iterator over the targets builtin to `target-spec` that this pattern matches.
+    pub fn matching_builtins(&self) -> impl Iterator<Item = BuiltinTarget> + '_ {
+        builtin_targets().filter(move |target| self.matches(target.triple_str()))
+    }
+}
+
+impl fmt::Display for TriplePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.pattern)
+    }
+}
+
+/// Either a [`TargetSpec`] or a [`TriplePattern`].
+///
+/// This is meant for configuration files that accept everything a `TargetSpec` does, as well as
+/// wildcard patterns like `x86_64-*-linux-*`.
+///
+/// # Examples
+///
+/// ```
+/// use target_spec::{Platform, SpecOrPattern, TargetFeatures};
+///
+/// let mac = Platform::new("aarch64-apple-darwin", TargetFeatures::Unknown).unwrap();
+///
+/// let pattern: SpecOrPattern = "*-apple-darwin".parse().unwrap();
+/// assert!(matches!(pattern, SpecOrPattern::Pattern(_)));
+/// assert_eq!(pattern.eval(&mac), Some(true));
+///
+/// let spec: SpecOrPattern = "cfg(target_os = \"macos\")".parse().unwrap();
+/// assert!(matches!(spec, SpecOrPattern::Spec(_)));
+/// assert_eq!(spec.eval(&mac), Some(true));
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum SpecOrPattern {
+    /// A target specification, as accepted by [`TargetSpec::new`].
+    Spec(TargetSpec),
+
+    /// A pattern matching a family of triples.
+    Pattern(TriplePattern),
+}
+
+impl SpecOrPattern {
+    /// Creates a new `SpecOrPattern` from a string.
+    ///
+    /// Strings with `*` wildcards in them, other than `cfg()` expressions, are parsed as patterns.
+    /// All other strings are parsed with [`TargetSpec::new`].
+    pub fn new(input: impl Into<Cow<'static, str>>) -> Result<Self, Error> {
+        let input = input.into();
+        if !input.starts_with("cfg(") && TriplePattern::is_pattern(&input) {
+            Ok(SpecOrPattern::Pattern(TriplePattern { pattern: input }))
+        } else {
+            Ok(SpecOrPattern::Spec(TargetSpec::new(input)?))
+        }
+    }
+
+    /// Evaluates this specification or pattern against the given platform.
+    ///
+    /// Returns `Some(true)` if there's a match, `Some(false)` if there's none, or `None` if the
+    /// result of the evaluation is unknown. Patterns are always either `Some(true)` or
+    /// `Some(false)`.
+    pub fn eval(&self, platform: &Platform) -> Option<bool> {
+        match self {
+            SpecOrPattern::Spec(spec) => spec.eval(platform),
+            SpecOrPattern::Pattern(pattern) => Some(pattern.eval(platform)),
+        }
+    }
+}
+
+impl FromStr for SpecOrPattern {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Self::new(input.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TargetFeatures;
+
+    #[test]
+    fn test_matches() {
+        let cases = [
+            ("x86_64-*-linux-*", "x86_64-unknown-linux-gnu", true),
+            ("x86_64-*-linux-*", "x86_64-unknown-linux-musl", true),
+            ("x86_64-*-linux-*", "aarch64-unknown-linux-gnu", false),
+            ("x86_64-*-linux-*", "x86_64-linux-android", false),
+            ("*-apple-darwin", "aarch64-apple-darwin", true),
+            ("*-apple-darwin", "x86_64-apple-darwin", true),
+            ("*-apple-darwin", "aarch64-apple-ios", false),
+            ("*-linux-*", "x86_64-unknown-linux-gnu", true),
+            ("*-linux-*", "arm-linux-androideabi", true),
+            ("*", "x86_64-pc-windows-msvc", true),
+            ("*-windows-*", "x86_64-pc-windows-msvc", true),
+            ("*-windows-*", "x86_64-pc-windows", false),
+            ("*-*-*-musl", "x86_64-unknown-linux-musl", true),
+            ("*-*-*-musl", "x86_64-unknown-linux-gnu", false),
+            ("*musl*", "armv7-unknown-linux-musleabihf", true),
+        ];
+
+        for (pattern, triple_str, expected) in cases {
+            let pattern = TriplePattern::new(pattern).expect("valid pattern");
+            assert_eq!(
+                pattern.matches(triple_str),
+                expected,
+                "for pattern {} and triple {}",
+                pattern,
+                triple_str
+            );
+        }
+
+        assert_eq!(TriplePattern::new("x86_64-unknown-linux-gnu"), None);
+    }
+
+    #[test]
+    fn test_matching_builtins() {
+        let pattern = TriplePattern::new("*-apple-darwin").unwrap();
+        let matching: Vec<_> = pattern
+            .matching_builtins()
+            .map(|target| target.triple_str())
+            .collect();
+        assert!(matching.contains(&"aarch64-apple-darwin"));
+        assert!(matching.contains(&"x86_64-apple-darwin"));
+        assert!(!matching.contains(&"aarch64-apple-ios"));
+    }
+
+    #[test]
+    fn test_spec_or_pattern() {
+        let spec = SpecOrPattern::new("*-apple-darwin").unwrap();
+        match &spec {
+            SpecOrPattern::Pattern(pattern) => assert_eq!(pattern.as_str(), "*-apple-darwin"),
+            other => panic!("expected pattern, got {:?}", other),
+        }
+        let mac = Platform::new("aarch64-apple-darwin", TargetFeatures::Unknown).unwrap();
+        let linux = Platform::new("x86_64-unknown-linux-gnu", TargetFeatures::Unknown).unwrap();
+        assert_eq!(spec.eval(&mac), Some(true));
+        assert_eq!(spec.eval(&linux), Some(false));
+
+        for input in ["x86_64-unknown-linux-gnu", "cfg(unix)", "macos"] {
+            assert_eq!(
+                SpecOrPattern::new(input).unwrap(),
+                SpecOrPattern::Spec(TargetSpec::new(input).unwrap()),
+                "for input {}",
+                input
+            );
+        }
+        assert!(SpecOrPattern::new("cfg(*)").is_err());
+    }
+}
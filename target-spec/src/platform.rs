@@ -1,8 +1,9 @@
 // Copyright (c) The cargo-guppy Contributors
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use crate::{Error, Triple};
-use std::{borrow::Cow, collections::BTreeSet, ops::Deref};
+use crate::{errors::TargetFeaturesParseError, target_lexicon, Error, Triple};
+use semver::Version;
+use std::{borrow::Cow, collections::BTreeSet, fmt, ops::Deref, str::FromStr};
 
 // This is generated by the build script.
 include!(concat!(env!("OUT_DIR"), "/current_platform.rs"));
@@ -13,6 +14,9 @@ pub struct Platform {
     triple: Triple,
     target_features: TargetFeatures,
     flags: BTreeSet<Cow<'static, str>>,
+    key_values: BTreeSet<(Cow<'static, str>, Cow<'static, str>)>,
+    rustc_version: Option<Version>,
+    compiler_cfgs: CompilerCfgs,
 }
 
 impl Platform {
@@ -31,14 +35,14 @@ impl Platform {
     ///
     /// This will return an error if the current platform was unknown to this version of
     /// `target-spec`.
+    ///
+    /// The platform detected at build time may not match the toolchain in use, for example when
+    /// running under emulation. With the `rustc` feature enabled, `Platform::current_from_rustc`
+    /// queries rustc for the host triple instead.
     pub fn current() -> Result<Self, Error> {
         let triple = Triple::new(CURRENT_TARGET).map_err(Error::UnknownPlatformTriple)?;
         let target_features = TargetFeatures::features(CURRENT_TARGET_FEATURES.iter().copied());
-        Ok(Self {
-            triple,
-            target_features,
-            flags: BTreeSet::new(),
-        })
+        Ok(Self::from_triple(triple, target_features))
     }
 
     /// Creates a new platform from a `Triple` and target features.
@@ -47,9 +51,19 @@ impl Platform {
             triple,
             target_features,
             flags: BTreeSet::new(),
+            key_values: BTreeSet::new(),
+            rustc_version: None,
+            compiler_cfgs: CompilerCfgs::default(),
         }
     }
 
+    /// Returns a [`PlatformBuilder`] for a platform based on the given triple.
+    ///
+    /// The builder can be used to set target features and custom cfgs on the platform.
+    pub fn builder(triple: Triple) -> PlatformBuilder {
+        PlatformBuilder::new(triple)
+    }
+
     /// Adds a set of flags to accept.
     ///
     /// A flag is a single token like the `foo` in `cfg(not(foo))`.
@@ -76,6 +90,57 @@ impl Platform {
         self.flags.contains(flag.as_ref())
     }
 
+    /// Returns the set of custom `key = "value"` cfgs enabled for this platform, in sorted order.
+    ///
+    /// Custom key-value cfgs can be set through [`PlatformBuilder::add_key_value`].
+    pub fn key_values(&self) -> impl ExactSizeIterator<Item = (&str, &str)> {
+        self.key_values
+            .iter()
+            .map(|(key, value)| (key.deref(), value.deref()))
+    }
+
+    /// Returns true if this custom `key = "value"` cfg is enabled for this platform.
+    pub fn has_key_value(&self, key: impl AsRef<str>, value: impl AsRef<str>) -> bool {
+        // BTreeSet<(Cow, Cow)> can't be looked up by (&str, &str), so do a linear scan. There are
+        // typically very few custom key-value cfgs.
+        let (key, value) = (key.as_ref(), value.as_ref());
+        self.key_values
+            .iter()
+            .any(|(k, v)| k.deref() == key && v.deref() == value)
+    }
+
+    /// Returns the version of rustc that `cfg(version("..."))` predicates are evaluated against,
+    /// if known.
+    ///
+    /// The rustc version can be set through [`PlatformBuilder::set_rustc_version`].
+    pub fn rustc_version(&self) -> Option<&Version> {
+        self.rustc_version.as_ref()
+    }
+
+    /// Returns true if `cfg(test)` is enabled for this platform.
+    ///
+    /// This can be set through [`PlatformBuilder::set_test`], and is false by default.
+    #[inline]
+    pub fn test(&self) -> bool {
+        self.compiler_cfgs.test
+    }
+
+    /// Returns true if `cfg(debug_assertions)` is enabled for this platform.
+    ///
+    /// This can be set through [`PlatformBuilder::set_debug_assertions`], and is false by default.
+    #[inline]
+    pub fn debug_assertions(&self) -> bool {
+        self.compiler_cfgs.debug_assertions
+    }
+
+    /// Returns true if `cfg(proc_macro)` is enabled for this platform.
+    ///
+    /// This can be set through [`PlatformBuilder::set_proc_macro`], and is false by default.
+    #[inline]
+    pub fn proc_macro(&self) -> bool {
+        self.compiler_cfgs.proc_macro
+    }
+
     /// Returns the underlying `Triple`.
     pub fn triple(&self) -> &Triple {
         &self.triple
@@ -87,7 +152,202 @@ impl Platform {
     }
 }
 
+/// Creates a platform for this triple, with unknown target features.
+impl From<Triple> for Platform {
+    #[inline]
+    fn from(triple: Triple) -> Self {
+        Self::from_triple(triple, TargetFeatures::Unknown)
+    }
+}
+
+/// Creates a platform for this `target-lexicon` triple, with unknown target features.
+impl From<target_lexicon::Triple> for Platform {
+    #[inline]
+    fn from(lexicon_triple: target_lexicon::Triple) -> Self {
+        Self::from_triple(lexicon_triple.into(), TargetFeatures::Unknown)
+    }
+}
+
+impl<'a> From<&'a Platform> for target_lexicon::Triple {
+    #[inline]
+    fn from(platform: &'a Platform) -> Self {
+        platform.triple().into()
+    }
+}
+
+/// A builder for [`Platform`] instances.
+///
+/// A `PlatformBuilder` is useful for platforms that have custom cfgs set, for example through
+/// `RUSTFLAGS="--cfg tokio_unstable"`. Such cfgs aren't builtin to rustc, and would otherwise
+/// always evaluate to false.
+///
+/// Created through [`Platform::builder`] or [`PlatformBuilder::new`].
+///
+/// # Examples
+///
+/// ```
+/// use target_spec::{Platform, TargetFeatures, TargetSpec, Triple};
+///
+/// let triple = Triple::new("x86_64-unknown-linux-gnu").unwrap();
+/// let platform = Platform::builder(triple)
+///     .set_target_features(TargetFeatures::none())
+///     .add_flag("tokio_unstable")
+///     .add_key_value("loom", "enabled")
+///     .build();
+///
+/// let spec: TargetSpec = "cfg(all(unix, tokio_unstable))".parse().unwrap();
+/// assert_eq!(spec.eval(&platform), Some(true));
+/// let spec: TargetSpec = "cfg(loom = \"enabled\")".parse().unwrap();
+/// assert_eq!(spec.eval(&platform), Some(true));
+/// let spec: TargetSpec = "cfg(loom = \"disabled\")".parse().unwrap();
+/// assert_eq!(spec.eval(&platform), Some(false));
+/// ```
+#[derive(Clone, Debug)]
+pub struct PlatformBuilder {
+    triple: Triple,
+    target_features: TargetFeatures,
+    flags: BTreeSet<Cow<'static, str>>,
+    key_values: BTreeSet<(Cow<'static, str>, Cow<'static, str>)>,
+    rustc_version: Option<Version>,
+    compiler_cfgs: CompilerCfgs,
+}
+
+impl PlatformBuilder {
+    /// Creates a new `PlatformBuilder` for the given triple.
+    ///
+    /// By default, target features and the rustc version are unknown, no custom cfgs are set, and
+    /// `test`, `debug_assertions` and `proc_macro` are disabled.
+    pub fn new(triple: Triple) -> Self {
+        Self {
+            triple,
+            target_features: TargetFeatures::Unknown,
+            flags: BTreeSet::new(),
+            key_values: BTreeSet::new(),
+            rustc_version: None,
+            compiler_cfgs: CompilerCfgs::default(),
+        }
+    }
+
+    /// Sets the target features for this platform.
+    pub fn set_target_features(&mut self, target_features: TargetFeatures) -> &mut Self {
+        self.target_features = target_features;
+        self
+    }
+
+    /// Adds a custom flag to this platform.
+    ///
+    /// A flag is a single token like the `foo` in `cfg(not(foo))`, and corresponds to
+    /// `--cfg foo` being passed into rustc.
+    pub fn add_flag(&mut self, flag: impl Into<Cow<'static, str>>) -> &mut Self {
+        self.flags.insert(flag.into());
+        self
+    }
+
+    /// Adds a set of custom flags to this platform.
+    ///
+    /// For more about flags, see [`add_flag`](Self::add_flag).
+    pub fn add_flags(
+        &mut self,
+        flags: impl IntoIterator<Item = impl Into<Cow<'static, str>>>,
+    ) -> &mut Self {
+        self.flags.extend(flags.into_iter().map(|flag| flag.into()));
+        self
+    }
+
+    /// Adds a custom `key = "value"` cfg to this platform.
+    ///
+    /// This corresponds to `--cfg 'key="value"'` being passed into rustc. A key may be added
+    /// multiple times with different values, in which case `cfg(key = "value")` is true for each
+    /// of those values.
+    pub fn add_key_value(
+        &mut self,
+        key: impl Into<Cow<'static, str>>,
+        value: impl Into<Cow<'static, str>>,
+    ) -> &mut Self {
+        self.key_values.insert((key.into(), value.into()));
+        self
+    }
+
+    /// Sets the version of rustc for this platform, used to evaluate `cfg(version("..."))`.
+    ///
+    /// If the rustc version isn't set, `cfg(version("..."))` predicates evaluate to `None`
+    /// (unknown). Pre-release versions such as `1.71.0-nightly` are treated as the corresponding
+    /// release, matching rustc.
+    pub fn set_rustc_version(&mut self, rustc_version: Version) -> &mut Self {
+        self.rustc_version = Some(rustc_version);
+        self
+    }
+
+    /// Sets whether `cfg(test)` is enabled for this platform.
+    ///
+    /// Cargo enables `test` while building unit tests, integration tests and benchmarks.
+    pub fn set_test(&mut self, test: bool) -> &mut Self {
+        self.compiler_cfgs.test = test;
+        self
+    }
+
+    /// Sets whether `cfg(debug_assertions)` is enabled for this platform.
+    ///
+    /// Cargo enables `debug_assertions` for the `dev` and `test` profiles by default.
+    pub fn set_debug_assertions(&mut self, debug_assertions: bool) -> &mut Self {
+        self.compiler_cfgs.debug_assertions = debug_assertions;
+        self
+    }
+
+    /// Sets whether `cfg(proc_macro)` is enabled for this platform.
+    ///
+    /// rustc enables `proc_macro` while building procedural macro crates.
+    pub fn set_proc_macro(&mut self, proc_macro: bool) -> &mut Self {
+        self.compiler_cfgs.proc_macro = proc_macro;
+        self
+    }
+
+    /// Builds a `Platform` out of this builder.
+    pub fn build(&self) -> Platform {
+        Platform {
+            triple: self.triple.clone(),
+            target_features: self.target_features.clone(),
+            flags: self.flags.clone(),
+            key_values: self.key_values.clone(),
+            rustc_version: self.rustc_version.clone(),
+            compiler_cfgs: self.compiler_cfgs,
+        }
+    }
+}
+
+/// Compiler-set cfgs that aren't derived from the target triple.
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+struct CompilerCfgs {
+    test: bool,
+    debug_assertions: bool,
+    proc_macro: bool,
+}
+
 /// A set of target features to match.
+///
+/// `TargetFeatures` has a canonical string form, produced by its `Display` implementation and
+/// parsed by its `FromStr` implementation:
+///
+/// * `"unknown"` for [`TargetFeatures::Unknown`].
+/// * `"all"` for [`TargetFeatures::All`].
+/// * `"none"` for an empty set of features.
+/// * Otherwise, a comma-separated list of features, each prefixed with `+`, in sorted order --
+///   for example, `"+avx,+sse2"`. This is the same syntax as rustc's `-C target-feature`.
+///
+/// While parsing, the `+` prefix is optional, and whitespace around features is ignored.
+///
+/// # Examples
+///
+/// ```
+/// use target_spec::TargetFeatures;
+///
+/// let features = TargetFeatures::features(["sse2", "avx"]);
+/// assert_eq!(features.to_string(), "+avx,+sse2");
+/// assert_eq!("+avx,+sse2".parse::<TargetFeatures>().unwrap(), features);
+///
+/// assert_eq!(TargetFeatures::none().to_string(), "none");
+/// assert_eq!("unknown".parse::<TargetFeatures>().unwrap(), TargetFeatures::Unknown);
+/// ```
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 #[non_exhaustive]
 pub enum TargetFeatures {
@@ -110,6 +370,59 @@ impl TargetFeatures {
         TargetFeatures::Features(BTreeSet::new())
     }
 
+    /// The WebAssembly `atomics` target feature, which enables shared memory and atomic
+    /// instructions.
+    pub const WASM_ATOMICS: &'static str = "atomics";
+
+    /// The WebAssembly `simd128` target feature, which enables 128-bit SIMD instructions.
+    pub const WASM_SIMD128: &'static str = "simd128";
+
+    /// The WebAssembly `bulk-memory` target feature, which enables bulk memory operations like
+    /// `memory.copy` and `memory.fill`.
+    pub const WASM_BULK_MEMORY: &'static str = "bulk-memory";
+
+    /// Creates a new `TargetFeatures` with the WebAssembly features required for threads:
+    /// `atomics` and `bulk-memory`.
+    ///
+    /// These are the features enabled by `-C target-feature=+atomics,+bulk-memory`, which is how
+    /// multithreaded wasm programs are typically built. They're also enabled by default for
+    /// `wasm32-wasip1-threads`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use target_spec::{Platform, TargetFeatures, TargetSpec};
+    ///
+    /// let platform = Platform::new("wasm32-unknown-unknown", TargetFeatures::wasm_threads()).unwrap();
+    /// let spec: TargetSpec = "cfg(target_feature = \"atomics\")".parse().unwrap();
+    /// assert_eq!(spec.eval(&platform), Some(true));
+    /// assert_eq!(platform.target_features().has_wasm_simd128(), Some(false));
+    /// ```
+    pub fn wasm_threads() -> Self {
+        TargetFeatures::features([Self::WASM_ATOMICS, Self::WASM_BULK_MEMORY])
+    }
+
+    /// Returns whether the WebAssembly `atomics` feature is enabled, or `None` if the set of
+    /// target features is unknown.
+    #[inline]
+    pub fn has_wasm_atomics(&self) -> Option<bool> {
+        self.matches(Self::WASM_ATOMICS)
+    }
+
+    /// Returns whether the WebAssembly `simd128` feature is enabled, or `None` if the set of
+    /// target features is unknown.
+    #[inline]
+    pub fn has_wasm_simd128(&self) -> Option<bool> {
+        self.matches(Self::WASM_SIMD128)
+    }
+
+    /// Returns whether the WebAssembly `bulk-memory` feature is enabled, or `None` if the set of
+    /// target features is unknown.
+    #[inline]
+    pub fn has_wasm_bulk_memory(&self) -> Option<bool> {
+        self.matches(Self::WASM_BULK_MEMORY)
+    }
+
     /// Returns `Some(true)` if this feature is a match, `Some(false)` if it isn't, and `None` if
     /// the set of target features is unknown.
     pub fn matches(&self, feature: &str) -> Option<bool> {
@@ -120,3 +433,121 @@ impl TargetFeatures {
         }
     }
 }
+
+impl fmt::Display for TargetFeatures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TargetFeatures::Unknown => f.write_str("unknown"),
+            TargetFeatures::All => f.write_str("all"),
+            TargetFeatures::Features(features) if features.is_empty() => f.write_str("none"),
+            TargetFeatures::Features(features) => {
+                for (idx, feature) in features.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "+{}", feature)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl FromStr for TargetFeatures {
+    type Err = TargetFeaturesParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.trim() {
+            "unknown" => return Ok(TargetFeatures::Unknown),
+            "all" => return Ok(TargetFeatures::All),
+            "none" => return Ok(TargetFeatures::none()),
+            _ => {}
+        }
+
+        let mut features = BTreeSet::new();
+        for feature in input.split(',') {
+            let feature = feature.trim();
+            let feature = feature.strip_prefix('+').unwrap_or(feature);
+            if feature.is_empty() {
+                return Err(TargetFeaturesParseError::new(
+                    input,
+                    "feature names must not be empty",
+                ));
+            }
+            if feature.starts_with('-') {
+                return Err(TargetFeaturesParseError::new(
+                    input,
+                    "disabling features with `-` is not supported",
+                ));
+            }
+            if feature.contains(char::is_whitespace) {
+                return Err(TargetFeaturesParseError::new(
+                    input,
+                    "feature names must not contain whitespace",
+                ));
+            }
+            features.insert(Cow::Owned(feature.to_owned()));
+        }
+        Ok(TargetFeatures::Features(features))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_features_roundtrip() {
+        let valid = [
+            ("unknown", TargetFeatures::Unknown, "unknown"),
+            ("all", TargetFeatures::All, "all"),
+            ("none", TargetFeatures::none(), "none"),
+            ("+sse2", TargetFeatures::features(["sse2"]), "+sse2"),
+            (
+                "+sse2,+avx",
+                TargetFeatures::features(["sse2", "avx"]),
+                "+avx,+sse2",
+            ),
+            (
+                " avx , +sse2 ",
+                TargetFeatures::features(["sse2", "avx"]),
+                "+avx,+sse2",
+            ),
+            // A feature named "all" is distinct from all features.
+            ("+all", TargetFeatures::features(["all"]), "+all"),
+        ];
+        for (input, expected, canonical) in valid {
+            let actual: TargetFeatures = input.parse().expect("input is valid");
+            assert_eq!(actual, expected, "for input {:?}", input);
+            assert_eq!(actual.to_string(), canonical, "for input {:?}", input);
+            assert_eq!(
+                canonical.parse::<TargetFeatures>().unwrap(),
+                expected,
+                "canonical form round-trips"
+            );
+        }
+
+        for input in ["", "+sse2,", "+sse2,,+avx", "-sse2", "+", "sse 2"] {
+            let err = input
+                .parse::<TargetFeatures>()
+                .expect_err("input is invalid");
+            assert_eq!(err.input(), input);
+        }
+    }
+
+    #[test]
+    fn test_wasm_features() {
+        let threads = TargetFeatures::wasm_threads();
+        assert_eq!(threads.to_string(), "+atomics,+bulk-memory");
+        assert_eq!(threads.has_wasm_atomics(), Some(true));
+        assert_eq!(threads.has_wasm_bulk_memory(), Some(true));
+        assert_eq!(threads.has_wasm_simd128(), Some(false));
+
+        let simd = TargetFeatures::features([TargetFeatures::WASM_SIMD128]);
+        assert_eq!(simd.has_wasm_simd128(), Some(true));
+        assert_eq!(simd.has_wasm_atomics(), Some(false));
+
+        assert_eq!(TargetFeatures::Unknown.has_wasm_atomics(), None);
+        assert_eq!(TargetFeatures::All.has_wasm_simd128(), Some(true));
+    }
+}
@@ -0,0 +1,111 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{Error, Platform, TargetSpec};
+use std::sync::Arc;
+
+/// A specifier for a single platform, or for a range of platforms.
+///
+/// Some uses of `target-spec` care about a single platform, and others care about queries against
+/// the intersection of all hypothetical platforms, or against a union of any of them.
+/// `PlatformSpec` represents all three cases, and [`TargetSpec::eval_platform_spec`] evaluates a
+/// `TargetSpec` against it.
+///
+/// # Examples
+///
+/// ```
+/// use target_spec::{Platform, PlatformSpec, TargetFeatures, TargetSpec};
+///
+/// let spec: TargetSpec = "cfg(unix)".parse().unwrap();
+/// let windows = Platform::new("x86_64-pc-windows-msvc", TargetFeatures::Unknown).unwrap();
+///
+/// assert_eq!(spec.eval_platform_spec(&PlatformSpec::Always), Some(false));
+/// assert_eq!(spec.eval_platform_spec(&PlatformSpec::Any), Some(true));
+/// assert_eq!(spec.eval_platform_spec(&PlatformSpec::from(windows)), Some(false));
+/// ```
+#[derive(Clone, Debug)]
+pub enum PlatformSpec {
+    /// The intersection of all platforms.
+    ///
+    /// A `TargetSpec` evaluated against this variant is never true, since specs are
+    /// platform-dependent. Evaluation never returns unknown.
+    ///
+    /// This variant does not currently understand expressions that always evaluate to true
+    /// (tautologies), like `cfg(any(unix, not(unix)))` or `cfg(all())`. In the future, an SMT
+    /// solver would be able to handle such expressions.
+    Always,
+
+    /// An individual platform.
+    ///
+    /// A `TargetSpec` evaluated against this variant is true if and only if it matches this
+    /// platform. Evaluation may return unknown, for example if the platform's target features are
+    /// unknown.
+    Platform(Arc<Platform>),
+
+    /// The union of all platforms.
+    ///
+    /// A `TargetSpec` evaluated against this variant is always true, since it may match some
+    /// platform.
+    ///
+    /// This variant does not currently understand expressions that always evaluate to false
+    /// (contradictions), like `cfg(all(unix, not(unix)))` or `cfg(any())`. In the future, an SMT
+    /// solver would be able to handle such expressions.
+    Any,
+}
+
+impl PlatformSpec {
+    /// Returns a `PlatformSpec` corresponding to the current platform, as detected at build time.
+    ///
+    /// Returns an error if the current platform was unknown to this version of `target-spec`.
+    pub fn current() -> Result<Self, Error> {
+        Ok(PlatformSpec::Platform(Arc::new(Platform::current()?)))
+    }
+}
+
+impl<T: Into<Arc<Platform>>> From<T> for PlatformSpec {
+    #[inline]
+    fn from(platform: T) -> Self {
+        PlatformSpec::Platform(platform.into())
+    }
+}
+
+impl TargetSpec {
+    /// Evaluates this specification against the given platform spec.
+    ///
+    /// Returns `Some(true)` if there's a match, `Some(false)` if there's none, or `None` if the
+    /// result of the evaluation is unknown. For more, see the documentation for each variant of
+    /// [`PlatformSpec`].
+    pub fn eval_platform_spec(&self, platform_spec: &PlatformSpec) -> Option<bool> {
+        match platform_spec {
+            PlatformSpec::Always => Some(false),
+            PlatformSpec::Platform(platform) => self.eval(platform),
+            PlatformSpec::Any => Some(true),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TargetFeatures;
+
+    #[test]
+    fn test_eval_platform_spec() {
+        let specs = [
+            "x86_64-unknown-linux-gnu",
+            "cfg(windows)",
+            "cfg(target_feature = \"sse2\")",
+        ];
+        let linux = PlatformSpec::from(
+            Platform::new("x86_64-unknown-linux-gnu", TargetFeatures::Unknown).unwrap(),
+        );
+        let expected_linux = [Some(true), Some(false), None];
+
+        for (spec, expected_linux) in specs.iter().zip(expected_linux) {
+            let spec: TargetSpec = spec.parse().unwrap();
+            assert_eq!(spec.eval_platform_spec(&PlatformSpec::Always), Some(false));
+            assert_eq!(spec.eval_platform_spec(&PlatformSpec::Any), Some(true));
+            assert_eq!(spec.eval_platform_spec(&linux), expected_linux);
+        }
+    }
+}
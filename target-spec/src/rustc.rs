@@ -0,0 +1,327 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Support for constructing platforms by querying rustc.
+//!
+//! Requires the `rustc` feature to be enabled.
+
+use crate::{
+    errors::{RustcCfgError, RustcCfgErrorKind},
+    Error, Platform, PlatformBuilder, TargetFeatures, Triple,
+};
+use std::{borrow::Cow, collections::BTreeSet, env, ffi::OsString, process::Command};
+
+impl Platform {
+    /// Creates a new `Platform` by running `rustc --print cfg --target <triple>`.
+    ///
+    /// The resulting platform has the exact set of target features rustc reports for this
+    /// target, instead of [`TargetFeatures::Unknown`]. Any custom cfgs rustc reports (for example,
+    /// through `-C` or `--cfg` options in a wrapper) are added as flags and key-value cfgs.
+    ///
+    /// The rustc binary is determined by the `RUSTC` environment variable, falling back to
+    /// `rustc` in `PATH`. For more control, use [`RustcCfgCommand`].
+    ///
+    /// Requires the `rustc` feature to be enabled.
+    pub fn from_rustc(triple_str: impl Into<Cow<'static, str>>) -> Result<Self, Error> {
+        let triple = Triple::new(triple_str.into()).map_err(Error::UnknownPlatformTriple)?;
+        let mut command = RustcCfgCommand::new();
+        command.set_target(triple.as_str());
+        command.build_platform(triple)
+    }
+
+    /// Returns the current platform, as reported by rustc.
+    ///
+    /// [`Platform::current`] returns the platform `target-spec` was built for, which isn't always
+    /// the platform the toolchain runs on. For example, an `x86_64-apple-darwin` binary running
+    /// under Rosetta on an Apple Silicon Mac reports `x86_64-apple-darwin`, even if the Rust
+    /// toolchain in use is `aarch64-apple-darwin`.
+    ///
+    /// This method instead runs `rustc -vV` to find the toolchain's host triple, then
+    /// `rustc --print cfg` to find its target features and custom cfgs. For more control, use
+    /// [`RustcCfgCommand::exec_host_triple`].
+    ///
+    /// Requires the `rustc` feature to be enabled.
+    pub fn current_from_rustc() -> Result<Self, Error> {
+        let mut command = RustcCfgCommand::new();
+        let host = command.exec_host_triple()?;
+        let triple = Triple::new(host.clone()).map_err(Error::UnknownPlatformTriple)?;
+        command.set_target(host);
+        command.build_platform(triple)
+    }
+
+    /// Creates a new `Platform` from the output of `rustc --print cfg` for the given triple.
+    ///
+    /// This is useful if the output of `rustc --print cfg` was obtained some other way, for
+    /// example by a build system.
+    ///
+    /// Requires the `rustc` feature to be enabled.
+    pub fn from_rustc_cfg_output(
+        triple_str: impl Into<Cow<'static, str>>,
+        output: &str,
+    ) -> Result<Self, Error> {
+        let triple = Triple::new(triple_str.into()).map_err(Error::UnknownPlatformTriple)?;
+        Ok(builder_from_cfg_output(triple, output).build())
+    }
+}
+
+/// A builder for a `rustc --print cfg` invocation.
+///
+/// Requires the `rustc` feature to be enabled.
+#[derive(Clone, Debug, Default)]
+pub struct RustcCfgCommand {
+    rustc_path: Option<OsString>,
+    target: Option<String>,
+    args: Vec<OsString>,
+}
+
+impl RustcCfgCommand {
+    /// Creates a new `RustcCfgCommand` with default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the path to the rustc binary.
+    ///
+    /// By default, the `RUSTC` environment variable is used, falling back to `rustc` in `PATH`.
+    pub fn set_rustc_path(&mut self, rustc_path: impl Into<OsString>) -> &mut Self {
+        self.rustc_path = Some(rustc_path.into());
+        self
+    }
+
+    /// Sets the target triple to pass in via `--target`.
+    ///
+    /// By default, no target is passed in, and rustc reports the cfgs for the host platform.
+    pub fn set_target(&mut self, target: impl Into<String>) -> &mut Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    /// Adds extra arguments to pass into rustc, for example `-C target-cpu=native` or
+    /// `--cfg tokio_unstable`.
+    pub fn add_args(&mut self, args: impl IntoIterator<Item = impl Into<OsString>>) -> &mut Self {
+        self.args.extend(args.into_iter().map(|arg| arg.into()));
+        self
+    }
+
+    /// Runs rustc and returns the list of cfgs it prints out, one per line.
+    pub fn exec(&self) -> Result<String, Error> {
+        let mut command = self.rustc_command();
+        command.args(["--print", "cfg"]);
+        if let Some(target) = &self.target {
+            command.args(["--target", target]);
+        }
+        command.args(&self.args);
+        run(command)
+    }
+
+    /// Runs `rustc -vV` and returns the host triple it reports.
+    ///
+    /// The target and extra arguments aren't passed in to rustc.
+    pub fn exec_host_triple(&self) -> Result<String, Error> {
+        let mut command = self.rustc_command();
+        command.arg("-vV");
+        let output = run(command)?;
+        parse_host_triple(&output)
+            .map(|host| host.to_owned())
+            .ok_or_else(|| Error::RustcCfg(RustcCfgError::new(RustcCfgErrorKind::MissingHost)))
+    }
+
+    /// Runs rustc and builds a `Platform` for the given triple out of its output.
+    ///
+    /// The triple should match the one passed in through [`set_target`](Self::set_target), or
+    /// the host triple if no target was set.
+    pub fn build_platform(&self, triple: Triple) -> Result<Platform, Error> {
+        let output = self.exec()?;
+        Ok(builder_from_cfg_output(triple, &output).build())
+    }
+
+    fn rustc_command(&self) -> Command {
+        let rustc_path = self
+            .rustc_path
+            .clone()
+            .or_else(|| env::var_os("RUSTC"))
+            .unwrap_or_else(|| "rustc".into());
+        Command::new(rustc_path)
+    }
+}
+
+/// Runs a rustc command, returning its standard output.
+fn run(mut command: Command) -> Result<String, Error> {
+    let output = command.output().map_err(|error| {
+        Error::RustcCfg(RustcCfgError::new(RustcCfgErrorKind::Exec(
+            error.to_string(),
+        )))
+    })?;
+    if !output.status.success() {
+        return Err(Error::RustcCfg(RustcCfgError::new(
+            RustcCfgErrorKind::Failed {
+                exit_code: output.status.code(),
+                stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            },
+        )));
+    }
+
+    String::from_utf8(output.stdout)
+        .map_err(|_| Error::RustcCfg(RustcCfgError::new(RustcCfgErrorKind::InvalidUtf8)))
+}
+
+/// Parses the host triple out of the output of `rustc -vV`.
+fn parse_host_triple(version_output: &str) -> Option<&str> {
+    version_output
+        .lines()
+        .find_map(|line| line.strip_prefix("host:"))
+        .map(|host| host.trim())
+        .filter(|host| !host.is_empty())
+}
+
+/// Compiler-set cfgs that aren't derived from the target triple. These aren't treated as custom
+/// cfgs.
+static COMPILER_CFGS: &[&str] = &[
+    "debug_assertions",
+    "overflow_checks",
+    "panic",
+    "relocation_model",
+    "sanitize",
+    "fmt_debug",
+    "ub_checks",
+    "contract_checks",
+    "emscripten_wasm_eh",
+];
+
+fn builder_from_cfg_output(triple: Triple, output: &str) -> PlatformBuilder {
+    let mut target_features = BTreeSet::new();
+    let mut builder = PlatformBuilder::new(triple);
+
+    for line in output.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), Some(value.trim().trim_matches('"'))),
+            None => (line, None),
+        };
+
+        match (key, value) {
+            ("target_feature", Some(feature)) => {
+                target_features.insert(Cow::Owned(feature.to_owned()));
+            }
+            // All other target_ cfgs, as well as unix and windows, are derived from the triple.
+            (key, _) if key.starts_with("target_") => {}
+            ("unix" | "windows", None) => {}
+            (key, _) if COMPILER_CFGS.contains(&key) => {}
+            (flag, None) => {
+                builder.add_flag(flag.to_owned());
+            }
+            (key, Some(value)) => {
+                builder.add_key_value(key.to_owned(), value.to_owned());
+            }
+        }
+    }
+
+    builder.set_target_features(TargetFeatures::Features(target_features));
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TargetSpec;
+
+    static X86_64_LINUX_OUTPUT: &str = r#"debug_assertions
+panic="unwind"
+target_abi=""
+target_arch="x86_64"
+target_endian="little"
+target_env="gnu"
+target_family="unix"
+target_feature="fxsr"
+target_feature="sse"
+target_feature="sse2"
+target_has_atomic="16"
+target_has_atomic="32"
+target_has_atomic="64"
+target_has_atomic="8"
+target_has_atomic="ptr"
+target_os="linux"
+target_pointer_width="64"
+target_vendor="unknown"
+tokio_unstable
+tracing="on"
+unix
+"#;
+
+    #[test]
+    fn test_from_cfg_output() {
+        let platform =
+            Platform::from_rustc_cfg_output("x86_64-unknown-linux-gnu", X86_64_LINUX_OUTPUT)
+                .expect("valid output");
+        assert_eq!(
+            platform.target_features(),
+            &TargetFeatures::features(["fxsr", "sse", "sse2"]),
+        );
+        assert_eq!(platform.flags().collect::<Vec<_>>(), vec!["tokio_unstable"]);
+        assert_eq!(
+            platform.key_values().collect::<Vec<_>>(),
+            vec![("tracing", "on")]
+        );
+
+        let expected = [
+            ("cfg(target_feature = \"sse2\")", Some(true)),
+            ("cfg(target_feature = \"avx2\")", Some(false)),
+            ("cfg(all(unix, tokio_unstable))", Some(true)),
+            ("cfg(tracing = \"on\")", Some(true)),
+        ];
+        for (spec, result) in expected {
+            let spec: TargetSpec = spec.parse().expect("valid spec");
+            assert_eq!(spec.eval(&platform), result, "for spec {:?}", spec);
+        }
+    }
+
+    #[test]
+    fn test_from_rustc_current() {
+        let current = Platform::current().expect("current platform is known");
+        let platform =
+            Platform::from_rustc(current.triple_str().to_owned()).expect("rustc ran successfully");
+        assert_eq!(platform.triple_str(), current.triple_str());
+        assert!(
+            matches!(platform.target_features(), TargetFeatures::Features(_)),
+            "target features are known"
+        );
+    }
+
+    #[test]
+    fn test_parse_host_triple() {
+        let output = "rustc 1.70.0 (90c541806 2023-05-31)
+binary: rustc
+commit-hash: 90c541806f23a127002de5b4038be731ba1458ca
+commit-date: 2023-05-31
+host: aarch64-apple-darwin
+release: 1.70.0
+LLVM version: 16.0.2
+";
+        assert_eq!(parse_host_triple(output), Some("aarch64-apple-darwin"));
+        assert_eq!(parse_host_triple("rustc 1.70.0\nhost:\n"), None);
+        assert_eq!(parse_host_triple(""), None);
+    }
+
+    #[test]
+    fn test_current_from_rustc() {
+        let platform = Platform::current_from_rustc().expect("rustc ran successfully");
+        // The tests are built by the same toolchain, so the host should match.
+        let current = Platform::current().expect("current platform is known");
+        assert_eq!(platform.triple_str(), current.triple_str());
+    }
+
+    #[test]
+    fn test_exec_failure() {
+        let mut command = RustcCfgCommand::new();
+        command.set_rustc_path("/this/rustc/does/not/exist");
+        let err = command.exec().expect_err("rustc doesn't exist");
+        assert!(matches!(
+            err,
+            Error::RustcCfg(err) if matches!(err.kind(), RustcCfgErrorKind::Exec(_))
+        ));
+    }
+}
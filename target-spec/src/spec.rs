@@ -1,9 +1,15 @@
 // Copyright (c) The cargo-guppy Contributors
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use crate::{errors::ExpressionParseError, Error, Platform, Triple};
-use cfg_expr::{Expression, Predicate};
-use std::{borrow::Cow, str::FromStr, sync::Arc};
+use crate::{
+    alias::builtin_alias_expansion,
+    errors::ExpressionParseError,
+    tree::{ExprTree, NormalizedExpr},
+    version::{eval_version, rewrite_version_predicates, VERSION_KEY},
+    CompiledTargetSpec, Error, Platform, Triple,
+};
+use cfg_expr::{Expression, Predicate, TargetPredicate};
+use std::{borrow::Cow, hash, str::FromStr, sync::Arc};
 
 /// A parsed target specification or triple, as found in a `Cargo.toml` file.
 ///
@@ -29,7 +35,13 @@ use std::{borrow::Cow, str::FromStr, sync::Arc};
 /// assert_eq!(spec.eval(&x86_64_mac), Some(false), "x86_64 MacOS matches no features");
 /// assert_eq!(spec.eval(&i686_linux), Some(true), "i686 Linux matches some features");
 /// ```
-#[derive(Clone, Debug)]
+///
+/// ## Equality
+///
+/// Two `TargetSpec` instances are equal if they are the same triple, or if they are expressions
+/// parsed from the same string. To compare specs that are equivalent but written differently, use
+/// [`TargetSpec::normalize`] first.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum TargetSpec {
     /// An exact target parsed from a triple.
     ///
@@ -44,9 +56,23 @@ pub enum TargetSpec {
 
 impl TargetSpec {
     /// Creates a new target from a string.
+    ///
+    /// The string is a triple, a `cfg()` expression, or one of the
+    /// [builtin aliases](crate::TargetAliases::builtin). To use custom aliases, see
+    /// [`TargetAliases`](crate::TargetAliases).
+    ///
+    /// Wildcard patterns like `x86_64-*-linux-*` aren't supported by Cargo, so they're rejected
+    /// with [`Error::UnknownTargetTriple`]. To accept them as well, use
+    /// [`SpecOrPattern`](crate::SpecOrPattern).
     pub fn new(input: impl Into<Cow<'static, str>>) -> Result<Self, Error> {
         let input = input.into();
+        match builtin_alias_expansion(&input) {
+            Some(expansion) => Self::new_without_aliases(expansion.into()),
+            None => Self::new_without_aliases(input),
+        }
+    }
 
+    pub(crate) fn new_without_aliases(input: Cow<'static, str>) -> Result<Self, Error> {
         if input.starts_with("cfg(") {
             Ok(TargetSpec::Expression(TargetExpression::new(&input)?))
         } else {
@@ -63,13 +89,123 @@ impl TargetSpec {
     /// result of the evaluation is unknown (typically found if target features are involved).
     #[inline]
     pub fn eval(&self, platform: &Platform) -> Option<bool> {
+        self.eval_with_mode(platform, EvalMode::Cargo)
+    }
+
+    /// Returns a simplified, canonical version of this specification.
+    ///
+    /// Triples are returned as-is. For expressions, see [`TargetExpression::normalize`].
+    pub fn normalize(&self) -> TargetSpec {
+        match self {
+            TargetSpec::Triple(triple) => TargetSpec::Triple(triple.clone()),
+            TargetSpec::Expression(expr) => TargetSpec::Expression(expr.normalize()),
+        }
+    }
+
+    /// Prepares this specification for repeated evaluation.
+    ///
+    /// Evaluating the returned [`CompiledTargetSpec`] gives the same results as evaluating this
+    /// specification, but is cheaper when done against many platforms.
+    pub fn compile(&self) -> CompiledTargetSpec {
+        CompiledTargetSpec::new(self)
+    }
+
+    /// Evaluates this specification against each of the given platforms, in order.
+    ///
+    /// Returns one result per platform, with the same meaning as the result of [`eval`]. The
+    /// specification is compiled once and then evaluated against every platform, which is
+    /// cheaper than calling `eval` in a loop.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use target_spec::{Platform, TargetFeatures, TargetSpec};
+    ///
+    /// let spec = TargetSpec::new("cfg(any(windows, target_feature = \"sse2\"))").unwrap();
+    /// let platforms = [
+    ///     Platform::new("x86_64-pc-windows-msvc", TargetFeatures::Unknown).unwrap(),
+    ///     Platform::new("aarch64-apple-darwin", TargetFeatures::none()).unwrap(),
+    ///     Platform::new("x86_64-unknown-linux-gnu", TargetFeatures::Unknown).unwrap(),
+    /// ];
+    /// assert_eq!(spec.eval_many(&platforms), vec![Some(true), Some(false), None]);
+    /// ```
+    ///
+    /// [`eval`]: Self::eval
+    pub fn eval_many<'a>(
+        &self,
+        platforms: impl IntoIterator<Item = &'a Platform>,
+    ) -> Vec<Option<bool>> {
+        self.compile().eval_many(platforms)
+    }
+
+    /// Evaluates this specification against the given platform, using the given evaluation mode.
+    ///
+    /// With [`EvalMode::Strict`], predicates whose value can't be determined from the platform
+    /// evaluate to unknown, rather than to false as Cargo does. For more, see [`EvalMode`].
+    ///
+    /// Returns `Some(true)` if there's a match, `Some(false)` if there's none, or `None` if the
+    /// result of the evaluation is unknown.
+    #[inline]
+    pub fn eval_with_mode(&self, platform: &Platform, mode: EvalMode) -> Option<bool> {
         match self {
             TargetSpec::Triple(triple) => Some(triple.eval(platform)),
-            TargetSpec::Expression(expr) => expr.eval(platform),
+            TargetSpec::Expression(expr) => expr.eval_with_mode(platform, mode),
         }
     }
 }
 
+/// Determines how predicates that can't be fully evaluated against a [`Platform`] are treated.
+///
+/// Cargo treats several kinds of predicates as false even though they may be true in some
+/// builds. For example, `cfg(my_flag)` is true if `RUSTFLAGS="--cfg my_flag"` is set, and
+/// `cfg(debug_assertions)` depends on the build profile. `EvalMode` controls whether such
+/// predicates are evaluated the way Cargo does, or conservatively as unknown.
+///
+/// # Examples
+///
+/// ```
+/// use target_spec::{EvalMode, Platform, TargetFeatures, TargetSpec};
+///
+/// let platform = Platform::new("x86_64-unknown-linux-gnu", TargetFeatures::Unknown).unwrap();
+/// let spec: TargetSpec = "cfg(all(unix, not(loom)))".parse().unwrap();
+///
+/// // Cargo treats unknown flags as false, so not(loom) is true.
+/// assert_eq!(spec.eval_with_mode(&platform, EvalMode::Cargo), Some(true));
+/// // In strict mode, it's not known whether loom is set.
+/// assert_eq!(spec.eval_with_mode(&platform, EvalMode::Strict), None);
+/// ```
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[non_exhaustive]
+pub enum EvalMode {
+    /// Evaluate predicates the same way Cargo does.
+    ///
+    /// * Flags and key-value cfgs not set on the platform evaluate to false.
+    /// * Compiler-set cfgs (`test`, `debug_assertions` and `proc_macro`) evaluate to false.
+    /// * Target features evaluate to unknown if the platform's target features are unknown.
+    ///
+    /// This is the default, and is what [`TargetSpec::eval`] uses.
+    Cargo,
+
+    /// Evaluate predicates whose value isn't known from the platform as unknown.
+    ///
+    /// * Flags and key-value cfgs set on the platform evaluate to true; all others evaluate to
+    ///   unknown.
+    /// * Compiler-set cfgs (`test`, `debug_assertions` and `proc_macro`) evaluate to unknown.
+    /// * Target predicates that can't be determined for the platform's triple (for example,
+    ///   `target_has_atomic` for triples not builtin to `target-spec`) evaluate to unknown.
+    /// * Target features evaluate to unknown if the platform's target features are unknown.
+    ///
+    /// `feature = "..."` predicates always evaluate to false, since Cargo never sets them.
+    Strict,
+}
+
+impl Default for EvalMode {
+    #[inline]
+    fn default() -> Self {
+        EvalMode::Cargo
+    }
+}
+
 impl FromStr for TargetSpec {
     type Err = Error;
 
@@ -81,9 +217,15 @@ impl FromStr for TargetSpec {
 /// A target expression.
 ///
 /// Parsed from a string beginning with `cfg(`.
+///
+/// Two `TargetExpression` instances are equal if they were parsed from the same string. To
+/// compare expressions that are equivalent but written differently, use
+/// [`normalize`](Self::normalize) first.
 #[derive(Clone, Debug)]
 pub struct TargetExpression {
     inner: Arc<Expression>,
+    // The original string, if it was rewritten before parsing (see the version module).
+    original: Option<Arc<str>>,
 }
 
 impl TargetExpression {
@@ -91,49 +233,181 @@ impl TargetExpression {
     ///
     /// Returns an error if the string could not be parsed, or if the string contains a predicate
     /// that wasn't understood by `target-spec`.
+    ///
+    /// In addition to the predicates supported by Cargo, `version("...")` predicates are
+    /// supported. These are evaluated against the [rustc version](Platform::rustc_version) of a
+    /// platform. (Because of how these are implemented, a `version = "..."` key-value predicate is
+    /// treated the same way.)
     pub fn new(input: &str) -> Result<Self, Error> {
-        let expr = Expression::parse(input)
+        let rewritten = rewrite_version_predicates(input);
+        // The rewritten string is as long as the input, so spans in errors are correct for it.
+        let expr = Expression::parse(&rewritten)
             .map_err(|err| Error::InvalidExpression(ExpressionParseError::new(input, err)))?;
+        let original = match rewritten {
+            Cow::Owned(_) => {
+                // Trim the input the same way cfg-expr does.
+                let trimmed = if input.starts_with("cfg(") && input.ends_with(')') {
+                    &input[4..input.len() - 1]
+                } else {
+                    input
+                };
+                Some(trimmed.into())
+            }
+            Cow::Borrowed(_) => None,
+        };
         Ok(Self {
             inner: Arc::new(expr),
+            original,
         })
     }
 
     /// Returns the string that was parsed into `self`.
     #[inline]
     pub fn expression_str(&self) -> &str {
-        self.inner.original()
+        match &self.original {
+            Some(original) => original,
+            None => self.inner.original(),
+        }
     }
 
     /// Evaluates this expression against the given platform.
     ///
     /// Returns `Some(true)` if there's a match, `Some(false)` if there's none, or `None` if the
     /// result of the evaluation is unknown (typically found if target features are involved).
+    #[inline]
     pub fn eval(&self, platform: &Platform) -> Option<bool> {
-        self.inner.eval(|pred| {
-            match pred {
-                Predicate::Target(target) => Some(platform.triple().matches(target)),
-                Predicate::TargetFeature(feature) => platform.target_features().matches(feature),
-                Predicate::Test | Predicate::DebugAssertions | Predicate::ProcMacro => {
-                    // Known families that always evaluate to false. See
-                    // https://docs.rs/cargo-platform/0.1.1/src/cargo_platform/lib.rs.html#76.
-                    Some(false)
-                }
-                Predicate::Feature(_) => {
-                    // NOTE: This is not supported by Cargo which always evaluates this to false. See
-                    // https://github.com/rust-lang/cargo/issues/7442 for more details.
-                    Some(false)
-                }
-                Predicate::Flag(flag) => {
-                    // This returns false by default but true in some cases.
-                    Some(platform.has_flag(flag))
-                }
-                Predicate::KeyValue { .. } => {
-                    // This is always interpreted by Cargo as false.
-                    Some(false)
-                }
-            }
-        })
+        self.eval_with_mode(platform, EvalMode::Cargo)
+    }
+
+    /// Returns a simplified, canonical version of this expression.
+    ///
+    /// Normalization:
+    ///
+    /// * flattens nested `any()` and `all()`, e.g. `all(unix, all(a, b))` becomes
+    ///   `all(a, b, unix)`
+    /// * removes duplicate operands and sorts the rest
+    /// * removes double negations, e.g. `not(not(unix))` becomes `unix`
+    /// * replaces tautologies like `any(unix, not(unix))` with `all()`, and contradictions like
+    ///   `all(unix, not(unix))` with `any()`
+    /// * replaces `any()` and `all()` with a single operand with that operand
+    /// * renders predicates in their canonical form, e.g. `target_family = "unix"` becomes `unix`
+    ///
+    /// Expressions that normalize to the same string are equivalent. (The converse isn't
+    /// necessarily true: more complex equivalences aren't detected.)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use target_spec::TargetExpression;
+    ///
+    /// let expr = TargetExpression::new(
+    ///     "cfg(all(target_family = \"unix\", all(target_os = \"linux\", unix)))",
+    /// ).unwrap();
+    /// assert_eq!(expr.normalize().expression_str(), "all(target_os = \"linux\", unix)");
+    ///
+    /// let expr = TargetExpression::new("cfg(any(unix, not(unix)))").unwrap();
+    /// assert_eq!(expr.normalize().expression_str(), "all()");
+    /// ```
+    pub fn normalize(&self) -> TargetExpression {
+        let mut canonical = String::new();
+        NormalizedExpr::new(&self.inner).render(&mut canonical);
+        TargetExpression::new(&canonical).expect("canonical expressions are always valid")
+    }
+
+    /// Evaluates this expression against the given platform, using the given evaluation mode.
+    ///
+    /// For more, see [`EvalMode`].
+    pub fn eval_with_mode(&self, platform: &Platform, mode: EvalMode) -> Option<bool> {
+        self.inner.eval(|pred| eval_predicate(pred, platform, mode))
+    }
+
+    /// Returns this expression as a tree.
+    pub(crate) fn tree(&self) -> ExprTree {
+        ExprTree::new(&self.inner)
+    }
+
+    /// Returns an iterator over the predicates in this expression.
+    #[inline]
+    pub(crate) fn predicates(&self) -> impl Iterator<Item = Predicate<'_>> {
+        self.inner.predicates()
+    }
+
+    /// Evaluates this expression using a custom function to evaluate each predicate.
+    #[inline]
+    pub(crate) fn eval_with(
+        &self,
+        eval_predicate: impl FnMut(&Predicate<'_>) -> Option<bool>,
+    ) -> Option<bool> {
+        self.inner.eval(eval_predicate)
+    }
+}
+
+/// Evaluates a single predicate against a platform.
+pub(crate) fn eval_predicate(
+    pred: &Predicate<'_>,
+    platform: &Platform,
+    mode: EvalMode,
+) -> Option<bool> {
+    // Returns the value of a predicate that Cargo treats as false if it isn't set.
+    let set_or_false = |is_set: bool| match (is_set, mode) {
+        (true, _) => Some(true),
+        (false, EvalMode::Cargo) => Some(false),
+        (false, EvalMode::Strict) => None,
+    };
+
+    match pred {
+        Predicate::Target(target) => eval_target_predicate(target, platform, mode),
+        Predicate::TargetFeature(feature) => platform.target_features().matches(feature),
+        // Cargo always evaluates these to false (see
+        // https://docs.rs/cargo-platform/0.1.1/src/cargo_platform/lib.rs.html#76), unless they've
+        // been enabled on the platform.
+        Predicate::Test => set_or_false(platform.test()),
+        Predicate::DebugAssertions => set_or_false(platform.debug_assertions()),
+        Predicate::ProcMacro => set_or_false(platform.proc_macro()),
+        Predicate::Feature(_) => {
+            // NOTE: This is not supported by Cargo which always evaluates this to false. See
+            // https://github.com/rust-lang/cargo/issues/7442 for more details.
+            Some(false)
+        }
+        Predicate::Flag(flag) => {
+            // This returns false by default but true in some cases.
+            set_or_false(platform.has_flag(flag))
+        }
+        Predicate::KeyValue { key, val } if *key == VERSION_KEY => {
+            // A rewritten version("...") predicate.
+            eval_version(val, platform.rustc_version())
+        }
+        Predicate::KeyValue { key, val } => {
+            // This is interpreted by Cargo as false, unless it's been passed in as a
+            // custom cfg.
+            set_or_false(platform.has_key_value(key, val))
+        }
+    }
+}
+
+/// Evaluates a single target predicate against a platform.
+pub(crate) fn eval_target_predicate(
+    target: &TargetPredicate,
+    platform: &Platform,
+    mode: EvalMode,
+) -> Option<bool> {
+    match mode {
+        EvalMode::Cargo => Some(platform.triple().matches(target)),
+        EvalMode::Strict => platform.triple().matches_strict(target),
+    }
+}
+
+impl PartialEq for TargetExpression {
+    fn eq(&self, other: &Self) -> bool {
+        self.expression_str() == other.expression_str()
+    }
+}
+
+impl Eq for TargetExpression {}
+
+impl hash::Hash for TargetExpression {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.expression_str().hash(state);
     }
 }
 
@@ -148,6 +422,7 @@ impl FromStr for TargetExpression {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::TargetFeatures;
     use cfg_expr::{
         targets::{Abi, Arch, Family, Os},
         Predicate, TargetPredicate,
@@ -197,6 +472,71 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_target_abi_eval() {
+        let cases = [
+            (
+                "cfg(target_abi = \"eabihf\")",
+                "armv7-unknown-linux-gnueabihf",
+                true,
+            ),
+            (
+                "cfg(target_abi = \"eabi\")",
+                "armv7-unknown-linux-gnueabihf",
+                false,
+            ),
+            (
+                "cfg(target_abi = \"eabihf\")",
+                "x86_64-unknown-linux-gnu",
+                false,
+            ),
+            ("cfg(target_abi = \"\")", "x86_64-unknown-linux-gnu", true),
+            (
+                "cfg(all(target_env = \"gnu\", target_abi = \"x32\"))",
+                "x86_64-unknown-linux-gnux32",
+                true,
+            ),
+            ("cfg(target_abi = \"sim\")", "aarch64-apple-ios-sim", true),
+            ("cfg(target_abi = \"sim\")", "aarch64-apple-ios", false),
+        ];
+
+        for (spec, triple, expected) in cases {
+            let spec = TargetSpec::new(spec).unwrap();
+            let platform = Platform::new(triple, TargetFeatures::Unknown).unwrap();
+            assert_eq!(
+                spec.eval(&platform),
+                Some(expected),
+                "for spec {:?} and triple {}",
+                spec,
+                triple
+            );
+            assert_eq!(
+                spec.eval_with_mode(&platform, EvalMode::Strict),
+                Some(expected),
+                "for spec {:?} and triple {} in strict mode",
+                spec,
+                triple
+            );
+        }
+    }
+
+    #[test]
+    fn test_pattern_not_accepted() {
+        // Patterns aren't supported by Cargo, so they're parsed by SpecOrPattern instead.
+        for pattern in ["*-apple-darwin", "x86_64-*-linux-*", "*"] {
+            assert!(
+                matches!(TargetSpec::new(pattern), Err(Error::UnknownTargetTriple(_))),
+                "TargetSpec rejects pattern {}",
+                pattern
+            );
+            assert!(
+                pattern.parse::<TargetSpec>().is_err(),
+                "FromStr rejects pattern {}",
+                pattern
+            );
+        }
+    }
+
     #[test]
     fn test_not() {
         assert!(matches!(
@@ -273,6 +613,267 @@ mod tests {
         assert_eq!(expr.eval(&platform), Some(true));
     }
 
+    #[test]
+    fn test_custom_cfgs() {
+        let triple = Triple::new("x86_64-unknown-linux-gnu").unwrap();
+        let platform = Platform::builder(triple)
+            .add_flags(["tokio_unstable", "loom"])
+            .add_key_value("tracing", "on")
+            .add_key_value("tracing", "verbose")
+            .build();
+        assert_eq!(
+            platform.key_values().collect::<Vec<_>>(),
+            vec![("tracing", "on"), ("tracing", "verbose")],
+        );
+
+        let expected = [
+            ("cfg(tokio_unstable)", true),
+            ("cfg(all(loom, tokio_unstable))", true),
+            ("cfg(not(loom))", false),
+            ("cfg(other_flag)", false),
+            ("cfg(tracing = \"on\")", true),
+            ("cfg(tracing = \"verbose\")", true),
+            ("cfg(tracing = \"off\")", false),
+            ("cfg(loom = \"on\")", false),
+        ];
+        for (spec, result) in expected {
+            let spec = TargetSpec::new(spec).unwrap();
+            assert_eq!(spec.eval(&platform), Some(result), "for spec {:?}", spec);
+        }
+    }
+
+    #[test]
+    fn test_compiler_cfgs() {
+        let triple = Triple::new("x86_64-unknown-linux-gnu").unwrap();
+        let default = Platform::builder(triple.clone()).build();
+        let enabled = Platform::builder(triple)
+            .set_test(true)
+            .set_debug_assertions(true)
+            .set_proc_macro(true)
+            .build();
+        assert!(!default.test() && !default.debug_assertions() && !default.proc_macro());
+        assert!(enabled.test() && enabled.debug_assertions() && enabled.proc_macro());
+
+        let expected = [
+            ("cfg(test)", Some(false), None, Some(true)),
+            ("cfg(not(test))", Some(true), None, Some(false)),
+            ("cfg(debug_assertions)", Some(false), None, Some(true)),
+            ("cfg(all(unix, proc_macro))", Some(false), None, Some(true)),
+            ("cfg(any(windows, test))", Some(false), None, Some(true)),
+        ];
+        for (spec, default_cargo, default_strict, enabled_result) in expected {
+            let spec = TargetSpec::new(spec).unwrap();
+            assert_eq!(
+                spec.eval_with_mode(&default, EvalMode::Cargo),
+                default_cargo,
+                "for spec {:?} with defaults in cargo mode",
+                spec
+            );
+            assert_eq!(
+                spec.eval_with_mode(&default, EvalMode::Strict),
+                default_strict,
+                "for spec {:?} with defaults in strict mode",
+                spec
+            );
+            for mode in [EvalMode::Cargo, EvalMode::Strict] {
+                assert_eq!(
+                    spec.eval_with_mode(&enabled, mode),
+                    enabled_result,
+                    "for spec {:?} with cfgs enabled in {:?} mode",
+                    spec,
+                    mode
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_eval_mode() {
+        let linux = Platform::builder(Triple::new("x86_64-unknown-linux-gnu").unwrap())
+            .set_target_features(TargetFeatures::none())
+            .add_flag("loom")
+            .build();
+        // x86_64-pc-darwin is not builtin, so it falls back to target-lexicon.
+        let lexicon =
+            Platform::new("x86_64-pc-darwin", TargetFeatures::Unknown).expect("lexicon triple");
+
+        let expected = [
+            ("cfg(loom)", &linux, Some(true), Some(true)),
+            ("cfg(not(loom))", &linux, Some(false), Some(false)),
+            ("cfg(tokio_unstable)", &linux, Some(false), None),
+            (
+                "cfg(any(unix, tokio_unstable))",
+                &linux,
+                Some(true),
+                Some(true),
+            ),
+            ("cfg(all(unix, tokio_unstable))", &linux, Some(false), None),
+            (
+                "cfg(all(windows, tokio_unstable))",
+                &linux,
+                Some(false),
+                Some(false),
+            ),
+            ("cfg(tracing = \"on\")", &linux, Some(false), None),
+            ("cfg(debug_assertions)", &linux, Some(false), None),
+            ("cfg(not(test))", &linux, Some(true), None),
+            ("cfg(feature = \"foo\")", &linux, Some(false), Some(false)),
+            (
+                "cfg(target_feature = \"sse2\")",
+                &linux,
+                Some(false),
+                Some(false),
+            ),
+            (
+                "cfg(target_has_atomic = \"64\")",
+                &linux,
+                Some(true),
+                Some(true),
+            ),
+            (
+                "cfg(target_os = \"macos\")",
+                &lexicon,
+                Some(true),
+                Some(true),
+            ),
+            (
+                "cfg(target_has_atomic = \"64\")",
+                &lexicon,
+                Some(false),
+                None,
+            ),
+            ("cfg(panic = \"unwind\")", &lexicon, Some(false), None),
+            ("cfg(target_feature = \"sse2\")", &lexicon, None, None),
+        ];
+        for (spec, platform, cargo_result, strict_result) in expected {
+            let spec = TargetSpec::new(spec).unwrap();
+            assert_eq!(
+                spec.eval_with_mode(platform, EvalMode::Cargo),
+                cargo_result,
+                "for spec {:?} in cargo mode",
+                spec
+            );
+            assert_eq!(
+                spec.eval_with_mode(platform, EvalMode::Strict),
+                strict_result,
+                "for spec {:?} in strict mode",
+                spec
+            );
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        let cases = [
+            ("cfg(unix)", "unix"),
+            ("cfg(target_family = \"unix\")", "unix"),
+            ("cfg(target_family = \"wasm\")", "target_family = \"wasm\""),
+            ("cfg(all(unix))", "unix"),
+            ("cfg(any(unix, unix, unix))", "unix"),
+            ("cfg(all(b, a, all(c, a)))", "all(a, b, c)"),
+            ("cfg(any(b, any(a, any(c))))", "any(a, b, c)"),
+            ("cfg(all(b, any(a, c)))", "all(b, any(a, c))"),
+            ("cfg(any(all(b), all(a, c)))", "any(b, all(a, c))"),
+            ("cfg(not(not(unix)))", "unix"),
+            ("cfg(not(not(not(unix))))", "not(unix)"),
+            ("cfg(any(unix, not(unix)))", "all()"),
+            ("cfg(all(unix, not(unix)))", "any()"),
+            ("cfg(all(windows, any(unix, not(unix))))", "windows"),
+            ("cfg(any(windows, all(unix, not(unix))))", "windows"),
+            ("cfg(all(windows, any()))", "any()"),
+            ("cfg(any(windows, all()))", "all()"),
+            ("cfg(not(all()))", "any()"),
+            (
+                "cfg(all(target_os=\"linux\",target_pointer_width=\"64\", foo = \"bar\"))",
+                "all(foo = \"bar\", target_os = \"linux\", target_pointer_width = \"64\")",
+            ),
+            (
+                "cfg(any(target_endian = \"big\", target_has_atomic = \"ptr\", debug_assertions))",
+                "any(debug_assertions, target_endian = \"big\", target_has_atomic = \"ptr\")",
+            ),
+        ];
+
+        for (input, expected) in cases {
+            let spec = TargetSpec::new(input).unwrap();
+            let normalized = spec.normalize();
+            match &normalized {
+                TargetSpec::Expression(expr) => {
+                    assert_eq!(expr.expression_str(), expected, "for input {}", input)
+                }
+                TargetSpec::Triple(triple) => {
+                    panic!("expected expression, got triple {:?}", triple)
+                }
+            }
+            // Normalization is idempotent.
+            assert_eq!(normalized.normalize(), normalized, "for input {}", input);
+        }
+
+        let a = TargetSpec::new("cfg(all(unix, target_arch = \"x86_64\"))").unwrap();
+        let b = TargetSpec::new("cfg(all(target_arch = \"x86_64\", target_family = \"unix\"))")
+            .unwrap();
+        assert_ne!(a, b);
+        assert_eq!(a.normalize(), b.normalize());
+    }
+
+    #[test]
+    fn test_version() {
+        let triple = Triple::new("x86_64-unknown-linux-gnu").unwrap();
+        let platform = Platform::builder(triple.clone())
+            .set_rustc_version(semver::Version::new(1, 70, 0))
+            .build();
+        let unknown_version = Platform::builder(triple).build();
+
+        let cases = [
+            ("cfg(version(\"1.70\"))", Some(true), None),
+            ("cfg(version(\"1.71\"))", Some(false), None),
+            ("cfg(all(unix, version(\"1.60.0\")))", Some(true), None),
+            (
+                "cfg(all(windows, version(\"1.60.0\")))",
+                Some(false),
+                Some(false),
+            ),
+            ("cfg(not(version(\"1.71\")))", Some(true), None),
+            ("cfg(version(\"not-a-version\"))", Some(false), None),
+        ];
+        for (input, expected, expected_unknown) in cases {
+            let spec = TargetSpec::new(input).unwrap();
+            assert_eq!(spec.eval(&platform), expected, "for input {}", input);
+            assert_eq!(
+                spec.eval(&unknown_version),
+                expected_unknown,
+                "for input {} with unknown version",
+                input
+            );
+            let expr = match &spec {
+                TargetSpec::Expression(expr) => expr,
+                TargetSpec::Triple(_) => panic!("expected expression"),
+            };
+            assert_eq!(
+                format!("cfg({})", expr.expression_str()),
+                input,
+                "expression_str is the original string"
+            );
+        }
+
+        let expr = TargetExpression::new("cfg(any(version ( \"1.70\" ), unix, all()))").unwrap();
+        assert_eq!(expr.normalize().expression_str(), "all()");
+        let expr = TargetExpression::new("cfg(all(version ( \"1.70\" ), unix))").unwrap();
+        assert_eq!(
+            expr.normalize().expression_str(),
+            "all(unix, version(\"1.70\"))"
+        );
+
+        // Errors in expressions with version predicates point to the right place.
+        let err = TargetExpression::new("cfg(all(version(\"1.70\"), not(a, b)))").unwrap_err();
+        match err {
+            Error::InvalidExpression(err) => {
+                assert_eq!(err.input, "cfg(all(version(\"1.70\"), not(a, b)))");
+                assert_eq!(&err.input[err.span], "not(a, b)");
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
     #[test]
     fn test_extra() {
         let res = TargetSpec::new("cfg(unix)this-is-extra");
@@ -0,0 +1,370 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{builtin_targets, EvalMode, Platform, TargetExpression, TargetSpec, Triple};
+use cfg_expr::{expr::Logic, Predicate};
+use std::collections::BTreeMap;
+
+/// A boolean combination of [`TargetSpec`] instances.
+///
+/// A `TargetSpecExpr` is produced by the set operations on `TargetSpec`:
+/// [`union`](TargetSpec::union), [`intersection`](TargetSpec::intersection) and
+/// [`negation`](TargetSpec::negation). It can be evaluated against platforms just like a
+/// `TargetSpec`, and can be checked for satisfiability.
+///
+/// # Examples
+///
+/// Detecting mutually exclusive `[target.'cfg(...)']` sections:
+///
+/// ```
+/// use target_spec::TargetSpec;
+///
+/// let unix: TargetSpec = "cfg(unix)".parse().unwrap();
+/// let windows: TargetSpec = "cfg(windows)".parse().unwrap();
+/// let x86_64: TargetSpec = "cfg(target_arch = \"x86_64\")".parse().unwrap();
+///
+/// // No platform is both unix and windows.
+/// assert!(!unix.overlaps(&windows));
+/// // But there are platforms that are both unix and x86_64.
+/// assert!(unix.overlaps(&x86_64));
+///
+/// // A spec and its negation never overlap.
+/// assert!(!unix.negation().intersection(&unix.clone().into()).is_satisfiable());
+/// ```
+#[derive(Clone, Debug)]
+pub enum TargetSpecExpr {
+    /// A single target spec.
+    Spec(TargetSpec),
+
+    /// Matches if any of the sub-expressions match.
+    ///
+    /// An empty `Any` never matches.
+    Any(Vec<TargetSpecExpr>),
+
+    /// Matches if all of the sub-expressions match.
+    ///
+    /// An empty `All` always matches.
+    All(Vec<TargetSpecExpr>),
+
+    /// Matches if the sub-expression doesn't match.
+    Not(Box<TargetSpecExpr>),
+}
+
+impl TargetSpecExpr {
+    /// Returns an expression that matches if either `self` or `other` match.
+    pub fn union(&self, other: &TargetSpecExpr) -> TargetSpecExpr {
+        TargetSpecExpr::Any(vec![self.clone(), other.clone()])
+    }
+
+    /// Returns an expression that matches if both `self` and `other` match.
+    pub fn intersection(&self, other: &TargetSpecExpr) -> TargetSpecExpr {
+        TargetSpecExpr::All(vec![self.clone(), other.clone()])
+    }
+
+    /// Returns an expression that matches if `self` doesn't match.
+    pub fn negation(&self) -> TargetSpecExpr {
+        TargetSpecExpr::Not(Box::new(self.clone()))
+    }
+
+    /// Evaluates this expression against the given platform.
+    ///
+    /// Returns `Some(true)` if there's a match, `Some(false)` if there's none, or `None` if the
+    /// result of the evaluation is unknown.
+    pub fn eval(&self, platform: &Platform) -> Option<bool> {
+        self.eval_with_mode(platform, EvalMode::Cargo)
+    }
+
+    /// Evaluates this expression against the given platform, using the given evaluation mode.
+    ///
+    /// For more, see [`EvalMode`].
+    pub fn eval_with_mode(&self, platform: &Platform, mode: EvalMode) -> Option<bool> {
+        self.fold(&mut |spec| spec.eval_with_mode(platform, mode))
+    }
+
+    /// Returns true if there's at least one platform that this expression could match.
+    ///
+    /// The set of platforms considered consists of all [builtin targets](crate::builtin_targets)
+    /// as well as any triples referenced in the expression, combined with every possible
+    /// assignment of target features, flags, key-value cfgs and compiler-set cfgs like `test`.
+    ///
+    /// This check is conservative with respect to target features: for example, it doesn't know
+    /// that `target_feature = "sse2"` can never be enabled on wasm.
+    pub fn is_satisfiable(&self) -> bool {
+        let atoms = self.free_atoms();
+
+        let mut triples: Vec<Triple> = builtin_targets().map(|target| target.to_triple()).collect();
+        self.collect_triples(&mut triples);
+        triples.sort_unstable();
+        triples.dedup();
+
+        triples.iter().any(|triple| {
+            let mut assignment = BTreeMap::new();
+            self.solve(triple, &atoms, &mut assignment)
+        })
+    }
+
+    /// Returns true if there's at least one platform that both `self` and `other` could match.
+    ///
+    /// For more about the set of platforms considered, see [`is_satisfiable`](Self::is_satisfiable).
+    pub fn overlaps(&self, other: &TargetSpecExpr) -> bool {
+        self.intersection(other).is_satisfiable()
+    }
+
+    // ---
+    // Helper methods
+    // ---
+
+    fn fold(&self, eval_spec: &mut impl FnMut(&TargetSpec) -> Option<bool>) -> Option<bool> {
+        // Note: Logic::and and Logic::or must be called explicitly, since Option has inherent
+        // methods with the same names.
+        match self {
+            TargetSpecExpr::Spec(spec) => eval_spec(spec),
+            TargetSpecExpr::Any(exprs) => exprs.iter().fold(Logic::bottom(), |acc, expr| {
+                Logic::or(acc, expr.fold(eval_spec))
+            }),
+            TargetSpecExpr::All(exprs) => exprs.iter().fold(Logic::top(), |acc, expr| {
+                Logic::and(acc, expr.fold(eval_spec))
+            }),
+            TargetSpecExpr::Not(expr) => Logic::not(expr.fold(eval_spec)),
+        }
+    }
+
+    fn for_each_spec<'a>(&'a self, f: &mut impl FnMut(&'a TargetSpec)) {
+        match self {
+            TargetSpecExpr::Spec(spec) => f(spec),
+            TargetSpecExpr::Any(exprs) | TargetSpecExpr::All(exprs) => {
+                exprs.iter().for_each(|expr| expr.for_each_spec(f))
+            }
+            TargetSpecExpr::Not(expr) => expr.for_each_spec(f),
+        }
+    }
+
+    fn collect_triples(&self, triples: &mut Vec<Triple>) {
+        self.for_each_spec(&mut |spec| {
+            if let TargetSpec::Triple(triple) = spec {
+                triples.push(triple.clone());
+            }
+        });
+    }
+
+    fn free_atoms(&self) -> Vec<FreeAtom> {
+        let mut atoms = Vec::new();
+        self.for_each_spec(&mut |spec| {
+            if let TargetSpec::Expression(expr) = spec {
+                atoms.extend(expr.predicates().filter_map(|pred| FreeAtom::new(&pred)));
+            }
+        });
+        atoms.sort_unstable();
+        atoms.dedup();
+        atoms
+    }
+
+    /// Evaluates `self` against `triple`, with free atoms taking on values from `assignment`.
+    fn eval_partial(&self, triple: &Triple, assignment: &BTreeMap<FreeAtom, bool>) -> Option<bool> {
+        self.fold(&mut |spec| match spec {
+            TargetSpec::Triple(spec_triple) => Some(spec_triple == triple),
+            TargetSpec::Expression(expr) => eval_expr_partial(expr, triple, assignment),
+        })
+    }
+
+    /// Returns true if some assignment of the free atoms makes `self` true against `triple`.
+    ///
+    /// This uses three-valued logic to prune the search: unassigned atoms are unknown, and
+    /// branching stops as soon as the result is known.
+    fn solve(
+        &self,
+        triple: &Triple,
+        atoms: &[FreeAtom],
+        assignment: &mut BTreeMap<FreeAtom, bool>,
+    ) -> bool {
+        match self.eval_partial(triple, assignment) {
+            Some(result) => result,
+            None => {
+                let atom = match atoms.iter().find(|atom| !assignment.contains_key(atom)) {
+                    Some(atom) => atom.clone(),
+                    // All atoms are assigned, so this can't happen -- but be conservative.
+                    None => return true,
+                };
+                for value in [true, false] {
+                    assignment.insert(atom.clone(), value);
+                    if self.solve(triple, atoms, assignment) {
+                        assignment.remove(&atom);
+                        return true;
+                    }
+                }
+                assignment.remove(&atom);
+                false
+            }
+        }
+    }
+}
+
+impl From<TargetSpec> for TargetSpecExpr {
+    #[inline]
+    fn from(spec: TargetSpec) -> Self {
+        TargetSpecExpr::Spec(spec)
+    }
+}
+
+/// ## Set operations
+///
+/// These methods combine `TargetSpec` instances into [`TargetSpecExpr`] instances.
+impl TargetSpec {
+    /// Returns an expression that matches if either `self` or `other` match.
+    pub fn union(&self, other: &TargetSpec) -> TargetSpecExpr {
+        TargetSpecExpr::Any(vec![self.clone().into(), other.clone().into()])
+    }
+
+    /// Returns an expression that matches if both `self` and `other` match.
+    pub fn intersection(&self, other: &TargetSpec) -> TargetSpecExpr {
+        TargetSpecExpr::All(vec![self.clone().into(), other.clone().into()])
+    }
+
+    /// Returns an expression that matches if `self` doesn't match.
+    pub fn negation(&self) -> TargetSpecExpr {
+        TargetSpecExpr::Not(Box::new(self.clone().into()))
+    }
+
+    /// Returns true if there's at least one platform that this spec could match.
+    ///
+    /// For more about the set of platforms considered, see [`TargetSpecExpr::is_satisfiable`].
+    pub fn is_satisfiable(&self) -> bool {
+        TargetSpecExpr::from(self.clone()).is_satisfiable()
+    }
+
+    /// Returns true if there's at least one platform that both `self` and `other` could match.
+    ///
+    /// Two specs that don't overlap are mutually exclusive. For more about the set of platforms
+    /// considered, see [`TargetSpecExpr::is_satisfiable`].
+    pub fn overlaps(&self, other: &TargetSpec) -> bool {
+        self.intersection(other).is_satisfiable()
+    }
+}
+
+/// A predicate whose value isn't determined by the target triple.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum FreeAtom {
+    TargetFeature(String),
+    Flag(String),
+    KeyValue(String, String),
+    Test,
+    DebugAssertions,
+    ProcMacro,
+}
+
+impl FreeAtom {
+    fn new(pred: &Predicate<'_>) -> Option<Self> {
+        match pred {
+            Predicate::Target(_) | Predicate::Feature(_) => None,
+            Predicate::TargetFeature(feature) => Some(FreeAtom::TargetFeature(feature.to_string())),
+            Predicate::Flag(flag) => Some(FreeAtom::Flag(flag.to_string())),
+            Predicate::KeyValue { key, val } => {
+                Some(FreeAtom::KeyValue(key.to_string(), val.to_string()))
+            }
+            Predicate::Test => Some(FreeAtom::Test),
+            Predicate::DebugAssertions => Some(FreeAtom::DebugAssertions),
+            Predicate::ProcMacro => Some(FreeAtom::ProcMacro),
+        }
+    }
+}
+
+fn eval_expr_partial(
+    expr: &TargetExpression,
+    triple: &Triple,
+    assignment: &BTreeMap<FreeAtom, bool>,
+) -> Option<bool> {
+    expr.eval_with(|pred| match pred {
+        Predicate::Target(target) => Some(triple.matches(target)),
+        // Cargo never sets features.
+        Predicate::Feature(_) => Some(false),
+        other => {
+            let atom = FreeAtom::new(other).expect("all other predicates are free atoms");
+            assignment.get(&atom).copied()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TargetFeatures;
+
+    fn spec(input: &str) -> TargetSpec {
+        input.parse().expect("spec is valid")
+    }
+
+    #[test]
+    fn test_eval() {
+        let linux = Platform::new("x86_64-unknown-linux-gnu", TargetFeatures::Unknown).unwrap();
+        let windows = Platform::new("x86_64-pc-windows-msvc", TargetFeatures::Unknown).unwrap();
+
+        let expr = spec("cfg(unix)").union(&spec("x86_64-pc-windows-msvc"));
+        assert_eq!(expr.eval(&linux), Some(true));
+        assert_eq!(expr.eval(&windows), Some(true));
+        assert_eq!(expr.negation().eval(&linux), Some(false));
+
+        let expr = spec("cfg(unix)").intersection(&spec("cfg(target_feature = \"sse2\")"));
+        assert_eq!(expr.eval(&linux), None);
+        assert_eq!(expr.eval(&windows), Some(false));
+
+        assert_eq!(TargetSpecExpr::Any(vec![]).eval(&linux), Some(false));
+        assert_eq!(TargetSpecExpr::All(vec![]).eval(&linux), Some(true));
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let pairs = [
+            ("cfg(unix)", "cfg(windows)", false),
+            ("cfg(unix)", "cfg(target_os = \"linux\")", true),
+            (
+                "cfg(target_os = \"linux\")",
+                "cfg(target_os = \"macos\")",
+                false,
+            ),
+            ("cfg(unix)", "cfg(not(unix))", false),
+            ("cfg(all(unix, loom))", "cfg(all(unix, not(loom)))", false),
+            (
+                "cfg(all(unix, loom))",
+                "cfg(all(unix, tokio_unstable))",
+                true,
+            ),
+            (
+                "cfg(target_feature = \"sse2\")",
+                "cfg(not(target_feature = \"sse2\"))",
+                false,
+            ),
+            (
+                "cfg(target_feature = \"sse2\")",
+                "cfg(target_feature = \"avx\")",
+                true,
+            ),
+            ("cfg(feature = \"foo\")", "cfg(unix)", false),
+            ("x86_64-unknown-linux-gnu", "cfg(unix)", true),
+            ("x86_64-unknown-linux-gnu", "cfg(windows)", false),
+            ("x86_64-unknown-linux-gnu", "x86_64-unknown-linux-gnu", true),
+            (
+                "x86_64-unknown-linux-gnu",
+                "x86_64-unknown-linux-musl",
+                false,
+            ),
+            // x86_64-pc-darwin isn't builtin, but is included because it's referenced.
+            ("x86_64-pc-darwin", "cfg(target_os = \"macos\")", true),
+        ];
+
+        for (a, b, expected) in pairs {
+            assert_eq!(spec(a).overlaps(&spec(b)), expected, "{} overlaps {}", a, b);
+            assert_eq!(spec(b).overlaps(&spec(a)), expected, "{} overlaps {}", b, a);
+        }
+    }
+
+    #[test]
+    fn test_satisfiable() {
+        assert!(spec("cfg(unix)").is_satisfiable());
+        assert!(!spec("cfg(all(unix, windows))").is_satisfiable());
+        assert!(!spec("cfg(all(windows, target_env = \"musl\"))").is_satisfiable());
+        assert!(!spec("cfg(any())").is_satisfiable());
+        assert!(spec("cfg(all())").is_satisfiable());
+        assert!(spec("cfg(any(a, b, c, d, e, f, g, h, i, j, k, l, m, n, o, p))").is_satisfiable());
+        assert!(!TargetSpecExpr::Any(vec![]).is_satisfiable());
+    }
+}
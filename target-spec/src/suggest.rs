@@ -0,0 +1,89 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Suggestions for misspelled triples and cfg keys.
+
+/// Returns the candidate closest to `input` by edit distance, if any is close enough to be a
+/// likely misspelling.
+///
+/// This uses the same threshold as rustc's suggestions: a third of the length of the input.
+pub(crate) fn best_match<'a>(
+    input: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let max_distance = input.chars().count().max(3) / 3;
+    let mut best = None;
+    for candidate in candidates {
+        if candidate == input {
+            // The input is already correct.
+            return None;
+        }
+        let distance = edit_distance(input, candidate);
+        // Earlier candidates win ties.
+        if distance <= max_distance
+            && best.map_or(true, |(best_distance, _)| distance < best_distance)
+        {
+            best = Some((distance, candidate));
+        }
+    }
+    best.map(|(_, candidate)| candidate)
+}
+
+/// Computes the edit distance between two strings: the number of insertions, deletions,
+/// substitutions and transpositions of adjacent characters needed to turn one into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // dist[i][j] is the distance between the first i characters of a and the first j of b.
+    let mut dist = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in dist.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, value) in dist[0].iter_mut().enumerate() {
+        *value = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut value = (dist[i - 1][j] + 1)
+                .min(dist[i][j - 1] + 1)
+                .min(dist[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                value = value.min(dist[i - 2][j - 2] + 1);
+            }
+            dist[i][j] = value;
+        }
+    }
+    dist[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("target_oss", "target_os"), 1);
+        assert_eq!(edit_distance("nto", "not"), 1);
+        assert_eq!(edit_distance("ayn", "any"), 1);
+    }
+
+    #[test]
+    fn test_best_match() {
+        let keys = ["target_os", "target_arch", "target_env"];
+        assert_eq!(best_match("target_oss", keys), Some("target_os"));
+        assert_eq!(best_match("target_ach", keys), Some("target_arch"));
+        assert_eq!(
+            best_match("target_os", keys),
+            None,
+            "exact matches aren't suggested"
+        );
+        assert_eq!(best_match("unix", keys), None, "nothing is close enough");
+        assert_eq!(best_match("nott", ["all", "any", "not"]), Some("not"));
+    }
+}
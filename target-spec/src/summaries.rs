@@ -6,11 +6,18 @@
 //! Some users of `target-spec` may want to serialize and deserialize its data structures into, say,
 //! TOML files. This module provides facilities for that.
 //!
+//! [`Platform`] and [`TargetSpec`](crate::TargetSpec) also implement `Serialize` and
+//! `Deserialize` directly, through their summaries and string forms respectively.
+//!
 //! Summaries require the `summaries` feature to be enabled.
 
-use crate::{Error, Platform, TargetFeatures};
+use crate::{Error, Platform, PlatformBuilder, TargetFeatures, Triple};
+use semver::Version;
 use serde::{Deserialize, Serialize};
-use std::{borrow::Cow, collections::BTreeSet};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+};
 
 impl Platform {
     /// Converts this `Platform` to a serializable form.
@@ -26,6 +33,10 @@ impl Platform {
 ///
 /// This structure can be serialized and deserialized using `serde`.
 ///
+/// Summaries don't include a platform's custom key-value cfgs or its rustc version, and only store
+/// the triple as a string: custom and opaque triples are turned back into platforms through
+/// [`Triple::new`]. To serialize a `Platform` losslessly, serialize the `Platform` directly.
+///
 /// Requires the `summaries` feature to be enabled.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 #[serde(rename_all = "kebab-case")]
@@ -37,17 +48,33 @@ pub struct PlatformSummary {
     pub target_features: TargetFeaturesSummary,
 
     /// The flags enabled.
+    ///
+    /// This also includes the compiler-set cfgs `test`, `debug_assertions` and `proc_macro`, if
+    /// they're enabled on the platform.
     #[serde(skip_serializing_if = "BTreeSet::is_empty", default)]
     pub flags: BTreeSet<String>,
 }
 
 impl PlatformSummary {
     /// Creates a new `PlatformSummary` instance from a platform.
+    ///
+    /// The platform's custom key-value cfgs and rustc version aren't part of the summary.
     pub fn new(platform: &Platform) -> Self {
+        let mut flags: BTreeSet<_> = platform.flags().map(|flag| flag.to_string()).collect();
+        for (name, enabled) in [
+            (TEST, platform.test()),
+            (DEBUG_ASSERTIONS, platform.debug_assertions()),
+            (PROC_MACRO, platform.proc_macro()),
+        ] {
+            if enabled {
+                flags.insert(name.to_owned());
+            }
+        }
+
         Self {
             triple: platform.triple_str().to_string(),
             target_features: TargetFeaturesSummary::new(platform.target_features()),
-            flags: platform.flags().map(|flag| flag.to_string()).collect(),
+            flags,
         }
     }
 
@@ -55,15 +82,35 @@ impl PlatformSummary {
     ///
     /// Returns an `Error` if the platform was unknown.
     pub fn to_platform(&self) -> Result<Platform, Error> {
-        let mut platform = Platform::new(
-            self.triple.to_owned(),
-            self.target_features.to_target_features(),
-        )?;
-        platform.add_flags(self.flags.iter().cloned());
-        Ok(platform)
+        Ok(self.to_builder()?.build())
+    }
+
+    fn to_builder(&self) -> Result<PlatformBuilder, Error> {
+        let triple = Triple::new(self.triple.to_owned()).map_err(Error::UnknownPlatformTriple)?;
+        Ok(self.to_builder_with(triple))
+    }
+
+    fn to_builder_with(&self, triple: Triple) -> PlatformBuilder {
+        let mut builder = PlatformBuilder::new(triple);
+        builder.set_target_features(self.target_features.to_target_features());
+        for flag in &self.flags {
+            match flag.as_str() {
+                TEST => builder.set_test(true),
+                DEBUG_ASSERTIONS => builder.set_debug_assertions(true),
+                PROC_MACRO => builder.set_proc_macro(true),
+                _ => builder.add_flag(flag.clone()),
+            };
+        }
+        builder
     }
 }
 
+// Compiler-set cfgs, which are stored as flags in summaries. cfg-expr never parses these as
+// flags, so they can't conflict with custom flags.
+const TEST: &str = "test";
+const DEBUG_ASSERTIONS: &str = "debug_assertions";
+const PROC_MACRO: &str = "proc_macro";
+
 /// An owned, serializable version of [`TargetFeatures`].
 ///
 /// This type can be serialized and deserialized using `serde`.
@@ -161,6 +208,223 @@ mod platform_impl {
             flags: BTreeSet<String>,
         },
     }
+
+    /// `Platform` is serialized as a [`PlatformSummary`], along with its rustc version and a
+    /// `key-values` table for its custom key-value cfgs.
+    ///
+    /// Triples that [`Triple::new`] wouldn't recreate from their string are serialized with
+    /// enough information to restore them: custom triples carry their target JSON specification,
+    /// opaque triples are marked as such, and triples created through
+    /// [`Triple::new_from_lexicon`] carry their components.
+    ///
+    /// Serializing a custom triple fails if it wasn't created from a JSON specification.
+    impl Serialize for Platform {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            PlatformRepr::new(self)
+                .map_err(serde::ser::Error::custom)?
+                .serialize(serializer)
+        }
+    }
+
+    /// `Platform` is deserialized from the same forms as a [`PlatformSummary`], along with an
+    /// optional rustc version and `key-values` table.
+    impl<'de> Deserialize<'de> for Platform {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let repr = match PlatformDeserialize::deserialize(deserializer)? {
+                PlatformDeserialize::String(triple) => PlatformRepr {
+                    summary: PlatformSummary {
+                        triple,
+                        target_features: TargetFeaturesSummary::default(),
+                        flags: BTreeSet::default(),
+                    },
+                    custom_target: None,
+                    opaque: false,
+                    triple_components: None,
+                    rustc_version: None,
+                    key_values: BTreeMap::default(),
+                },
+                PlatformDeserialize::Full(repr) => repr,
+            };
+            repr.to_platform().map_err(serde::de::Error::custom)
+        }
+    }
+
+    /// The serialized form of a `Platform`, which includes everything in a summary.
+    #[derive(Deserialize, Serialize)]
+    #[serde(rename_all = "kebab-case")]
+    struct PlatformRepr {
+        #[serde(flatten)]
+        summary: PlatformSummary,
+        /// The target JSON specification of a custom triple.
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        custom_target: Option<String>,
+        /// Whether the triple is opaque.
+        #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+        opaque: bool,
+        /// The components of a triple, if they're different from the ones parsed from its string.
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        triple_components: Option<String>,
+        /// The version of rustc used to evaluate `cfg(version("..."))`, if known.
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        rustc_version: Option<Version>,
+        // This is serialized as a table, so it must come after all plain values for TOML.
+        /// The custom key-value cfgs enabled, as a map of keys to values.
+        ///
+        /// For example, `cfg(tracing = "on")` is represented as `tracing = ["on"]`.
+        #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+        key_values: BTreeMap<String, BTreeSet<String>>,
+    }
+
+    impl PlatformRepr {
+        fn new(platform: &Platform) -> Result<Self, String> {
+            let mut key_values = BTreeMap::new();
+            for (key, value) in platform.key_values() {
+                key_values
+                    .entry(key.to_owned())
+                    .or_insert_with(BTreeSet::new)
+                    .insert(value.to_owned());
+            }
+            let triple = platform.triple();
+            Ok(Self {
+                summary: PlatformSummary::new(platform),
+                custom_target: custom_target(triple)?,
+                opaque: triple.is_opaque(),
+                triple_components: triple
+                    .nonstandard_components()
+                    .map(|components| components.to_string()),
+                rustc_version: platform.rustc_version().cloned(),
+                key_values,
+            })
+        }
+
+        fn to_platform(&self) -> Result<Platform, String> {
+            let mut builder = self.summary.to_builder_with(self.to_triple()?);
+            if let Some(rustc_version) = &self.rustc_version {
+                builder.set_rustc_version(rustc_version.clone());
+            }
+            for (key, values) in &self.key_values {
+                for value in values {
+                    builder.add_key_value(key.clone(), value.clone());
+                }
+            }
+            Ok(builder.build())
+        }
+
+        fn to_triple(&self) -> Result<Triple, String> {
+            let triple_str = self.summary.triple.clone();
+            if let Some(json) = &self.custom_target {
+                return custom_triple(triple_str, json);
+            }
+            if self.opaque {
+                return Ok(Triple::new_opaque(triple_str));
+            }
+            match &self.triple_components {
+                Some(components) => {
+                    let lexicon_triple = components.parse().map_err(|err| {
+                        format!("invalid triple components `{}`: {}", components, err)
+                    })?;
+                    Ok(Triple::new_from_lexicon(triple_str, lexicon_triple))
+                }
+                None => Triple::new(triple_str)
+                    .map_err(|err| Error::UnknownPlatformTriple(err).to_string()),
+            }
+        }
+    }
+
+    #[cfg(feature = "custom")]
+    fn custom_target(triple: &Triple) -> Result<Option<String>, String> {
+        if !triple.is_custom() {
+            return Ok(None);
+        }
+        match triple.custom_json() {
+            Some(json) => Ok(Some(json.to_owned())),
+            None => Err(format!(
+                "custom triple `{}` wasn't created from a target JSON specification",
+                triple.as_str()
+            )),
+        }
+    }
+
+    #[cfg(not(feature = "custom"))]
+    fn custom_target(_triple: &Triple) -> Result<Option<String>, String> {
+        Ok(None)
+    }
+
+    #[cfg(feature = "custom")]
+    fn custom_triple(triple_str: String, json: &str) -> Result<Triple, String> {
+        Triple::new_custom(triple_str, json).map_err(|err| err.to_string())
+    }
+
+    #[cfg(not(feature = "custom"))]
+    fn custom_triple(triple_str: String, _json: &str) -> Result<Triple, String> {
+        Err(format!(
+            "triple `{}` has a custom target specification, which requires the `custom` feature",
+            triple_str
+        ))
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum PlatformDeserialize {
+        String(String),
+        Full(PlatformRepr),
+    }
+}
+
+mod target_spec_impl {
+    use crate::{TargetExpression, TargetSpec};
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    /// `TargetSpec` is serialized as the string it was parsed from, e.g.
+    /// `"x86_64-unknown-linux-gnu"` or `"cfg(unix)"`.
+    impl Serialize for TargetSpec {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match self {
+                TargetSpec::Triple(triple) => triple.as_str().serialize(serializer),
+                TargetSpec::Expression(expr) => expr.serialize(serializer),
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for TargetSpec {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let input = String::deserialize(deserializer)?;
+            TargetSpec::new(input).map_err(D::Error::custom)
+        }
+    }
+
+    /// `TargetExpression` is serialized as the string it was parsed from, including the
+    /// surrounding `cfg()`.
+    impl Serialize for TargetExpression {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            format!("cfg({})", self.expression_str()).serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for TargetExpression {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let input = String::deserialize(deserializer)?;
+            TargetExpression::new(&input).map_err(D::Error::custom)
+        }
+    }
 }
 
 mod target_features_impl {
@@ -187,16 +451,11 @@ mod target_features_impl {
         {
             let d = TargetFeaturesDeserialize::deserialize(deserializer)?;
             match d {
-                TargetFeaturesDeserialize::String(target_features) => {
-                    match target_features.as_str() {
-                        "unknown" => Ok(TargetFeaturesSummary::Unknown),
-                        "all" => Ok(TargetFeaturesSummary::All),
-                        other => Err(D::Error::custom(format!(
-                            "unknown string for target features: {}",
-                            other,
-                        ))),
-                    }
-                }
+                // Accept the canonical string form of `TargetFeatures` as well, e.g. "+avx,+sse2".
+                TargetFeaturesDeserialize::String(target_features) => target_features
+                    .parse::<TargetFeatures>()
+                    .map(|target_features| TargetFeaturesSummary::new(&target_features))
+                    .map_err(D::Error::custom),
                 TargetFeaturesDeserialize::List(target_features) => {
                     Ok(TargetFeaturesSummary::Features(target_features))
                 }
@@ -204,6 +463,28 @@ mod target_features_impl {
         }
     }
 
+    /// `TargetFeatures` is serialized as a [`TargetFeaturesSummary`]: `"unknown"`, `"all"`, or a
+    /// list of features.
+    impl Serialize for TargetFeatures {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            TargetFeaturesSummary::new(self).serialize(serializer)
+        }
+    }
+
+    /// `TargetFeatures` is deserialized from a [`TargetFeaturesSummary`], or from its canonical
+    /// string form.
+    impl<'de> Deserialize<'de> for TargetFeatures {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            Ok(TargetFeaturesSummary::deserialize(deserializer)?.to_target_features())
+        }
+    }
+
     #[derive(Deserialize)]
     #[serde(untagged)]
     enum TargetFeaturesDeserialize {
@@ -291,6 +572,243 @@ mod tests {
             assert_eq!(actual, actual_2, "for input: {}", input);
         }
     }
+
+    #[test]
+    fn platform_serde_roundtrip() {
+        #[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+        struct Wrapper {
+            platform: Platform,
+        }
+
+        let triple = Triple::new("x86_64-unknown-linux-gnu").unwrap();
+        let platform = Platform::builder(triple)
+            .set_target_features(TargetFeatures::features(["sse2", "avx"]))
+            .add_flag("tokio_unstable")
+            .add_key_value("tracing", "on")
+            .add_key_value("tracing", "verbose")
+            .add_key_value("loom", "enabled")
+            .set_rustc_version(Version::new(1, 70, 0))
+            .set_test(true)
+            .set_debug_assertions(true)
+            .build();
+        let wrapper = Wrapper { platform };
+
+        let serialized = toml::to_string(&wrapper).expect("serialized correctly");
+        let deserialized: Wrapper = toml::from_str(&serialized).expect("deserialized correctly");
+        assert_eq!(wrapper, deserialized, "for serialized: {}", serialized);
+
+        let serialized = serde_json::to_string(&wrapper).expect("serialized correctly");
+        let deserialized: Wrapper =
+            serde_json::from_str(&serialized).expect("deserialized correctly");
+        assert_eq!(wrapper, deserialized, "for serialized: {}", serialized);
+
+        let deserialized: Wrapper = toml::from_str(
+            r#"platform = { triple = "x86_64-unknown-linux-gnu", rustc-version = "1.71.0-nightly" }"#,
+        )
+        .expect("deserialized correctly");
+        assert_eq!(
+            deserialized.platform.rustc_version(),
+            Some(&"1.71.0-nightly".parse().unwrap()),
+            "rustc version deserialized"
+        );
+
+        let deserialized: Wrapper = toml::from_str(
+            r#"platform = { triple = "x86_64-unknown-linux-gnu", key-values = { tracing = ["on", "verbose"] } }"#,
+        )
+        .expect("deserialized correctly");
+        assert_eq!(
+            deserialized.platform.key_values().collect::<Vec<_>>(),
+            vec![("tracing", "on"), ("tracing", "verbose")],
+            "key-value cfgs deserialized"
+        );
+        assert_eq!(
+            wrapper
+                .platform
+                .to_summary()
+                .to_platform()
+                .unwrap()
+                .key_values()
+                .len(),
+            0,
+            "summaries don't include key-value cfgs"
+        );
+
+        let err = toml::from_str::<Wrapper>(r#"platform = "cannot-be-known""#)
+            .expect_err("unknown triple fails to deserialize");
+        assert!(
+            err.to_string().contains("unknown platform triple"),
+            "error is about the triple: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn platform_serde_nonstandard_triples() {
+        #[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+        struct Wrapper {
+            platform: Platform,
+        }
+
+        // An opaque triple stays opaque, even if its string could be parsed.
+        let triple = Triple::new_opaque("x86_64-acme-linux-gnu");
+        let wrapper = Wrapper {
+            platform: Platform::from_triple(triple, TargetFeatures::Unknown),
+        };
+        let serialized = toml::to_string(&wrapper).expect("serialized correctly");
+        let deserialized: Wrapper = toml::from_str(&serialized).expect("deserialized correctly");
+        assert_eq!(wrapper, deserialized, "for serialized: {}", serialized);
+        assert!(
+            deserialized.platform.triple().is_opaque(),
+            "for serialized: {}",
+            serialized
+        );
+
+        // A triple with components different from its string keeps those components.
+        let lexicon_triple: target_lexicon::Triple = "riscv64gc-unknown-linux-gnu".parse().unwrap();
+        let triple = Triple::new_from_lexicon("riscv64gc-acme-linux-gnu", lexicon_triple.clone());
+        let wrapper = Wrapper {
+            platform: Platform::from_triple(triple, TargetFeatures::Unknown),
+        };
+        let serialized = toml::to_string(&wrapper).expect("serialized correctly");
+        let deserialized: Wrapper = toml::from_str(&serialized).expect("deserialized correctly");
+        assert_eq!(wrapper, deserialized, "for serialized: {}", serialized);
+        assert_eq!(
+            deserialized.platform.triple().lexicon_triple(),
+            &lexicon_triple,
+            "for serialized: {}",
+            serialized
+        );
+
+        // Triples that Triple::new can parse are serialized without any extra information.
+        let platform = Platform::new("x86_64-unknown-linux-gnu", TargetFeatures::Unknown).unwrap();
+        let serialized = serde_json::to_string(&platform).expect("serialized correctly");
+        assert_eq!(
+            serialized,
+            r#"{"triple":"x86_64-unknown-linux-gnu","target-features":"unknown"}"#
+        );
+    }
+
+    #[cfg(feature = "custom")]
+    #[test]
+    fn platform_serde_custom() {
+        #[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+        struct Wrapper {
+            platform: Platform,
+        }
+
+        static ARMV7EM_JSON: &str = r#"{
+            "arch": "arm",
+            "os": "none",
+            "target-endian": "little",
+            "target-pointer-width": "32",
+            "max-atomic-width": 32,
+            "panic-strategy": "abort"
+        }"#;
+
+        let platform = Platform::new_custom(
+            "armv7em-acme-none-eabi",
+            ARMV7EM_JSON,
+            TargetFeatures::features(["v7"]),
+        )
+        .expect("custom platform is valid");
+        let wrapper = Wrapper { platform };
+
+        let serialized = toml::to_string(&wrapper).expect("serialized correctly");
+        let deserialized: Wrapper = toml::from_str(&serialized).expect("deserialized correctly");
+        assert_eq!(wrapper, deserialized, "for serialized: {}", serialized);
+        let triple = deserialized.platform.triple();
+        assert!(triple.is_custom(), "for serialized: {}", serialized);
+        assert_eq!(triple.custom_json(), Some(ARMV7EM_JSON));
+
+        let spec: crate::TargetSpec = "cfg(panic = \"abort\")".parse().unwrap();
+        assert_eq!(
+            spec.eval(&deserialized.platform),
+            Some(true),
+            "custom target info was restored"
+        );
+
+        // Custom triples that weren't created from JSON can't be serialized.
+        let target_info = triple.target_info().unwrap().clone();
+        let platform = Platform::from_triple(Triple::from(target_info), TargetFeatures::Unknown);
+        let err = serde_json::to_string(&platform).expect_err("serialization fails");
+        assert!(
+            err.to_string().contains("target JSON specification"),
+            "error is about the JSON spec: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn target_features_serde_roundtrip() {
+        #[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+        struct Wrapper {
+            target_features: TargetFeatures,
+        }
+
+        for target_features in [
+            TargetFeatures::Unknown,
+            TargetFeatures::All,
+            TargetFeatures::none(),
+            TargetFeatures::features(["sse2", "avx"]),
+        ] {
+            let wrapper = Wrapper { target_features };
+            let serialized = toml::to_string(&wrapper).expect("serialized correctly");
+            let deserialized: Wrapper =
+                toml::from_str(&serialized).expect("deserialized correctly");
+            assert_eq!(wrapper, deserialized, "for serialized: {}", serialized);
+
+            // The canonical string form is also accepted.
+            let serialized = serde_json::json!({
+                "target_features": wrapper.target_features.to_string(),
+            });
+            let deserialized: Wrapper =
+                serde_json::from_value(serialized.clone()).expect("deserialized correctly");
+            assert_eq!(wrapper, deserialized, "for serialized: {}", serialized);
+        }
+
+        let summary: TargetFeaturesSummary =
+            serde_json::from_str(r#""+avx, sse2""#).expect("deserialized correctly");
+        assert_eq!(
+            summary,
+            TargetFeaturesSummary::Features(
+                ["avx".to_owned(), "sse2".to_owned()].into_iter().collect()
+            )
+        );
+        serde_json::from_str::<TargetFeaturesSummary>(r#""-avx""#)
+            .expect_err("disabled feature fails to deserialize");
+    }
+
+    #[test]
+    fn target_spec_serde_roundtrip() {
+        use crate::TargetSpec;
+
+        #[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+        struct Wrapper {
+            spec: TargetSpec,
+        }
+
+        for input in [
+            "x86_64-unknown-linux-gnu",
+            "cfg(unix)",
+            "cfg(all(target_os = \"linux\", not(target_env = \"musl\")))",
+        ] {
+            let wrapper = Wrapper {
+                spec: TargetSpec::new(input).unwrap(),
+            };
+            let serialized = serde_json::to_string(&wrapper).expect("serialized correctly");
+            assert_eq!(
+                serialized,
+                serde_json::json!({ "spec": input }).to_string(),
+                "serialized as the original string"
+            );
+            let deserialized: Wrapper =
+                serde_json::from_str(&serialized).expect("deserialized correctly");
+            assert_eq!(wrapper, deserialized, "for input: {}", input);
+        }
+
+        serde_json::from_str::<Wrapper>(r#"{ "spec": "cfg(not(unix)" }"#)
+            .expect_err("invalid expression fails to deserialize");
+    }
 }
 
 #[cfg(all(test, feature = "proptest1"))]
@@ -313,5 +831,13 @@ mod proptests {
             assert_eq!(platform.target_features(), platform2.target_features(), "target features match");
             assert_eq!(platform.flags().collect::<HashSet<_>>(), platform2.flags().collect::<HashSet<_>>(), "flags match");
         }
+
+        #[test]
+        fn platform_roundtrip(platform in Platform::strategy(any::<TargetFeatures>())) {
+            let serialized = toml::ser::to_string(&platform).expect("serialization succeeded");
+            let deserialized: Platform = toml::from_str(&serialized).expect("deserialization succeeded");
+            assert_eq!(platform, deserialized, "platform and deserialized should match");
+            assert_eq!(platform.key_values().collect::<HashSet<_>>(), deserialized.key_values().collect::<HashSet<_>>(), "key-values match");
+        }
     }
 }
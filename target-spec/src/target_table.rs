@@ -0,0 +1,247 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{Error, TargetExpression, TargetSpec, Triple};
+
+/// The parsed keys of a Cargo manifest's `[target]` tables.
+///
+/// In a `Cargo.toml`, platform-specific dependencies are specified in tables like
+/// `[target.'cfg(unix)'.dependencies]` or `[target.x86_64-pc-windows-gnu.dependencies]`. The keys
+/// of these tables are either target triples or `cfg()` expressions. `TargetTableKeys` parses and
+/// validates a list of such keys, which is useful for tools that lint manifests.
+///
+/// Parsing follows Cargo's rules, which are stricter than [`TargetSpec::new`]:
+///
+/// * Keys starting with `cfg(` are parsed as `cfg()` expressions.
+/// * All other keys are triples. Cargo accepts triples it doesn't know about (for example, the
+///   names of custom targets), so triples that can't be parsed are accepted as well, and are
+///   [opaque](Triple::is_opaque).
+/// * Aliases and triple patterns aren't supported by Cargo, so they aren't expanded.
+///
+/// Each valid key is also normalized, and keys that normalize to the same specification are
+/// reported as duplicates. For example, `cfg(all(unix, target_os = "linux"))` and
+/// `cfg(all(target_os = "linux", unix))` are duplicates.
+///
+/// # Examples
+///
+/// ```
+/// use target_spec::TargetTableKeys;
+///
+/// let keys = TargetTableKeys::parse([
+///     "cfg(unix)",
+///     "x86_64-pc-windows-msvc",
+///     "cfg(any(unix, unix))",
+///     "cfg(not(unix)",
+/// ]);
+/// assert_eq!(keys.keys().len(), 3);
+///
+/// // cfg(any(unix, unix)) is a duplicate of cfg(unix).
+/// let duplicates: Vec<_> = keys
+///     .duplicates()
+///     .map(|(first, duplicate)| (first.key(), duplicate.key()))
+///     .collect();
+/// assert_eq!(duplicates, vec![("cfg(unix)", "cfg(any(unix, unix))")]);
+///
+/// // cfg(not(unix) is invalid.
+/// assert_eq!(keys.errors().len(), 1);
+/// assert_eq!(keys.errors()[0].0, "cfg(not(unix)");
+/// ```
+#[derive(Clone, Debug)]
+pub struct TargetTableKeys {
+    keys: Vec<TargetTableKey>,
+    errors: Vec<(String, Error)>,
+}
+
+impl TargetTableKeys {
+    /// Parses the given `[target]` table keys, in order.
+    pub fn parse<'a>(keys: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut parsed: Vec<TargetTableKey> = Vec::new();
+        let mut errors = Vec::new();
+
+        for key in keys {
+            let spec = match parse_key(key) {
+                Ok(spec) => spec,
+                Err(err) => {
+                    errors.push((key.to_owned(), err));
+                    continue;
+                }
+            };
+            let normalized = spec.normalize();
+            let duplicate_of = parsed
+                .iter()
+                .position(|existing| existing.normalized == normalized);
+            parsed.push(TargetTableKey {
+                key: key.to_owned(),
+                spec,
+                normalized,
+                duplicate_of,
+            });
+        }
+
+        Self {
+            keys: parsed,
+            errors,
+        }
+    }
+
+    /// Returns the keys that were parsed successfully, in the order they were provided.
+    #[inline]
+    pub fn keys(&self) -> &[TargetTableKey] {
+        &self.keys
+    }
+
+    /// Returns the keys that couldn't be parsed, along with the errors that occurred, in the
+    /// order they were provided.
+    #[inline]
+    pub fn errors(&self) -> &[(String, Error)] {
+        &self.errors
+    }
+
+    /// Returns pairs of keys that normalize to the same specification.
+    ///
+    /// Each pair consists of the first key with a given normalized form, and a later key with the
+    /// same normalized form.
+    pub fn duplicates(&self) -> impl Iterator<Item = (&TargetTableKey, &TargetTableKey)> + '_ {
+        self.keys.iter().filter_map(move |key| {
+            key.duplicate_of
+                .map(|first_idx| (&self.keys[first_idx], key))
+        })
+    }
+
+    /// Returns true if all keys were parsed successfully and there were no duplicates.
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty() && self.duplicates().next().is_none()
+    }
+}
+
+/// A single parsed key of a `[target]` table.
+///
+/// Part of [`TargetTableKeys`].
+#[derive(Clone, Debug)]
+pub struct TargetTableKey {
+    key: String,
+    spec: TargetSpec,
+    normalized: TargetSpec,
+    duplicate_of: Option<usize>,
+}
+
+impl TargetTableKey {
+    /// Returns the key as written in the manifest.
+    #[inline]
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns the specification this key was parsed into.
+    #[inline]
+    pub fn spec(&self) -> &TargetSpec {
+        &self.spec
+    }
+
+    /// Returns the normalized form of [`spec`](Self::spec).
+    ///
+    /// For more about normalization, see [`TargetExpression::normalize`].
+    #[inline]
+    pub fn normalized(&self) -> &TargetSpec {
+        &self.normalized
+    }
+
+    /// Returns true if this key is a triple unknown to `target-spec`.
+    ///
+    /// Cargo accepts such keys, but they might be misspellings of known triples.
+    pub fn is_unknown_triple(&self) -> bool {
+        match &self.spec {
+            TargetSpec::Triple(triple) => triple.is_opaque(),
+            _ => false,
+        }
+    }
+}
+
+fn parse_key(key: &str) -> Result<TargetSpec, Error> {
+    if key.starts_with("cfg(") {
+        TargetExpression::new(key).map(TargetSpec::Expression)
+    } else {
+        // Cargo accepts any triple here, so fall back to an opaque triple for unknown ones.
+        let triple =
+            Triple::new(key.to_owned()).unwrap_or_else(|_| Triple::new_opaque(key.to_owned()));
+        Ok(TargetSpec::Triple(triple))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_table_keys() {
+        let keys = TargetTableKeys::parse([
+            "cfg(all(unix, target_os = \"linux\"))",
+            "x86_64-unknown-linux-gnu",
+            "my-custom-target",
+            "cfg(all(target_os = \"linux\", unix))",
+            "x86_64-unknown-linux-gnu",
+            "linux",
+            "*-apple-darwin",
+            "x86_64-pc-darwin",
+            "cfg(",
+        ]);
+
+        let parsed: Vec<_> = keys.keys().iter().map(|key| key.key()).collect();
+        assert_eq!(
+            parsed,
+            vec![
+                "cfg(all(unix, target_os = \"linux\"))",
+                "x86_64-unknown-linux-gnu",
+                "my-custom-target",
+                "cfg(all(target_os = \"linux\", unix))",
+                "x86_64-unknown-linux-gnu",
+                // Aliases and patterns aren't supported by Cargo, so they're treated as triples.
+                "linux",
+                "*-apple-darwin",
+                "x86_64-pc-darwin",
+            ]
+        );
+        assert!(
+            !keys.keys()[7].is_unknown_triple(),
+            "parsed by target-lexicon"
+        );
+        assert!(!keys.keys()[1].is_unknown_triple());
+        assert!(keys.keys()[2].is_unknown_triple());
+        assert!(
+            keys.keys()[5].is_unknown_triple(),
+            "aliases aren't expanded"
+        );
+        assert!(
+            !keys.keys()[0].is_unknown_triple(),
+            "expressions aren't triples"
+        );
+        assert_eq!(
+            keys.keys()[3].normalized(),
+            &TargetSpec::new("cfg(all(target_os = \"linux\", unix))")
+                .unwrap()
+                .normalize()
+        );
+
+        let duplicates: Vec<_> = keys
+            .duplicates()
+            .map(|(first, duplicate)| (first.key(), duplicate.key()))
+            .collect();
+        assert_eq!(
+            duplicates,
+            vec![
+                (
+                    "cfg(all(unix, target_os = \"linux\"))",
+                    "cfg(all(target_os = \"linux\", unix))"
+                ),
+                ("x86_64-unknown-linux-gnu", "x86_64-unknown-linux-gnu"),
+            ]
+        );
+
+        assert_eq!(keys.errors().len(), 1);
+        assert_eq!(keys.errors()[0].0, "cfg(");
+        assert!(matches!(keys.errors()[0].1, Error::InvalidExpression(_)));
+        assert!(!keys.is_valid());
+
+        assert!(TargetTableKeys::parse(["cfg(unix)", "cfg(windows)"]).is_valid());
+    }
+}
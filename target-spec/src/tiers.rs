@@ -0,0 +1,335 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Information about rustc's target tiers.
+//!
+//! This data is from [the rustc book](https://doc.rust-lang.org/nightly/rustc/platform-support.html)
+//! as of Rust 1.65, matching the set of builtin targets.
+
+use crate::{BuiltinTarget, Triple};
+
+/// The [support tier](https://doc.rust-lang.org/nightly/rustc/platform-support.html) of a target.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[non_exhaustive]
+pub enum TargetTier {
+    /// Tier 1 targets are guaranteed to work, and are tested on every change to rustc.
+    Tier1,
+    /// Tier 2 targets are guaranteed to build, but aren't necessarily tested.
+    Tier2,
+    /// Tier 3 targets are supported by rustc, but aren't built or tested by the Rust
+    /// project.
+    Tier3,
+}
+
+/// Whether the standard library is available for a target.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[non_exhaustive]
+pub enum StdSupport {
+    /// The full standard library is available.
+    Full,
+    /// Only `core` (and possibly `alloc`) are available -- the target is `no_std`.
+    NoStd,
+    /// It isn't known whether the standard library is available.
+    ///
+    /// This is the case for most tier 3 targets.
+    Unknown,
+}
+
+/// Tier information about a target: its support tier, whether host tools are available, and
+/// whether the standard library is available.
+///
+/// Returned by [`BuiltinTarget::tier_info`] and [`Triple::tier_info`].
+///
+/// # Examples
+///
+/// ```
+/// use target_spec::{StdSupport, TargetTier, Triple};
+///
+/// let triple = Triple::new("thumbv7em-none-eabihf").unwrap();
+/// let tier_info = triple.tier_info().expect("builtin triples have tier info");
+/// assert_eq!(tier_info.tier(), TargetTier::Tier2);
+/// assert!(!tier_info.host_tools());
+/// assert_eq!(tier_info.std_support(), StdSupport::NoStd);
+/// ```
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TargetTierInfo {
+    tier: TargetTier,
+    host_tools: bool,
+    std_support: StdSupport,
+}
+
+impl TargetTierInfo {
+    /// Returns the support tier of this target.
+    #[inline]
+    pub fn tier(&self) -> TargetTier {
+        self.tier
+    }
+
+    /// Returns true if host tools (rustc, cargo, etc) are distributed for this target.
+    #[inline]
+    pub fn host_tools(&self) -> bool {
+        self.host_tools
+    }
+
+    /// Returns whether the standard library is available for this target.
+    #[inline]
+    pub fn std_support(&self) -> StdSupport {
+        self.std_support
+    }
+
+    pub(crate) fn for_triple_str(triple_str: &str) -> Self {
+        if TIER1.binary_search(&triple_str).is_ok() {
+            return Self {
+                tier: TargetTier::Tier1,
+                host_tools: true,
+                std_support: StdSupport::Full,
+            };
+        }
+        if TIER2_HOST_TOOLS.binary_search(&triple_str).is_ok() {
+            return Self {
+                tier: TargetTier::Tier2,
+                host_tools: true,
+                std_support: StdSupport::Full,
+            };
+        }
+        if TIER2_STD.binary_search(&triple_str).is_ok() {
+            return Self {
+                tier: TargetTier::Tier2,
+                host_tools: false,
+                std_support: StdSupport::Full,
+            };
+        }
+        if TIER2_NO_STD.binary_search(&triple_str).is_ok() {
+            return Self {
+                tier: TargetTier::Tier2,
+                host_tools: false,
+                std_support: StdSupport::NoStd,
+            };
+        }
+
+        let std_support = if TIER3_NO_STD.binary_search(&triple_str).is_ok() {
+            StdSupport::NoStd
+        } else {
+            StdSupport::Unknown
+        };
+        Self {
+            tier: TargetTier::Tier3,
+            host_tools: false,
+            std_support,
+        }
+    }
+}
+
+impl BuiltinTarget {
+    /// Returns tier information for this target.
+    #[inline]
+    pub fn tier_info(&self) -> TargetTierInfo {
+        TargetTierInfo::for_triple_str(self.triple_str())
+    }
+}
+
+impl Triple {
+    /// Returns tier information for this triple.
+    ///
+    /// Returns `None` if this triple isn't builtin to `target-spec`.
+    pub fn tier_info(&self) -> Option<TargetTierInfo> {
+        BuiltinTarget::new_from_triple_str(self.as_str()).map(|target| target.tier_info())
+    }
+}
+
+// All of these lists are sorted, so that they can be binary searched.
+
+static TIER1: &[&str] = &[
+    "aarch64-unknown-linux-gnu",
+    "i686-pc-windows-gnu",
+    "i686-pc-windows-msvc",
+    "i686-unknown-linux-gnu",
+    "x86_64-apple-darwin",
+    "x86_64-pc-windows-gnu",
+    "x86_64-pc-windows-msvc",
+    "x86_64-unknown-linux-gnu",
+];
+
+static TIER2_HOST_TOOLS: &[&str] = &[
+    "aarch64-apple-darwin",
+    "aarch64-pc-windows-msvc",
+    "aarch64-unknown-linux-musl",
+    "arm-unknown-linux-gnueabi",
+    "arm-unknown-linux-gnueabihf",
+    "armv7-unknown-linux-gnueabihf",
+    "mips-unknown-linux-gnu",
+    "mips64-unknown-linux-gnuabi64",
+    "mips64el-unknown-linux-gnuabi64",
+    "mipsel-unknown-linux-gnu",
+    "powerpc-unknown-linux-gnu",
+    "powerpc64-unknown-linux-gnu",
+    "powerpc64le-unknown-linux-gnu",
+    "riscv64gc-unknown-linux-gnu",
+    "s390x-unknown-linux-gnu",
+    "x86_64-unknown-freebsd",
+    "x86_64-unknown-illumos",
+    "x86_64-unknown-linux-musl",
+    "x86_64-unknown-netbsd",
+];
+
+static TIER2_STD: &[&str] = &[
+    "aarch64-apple-ios",
+    "aarch64-apple-ios-sim",
+    "aarch64-fuchsia",
+    "aarch64-linux-android",
+    "arm-linux-androideabi",
+    "arm-unknown-linux-musleabi",
+    "arm-unknown-linux-musleabihf",
+    "armv5te-unknown-linux-gnueabi",
+    "armv5te-unknown-linux-musleabi",
+    "armv7-linux-androideabi",
+    "armv7-unknown-linux-gnueabi",
+    "armv7-unknown-linux-musleabi",
+    "armv7-unknown-linux-musleabihf",
+    "asmjs-unknown-emscripten",
+    "i586-pc-windows-msvc",
+    "i586-unknown-linux-gnu",
+    "i586-unknown-linux-musl",
+    "i686-linux-android",
+    "i686-unknown-freebsd",
+    "i686-unknown-linux-musl",
+    "mips-unknown-linux-musl",
+    "mips64-unknown-linux-muslabi64",
+    "mips64el-unknown-linux-muslabi64",
+    "mipsel-unknown-linux-musl",
+    "sparc64-unknown-linux-gnu",
+    "sparcv9-sun-solaris",
+    "thumbv7neon-linux-androideabi",
+    "thumbv7neon-unknown-linux-gnueabihf",
+    "wasm32-unknown-emscripten",
+    "wasm32-unknown-unknown",
+    "wasm32-wasi",
+    "x86_64-apple-ios",
+    "x86_64-fortanix-unknown-sgx",
+    "x86_64-fuchsia",
+    "x86_64-linux-android",
+    "x86_64-pc-solaris",
+    "x86_64-sun-solaris",
+    "x86_64-unknown-linux-gnux32",
+    "x86_64-unknown-redox",
+];
+
+static TIER2_NO_STD: &[&str] = &[
+    "aarch64-unknown-none",
+    "aarch64-unknown-none-softfloat",
+    "armebv7r-none-eabi",
+    "armebv7r-none-eabihf",
+    "armv7a-none-eabi",
+    "armv7r-none-eabi",
+    "armv7r-none-eabihf",
+    "nvptx64-nvidia-cuda",
+    "riscv32i-unknown-none-elf",
+    "riscv32imac-unknown-none-elf",
+    "riscv32imc-unknown-none-elf",
+    "riscv64gc-unknown-none-elf",
+    "riscv64imac-unknown-none-elf",
+    "thumbv6m-none-eabi",
+    "thumbv7em-none-eabi",
+    "thumbv7em-none-eabihf",
+    "thumbv7m-none-eabi",
+    "thumbv8m.base-none-eabi",
+    "thumbv8m.main-none-eabi",
+    "thumbv8m.main-none-eabihf",
+    "x86_64-unknown-none",
+];
+
+static TIER3_NO_STD: &[&str] = &[
+    "aarch64-nintendo-switch-freestanding",
+    "aarch64-unknown-uefi",
+    "armv4t-none-eabi",
+    "armv7a-none-eabihf",
+    "avr-unknown-gnu-atmega328",
+    "bpfeb-unknown-none",
+    "bpfel-unknown-none",
+    "i686-unknown-uefi",
+    "mipsel-sony-psp",
+    "mipsel-unknown-none",
+    "msp430-none-elf",
+    "riscv32im-unknown-none-elf",
+    "thumbv4t-none-eabi",
+    "x86_64-unknown-none-linuxkernel",
+    "x86_64-unknown-uefi",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builtin_targets;
+
+    #[test]
+    fn test_lists_sorted_and_builtin() {
+        for list in [
+            TIER1,
+            TIER2_HOST_TOOLS,
+            TIER2_STD,
+            TIER2_NO_STD,
+            TIER3_NO_STD,
+        ] {
+            let mut sorted = list.to_vec();
+            sorted.sort_unstable();
+            sorted.dedup();
+            assert_eq!(list, &sorted[..], "list is sorted and has no duplicates");
+            for triple_str in list {
+                assert!(
+                    BuiltinTarget::new_from_triple_str(triple_str).is_some(),
+                    "{} is builtin",
+                    triple_str
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_builtins_version() {
+        // The tier lists must be updated whenever the builtin targets are.
+        assert_eq!(
+            cfg_expr::targets::rustc_version(),
+            "1.65.0",
+            "tier lists match the rustc version of the builtin targets"
+        );
+    }
+
+    #[test]
+    fn test_tier_info() {
+        let linux = Triple::new("x86_64-unknown-linux-gnu").unwrap();
+        let info = linux.tier_info().unwrap();
+        assert_eq!(info.tier(), TargetTier::Tier1);
+        assert!(info.host_tools());
+        assert_eq!(info.std_support(), StdSupport::Full);
+
+        let wasm = Triple::new("wasm32-unknown-unknown")
+            .unwrap()
+            .tier_info()
+            .unwrap();
+        assert_eq!(wasm.tier(), TargetTier::Tier2);
+        assert!(!wasm.host_tools());
+        assert_eq!(wasm.std_support(), StdSupport::Full);
+
+        let avr = Triple::new("avr-unknown-gnu-atmega328")
+            .unwrap()
+            .tier_info()
+            .unwrap();
+        assert_eq!(avr.tier(), TargetTier::Tier3);
+        assert_eq!(avr.std_support(), StdSupport::NoStd);
+
+        let haiku = Triple::new("x86_64-unknown-haiku")
+            .unwrap()
+            .tier_info()
+            .unwrap();
+        assert_eq!(haiku.tier(), TargetTier::Tier3);
+        assert_eq!(haiku.std_support(), StdSupport::Unknown);
+
+        // Not builtin.
+        assert_eq!(Triple::new("x86_64-pc-darwin").unwrap().tier_info(), None);
+
+        let tier1_count = builtin_targets()
+            .filter(|target| target.tier_info().tier() == TargetTier::Tier1)
+            .count();
+        assert_eq!(tier1_count, TIER1.len());
+    }
+}
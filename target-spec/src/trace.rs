@@ -0,0 +1,282 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{
+    spec::eval_predicate,
+    tree::{render_predicate, ExprTree},
+    EvalMode, Platform, TargetExpression, TargetSpec,
+};
+use cfg_expr::Predicate;
+use std::fmt;
+
+impl TargetSpec {
+    /// Evaluates this specification against the given platform, recording the result of every
+    /// part of it.
+    ///
+    /// The result of the returned trace is the same as the result of [`eval`](Self::eval).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use target_spec::{Platform, TargetFeatures, TargetSpec};
+    ///
+    /// let spec = TargetSpec::new("cfg(all(unix, target_pointer_width = \"32\"))").unwrap();
+    /// let platform = Platform::new("x86_64-unknown-linux-gnu", TargetFeatures::Unknown).unwrap();
+    /// let trace = spec.eval_trace(&platform);
+    /// assert_eq!(trace.result(), Some(false));
+    ///
+    /// // Find the predicates that didn't match.
+    /// let failed: Vec<_> = trace
+    ///     .predicates()
+    ///     .filter(|(_, result)| *result == Some(false))
+    ///     .map(|(predicate, _)| predicate)
+    ///     .collect();
+    /// assert_eq!(failed, vec!["target_pointer_width = \"32\""]);
+    /// ```
+    #[inline]
+    pub fn eval_trace(&self, platform: &Platform) -> EvalTrace {
+        self.eval_trace_with_mode(platform, EvalMode::Cargo)
+    }
+
+    /// Evaluates this specification against the given platform using the given evaluation mode,
+    /// recording the result of every part of it.
+    ///
+    /// The result of the returned trace is the same as the result of
+    /// [`eval_with_mode`](Self::eval_with_mode).
+    pub fn eval_trace_with_mode(&self, platform: &Platform, mode: EvalMode) -> EvalTrace {
+        match self {
+            TargetSpec::Triple(triple) => EvalTrace {
+                result: Some(triple.eval(platform)),
+                kind: EvalTraceKind::Triple(triple.as_str().to_owned()),
+            },
+            TargetSpec::Expression(expr) => trace_expr(expr, platform, mode),
+        }
+    }
+}
+
+/// The result of evaluating a [`TargetSpec`] against a platform, along with the results of its
+/// parts.
+///
+/// For `cfg()` expressions, an `EvalTrace` is a tree that mirrors the expression, and records
+/// the result of each predicate, `all()`, `any()` and `not()`. This is useful to find out why a
+/// specification did or didn't match a platform.
+///
+/// Created by [`TargetSpec::eval_trace`] and [`TargetSpec::eval_trace_with_mode`].
+///
+/// The `Display` implementation renders the trace as an indented tree, with one line per node.
+/// The operands of `all()` and `any()` aren't necessarily in the order they were written in.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EvalTrace {
+    result: Option<bool>,
+    kind: EvalTraceKind,
+}
+
+impl EvalTrace {
+    /// Returns the result of evaluating this node: `Some(true)` if it matched, `Some(false)` if it
+    /// didn't, or `None` if the result is unknown.
+    #[inline]
+    pub fn result(&self) -> Option<bool> {
+        self.result
+    }
+
+    /// Returns the kind of this node, along with its children if any.
+    #[inline]
+    pub fn kind(&self) -> &EvalTraceKind {
+        &self.kind
+    }
+
+    /// Returns the predicates in this trace along with their results, in the order they were
+    /// evaluated.
+    ///
+    /// Predicates are rendered in their canonical form, e.g. `target_os = "linux"`. Triples are
+    /// returned as predicates as well.
+    pub fn predicates(&self) -> impl Iterator<Item = (&str, Option<bool>)> + '_ {
+        let mut out = Vec::new();
+        self.collect_predicates(&mut out);
+        out.into_iter()
+    }
+
+    fn collect_predicates<'a>(&'a self, out: &mut Vec<(&'a str, Option<bool>)>) {
+        match &self.kind {
+            EvalTraceKind::Triple(s) | EvalTraceKind::Predicate(s) => out.push((s, self.result)),
+            EvalTraceKind::Not(operand) => operand.collect_predicates(out),
+            EvalTraceKind::All(operands) | EvalTraceKind::Any(operands) => {
+                for operand in operands {
+                    operand.collect_predicates(out);
+                }
+            }
+        }
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        write!(f, "{:indent$}", "", indent = indent)?;
+        match &self.kind {
+            EvalTraceKind::Triple(s) | EvalTraceKind::Predicate(s) => f.write_str(s)?,
+            EvalTraceKind::Not(_) => f.write_str("not")?,
+            EvalTraceKind::All(_) => f.write_str("all")?,
+            EvalTraceKind::Any(_) => f.write_str("any")?,
+        }
+        match self.result {
+            Some(true) => f.write_str(": true")?,
+            Some(false) => f.write_str(": false")?,
+            None => f.write_str(": unknown")?,
+        }
+
+        match &self.kind {
+            EvalTraceKind::Not(operand) => {
+                writeln!(f)?;
+                operand.fmt_indented(f, indent + 2)
+            }
+            EvalTraceKind::All(operands) | EvalTraceKind::Any(operands) => {
+                for operand in operands {
+                    writeln!(f)?;
+                    operand.fmt_indented(f, indent + 2)?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for EvalTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+/// The kind of a node in an [`EvalTrace`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum EvalTraceKind {
+    /// The specification was a triple, which is compared against the platform's triple.
+    Triple(String),
+    /// A single predicate, rendered in its canonical form, e.g. `target_os = "linux"`.
+    Predicate(String),
+    /// `not()`, and its operand.
+    Not(Box<EvalTrace>),
+    /// `all()`, and its operands.
+    All(Vec<EvalTrace>),
+    /// `any()`, and its operands.
+    Any(Vec<EvalTrace>),
+}
+
+fn trace_expr(expr: &TargetExpression, platform: &Platform, mode: EvalMode) -> EvalTrace {
+    let predicates: Vec<_> = expr.predicates().collect();
+    trace_tree(&expr.tree(), &predicates, platform, mode)
+}
+
+fn trace_tree(
+    tree: &ExprTree,
+    predicates: &[Predicate<'_>],
+    platform: &Platform,
+    mode: EvalMode,
+) -> EvalTrace {
+    let trace_all = |operands: &[ExprTree]| -> Vec<EvalTrace> {
+        operands
+            .iter()
+            .map(|operand| trace_tree(operand, predicates, platform, mode))
+            .collect()
+    };
+
+    match tree {
+        ExprTree::Predicate(idx) => {
+            let pred = &predicates[*idx];
+            EvalTrace {
+                result: eval_predicate(pred, platform, mode),
+                kind: EvalTraceKind::Predicate(render_predicate(pred)),
+            }
+        }
+        ExprTree::Not(operand) => {
+            let operand = trace_tree(operand, predicates, platform, mode);
+            EvalTrace {
+                result: operand.result.map(|result| !result),
+                kind: EvalTraceKind::Not(Box::new(operand)),
+            }
+        }
+        ExprTree::All(operands) => {
+            let operands = trace_all(operands);
+            // all() is false if any operand is false, and unknown if any operand is unknown.
+            let result =
+                operands
+                    .iter()
+                    .try_fold(Some(true), |acc, operand| match (acc, operand.result) {
+                        (_, Some(false)) => Err(()),
+                        (acc, Some(true)) => Ok(acc),
+                        (_, None) => Ok(None),
+                    });
+            EvalTrace {
+                result: result.unwrap_or(Some(false)),
+                kind: EvalTraceKind::All(operands),
+            }
+        }
+        ExprTree::Any(operands) => {
+            let operands = trace_all(operands);
+            // any() is true if any operand is true, and unknown if any operand is unknown.
+            let result =
+                operands
+                    .iter()
+                    .try_fold(Some(false), |acc, operand| match (acc, operand.result) {
+                        (_, Some(true)) => Err(()),
+                        (acc, Some(false)) => Ok(acc),
+                        (_, None) => Ok(None),
+                    });
+            EvalTrace {
+                result: result.unwrap_or(Some(true)),
+                kind: EvalTraceKind::Any(operands),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{builtin_targets, TargetFeatures};
+
+    #[test]
+    fn test_trace_matches_eval() {
+        let specs = [
+            "x86_64-unknown-linux-gnu",
+            "aarch64-apple-darwin",
+            "cfg(all(unix, not(target_os = \"macos\")))",
+            "cfg(any(windows, target_feature = \"sse2\", my_flag))",
+            "cfg(all(target_feature = \"neon\", not(target_feature = \"sse2\")))",
+            "cfg(all())",
+            "cfg(any())",
+        ];
+        for spec_str in specs {
+            let spec = TargetSpec::new(spec_str).unwrap();
+            for target in builtin_targets() {
+                let platform = target.to_platform(TargetFeatures::Unknown);
+                for mode in [EvalMode::Cargo, EvalMode::Strict] {
+                    assert_eq!(
+                        spec.eval_trace_with_mode(&platform, mode).result(),
+                        spec.eval_with_mode(&platform, mode),
+                        "for spec {}, platform {}, mode {:?}",
+                        spec_str,
+                        target,
+                        mode
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_trace_display() {
+        let spec =
+            TargetSpec::new("cfg(any(windows, all(unix, target_feature = \"sse2\")))").unwrap();
+        let platform = Platform::new("x86_64-unknown-linux-gnu", TargetFeatures::Unknown).unwrap();
+        let trace = spec.eval_trace(&platform);
+        assert_eq!(trace.result(), None);
+        assert_eq!(
+            trace.to_string(),
+            "any: unknown\n  \
+               all: unknown\n    \
+                 unix: true\n    \
+                 target_feature = \"sse2\": unknown\n  \
+               windows: false"
+        );
+    }
+}
@@ -0,0 +1,281 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! An owned tree representation of `cfg()` expressions.
+//!
+//! `cfg_expr::Expression` stores expressions in postfix form, and doesn't provide a way to walk
+//! them as trees. This module reconstructs the tree by evaluating the expression with a `Logic`
+//! implementation that builds nodes instead of computing values.
+
+use crate::version::VERSION_KEY;
+use cfg_expr::{
+    expr::{Logic, TargetPredicate},
+    targets::{Endian, Family},
+    Expression, Predicate,
+};
+
+/// A `cfg()` expression as a tree.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum ExprTree {
+    /// A predicate, stored as an index into `Expression::predicates()`.
+    Predicate(usize),
+    Any(Vec<ExprTree>),
+    All(Vec<ExprTree>),
+    Not(Box<ExprTree>),
+}
+
+impl ExprTree {
+    pub(crate) fn new(expr: &Expression) -> Self {
+        let mut next_index = 0;
+        let builder = expr.eval(|_| {
+            let index = next_index;
+            next_index += 1;
+            TreeBuilder(ExprTree::Predicate(index))
+        });
+        builder.0
+    }
+}
+
+/// A `Logic` implementation that builds an `ExprTree`.
+///
+/// `Expression::eval` computes `all()` and `any()` by starting from `top()` or `bottom()` and
+/// folding in operands, last operand first. So the left-hand side of `and` and `or` is always
+/// the `all()` or `any()` node being built, and the right-hand side is always a complete operand.
+#[derive(Debug)]
+struct TreeBuilder(ExprTree);
+
+impl Logic for TreeBuilder {
+    fn top() -> Self {
+        TreeBuilder(ExprTree::All(vec![]))
+    }
+
+    fn bottom() -> Self {
+        TreeBuilder(ExprTree::Any(vec![]))
+    }
+
+    fn and(self, other: Self) -> Self {
+        match self.0 {
+            ExprTree::All(mut operands) => {
+                operands.insert(0, other.0);
+                TreeBuilder(ExprTree::All(operands))
+            }
+            lhs => TreeBuilder(ExprTree::All(vec![lhs, other.0])),
+        }
+    }
+
+    fn or(self, other: Self) -> Self {
+        match self.0 {
+            ExprTree::Any(mut operands) => {
+                operands.insert(0, other.0);
+                TreeBuilder(ExprTree::Any(operands))
+            }
+            lhs => TreeBuilder(ExprTree::Any(vec![lhs, other.0])),
+        }
+    }
+
+    fn not(self) -> Self {
+        TreeBuilder(ExprTree::Not(Box::new(self.0)))
+    }
+}
+
+/// A simplified expression, with predicates rendered to their canonical strings.
+///
+/// The order of the variants determines the order in which operands are rendered.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub(crate) enum NormalizedExpr {
+    Predicate(String),
+    Not(Box<NormalizedExpr>),
+    All(Vec<NormalizedExpr>),
+    Any(Vec<NormalizedExpr>),
+}
+
+impl NormalizedExpr {
+    /// Simplifies an expression:
+    ///
+    /// * nested `any()` and `all()` are flattened
+    /// * duplicate operands are removed, and operands are sorted
+    /// * `not(not(x))` is replaced with `x`
+    /// * tautologies like `any(x, not(x))` become `all()`, and contradictions like
+    ///   `all(x, not(x))` become `any()`
+    /// * `any()` and `all()` with a single operand are replaced with that operand
+    pub(crate) fn new(expr: &Expression) -> Self {
+        let predicates: Vec<_> = expr
+            .predicates()
+            .map(|pred| render_predicate(&pred))
+            .collect();
+        Self::simplify(&ExprTree::new(expr), &predicates)
+    }
+
+    /// Renders this expression, without the surrounding `cfg()`.
+    pub(crate) fn render(&self, out: &mut String) {
+        let render_list = |name: &str, operands: &[NormalizedExpr], out: &mut String| {
+            out.push_str(name);
+            out.push('(');
+            for (idx, operand) in operands.iter().enumerate() {
+                if idx > 0 {
+                    out.push_str(", ");
+                }
+                operand.render(out);
+            }
+            out.push(')');
+        };
+
+        match self {
+            NormalizedExpr::Predicate(pred) => out.push_str(pred),
+            NormalizedExpr::Not(operand) => {
+                out.push_str("not(");
+                operand.render(out);
+                out.push(')');
+            }
+            NormalizedExpr::All(operands) => render_list("all", operands, out),
+            NormalizedExpr::Any(operands) => render_list("any", operands, out),
+        }
+    }
+
+    fn simplify(tree: &ExprTree, predicates: &[String]) -> Self {
+        match tree {
+            ExprTree::Predicate(index) => NormalizedExpr::Predicate(predicates[*index].clone()),
+            ExprTree::Not(operand) => match Self::simplify(operand, predicates) {
+                NormalizedExpr::Not(inner) => *inner,
+                // not(all()) is any(), and not(any()) is all().
+                NormalizedExpr::All(operands) if operands.is_empty() => NormalizedExpr::Any(vec![]),
+                NormalizedExpr::Any(operands) if operands.is_empty() => NormalizedExpr::All(vec![]),
+                other => NormalizedExpr::Not(Box::new(other)),
+            },
+            ExprTree::All(operands) => Self::simplify_list(operands, predicates, true),
+            ExprTree::Any(operands) => Self::simplify_list(operands, predicates, false),
+        }
+    }
+
+    fn simplify_list(operands: &[ExprTree], predicates: &[String], is_all: bool) -> Self {
+        let make = |operands| {
+            if is_all {
+                NormalizedExpr::All(operands)
+            } else {
+                NormalizedExpr::Any(operands)
+            }
+        };
+        // The absorbing element: any() (false) for all(), and all() (true) for any().
+        let absorbing = || {
+            if is_all {
+                NormalizedExpr::Any(vec![])
+            } else {
+                NormalizedExpr::All(vec![])
+            }
+        };
+
+        let mut flattened = Vec::with_capacity(operands.len());
+        for operand in operands {
+            match (Self::simplify(operand, predicates), is_all) {
+                // Operands of the same kind are flattened. (This also drops the identity element,
+                // since it's an empty list.)
+                (NormalizedExpr::All(inner), true) | (NormalizedExpr::Any(inner), false) => {
+                    flattened.extend(inner)
+                }
+                (NormalizedExpr::Any(inner), true) | (NormalizedExpr::All(inner), false)
+                    if inner.is_empty() =>
+                {
+                    return absorbing();
+                }
+                (other, _) => flattened.push(other),
+            }
+        }
+
+        flattened.sort_unstable();
+        flattened.dedup();
+
+        // Check for x and not(x) both being present.
+        let has_complement = flattened.iter().any(|operand| match operand {
+            NormalizedExpr::Not(inner) => flattened.binary_search(&**inner).is_ok(),
+            _ => false,
+        });
+        if has_complement {
+            return absorbing();
+        }
+
+        if flattened.len() == 1 {
+            flattened.pop().expect("length is 1")
+        } else {
+            make(flattened)
+        }
+    }
+}
+
+/// Renders a predicate in its canonical form.
+pub(crate) fn render_predicate(pred: &Predicate<'_>) -> String {
+    match pred {
+        Predicate::Target(target) => match target {
+            TargetPredicate::Abi(abi) => format!("target_abi = \"{}\"", abi),
+            TargetPredicate::Arch(arch) => format!("target_arch = \"{}\"", arch),
+            TargetPredicate::Endian(endian) => {
+                let endian = match endian {
+                    Endian::little => "little",
+                    Endian::big => "big",
+                };
+                format!("target_endian = \"{}\"", endian)
+            }
+            TargetPredicate::Env(env) => format!("target_env = \"{}\"", env),
+            TargetPredicate::Family(family) => {
+                if family == &Family::unix || family == &Family::windows {
+                    family.to_string()
+                } else {
+                    format!("target_family = \"{}\"", family)
+                }
+            }
+            TargetPredicate::HasAtomic(has_atomic) => {
+                format!("target_has_atomic = \"{}\"", has_atomic)
+            }
+            TargetPredicate::Os(os) => format!("target_os = \"{}\"", os),
+            TargetPredicate::Panic(panic) => format!("panic = \"{}\"", panic),
+            TargetPredicate::PointerWidth(width) => {
+                format!("target_pointer_width = \"{}\"", width)
+            }
+            TargetPredicate::Vendor(vendor) => format!("target_vendor = \"{}\"", vendor),
+        },
+        Predicate::Test => "test".to_owned(),
+        Predicate::DebugAssertions => "debug_assertions".to_owned(),
+        Predicate::ProcMacro => "proc_macro".to_owned(),
+        Predicate::Feature(feature) => format!("feature = \"{}\"", feature),
+        Predicate::TargetFeature(feature) => format!("target_feature = \"{}\"", feature),
+        Predicate::Flag(flag) => (*flag).to_owned(),
+        Predicate::KeyValue { key, val } if *key == VERSION_KEY => format!("version(\"{}\")", val),
+        Predicate::KeyValue { key, val } => format!("{} = \"{}\"", key, val),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree() {
+        // cfg-expr's parser emits a leading predicate operand after the operands that follow it,
+        // so the tree's operands aren't necessarily in source order.
+        let expr = Expression::parse("all(z, any(a, b, c), not(d), all())").unwrap();
+        assert_eq!(
+            ExprTree::new(&expr),
+            ExprTree::All(vec![
+                ExprTree::Any(vec![
+                    ExprTree::Predicate(0),
+                    ExprTree::Predicate(1),
+                    ExprTree::Predicate(2),
+                ]),
+                ExprTree::Not(Box::new(ExprTree::Predicate(3))),
+                ExprTree::All(vec![]),
+                ExprTree::Predicate(4),
+            ])
+        );
+
+        let expr = Expression::parse("all(all(a, b), c)").unwrap();
+        assert_eq!(
+            ExprTree::new(&expr),
+            ExprTree::All(vec![
+                ExprTree::All(vec![ExprTree::Predicate(0), ExprTree::Predicate(1)]),
+                ExprTree::Predicate(2),
+            ])
+        );
+
+        let expr = Expression::parse("a").unwrap();
+        assert_eq!(ExprTree::new(&expr), ExprTree::Predicate(0));
+    }
+}
@@ -1,13 +1,17 @@
 // Copyright (c) The cargo-guppy Contributors
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use crate::{errors::TripleParseError, Platform};
+use crate::{builtins::builtin_target_info, errors::TripleParseError, Platform};
 use cfg_expr::{
     expr::TargetMatcher,
-    target_lexicon,
-    targets::{get_builtin_target_by_triple, TargetInfo},
+    target_lexicon::{
+        self, Architecture, BinaryFormat, Endianness, Environment, OperatingSystem, PointerWidth,
+        Vendor,
+    },
+    targets::{Endian, Family, TargetInfo},
     TargetPredicate,
 };
+use semver::Version;
 use std::{borrow::Cow, cmp::Ordering, hash, str::FromStr};
 
 /// A single, specific target, uniquely identified by a triple.
@@ -24,6 +28,8 @@ use std::{borrow::Cow, cmp::Ordering, hash, str::FromStr};
 ///
 /// // Parse a simple target.
 /// let target = Triple::new("x86_64-unknown-linux-gnu").unwrap();
+/// // Apple targets may carry a deployment version.
+/// let target = Triple::new("x86_64-apple-macosx10.14").unwrap();
 /// // This is not a valid triple.
 /// let err = Triple::new("cannot-be-known").unwrap_err();
 /// ```
@@ -39,12 +45,299 @@ impl Triple {
         Ok(Self { inner })
     }
 
+    /// Creates a new `Triple` from a triple string and its components, as a
+    /// [`target_lexicon::Triple`].
+    ///
+    /// This is useful for triples that newer versions of rustc support, but that aren't known to
+    /// the version of [`target-lexicon`](target_lexicon) in use. Target predicates are matched
+    /// against the given components.
+    ///
+    /// Builtin triples are always created from the builtin information, ignoring the given
+    /// components.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use target_spec::{target_lexicon, Platform, TargetFeatures, TargetSpec, Triple};
+    ///
+    /// let mut components = target_lexicon::Triple::unknown();
+    /// components.architecture = target_lexicon::Architecture::X86_64;
+    /// components.operating_system = target_lexicon::OperatingSystem::Linux;
+    /// let triple = Triple::new_from_lexicon("x86_64-unknown-linux-newlibc", components);
+    ///
+    /// let platform = Platform::from_triple(triple, TargetFeatures::Unknown);
+    /// let spec: TargetSpec = "cfg(target_os = \"linux\")".parse().unwrap();
+    /// assert_eq!(spec.eval(&platform), Some(true));
+    /// ```
+    pub fn new_from_lexicon(
+        triple_str: impl Into<Cow<'static, str>>,
+        lexicon_triple: target_lexicon::Triple,
+    ) -> Self {
+        let triple_str = triple_str.into();
+        let inner = match builtin_target_info(&triple_str) {
+            Some(target_info) => TripleInner::new_builtin(target_info),
+            None => TripleInner::Lexicon {
+                triple_str,
+                lexicon_triple,
+            },
+        };
+        Self { inner }
+    }
+
+    /// Creates a new `Triple` from a triple string, without parsing it.
+    ///
+    /// Unlike [`new`](Self::new), this never fails. If the triple isn't builtin to `target-spec`,
+    /// nothing is known about it other than its string, so:
+    ///
+    /// * [`TargetSpec`](crate::TargetSpec) triples are matched by comparing strings, as usual.
+    /// * Target predicates like `cfg(unix)` evaluate to false, or to unknown with
+    ///   [`EvalMode::Strict`](crate::EvalMode::Strict).
+    /// * All components are unknown.
+    ///
+    /// This is useful for dependencies on triples that newer versions of rustc support, but that
+    /// aren't known to `target-spec`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use target_spec::{EvalMode, Platform, TargetFeatures, TargetSpec, Triple};
+    ///
+    /// let triple = Triple::new_opaque("riscv64-unknown-futureos");
+    /// let platform = Platform::from_triple(triple.clone(), TargetFeatures::Unknown);
+    ///
+    /// assert!(triple.eval(&platform));
+    /// let spec: TargetSpec = "cfg(unix)".parse().unwrap();
+    /// assert_eq!(spec.eval(&platform), Some(false));
+    /// assert_eq!(spec.eval_with_mode(&platform, EvalMode::Strict), None);
+    /// ```
+    pub fn new_opaque(triple_str: impl Into<Cow<'static, str>>) -> Self {
+        let triple_str = triple_str.into();
+        let inner = match builtin_target_info(&triple_str) {
+            Some(target_info) => TripleInner::new_builtin(target_info),
+            None => TripleInner::Opaque {
+                triple_str,
+                lexicon_triple: target_lexicon::Triple::unknown(),
+            },
+        };
+        Self { inner }
+    }
+
+    /// Returns true if this triple was created through [`new_opaque`](Self::new_opaque) and
+    /// nothing is known about it other than its string.
+    #[inline]
+    pub fn is_opaque(&self) -> bool {
+        matches!(self.inner, TripleInner::Opaque { .. })
+    }
+
+    /// Returns true if this is a custom triple, created through
+    /// [`new_custom`](Self::new_custom) or from a `cfg-expr` target definition.
+    ///
+    /// Requires the `custom` feature to be enabled.
+    #[cfg(feature = "custom")]
+    #[inline]
+    pub fn is_custom(&self) -> bool {
+        matches!(self.inner, TripleInner::Custom { .. })
+    }
+
+    /// Returns the custom target JSON specification this triple was created from, if it was
+    /// created through [`new_custom`](Self::new_custom).
+    ///
+    /// Requires the `custom` feature to be enabled.
+    #[cfg(feature = "custom")]
+    #[inline]
+    pub fn custom_json(&self) -> Option<&str> {
+        match &self.inner {
+            TripleInner::Custom { json, .. } => json.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the components of a triple created through
+    /// [`new_from_lexicon`](Self::new_from_lexicon), if they're different from the components
+    /// [`new`](Self::new) would parse out of its string.
+    #[cfg(feature = "summaries")]
+    pub(crate) fn nonstandard_components(&self) -> Option<&target_lexicon::Triple> {
+        match &self.inner {
+            TripleInner::Lexicon {
+                triple_str,
+                lexicon_triple,
+            } => match TripleInner::new(triple_str.clone()) {
+                Ok(TripleInner::Lexicon {
+                    lexicon_triple: parsed,
+                    ..
+                }) if &parsed == lexicon_triple => None,
+                _ => Some(lexicon_triple),
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns the `cfg-expr` information about this triple, if it's known.
+    ///
+    /// This is available for builtin triples and custom triples, and is `None` for triples that
+    /// were only parsed by `target-lexicon`.
+    #[inline]
+    pub fn target_info(&self) -> Option<&TargetInfo> {
+        self.inner.target_info()
+    }
+
+    /// Returns the `target-lexicon` representation of this triple.
+    ///
+    /// For triples that `target-lexicon` can't parse, components that are unknown are
+    /// represented as `Unknown`.
+    #[inline]
+    pub fn lexicon_triple(&self) -> &target_lexicon::Triple {
+        self.inner.lexicon_triple()
+    }
+
     /// Returns the string corresponding to this triple.
     #[inline]
     pub fn as_str(&self) -> &str {
         self.inner.as_str()
     }
 
+    /// Returns the minimum OS version for this triple, if it's an Apple triple with a deployment
+    /// version.
+    ///
+    /// Apple toolchains accept triples like `x86_64-apple-macosx10.14` or
+    /// `aarch64-apple-ios14.0-simulator`, which carry the minimum OS version the target is
+    /// deployed to. These triples are based on a builtin target, with the same `cfg()` values:
+    /// for example, `x86_64-apple-macosx10.14` is based on `x86_64-apple-darwin`, and
+    /// `aarch64-apple-ios14.0-simulator` on `aarch64-apple-ios-sim`. The builtin target is
+    /// available through [`target_info`](Self::target_info).
+    ///
+    /// The triple string is preserved, so a versioned triple is a different `Triple` from the
+    /// builtin triple it's based on.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use target_spec::{Platform, TargetFeatures, TargetSpec, Triple};
+    ///
+    /// let triple = Triple::new("x86_64-apple-macosx10.14").unwrap();
+    /// assert_eq!(triple.min_os_version(), Some(&semver::Version::new(10, 14, 0)));
+    /// assert_eq!(triple.target_info().unwrap().triple.as_str(), "x86_64-apple-darwin");
+    ///
+    /// let platform = Platform::from_triple(triple, TargetFeatures::Unknown);
+    /// let spec: TargetSpec = "cfg(target_os = \"macos\")".parse().unwrap();
+    /// assert_eq!(spec.eval(&platform), Some(true));
+    ///
+    /// // Unversioned triples don't have a minimum OS version.
+    /// let triple = Triple::new("x86_64-apple-darwin").unwrap();
+    /// assert_eq!(triple.min_os_version(), None);
+    /// ```
+    #[inline]
+    pub fn min_os_version(&self) -> Option<&Version> {
+        self.inner.min_os_version()
+    }
+
+    /// Returns the CPU architecture for this triple.
+    ///
+    /// This is the architecture as parsed by [`target-lexicon`](target_lexicon), which may be
+    /// more specific than the value matched by `cfg(target_arch = "...")`. For example, the
+    /// architecture for `armv7-unknown-linux-gnueabihf` is `Arm(Armv7)`, while its `target_arch`
+    /// is `arm`.
+    #[inline]
+    pub fn architecture(&self) -> &Architecture {
+        &self.inner.lexicon_triple().architecture
+    }
+
+    /// Returns the vendor for this triple, as parsed by [`target-lexicon`](target_lexicon).
+    #[inline]
+    pub fn vendor(&self) -> &Vendor {
+        &self.inner.lexicon_triple().vendor
+    }
+
+    /// Returns the operating system for this triple, as parsed by
+    /// [`target-lexicon`](target_lexicon).
+    #[inline]
+    pub fn operating_system(&self) -> &OperatingSystem {
+        &self.inner.lexicon_triple().operating_system
+    }
+
+    /// Returns the environment for this triple, as parsed by [`target-lexicon`](target_lexicon).
+    #[inline]
+    pub fn environment(&self) -> &Environment {
+        &self.inner.lexicon_triple().environment
+    }
+
+    /// Returns the binary format for this triple.
+    ///
+    /// This is the binary format as parsed by [`target-lexicon`](target_lexicon). For builtin and
+    /// custom triples that `target-lexicon` doesn't know the binary format for, it is inferred
+    /// from the target definition: for example, `Wasm` for `asmjs-unknown-emscripten` and `Macho`
+    /// for `arm64_32-apple-watchos`.
+    #[inline]
+    pub fn binary_format(&self) -> &BinaryFormat {
+        &self.inner.lexicon_triple().binary_format
+    }
+
+    /// Returns the target families for this triple, as matched by `cfg(target_family = "...")`.
+    ///
+    /// A triple may be part of several families: for example, `wasm32-unknown-emscripten` is
+    /// part of both the `unix` and `wasm` families, while `wasm32-unknown-unknown` is only part of
+    /// `wasm`. Apple simulator triples like `aarch64-apple-ios-sim` are part of `unix`.
+    ///
+    /// Returns an empty list if this triple isn't part of any families, or if it is opaque.
+    pub fn families(&self) -> &[Family] {
+        self.inner.families()
+    }
+
+    /// Returns true if this triple is part of the given family, as matched by
+    /// `cfg(target_family = "...")`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use target_spec::Triple;
+    ///
+    /// let triple = Triple::new("wasm32-unknown-emscripten").unwrap();
+    /// assert!(triple.has_family("unix"));
+    /// assert!(triple.has_family("wasm"));
+    /// assert!(!triple.has_family("windows"));
+    /// ```
+    pub fn has_family(&self, family: &str) -> bool {
+        self.families().iter().any(|f| f.as_str() == family)
+    }
+
+    /// Returns the ABI for this triple, as matched by `cfg(target_abi = "...")`.
+    ///
+    /// For most triples, the ABI is inferred from the environment component, for example
+    /// `eabihf` for `armv7-unknown-linux-gnueabihf`. Returns `None` if this triple doesn't have a
+    /// specific ABI. (In that case, `cfg(target_abi = "")` matches this triple.)
+    #[inline]
+    pub fn abi(&self) -> Option<&str> {
+        self.inner.abi()
+    }
+
+    /// Returns the endianness of this triple.
+    ///
+    /// Returns `None` if the endianness isn't known.
+    pub fn endianness(&self) -> Option<Endianness> {
+        match self.inner.target_info() {
+            Some(target_info) => Some(match target_info.endian {
+                Endian::big => Endianness::Big,
+                Endian::little => Endianness::Little,
+            }),
+            None => self.inner.lexicon_triple().endianness().ok(),
+        }
+    }
+
+    /// Returns the size of a pointer on this triple.
+    ///
+    /// Returns `None` if the pointer width isn't known.
+    pub fn pointer_width(&self) -> Option<PointerWidth> {
+        match self.inner.target_info() {
+            Some(target_info) => match target_info.pointer_width {
+                16 => Some(PointerWidth::U16),
+                32 => Some(PointerWidth::U32),
+                64 => Some(PointerWidth::U64),
+                _ => None,
+            },
+            None => self.inner.lexicon_triple().pointer_width().ok(),
+        }
+    }
+
     /// Evaluates this triple against the given platform.
     ///
     /// This simply compares `self` against the `Triple` the platform is based on, ignoring
@@ -54,11 +347,77 @@ impl Triple {
         self == platform.triple()
     }
 
+    /// Creates a new `Triple` from a custom target definition.
+    #[cfg(feature = "custom")]
+    pub(crate) fn from_custom_info(target_info: TargetInfo, json: Option<Box<str>>) -> Self {
+        let lexicon_triple = lexicon_triple_for(&target_info);
+        Self {
+            inner: TripleInner::Custom {
+                target_info: Box::new(target_info),
+                lexicon_triple,
+                json,
+            },
+        }
+    }
+
     // Use cfg-expr's target matcher.
     #[inline]
     pub(crate) fn matches(&self, tp: &TargetPredicate) -> bool {
         self.inner.matches(tp)
     }
+
+    // Like `matches`, but returns `None` for predicates that can't be determined for this triple.
+    #[inline]
+    pub(crate) fn matches_strict(&self, tp: &TargetPredicate) -> Option<bool> {
+        self.inner.matches_strict(tp)
+    }
+}
+
+/// Converts a `target-lexicon` triple into a `Triple`.
+///
+/// The triple string is the `Display` representation of the `target-lexicon` triple. If that's a
+/// builtin triple, the builtin information is used.
+impl From<target_lexicon::Triple> for Triple {
+    fn from(lexicon_triple: target_lexicon::Triple) -> Self {
+        let triple_str = lexicon_triple.to_string();
+        Self::new_from_lexicon(triple_str, lexicon_triple)
+    }
+}
+
+impl From<Triple> for target_lexicon::Triple {
+    #[inline]
+    fn from(triple: Triple) -> Self {
+        triple.lexicon_triple().clone()
+    }
+}
+
+impl<'a> From<&'a Triple> for target_lexicon::Triple {
+    #[inline]
+    fn from(triple: &'a Triple) -> Self {
+        triple.lexicon_triple().clone()
+    }
+}
+
+/// Converts `cfg-expr`'s information about a target, such as an element of
+/// [`ALL_BUILTINS`](cfg_expr::targets::ALL_BUILTINS), into a `Triple`.
+impl From<&'static TargetInfo> for Triple {
+    #[inline]
+    fn from(target_info: &'static TargetInfo) -> Self {
+        Self {
+            inner: TripleInner::new_builtin(target_info),
+        }
+    }
+}
+
+/// Converts a `cfg-expr` target definition into a custom `Triple`.
+///
+/// Requires the `custom` feature to be enabled.
+#[cfg(feature = "custom")]
+impl From<TargetInfo> for Triple {
+    #[inline]
+    fn from(target_info: TargetInfo) -> Self {
+        Self::from_custom_info(target_info, None)
+    }
 }
 
 impl FromStr for Triple {
@@ -74,19 +433,52 @@ impl FromStr for Triple {
 #[derive(Clone, Debug)]
 enum TripleInner {
     /// Prefer the builtin representation as it's more accurate.
-    Builtin(&'static TargetInfo),
+    Builtin {
+        target_info: &'static TargetInfo,
+        lexicon_triple: target_lexicon::Triple,
+    },
+    /// A builtin Apple target with a deployment version, such as `x86_64-apple-macosx10.14`.
+    Versioned {
+        triple_str: Cow<'static, str>,
+        target_info: &'static TargetInfo,
+        lexicon_triple: target_lexicon::Triple,
+        min_os_version: Version,
+    },
     /// Fall back to the lexicon representation.
     Lexicon {
         triple_str: Cow<'static, str>,
         lexicon_triple: target_lexicon::Triple,
     },
+    /// A triple that nothing is known about.
+    Opaque {
+        triple_str: Cow<'static, str>,
+        // Always unknown, stored so that lexicon_triple() can return a reference.
+        lexicon_triple: target_lexicon::Triple,
+    },
+    /// A custom target, defined through a target JSON specification.
+    #[cfg(feature = "custom")]
+    Custom {
+        target_info: Box<TargetInfo>,
+        lexicon_triple: target_lexicon::Triple,
+        // The JSON specification, if the triple was created from one.
+        json: Option<Box<str>>,
+    },
 }
 
 impl TripleInner {
     fn new(triple_str: Cow<'static, str>) -> Result<Self, TripleParseError> {
         // First try getting the builtin.
-        if let Some(target_info) = get_builtin_target_by_triple(&triple_str) {
-            return Ok(TripleInner::Builtin(target_info));
+        if let Some(target_info) = builtin_target_info(&triple_str) {
+            return Ok(TripleInner::new_builtin(target_info));
+        }
+
+        // Next, try a builtin Apple target with a deployment version.
+        if let Some((target_info, min_os_version)) = parse_versioned_apple(&triple_str) {
+            return Ok(TripleInner::new_versioned(
+                triple_str,
+                target_info,
+                min_os_version,
+            ));
         }
 
         // Next, try getting the lexicon representation.
@@ -101,8 +493,17 @@ impl TripleInner {
 
     fn from_borrowed_str(triple_str: &str) -> Result<Self, TripleParseError> {
         // First try getting the builtin.
-        if let Some(target_info) = get_builtin_target_by_triple(triple_str) {
-            return Ok(TripleInner::Builtin(target_info));
+        if let Some(target_info) = builtin_target_info(triple_str) {
+            return Ok(TripleInner::new_builtin(target_info));
+        }
+
+        // Next, try a builtin Apple target with a deployment version.
+        if let Some((target_info, min_os_version)) = parse_versioned_apple(triple_str) {
+            return Ok(TripleInner::new_versioned(
+                triple_str.to_owned().into(),
+                target_info,
+                min_os_version,
+            ));
         }
 
         // Next, try getting the lexicon representation.
@@ -118,18 +519,351 @@ impl TripleInner {
         }
     }
 
+    fn new_builtin(target_info: &'static TargetInfo) -> Self {
+        TripleInner::Builtin {
+            target_info,
+            lexicon_triple: lexicon_triple_for(target_info),
+        }
+    }
+
+    fn new_versioned(
+        triple_str: Cow<'static, str>,
+        target_info: &'static TargetInfo,
+        min_os_version: Version,
+    ) -> Self {
+        let mut lexicon_triple = lexicon_triple_for(target_info);
+        // target-lexicon represents macOS deployment versions as part of the operating system.
+        if lexicon_triple.operating_system == OperatingSystem::Darwin {
+            if let (Ok(major), Ok(minor), Ok(patch)) = (
+                min_os_version.major.try_into(),
+                min_os_version.minor.try_into(),
+                min_os_version.patch.try_into(),
+            ) {
+                lexicon_triple.operating_system = OperatingSystem::MacOSX {
+                    major,
+                    minor,
+                    patch,
+                };
+            }
+        }
+        TripleInner::Versioned {
+            triple_str,
+            target_info,
+            lexicon_triple,
+            min_os_version,
+        }
+    }
+
     fn as_str(&self) -> &str {
         match self {
-            TripleInner::Builtin(target_info) => target_info.triple.as_str(),
-            TripleInner::Lexicon { triple_str, .. } => triple_str,
+            TripleInner::Builtin { target_info, .. } => target_info.triple.as_str(),
+            TripleInner::Versioned { triple_str, .. }
+            | TripleInner::Lexicon { triple_str, .. }
+            | TripleInner::Opaque { triple_str, .. } => triple_str,
+            #[cfg(feature = "custom")]
+            TripleInner::Custom { target_info, .. } => target_info.triple.as_str(),
+        }
+    }
+
+    fn target_info(&self) -> Option<&TargetInfo> {
+        match self {
+            TripleInner::Builtin { target_info, .. }
+            | TripleInner::Versioned { target_info, .. } => Some(target_info),
+            TripleInner::Lexicon { .. } | TripleInner::Opaque { .. } => None,
+            #[cfg(feature = "custom")]
+            TripleInner::Custom { target_info, .. } => Some(target_info),
+        }
+    }
+
+    fn lexicon_triple(&self) -> &target_lexicon::Triple {
+        match self {
+            TripleInner::Builtin { lexicon_triple, .. }
+            | TripleInner::Versioned { lexicon_triple, .. }
+            | TripleInner::Lexicon { lexicon_triple, .. }
+            | TripleInner::Opaque { lexicon_triple, .. } => lexicon_triple,
+            #[cfg(feature = "custom")]
+            TripleInner::Custom { lexicon_triple, .. } => lexicon_triple,
+        }
+    }
+
+    fn min_os_version(&self) -> Option<&Version> {
+        match self {
+            TripleInner::Versioned { min_os_version, .. } => Some(min_os_version),
+            _ => None,
+        }
+    }
+
+    fn abi(&self) -> Option<&str> {
+        match self {
+            // cfg-expr's builtin targets don't always have the ABI set, so fall back to inferring
+            // it from the triple.
+            TripleInner::Builtin { target_info, .. }
+            | TripleInner::Versioned { target_info, .. } => match &target_info.abi {
+                Some(abi) => Some(abi.as_str()),
+                None => infer_abi(target_info.triple.as_str()),
+            },
+            TripleInner::Lexicon { triple_str, .. } => infer_abi(triple_str),
+            TripleInner::Opaque { .. } => None,
+            // The target JSON specification is authoritative for custom targets.
+            #[cfg(feature = "custom")]
+            TripleInner::Custom { target_info, .. } => {
+                target_info.abi.as_ref().map(|abi| abi.as_str())
+            }
+        }
+    }
+
+    fn families(&self) -> &[Family] {
+        match self {
+            TripleInner::Builtin { target_info, .. }
+            | TripleInner::Versioned { target_info, .. } => &target_info.families,
+            TripleInner::Lexicon { lexicon_triple, .. } => lexicon_families(lexicon_triple),
+            TripleInner::Opaque { .. } => &[],
+            #[cfg(feature = "custom")]
+            TripleInner::Custom { target_info, .. } => &target_info.families,
+        }
+    }
+
+    fn matches_strict(&self, tp: &TargetPredicate) -> Option<bool> {
+        match (self, tp) {
+            // Nothing is known about opaque triples.
+            (TripleInner::Opaque { .. }, _) => None,
+            // target-lexicon doesn't have information about atomics or the panic strategy, and
+            // cfg-expr's matcher always returns false for them.
+            (
+                TripleInner::Lexicon { .. },
+                TargetPredicate::HasAtomic(_) | TargetPredicate::Panic(_),
+            ) => None,
+            _ => Some(self.matches(tp)),
         }
     }
 
     fn matches(&self, tp: &TargetPredicate) -> bool {
+        if let TripleInner::Opaque { .. } = self {
+            return false;
+        }
+        if let TargetPredicate::Abi(abi) = tp {
+            // rustc sets target_abi to "" for targets without a specific ABI.
+            return self.abi().unwrap_or("") == abi.as_str();
+        }
+        if let TargetPredicate::Family(family) = tp {
+            // cfg-expr's matcher for target-lexicon triples doesn't handle every wasm target, so
+            // use the same list of families that `families()` returns.
+            return self.families().contains(family);
+        }
+
         match self {
-            TripleInner::Builtin(target_info) => target_info.matches(tp),
+            TripleInner::Builtin { target_info, .. }
+            | TripleInner::Versioned { target_info, .. } => target_info.matches(tp),
             TripleInner::Lexicon { lexicon_triple, .. } => lexicon_triple.matches(tp),
+            TripleInner::Opaque { .. } => false,
+            #[cfg(feature = "custom")]
+            TripleInner::Custom { target_info, .. } => target_info.matches(tp),
+        }
+    }
+}
+
+/// Infers the value of `target_abi` from a triple string, matching rustc's target definitions.
+fn infer_abi(triple_str: &str) -> Option<&'static str> {
+    // A few targets have ABIs that aren't part of their environment component.
+    if triple_str == "armv6k-nintendo-3ds" {
+        return Some("eabihf");
+    }
+    let mut components = triple_str.split('-');
+    let vendor = components.nth(1);
+    if vendor == Some("uwp") {
+        return Some("uwp");
+    }
+    if vendor == Some("fortanix") {
+        return Some("fortanix");
+    }
+
+    // The ABI is the suffix of the last component after the libc (if any), e.g. "eabihf" in
+    // "gnueabihf", "abi64" in "muslabi64", or "sim" in "aarch64-apple-ios-sim".
+    let last = triple_str.rsplit('-').next()?;
+    let abi = ["android", "gnu", "musl", "uclibc", "newlib"]
+        .iter()
+        .find_map(|libc| last.strip_prefix(libc))
+        .unwrap_or(last)
+        .trim_start_matches('_');
+
+    static KNOWN_ABIS: &[&str] = &[
+        "abi64",
+        "abiv2",
+        "abiv2hf",
+        "eabi",
+        "eabihf",
+        "ilp32",
+        "llvm",
+        "macabi",
+        "sim",
+        "softfloat",
+        "spe",
+        "x32",
+    ];
+    KNOWN_ABIS.iter().copied().find(|known| *known == abi)
+}
+
+/// Apple operating systems that accept deployment versions, and the OS component of the builtin
+/// triples they're based on.
+static VERSIONED_APPLE_OSES: &[(&str, &str)] = &[
+    // "macosx" must come before "macos", since it's matched as a prefix.
+    ("macosx", "darwin"),
+    ("macos", "darwin"),
+    ("ios", "ios"),
+    ("tvos", "tvos"),
+    ("watchos", "watchos"),
+];
+
+/// Parses an Apple triple with a deployment version, such as `x86_64-apple-macosx10.14` or
+/// `arm64-apple-ios14.0-simulator`, into the builtin target it's based on and its minimum OS
+/// version.
+fn parse_versioned_apple(triple_str: &str) -> Option<(&'static TargetInfo, Version)> {
+    let mut components = triple_str.split('-');
+    let arch = components.next()?;
+    if components.next()? != "apple" {
+        return None;
+    }
+    let os_and_version = components.next()?;
+    let env = match components.next() {
+        None => "",
+        // Apple toolchains use "simulator", while rustc uses "sim".
+        Some("simulator") | Some("sim") => "-sim",
+        Some("macabi") => "-macabi",
+        Some(_) => return None,
+    };
+    if components.next().is_some() {
+        return None;
+    }
+
+    let (os, version) = VERSIONED_APPLE_OSES.iter().find_map(|(prefix, os)| {
+        os_and_version
+            .strip_prefix(prefix)
+            .map(|version| (*os, version))
+    })?;
+    let min_os_version = parse_os_version(version)?;
+
+    // Apple toolchains call aarch64 "arm64".
+    let arches: &[&str] = if arch == "arm64" {
+        &["arm64", "aarch64"]
+    } else {
+        &[arch]
+    };
+    arches.iter().find_map(|arch| {
+        builtin_target_info(&format!("{}-apple-{}{}", arch, os, env))
+            .map(|target_info| (target_info, min_os_version.clone()))
+    })
+}
+
+/// Parses an OS version with one to three components, such as `14`, `10.14` or `10.14.6`.
+fn parse_os_version(version: &str) -> Option<Version> {
+    let mut parts = [0; 3];
+    for (idx, part) in version.split('.').enumerate() {
+        if idx == parts.len() || part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
         }
+        parts[idx] = part.parse().ok()?;
+    }
+    Some(Version::new(parts[0], parts[1], parts[2]))
+}
+
+/// Parses the `target-lexicon` representation of a target.
+///
+/// Most triples can be parsed directly by `target-lexicon`. For the ones that can't (typically
+/// newer or custom targets), each component is parsed individually, falling back to `Unknown`.
+fn parse_lexicon_triple(target_info: &TargetInfo) -> target_lexicon::Triple {
+    if let Ok(lexicon_triple) = target_info.triple.as_str().parse() {
+        return lexicon_triple;
+    }
+
+    let mut lexicon_triple = target_lexicon::Triple::unknown();
+    if let Ok(architecture) = target_info.arch.as_str().parse() {
+        lexicon_triple.architecture = architecture;
+    }
+    if let Some(Ok(vendor)) = target_info
+        .vendor
+        .as_ref()
+        .map(|vendor| vendor.as_str().parse())
+    {
+        lexicon_triple.vendor = vendor;
+    }
+    if let Some(Ok(operating_system)) = target_info.os.as_ref().map(|os| os.as_str().parse()) {
+        lexicon_triple.operating_system = operating_system;
+    }
+    if let Some(Ok(environment)) = target_info.env.as_ref().map(|env| env.as_str().parse()) {
+        lexicon_triple.environment = environment;
+    }
+    lexicon_triple
+}
+
+/// Returns the `target-lexicon` representation of a target, with the binary format filled in.
+fn lexicon_triple_for(target_info: &TargetInfo) -> target_lexicon::Triple {
+    let mut lexicon_triple = parse_lexicon_triple(target_info);
+    if lexicon_triple.binary_format == BinaryFormat::Unknown {
+        lexicon_triple.binary_format = infer_binary_format(target_info);
+    }
+    lexicon_triple
+}
+
+/// Infers the binary format for a target that `target-lexicon` doesn't know the format for.
+fn infer_binary_format(target_info: &TargetInfo) -> BinaryFormat {
+    let arch = target_info.arch.as_str();
+    let os = target_info.os.as_ref().map(|os| os.as_str());
+    let vendor = target_info.vendor.as_ref().map(|vendor| vendor.as_str());
+
+    if arch == "wasm32" || arch == "wasm64" {
+        BinaryFormat::Wasm
+    } else if vendor == Some("apple") || matches!(os, Some("macos" | "ios" | "tvos" | "watchos")) {
+        BinaryFormat::Macho
+    } else if os == Some("windows") {
+        BinaryFormat::Coff
+    } else if target_info.families.contains(&Family::unix) {
+        BinaryFormat::Elf
+    } else {
+        BinaryFormat::Unknown
+    }
+}
+
+/// Returns the target families for a triple parsed by `target-lexicon`, matching rustc's target
+/// definitions.
+fn lexicon_families(lexicon_triple: &target_lexicon::Triple) -> &'static [Family] {
+    static UNIX: &[Family] = &[Family::unix];
+    static UNIX_WASM: &[Family] = &[Family::unix, Family::wasm];
+    static WASM: &[Family] = &[Family::wasm];
+    static WINDOWS: &[Family] = &[Family::windows];
+
+    let is_wasm = matches!(
+        lexicon_triple.architecture,
+        Architecture::Asmjs | Architecture::Wasm32 | Architecture::Wasm64
+    );
+    match lexicon_triple.operating_system {
+        // Emscripten targets are part of both the unix and wasm families.
+        OperatingSystem::Emscripten if is_wasm => UNIX_WASM,
+        OperatingSystem::Emscripten => UNIX,
+        OperatingSystem::Unknown if is_wasm => WASM,
+        OperatingSystem::Wasi => WASM,
+        OperatingSystem::Windows => WINDOWS,
+        // The kernel environment isn't part of the unix family.
+        OperatingSystem::Linux if lexicon_triple.environment == Environment::Kernel => &[],
+        OperatingSystem::Darwin
+        | OperatingSystem::Dragonfly
+        | OperatingSystem::Espidf
+        | OperatingSystem::Freebsd
+        | OperatingSystem::Fuchsia
+        | OperatingSystem::Haiku
+        | OperatingSystem::Horizon
+        | OperatingSystem::Illumos
+        | OperatingSystem::Ios
+        | OperatingSystem::L4re
+        | OperatingSystem::Linux
+        | OperatingSystem::MacOSX { .. }
+        | OperatingSystem::Netbsd
+        | OperatingSystem::Openbsd
+        | OperatingSystem::Redox
+        | OperatingSystem::Solaris
+        | OperatingSystem::Tvos
+        | OperatingSystem::VxWorks
+        | OperatingSystem::Watchos => UNIX,
+        _ => &[],
     }
 }
 
@@ -137,7 +871,8 @@ impl TripleInner {
 // Trait impls
 //
 // These impls only use the `triple_str`, which is valid because the triple is a pure
-// function of the `triple_str`.
+// function of the `triple_str`. (The exceptions are triples created with `new_from_lexicon` or
+// `new_opaque`, which are expected to be used consistently for a given `triple_str`.)
 // ---
 
 impl PartialEq for Triple {
@@ -189,13 +924,336 @@ mod tests {
 
         let actual_triple = match target.inner {
             TripleInner::Lexicon { lexicon_triple, .. } => lexicon_triple,
-            TripleInner::Builtin(_) => {
+            TripleInner::Builtin { .. } => {
                 panic!("should not have been able to parse x86_64-pc-darwin as a builtin");
             }
+            TripleInner::Versioned { .. } => {
+                panic!("x86_64-pc-darwin should not be parsed as a versioned triple");
+            }
+            TripleInner::Opaque { .. } => {
+                panic!("x86_64-pc-darwin should not be parsed as an opaque triple");
+            }
+            #[cfg(feature = "custom")]
+            TripleInner::Custom { .. } => {
+                panic!("x86_64-pc-darwin should not be parsed as a custom target");
+            }
         };
         assert_eq!(
             actual_triple, expected_triple,
             "lexicon triple matched correctly"
         );
     }
+
+    #[test]
+    fn test_abi() {
+        let cases = [
+            ("x86_64-unknown-linux-gnu", None),
+            ("armv7-unknown-linux-gnueabihf", Some("eabihf")),
+            ("arm-unknown-linux-musleabi", Some("eabi")),
+            ("arm-linux-androideabi", Some("eabi")),
+            ("thumbv7em-none-eabihf", Some("eabihf")),
+            ("mips64-unknown-linux-gnuabi64", Some("abi64")),
+            ("mips64-unknown-linux-muslabi64", Some("abi64")),
+            ("x86_64-unknown-linux-gnux32", Some("x32")),
+            ("aarch64-unknown-linux-gnu_ilp32", Some("ilp32")),
+            ("powerpc-unknown-linux-gnuspe", Some("spe")),
+            ("powerpc-wrs-vxworks-spe", Some("spe")),
+            ("aarch64-apple-ios-sim", Some("sim")),
+            ("x86_64-apple-ios-macabi", Some("macabi")),
+            ("x86_64-pc-windows-gnullvm", Some("llvm")),
+            ("x86_64-pc-windows-msvc", None),
+            ("x86_64-uwp-windows-msvc", Some("uwp")),
+            ("x86_64-fortanix-unknown-sgx", Some("fortanix")),
+            ("aarch64-unknown-none-softfloat", Some("softfloat")),
+            ("armv6k-nintendo-3ds", Some("eabihf")),
+            // Not builtin.
+            ("armv7-unknown-freebsd-gnueabihf", Some("eabihf")),
+            ("x86_64-pc-darwin", None),
+        ];
+
+        for (triple_str, expected) in cases {
+            let triple = super::Triple::new(triple_str).unwrap();
+            assert_eq!(triple.abi(), expected, "for triple {}", triple_str);
+
+            let abi = cfg_expr::targets::Abi::new(expected.unwrap_or(""));
+            assert!(
+                triple.matches(&TargetPredicate::Abi(abi)),
+                "triple {} matches its own ABI",
+                triple_str
+            );
+            assert!(
+                !triple.matches(&TargetPredicate::Abi(cfg_expr::targets::Abi::new("other"))),
+                "triple {} doesn't match other ABIs",
+                triple_str
+            );
+        }
+    }
+
+    #[test]
+    fn test_components() {
+        let target = super::Triple::new("x86_64-pc-darwin").unwrap();
+        assert_eq!(target.architecture(), &Architecture::X86_64);
+        assert_eq!(target.vendor(), &Vendor::Pc);
+        assert_eq!(target.operating_system(), &OperatingSystem::Darwin);
+        assert_eq!(target.environment(), &Environment::Unknown);
+        assert_eq!(target.binary_format(), &BinaryFormat::Macho);
+        assert_eq!(target.endianness(), Some(Endianness::Little));
+        assert_eq!(target.pointer_width(), Some(PointerWidth::U64));
+
+        let target = super::Triple::new("armv7-unknown-linux-gnueabihf").unwrap();
+        assert_eq!(
+            target.architecture(),
+            &Architecture::Arm(ArmArchitecture::Armv7)
+        );
+        assert_eq!(target.vendor(), &Vendor::Unknown);
+        assert_eq!(target.operating_system(), &OperatingSystem::Linux);
+        assert_eq!(target.environment(), &Environment::Gnueabihf);
+        assert_eq!(target.binary_format(), &BinaryFormat::Elf);
+        assert_eq!(target.endianness(), Some(Endianness::Little));
+        assert_eq!(target.pointer_width(), Some(PointerWidth::U32));
+
+        let target = super::Triple::new("powerpc64-unknown-linux-gnu").unwrap();
+        assert_eq!(target.endianness(), Some(Endianness::Big));
+        assert_eq!(target.pointer_width(), Some(PointerWidth::U64));
+
+        // Every builtin target has a known pointer width and endianness.
+        for target in crate::builtin_targets() {
+            let triple = target.to_triple();
+            assert!(
+                triple.endianness().is_some(),
+                "for triple {}",
+                triple.as_str()
+            );
+            assert!(
+                triple.pointer_width().is_some(),
+                "for triple {}",
+                triple.as_str()
+            );
+        }
+    }
+
+    #[test]
+    fn test_families_and_binary_format() {
+        let cases: &[(&str, &[&str], BinaryFormat)] = &[
+            ("wasm32-unknown-unknown", &["wasm"], BinaryFormat::Wasm),
+            ("wasm32-wasi", &["wasm"], BinaryFormat::Wasm),
+            (
+                "wasm32-unknown-emscripten",
+                &["unix", "wasm"],
+                BinaryFormat::Wasm,
+            ),
+            (
+                "asmjs-unknown-emscripten",
+                &["unix", "wasm"],
+                BinaryFormat::Wasm,
+            ),
+            ("aarch64-apple-ios-sim", &["unix"], BinaryFormat::Macho),
+            ("x86_64-apple-ios", &["unix"], BinaryFormat::Macho),
+            ("arm64_32-apple-watchos", &["unix"], BinaryFormat::Macho),
+            ("x86_64-pc-windows-msvc", &["windows"], BinaryFormat::Coff),
+            ("x86_64-wrs-vxworks", &["unix"], BinaryFormat::Elf),
+            ("thumbv7em-none-eabihf", &[], BinaryFormat::Elf),
+            // Not builtin, but parsed by target-lexicon.
+            (
+                "wasm64-unknown-emscripten",
+                &["unix", "wasm"],
+                BinaryFormat::Wasm,
+            ),
+            ("wasm32-unknown-wasi", &["wasm"], BinaryFormat::Wasm),
+            ("x86_64-pc-darwin", &["unix"], BinaryFormat::Macho),
+        ];
+
+        for (triple_str, expected_families, expected_format) in cases {
+            let triple = super::Triple::new(*triple_str).unwrap();
+            let families: Vec<_> = triple.families().iter().map(|f| f.as_str()).collect();
+            assert_eq!(&families, expected_families, "families for {}", triple_str);
+            assert_eq!(
+                triple.binary_format(),
+                expected_format,
+                "binary format for {}",
+                triple_str
+            );
+
+            for family in ["unix", "wasm", "windows"] {
+                let tp = TargetPredicate::Family(cfg_expr::targets::Family::new(family));
+                assert_eq!(
+                    triple.matches(&tp),
+                    expected_families.contains(&family),
+                    "target_family = {:?} for {}",
+                    family,
+                    triple_str
+                );
+            }
+        }
+
+        // Opaque triples aren't part of any families.
+        let opaque = super::Triple::new_opaque("wasm32-unknown-futureos");
+        assert!(opaque.families().is_empty());
+
+        // The families reported for builtin triples match the families target-lexicon triples
+        // would have.
+        for target in crate::builtin_targets() {
+            let triple = target.to_triple();
+            let from_lexicon = super::Triple::new_from_lexicon(
+                "not-a-builtin-triple",
+                triple.lexicon_triple().clone(),
+            );
+            if triple.lexicon_triple().operating_system != OperatingSystem::Unknown
+                && triple.as_str().parse::<target_lexicon::Triple>().is_ok()
+            {
+                assert_eq!(
+                    triple.families(),
+                    from_lexicon.families(),
+                    "for triple {}",
+                    triple.as_str()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_versioned_apple() {
+        let cases = [
+            (
+                "x86_64-apple-macosx10.14",
+                "x86_64-apple-darwin",
+                (10, 14, 0),
+            ),
+            (
+                "x86_64-apple-macos10.14.6",
+                "x86_64-apple-darwin",
+                (10, 14, 6),
+            ),
+            ("arm64-apple-macosx11", "aarch64-apple-darwin", (11, 0, 0)),
+            ("aarch64-apple-ios14.0", "aarch64-apple-ios", (14, 0, 0)),
+            (
+                "arm64-apple-ios14.0-simulator",
+                "aarch64-apple-ios-sim",
+                (14, 0, 0),
+            ),
+            (
+                "x86_64-apple-ios13.1-macabi",
+                "x86_64-apple-ios-macabi",
+                (13, 1, 0),
+            ),
+            ("aarch64-apple-tvos15.0", "aarch64-apple-tvos", (15, 0, 0)),
+            ("armv7k-apple-watchos8.0", "armv7k-apple-watchos", (8, 0, 0)),
+        ];
+        for (triple_str, builtin, (major, minor, patch)) in cases {
+            let triple = super::Triple::new(triple_str).unwrap_or_else(|err| {
+                panic!("{} should parse: {}", triple_str, err);
+            });
+            assert_eq!(triple.as_str(), triple_str);
+            assert_eq!(
+                triple.min_os_version(),
+                Some(&Version::new(major, minor, patch)),
+                "min OS version for {}",
+                triple_str
+            );
+            assert_eq!(
+                triple.target_info().unwrap().triple.as_str(),
+                builtin,
+                "builtin for {}",
+                triple_str
+            );
+            let from_str: super::Triple = triple_str.parse().unwrap();
+            assert_eq!(from_str.min_os_version(), triple.min_os_version());
+        }
+
+        let triple = super::Triple::new("x86_64-apple-macosx10.14").unwrap();
+        assert_eq!(
+            triple.operating_system(),
+            &OperatingSystem::MacOSX {
+                major: 10,
+                minor: 14,
+                patch: 0
+            }
+        );
+        assert!(triple.has_family("unix"));
+        assert_ne!(triple, super::Triple::new("x86_64-apple-darwin").unwrap());
+
+        for triple_str in [
+            "x86_64-apple-macosx",
+            "x86_64-apple-macosx10.",
+            "x86_64-apple-macosx10.14.6.1",
+            "x86_64-apple-macosx10.x",
+            "x86_64-apple-ios13.0-gnu",
+            "x86_64-unknown-macosx10.14",
+        ] {
+            let min_os_version = super::Triple::new(triple_str)
+                .ok()
+                .and_then(|triple| triple.min_os_version().cloned());
+            assert_eq!(min_os_version, None, "for {}", triple_str);
+        }
+        assert_eq!(
+            super::Triple::new("x86_64-apple-darwin")
+                .unwrap()
+                .min_os_version(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_opaque_and_from_lexicon() {
+        let newer = "aarch64-unknown-linux-futurelibc";
+        assert!(super::Triple::new(newer).is_err());
+
+        let opaque = super::Triple::new_opaque(newer);
+        assert!(opaque.is_opaque());
+        assert_eq!(opaque.as_str(), newer);
+        assert_eq!(opaque.architecture(), &Architecture::Unknown);
+        assert_eq!(opaque.abi(), None);
+        let tp = TargetPredicate::Abi(cfg_expr::targets::Abi::new(""));
+        assert!(!opaque.matches(&tp));
+        assert_eq!(opaque.matches_strict(&tp), None);
+
+        // Builtin triples are never opaque.
+        let builtin = super::Triple::new_opaque("x86_64-unknown-linux-gnu");
+        assert!(!builtin.is_opaque());
+        assert_eq!(builtin.architecture(), &Architecture::X86_64);
+
+        let mut components = target_lexicon::Triple::unknown();
+        components.architecture = Architecture::Aarch64(Aarch64Architecture::Aarch64);
+        components.operating_system = OperatingSystem::Linux;
+        let from_lexicon = super::Triple::new_from_lexicon(newer, components);
+        assert!(!from_lexicon.is_opaque());
+        assert_eq!(from_lexicon, opaque, "triples are compared by string");
+        let tp = TargetPredicate::Os(cfg_expr::targets::Os::linux);
+        assert!(from_lexicon.matches(&tp));
+        assert_eq!(from_lexicon.matches_strict(&tp), Some(true));
+        assert!(!opaque.matches(&tp));
+    }
+
+    #[test]
+    fn test_conversions() {
+        // target-lexicon triples that are builtin.
+        let lexicon_triple: target_lexicon::Triple = "x86_64-unknown-linux-gnu".parse().unwrap();
+        let triple = super::Triple::from(lexicon_triple.clone());
+        assert_eq!(triple.as_str(), "x86_64-unknown-linux-gnu");
+        assert!(triple.target_info().is_some(), "builtin info is used");
+        assert_eq!(target_lexicon::Triple::from(&triple), lexicon_triple);
+
+        // target-lexicon triples that aren't builtin.
+        let lexicon_triple: target_lexicon::Triple = "x86_64-pc-darwin".parse().unwrap();
+        let triple = super::Triple::from(lexicon_triple.clone());
+        assert_eq!(triple.as_str(), "x86_64-pc-darwin");
+        assert_eq!(triple.target_info(), None);
+        assert_eq!(triple.lexicon_triple(), &lexicon_triple);
+
+        // cfg-expr builtins.
+        for target_info in cfg_expr::targets::ALL_BUILTINS {
+            let triple = super::Triple::from(target_info);
+            assert_eq!(triple.as_str(), target_info.triple.as_str());
+            assert_eq!(
+                triple,
+                super::Triple::new(target_info.triple.as_str()).unwrap()
+            );
+        }
+
+        // Platforms.
+        let platform = crate::Platform::from(lexicon_triple.clone());
+        assert_eq!(platform.triple_str(), "x86_64-pc-darwin");
+        assert_eq!(platform.target_features(), &crate::TargetFeatures::Unknown);
+        assert_eq!(target_lexicon::Triple::from(&platform), lexicon_triple);
+    }
 }
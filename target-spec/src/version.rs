@@ -0,0 +1,212 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Support for `cfg(version("..."))` predicates.
+//!
+//! `cfg-expr` doesn't understand `version("...")`. Before parsing, each `version("...")` is
+//! rewritten to `version="..." `, which `cfg-expr` parses as a key-value predicate. The rewritten
+//! string is exactly as long as the original, so spans in parse errors still point to the right
+//! place in the original.
+
+use semver::Version;
+use std::borrow::Cow;
+
+/// The key that `version("...")` predicates are rewritten to.
+pub(crate) const VERSION_KEY: &str = "version";
+
+/// Rewrites `version("...")` predicates in the input to `version="..." `.
+///
+/// Returns the input unchanged if it doesn't have any `version("...")` predicates.
+pub(crate) fn rewrite_version_predicates(input: &str) -> Cow<'_, str> {
+    let bytes = input.as_bytes();
+    let mut rewritten: Option<Vec<u8>> = None;
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        match bytes[idx] {
+            // Skip over string literals.
+            b'"' => {
+                idx = match input[idx + 1..].find('"') {
+                    Some(end) => idx + 1 + end + 1,
+                    None => bytes.len(),
+                };
+            }
+            ch if is_ident_start(ch) => {
+                let start = idx;
+                while idx < bytes.len() && is_ident_rest(bytes[idx]) {
+                    idx += 1;
+                }
+                if &input[start..idx] == VERSION_KEY {
+                    if let Some((open, close)) = match_version_call(bytes, idx) {
+                        let rewritten = rewritten.get_or_insert_with(|| bytes.to_vec());
+                        rewritten[open] = b'=';
+                        rewritten[close] = b' ';
+                        idx = close + 1;
+                    }
+                }
+            }
+            _ => idx += 1,
+        }
+    }
+
+    match rewritten {
+        Some(rewritten) => Cow::Owned(
+            String::from_utf8(rewritten).expect("only ASCII bytes were replaced with ASCII bytes"),
+        ),
+        None => Cow::Borrowed(input),
+    }
+}
+
+/// Given the index just past a `version` identifier, checks whether it's followed by
+/// `("...")`, and if so returns the indexes of the opening and closing parentheses.
+fn match_version_call(bytes: &[u8], mut idx: usize) -> Option<(usize, usize)> {
+    let skip_whitespace = |idx: &mut usize| {
+        while *idx < bytes.len() && bytes[*idx].is_ascii_whitespace() {
+            *idx += 1;
+        }
+    };
+
+    skip_whitespace(&mut idx);
+    if bytes.get(idx) != Some(&b'(') {
+        return None;
+    }
+    let open = idx;
+    idx += 1;
+
+    skip_whitespace(&mut idx);
+    if bytes.get(idx) != Some(&b'"') {
+        return None;
+    }
+    idx += 1;
+    while bytes.get(idx)? != &b'"' {
+        idx += 1;
+    }
+    idx += 1;
+
+    skip_whitespace(&mut idx);
+    if bytes.get(idx) != Some(&b')') {
+        return None;
+    }
+    Some((open, idx))
+}
+
+// These match the definitions in cfg-expr's lexer.
+fn is_ident_start(ch: u8) -> bool {
+    ch == b'_' || ch.is_ascii_alphabetic()
+}
+
+fn is_ident_rest(ch: u8) -> bool {
+    is_ident_start(ch) || ch.is_ascii_digit()
+}
+
+/// Evaluates a `version("...")` predicate against the given rustc version.
+///
+/// Like rustc, pre-release versions of rustc are treated as the corresponding release, and
+/// invalid version literals evaluate to false.
+pub(crate) fn eval_version(literal: &str, rustc_version: Option<&Version>) -> Option<bool> {
+    let rustc_version = rustc_version?;
+    match parse_version_literal(literal) {
+        Some(min) => Some(
+            (
+                rustc_version.major,
+                rustc_version.minor,
+                rustc_version.patch,
+            ) >= min,
+        ),
+        None => Some(false),
+    }
+}
+
+/// Parses a version literal of the form `major.minor` or `major.minor.patch`.
+fn parse_version_literal(literal: &str) -> Option<(u64, u64, u64)> {
+    let mut components = literal.split('.');
+    let major = parse_component(components.next()?)?;
+    let minor = parse_component(components.next()?)?;
+    let patch = match components.next() {
+        Some(patch) => parse_component(patch)?,
+        None => 0,
+    };
+    if components.next().is_some() {
+        return None;
+    }
+    Some((major, minor, patch))
+}
+
+fn parse_component(component: &str) -> Option<u64> {
+    if component.is_empty() || !component.bytes().all(|ch| ch.is_ascii_digit()) {
+        return None;
+    }
+    component.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite() {
+        let cases = [
+            ("cfg(unix)", "cfg(unix)"),
+            (r#"cfg(version("1.70"))"#, r#"cfg(version="1.70" )"#),
+            (
+                r#"cfg(all(unix, version ( "1.70.1" ) ))"#,
+                r#"cfg(all(unix, version = "1.70.1"   ))"#,
+            ),
+            (
+                r#"cfg(any(version("1.60"), not(version("1.70"))))"#,
+                r#"cfg(any(version="1.60" , not(version="1.70" )))"#,
+            ),
+            // Already a key-value.
+            (r#"cfg(version = "1.70")"#, r#"cfg(version = "1.70")"#),
+            // Other identifiers, or version inside string literals, are left alone.
+            (
+                r#"cfg(rustc_version("1.70"))"#,
+                r#"cfg(rustc_version("1.70"))"#,
+            ),
+            (r#"cfg(foo = "version(")"#, r#"cfg(foo = "version(")"#),
+            // Malformed calls are left alone, and cause a parse error later.
+            (r#"cfg(version(1.70))"#, r#"cfg(version(1.70))"#),
+            (
+                r#"cfg(version("1.70" "1.71"))"#,
+                r#"cfg(version("1.70" "1.71"))"#,
+            ),
+        ];
+
+        for (input, expected) in cases {
+            let rewritten = rewrite_version_predicates(input);
+            assert_eq!(rewritten, expected, "for input {}", input);
+            assert_eq!(rewritten.len(), input.len(), "length is preserved");
+        }
+    }
+
+    #[test]
+    fn test_eval_version() {
+        let stable = Version::new(1, 70, 0);
+        let nightly: Version = "1.71.0-nightly".parse().unwrap();
+
+        let cases = [
+            ("1.70", &stable, Some(true)),
+            ("1.70.0", &stable, Some(true)),
+            ("1.69", &stable, Some(true)),
+            ("1.70.1", &stable, Some(false)),
+            ("1.71", &stable, Some(false)),
+            ("1.71", &nightly, Some(true)),
+            ("1.72", &nightly, Some(false)),
+            ("1", &stable, Some(false)),
+            ("1.70.0.0", &stable, Some(false)),
+            ("1.70-beta", &stable, Some(false)),
+            ("1.+70", &stable, Some(false)),
+        ];
+        for (literal, rustc_version, expected) in cases {
+            assert_eq!(
+                eval_version(literal, Some(rustc_version)),
+                expected,
+                "for literal {} and rustc {}",
+                literal,
+                rustc_version
+            );
+        }
+
+        assert_eq!(eval_version("1.70", None), None);
+    }
+}
@@ -14,7 +14,7 @@ use guppy::{
     graph::{
         cargo::{CargoOptions, CargoSet},
         feature::{FeatureFilter, FeatureSet, StandardFeatures},
-        DependencyDirection, PackageGraph, PackageMetadata, PackageSet, Workspace,
+        DependencyDirection, PackageGraph, PackageMetadata, PackageSet, PathOwner, Workspace,
     },
     platform::PlatformSpec,
     PackageId,
@@ -412,11 +412,9 @@ fn process_path<'g>(
     }
 
     // 2. Map the path to its nearest ancestor package.
-    for ancestor in path.ancestors() {
-        if let Ok(package) = workspace.member_by_path(ancestor) {
-            match_cb(package.id());
-            return PathMatch::AncestorMatched;
-        }
+    if let PathOwner::Member(package) = workspace.owner_of_path(path) {
+        match_cb(package.id());
+        return PathMatch::AncestorMatched;
     }
 
     // 3. If a file didn't match anything so far, rebuild everything.